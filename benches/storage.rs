@@ -0,0 +1,67 @@
+//! Benchmarks for parsing and reading mems: `cargo bench`.
+//!
+//! `mem bench` times whole commands on a synthetic store; these time the
+//! pieces underneath them.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mem::bench::{self, Shape};
+use mem::mem::{split_frontmatter, Mem};
+use mem::storage::Storage;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+const SHAPE: Shape = Shape {
+    mems: 500,
+    depth: 2,
+    size: 2000,
+};
+
+/// A synthetic store of `SHAPE`, kept alive by the returned directory.
+fn store() -> (TempDir, Storage) {
+    let temp = TempDir::new().unwrap();
+    let storage = Storage::new(temp.path().to_path_buf());
+    bench::generate(&storage, SHAPE).unwrap();
+    (temp, storage)
+}
+
+fn parsing(c: &mut Criterion) {
+    let mem = Mem::new(
+        PathBuf::from("notes/deploy"),
+        "Deploy".to_string(),
+        "## Steps\n\nRun the migration, then deploy.\n".repeat(50),
+    )
+    .with_tags(vec!["ops".to_string(), "deploy".to_string()]);
+    let raw = mem.serialize().unwrap();
+
+    c.bench_function("split_frontmatter", |b| {
+        b.iter(|| split_frontmatter(black_box(&raw)))
+    });
+    c.bench_function("Mem::parse", |b| {
+        b.iter(|| Mem::parse(PathBuf::from("notes/deploy"), black_box(&raw)).unwrap())
+    });
+    c.bench_function("Mem::serialize", |b| {
+        b.iter(|| black_box(&mem).serialize().unwrap())
+    });
+}
+
+fn storage(c: &mut Criterion) {
+    let (_temp, storage) = store();
+    let path = storage.list_headers_under("").unwrap()[SHAPE.mems / 2].path_str();
+
+    c.bench_function("Storage::list_mems", |b| {
+        b.iter(|| storage.list_mems().unwrap())
+    });
+    c.bench_function("Storage::list_headers_under", |b| {
+        b.iter(|| storage.list_headers_under("").unwrap())
+    });
+    c.bench_function("Storage::read_mem", |b| {
+        b.iter(|| storage.read_mem(black_box(&path)).unwrap())
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = parsing, storage
+}
+criterion_main!(benches);
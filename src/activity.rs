@@ -0,0 +1,291 @@
+//! How much writing went on over the past year (`mem activity`): mems
+//! created and edited per day, as monthly bars or a contribution-style
+//! heatmap.
+
+use crate::mem::Mem;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// Weeks shown in the heatmap, the current one included.
+const WEEKS: u64 = 53;
+
+/// Heatmap cells from no activity to the most.
+const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// Widest bar of the monthly view.
+const BAR_WIDTH: usize = 40;
+
+/// Changes on one day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Day {
+    pub created: usize,
+    pub edited: usize,
+}
+
+impl Day {
+    pub fn total(&self) -> usize {
+        self.created + self.edited
+    }
+}
+
+/// Changes per day over the past year.
+#[derive(Debug, Clone, Serialize)]
+pub struct Activity {
+    /// First day counted, a Monday
+    pub since: NaiveDate,
+    pub until: NaiveDate,
+    pub created: usize,
+    pub edited: usize,
+
+    /// Latest change to any mem, in the period or not
+    pub last_change: Option<DateTime<Utc>>,
+
+    /// Days with changes
+    pub days: BTreeMap<NaiveDate, Day>,
+}
+
+impl Activity {
+    /// An empty record of the year up to `until`, starting on the Monday
+    /// that gives the heatmap [`WEEKS`] full columns.
+    pub fn new(until: NaiveDate) -> Self {
+        let monday = until - Days::new(until.weekday().num_days_from_monday().into());
+        Self {
+            since: monday - Days::new((WEEKS - 1) * 7),
+            until,
+            created: 0,
+            edited: 0,
+            last_change: None,
+            days: BTreeMap::new(),
+        }
+    }
+
+    /// Count a mem's creation and its latest edit. An edit on the day it was
+    /// created counts only as the creation; earlier edits leave no trace in
+    /// the timestamps.
+    pub fn add(&mut self, mem: &Mem) {
+        let created = mem.created_at.date_naive();
+        let updated = mem.updated_at.date_naive();
+        if self.contains(created) {
+            self.days.entry(created).or_default().created += 1;
+            self.created += 1;
+        }
+        if updated != created && self.contains(updated) {
+            self.days.entry(updated).or_default().edited += 1;
+            self.edited += 1;
+        }
+        let latest = mem.created_at.max(mem.updated_at);
+        self.last_change = self.last_change.max(Some(latest));
+    }
+
+    fn contains(&self, day: NaiveDate) -> bool {
+        self.since <= day && day <= self.until
+    }
+
+    fn day(&self, day: NaiveDate) -> Day {
+        self.days.get(&day).copied().unwrap_or_default()
+    }
+
+    /// Changes per month for the twelve months up to `until`, oldest first.
+    pub fn months(&self) -> Vec<(NaiveDate, Day)> {
+        let current = self.until.with_day(1).unwrap_or(self.until);
+        (0..12)
+            .rev()
+            .map(|back| {
+                let month = current - Months::new(back);
+                let mut sum = Day::default();
+                for (_, day) in self.days.range(month..month + Months::new(1)) {
+                    sum.created += day.created;
+                    sum.edited += day.edited;
+                }
+                (month, sum)
+            })
+            .collect()
+    }
+
+    /// One line per month: its counts and a bar scaled to the busiest.
+    pub fn render_months(&self) -> String {
+        let months = self.months();
+        let max = months.iter().map(|(_, d)| d.total()).max().unwrap_or(0);
+        let mut out = String::new();
+        for (month, day) in months {
+            let width = if max == 0 {
+                0
+            } else {
+                (day.total() * BAR_WIDTH).div_ceil(max)
+            };
+            let _ = writeln!(
+                out,
+                "{}  {:>4} created  {:>4} edited  {}",
+                month.format("%Y-%m"),
+                day.created,
+                day.edited,
+                "█".repeat(width)
+            );
+        }
+        out.push_str(&self.summary());
+        out
+    }
+
+    /// A week per column, Monday to Sunday top to bottom, each day shaded
+    /// by its changes relative to the busiest day.
+    pub fn render_heatmap(&self) -> String {
+        let max = self.days.values().map(Day::total).max().unwrap_or(0);
+        let weeks: Vec<NaiveDate> = (0..WEEKS).map(|w| self.since + Days::new(w * 7)).collect();
+
+        // Month names over the first week of each month, where they fit; the
+        // first column's month only if the next doesn't start right away
+        let mut labels = vec![' '; weeks.len() + 3];
+        let mut free = 0;
+        for (column, monday) in weeks.iter().enumerate() {
+            let starts_month = match column {
+                0 => weeks[..4].iter().all(|w| w.month() == monday.month()),
+                _ => monday.month() != weeks[column - 1].month(),
+            };
+            if starts_month && column >= free {
+                let name = monday.format("%b").to_string();
+                for (i, c) in name.chars().enumerate() {
+                    labels[column + i] = c;
+                }
+                free = column + name.len() + 1;
+            }
+        }
+        let mut out = format!("    {}\n", labels.iter().collect::<String>().trim_end());
+
+        for (row, name) in ["Mon", "", "Wed", "", "Fri", "", ""].iter().enumerate() {
+            let _ = write!(out, "{name:<4}");
+            for monday in &weeks {
+                let day = *monday + Days::new(row as u64);
+                if day > self.until {
+                    break;
+                }
+                out.push(SHADES[level(self.day(day).total(), max)]);
+            }
+            out.push('\n');
+        }
+        let _ = writeln!(out, "    Less {} More", SHADES.iter().collect::<String>());
+        out.push_str(&self.summary());
+        out
+    }
+
+    /// Totals for the year and when the last change was.
+    fn summary(&self) -> String {
+        let total = self.created + self.edited;
+        let mut out = format!(
+            "\n{total} change{} in the past year: {} created, {} edited\n",
+            if total == 1 { "" } else { "s" },
+            self.created,
+            self.edited
+        );
+        match self.last_change {
+            Some(last) => {
+                let ago = (self.until - last.date_naive()).num_days().max(0);
+                let _ = writeln!(
+                    out,
+                    "Last change: {} ({ago} day{} ago)",
+                    last.format("%Y-%m-%d"),
+                    if ago == 1 { "" } else { "s" }
+                );
+            }
+            None => out.push_str("No mems yet\n"),
+        }
+        out
+    }
+}
+
+/// Shade index for `count` changes when the busiest day had `max`.
+fn level(count: usize, max: usize) -> usize {
+    if count == 0 || max == 0 {
+        0
+    } else {
+        (count * (SHADES.len() - 1))
+            .div_ceil(max)
+            .clamp(1, SHADES.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn mem(created: &str, updated: &str) -> Mem {
+        let mut mem = Mem::new(PathBuf::from("a"), "A".to_string(), String::new());
+        mem.created_at = created.parse().unwrap();
+        mem.updated_at = updated.parse().unwrap();
+        mem
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_counts() {
+        // A Wednesday
+        let mut activity = Activity::new(date("2024-05-15"));
+        assert_eq!(activity.since, date("2023-05-15"));
+        assert_eq!(activity.since.weekday(), chrono::Weekday::Mon);
+
+        activity.add(&mem("2024-05-01T10:00:00Z", "2024-05-14T09:00:00Z"));
+        activity.add(&mem("2024-05-14T08:00:00Z", "2024-05-14T18:00:00Z"));
+        activity.add(&mem("2020-01-01T00:00:00Z", "2020-01-01T00:00:00Z"));
+        assert_eq!((activity.created, activity.edited), (2, 1));
+        assert_eq!(
+            activity.day(date("2024-05-14")),
+            Day {
+                created: 1,
+                edited: 1
+            }
+        );
+        assert_eq!(
+            activity.last_change,
+            Some("2024-05-14T18:00:00Z".parse().unwrap())
+        );
+
+        let months = activity.months();
+        assert_eq!(months.len(), 12);
+        assert_eq!(months[0].0, date("2023-06-01"));
+        assert_eq!(
+            months[11],
+            (
+                date("2024-05-01"),
+                Day {
+                    created: 2,
+                    edited: 1
+                }
+            )
+        );
+        assert!(activity.render_months().contains(
+            "2024-05     2 created     1 edited  ████████████████████████████████████████\n"
+        ));
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let mut activity = Activity::new(date("2024-05-15"));
+        for _ in 0..4 {
+            activity.add(&mem("2024-05-13T10:00:00Z", "2024-05-13T10:00:00Z"));
+        }
+        activity.add(&mem("2024-05-14T10:00:00Z", "2024-05-14T10:00:00Z"));
+
+        let heatmap = activity.render_heatmap();
+        let lines: Vec<&str> = heatmap.lines().collect();
+        assert!(lines[0].starts_with("       Jun Jul"), "{heatmap}");
+        // The last column ends on the Wednesday
+        assert!(lines[1].starts_with("Mon ·"));
+        assert!(lines[1].ends_with("·█"));
+        assert!(lines[2].ends_with("·░"));
+        assert!(lines[3].ends_with("··"));
+        assert!(lines[4].ends_with("·"));
+        assert_eq!(lines[1].chars().count(), 4 + WEEKS as usize);
+        assert_eq!(lines[4].chars().count(), 4 + WEEKS as usize - 1);
+        assert!(heatmap.contains("5 changes in the past year: 5 created, 0 edited"));
+        assert!(heatmap.ends_with("Last change: 2024-05-14 (1 day ago)\n"));
+
+        assert_eq!(level(0, 4), 0);
+        assert_eq!(level(1, 100), 1);
+        assert_eq!(level(100, 100), 4);
+    }
+}
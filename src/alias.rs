@@ -0,0 +1,297 @@
+//! Command aliases and per-command default flags from the config, expanded
+//! in the command line before it is parsed.
+//!
+//! ```yaml
+//! aliases:
+//!   hot: ls --long --hot
+//! defaults:
+//!   ls: --long
+//!   cache build: --quiet
+//! ```
+//!
+//! An alias can't replace a built-in command, and flags given on the command
+//! line win over the defaults for the same flag.
+
+use anyhow::{anyhow, Result};
+use clap::{Arg, Command};
+use std::collections::BTreeMap;
+
+/// Alias chains longer than this are assumed to loop.
+const MAX_DEPTH: usize = 16;
+
+/// Split an alias or defaults value into words, as a shell would: on
+/// whitespace, with single and double quotes and backslash escapes.
+pub fn split(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| anyhow!("trailing backslash in '{line}'"))?;
+                word.push(escaped);
+            }
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(anyhow!("unterminated quote in '{line}'"));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// The option of `command` that `word` sets, if it is one: `--name`,
+/// `--name=value`, or `-n`.
+fn find_arg<'a>(command: &'a Command, word: &str) -> Option<&'a Arg> {
+    let name = option_name(word);
+    if let Some(long) = name.strip_prefix("--") {
+        return command.get_arguments().find(|a| a.get_long() == Some(long));
+    }
+    let mut short = name.strip_prefix('-')?.chars();
+    match (short.next(), short.next()) {
+        (Some(c), None) => command.get_arguments().find(|a| a.get_short() == Some(c)),
+        _ => None,
+    }
+}
+
+/// Whether `word` is an option of `command` that takes its value from the
+/// next word.
+fn takes_value(command: &Command, word: &str) -> bool {
+    !word.contains('=') && find_arg(command, word).is_some_and(|a| a.get_action().takes_values())
+}
+
+/// What identifies the option `word` sets, so `-l` and `--long` match.
+fn option_id(command: &Command, word: &str) -> String {
+    match find_arg(command, word) {
+        Some(arg) => arg.get_id().to_string(),
+        None => option_name(word).to_string(),
+    }
+}
+
+/// Index of the first word of `args` from `start` that isn't an option of
+/// `command` or an option's value, if there is one before `--`.
+fn next_word(args: &[String], start: usize, command: &Command) -> Option<usize> {
+    let mut i = start;
+    while i < args.len() {
+        let word = &args[i];
+        if word == "--" {
+            return None;
+        }
+        if !word.starts_with('-') || word == "-" {
+            return Some(i);
+        }
+        i += if takes_value(command, word) { 2 } else { 1 };
+    }
+    None
+}
+
+/// The option a word sets, without its value: `--limit` for `--limit=5`.
+fn option_name(word: &str) -> &str {
+    word.split_once('=').map_or(word, |(name, _)| name)
+}
+
+/// Expand the alias and add the default flags in `args` (the full command
+/// line, program name first) for the commands of `cli`.
+pub fn expand(
+    mut args: Vec<String>,
+    aliases: &BTreeMap<String, String>,
+    defaults: &BTreeMap<String, String>,
+    cli: &Command,
+) -> Result<Vec<String>> {
+    let mut cli = cli.clone();
+    cli.build();
+    let Some(at) = next_word(&args, 1, &cli) else {
+        return Ok(args);
+    };
+
+    let mut seen = Vec::new();
+    while cli.find_subcommand(&args[at]).is_none() {
+        let name = args[at].clone();
+        let Some(expansion) = aliases.get(&name) else {
+            break;
+        };
+        if seen.contains(&name) || seen.len() == MAX_DEPTH {
+            return Err(anyhow!("alias '{name}' expands to itself"));
+        }
+        let words = split(expansion).map_err(|e| anyhow!("invalid alias '{name}': {e}"))?;
+        if words.is_empty() {
+            return Err(anyhow!("alias '{name}' is empty"));
+        }
+        args.splice(at..=at, words);
+        seen.push(name);
+    }
+
+    // The command's full name, through nested subcommands
+    let Some(mut command) = cli.find_subcommand(&args[at]) else {
+        return Ok(args);
+    };
+    let mut path = vec![command.get_name().to_string()];
+    let mut end = at + 1;
+    while let Some(sub) = args.get(end).and_then(|word| command.find_subcommand(word)) {
+        command = sub;
+        path.push(command.get_name().to_string());
+        end += 1;
+    }
+    let Some(flags) = defaults.get(&path.join(" ")) else {
+        return Ok(args);
+    };
+    let flags =
+        split(flags).map_err(|e| anyhow!("invalid defaults for '{}': {e}", path.join(" ")))?;
+
+    let given: Vec<String> = args[end..]
+        .iter()
+        .take_while(|word| *word != "--")
+        .filter(|word| word.starts_with('-'))
+        .map(|word| option_id(command, word))
+        .collect();
+    let mut added = Vec::new();
+    let mut i = 0;
+    while i < flags.len() {
+        let word = &flags[i];
+        let len = if word.starts_with('-') && takes_value(command, word) {
+            2
+        } else {
+            1
+        };
+        if !(word.starts_with('-') && given.contains(&option_id(command, word))) {
+            added.extend(flags[i..flags.len().min(i + len)].iter().cloned());
+        }
+        i += len;
+    }
+    args.splice(end..end, added);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ArgAction;
+
+    fn cli() -> Command {
+        Command::new("mem")
+            .arg(
+                Arg::new("dir")
+                    .long("dir")
+                    .global(true)
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("quiet")
+                    .short('q')
+                    .long("quiet")
+                    .global(true)
+                    .action(ArgAction::SetTrue),
+            )
+            .subcommand(
+                Command::new("ls")
+                    .arg(Arg::new("path"))
+                    .arg(
+                        Arg::new("long")
+                            .short('l')
+                            .long("long")
+                            .action(ArgAction::SetTrue),
+                    )
+                    .arg(Arg::new("kind").long("kind")),
+            )
+            .subcommand(Command::new("cache").subcommand(
+                Command::new("build").arg(Arg::new("full").long("full").action(ArgAction::SetTrue)),
+            ))
+    }
+
+    fn args(line: &str) -> Vec<String> {
+        split(line).unwrap()
+    }
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            args(r#"ls --where 'status=in review' "a b"c \"d"#),
+            ["ls", "--where", "status=in review", "a bc", "\"d"]
+        );
+        assert_eq!(args(" x  ''  "), ["x", ""]);
+        assert!(split("ls 'open").is_err());
+    }
+
+    #[test]
+    fn test_expand_alias() {
+        let aliases = map(&[
+            ("rls", "ls --long --kind runbook"),
+            ("r", "rls"),
+            ("ls", "ls --kind nope"),
+            ("loop", "loop2 x"),
+            ("loop2", "loop"),
+        ]);
+        let none = BTreeMap::new();
+        let expand = |line| expand(args(line), &aliases, &none, &cli());
+
+        assert_eq!(
+            expand("mem --dir a r ops -q").unwrap(),
+            args("mem --dir a ls --long --kind runbook ops -q")
+        );
+        // Built-in commands aren't replaced, and words after `--` are left alone
+        assert_eq!(expand("mem ls").unwrap(), args("mem ls"));
+        assert_eq!(expand("mem -- rls").unwrap(), args("mem -- rls"));
+        // The value of a global option isn't a command
+        assert_eq!(
+            expand("mem --dir rls ls").unwrap(),
+            args("mem --dir rls ls")
+        );
+        let err = expand("mem loop").unwrap_err();
+        assert_eq!(err.to_string(), "alias 'loop' expands to itself");
+    }
+
+    #[test]
+    fn test_expand_defaults() {
+        let defaults = map(&[("ls", "--long --kind runbook"), ("cache build", "--full")]);
+        let aliases = map(&[("rb", "ls --kind adr")]);
+        let expand = |line| expand(args(line), &aliases, &defaults, &cli());
+
+        assert_eq!(
+            expand("mem ls ops").unwrap(),
+            args("mem ls --long --kind runbook ops")
+        );
+        // Flags on the command line win, in either form
+        assert_eq!(
+            expand("mem ls --kind=adr").unwrap(),
+            args("mem ls --long --kind=adr")
+        );
+        assert_eq!(
+            expand("mem ls -l").unwrap(),
+            args("mem ls --kind runbook -l")
+        );
+        assert_eq!(expand("mem rb").unwrap(), args("mem ls --long --kind adr"));
+        assert_eq!(
+            expand("mem cache build").unwrap(),
+            args("mem cache build --full")
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Text analysis for semantic search: splitting text into the terms that are
+//! hashed into local embeddings and matched as keywords, per the `analyzer`
+//! config (stemming, stopwords, and n-grams for CJK text).
+
+use crate::config::{AnalyzerConfig, Stemming};
+use crate::semantic::{self, STOPWORDS};
+use rust_stemmers::{Algorithm, Stemmer};
+
+/// Splits text into search terms.
+pub struct Analyzer {
+    config: AnalyzerConfig,
+    stemmer: Option<Stemmer>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::new(&AnalyzerConfig::default())
+    }
+}
+
+impl Analyzer {
+    pub fn new(config: &AnalyzerConfig) -> Self {
+        let stemmer = match config.stemming {
+            Stemming::None => None,
+            Stemming::English => Some(Stemmer::create(Algorithm::English)),
+        };
+        Self {
+            config: config.clone(),
+            stemmer,
+        }
+    }
+
+    /// Identifier of the settings, recorded with an embedding index so one
+    /// built with other settings is rebuilt.
+    pub fn id(&self) -> String {
+        let stemming = match self.config.stemming {
+            Stemming::None => "none",
+            Stemming::English => "english",
+        };
+        format!(
+            "stemming={stemming},stopwords={},cjk-ngram={}",
+            self.config.stopwords, self.config.cjk_ngram
+        )
+    }
+
+    /// The terms of `text`: case-folded words, stemmed and without
+    /// stopwords as configured, with runs of CJK characters split into
+    /// n-grams.
+    pub fn terms(&self, text: &str) -> Vec<String> {
+        let mut terms = Vec::new();
+        for word in semantic::tokenize(text) {
+            for (cjk, run) in runs(&word) {
+                if cjk {
+                    terms.extend(ngrams(run, self.config.cjk_ngram));
+                } else if !(self.config.stopwords && STOPWORDS.contains(&run)) {
+                    terms.push(match &self.stemmer {
+                        Some(stemmer) => stemmer.stem(run).into_owned(),
+                        None => run.to_string(),
+                    });
+                }
+            }
+        }
+        terms
+    }
+}
+
+/// Whether `c` is written without spaces between words: Han, kana, or
+/// Hangul.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'     // Hiragana, Katakana
+        | '\u{3400}'..='\u{4dbf}'   // CJK Extension A
+        | '\u{4e00}'..='\u{9fff}'   // CJK Unified Ideographs
+        | '\u{f900}'..='\u{faff}'   // CJK Compatibility Ideographs
+        | '\u{1100}'..='\u{11ff}'   // Hangul Jamo
+        | '\u{3130}'..='\u{318f}'   // Hangul Compatibility Jamo
+        | '\u{ac00}'..='\u{d7af}'   // Hangul Syllables
+        | '\u{20000}'..='\u{2a6df}' // CJK Extension B
+    )
+}
+
+/// `word` split into runs of CJK and other characters, each flagged with
+/// whether it is CJK.
+fn runs(word: &str) -> Vec<(bool, &str)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut current = None;
+    for (i, c) in word.char_indices() {
+        let cjk = is_cjk(c);
+        if current.is_some_and(|kind| kind != cjk) {
+            runs.push((!cjk, &word[start..i]));
+            start = i;
+        }
+        current = Some(cjk);
+    }
+    if let Some(cjk) = current {
+        runs.push((cjk, &word[start..]));
+    }
+    runs
+}
+
+/// Overlapping `n`-character slices of `run`, or the whole run when it is
+/// shorter or `n` is 0.
+fn ngrams(run: &str, n: usize) -> Vec<String> {
+    let chars: Vec<char> = run.chars().collect();
+    if n == 0 || chars.len() <= n {
+        return vec![run.to_string()];
+    }
+    chars.windows(n).map(|w| w.iter().collect()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_terms() {
+        let analyzer = Analyzer::default();
+        assert_eq!(
+            analyzer.terms("How to rotate DB-credentials"),
+            ["rotate", "db", "credentials"]
+        );
+        // CJK runs become bigrams; mixed words are split at the boundary
+        assert_eq!(
+            analyzer.terms("数据库密码 API密钥"),
+            ["数据", "据库", "库密", "密码", "api", "密钥"]
+        );
+        assert_eq!(analyzer.terms("鍵"), ["鍵"]);
+    }
+
+    #[test]
+    fn test_configured_terms() {
+        let analyzer = Analyzer::new(&AnalyzerConfig {
+            stemming: Stemming::English,
+            stopwords: false,
+            cjk_ngram: 0,
+        });
+        assert_eq!(
+            analyzer.terms("the rotating of rotated keys 数据库"),
+            ["the", "rotat", "of", "rotat", "key", "数据库"]
+        );
+        assert_ne!(analyzer.id(), Analyzer::default().id());
+    }
+}
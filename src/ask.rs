@@ -0,0 +1,137 @@
+use crate::analyzer::Analyzer;
+use crate::config::LlmConfig;
+use crate::mem::Mem;
+use crate::semantic::{self, Embedder};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Instructions sent ahead of the packed mems.
+const SYSTEM_PROMPT: &str = "You answer questions about a project using only the provided \
+knowledge base excerpts. Each excerpt starts with its path in square brackets. Cite the paths \
+you relied on in square brackets, e.g. [arch/decisions/adr-001]. If the excerpts do not contain \
+the answer, say so.";
+
+/// A chat message in the OpenAI-compatible format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// Retrieve the mems most relevant to a question (keyword + semantic), best first.
+pub fn retrieve(
+    root: &Path,
+    mems: &[Mem],
+    question: &str,
+    embedder: &dyn Embedder,
+    analyzer: &Analyzer,
+    limit: usize,
+) -> Result<Vec<(usize, f32)>> {
+    let mut ranked = semantic::hybrid_search(root, mems, question, embedder, analyzer)?;
+    ranked.truncate(limit);
+    Ok(ranked)
+}
+
+/// Pack retrieved mems into a prompt, stopping at the token budget.
+///
+/// Returns the messages and the paths of the mems actually included, which
+/// are the valid citations for the answer.
+pub fn build_messages(
+    question: &str,
+    context: &[&Mem],
+    max_context_tokens: usize,
+) -> (Vec<ChatMessage>, Vec<String>) {
+    let mut packed = String::new();
+    let mut used_tokens = 0;
+    let mut cited = Vec::new();
+
+    for mem in context {
+        let path = mem.path_str();
+        let excerpt = format!("[{path}]\n# {}\n\n{}\n\n", mem.title, mem.content.trim());
+        let tokens = excerpt.chars().count().div_ceil(4);
+        // Always include the best match, even if it alone exceeds the budget
+        if !cited.is_empty() && used_tokens + tokens > max_context_tokens {
+            break;
+        }
+        used_tokens += tokens;
+        packed.push_str(&excerpt);
+        cited.push(path);
+    }
+
+    let messages = vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: SYSTEM_PROMPT.to_string(),
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: format!("Knowledge base excerpts:\n\n{packed}Question: {question}"),
+        },
+    ];
+    (messages, cited)
+}
+
+#[derive(Deserialize)]
+struct CompletionResponse {
+    choices: Vec<CompletionChoice>,
+}
+
+#[derive(Deserialize)]
+struct CompletionChoice {
+    message: ChatMessage,
+}
+
+/// Send messages to the configured chat completion endpoint and return the reply.
+pub fn complete(config: &LlmConfig, messages: &[ChatMessage]) -> Result<String> {
+    let mut request = ureq::post(&config.url);
+    if let Ok(key) = std::env::var(&config.api_key_env) {
+        request = request.set("Authorization", &format!("Bearer {key}"));
+    }
+
+    let response: CompletionResponse = request
+        .send_json(serde_json::json!({ "model": config.model, "messages": messages }))
+        .map_err(|e| anyhow!("LLM request failed: {e}"))?
+        .into_json()
+        .context("invalid LLM response")?;
+
+    response
+        .choices
+        .into_iter()
+        .next()
+        .map(|c| c.message.content)
+        .ok_or_else(|| anyhow!("LLM response contained no choices"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn mem(path: &str, content: &str) -> Mem {
+        Mem::new(PathBuf::from(path), path.to_string(), content.to_string())
+    }
+
+    #[test]
+    fn test_build_messages_includes_paths() {
+        let a = mem("db/choice", "We chose postgres for JSONB support.");
+        let (messages, cited) = build_messages("why postgres?", &[&a], 1000);
+
+        assert_eq!(cited, vec!["db/choice"]);
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[1].content.contains("[db/choice]"));
+        assert!(messages[1].content.ends_with("Question: why postgres?"));
+    }
+
+    #[test]
+    fn test_build_messages_respects_budget() {
+        let a = mem("a", &"word ".repeat(100));
+        let b = mem("b", &"word ".repeat(100));
+        let (_, cited) = build_messages("q", &[&a, &b], 150);
+        assert_eq!(cited, vec!["a"]);
+
+        // The best match is kept even when it alone is over budget
+        let (_, cited) = build_messages("q", &[&a, &b], 10);
+        assert_eq!(cited, vec!["a"]);
+    }
+}
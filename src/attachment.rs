@@ -0,0 +1,290 @@
+//! Files attached to mems, stored once by content: `attachments/<sha256>.<ext>`
+//! at the root of the store, linked from mems by relative path. The same
+//! diagram attached twice, or to two mems, costs one copy, and a blob stays
+//! as long as some mem, active or archived, links to it (`mem gc
+//! --attachments` deletes the rest). A link counts by the blob's file name,
+//! which the hash makes unique, so a mem moved without fixing its relative
+//! links still holds on to its blobs.
+
+use crate::config::CONFIG_FILE;
+use crate::dictionary::DICTIONARY_FILE;
+use crate::directory::DIR_FILE;
+use crate::hash::sha256_hex;
+use crate::info::STORE_FILE;
+use crate::links;
+use crate::mem::Mem;
+use crate::storage::Storage;
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path};
+
+/// Directory of attachment blobs, at the root of the store.
+pub const ATTACHMENT_DIR: &str = "attachments";
+
+/// Files at the root of the store that configure it rather than hold content.
+const CONTROL_FILES: [&str; 3] = [CONFIG_FILE, STORE_FILE, DICTIONARY_FILE];
+
+/// Whether the store file at `key` may travel as an attachment: not a mem,
+/// not hidden, not archived, and not a file that configures the store.
+pub fn is_attachable(key: &str) -> bool {
+    let name = key.rsplit('/').next().unwrap_or(key);
+    !key.is_empty()
+        && !key.ends_with(".md")
+        && !key.starts_with("archive/")
+        && !key.split('/').any(|s| s.starts_with('.'))
+        && !CONTROL_FILES.contains(&key)
+        && name != DIR_FILE
+}
+
+/// Key of the blob holding `data`, keeping the extension of `name` so
+/// viewers know the type. Markdown files get none, so they don't list as
+/// mems.
+pub fn blob_key(name: &str, data: &[u8]) -> String {
+    let ext = Path::new(name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .filter(|e| e != "md" && !e.is_empty() && e.chars().all(|c| c.is_ascii_alphanumeric()));
+    match ext {
+        Some(ext) => format!("{ATTACHMENT_DIR}/{}.{ext}", sha256_hex(data)),
+        None => format!("{ATTACHMENT_DIR}/{}", sha256_hex(data)),
+    }
+}
+
+/// Whether `key` names an attachment blob.
+pub fn is_blob(key: &str) -> bool {
+    key.strip_prefix(ATTACHMENT_DIR)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .and_then(|file| file.split('.').next())
+        .is_some_and(|hash| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Store `data` as the blob for a file called `name`, unless an identical
+/// file is stored already. Returns the blob's key and whether it was written.
+pub fn store(storage: &Storage, name: &str, data: &[u8]) -> Result<(String, bool)> {
+    let key = blob_key(name, data);
+    if storage.file_exists(&key)? {
+        return Ok((key, false));
+    }
+    storage.write_file(&key, data)?;
+    Ok((key, true))
+}
+
+/// The link target for the blob at `key` from the mem at `mem_path`.
+pub fn link_target(mem_path: &str, key: &str) -> String {
+    let target = links::relative_target(mem_path, key);
+    target.strip_suffix(".md").unwrap_or(&target).to_string()
+}
+
+/// `content` with links and `![[...]]` embeds whose target is `from` (up to
+/// a title, fragment, or query) pointed at `to` instead.
+pub fn retarget_links(content: &str, from: &str, to: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(i) = rest.find(from) {
+        let before = &rest[..i];
+        let after = &rest[i + from.len()..];
+        let opens = ["](", "](<", "![["].iter().any(|o| before.ends_with(o));
+        let closes = after.is_empty() || after.starts_with([')', ' ', '#', '?', '>', ']', '|']);
+        out.push_str(before);
+        out.push_str(if opens && closes { to } else { from });
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The blob a link target names by its file name, wherever it points.
+fn blob_named(target: &str) -> Option<String> {
+    let name = target.rsplit('/').next()?;
+    let key = format!("{ATTACHMENT_DIR}/{name}");
+    is_blob(&key).then_some(key)
+}
+
+/// `content` of a mem now at `mem_path` with its links to blobs pointed at
+/// them from there, or `None` if they all already are.
+pub fn relink(content: &str, mem_path: &str) -> Option<String> {
+    let mut relinked = content.to_string();
+    for target in attachment_targets(content) {
+        let Some(key) = blob_named(&target) else {
+            continue;
+        };
+        let fixed = link_target(mem_path, &key);
+        if fixed != target {
+            relinked = retarget_links(&relinked, &target, &fixed);
+        }
+    }
+    (relinked != content).then_some(relinked)
+}
+
+/// How many of `mems` link to each blob.
+pub fn references(mems: &[Mem]) -> BTreeMap<String, usize> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for mem in mems {
+        let blobs: BTreeSet<String> = attachment_targets(&mem.content)
+            .iter()
+            .filter_map(|target| blob_named(target))
+            .collect();
+        for blob in blobs {
+            *counts.entry(blob).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// Keys of the blobs in the store.
+pub fn blobs(storage: &Storage) -> Result<Vec<String>> {
+    let mut keys = storage.file_keys(ATTACHMENT_DIR)?;
+    keys.retain(|key| is_blob(key));
+    Ok(keys)
+}
+
+/// Of `blobs`, those none of `mems` link to.
+pub fn unreferenced(blobs: Vec<String>, mems: &[Mem]) -> Vec<String> {
+    let references = references(mems);
+    blobs
+        .into_iter()
+        .filter(|key| !references.contains_key(key))
+        .collect()
+}
+
+/// Where a link target relative to the mem at `mem_path` points inside the
+/// store, or `None` if it leaves the store.
+pub fn store_key(mem_path: &str, target: &str) -> Option<String> {
+    let dir = Path::new(mem_path).parent().unwrap_or(Path::new(""));
+    let mut parts: Vec<String> = Vec::new();
+    for component in dir.join(target).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            _ => return None,
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Targets of the links and `![[...]]` embeds in `content` that may be local
+/// attachments: relative, and not to another mem.
+pub fn attachment_targets(content: &str) -> Vec<String> {
+    let embeds = content.split("![[").skip(1).filter_map(|rest| {
+        let inner = &rest[..rest.find("]]")?];
+        inner.split('|').next()
+    });
+    let mut targets: Vec<String> = links::extract_links(content)
+        .into_iter()
+        .filter_map(|link| {
+            // Drop a title (`(a.png "Title")`), fragment, or query
+            let target = link.target.split_whitespace().next()?;
+            let target = target.trim_start_matches('<').trim_end_matches('>');
+            target.split(['#', '?']).next().map(str::to_string)
+        })
+        .chain(embeds.map(|target| target.trim().to_string()))
+        .filter(|target| {
+            !target.is_empty()
+                && !target.contains(':')
+                && !target.starts_with('/')
+                && !target.ends_with(".md")
+                && target.contains('.')
+        })
+        .collect();
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_attachment_targets() {
+        let content = "![Diagram](img/flow.png \"Flow\") [spec](spec.pdf#page=2)\n\
+                       [other](../ops/deploy.md) [site](https://example.com/a.png)\n\
+                       ![[notes.txt|Notes]] [section](#steps) [root](/etc/hosts.txt)";
+        assert_eq!(
+            attachment_targets(content),
+            ["img/flow.png", "notes.txt", "spec.pdf"]
+        );
+        assert_eq!(
+            store_key("ops/deploy", "../img/a.png").unwrap(),
+            "img/a.png"
+        );
+        assert_eq!(store_key("deploy", "../a.png"), None);
+    }
+
+    #[test]
+    fn test_is_attachable() {
+        for key in ["img/flow.png", "docs/config.yaml", "attachments/a.pdf"] {
+            assert!(is_attachable(key), "{key}");
+        }
+        for key in [
+            "config.yaml",
+            "store.yaml",
+            "dictionary.txt",
+            "ops/_dir.yaml",
+            ".memsignore",
+            "img/.hidden.png",
+            "archive/old.png",
+            "notes/other.md",
+        ] {
+            assert!(!is_attachable(key), "{key}");
+        }
+    }
+
+    #[test]
+    fn test_retarget_links() {
+        let content = "![Flow](img/flow.png \"Flow\") [again](<img/flow.png>) \
+                       ![[img/flow.png|Flow]] img/flow.png [x](img/flow.png.bak)";
+        assert_eq!(
+            retarget_links(content, "img/flow.png", "../attachments/f.png"),
+            "![Flow](../attachments/f.png \"Flow\") [again](<../attachments/f.png>) \
+             ![[../attachments/f.png|Flow]] img/flow.png [x](img/flow.png.bak)"
+        );
+    }
+
+    #[test]
+    fn test_store_and_references() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let (key, written) = store(&storage, "Flow.PNG", b"png").unwrap();
+        assert_eq!(key, format!("attachments/{}.png", sha256_hex(b"png")));
+        assert!(written && is_blob(&key));
+        assert_eq!(
+            store(&storage, "copy.png", b"png").unwrap(),
+            (key.clone(), false)
+        );
+        let (notes, _) = store(&storage, "notes.md", b"# Notes").unwrap();
+        assert!(!notes.ends_with(".md") && is_blob(&notes));
+        assert!(storage.list_mems().unwrap().is_empty());
+        assert!(!is_blob("attachments/flow.png"));
+
+        let target = link_target("ops/deploy", &key);
+        assert_eq!(target, format!("../{key}"));
+        let linking =
+            |path: &str, content: String| Mem::new(PathBuf::from(path), path.to_string(), content);
+        let mems = vec![
+            linking(
+                "ops/deploy",
+                format!("![Flow]({target}) ![Again]({target})"),
+            ),
+            linking("intro", format!("[Flow]({key})")),
+        ];
+        assert_eq!(references(&mems), BTreeMap::from([(key.clone(), 2)]));
+        let blobs = blobs(&storage).unwrap();
+        assert_eq!(blobs.len(), 2);
+        assert_eq!(unreferenced(blobs.clone(), &mems), vec![notes.clone()]);
+
+        // Moved up a level, the deploy mem's links still hold its blob
+        let moved = vec![linking("deploy", format!("![Flow]({target})"))];
+        assert_eq!(unreferenced(blobs, &moved), [notes]);
+        assert_eq!(
+            relink(&moved[0].content, "deploy").unwrap(),
+            format!("![Flow]({key})")
+        );
+        assert_eq!(relink(&moved[0].content, "ops/deploy"), None);
+    }
+}
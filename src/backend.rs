@@ -0,0 +1,480 @@
+use crate::config::Config;
+use anyhow::{anyhow, Context, Result};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::debug;
+
+/// Where a store's files live.
+///
+/// Keys are `/`-separated paths relative to the store root, e.g.
+/// `arch/decisions/adr-001.md`. Backends only move bytes; parsing and
+/// filtering of mems happens in [`crate::storage::Storage`].
+pub trait StorageBackend: std::fmt::Debug + Send + Sync {
+    /// Read a file, or `None` if it does not exist.
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Read a file up to the line closing its frontmatter, or `None` if it
+    /// does not exist. Backends that can read part of a file override this
+    /// so listings don't load large mem bodies.
+    fn read_frontmatter(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.read(key)? {
+            Some(data) => Ok(Some(frontmatter_head(&data[..])?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Create or replace a file. Readers never observe a partial write.
+    fn write(&self, key: &str, data: &[u8]) -> Result<()>;
+
+    /// Delete a file that exists.
+    fn delete(&self, key: &str) -> Result<()>;
+
+    /// Check if a file exists.
+    fn exists(&self, key: &str) -> Result<bool>;
+
+    /// Keys of all files under `prefix` (recursively; `""` for the whole
+    /// store), excluding hidden (dot-prefixed) entries.
+    fn list(&self, prefix: &str) -> Result<Vec<String>>;
+
+    /// Move a file to a new key.
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let data = self
+            .read(from)?
+            .ok_or_else(|| anyhow!("file not found: {from}"))?;
+        self.write(to, &data)?;
+        self.delete(from)
+    }
+
+    /// Local directory holding the files, if the backend is the filesystem.
+    fn local_root(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Whether a file is a symbolic link to another file.
+    fn is_link(&self, _key: &str) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Files in a local directory (the default backend).
+///
+/// Symlinked mem files are listed like any other, and writes go through to
+/// the file they point to. Symlinked directories are only walked with
+/// `follow-symlinks: true` in the store config, and never into a loop.
+#[derive(Debug)]
+pub struct FsBackend {
+    root: PathBuf,
+
+    /// Whether `list` walks symlinked directories, loaded on first use
+    follow_symlinks: OnceLock<bool>,
+}
+
+impl FsBackend {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            follow_symlinks: OnceLock::new(),
+        }
+    }
+
+    fn follow_symlinks(&self) -> Result<bool> {
+        if let Some(follow) = self.follow_symlinks.get() {
+            return Ok(*follow);
+        }
+        let follow = Config::load(&self.root)?.follow_symlinks;
+        Ok(*self.follow_symlinks.get_or_init(|| follow))
+    }
+
+    /// Remove empty parent directories of `file`, stopping at the root.
+    fn cleanup_empty_dirs(&self, file: &Path) {
+        let mut parent = file.parent();
+        while let Some(p) = parent {
+            if p == self.root {
+                break;
+            }
+            if p.read_dir()
+                .map(|mut d| d.next().is_none())
+                .unwrap_or(false)
+            {
+                fs::remove_dir(p).ok();
+                parent = p.parent();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Collect the keys of the files in `dir`. `ancestors` holds the
+    /// canonical paths of the directories being walked, to detect loops.
+    fn list_in(
+        &self,
+        dir: &Path,
+        prefix: &str,
+        keys: &mut Vec<String>,
+        ancestors: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        for entry in fs::read_dir(dir).context("failed to read directory")? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+
+            let key = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+            let path = entry.path();
+            let is_link = entry.file_type()?.is_symlink();
+            let Ok(metadata) = fs::metadata(&path) else {
+                eprintln!("warning: skipping broken symlink {key}");
+                continue;
+            };
+            if !metadata.is_dir() {
+                keys.push(key);
+                continue;
+            }
+            if is_link && !self.follow_symlinks()? {
+                debug!(key, "not following symlinked directory");
+                continue;
+            }
+            let canonical = path.canonicalize()?;
+            if ancestors.contains(&canonical) {
+                eprintln!("warning: skipping symlink loop at {key}");
+                continue;
+            }
+            ancestors.push(canonical);
+            self.list_in(&path, &key, keys, ancestors)?;
+            ancestors.pop();
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackend for FsBackend {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.root.join(key)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context("failed to read file"),
+        }
+    }
+
+    /// Read lines only until the frontmatter closes.
+    fn read_frontmatter(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match File::open(self.root.join(key)) {
+            Ok(file) => Ok(Some(frontmatter_head(BufReader::new(file))?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context("failed to read file"),
+        }
+    }
+
+    /// Write atomically (temp file + rename), creating parent directories.
+    /// A symlink is written through, replacing the file it points to.
+    fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        let mut path = self.root.join(key);
+        if self.is_link(key)? {
+            path = path.canonicalize().context("failed to resolve symlink")?;
+        }
+        let parent = path.parent().ok_or_else(|| anyhow!("invalid path"))?;
+
+        // Ensure parent directories exist
+        fs::create_dir_all(parent).context("failed to create directories")?;
+
+        // Write to temp file in same directory
+        let temp_name = format!(
+            ".{}.{}.tmp",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            std::process::id() ^ rand_u32()
+        );
+        let temp_path = parent.join(&temp_name);
+
+        let mut file = File::create(&temp_path).context("failed to create temp file")?;
+        file.write_all(data).context("failed to write temp file")?;
+        file.sync_all().context("failed to sync temp file")?;
+        drop(file);
+
+        let existed = path.exists();
+        if let Err(e) = replace_file(&temp_path, &path) {
+            // The temp file is the only copy left if the destination is gone
+            if !existed || path.exists() {
+                let _ = fs::remove_file(&temp_path);
+            }
+            return Err(e).context("failed to rename temp file");
+        }
+
+        Ok(())
+    }
+
+    /// Delete a file and clean up empty parent directories.
+    fn delete(&self, key: &str) -> Result<()> {
+        let path = self.root.join(key);
+        fs::remove_file(&path).context("failed to delete file")?;
+        self.cleanup_empty_dirs(&path);
+        Ok(())
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.root.join(key).is_file())
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let dir = self.root.join(prefix);
+        let mut keys = Vec::new();
+        if dir.is_dir() {
+            let mut ancestors = vec![dir.canonicalize()?];
+            self.list_in(
+                &dir,
+                prefix.trim_end_matches('/'),
+                &mut keys,
+                &mut ancestors,
+            )?;
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Move a file. A symlink moves as a link to the same file, with a
+    /// relative target rewritten for its new directory.
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let src = self.root.join(from);
+        let dest = self.root.join(to);
+        let parent = dest.parent().ok_or_else(|| anyhow!("invalid path"))?;
+        fs::create_dir_all(parent).context("failed to create directories")?;
+        match fs::read_link(&src) {
+            Ok(target) if target.is_relative() => {
+                let target = src.parent().unwrap_or(&self.root).join(target);
+                let target = relative_path(&target.canonicalize()?, &parent.canonicalize()?);
+                symlink(&target, &dest).context("failed to move symlink")?;
+                fs::remove_file(&src).context("failed to move symlink")?;
+            }
+            _ => fs::rename(&src, &dest).context("failed to move file")?,
+        }
+        self.cleanup_empty_dirs(&src);
+        Ok(())
+    }
+
+    fn local_root(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+
+    fn is_link(&self, key: &str) -> Result<bool> {
+        match fs::symlink_metadata(self.root.join(key)) {
+            Ok(metadata) => Ok(metadata.file_type().is_symlink()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).context("failed to read file metadata"),
+        }
+    }
+}
+
+/// The start of a mem file up to and including the `---` line closing its
+/// frontmatter: all of it if the frontmatter is not closed, or only the
+/// first line if there is none.
+fn frontmatter_head(mut reader: impl BufRead) -> Result<Vec<u8>> {
+    let mut head = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader
+            .read_until(b'\n', &mut line)
+            .context("failed to read file")?
+            == 0
+        {
+            break;
+        }
+        let first = head.is_empty();
+        head.extend_from_slice(&line);
+        // Stop at a first line that doesn't open frontmatter, or the line
+        // closing it
+        if line.starts_with(b"---") != first {
+            break;
+        }
+    }
+    Ok(head)
+}
+
+/// `target` relative to the directory `base`; both absolute.
+fn relative_path(target: &Path, base: &Path) -> PathBuf {
+    let target: Vec<Component> = target.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = target.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let mut path: PathBuf = base[common..].iter().map(|_| "..").collect();
+    path.extend(&target[common..]);
+    path
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+/// Rename `from` over `to`, replacing it in one step (`rename(2)`, or
+/// `MoveFileExW` with `MOVEFILE_REPLACE_EXISTING` on Windows), so `to` always
+/// holds either the old or the new contents. Windows refuses to replace a
+/// file while another process (an editor, a virus scanner) has it open, so
+/// there the rename is retried briefly before giving up with both files
+/// intact.
+pub fn replace_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    let mut result = fs::rename(from, to);
+    if cfg!(windows) {
+        for attempt in 1..=RENAME_RETRIES {
+            match &result {
+                Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                    std::thread::sleep(std::time::Duration::from_millis(10 * attempt));
+                    result = fs::rename(from, to);
+                }
+                _ => break,
+            }
+        }
+    }
+    result
+}
+
+/// Times [`replace_file`] retries a rename Windows refused.
+const RENAME_RETRIES: u64 = 5;
+
+/// Simple random u32 using system entropy.
+fn rand_u32() -> u32 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let state = RandomState::new();
+    let mut hasher = state.build_hasher();
+    hasher.write_u64(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+    );
+    hasher.finish() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_fs_backend() {
+        let temp = TempDir::new().unwrap();
+        let backend = FsBackend::new(temp.path().to_path_buf());
+
+        backend.write("a/b/c.md", b"one").unwrap();
+        backend.write("top.md", b"two").unwrap();
+        backend.write(".hidden/x.md", b"three").unwrap();
+
+        assert_eq!(backend.read("a/b/c.md").unwrap().unwrap(), b"one");
+        assert_eq!(backend.read("missing.md").unwrap(), None);
+        assert_eq!(backend.list("").unwrap(), vec!["a/b/c.md", "top.md"]);
+        assert_eq!(backend.list("a").unwrap(), vec!["a/b/c.md"]);
+        assert!(backend.list("nope").unwrap().is_empty());
+
+        backend.rename("a/b/c.md", "archive/a/b/c.md").unwrap();
+        assert!(!temp.path().join("a").exists());
+        assert!(backend.exists("archive/a/b/c.md").unwrap());
+
+        backend.delete("archive/a/b/c.md").unwrap();
+        assert!(!temp.path().join("archive").exists());
+    }
+
+    #[test]
+    fn test_read_frontmatter() {
+        let temp = TempDir::new().unwrap();
+        let backend = FsBackend::new(temp.path().to_path_buf());
+        let body = "x".repeat(100_000);
+        backend
+            .write(
+                "big.md",
+                format!("---\r\ntitle: Big\r\n---\r\n{body}").as_bytes(),
+            )
+            .unwrap();
+        backend
+            .write("plain.md", b"no frontmatter\nat all\n")
+            .unwrap();
+        assert_eq!(
+            backend.read_frontmatter("big.md").unwrap().unwrap(),
+            b"---\r\ntitle: Big\r\n---\r\n"
+        );
+        assert_eq!(
+            backend.read_frontmatter("plain.md").unwrap().unwrap(),
+            b"no frontmatter\n"
+        );
+        assert_eq!(backend.read_frontmatter("missing.md").unwrap(), None);
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let temp = TempDir::new().unwrap();
+        let (from, to) = (temp.path().join("new"), temp.path().join("old"));
+        fs::write(&from, "new").unwrap();
+        fs::write(&to, "old").unwrap();
+        replace_file(&from, &to).unwrap();
+        assert_eq!(fs::read_to_string(&to).unwrap(), "new");
+        assert!(!from.exists());
+        assert!(replace_file(&from, &to).is_err());
+
+        // A rename that fails leaves both files where they were
+        fs::write(&from, "new").unwrap();
+        let dir = temp.path().join("dir");
+        fs::create_dir_all(dir.join("child")).unwrap();
+        assert!(replace_file(&from, &dir).is_err());
+        assert_eq!(fs::read_to_string(&from).unwrap(), "new");
+        assert!(dir.join("child").is_dir());
+        fs::remove_file(&from).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Overwriting leaves no temp files behind
+        let backend = FsBackend::new(temp.path().to_path_buf());
+        backend.write("a.md", b"one").unwrap();
+        backend.write("a.md", b"two").unwrap();
+        assert_eq!(backend.read("a.md").unwrap().unwrap(), b"two");
+        let names: Vec<_> = fs::read_dir(temp.path()).unwrap().collect();
+        assert_eq!(names.len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fs_backend_symlinks() {
+        use std::os::unix::fs::symlink;
+        let temp = TempDir::new().unwrap();
+        let backend = FsBackend::new(temp.path().to_path_buf());
+        backend.write("docs/guide.md", b"one").unwrap();
+        symlink("../docs", temp.path().join("docs/loop")).unwrap();
+        symlink("guide.md", temp.path().join("docs/alias.md")).unwrap();
+        symlink("missing.md", temp.path().join("docs/broken.md")).unwrap();
+        assert_eq!(
+            backend.list("").unwrap(),
+            vec!["docs/alias.md", "docs/guide.md"]
+        );
+
+        // Followed when configured, without walking into the loop
+        fs::write(temp.path().join("config.yaml"), "follow-symlinks: true\n").unwrap();
+        symlink("docs", temp.path().join("shared")).unwrap();
+        let backend = FsBackend::new(temp.path().to_path_buf());
+        assert_eq!(
+            backend.list("").unwrap(),
+            vec![
+                "config.yaml",
+                "docs/alias.md",
+                "docs/guide.md",
+                "shared/alias.md",
+                "shared/guide.md"
+            ]
+        );
+
+        // Writes go through the link, and moves keep it a link
+        backend.write("docs/alias.md", b"two").unwrap();
+        assert_eq!(backend.read("docs/guide.md").unwrap().unwrap(), b"two");
+        backend.rename("docs/alias.md", "other/alias.md").unwrap();
+        assert!(backend.is_link("other/alias.md").unwrap());
+        assert_eq!(backend.read("other/alias.md").unwrap().unwrap(), b"two");
+    }
+}
@@ -0,0 +1,403 @@
+use crate::backend::replace_file;
+use crate::hash::sha256_hex;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// Directory inside .mems/ where snapshots are kept by default.
+pub const BACKUP_DIR: &str = ".backups";
+
+/// Directory inside .mems/ where `restore` writes files before moving them
+/// into place. Its `.tmp` suffix keeps it out of snapshots.
+const STAGING_DIR: &str = ".restore.tmp";
+
+/// Archive entry holding the manifest, written first.
+const MANIFEST: &str = ".mem-backup.json";
+
+/// Snapshot file name prefix and extension.
+const PREFIX: &str = "mems-";
+const EXTENSION: &str = ".tar.zst";
+
+/// zstd compression level (the library default).
+const LEVEL: i32 = 3;
+
+/// Contents of a snapshot: every file with its SHA-256 checksum.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub created_at: DateTime<Utc>,
+    pub files: BTreeMap<String, String>,
+}
+
+/// Default snapshot path for a store at `now`.
+pub fn default_path(root: &Path, now: DateTime<Utc>) -> PathBuf {
+    root.join(BACKUP_DIR).join(format!(
+        "{PREFIX}{}{EXTENSION}",
+        now.format("%Y%m%dT%H%M%S%3fZ")
+    ))
+}
+
+/// Write a compressed snapshot of the store (including archive and index) to `out`.
+pub fn create(root: &Path, out: &Path) -> Result<Manifest> {
+    if out.exists() {
+        return Err(anyhow!("backup already exists: {}", out.display()));
+    }
+
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    collect(root, "", &mut dirs, &mut files)?;
+
+    let manifest = Manifest {
+        created_at: Utc::now(),
+        files: files
+            .iter()
+            .map(|(path, data)| (path.clone(), sha256_hex(data)))
+            .collect(),
+    };
+
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent).context("failed to create backup directory")?;
+    }
+
+    // Write to a temp file so a failed backup never looks complete
+    let temp = out.with_extension("tmp");
+    let result = (|| -> Result<()> {
+        let encoder = zstd::Encoder::new(File::create(&temp)?, LEVEL)?;
+        let mut builder = tar::Builder::new(encoder);
+        let mtime = manifest.created_at.timestamp().max(0) as u64;
+
+        append_file(
+            &mut builder,
+            MANIFEST,
+            &serde_json::to_vec_pretty(&manifest)?,
+            mtime,
+        )?;
+        for dir in &dirs {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_mtime(mtime);
+            header.set_size(0);
+            builder.append_data(&mut header, dir, std::io::empty())?;
+        }
+        for (path, data) in &files {
+            append_file(&mut builder, path, data, mtime)?;
+        }
+
+        builder.into_inner()?.finish()?.sync_all()?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(e.context("failed to write backup"));
+    }
+    replace_file(&temp, out).context("failed to rename backup")?;
+
+    Ok(manifest)
+}
+
+/// Verify a snapshot and replace the store's contents with it.
+///
+/// Every checksum is verified, and every file written to a staging
+/// directory, before anything in the store is touched; the staged files are
+/// then renamed into place. Existing backups in the store are kept.
+pub fn restore(archive: &Path, root: &Path) -> Result<Manifest> {
+    let (manifest, dirs, files) = read(archive)?;
+
+    fs::create_dir_all(root).context("failed to create store")?;
+    let staging = root.join(STAGING_DIR);
+    if staging.exists() {
+        // Left behind by an interrupted restore
+        fs::remove_dir_all(&staging).context("failed to clear staging directory")?;
+    }
+    if let Err(e) = stage(&staging, &dirs, &files) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    for entry in fs::read_dir(root).context("failed to read store")? {
+        let entry = entry?;
+        if entry.file_name() == BACKUP_DIR || entry.file_name() == STAGING_DIR {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        }
+        .with_context(|| format!("failed to remove {}", path.display()))?;
+    }
+
+    for entry in fs::read_dir(&staging).context("failed to read staging directory")? {
+        let entry = entry?;
+        let dest = root.join(entry.file_name());
+        replace_file(&entry.path(), &dest)
+            .with_context(|| format!("failed to move {} into place", dest.display()))?;
+    }
+    fs::remove_dir(&staging).context("failed to remove staging directory")?;
+
+    Ok(manifest)
+}
+
+/// Write a snapshot's directories and files under `staging`, synced to disk.
+fn stage(staging: &Path, dirs: &[String], files: &[(String, Vec<u8>)]) -> Result<()> {
+    fs::create_dir_all(staging).context("failed to create staging directory")?;
+    for dir in dirs {
+        fs::create_dir_all(staging.join(dir))?;
+    }
+    for (path, data) in files {
+        let dest = staging.join(path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let write = || -> std::io::Result<()> {
+            let mut file = File::create(&dest)?;
+            file.write_all(data)?;
+            file.sync_all()
+        };
+        write().with_context(|| format!("failed to write {path}"))?;
+    }
+    Ok(())
+}
+
+/// Read a snapshot and verify it against its manifest without restoring it.
+pub fn verify(archive: &Path) -> Result<Manifest> {
+    read(archive).map(|(manifest, _, _)| manifest)
+}
+
+/// Snapshots in the store's backup directory, oldest first.
+pub fn list(root: &Path) -> Result<Vec<PathBuf>> {
+    let dir = root.join(BACKUP_DIR);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(&dir)
+        .context("failed to read backup directory")?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy())
+                .is_some_and(|n| n.starts_with(PREFIX) && n.ends_with(EXTENSION))
+        })
+        .collect();
+    // Names embed a sortable timestamp
+    backups.sort();
+    Ok(backups)
+}
+
+/// Delete all but the newest `keep` snapshots, returning the removed paths.
+pub fn prune(root: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    let mut backups = list(root)?;
+    let excess = backups.len().saturating_sub(keep);
+    let removed: Vec<PathBuf> = backups.drain(..excess).collect();
+    for path in &removed {
+        fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))?;
+    }
+    Ok(removed)
+}
+
+type Contents = (Manifest, Vec<String>, Vec<(String, Vec<u8>)>);
+
+/// Read and verify every entry of a snapshot.
+fn read(archive: &Path) -> Result<Contents> {
+    let file = File::open(archive)
+        .with_context(|| format!("failed to open backup {}", archive.display()))?;
+    let mut tar = tar::Archive::new(zstd::Decoder::new(file)?);
+
+    let mut manifest: Option<Manifest> = None;
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+
+    for entry in tar.entries().context("invalid backup archive")? {
+        let mut entry = entry.context("invalid backup archive")?;
+        let path = entry.path()?.into_owned();
+        if path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(anyhow!("unsafe path in backup: {}", path.display()));
+        }
+        let path = path.to_string_lossy().replace('\\', "/");
+
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => dirs.push(path.trim_end_matches('/').to_string()),
+            tar::EntryType::Regular => {
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                if path == MANIFEST {
+                    manifest = Some(serde_json::from_slice(&data).context("invalid manifest")?);
+                } else {
+                    files.push((path, data));
+                }
+            }
+            other => return Err(anyhow!("unsupported entry in backup: {path} ({other:?})")),
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| anyhow!("backup has no manifest"))?;
+    for (path, data) in &files {
+        match manifest.files.get(path) {
+            Some(sum) if *sum == sha256_hex(data) => {}
+            Some(_) => return Err(anyhow!("checksum mismatch in backup: {path}")),
+            None => return Err(anyhow!("file not in backup manifest: {path}")),
+        }
+    }
+    if files.len() != manifest.files.len() {
+        return Err(anyhow!(
+            "backup is missing {} files listed in its manifest",
+            manifest.files.len() - files.len()
+        ));
+    }
+
+    Ok((manifest, dirs, files))
+}
+
+/// Gather store contents, skipping backups, temp files, and lock files.
+fn collect(
+    dir: &Path,
+    prefix: &str,
+    dirs: &mut Vec<String>,
+    files: &mut Vec<(String, Vec<u8>)>,
+) -> Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .context("failed to read directory")?
+        .collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if prefix.is_empty() && name == BACKUP_DIR {
+            continue;
+        }
+        if name.ends_with(".tmp") || name.ends_with(".lock") {
+            continue;
+        }
+
+        let rel = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}/{name}")
+        };
+        let path = entry.path();
+        if path.is_dir() {
+            dirs.push(rel.clone());
+            collect(&path, &rel, dirs, files)?;
+        } else {
+            let data = fs::read(&path).with_context(|| format!("failed to read {rel}"))?;
+            files.push((rel, data));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn append_file<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    data: &[u8],
+    mtime: u64,
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_size(data.len() as u64);
+    builder.append_data(&mut header, path, data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup_store() -> (TempDir, PathBuf) {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join(".mems");
+        fs::create_dir_all(root.join("archive/old")).unwrap();
+        fs::create_dir_all(root.join(".index")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::write(root.join("a.md"), "a").unwrap();
+        fs::write(root.join("archive/old/b.md"), "b").unwrap();
+        fs::write(root.join(".index/embeddings"), "{}").unwrap();
+        fs::write(root.join("c.md.tmp"), "partial").unwrap();
+        (temp, root)
+    }
+
+    #[test]
+    fn test_create_and_restore() {
+        let (temp, root) = setup_store();
+        let out = temp.path().join("snap.tar.zst");
+
+        let manifest = create(&root, &out).unwrap();
+        let files: Vec<_> = manifest.files.keys().map(String::as_str).collect();
+        assert_eq!(files, vec![".index/embeddings", "a.md", "archive/old/b.md"]);
+
+        fs::write(root.join("a.md"), "changed").unwrap();
+        fs::write(root.join("new.md"), "new").unwrap();
+        // Left behind by an interrupted restore
+        fs::create_dir_all(root.join(STAGING_DIR)).unwrap();
+        fs::write(root.join(STAGING_DIR).join("stale.md"), "stale").unwrap();
+        restore(&out, &root).unwrap();
+
+        assert_eq!(fs::read_to_string(root.join("a.md")).unwrap(), "a");
+        assert_eq!(
+            fs::read_to_string(root.join("archive/old/b.md")).unwrap(),
+            "b"
+        );
+        assert!(root.join("empty").is_dir());
+        assert!(!root.join("new.md").exists());
+        assert!(!root.join("c.md.tmp").exists());
+        assert!(!root.join(STAGING_DIR).exists());
+        assert!(!root.join("stale.md").exists());
+    }
+
+    #[test]
+    fn test_restore_rejects_corrupt_backup() {
+        let (temp, root) = setup_store();
+        let out = temp.path().join("snap.tar.zst");
+        create(&root, &out).unwrap();
+
+        // Rewrite the archive with tampered content but the original manifest
+        let (manifest, _, _) = read(&out).unwrap();
+        let bad = temp.path().join("bad.tar.zst");
+        let encoder = zstd::Encoder::new(File::create(&bad).unwrap(), LEVEL).unwrap();
+        let mut builder = tar::Builder::new(encoder);
+        append_file(
+            &mut builder,
+            MANIFEST,
+            &serde_json::to_vec(&manifest).unwrap(),
+            0,
+        )
+        .unwrap();
+        append_file(&mut builder, "a.md", b"tampered", 0).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let err = restore(&bad, &root).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"));
+        // The store is untouched
+        assert_eq!(fs::read_to_string(root.join("a.md")).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_prune_keeps_newest() {
+        let (_temp, root) = setup_store();
+        let now = Utc::now();
+        let paths: Vec<_> = (0..3)
+            .map(|i| default_path(&root, now + chrono::Duration::seconds(i)))
+            .collect();
+        for path in &paths {
+            create(&root, path).unwrap();
+        }
+        assert_eq!(list(&root).unwrap(), paths);
+
+        let removed = prune(&root, 1).unwrap();
+        assert_eq!(removed, paths[..2]);
+        assert_eq!(list(&root).unwrap(), paths[2..]);
+    }
+}
@@ -0,0 +1,217 @@
+//! `mem bench`: time common operations on a synthetic store, to check the
+//! effect of cache and index changes and catch performance regressions.
+
+use crate::dump;
+use crate::find::{self, Filter};
+use crate::links;
+use crate::lint;
+use crate::mem::Mem;
+use crate::progress::Silent;
+use crate::query::Query;
+use crate::storage::Storage;
+use anyhow::Result;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Words synthetic content is made of; a few match the benchmark query.
+const WORDS: &[&str] = &[
+    "deploy",
+    "rollback",
+    "database",
+    "migration",
+    "latency",
+    "cache",
+    "index",
+    "owner",
+    "runbook",
+    "incident",
+    "service",
+    "queue",
+    "retry",
+    "timeout",
+    "config",
+    "release",
+    "schema",
+    "backup",
+    "restore",
+    "alert",
+    "metric",
+    "token",
+    "review",
+    "design",
+];
+
+/// Query timed as `find`.
+const FIND_QUERY: &str = "database migration -legacy";
+
+/// Shape of a synthetic store.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Shape {
+    /// Number of mems
+    pub mems: usize,
+
+    /// Directory levels above each mem
+    pub depth: usize,
+
+    /// Approximate content size of each mem, in bytes
+    pub size: usize,
+}
+
+/// Time taken by one operation over several runs.
+#[derive(Debug, Clone, Serialize)]
+pub struct Timing {
+    pub operation: &'static str,
+    pub runs: usize,
+    pub min_ms: f64,
+    pub mean_ms: f64,
+}
+
+/// Results of a benchmark run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    #[serde(flatten)]
+    pub shape: Shape,
+    pub generate_ms: f64,
+    pub timings: Vec<Timing>,
+}
+
+/// Deterministic pseudo-random numbers (xorshift), so every run generates
+/// the same store.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Path of the `i`th synthetic mem: `depth` directories, eight per level.
+fn path_for(i: usize, depth: usize) -> String {
+    let mut segments = Vec::with_capacity(depth + 1);
+    let mut n = i;
+    for level in 0..depth {
+        segments.push(format!("d{level}-{}", n % 8));
+        n /= 8;
+    }
+    segments.push(format!("mem-{i:06}"));
+    segments.join("/")
+}
+
+/// Write a synthetic store of the given shape: mems with headings,
+/// paragraphs, tags, and links to earlier mems.
+pub fn generate(storage: &Storage, shape: Shape) -> Result<()> {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    for i in 0..shape.mems {
+        let path = path_for(i, shape.depth);
+        let mut content = String::with_capacity(shape.size + 64);
+        for paragraph in 0.. {
+            if content.len() >= shape.size {
+                break;
+            }
+            if paragraph % 3 == 0 {
+                content.push_str(&format!("## {}\n\n", WORDS[rng.below(WORDS.len())]));
+            }
+            for _ in 0..40 {
+                content.push_str(WORDS[rng.below(WORDS.len())]);
+                content.push(' ');
+            }
+            if i > 0 {
+                let target = links::relative_target(&path, &path_for(rng.below(i), shape.depth));
+                content.push_str(&format!("See [related]({target})."));
+            }
+            content.push_str("\n\n");
+        }
+        let title = format!("Synthetic {i}");
+        let tags = vec![WORDS[i % WORDS.len()].to_string()];
+        storage.write_mem(&Mem::new(PathBuf::from(path), title, content).with_tags(tags))?;
+    }
+    Ok(())
+}
+
+/// Time `operation` over `runs` runs.
+fn time(
+    operation: &'static str,
+    runs: usize,
+    mut run: impl FnMut() -> Result<()>,
+) -> Result<Timing> {
+    let mut times = Vec::with_capacity(runs);
+    for _ in 0..runs.max(1) {
+        let start = Instant::now();
+        run()?;
+        times.push(start.elapsed().as_secs_f64() * 1000.0);
+    }
+    Ok(Timing {
+        operation,
+        runs: times.len(),
+        min_ms: times.iter().copied().fold(f64::INFINITY, f64::min),
+        mean_ms: times.iter().sum::<f64>() / times.len() as f64,
+    })
+}
+
+/// Time listing, `find`, `lint`, and `dump` over `storage`.
+pub fn run(storage: &Storage, runs: usize) -> Result<Vec<Timing>> {
+    let query = Query::parse(FIND_QUERY);
+    Ok(vec![
+        time("list", runs, || storage.list_mems().map(drop))?,
+        time("find", runs, || {
+            find::find(storage, &query, &Filter::default()).map(drop)
+        })?,
+        time("lint", runs, || {
+            lint::lint_storage("", storage, &Silent).map(drop)
+        })?,
+        time("dump", runs, || {
+            let mut out = io::sink();
+            dump::for_each_section(storage, "", dump::Order::Path, |_, section| {
+                Ok(out.write_all(section.as_bytes())?)
+            })
+        })?,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_path_for() {
+        assert_eq!(path_for(0, 0), "mem-000000");
+        assert_eq!(path_for(9, 2), "d0-1/d1-1/mem-000009");
+    }
+
+    #[test]
+    fn test_generate_and_run() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let shape = Shape {
+            mems: 20,
+            depth: 2,
+            size: 500,
+        };
+        generate(&storage, shape).unwrap();
+        let mems = storage.list_mems().unwrap();
+        assert_eq!(mems.len(), 20);
+        assert!(mems.iter().all(|m| m.content.len() >= 500));
+
+        let timings = run(&storage, 2).unwrap();
+        let operations: Vec<&str> = timings.iter().map(|t| t.operation).collect();
+        assert_eq!(operations, ["list", "find", "lint", "dump"]);
+        assert!(timings.iter().all(|t| t.runs == 2 && t.min_ms <= t.mean_ms));
+
+        // Generated links resolve, so lint finds nothing broken
+        let (_, issues) = lint::lint_storage("", &storage, &Silent).unwrap();
+        assert!(
+            !issues.iter().any(|i| i.rule == "broken-link"),
+            "{issues:?}"
+        );
+    }
+}
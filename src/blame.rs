@@ -0,0 +1,195 @@
+use crate::backup;
+use crate::git::BlameLine;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Last change to a section of a mem: the lines from one heading to the next.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Section {
+    /// Heading text without the `#` markers (`""` before the first heading)
+    pub heading: String,
+
+    /// 1-based file lines the section spans
+    pub start: usize,
+    pub end: usize,
+
+    /// Attribution of the most recently changed line
+    pub commit: String,
+    pub author: String,
+    pub time: DateTime<Utc>,
+}
+
+/// Group blamed content lines into sections by markdown heading. Lines up to
+/// `offset` (the frontmatter) are skipped.
+pub fn sections(lines: &[BlameLine], offset: usize) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut in_fence = false;
+    for line in lines.iter().filter(|l| l.line > offset) {
+        if line.text.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let heading = (!in_fence).then(|| heading(&line.text)).flatten();
+        match sections.last_mut() {
+            Some(section) if heading.is_none() => {
+                section.end = line.line;
+                if line.time > section.time {
+                    section.commit = line.commit.clone();
+                    section.author = line.author.clone();
+                    section.time = line.time;
+                }
+            }
+            _ => sections.push(Section {
+                heading: heading.unwrap_or_default().to_string(),
+                start: line.line,
+                end: line.line,
+                commit: line.commit.clone(),
+                author: line.author.clone(),
+                time: line.time,
+            }),
+        }
+    }
+    sections
+}
+
+fn heading(text: &str) -> Option<&str> {
+    let level = text.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    text[level..].strip_prefix(' ').map(str::trim)
+}
+
+/// How a mem file differs from the previous snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl Change {
+    /// Lowercase name, as used in output formats.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Modified => "modified",
+            Change::Deleted => "deleted",
+        }
+    }
+}
+
+/// A snapshot in which a mem file changed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Version {
+    pub snapshot: PathBuf,
+    pub taken: DateTime<Utc>,
+    pub change: Change,
+}
+
+/// Whole-file history of a mem from the store's backup snapshots, oldest
+/// first, for stores outside git. Only snapshots that changed the file are
+/// listed.
+pub fn snapshot_history(root: &Path, path: &str) -> Result<Vec<Version>> {
+    let file = format!("{path}.md");
+    let mut versions = Vec::new();
+    let mut previous: Option<String> = None;
+    for snapshot in backup::list(root)? {
+        let manifest = backup::verify(&snapshot)?;
+        let current = manifest.files.get(&file).cloned();
+        let change = match (&previous, &current) {
+            (None, Some(_)) => Some(Change::Added),
+            (Some(a), Some(b)) if a != b => Some(Change::Modified),
+            (Some(_), None) => Some(Change::Deleted),
+            _ => None,
+        };
+        if let Some(change) = change {
+            versions.push(Version {
+                snapshot,
+                taken: manifest.created_at,
+                change,
+            });
+        }
+        previous = current;
+    }
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn line(line: usize, day: u32, text: &str) -> BlameLine {
+        BlameLine {
+            commit: format!("c{day}"),
+            author: format!("author{day}"),
+            time: format!("2024-01-{day:02}T00:00:00Z").parse().unwrap(),
+            line,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sections() {
+        let lines = vec![
+            line(1, 9, "---"),
+            line(2, 9, "---"),
+            line(3, 1, "Intro"),
+            line(4, 1, "## On-call escalation"),
+            line(5, 3, "Page the secondary"),
+            line(6, 2, "```"),
+            line(7, 2, "# not a heading"),
+            line(8, 2, "```"),
+            line(9, 1, "## Contacts"),
+        ];
+        let sections = sections(&lines, 2);
+        let summary: Vec<(&str, usize, usize, &str)> = sections
+            .iter()
+            .map(|s| (s.heading.as_str(), s.start, s.end, s.author.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("", 3, 3, "author1"),
+                ("On-call escalation", 4, 8, "author3"),
+                ("Contacts", 9, 9, "author1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_history() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        let snapshot = |n: u32| {
+            let out = root
+                .join(backup::BACKUP_DIR)
+                .join(format!("mems-2024010{n}T000000000Z.tar.zst"));
+            backup::create(root, &out).unwrap();
+        };
+
+        fs::write(root.join("other.md"), "x").unwrap();
+        snapshot(1);
+        fs::write(root.join("a.md"), "1").unwrap();
+        snapshot(2);
+        snapshot(3);
+        fs::write(root.join("a.md"), "2").unwrap();
+        snapshot(4);
+        fs::remove_file(root.join("a.md")).unwrap();
+        snapshot(5);
+
+        let changes: Vec<Change> = snapshot_history(root, "a")
+            .unwrap()
+            .iter()
+            .map(|v| v.change)
+            .collect();
+        assert_eq!(
+            changes,
+            vec![Change::Added, Change::Modified, Change::Deleted]
+        );
+    }
+}
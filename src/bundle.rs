@@ -0,0 +1,391 @@
+//! Single-mem bundles (`.memx`) for sharing one document between stores:
+//! the mem file with its frontmatter, the local files it links to, and the
+//! commits that changed it, in one zstd-compressed tar.
+//!
+//! `mem export mem` writes a bundle and `mem import memx` unpacks it into
+//! another store. Only plain files travel as attachments: never mems, hidden
+//! files, archived files, or files that configure the store. On import they
+//! are stored by content under `attachments/` and the mem's links follow them.
+
+use crate::attachment::{self, attachment_targets, store_key};
+use crate::backup::append_file;
+use crate::git::{self, Commit};
+use crate::hash::sha256_hex;
+use crate::id;
+use crate::mem::Mem;
+use crate::storage::{normalize_path, Storage};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// File extension of bundles.
+pub const EXTENSION: &str = "memx";
+
+/// Version of the bundle format this build writes.
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Bundle entry holding the manifest, written first.
+const MANIFEST: &str = "bundle.json";
+
+/// Bundle entry holding the mem file.
+const MEM_FILE: &str = "mem.md";
+
+/// Directory of attachment entries.
+const ATTACHMENT_DIR: &str = "attachments";
+
+/// zstd compression level (the library default).
+const LEVEL: i32 = 3;
+
+/// A file a mem links to, carried in the bundle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Link target in the mem, relative to its directory
+    pub target: String,
+
+    /// Entry in the bundle
+    pub file: String,
+}
+
+/// What a bundle holds, with the SHA-256 checksum of every entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+
+    /// Path of the mem in the store it was exported from
+    pub path: String,
+    pub exported_at: DateTime<Utc>,
+    pub attachments: Vec<Attachment>,
+
+    /// Commits that changed the mem, newest first (empty outside git)
+    pub history: Vec<Commit>,
+    pub files: BTreeMap<String, String>,
+}
+
+/// A mem with its attachments and history.
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    pub manifest: Manifest,
+
+    /// The mem file as stored: frontmatter and content
+    pub source: String,
+
+    /// Contents of each attachment, in manifest order
+    pub attachments: Vec<Vec<u8>>,
+}
+
+impl Bundle {
+    /// Bundle the mem at `path` with the store files it links to and its
+    /// git history.
+    pub fn collect(storage: &Storage, path: &str) -> Result<Bundle> {
+        let path = storage.resolve(path)?;
+        let source = storage.read_source(&path)?;
+        let mem = Mem::parse(PathBuf::from(&path), &source)?;
+
+        let mut manifest = Manifest {
+            version: BUNDLE_VERSION,
+            path: path.clone(),
+            exported_at: Utc::now(),
+            attachments: Vec::new(),
+            history: Vec::new(),
+            files: BTreeMap::from([(MEM_FILE.to_string(), sha256_hex(source.as_bytes()))]),
+        };
+        let mut attachments = Vec::new();
+        for target in attachment_targets(&mem.content) {
+            let Some(key) = store_key(&path, &target) else {
+                continue;
+            };
+            if !attachment::is_attachable(&key) {
+                continue;
+            }
+            let Some(data) = storage.read_bytes(&key)? else {
+                continue;
+            };
+            let name = key.rsplit('/').next().unwrap_or(&key);
+            let file = format!("{ATTACHMENT_DIR}/{}-{name}", attachments.len() + 1);
+            manifest.files.insert(file.clone(), sha256_hex(&data));
+            manifest.attachments.push(Attachment { target, file });
+            attachments.push(data);
+        }
+        if storage.local_root().is_ok() {
+            // A store outside git has no history to carry
+            manifest.history = git::log(&storage.mem_path(&path)).unwrap_or_default();
+        }
+
+        Ok(Bundle {
+            manifest,
+            source,
+            attachments,
+        })
+    }
+
+    /// Write the bundle to `out`.
+    pub fn write(&self, out: &Path) -> Result<()> {
+        let result = (|| -> Result<()> {
+            let encoder = zstd::Encoder::new(File::create(out)?, LEVEL)?;
+            let mut builder = tar::Builder::new(encoder);
+            let mtime = self.manifest.exported_at.timestamp().max(0) as u64;
+            append_file(
+                &mut builder,
+                MANIFEST,
+                &serde_json::to_vec_pretty(&self.manifest)?,
+                mtime,
+            )?;
+            append_file(&mut builder, MEM_FILE, self.source.as_bytes(), mtime)?;
+            for (attachment, data) in self.manifest.attachments.iter().zip(&self.attachments) {
+                append_file(&mut builder, &attachment.file, data, mtime)?;
+            }
+            builder.into_inner()?.finish()?.sync_all()?;
+            Ok(())
+        })();
+        if let Err(e) = result {
+            let _ = fs::remove_file(out);
+            return Err(e.context(format!("failed to write bundle {}", out.display())));
+        }
+        Ok(())
+    }
+
+    /// Read a bundle and verify its entries against the manifest.
+    pub fn read(file: &Path) -> Result<Bundle> {
+        let archive = File::open(file)
+            .with_context(|| format!("failed to open bundle {}", file.display()))?;
+        let mut tar = tar::Archive::new(zstd::Decoder::new(archive)?);
+
+        let mut manifest: Option<Manifest> = None;
+        let mut entries = BTreeMap::new();
+        for entry in tar.entries().context("invalid bundle")? {
+            let mut entry = entry.context("invalid bundle")?;
+            let name = entry.path()?.to_string_lossy().replace('\\', "/");
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            if name == MANIFEST {
+                manifest = Some(serde_json::from_slice(&data).context("invalid bundle manifest")?);
+            } else {
+                entries.insert(name, data);
+            }
+        }
+
+        let manifest = manifest.ok_or_else(|| anyhow!("bundle has no manifest"))?;
+        if manifest.version > BUNDLE_VERSION {
+            return Err(anyhow!(
+                "bundle version {} is newer than this mem supports ({BUNDLE_VERSION})",
+                manifest.version
+            ));
+        }
+        for (name, sum) in &manifest.files {
+            match entries.get(name) {
+                Some(data) if sha256_hex(data) == *sum => {}
+                Some(_) => return Err(anyhow!("checksum mismatch in bundle: {name}")),
+                None => return Err(anyhow!("bundle is missing {name}")),
+            }
+        }
+        if let Some(extra) = entries
+            .keys()
+            .find(|name| !manifest.files.contains_key(*name))
+        {
+            return Err(anyhow!("file not in bundle manifest: {extra}"));
+        }
+
+        let source = String::from_utf8(entries.remove(MEM_FILE).unwrap_or_default())
+            .context("bundled mem is not UTF-8")?;
+        let attachments = manifest
+            .attachments
+            .iter()
+            .map(|a| {
+                entries
+                    .get(&a.file)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("bundle is missing {}", a.file))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Bundle {
+            manifest,
+            source,
+            attachments,
+        })
+    }
+}
+
+/// What importing a bundle wrote.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Imported {
+    pub path: String,
+
+    /// Blobs under `attachments/` holding the attachments
+    pub attachments: Vec<String>,
+
+    /// The id the mem had, when another mem in the store already used it
+    /// and it was given a new one
+    pub replaced_id: Option<String>,
+}
+
+/// Unpack `bundle` into `storage` at `dest` (default: the path it was
+/// exported from). An existing mem is only overwritten with `force`.
+pub fn import(
+    storage: &Storage,
+    bundle: &Bundle,
+    dest: Option<&str>,
+    force: bool,
+) -> Result<Imported> {
+    let path = normalize_path(dest.unwrap_or(&bundle.manifest.path))?;
+    if !force && storage.exists(&path) {
+        return Err(anyhow!(
+            "mem already exists: {path} (use --force to overwrite)"
+        ));
+    }
+
+    // Check every attachment before writing any
+    let mut names = Vec::new();
+    for attachment in &bundle.manifest.attachments {
+        let key = store_key(&bundle.manifest.path, &attachment.target)
+            .filter(|key| attachment::is_attachable(key))
+            .ok_or_else(|| anyhow!("bundle has a disallowed attachment: {}", attachment.target))?;
+        names.push(key.rsplit('/').next().unwrap_or(&key).to_string());
+    }
+
+    let mut mem = Mem::parse(PathBuf::from(&path), &bundle.source)?;
+    let mut replaced_id = None;
+    if let Some(old) = mem.id.clone() {
+        if storage.id_taken(&old, &path)? {
+            mem.id = Some(id::generate());
+            replaced_id = Some(old);
+        }
+    }
+
+    let mut keys = Vec::new();
+    for ((attachment, data), name) in bundle
+        .manifest
+        .attachments
+        .iter()
+        .zip(&bundle.attachments)
+        .zip(&names)
+    {
+        let (key, _) = attachment::store(storage, name, data)?;
+        let target = attachment::link_target(&path, &key);
+        mem.content = attachment::retarget_links(&mem.content, &attachment.target, &target);
+        keys.push(key);
+    }
+    storage.write_mem(&mem)?;
+    Ok(Imported {
+        path,
+        attachments: keys,
+        replaced_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn store() -> (TempDir, Storage) {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::init_at(&temp.path().join(".mems")).unwrap();
+        (temp, storage)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (temp, source) = store();
+        let root = source.local_root().unwrap();
+        let mut mem = Mem::new(
+            PathBuf::from("ops/deploy"),
+            "Deploy".to_string(),
+            "![Flow](img/flow.png) and [gone](missing.png)\n".to_string(),
+        );
+        mem.set_field("owner", "sam");
+        mem.tags = vec!["ops".to_string()];
+        source.write_mem(&mem).unwrap();
+        fs::create_dir_all(root.join("ops/img")).unwrap();
+        fs::write(root.join("ops/img/flow.png"), b"\x89PNG").unwrap();
+
+        let out = temp.path().join("deploy.memx");
+        let bundle = Bundle::collect(&source, "ops/deploy").unwrap();
+        assert_eq!(bundle.manifest.attachments.len(), 1);
+        bundle.write(&out).unwrap();
+
+        let (_dest_temp, dest) = store();
+        let read = Bundle::read(&out).unwrap();
+        assert_eq!(read.manifest, bundle.manifest);
+        let imported = import(&dest, &read, Some("shared/deploy"), false).unwrap();
+        let blob = attachment::blob_key("flow.png", b"\x89PNG");
+        assert_eq!(imported.attachments, vec![blob.clone()]);
+        assert_eq!(imported.replaced_id, None);
+
+        let copy = dest.read_mem("shared/deploy").unwrap();
+        assert_eq!(copy.id, mem.id);
+        assert_eq!(copy.owner(), Some("sam"));
+        assert_eq!(copy.tags, mem.tags);
+        assert_eq!(copy.created_at, mem.created_at);
+        assert_eq!(
+            copy.content,
+            format!(
+                "![Flow]({}) and [gone](missing.png)\n",
+                attachment::link_target("shared/deploy", &blob)
+            )
+        );
+        assert_eq!(dest.read_bytes(&blob).unwrap().unwrap(), b"\x89PNG");
+        assert!(!dest.file_exists("shared/img/flow.png").unwrap());
+
+        // Again elsewhere: the id is taken, so the copy gets a new one
+        let err = import(&dest, &read, Some("shared/deploy"), false).unwrap_err();
+        assert!(err.to_string().starts_with("mem already exists"), "{err}");
+        let again = import(&dest, &read, Some("shared/other"), false).unwrap();
+        assert_eq!(again.replaced_id, mem.id);
+        assert_ne!(dest.read_mem("shared/other").unwrap().id, mem.id);
+    }
+
+    #[test]
+    fn test_control_files_stay_home() {
+        let (_temp, source) = store();
+        let root = source.local_root().unwrap();
+        source
+            .write_mem(&Mem::new(
+                PathBuf::from("setup"),
+                "Setup".to_string(),
+                "[config](config.yaml) [ignore](.memsignore) [other](other.md)\n".to_string(),
+            ))
+            .unwrap();
+        fs::write(root.join(".memsignore"), "drafts/\n").unwrap();
+        let bundle = Bundle::collect(&source, "setup").unwrap();
+        assert!(bundle.manifest.attachments.is_empty());
+
+        // A crafted bundle naming one is refused before anything is written
+        let mut crafted = bundle.clone();
+        crafted.manifest.attachments.push(Attachment {
+            target: "config.yaml".to_string(),
+            file: format!("{ATTACHMENT_DIR}/1-config.yaml"),
+        });
+        crafted.attachments.push(b"backend: s3\n".to_vec());
+        let (_dest_temp, dest) = store();
+        let config = dest.read_bytes("config.yaml").unwrap();
+        let err = import(&dest, &crafted, None, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "bundle has a disallowed attachment: config.yaml"
+        );
+        assert_eq!(dest.read_bytes("config.yaml").unwrap(), config);
+        assert!(!dest.exists("setup"));
+    }
+
+    #[test]
+    fn test_read_rejects_tampering() {
+        let (temp, storage) = store();
+        storage
+            .write_mem(&Mem::new(
+                PathBuf::from("a"),
+                "A".to_string(),
+                "Hi\n".to_string(),
+            ))
+            .unwrap();
+        let mut bundle = Bundle::collect(&storage, "a").unwrap();
+        bundle.source.push_str("tampered\n");
+        let out = temp.path().join("a.memx");
+        bundle.write(&out).unwrap();
+        let err = Bundle::read(&out).unwrap_err();
+        assert_eq!(err.to_string(), "checksum mismatch in bundle: mem.md");
+    }
+}
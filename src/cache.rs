@@ -0,0 +1,355 @@
+use crate::links;
+use crate::mem::{is_tampered, Mem};
+use crate::progress::Progress;
+use crate::storage::Storage;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tracing::debug;
+
+/// Name of the cache database inside .mems/.
+pub const CACHE_FILE: &str = ".cache.db";
+
+/// Bumped whenever the schema changes; older caches are rebuilt.
+const SCHEMA_VERSION: i64 = 3;
+
+const SCHEMA: &str = "
+    CREATE TABLE mems (
+        path TEXT PRIMARY KEY,
+        id TEXT,
+        title TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        checksum TEXT,
+        extra TEXT NOT NULL,
+        content TEXT NOT NULL,
+        mtime INTEGER NOT NULL
+    );
+    CREATE TABLE tags (path TEXT NOT NULL, tag TEXT NOT NULL);
+    CREATE TABLE links (path TEXT NOT NULL, target TEXT NOT NULL);
+    CREATE INDEX mems_by_id ON mems(id);
+    CREATE INDEX tags_by_path ON tags(path);
+    CREATE INDEX tags_by_tag ON tags(tag);
+    CREATE INDEX links_by_path ON links(path);
+    CREATE INDEX links_by_target ON links(target);
+";
+
+/// SQLite mirror of a store's mems, tags, and links.
+///
+/// The cache is opt-in: it is only used once `.cache.db` exists. Entries are
+/// validated against file modification times whenever it is opened, so edits
+/// made outside `mem` are picked up.
+pub struct Cache {
+    conn: Connection,
+}
+
+impl Cache {
+    /// Open the cache of a store directory, if one has been created.
+    pub fn open(root: &Path) -> Result<Option<Self>> {
+        let path = root.join(CACHE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::connect(&path).map(Some)
+    }
+
+    /// Create an empty cache, replacing any existing one.
+    pub fn create(root: &Path) -> Result<Self> {
+        let path = root.join(CACHE_FILE);
+        if path.exists() {
+            fs::remove_file(&path).context("failed to remove old cache")?;
+        }
+        Self::connect(&path)
+    }
+
+    fn connect(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).context("failed to open cache")?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+        if version != SCHEMA_VERSION {
+            conn.execute_batch(&format!(
+                "DROP TABLE IF EXISTS mems; DROP TABLE IF EXISTS tags; DROP TABLE IF EXISTS links;
+                 {SCHEMA}
+                 PRAGMA user_version = {SCHEMA_VERSION};"
+            ))
+            .context("failed to initialize cache")?;
+        }
+        Ok(Self { conn })
+    }
+
+    /// Bring the cache in line with the files on disk, returning how many
+    /// mems were re-read.
+    pub fn refresh(&mut self, storage: &Storage, progress: &dyn Progress) -> Result<usize> {
+        let start = Instant::now();
+        let cached: HashMap<String, i64> = {
+            let mut stmt = self.conn.prepare("SELECT path, mtime FROM mems")?;
+            let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let mut current = HashMap::new();
+        for path in storage.mem_keys("")? {
+            let mtime = file_mtime(&storage.mem_path(&path))?;
+            current.insert(path, mtime);
+        }
+
+        let stale: Vec<(&String, &i64)> = current
+            .iter()
+            .filter(|(path, mtime)| cached.get(*path) != Some(*mtime))
+            .collect();
+        let tx = self.conn.transaction()?;
+        progress.start("Caching", stale.len());
+        for (path, mtime) in &stale {
+            match storage.read_mem(path) {
+                Ok(mem) => upsert(&tx, &mem, **mtime)?,
+                Err(e) => {
+                    eprintln!("warning: skipping invalid mem {path}: {e}");
+                    remove(&tx, path)?;
+                }
+            }
+            progress.advance(path);
+        }
+        progress.finish();
+        let reread = stale.len();
+        for path in cached.keys().filter(|p| !current.contains_key(*p)) {
+            remove(&tx, path)?;
+        }
+        tx.commit()?;
+
+        debug!(
+            mems = current.len(),
+            reread,
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "refreshed cache"
+        );
+        Ok(reread)
+    }
+
+    /// Update the entry for one mem after it was written, deleted, or moved.
+    pub fn sync_path(&mut self, storage: &Storage, path: &str) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        if storage.exists(path) {
+            let mtime = file_mtime(&storage.mem_path(path))?;
+            upsert(&tx, &storage.read_mem(path)?, mtime)?;
+        } else {
+            remove(&tx, path)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Number of cached mems.
+    pub fn len(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM mems", [], |r| r.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Whether the cache holds no mems.
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Mems under `prefix` (all mems for `""`), sorted by path.
+    pub fn mems_under(&self, prefix: &str) -> Result<Vec<Mem>> {
+        let prefix = prefix.trim_end_matches('/');
+        let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+        {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT path, tag FROM tags ORDER BY rowid")?;
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get(1)?)))?;
+            for row in rows {
+                let (path, tag) = row?;
+                tags.entry(path).or_default().push(tag);
+            }
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT path, id, title, created_at, updated_at, checksum, extra, content FROM mems
+             WHERE ?1 = '' OR substr(path, 1, length(?1) + 1) = ?1 || '/'
+             ORDER BY path",
+        )?;
+        let rows = stmt.query_map([prefix], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, Option<String>>(1)?,
+                r.get::<_, String>(2)?,
+                r.get::<_, String>(3)?,
+                r.get::<_, String>(4)?,
+                r.get::<_, Option<String>>(5)?,
+                r.get::<_, String>(6)?,
+                r.get::<_, String>(7)?,
+            ))
+        })?;
+
+        let mut mems = Vec::new();
+        for row in rows {
+            let (path, id, title, created_at, updated_at, checksum, extra, content) = row?;
+            mems.push(Mem {
+                tags: tags.remove(&path).unwrap_or_default(),
+                path: PathBuf::from(path),
+                id,
+                title,
+                created_at: parse_time(&created_at)?,
+                updated_at: parse_time(&updated_at)?,
+                tampered: is_tampered(&checksum, &content),
+                checksum,
+                extra: serde_yaml::from_str(&extra).context("invalid cached fields")?,
+                content,
+            });
+        }
+        Ok(mems)
+    }
+
+    /// Every tag with the number of mems using it, most used first.
+    pub fn tag_counts(&self) -> Result<Vec<(String, usize)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT tag, COUNT(DISTINCT path) AS n FROM tags GROUP BY tag ORDER BY n DESC, tag",
+        )?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get::<_, i64>(1)? as usize)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Paths of the mems that link to `path`, by path or by id.
+    pub fn backlinks(&self, path: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT path FROM links
+             WHERE target = ?1
+                OR target = (SELECT 'id:' || upper(id) FROM mems WHERE path = ?1)
+             ORDER BY path",
+        )?;
+        let rows = stmt.query_map([path], |r| r.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Paths of the mems with the given id, sorted.
+    pub fn paths_for_id(&self, id: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM mems WHERE upper(id) = upper(?1) ORDER BY path")?;
+        let rows = stmt.query_map([id], |r| r.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+fn upsert(conn: &Connection, mem: &Mem, mtime: i64) -> Result<()> {
+    let path = mem.path_str();
+    remove(conn, &path)?;
+    conn.execute(
+        "INSERT INTO mems (path, id, title, created_at, updated_at, checksum, extra, content, mtime)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            path,
+            mem.id,
+            mem.title,
+            format_time(mem.created_at),
+            format_time(mem.updated_at),
+            mem.checksum,
+            serde_yaml::to_string(&mem.extra)?,
+            mem.content,
+            mtime
+        ],
+    )?;
+    for tag in &mem.tags {
+        conn.execute(
+            "INSERT INTO tags (path, tag) VALUES (?1, ?2)",
+            params![path, tag],
+        )?;
+    }
+    for target in links::targets(mem) {
+        conn.execute(
+            "INSERT INTO links (path, target) VALUES (?1, ?2)",
+            params![path, target],
+        )?;
+    }
+    Ok(())
+}
+
+fn remove(conn: &Connection, path: &str) -> Result<()> {
+    for table in ["mems", "tags", "links"] {
+        conn.execute(&format!("DELETE FROM {table} WHERE path = ?1"), [path])?;
+    }
+    Ok(())
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| anyhow!("invalid cached timestamp {value}: {e}"))
+}
+
+/// Modification time of a file in nanoseconds since the epoch.
+fn file_mtime(path: &Path) -> Result<i64> {
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .with_context(|| format!("failed to stat {}", path.display()))?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Silent;
+    use tempfile::TempDir;
+
+    fn setup_storage() -> (TempDir, Storage) {
+        let temp = TempDir::new().unwrap();
+        let mems_dir = temp.path().join(".mems");
+        fs::create_dir(&mems_dir).unwrap();
+        (temp, Storage::new(mems_dir))
+    }
+
+    #[test]
+    fn test_refresh_tracks_external_changes() {
+        let (_temp, storage) = setup_storage();
+        let mut mem = Mem::new(
+            PathBuf::from("a/one"),
+            "One".to_string(),
+            "See [two](two.md)".to_string(),
+        );
+        mem.tags = vec!["x".to_string(), "y".to_string()];
+        mem.set_field("owner", "ops");
+        storage.write_mem(&mem).unwrap();
+        storage
+            .write_mem(&Mem::new(
+                PathBuf::from("b"),
+                "B".to_string(),
+                String::new(),
+            ))
+            .unwrap();
+
+        let mut cache = Cache::create(storage.root()).unwrap();
+        assert_eq!(cache.refresh(&storage, &Silent).unwrap(), 2);
+        assert_eq!(cache.refresh(&storage, &Silent).unwrap(), 0);
+
+        let mems = cache.mems_under("a").unwrap();
+        assert_eq!(mems.len(), 1);
+        assert_eq!(mems[0].tags, vec!["x", "y"]);
+        assert_eq!(mems[0].field("owner"), Some("ops"));
+        assert_eq!(mems[0].created_at, mem.created_at);
+        assert_eq!(cache.backlinks("a/two").unwrap(), vec!["a/one"]);
+        assert_eq!(
+            cache.tag_counts().unwrap(),
+            vec![("x".to_string(), 1), ("y".to_string(), 1)]
+        );
+
+        // Files changed behind the cache's back are noticed
+        fs::remove_file(storage.mem_path("b")).unwrap();
+        assert_eq!(cache.refresh(&storage, &Silent).unwrap(), 0);
+        assert_eq!(cache.len().unwrap(), 1);
+    }
+}
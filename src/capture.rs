@@ -0,0 +1,155 @@
+//! Recording a command's output in a mem (`mem capture`).
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::fmt::Write as _;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// The result of running a command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    pub command: Vec<String>,
+    pub stdout: String,
+    pub stderr: String,
+
+    /// Exit code (`None` when killed by a signal)
+    pub code: Option<i32>,
+
+    pub started_at: DateTime<Utc>,
+    pub duration: Duration,
+}
+
+impl Capture {
+    /// Run `command` (program and arguments), collecting its output. Stdin is
+    /// inherited so interactive prompts still work.
+    pub fn run(command: &[String]) -> Result<Self> {
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| anyhow!("no command to capture"))?;
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::inherit())
+            .output()
+            .with_context(|| format!("failed to run {program}"))?;
+        Ok(Self {
+            command: command.to_vec(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            code: output.status.code(),
+            started_at,
+            duration: start.elapsed(),
+        })
+    }
+
+    /// The command line as it would be typed in a shell.
+    pub fn command_line(&self) -> String {
+        self.command
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// A markdown section recording the run: the command as a heading, when
+    /// and how it ended, and each non-empty stream in a fenced block.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("## `{}`\n\n", self.command_line());
+        let code = self
+            .code
+            .map_or("none (killed by a signal)".to_string(), |c| c.to_string());
+        let _ = writeln!(
+            out,
+            "Ran {} for {:.1}s, exit code {code}.",
+            self.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            self.duration.as_secs_f64()
+        );
+        for (name, text) in [("stdout", &self.stdout), ("stderr", &self.stderr)] {
+            if text.is_empty() {
+                continue;
+            }
+            let fence = fence_for(text);
+            let _ = write!(out, "\n{name}:\n\n{fence}text\n{text}");
+            if !text.ends_with('\n') {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "{fence}");
+        }
+        if self.stdout.is_empty() && self.stderr.is_empty() {
+            out.push_str("\nNo output.\n");
+        }
+        out
+    }
+}
+
+/// Quote an argument for display when a shell would split or expand it.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// A code fence longer than any run of backticks in `text`, so output
+/// containing fences can't close the block early.
+fn fence_for(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat((longest + 1).max(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn capture() -> Capture {
+        Capture {
+            command: vec![
+                "grep".to_string(),
+                "-c".to_string(),
+                "it's here".to_string(),
+            ],
+            stdout: "3\n".to_string(),
+            stderr: String::new(),
+            code: Some(0),
+            started_at: Utc.with_ymd_and_hms(2025, 3, 1, 9, 30, 0).unwrap(),
+            duration: Duration::from_millis(1300),
+        }
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        assert_eq!(
+            capture().to_markdown(),
+            "## `grep -c 'it'\\''s here'`\n\n\
+             Ran 2025-03-01 09:30:00 UTC for 1.3s, exit code 0.\n\n\
+             stdout:\n\n```text\n3\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_fence_for() {
+        assert_eq!(fence_for("plain"), "```");
+        assert_eq!(fence_for("```rust\n```"), "````");
+    }
+
+    #[test]
+    fn test_run() {
+        let command: Vec<String> = ["sh", "-c", "echo out; echo err >&2; exit 3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let capture = Capture::run(&command).unwrap();
+        assert_eq!(capture.stdout, "out\n");
+        assert_eq!(capture.stderr, "err\n");
+        assert_eq!(capture.code, Some(3));
+        assert!(Capture::run(&[]).is_err());
+    }
+}
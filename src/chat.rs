@@ -0,0 +1,579 @@
+//! Importing chat history from Slack and Discord exports
+//! (`mem import slack`, `mem import discord`).
+//!
+//! Each thread becomes a mem under its channel, titled by its first message;
+//! messages outside threads are collected into one mem per channel and day.
+//! The people who took part go in a `participants` field, and the mem's
+//! timestamps are those of its first and last message.
+
+use crate::import::{unique_path, Imported};
+use crate::mem::{slugify, Mem};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Longest title taken from a thread's first message, in characters.
+const TITLE_CHARS: usize = 60;
+
+/// A chat message, with its text as markdown.
+#[derive(Debug, Clone)]
+struct Message {
+    id: String,
+    author: String,
+    time: DateTime<Utc>,
+    text: String,
+
+    /// Id of the message that started the thread this one replies in
+    thread: Option<String>,
+}
+
+/// A channel's messages, oldest first.
+#[derive(Debug)]
+struct Channel {
+    name: String,
+
+    /// Whether the whole channel is one thread (Discord thread channels)
+    is_thread: bool,
+    messages: Vec<Message>,
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())
+}
+
+/// JSON files directly in `dir`, sorted.
+fn json_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("failed to read {}", dir.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "json"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn read_json(file: &Path) -> Result<Value> {
+    let text =
+        fs::read_to_string(file).with_context(|| format!("failed to read {}", file.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("invalid JSON in {}", file.display()))
+}
+
+fn file_stem(file: &Path) -> String {
+    file.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Slack `ts` values ("1709476200.000100") as times.
+fn slack_time(ts: &str) -> Option<DateTime<Utc>> {
+    let (secs, micros) = ts.split_once('.').unwrap_or((ts, "0"));
+    let micros: u32 = format!("{micros:0<6}").get(..6)?.parse().ok()?;
+    DateTime::from_timestamp(secs.parse().ok()?, micros * 1000)
+}
+
+/// Slack's message formatting as markdown: mentions, channel references,
+/// and labelled links resolved, HTML entities decoded.
+fn slack_markdown(text: &str, users: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let inner = &rest[start + 1..start + len];
+        let (target, label) = match inner.split_once('|') {
+            Some((target, label)) => (target, Some(label)),
+            None => (inner, None),
+        };
+        let converted = if let Some(user) = target.strip_prefix('@') {
+            let name = label
+                .or(users.get(user).map(String::as_str))
+                .unwrap_or(user);
+            format!("@{name}")
+        } else if let Some(channel) = target.strip_prefix('#') {
+            format!("#{}", label.unwrap_or(channel))
+        } else if let Some(special) = target.strip_prefix('!') {
+            format!("@{}", label.unwrap_or(special).trim_start_matches('@'))
+        } else {
+            match label {
+                Some(label) => format!("[{label}]({target})"),
+                None => format!("<{target}>"),
+            }
+        };
+        out.push_str(&converted);
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Messages of a Slack channel history: an array, or an object holding one
+/// in `messages` (as the conversations API returns).
+fn slack_messages(value: &Value, users: &HashMap<String, String>) -> Result<Vec<Message>> {
+    let list = match value {
+        Value::Array(list) => list,
+        _ => value
+            .get("messages")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("not a Slack export: expected an array of messages"))?,
+    };
+    let mut messages = Vec::new();
+    for message in list {
+        // Joins, leaves, topic changes, and the like aren't conversation
+        if str_field(message, "subtype")
+            .is_some_and(|s| s != "thread_broadcast" && s != "bot_message")
+        {
+            continue;
+        }
+        let (Some(ts), Some(text)) = (str_field(message, "ts"), str_field(message, "text")) else {
+            continue;
+        };
+        let Some(time) = slack_time(ts) else {
+            continue;
+        };
+        let user = str_field(message, "user");
+        let profile = message.get("user_profile");
+        let author = profile
+            .and_then(|p| str_field(p, "real_name").or(str_field(p, "display_name")))
+            .map(str::to_string)
+            .or_else(|| user.and_then(|u| users.get(u).cloned()))
+            .or_else(|| str_field(message, "username").map(str::to_string))
+            .or_else(|| user.map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string());
+        messages.push(Message {
+            id: ts.to_string(),
+            author,
+            time,
+            text: slack_markdown(text, users),
+            thread: str_field(message, "thread_ts").map(str::to_string),
+        });
+    }
+    Ok(messages)
+}
+
+/// Names of the users in a workspace export's `users.json`, by id.
+fn slack_users(dir: &Path) -> Result<HashMap<String, String>> {
+    let file = dir.join("users.json");
+    if !file.is_file() {
+        return Ok(HashMap::new());
+    }
+    let users = read_json(&file)?;
+    Ok(users
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|user| {
+            let id = str_field(user, "id")?;
+            let profile = user.get("profile");
+            let name = profile
+                .and_then(|p| str_field(p, "real_name").or(str_field(p, "display_name")))
+                .or(str_field(user, "real_name"))
+                .or(str_field(user, "name"))?;
+            Some((id.to_string(), name.to_string()))
+        })
+        .collect())
+}
+
+/// Read a Slack export: a workspace export directory (a directory per
+/// channel holding a JSON file per day, and `users.json`), or one channel's
+/// history as a JSON file.
+fn read_slack(path: &Path) -> Result<Vec<Channel>> {
+    if path.is_file() {
+        let value = read_json(path)?;
+        return Ok(vec![Channel {
+            name: file_stem(path),
+            is_thread: false,
+            messages: slack_messages(&value, &HashMap::new())?,
+        }]);
+    }
+
+    let users = slack_users(path)?;
+    let mut dirs: Vec<PathBuf> = fs::read_dir(path)
+        .with_context(|| format!("failed to read {}", path.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort();
+    let mut channels = Vec::new();
+    for dir in dirs {
+        let mut messages = Vec::new();
+        for file in json_files(&dir)? {
+            messages.extend(slack_messages(&read_json(&file)?, &users)?);
+        }
+        if !messages.is_empty() {
+            channels.push(Channel {
+                name: file_stem(&dir),
+                is_thread: false,
+                messages,
+            });
+        }
+    }
+    if channels.is_empty() {
+        return Err(anyhow!(
+            "not a Slack export (no channel directories with messages): {}",
+            path.display()
+        ));
+    }
+    Ok(channels)
+}
+
+/// Read a DiscordChatExporter JSON export of a channel.
+fn discord_channel(file: &Path) -> Result<Channel> {
+    let value = read_json(file)?;
+    let channel = value.get("channel");
+    let list = value
+        .get("messages")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("not a Discord export (no messages): {}", file.display()))?;
+
+    let mut messages: Vec<Message> = Vec::new();
+    for message in list {
+        // Pins, joins, and the like aren't conversation
+        if str_field(message, "type").is_some_and(|t| t != "Default" && t != "Reply") {
+            continue;
+        }
+        let (Some(id), Some(time)) = (str_field(message, "id"), str_field(message, "timestamp"))
+        else {
+            continue;
+        };
+        let Ok(time) = DateTime::parse_from_rfc3339(time) else {
+            continue;
+        };
+        let mut text = str_field(message, "content")
+            .unwrap_or_default()
+            .to_string();
+        for attachment in message
+            .get("attachments")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(url) = str_field(attachment, "url") {
+                let name = str_field(attachment, "fileName").unwrap_or(url);
+                text.push_str(&format!("\n\n[{name}]({url})"));
+            }
+        }
+        if text.trim().is_empty() {
+            continue;
+        }
+        let author = message.get("author");
+        let author = author
+            .and_then(|a| str_field(a, "nickname").or(str_field(a, "name")))
+            .unwrap_or("unknown")
+            .to_string();
+
+        // A reply belongs to the thread of the message it answers
+        let thread = message
+            .get("reference")
+            .and_then(|r| str_field(r, "messageId"))
+            .map(|parent| {
+                messages
+                    .iter()
+                    .find(|m| m.id == parent)
+                    .and_then(|m| m.thread.clone())
+                    .unwrap_or_else(|| parent.to_string())
+            });
+        messages.push(Message {
+            id: id.to_string(),
+            author,
+            time: time.with_timezone(&Utc),
+            text: text.trim().to_string(),
+            thread,
+        });
+    }
+
+    Ok(Channel {
+        name: channel
+            .and_then(|c| str_field(c, "name"))
+            .map(str::to_string)
+            .unwrap_or_else(|| file_stem(file)),
+        is_thread: channel
+            .and_then(|c| str_field(c, "type"))
+            .is_some_and(|t| t.contains("Thread")),
+        messages,
+    })
+}
+
+/// Read Discord exports: a DiscordChatExporter JSON file, or a directory
+/// of them.
+fn read_discord(path: &Path) -> Result<Vec<Channel>> {
+    if path.is_file() {
+        return Ok(vec![discord_channel(path)?]);
+    }
+    json_files(path)?
+        .iter()
+        .map(|file| discord_channel(file))
+        .collect()
+}
+
+/// A mem holding `messages` (oldest first) at `path`.
+fn conversation(
+    path: String,
+    title: String,
+    source: &str,
+    channel: &str,
+    messages: &[&Message],
+) -> Mem {
+    let mut participants: Vec<&str> = Vec::new();
+    let mut content = String::new();
+    for message in messages {
+        if !participants.contains(&message.author.as_str()) {
+            participants.push(&message.author);
+        }
+        content.push_str(&format!(
+            "**{}** · {}\n\n{}\n\n",
+            message.author,
+            message.time.format("%Y-%m-%d %H:%M"),
+            message.text.trim()
+        ));
+    }
+
+    let mut mem = Mem::new(
+        PathBuf::from(path),
+        title,
+        content.trim_end().to_string() + "\n",
+    );
+    if let (Some(first), Some(last)) = (messages.first(), messages.last()) {
+        mem.created_at = first.time;
+        mem.updated_at = last.time;
+    }
+    mem.set_field("source", source);
+    mem.set_field("channel", channel);
+    mem.extra.insert(
+        "participants".to_string(),
+        serde_yaml::Value::Sequence(
+            participants
+                .iter()
+                .map(|name| serde_yaml::Value::String(name.to_string()))
+                .collect(),
+        ),
+    );
+    mem
+}
+
+/// A title from the first line of a message, shortened to a word boundary.
+fn thread_title(text: &str) -> String {
+    let line = text
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or("Thread")
+        .trim();
+    if line.chars().count() <= TITLE_CHARS {
+        return line.to_string();
+    }
+    let cut: String = line.chars().take(TITLE_CHARS).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > TITLE_CHARS / 2 => &cut[..space],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end())
+}
+
+/// Mems for the threads and daily logs of `channels`.
+fn to_mems(channels: Vec<Channel>, source: &str) -> Imported {
+    let mut imported = Imported::default();
+    let mut used = HashSet::new();
+    for mut channel in channels {
+        channel.messages.sort_by_key(|m| m.time);
+        let dir = slugify(channel.name.trim_start_matches('#'));
+
+        if channel.is_thread {
+            let messages: Vec<&Message> = channel.messages.iter().collect();
+            if let Some(first) = messages.first() {
+                let path = unique_path(
+                    &format!("{dir}/{}", first.time.format("%Y-%m-%d")),
+                    &mut used,
+                );
+                let mem =
+                    conversation(path, channel.name.clone(), source, &channel.name, &messages);
+                imported.mems.push(mem);
+            }
+            continue;
+        }
+
+        // Threads by the id of the message that started them; a message
+        // starts a thread if anything replies to it
+        let mut threads: BTreeMap<&str, Vec<&Message>> = BTreeMap::new();
+        for message in &channel.messages {
+            if let Some(root) = message.thread.as_deref().filter(|root| *root != message.id) {
+                threads.entry(root).or_default().push(message);
+            }
+        }
+        let ids: HashSet<&str> = channel.messages.iter().map(|m| m.id.as_str()).collect();
+        let mut days: BTreeMap<String, Vec<&Message>> = BTreeMap::new();
+        let mut roots = Vec::new();
+        for message in &channel.messages {
+            if threads.contains_key(message.id.as_str()) {
+                roots.push(message);
+            } else if message
+                .thread
+                .as_deref()
+                .is_none_or(|root| root == message.id || !ids.contains(root))
+            {
+                // Outside any thread, or replying to a message not in the export
+                days.entry(message.time.format("%Y-%m-%d").to_string())
+                    .or_default()
+                    .push(message);
+            }
+        }
+
+        for root in roots {
+            let mut messages = vec![root];
+            messages.extend(&threads[root.id.as_str()]);
+            let path = unique_path(
+                &format!(
+                    "{dir}/{}-{}",
+                    root.time.format("%Y-%m-%d"),
+                    slugify(&thread_title(&root.text))
+                ),
+                &mut used,
+            );
+            let title = thread_title(&root.text);
+            imported
+                .mems
+                .push(conversation(path, title, source, &channel.name, &messages));
+        }
+        for (day, messages) in days {
+            let path = unique_path(&format!("{dir}/{day}"), &mut used);
+            let title = format!("#{} on {day}", channel.name.trim_start_matches('#'));
+            imported
+                .mems
+                .push(conversation(path, title, source, &channel.name, &messages));
+        }
+    }
+    imported.mems.sort_by(|a, b| a.path.cmp(&b.path));
+    imported
+}
+
+/// Convert a Slack export into mems.
+pub fn import_slack(path: &Path) -> Result<Imported> {
+    Ok(to_mems(read_slack(path)?, "slack"))
+}
+
+/// Convert DiscordChatExporter JSON exports into mems.
+pub fn import_discord(path: &Path) -> Result<Imported> {
+    Ok(to_mems(read_discord(path)?, "discord"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_slack_markdown() {
+        let users = HashMap::from([("U1".to_string(), "Ana".to_string())]);
+        assert_eq!(
+            slack_markdown(
+                "<@U1> see <https://x.io/a|the doc> &amp; <#C2|ops> <!here> <https://y.io>",
+                &users
+            ),
+            "@Ana see [the doc](https://x.io/a) & #ops @here <https://y.io>"
+        );
+        assert_eq!(
+            slack_time("1709476200.000100").unwrap().to_rfc3339(),
+            "2024-03-03T14:30:00.000100+00:00"
+        );
+        assert_eq!(
+            thread_title("Should we move the billing database to Postgres before the Q3 freeze?"),
+            "Should we move the billing database to Postgres before the…"
+        );
+    }
+
+    #[test]
+    fn test_import_slack_workspace() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::write(
+            root.join("users.json"),
+            r#"[{"id": "U1", "profile": {"real_name": "Ana"}}, {"id": "U2", "name": "bo"}]"#,
+        )
+        .unwrap();
+        fs::create_dir(root.join("ops")).unwrap();
+        fs::write(
+            root.join("ops/2024-03-03.json"),
+            r#"[
+              {"type": "message", "subtype": "channel_join", "user": "U2", "text": "joined", "ts": "1709470000.0"},
+              {"type": "message", "user": "U1", "text": "Freeze deploys?", "ts": "1709476200.0", "thread_ts": "1709476200.0", "reply_count": 1},
+              {"type": "message", "user": "U2", "text": "Yes, <@U1>", "ts": "1709476300.0", "thread_ts": "1709476200.0"},
+              {"type": "message", "user": "U2", "text": "lunch?", "ts": "1709480000.0"}
+            ]"#,
+        )
+        .unwrap();
+
+        let imported = import_slack(root).unwrap();
+        let paths: Vec<String> = imported.mems.iter().map(|m| m.path_str()).collect();
+        assert_eq!(paths, ["ops/2024-03-03", "ops/2024-03-03-freeze-deploys"]);
+
+        let thread = &imported.mems[1];
+        assert_eq!(thread.title, "Freeze deploys?");
+        assert_eq!(thread.field("source"), Some("slack"));
+        assert_eq!(thread.field("channel"), Some("ops"));
+        assert_eq!(
+            thread.extra["participants"],
+            serde_yaml::from_str::<serde_yaml::Value>("[Ana, bo]").unwrap()
+        );
+        assert_eq!(thread.created_at.to_rfc3339(), "2024-03-03T14:30:00+00:00");
+        assert_eq!(thread.updated_at.to_rfc3339(), "2024-03-03T14:31:40+00:00");
+        assert!(thread
+            .content
+            .contains("**bo** · 2024-03-03 14:31\n\nYes, @Ana\n"));
+
+        let day = &imported.mems[0];
+        assert_eq!(day.title, "#ops on 2024-03-03");
+        assert!(day.content.contains("lunch?"));
+        assert!(!day.content.contains("joined"));
+
+        assert!(import_slack(&root.join("ops")).is_err());
+    }
+
+    #[test]
+    fn test_import_discord() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("export.json");
+        fs::write(
+            &file,
+            r#"{
+              "guild": {"name": "Proj"},
+              "channel": {"name": "backend", "type": "GuildTextChat"},
+              "messages": [
+                {"id": "1", "type": "Default", "timestamp": "2024-03-03T10:00:00+00:00", "content": "Which queue?", "author": {"name": "ana", "nickname": "Ana"}},
+                {"id": "2", "type": "Reply", "timestamp": "2024-03-03T10:05:00+00:00", "content": "SQS", "author": {"name": "bo"}, "reference": {"messageId": "1"}},
+                {"id": "3", "type": "Reply", "timestamp": "2024-03-03T10:06:00+00:00", "content": "Agreed", "author": {"name": "cy"}, "reference": {"messageId": "2"}},
+                {"id": "4", "type": "ChannelPinnedMessage", "timestamp": "2024-03-03T10:07:00+00:00", "content": "", "author": {"name": "bo"}},
+                {"id": "5", "type": "Default", "timestamp": "2024-03-04T09:00:00+00:00", "content": "", "author": {"name": "bo"},
+                 "attachments": [{"url": "https://cdn.example/a.png", "fileName": "a.png"}]}
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        let imported = import_discord(&file).unwrap();
+        let paths: Vec<String> = imported.mems.iter().map(|m| m.path_str()).collect();
+        assert_eq!(
+            paths,
+            ["backend/2024-03-03-which-queue", "backend/2024-03-04"]
+        );
+        let thread = &imported.mems[0];
+        assert_eq!(
+            thread.extra["participants"],
+            serde_yaml::from_str::<serde_yaml::Value>("[Ana, bo, cy]").unwrap()
+        );
+        assert!(thread
+            .content
+            .ends_with("**cy** · 2024-03-03 10:06\n\nAgreed\n"));
+        assert!(imported.mems[1]
+            .content
+            .contains("[a.png](https://cdn.example/a.png)"));
+    }
+}
@@ -0,0 +1,361 @@
+use crate::federation::SearchConfig;
+use crate::gc::RetentionConfig;
+use crate::kind::KindConfig;
+use crate::lint::RuleLevel;
+use crate::schema::PrefixSchema;
+use crate::storage::Storage;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Name of the config file inside .mems/.
+pub const CONFIG_FILE: &str = "config.yaml";
+
+/// Default for `max-mem-kb`.
+const DEFAULT_MAX_MEM_KB: u64 = 1024;
+
+/// Store configuration loaded from `.mems/config.yaml`.
+///
+/// Every field is optional; a missing file yields the defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// Embedding backend used by semantic search
+    pub embeddings: EmbeddingsConfig,
+
+    /// How semantic search splits text into terms
+    pub analyzer: AnalyzerConfig,
+
+    /// Ranking of `find` results from several stores
+    pub search: SearchConfig,
+
+    /// Chat completion endpoint used by `ask`
+    pub llm: LlmConfig,
+
+    /// Settings for `dump`
+    pub dump: DumpConfig,
+
+    /// Delivery settings for `notify`
+    pub notify: NotifyConfig,
+
+    /// Content checksums checked by `verify`
+    pub integrity: IntegrityConfig,
+
+    /// Frontmatter schemas by path prefix, enforced by `lint`
+    pub schemas: Vec<PrefixSchema>,
+
+    /// Store-wide tag vocabulary
+    pub tags: TagsConfig,
+
+    /// Daily picks for `review`
+    pub review: ReviewConfig,
+
+    /// Rule settings for `lint`
+    pub lint: LintConfig,
+
+    /// Per-kind templates, required headings, stale thresholds, and tree
+    /// order, by kind name
+    pub kinds: BTreeMap<String, KindConfig>,
+
+    /// Auto-archive policies and archive retention, applied by `gc`
+    pub retention: RetentionConfig,
+
+    /// Command aliases: `rls: ls --long --hot` makes `mem rls` run that
+    pub aliases: BTreeMap<String, String>,
+
+    /// Flags added to a command, by command name (`ls`, `cache build`)
+    pub defaults: BTreeMap<String, String>,
+
+    /// Mem prefixes by repository directory: run inside a mapped directory,
+    /// `ls` lists the mems under its prefix first
+    pub scopes: BTreeMap<String, String>,
+
+    /// Use every `.mems/` under the repository as a labeled store (monorepos)
+    pub discover_nested: bool,
+
+    /// Walk symlinked directories when listing mems (loops are skipped)
+    pub follow_symlinks: bool,
+
+    /// Size in KB above which `lint` warns and `add`/`edit` ask before
+    /// saving (default 1024; 0 for no limit)
+    pub max_mem_kb: Option<u64>,
+}
+
+/// Embedding backend selection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct EmbeddingsConfig {
+    /// Backend name: `local` (hashed features, no model) or `api`
+    pub backend: String,
+
+    /// OpenAI-compatible embeddings endpoint (api backend)
+    pub url: String,
+
+    /// Model name sent to the endpoint (api backend)
+    pub model: String,
+
+    /// Environment variable holding the API key (api backend)
+    pub api_key_env: String,
+}
+
+impl Default for EmbeddingsConfig {
+    fn default() -> Self {
+        Self {
+            backend: "local".to_string(),
+            url: "https://api.openai.com/v1/embeddings".to_string(),
+            model: "text-embedding-3-small".to_string(),
+            api_key_env: "OPENAI_API_KEY".to_string(),
+        }
+    }
+}
+
+/// Text analysis for semantic search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AnalyzerConfig {
+    /// Language whose word endings are stripped, so `rotating` matches
+    /// `rotation`
+    pub stemming: Stemming,
+
+    /// Ignore common English words such as `the` and `how`
+    pub stopwords: bool,
+
+    /// Length of the character n-grams Chinese, Japanese, and Korean text is
+    /// split into (0 keeps each run of such characters as one term)
+    pub cjk_ngram: usize,
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        Self {
+            stemming: Stemming::None,
+            stopwords: true,
+            cjk_ngram: 2,
+        }
+    }
+}
+
+/// Stemming language.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stemming {
+    #[default]
+    None,
+    English,
+}
+
+/// OpenAI-compatible chat completion endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LlmConfig {
+    /// Chat completions URL
+    pub url: String,
+
+    /// Model name sent to the endpoint
+    pub model: String,
+
+    /// Environment variable holding the API key
+    pub api_key_env: String,
+
+    /// Estimated token budget for mem context packed into the prompt
+    pub max_context_tokens: usize,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            url: "https://api.openai.com/v1/chat/completions".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            api_key_env: "OPENAI_API_KEY".to_string(),
+            max_context_tokens: 6000,
+        }
+    }
+}
+
+/// Settings for `dump`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DumpConfig {
+    /// Template of the header `--with-header` writes (default:
+    /// [`crate::preamble::DEFAULT_HEADER`])
+    pub header: Option<String>,
+}
+
+/// Settings for scheduled `notify` runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NotifyConfig {
+    /// Slack/Teams incoming webhook URL for `--format webhook`
+    pub webhook_url: Option<String>,
+
+    /// Days without update before a mem counts as stale
+    pub stale_days: u32,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            stale_days: 90,
+        }
+    }
+}
+
+/// Content integrity settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct IntegrityConfig {
+    /// Add a content checksum to every mem written
+    pub checksums: bool,
+}
+
+/// Tag vocabulary settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TagsConfig {
+    /// Tags mems may use; empty allows any tag
+    pub allowed: Vec<String>,
+}
+
+/// Settings for `review`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ReviewConfig {
+    /// Mems surfaced per day
+    pub per_day: usize,
+
+    /// Tags marking mems that should come up more often
+    pub important_tags: Vec<String>,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self {
+            per_day: 3,
+            important_tags: Vec::new(),
+        }
+    }
+}
+
+/// Settings for `lint`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LintConfig {
+    /// Words a paragraph may have before `long-paragraph` reports it
+    pub max_paragraph_words: usize,
+
+    /// Days `updated-at` may trail the file's last commit before
+    /// `timestamp-before-history` reports it
+    pub max_history_gap_days: u32,
+
+    /// Severity by rule id (`error`, `warning`, or `off`), overriding the defaults
+    pub rules: BTreeMap<String, RuleLevel>,
+
+    /// Requests in flight at once for `--check-external`
+    pub external_concurrency: usize,
+
+    /// Seconds to wait for each external link
+    pub external_timeout_secs: u64,
+
+    /// Hours before a checked external link is requested again
+    pub external_cache_hours: u32,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            max_paragraph_words: 250,
+            max_history_gap_days: 365,
+            rules: BTreeMap::new(),
+            external_concurrency: 8,
+            external_timeout_secs: 10,
+            external_cache_hours: 24,
+        }
+    }
+}
+
+impl Config {
+    /// Load the config for a store root, falling back to defaults if absent.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path).context("failed to read config")?;
+        Self::parse(&content)
+    }
+
+    /// Load the config of a store on any backend.
+    pub fn for_storage(storage: &Storage) -> Result<Self> {
+        match storage.read_file(CONFIG_FILE)? {
+            Some(content) => Self::parse(&content),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// The largest a mem file should be, in bytes, if there is a limit.
+    pub fn max_mem_bytes(&self) -> Option<u64> {
+        match self.max_mem_kb.unwrap_or(DEFAULT_MAX_MEM_KB) {
+            0 => None,
+            kb => Some(kb * 1024),
+        }
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_yaml::from_str(content).map_err(|e| anyhow!("invalid {CONFIG_FILE}: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_missing_config_uses_defaults() {
+        let temp = TempDir::new().unwrap();
+        let config = Config::load(temp.path()).unwrap();
+        assert_eq!(config.embeddings.backend, "local");
+        assert_eq!(config.max_mem_bytes(), Some(1024 * 1024));
+    }
+
+    #[test]
+    fn test_max_mem_bytes() {
+        assert_eq!(
+            Config::parse("max-mem-kb: 4").unwrap().max_mem_bytes(),
+            Some(4096)
+        );
+        assert_eq!(
+            Config::parse("max-mem-kb: 0").unwrap().max_mem_bytes(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_partial_config() {
+        let temp = TempDir::new().unwrap();
+        fs::write(
+            temp.path().join(CONFIG_FILE),
+            "embeddings:\n  backend: api\n  model: nomic-embed-text\n",
+        )
+        .unwrap();
+
+        let config = Config::load(temp.path()).unwrap();
+        assert_eq!(config.embeddings.backend, "api");
+        assert_eq!(config.embeddings.model, "nomic-embed-text");
+        assert_eq!(config.embeddings.api_key_env, "OPENAI_API_KEY");
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join(CONFIG_FILE), "embeddings: [").unwrap();
+        assert!(Config::load(temp.path()).is_err());
+    }
+}
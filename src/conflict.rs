@@ -0,0 +1,218 @@
+use crate::mem::Mem;
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::path::Path;
+
+/// A mem file still containing git conflict markers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub path: String,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unresolved merge conflict in {} (fix with `mem resolve {}`)",
+            self.path, self.path
+        )
+    }
+}
+
+impl std::error::Error for MergeConflict {}
+
+/// How `resolve` settles the conflicting parts of a mem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Keep our title, content, and fields
+    Ours,
+    /// Keep their title, content, and fields
+    Theirs,
+    /// Only merge frontmatter; the content must not conflict
+    UnionTags,
+}
+
+/// Whether text has a complete `<<<<<<<` ... `>>>>>>>` conflict block.
+///
+/// A lone `=======` is a markdown heading underline, so it isn't enough, and
+/// markers in fenced code are examples, not conflicts.
+pub fn has_markers(text: &str) -> bool {
+    first_conflict(text).is_some()
+}
+
+/// The 1-based line of the `<<<<<<<` opening the first complete conflict
+/// block in `text`, outside fenced code.
+pub fn first_conflict(text: &str) -> Option<usize> {
+    let mut open = None;
+    let mut in_fence = false;
+    for (i, line) in text.lines().enumerate() {
+        if open.is_none() && is_fence(line) {
+            in_fence = !in_fence;
+        } else if in_fence {
+            continue;
+        } else if line.starts_with("<<<<<<<") {
+            open = Some(i + 1);
+        } else if open.is_some() && line.starts_with(">>>>>>>") {
+            return open;
+        }
+    }
+    None
+}
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// Split conflicted text into our and their versions. Common lines go to
+/// both; diff3-style base sections (`|||||||`) are dropped.
+pub fn sides(text: &str) -> (String, String) {
+    #[derive(Clone, Copy)]
+    enum Section {
+        Common,
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut ours = String::new();
+    let mut theirs = String::new();
+    let mut section = Section::Common;
+    let mut in_fence = false;
+    for line in text.split_inclusive('\n') {
+        let bare = line.trim_end_matches(['\n', '\r']);
+        if matches!(section, Section::Common) && is_fence(bare) {
+            in_fence = !in_fence;
+        }
+        section = match section {
+            Section::Common if !in_fence && bare.starts_with("<<<<<<<") => Section::Ours,
+            Section::Ours if bare.starts_with("|||||||") => Section::Base,
+            Section::Ours | Section::Base if bare == "=======" => Section::Theirs,
+            Section::Theirs if bare.starts_with(">>>>>>>") => Section::Common,
+            section => {
+                match section {
+                    Section::Common => {
+                        ours.push_str(line);
+                        theirs.push_str(line);
+                    }
+                    Section::Ours => ours.push_str(line),
+                    Section::Base => {}
+                    Section::Theirs => theirs.push_str(line),
+                }
+                section
+            }
+        };
+    }
+    (ours, theirs)
+}
+
+/// Resolve a conflicted mem file.
+///
+/// Frontmatter is merged field by field whatever the strategy: the earliest
+/// `created-at`, the latest `updated-at`, and the union of both sides' tags.
+/// The title, content, and other fields come from the chosen side (filling
+/// in fields only the other side has).
+pub fn resolve(path: &Path, text: &str, strategy: Strategy) -> Result<Mem> {
+    let (ours_text, theirs_text) = sides(text);
+    let ours = Mem::parse(path.to_path_buf(), &ours_text)
+        .map_err(|e| anyhow!("cannot parse our side of {}: {e}", path.display()))?;
+    let theirs = Mem::parse(path.to_path_buf(), &theirs_text)
+        .map_err(|e| anyhow!("cannot parse their side of {}: {e}", path.display()))?;
+
+    let (mut mem, other) = match strategy {
+        Strategy::Ours => (ours, theirs),
+        Strategy::Theirs => (theirs, ours),
+        Strategy::UnionTags => {
+            if ours.title != theirs.title || ours.content != theirs.content {
+                return Err(anyhow!(
+                    "title or content of {} conflicts; use --ours or --theirs",
+                    path.display()
+                ));
+            }
+            (ours, theirs)
+        }
+    };
+
+    mem.created_at = mem.created_at.min(other.created_at);
+    mem.updated_at = mem.updated_at.max(other.updated_at);
+    if mem.id.is_none() {
+        mem.id = other.id;
+    }
+    for tag in other.tags {
+        if !mem.tags.contains(&tag) {
+            mem.tags.push(tag);
+        }
+    }
+    for (key, value) in other.extra {
+        mem.extra.entry(key).or_insert(value);
+    }
+    Ok(mem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFLICTED: &str = "---
+title: Plan
+created-at: 2025-01-01T00:00:00Z
+<<<<<<< HEAD
+updated-at: 2025-01-03T00:00:00Z
+tags:
+  - a
+  - b
+=======
+updated-at: 2025-01-02T00:00:00Z
+tags:
+  - b
+  - c
+>>>>>>> feature
+---
+Intro
+<<<<<<< HEAD
+ours
+||||||| base
+original
+=======
+theirs
+>>>>>>> feature
+";
+
+    #[test]
+    fn test_has_markers() {
+        assert!(has_markers(CONFLICTED));
+        assert!(!has_markers("Heading\n=======\n\nText"));
+        assert!(!has_markers("<<<<<<< only an opener"));
+        assert_eq!(first_conflict(CONFLICTED), Some(4));
+
+        // Markers shown in a code block are not a conflict
+        let example =
+            "Resolve blocks like:\n\n```\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> main\n```\n";
+        assert!(!has_markers(example));
+        let (ours, theirs) = sides(example);
+        assert_eq!((ours.as_str(), theirs.as_str()), (example, example));
+    }
+
+    #[test]
+    fn test_sides() {
+        let (ours, theirs) = sides(CONFLICTED);
+        assert!(ours.ends_with("Intro\nours\n"));
+        assert!(theirs.ends_with("Intro\ntheirs\n"));
+        assert!(!has_markers(&ours) && !ours.contains("original"));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let path = Path::new("plan");
+        let mem = resolve(path, CONFLICTED, Strategy::Theirs).unwrap();
+        assert_eq!(mem.content, "Intro\ntheirs\n");
+        assert_eq!(mem.tags, vec!["b", "c", "a"]);
+        assert_eq!(mem.updated_at.to_rfc3339(), "2025-01-03T00:00:00+00:00");
+
+        // Content conflicts need an explicit side
+        assert!(resolve(path, CONFLICTED, Strategy::UnionTags).is_err());
+        let frontmatter_only = CONFLICTED.split("Intro").next().unwrap().to_string() + "Intro\n";
+        let mem = resolve(path, &frontmatter_only, Strategy::UnionTags).unwrap();
+        assert_eq!(mem.tags, vec!["a", "b", "c"]);
+    }
+}
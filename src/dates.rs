@@ -0,0 +1,92 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+/// Parse a date given as `YYYY-MM-DD` or an RFC 3339 timestamp.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    parse_timestamp(value).map(|t| t.date_naive())
+}
+
+/// Parse a timestamp given as RFC 3339 or a bare `YYYY-MM-DD` (midnight UTC).
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|d| d.and_utc())
+        })
+}
+
+/// Parse a duration in hours, days, or weeks: `12h`, `3d`, `1w`.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let unit = value.chars().last()?;
+    value[..value.len() - unit.len_utf8()]
+        .parse::<i64>()
+        .ok()
+        .and_then(|n| match unit {
+            'h' => Duration::try_hours(n),
+            'd' => Duration::try_days(n),
+            'w' => Duration::try_weeks(n),
+            _ => None,
+        })
+}
+
+/// Parse the start of a period: a duration back from `now` (`12h`, `3d`,
+/// `1w`) or a timestamp accepted by [`parse_timestamp`].
+pub fn parse_since(value: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    match parse_duration(value) {
+        Some(duration) => now.checked_sub_signed(duration),
+        None => parse_timestamp(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("2025-03-01"),
+            NaiveDate::from_ymd_opt(2025, 3, 1)
+        );
+        assert_eq!(
+            parse_date("2025-03-01T23:30:00-02:00"),
+            NaiveDate::from_ymd_opt(2025, 3, 2)
+        );
+        assert!(parse_date("next tuesday").is_none());
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp("2025-03-01"),
+            Some(Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap())
+        );
+        assert!(parse_timestamp("HEAD~3").is_none());
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 15, 12, 0, 0).unwrap();
+        assert_eq!(
+            parse_since("1w", now),
+            Some(Utc.with_ymd_and_hms(2025, 3, 8, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_since("36h", now),
+            Some(Utc.with_ymd_and_hms(2025, 3, 14, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_since("2025-03-01", now),
+            Some(Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap())
+        );
+        assert!(parse_since("1y", now).is_none());
+        assert!(parse_since("", now).is_none());
+    }
+}
@@ -0,0 +1,263 @@
+//! The decision log (`mem decisions`): what each ADR or decision record
+//! decided, its status, and when, in date order across the store.
+
+use crate::dates;
+use crate::kind;
+use crate::mem::Mem;
+use crate::structure::{self, Heading};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::fmt::Write as _;
+
+/// Kinds that record a decision, whether or not they have a Decision section.
+pub const DECISION_KINDS: [&str; 2] = ["adr", "decision"];
+
+/// One decision, taken from a mem.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Decision {
+    /// Label of the store the mem is in, with several stores
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub store: String,
+    pub path: String,
+    pub title: String,
+
+    /// First sentence of the Decision section (or of the mem, without one)
+    pub decision: Option<String>,
+
+    /// From the `status` field, a Status section, or a `Status:` line
+    pub status: Option<String>,
+
+    /// From the `date` field or a `Date:` line, else when the mem was created
+    pub date: NaiveDate,
+}
+
+impl Decision {
+    /// The decision a mem records, if it is of a decision kind or has a
+    /// Decision section.
+    pub fn of(store: &str, mem: &Mem) -> Option<Decision> {
+        let headings = structure::headings(&mem.content);
+        let decided = section(&mem.content, &headings, "Decision");
+        if decided.is_none() && !DECISION_KINDS.contains(&kind::of(mem)) {
+            return None;
+        }
+        let decision = first_sentence(decided.as_deref().unwrap_or(&mem.content));
+        let status = mem
+            .field("status")
+            .map(str::to_string)
+            .or_else(|| section(&mem.content, &headings, "Status").and_then(first_line))
+            .or_else(|| labeled_line(&mem.content, "Status"));
+        let date = mem
+            .field("date")
+            .map(str::to_string)
+            .or_else(|| labeled_line(&mem.content, "Date"))
+            .and_then(|d| dates::parse_date(&d))
+            .unwrap_or_else(|| mem.created_at.date_naive());
+        Some(Decision {
+            store: store.to_string(),
+            path: mem.path_str(),
+            title: mem.title.clone(),
+            decision,
+            status,
+            date,
+        })
+    }
+
+    /// The mem as a link target: `path`, or `store:path` with several stores.
+    fn reference(&self) -> String {
+        match self.store.as_str() {
+            "" => self.path.clone(),
+            store => format!("{store}:{}", self.path),
+        }
+    }
+}
+
+/// Content under the first heading named `name`, up to the next heading at
+/// its level or above.
+fn section(content: &str, headings: &[Heading], name: &str) -> Option<String> {
+    let index = headings.iter().position(|h| h.matches(name))?;
+    let heading = &headings[index];
+    let end = headings[index + 1..]
+        .iter()
+        .find(|h| h.level <= heading.level)
+        .map_or(usize::MAX, |h| h.line - 1);
+    let lines: Vec<&str> = content.lines().take(end).skip(heading.line).collect();
+    Some(lines.join("\n"))
+}
+
+/// Lines of prose in `text`, without list markers and emphasis; blank lines
+/// and headings, which end a paragraph, come through empty.
+fn prose(text: &str) -> impl Iterator<Item = String> + '_ {
+    structure::prose_lines(text).map(|(_, line)| {
+        let line = line.trim();
+        if line.starts_with('#') {
+            return String::new();
+        }
+        let line = line.trim_start_matches(['-', '*', '+', '>']).trim_start();
+        line.replace("**", "").replace("__", "")
+    })
+}
+
+fn first_line(text: String) -> Option<String> {
+    prose(&text).find(|line| !line.is_empty())
+}
+
+/// The first sentence of the first paragraph of `text`.
+fn first_sentence(text: &str) -> Option<String> {
+    let paragraph = prose(text)
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let end = paragraph
+        .match_indices(['.', '!', '?'])
+        .map(|(i, _)| i + 1)
+        .find(|&i| paragraph[i..].is_empty() || paragraph[i..].starts_with(' '))
+        .unwrap_or(paragraph.len());
+    let sentence = paragraph[..end].trim();
+    (!sentence.is_empty()).then(|| sentence.to_string())
+}
+
+/// The value of a `Label: value` line, as ADR templates put the status and
+/// date.
+fn labeled_line(content: &str, label: &str) -> Option<String> {
+    prose(content).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        let value = value.trim();
+        (name.trim().eq_ignore_ascii_case(label) && !value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Decisions in date order, oldest first.
+pub fn sort(decisions: &mut [Decision]) {
+    decisions.sort_by(|a, b| (a.date, &a.store, &a.path).cmp(&(b.date, &b.store, &b.path)));
+}
+
+/// One decision per entry: date, status, title, and path, with the decision
+/// indented below.
+pub fn render_text(decisions: &[Decision]) -> String {
+    let width = decisions
+        .iter()
+        .map(|d| d.status.as_deref().unwrap_or("-").chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for d in decisions {
+        let status = d.status.as_deref().unwrap_or("-");
+        let _ = writeln!(
+            out,
+            "{}  {status:<width$}  {} ({})",
+            d.date,
+            d.title,
+            d.reference()
+        );
+        if let Some(decision) = &d.decision {
+            let _ = writeln!(out, "    {decision}");
+        }
+    }
+    out
+}
+
+/// A markdown list linking each decision's mem, to paste into a mem.
+pub fn render_markdown(decisions: &[Decision]) -> String {
+    let mut out = String::from("# Decision Log\n\n");
+    for d in decisions {
+        let status = d
+            .status
+            .as_deref()
+            .map(|s| format!(" ({s})"))
+            .unwrap_or_default();
+        let _ = write!(
+            out,
+            "- {}: [{}]({}.md){status}",
+            d.date,
+            d.title,
+            d.reference()
+        );
+        match &d.decision {
+            Some(decision) => {
+                let _ = writeln!(out, " — {decision}");
+            }
+            None => out.push('\n'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn mem(path: &str, content: &str) -> Mem {
+        let mut mem = Mem::new(PathBuf::from(path), path.to_string(), content.to_string());
+        mem.created_at = "2024-06-01T12:00:00Z".parse().unwrap();
+        mem
+    }
+
+    #[test]
+    fn test_of() {
+        let mut adr = mem(
+            "arch/adr-001",
+            "Status: Accepted\nDate: 2024-03-02\n\n## Context\n\nSlow queries.\n\n\
+             ## Decision\n\nWe will use **Postgres** for the ledger. It has\ntransactions.\n\n\
+             ### Notes\n\nMore.\n\n## Consequences\n\nMigrations.\n",
+        );
+        adr.set_field("kind", "adr");
+        let decision = Decision::of("", &adr).unwrap();
+        assert_eq!(
+            decision.decision.as_deref(),
+            Some("We will use Postgres for the ledger.")
+        );
+        assert_eq!(decision.status.as_deref(), Some("Accepted"));
+        assert_eq!(decision.date, "2024-03-02".parse::<NaiveDate>().unwrap());
+
+        // Fields win over lines, and a Status section counts
+        adr.set_field("status", "superseded");
+        assert_eq!(
+            Decision::of("", &adr).unwrap().status.as_deref(),
+            Some("superseded")
+        );
+        let sectioned = mem(
+            "notes/queues",
+            "## Status\n\n- Proposed\n\n## Decision\n\nUse SQS\n",
+        );
+        let decision = Decision::of("", &sectioned).unwrap();
+        assert_eq!(decision.status.as_deref(), Some("Proposed"));
+        assert_eq!(decision.decision.as_deref(), Some("Use SQS"));
+        assert_eq!(decision.date, "2024-06-01".parse::<NaiveDate>().unwrap());
+
+        // A decision kind without the section falls back to the first sentence
+        let mut bare = mem("decisions/tabs", "Tabs, not spaces. Always.");
+        bare.set_field("kind", "decision");
+        assert_eq!(
+            Decision::of("", &bare).unwrap().decision.as_deref(),
+            Some("Tabs, not spaces.")
+        );
+        assert!(Decision::of("", &mem("notes/misc", "Decision: none")).is_none());
+    }
+
+    #[test]
+    fn test_render() {
+        let mut decisions = vec![
+            Decision::of("team", &mem("b", "## Decision\n\nLater one.\n")).unwrap(),
+            Decision::of(
+                "team",
+                &mem("a", "Date: 2024-01-05\n\n## Decision\n\nFirst.\n"),
+            )
+            .unwrap(),
+        ];
+        decisions[0].status = Some("accepted".to_string());
+        sort(&mut decisions);
+        assert_eq!(decisions[0].path, "a");
+        assert_eq!(
+            render_text(&decisions),
+            "2024-01-05  -         a (team:a)\n    First.\n\
+             2024-06-01  accepted  b (team:b)\n    Later one.\n"
+        );
+        assert_eq!(
+            render_markdown(&decisions),
+            "# Decision Log\n\n- 2024-01-05: [a](team:a.md) — First.\n\
+             - 2024-06-01: [b](team:b.md) (accepted) — Later one.\n"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Project dictionary of banned or replaced terms, checked by `lint`.
+//!
+//! `.mems/dictionary.txt` has one entry per line: `old -> new` maps a
+//! deprecated name or misspelling to its replacement, and a bare term is
+//! banned outright. `#` starts a comment.
+
+use crate::highlight;
+use crate::storage::Storage;
+use crate::structure;
+use anyhow::Result;
+use std::ops::Range;
+
+/// Dictionary file inside .mems/.
+pub const DICTIONARY_FILE: &str = "dictionary.txt";
+
+/// A term the dictionary flags.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub term: String,
+
+    /// What to write instead (`None` for banned terms)
+    pub replacement: Option<String>,
+}
+
+/// A flagged term found in mem content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    /// 1-based line within the content
+    pub line: usize,
+
+    /// Byte range within the line
+    pub range: Range<usize>,
+
+    /// The text as written
+    pub found: String,
+
+    /// What `--fix` writes instead, matching the case of the first letter
+    pub replacement: Option<String>,
+}
+
+impl Hit {
+    /// Human-readable description, as used in lint messages.
+    pub fn message(&self) -> String {
+        match &self.replacement {
+            Some(replacement) => format!("{:?} should be {replacement:?}", self.found),
+            None => format!("{:?} is a banned term", self.found),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dictionary {
+    pub entries: Vec<Entry>,
+}
+
+impl Dictionary {
+    pub fn parse(text: &str) -> Self {
+        let entries = text
+            .lines()
+            .map(|line| line.split_once('#').map_or(line, |(entry, _)| entry).trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| match line.split_once("->") {
+                Some((term, replacement)) => Entry {
+                    term: term.trim().to_string(),
+                    replacement: Some(replacement.trim().to_string()),
+                },
+                None => Entry {
+                    term: line.to_string(),
+                    replacement: None,
+                },
+            })
+            .filter(|entry| !entry.term.is_empty())
+            .collect();
+        Self { entries }
+    }
+
+    /// The store's dictionary, if it has one.
+    pub fn load(storage: &Storage) -> Result<Option<Self>> {
+        Ok(storage.read_file(DICTIONARY_FILE)?.map(|t| Self::parse(&t)))
+    }
+
+    /// Whole-word, case-insensitive occurrences of dictionary terms, outside
+    /// code blocks and inline code, in document order.
+    pub fn check(&self, content: &str) -> Vec<Hit> {
+        let mut hits = Vec::new();
+        for (line, text) in structure::prose_lines(content) {
+            let code = code_spans(text);
+            let mut found: Vec<Hit> = self
+                .entries
+                .iter()
+                .flat_map(|entry| {
+                    highlight::find_matches(text, &entry.term)
+                        .into_iter()
+                        .filter(|r| is_word(text, r) && !code.iter().any(|c| overlaps(c, r)))
+                        .map(move |range| Hit {
+                            line,
+                            found: text[range.clone()].to_string(),
+                            replacement: entry
+                                .replacement
+                                .as_ref()
+                                .map(|r| match_case(&text[range.clone()], r)),
+                            range,
+                        })
+                })
+                .collect();
+
+            // Earliest first; of overlapping terms, the longest wins
+            found.sort_by_key(|h| (h.range.start, std::cmp::Reverse(h.range.end)));
+            let mut end = 0;
+            for hit in found {
+                if hit.range.start >= end {
+                    end = hit.range.end;
+                    hits.push(hit);
+                }
+            }
+        }
+        hits
+    }
+
+    /// Apply every replacement in `content`, returning the fixed content and
+    /// the number of terms replaced. Banned terms are left for a person.
+    pub fn fix(&self, content: &str) -> (String, usize) {
+        let hits: Vec<Hit> = self
+            .check(content)
+            .into_iter()
+            .filter(|h| h.replacement.is_some())
+            .collect();
+        if hits.is_empty() {
+            return (content.to_string(), 0);
+        }
+
+        let mut lines: Vec<String> = content.split('\n').map(str::to_string).collect();
+        for hit in hits.iter().rev() {
+            if let (Some(line), Some(replacement)) = (lines.get_mut(hit.line - 1), &hit.replacement)
+            {
+                line.replace_range(hit.range.clone(), replacement);
+            }
+        }
+        (lines.join("\n"), hits.len())
+    }
+}
+
+/// Whether a match stands alone rather than inside a longer word.
+pub(crate) fn is_word(text: &str, range: &Range<usize>) -> bool {
+    let word_char = |c: char| c.is_alphanumeric() || c == '_';
+    !text[..range.start]
+        .chars()
+        .next_back()
+        .is_some_and(word_char)
+        && !text[range.end..].chars().next().is_some_and(word_char)
+}
+
+/// Byte ranges of inline code spans (`like this`) in a line.
+pub(crate) fn code_spans(text: &str) -> Vec<Range<usize>> {
+    let ticks: Vec<usize> = text.match_indices('`').map(|(i, _)| i).collect();
+    ticks.chunks_exact(2).map(|p| p[0]..p[1] + 1).collect()
+}
+
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+/// Capitalize the replacement when the original starts with a capital, so
+/// fixes at the start of a sentence stay capitalized.
+fn match_case(original: &str, replacement: &str) -> String {
+    let capital = original.chars().next().is_some_and(char::is_uppercase);
+    let mut chars = replacement.chars();
+    match chars.next() {
+        Some(first) if capital && first.is_lowercase() => {
+            first.to_uppercase().chain(chars).collect()
+        }
+        _ => replacement.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary() -> Dictionary {
+        Dictionary::parse(
+            "# Renamed services\nMegaStore -> Catalog\nmega store -> Catalog\n\nteh -> the  # typo\nblacklist\n",
+        )
+    }
+
+    #[test]
+    fn test_parse() {
+        let dictionary = dictionary();
+        assert_eq!(dictionary.entries.len(), 4);
+        assert_eq!(
+            dictionary.entries[3],
+            Entry {
+                term: "blacklist".to_string(),
+                replacement: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let content = "Teh megastore API.\n\n```\nteh code\n```\nUse `teh` or tehran, not Blacklist.\nThe Mega Store team";
+        let hits = dictionary().check(content);
+        let hits: Vec<(usize, &str, Option<&str>)> = hits
+            .iter()
+            .map(|h| (h.line, h.found.as_str(), h.replacement.as_deref()))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![
+                (1, "Teh", Some("The")),
+                (1, "megastore", Some("Catalog")),
+                (6, "Blacklist", None),
+                (7, "Mega Store", Some("Catalog")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fix() {
+        let (fixed, count) = dictionary().fix("teh MegaStore and blacklist\n`teh`\n");
+        assert_eq!(fixed, "the Catalog and blacklist\n`teh`\n");
+        assert_eq!(count, 2);
+    }
+}
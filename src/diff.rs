@@ -0,0 +1,281 @@
+//! Line and word diffs between two texts, for `mem diff`.
+
+use crate::highlight::ANSI_END;
+use serde::Serialize;
+
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_GREEN: &str = "\x1b[32m";
+const ANSI_CYAN: &str = "\x1b[36m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// A run of text with the same change.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub op: Op,
+    pub text: String,
+}
+
+/// A group of nearby line changes with surrounding context, as in a unified
+/// diff. Line numbers are 1-based.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+
+    /// One change per line
+    pub lines: Vec<Change>,
+}
+
+/// Longest-common-subsequence diff of two token sequences. Common prefixes
+/// and suffixes are matched directly so typical edits stay cheap.
+fn diff_tokens<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    // lengths[i][j]: LCS length of a[i..] and b[j..]
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<(Op, &str)> = old[..prefix].iter().map(|t| (Op::Equal, *t)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((Op::Equal, a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            ops.push((Op::Delete, a[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Insert, b[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|t| (Op::Equal, *t)));
+    ops
+}
+
+/// Line diff grouped into hunks with `context` unchanged lines around each
+/// change. Identical texts have no hunks.
+pub fn hunks(old: &str, new: &str, context: usize) -> Vec<Hunk> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_tokens(&old_lines, &new_lines);
+
+    // Line numbers before each op
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut old_line, mut new_line) = (1, 1);
+    for (op, _) in &ops {
+        positions.push((old_line, new_line));
+        match op {
+            Op::Equal => {
+                old_line += 1;
+                new_line += 1;
+            }
+            Op::Delete => old_line += 1,
+            Op::Insert => new_line += 1,
+        }
+    }
+
+    // Op ranges to show: each change widened by the context, merged when
+    // they touch
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (index, (op, _)) in ops.iter().enumerate() {
+        if *op == Op::Equal {
+            continue;
+        }
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let lines: Vec<Change> = ops[start..end]
+                .iter()
+                .map(|(op, text)| Change {
+                    op: *op,
+                    text: text.to_string(),
+                })
+                .collect();
+            let count = |skip: Op| lines.iter().filter(|l| l.op != skip).count();
+            Hunk {
+                old_start: positions[start].0,
+                old_lines: count(Op::Insert),
+                new_start: positions[start].1,
+                new_lines: count(Op::Delete),
+                lines,
+            }
+        })
+        .collect()
+}
+
+/// Render hunks as a unified diff, colored for a terminal when `color` is set.
+pub fn unified(hunks: &[Hunk], old_label: &str, new_label: &str, color: bool) -> String {
+    let paint = |code: &str, text: String| {
+        if color {
+            format!("{code}{text}{ANSI_END}")
+        } else {
+            text
+        }
+    };
+    let mut out = format!("--- {old_label}\n+++ {new_label}\n");
+    for hunk in hunks {
+        // An empty side starts at the line before, as in diff(1)
+        let start = |start: usize, lines: usize| if lines == 0 { start - 1 } else { start };
+        let header = format!(
+            "@@ -{},{} +{},{} @@",
+            start(hunk.old_start, hunk.old_lines),
+            hunk.old_lines,
+            start(hunk.new_start, hunk.new_lines),
+            hunk.new_lines
+        );
+        out.push_str(&paint(ANSI_CYAN, header));
+        out.push('\n');
+        for line in &hunk.lines {
+            let text = match line.op {
+                Op::Equal => format!(" {}", line.text),
+                Op::Delete => paint(ANSI_RED, format!("-{}", line.text)),
+                Op::Insert => paint(ANSI_GREEN, format!("+{}", line.text)),
+            };
+            out.push_str(&text);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Split text into words and the whitespace between them.
+fn word_tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let space = c.is_whitespace();
+        let boundary = chars
+            .peek()
+            .is_none_or(|&(_, next)| next.is_whitespace() != space);
+        if boundary {
+            let end = chars.peek().map_or(text.len(), |&(i, _)| i);
+            tokens.push(&text[start..end]);
+            start = end;
+        }
+    }
+    tokens
+}
+
+/// Word-level diff: runs of equal, deleted, and inserted text that together
+/// spell out both texts.
+pub fn words(old: &str, new: &str) -> Vec<Change> {
+    let mut changes: Vec<Change> = Vec::new();
+    for (op, text) in diff_tokens(&word_tokens(old), &word_tokens(new)) {
+        match changes.last_mut() {
+            Some(last) if last.op == op => last.text.push_str(text),
+            _ => changes.push(Change {
+                op,
+                text: text.to_string(),
+            }),
+        }
+    }
+    changes
+}
+
+/// Render a word diff inline: `[-deleted-]{+inserted+}` as in
+/// `git diff --word-diff`, or red and green text for a terminal.
+pub fn render_words(changes: &[Change], color: bool) -> String {
+    changes
+        .iter()
+        .map(|change| match (change.op, color) {
+            (Op::Equal, _) => change.text.clone(),
+            (Op::Delete, true) => format!("{ANSI_RED}{}{ANSI_END}", change.text),
+            (Op::Insert, true) => format!("{ANSI_GREEN}{}{ANSI_END}", change.text),
+            (Op::Delete, false) => format!("[-{}-]", change.text),
+            (Op::Insert, false) => format!("{{+{}+}}", change.text),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hunks() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        let hunks = hunks(old, new, 1);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(
+            (
+                hunks[0].old_start,
+                hunks[0].old_lines,
+                hunks[0].new_start,
+                hunks[0].new_lines
+            ),
+            (1, 3, 1, 3)
+        );
+        let ops: Vec<(Op, &str)> = hunks[0]
+            .lines
+            .iter()
+            .map(|l| (l.op, l.text.as_str()))
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                (Op::Equal, "a"),
+                (Op::Delete, "b"),
+                (Op::Insert, "B"),
+                (Op::Equal, "c")
+            ]
+        );
+        assert_eq!((hunks[1].old_start, hunks[1].new_start), (10, 10));
+
+        assert!(super::hunks(old, old, 3).is_empty());
+    }
+
+    #[test]
+    fn test_unified() {
+        let text = unified(&hunks("x\n", "x\ny\n", 3), "a", "b", false);
+        assert_eq!(text, "--- a\n+++ b\n@@ -1,1 +1,2 @@\n x\n+y\n");
+        let text = unified(&hunks("", "new\n", 3), "a", "b", false);
+        assert!(text.contains("@@ -0,0 +1,1 @@\n+new\n"));
+    }
+
+    #[test]
+    fn test_words() {
+        let changes = words("Rotate the keys weekly.", "Rotate all keys weekly.");
+        assert_eq!(
+            render_words(&changes, false),
+            "Rotate [-the-]{+all+} keys weekly."
+        );
+        assert_eq!(word_tokens("a  b\nc"), vec!["a", "  ", "b", "\n", "c"]);
+    }
+}
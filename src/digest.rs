@@ -0,0 +1,261 @@
+use crate::dates::parse_timestamp;
+use crate::html::escape;
+use crate::mem::Mem;
+use crate::storage::{ARCHIVED_AT_FIELD, ARCHIVE_REASON_FIELD};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// A mem listed in a digest.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    /// Store label (empty for the default store)
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub store: String,
+    pub path: String,
+    pub title: String,
+
+    /// When it was created, edited, went stale, or was archived
+    pub at: DateTime<Utc>,
+
+    /// Why it was archived, if recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// What happened to a store's mems in a period.
+#[derive(Debug, Clone, Serialize)]
+pub struct Digest {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub created: Vec<Entry>,
+    pub edited: Vec<Entry>,
+
+    /// Mems that crossed the staleness threshold during the period
+    pub stale: Vec<Entry>,
+    pub archived: Vec<Entry>,
+}
+
+impl Digest {
+    /// An empty digest for the period from `since` to `until`.
+    pub fn new(since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        Self {
+            since,
+            until,
+            created: Vec::new(),
+            edited: Vec::new(),
+            stale: Vec::new(),
+            archived: Vec::new(),
+        }
+    }
+
+    /// Whether nothing happened in the period.
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.edited.is_empty()
+            && self.stale.is_empty()
+            && self.archived.is_empty()
+    }
+
+    /// Add one store's active and archived mems.
+    ///
+    /// Archived mems count by their `archived-at` field, or by `archived_at`
+    /// (e.g. from git history) for mems archived before the field existed.
+    /// `stale_days` gives each mem's staleness threshold (`None` for never).
+    pub fn evaluate(
+        &mut self,
+        label: &str,
+        mems: &[Mem],
+        archived: &[Mem],
+        archived_at: impl Fn(&Mem) -> Option<DateTime<Utc>>,
+        stale_days: impl Fn(&Mem) -> Option<u32>,
+    ) {
+        let entry = |mem: &Mem, at| Entry {
+            store: label.to_string(),
+            path: mem.path_str(),
+            title: mem.title.clone(),
+            at,
+            reason: None,
+        };
+        let in_period = |at: DateTime<Utc>| at >= self.since && at <= self.until;
+
+        for mem in mems {
+            if in_period(mem.created_at) {
+                self.created.push(entry(mem, mem.created_at));
+            } else if in_period(mem.updated_at) {
+                self.edited.push(entry(mem, mem.updated_at));
+            }
+            if let Some(days) = stale_days(mem) {
+                let stale_at = mem.updated_at + Duration::days(i64::from(days));
+                if in_period(stale_at) {
+                    self.stale.push(entry(mem, stale_at));
+                }
+            }
+        }
+        for mem in archived {
+            let at = mem
+                .field(ARCHIVED_AT_FIELD)
+                .and_then(parse_timestamp)
+                .or_else(|| archived_at(mem));
+            if let Some(at) = at.filter(|at| in_period(*at)) {
+                self.archived.push(Entry {
+                    reason: mem.field(ARCHIVE_REASON_FIELD).map(str::to_string),
+                    ..entry(mem, at)
+                });
+            }
+        }
+
+        for list in [
+            &mut self.created,
+            &mut self.edited,
+            &mut self.stale,
+            &mut self.archived,
+        ] {
+            list.sort_by(|a, b| b.at.cmp(&a.at).then_with(|| a.path.cmp(&b.path)));
+        }
+    }
+
+    fn sections(&self) -> [(&'static str, &[Entry]); 4] {
+        [
+            ("New", &self.created),
+            ("Edited", &self.edited),
+            ("Newly stale", &self.stale),
+            ("Archived", &self.archived),
+        ]
+    }
+
+    fn title(&self) -> String {
+        format!(
+            "Mem digest: {} to {}",
+            self.since.format("%Y-%m-%d"),
+            self.until.format("%Y-%m-%d")
+        )
+    }
+
+    /// Render as markdown, omitting empty sections.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n", self.title());
+        if self.is_empty() {
+            out.push_str("\nNo changes.\n");
+        }
+        for (heading, entries) in self.sections() {
+            if entries.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("\n## {heading} ({})\n\n", entries.len()));
+            for e in entries {
+                let reason = e.reason.as_ref().map(|r| format!(": {r}"));
+                out.push_str(&format!(
+                    "- {} (`{}`, {}){}\n",
+                    e.title,
+                    name(e),
+                    e.at.format("%Y-%m-%d"),
+                    reason.unwrap_or_default()
+                ));
+            }
+        }
+        out
+    }
+
+    /// Render as an HTML fragment, omitting empty sections.
+    pub fn to_html(&self) -> String {
+        let mut out = format!("<h1>{}</h1>\n", escape(&self.title()));
+        if self.is_empty() {
+            out.push_str("<p>No changes.</p>\n");
+        }
+        for (heading, entries) in self.sections() {
+            if entries.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("<h2>{heading} ({})</h2>\n<ul>\n", entries.len()));
+            for e in entries {
+                let reason = e.reason.as_ref().map(|r| format!(": {}", escape(r)));
+                out.push_str(&format!(
+                    "<li>{} (<code>{}</code>, {}){}</li>\n",
+                    escape(&e.title),
+                    escape(&name(e)),
+                    e.at.format("%Y-%m-%d"),
+                    reason.unwrap_or_default()
+                ));
+            }
+            out.push_str("</ul>\n");
+        }
+        out
+    }
+}
+
+fn name(e: &Entry) -> String {
+    if e.store.is_empty() {
+        e.path.clone()
+    } else {
+        format!("[{}] {}", e.store, e.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn at(day: u32) -> DateTime<Utc> {
+        format!("2025-03-{day:02}T12:00:00Z").parse().unwrap()
+    }
+
+    fn mem(path: &str, created: u32, updated: u32) -> Mem {
+        let mut mem = Mem::new(PathBuf::from(path), path.to_uppercase(), String::new());
+        mem.created_at = at(created);
+        mem.updated_at = at(updated);
+        mem
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mems = vec![
+            mem("new", 10, 11),
+            mem("edited", 1, 11),
+            mem("quiet", 1, 2),
+            // Went stale (5 days without update) during the period
+            mem("rotting", 1, 4),
+        ];
+        let mut archived_marked = mem("gone", 1, 1);
+        archived_marked.set_field(ARCHIVED_AT_FIELD, at(12).to_rfc3339());
+        archived_marked.set_field(ARCHIVE_REASON_FIELD, "superseded by adr-017");
+        let archived = vec![
+            archived_marked,
+            mem("old-gone", 1, 1),
+            mem("from-git", 1, 1),
+        ];
+        let from_git = |m: &Mem| (m.path == Path::new("from-git")).then(|| at(9));
+
+        let mut digest = Digest::new(at(8), at(15));
+        digest.evaluate("", &mems, &archived, from_git, |_| Some(5));
+
+        let paths =
+            |entries: &[Entry]| -> Vec<String> { entries.iter().map(|e| e.path.clone()).collect() };
+        assert_eq!(paths(&digest.created), vec!["new"]);
+        assert_eq!(paths(&digest.edited), vec!["edited"]);
+        assert_eq!(paths(&digest.stale), vec!["rotting"]);
+        assert_eq!(paths(&digest.archived), vec!["gone", "from-git"]);
+        assert_eq!(
+            digest.archived[0].reason.as_deref(),
+            Some("superseded by adr-017")
+        );
+        assert!(digest
+            .to_markdown()
+            .contains("- GONE (`gone`, 2025-03-12): superseded by adr-017\n"));
+    }
+
+    #[test]
+    fn test_render() {
+        let mut digest = Digest::new(at(8), at(15));
+        assert!(digest.to_markdown().ends_with("No changes.\n"));
+
+        digest.evaluate("", &[mem("a<b", 10, 10)], &[], |_| None, |_| Some(90));
+        assert_eq!(
+            digest.to_markdown(),
+            "# Mem digest: 2025-03-08 to 2025-03-15\n\n## New (1)\n\n- A<B (`a<b`, 2025-03-10)\n"
+        );
+        assert!(digest
+            .to_html()
+            .contains("<li>A&lt;B (<code>a&lt;b</code>, 2025-03-10)</li>"));
+    }
+}
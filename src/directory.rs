@@ -0,0 +1,158 @@
+use crate::mem::Mem;
+use crate::schema::Schema;
+use crate::storage::Storage;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Name of the metadata file inside a mem directory (e.g. `.mems/arch/_dir.yaml`).
+pub const DIR_FILE: &str = "_dir.yaml";
+
+/// Directory metadata loaded from a `_dir.yaml` file.
+///
+/// Every field is optional; a missing file yields the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DirMeta {
+    /// One-line description shown by `tree` and `ls`
+    pub description: Option<String>,
+
+    /// Owner recorded on new mems under the directory
+    pub owner: Option<String>,
+
+    /// Tags added to new mems under the directory
+    pub tags: Vec<String>,
+
+    /// Frontmatter rules for mems under the directory (checked by `lint`)
+    #[serde(flatten)]
+    pub schema: Schema,
+}
+
+/// Load the metadata of one directory (`""` for the store root).
+pub fn load(storage: &Storage, dir: &str) -> Result<DirMeta> {
+    let name = if dir.is_empty() {
+        DIR_FILE.to_string()
+    } else {
+        format!("{dir}/{DIR_FILE}")
+    };
+    match storage.read_file(&name)? {
+        Some(content) if !content.trim().is_empty() => {
+            serde_yaml::from_str(&content).map_err(|e| anyhow!("invalid {name}: {e}"))
+        }
+        _ => Ok(DirMeta::default()),
+    }
+}
+
+/// The metadata that applies to a mem path, merged from the store root down
+/// to the mem's directory.
+///
+/// The nearest description and owner win; tags from every level accumulate.
+/// Schemas are merged separately, by [`crate::schema::for_path`].
+pub fn effective(storage: &Storage, mem_path: &str) -> Result<DirMeta> {
+    let mut merged = DirMeta::default();
+    let parts: Vec<&str> = mem_path.split('/').collect();
+    for depth in 0..parts.len() {
+        let meta = load(storage, &parts[..depth].join("/"))?;
+        if meta.description.is_some() {
+            merged.description = meta.description;
+        }
+        if meta.owner.is_some() {
+            merged.owner = meta.owner;
+        }
+        for tag in meta.tags {
+            if !merged.tags.contains(&tag) {
+                merged.tags.push(tag);
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Apply inherited defaults to a new mem: directory tags are added to its
+/// own, and the owner is set unless the mem already has one.
+pub fn apply_defaults(mem: &mut Mem, meta: &DirMeta) {
+    for tag in &meta.tags {
+        if !mem.tags.contains(tag) {
+            mem.tags.push(tag.clone());
+        }
+    }
+    if let Some(owner) = &meta.owner {
+        if mem.owner().is_none() {
+            mem.set_field("owner", owner.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn store() -> (TempDir, Storage) {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        fs::create_dir_all(temp.path().join("arch/decisions")).unwrap();
+        fs::write(temp.path().join(DIR_FILE), "owner: core\ntags: [team]\n").unwrap();
+        fs::write(
+            temp.path().join("arch").join(DIR_FILE),
+            "description: Architecture notes\ntags: [arch, team]\n",
+        )
+        .unwrap();
+        fs::write(
+            temp.path().join("arch/decisions").join(DIR_FILE),
+            "description: ADRs\nowner: platform\nrequired-fields: [deciders]\n",
+        )
+        .unwrap();
+        (temp, storage)
+    }
+
+    #[test]
+    fn test_effective_merges_ancestors() {
+        let (_temp, storage) = store();
+        assert_eq!(
+            effective(&storage, "arch/decisions/adr-001").unwrap(),
+            DirMeta {
+                description: Some("ADRs".to_string()),
+                owner: Some("platform".to_string()),
+                tags: vec!["team".to_string(), "arch".to_string()],
+                schema: Schema::default(),
+            }
+        );
+        let top = effective(&storage, "notes").unwrap();
+        assert_eq!(top.description, None);
+        assert_eq!(top.owner.as_deref(), Some("core"));
+        assert_eq!(load(&storage, "missing").unwrap(), DirMeta::default());
+    }
+
+    #[test]
+    fn test_apply_defaults() {
+        let (_temp, storage) = store();
+        let meta = effective(&storage, "arch/decisions/adr-001").unwrap();
+        let mut mem = Mem::new(
+            PathBuf::from("arch/decisions/adr-001"),
+            "ADR".to_string(),
+            "x".to_string(),
+        )
+        .with_tags(vec!["db".to_string()]);
+        apply_defaults(&mut mem, &meta);
+        assert_eq!(mem.tags, vec!["db", "team", "arch"]);
+        assert_eq!(mem.field("owner"), Some("platform"));
+
+        assert_eq!(
+            load(&storage, "arch/decisions")
+                .unwrap()
+                .schema
+                .required_fields,
+            vec!["deciders"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        let (temp, storage) = store();
+        fs::write(temp.path().join("arch").join(DIR_FILE), "tags: 3\n").unwrap();
+        let err = effective(&storage, "arch/x").unwrap_err().to_string();
+        assert!(err.starts_with("invalid arch/_dir.yaml"), "{err}");
+    }
+}
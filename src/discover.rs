@@ -0,0 +1,148 @@
+//! Monorepo discovery: the `.mems/` directories nested under a repository
+//! root, each used as a store labeled by the package directory holding it.
+
+use crate::git;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of a store directory.
+const STORE_DIR: &str = ".mems";
+
+/// Directories never searched for stores (dependencies and build output).
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor"];
+
+/// A store found under the repository root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Found {
+    /// Name of the directory holding the store, or its path from the root
+    /// when several share a name
+    pub label: String,
+
+    /// The `.mems/` directory
+    pub dir: PathBuf,
+}
+
+/// The repository root for `dir`: the top of its git work tree, or `dir`
+/// itself outside git.
+pub fn root(dir: &Path) -> PathBuf {
+    git::toplevel(dir).unwrap_or_else(|_| dir.to_path_buf())
+}
+
+/// Every store under `root`, in path order. Hidden directories and those in
+/// [`SKIPPED_DIRS`] are not searched.
+pub fn find_stores(root: &Path) -> Result<Vec<Found>> {
+    let mut packages = Vec::new();
+    walk(root, root, &mut packages)?;
+    packages.sort();
+
+    let name = |package: &Path| {
+        package
+            .file_name()
+            .or_else(|| root.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| ".".to_string())
+    };
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for package in &packages {
+        *counts.entry(name(package)).or_default() += 1;
+    }
+    Ok(packages
+        .iter()
+        .map(|package| {
+            let name = name(package);
+            let label = if counts[&name] > 1 && !package.as_os_str().is_empty() {
+                package.to_string_lossy().replace('\\', "/")
+            } else {
+                name
+            };
+            Found {
+                label,
+                dir: root.join(package).join(STORE_DIR),
+            }
+        })
+        .collect())
+}
+
+/// Names cross-store links can use for the store labeled `label` at `dir`:
+/// its label, and the name of the directory holding it (so stores given with
+/// `--dir` can be linked to by package name).
+pub fn names(label: &str, dir: &Path) -> Vec<String> {
+    let holder = if dir.file_name().is_some_and(|n| n == STORE_DIR) {
+        dir.parent()
+    } else {
+        Some(dir)
+    };
+    let mut names = vec![label.to_string()];
+    if let Some(name) = holder.and_then(Path::file_name) {
+        names.push(name.to_string_lossy().to_string());
+    }
+    names.retain(|n| !n.is_empty());
+    names.dedup();
+    names
+}
+
+/// Collect the directories under `dir` holding a store, relative to `root`.
+fn walk(root: &Path, dir: &Path, packages: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == STORE_DIR {
+            packages.push(dir.strip_prefix(root).unwrap_or(dir).to_path_buf());
+        } else if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+            walk(root, &entry.path(), packages)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_stores() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        for dir in [
+            ".mems",
+            "packages/billing/.mems",
+            "packages/search/.mems",
+            "apps/search/.mems",
+            "node_modules/dep/.mems",
+            ".git/.mems",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+
+        let found = find_stores(root).unwrap();
+        let labels: Vec<&str> = found.iter().map(|f| f.label.as_str()).collect();
+        let root_name = root.file_name().unwrap().to_string_lossy();
+        assert_eq!(
+            labels,
+            [&*root_name, "apps/search", "billing", "packages/search"]
+        );
+        assert_eq!(found[2].dir, root.join("packages/billing/.mems"));
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            names(
+                "./packages/billing/.mems",
+                Path::new("./packages/billing/.mems")
+            ),
+            ["./packages/billing/.mems", "billing"]
+        );
+        assert_eq!(
+            names("billing", Path::new("/repo/billing/.mems")),
+            ["billing"]
+        );
+    }
+}
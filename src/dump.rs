@@ -0,0 +1,82 @@
+//! The mem sections `mem dump` writes: a divider naming each mem, its title
+//! and tags, and its content with includes expanded, one store at a time.
+
+use crate::include;
+use crate::mem::Mem;
+use crate::storage::Storage;
+use anyhow::Result;
+use clap::ValueEnum;
+use std::io::{self, Write};
+
+/// Order of mems in a dump.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Order {
+    #[default]
+    Path,
+    Updated,
+}
+
+/// Section divider naming a mem, between mems printed together.
+pub fn write_divider(out: &mut dyn Write, path: &str) -> io::Result<()> {
+    writeln!(
+        out,
+        "<!-- ═══════════════════════════════════════════════════════════════════ -->"
+    )?;
+    writeln!(out, "<!-- {path} -->")?;
+    writeln!(
+        out,
+        "<!-- ═══════════════════════════════════════════════════════════════════ -->"
+    )?;
+    writeln!(out)
+}
+
+/// A mem as `dump` writes it: divider, title, tags, and content with
+/// includes expanded.
+pub fn section(storage: &Storage, mem: &Mem) -> Result<String> {
+    let mut section = Vec::new();
+    write_divider(&mut section, &mem.path_str())?;
+
+    // Title as H1
+    writeln!(section, "# {}", mem.title)?;
+    writeln!(section)?;
+
+    // Tags if present
+    if !mem.tags.is_empty() {
+        writeln!(section, "Tags: {}", mem.tags.join(", "))?;
+        writeln!(section)?;
+    }
+
+    // Content, with includes expanded
+    writeln!(section, "{}", include::expand(storage, mem)?)?;
+    writeln!(section)?;
+    Ok(String::from_utf8(section)?)
+}
+
+/// Call `each` with every mem under `prefix` and its section, in `order`,
+/// reading one mem at a time so a large store isn't held in memory.
+pub fn for_each_section(
+    storage: &Storage,
+    prefix: &str,
+    order: Order,
+    mut each: impl FnMut(&Mem, String) -> Result<()>,
+) -> Result<()> {
+    let mems: Box<dyn Iterator<Item = Mem>> = match order {
+        Order::Path => Box::new(storage.iter_mems_under(prefix)?),
+        Order::Updated => {
+            // Order by frontmatter alone, then read each mem in turn
+            let mut headers = storage.list_headers_under(prefix)?;
+            headers.sort_by(|a, b| {
+                b.updated_at
+                    .cmp(&a.updated_at)
+                    .then_with(|| a.path.cmp(&b.path))
+            });
+            let paths = headers.iter().map(Mem::path_str).collect();
+            Box::new(storage.read_each(paths))
+        }
+    };
+    for mem in mems {
+        let section = section(storage, &mem)?;
+        each(&mem, section)?;
+    }
+    Ok(())
+}
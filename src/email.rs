@@ -0,0 +1,232 @@
+//! Capturing emails as mems (`mem capture-email`): the subject becomes the
+//! title, the date the creation time, the sender a `from` field, and the body
+//! markdown. Attachments are stored by content in the store's attachment
+//! directory (see [`crate::attachment`]) and linked from the mem, so they
+//! travel with it in bundles (see [`crate::bundle`]).
+
+use crate::attachment;
+use crate::html;
+use crate::mem::{slugify, Mem};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use mail_parser::{MessageParser, MimeHeaders};
+use std::path::PathBuf;
+
+/// A file attached to an email.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    /// File name, made safe for the store
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// The parts of an email that go into a mem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    pub subject: Option<String>,
+
+    /// Sender as `Name <address>`
+    pub from: Option<String>,
+    pub date: Option<DateTime<Utc>>,
+    pub message_id: Option<String>,
+
+    /// Body as markdown: the plain text part, else the HTML part converted
+    pub body: String,
+    pub attachments: Vec<Attachment>,
+}
+
+/// A file name with path separators and anything a markdown link would
+/// trip over replaced.
+fn safe_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    name.trim_start_matches('.').to_string()
+}
+
+/// Parse a raw RFC 822 message.
+pub fn parse(raw: &[u8]) -> Result<Email> {
+    let message = MessageParser::default()
+        .parse(raw)
+        .ok_or_else(|| anyhow!("not an email: no headers found"))?;
+
+    let from = message.from().and_then(|from| from.first()).map(|addr| {
+        match (addr.name(), addr.address()) {
+            (Some(name), Some(address)) => format!("{name} <{address}>"),
+            (name, address) => name.or(address).unwrap_or_default().to_string(),
+        }
+    });
+    let date = message
+        .date()
+        .and_then(|date| DateTime::from_timestamp(date.to_timestamp(), 0));
+
+    // Without a plain text part, the parser offers the HTML part as text
+    let body = match message.text_part(0) {
+        Some(part) if part.is_text_html() => {
+            html::to_markdown(part.text_contents().unwrap_or_default(), "").markdown
+        }
+        Some(part) => part
+            .text_contents()
+            .unwrap_or_default()
+            .replace("\r\n", "\n"),
+        None => String::new(),
+    };
+
+    let mut attachments: Vec<Attachment> = Vec::new();
+    for (i, part) in message.attachments().enumerate() {
+        let fallback = if part.is_message() {
+            format!("message-{}.eml", i + 1)
+        } else {
+            format!("attachment-{}", i + 1)
+        };
+        let mut name = part.attachment_name().map(safe_name).unwrap_or_default();
+        if name.is_empty() {
+            name = fallback;
+        }
+        if attachments.iter().any(|a| a.name == name) {
+            name = format!("{}-{name}", i + 1);
+        }
+        attachments.push(Attachment {
+            name,
+            data: part.contents().to_vec(),
+        });
+    }
+
+    Ok(Email {
+        subject: message
+            .subject()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string),
+        from,
+        date,
+        message_id: message.message_id().map(str::to_string),
+        body: body.trim().to_string(),
+        attachments,
+    })
+}
+
+impl Email {
+    /// Default mem path: `email/<date>-<subject slug>`.
+    pub fn default_path(&self) -> String {
+        let date = self.date.unwrap_or_else(Utc::now).format("%Y-%m-%d");
+        let subject = self.subject.as_deref().unwrap_or("email");
+        format!("email/{date}-{}", slugify(subject))
+    }
+
+    /// The mem for this email at `path`, linking to the blobs its
+    /// attachments are stored as.
+    pub fn to_mem(&self, path: &str) -> Mem {
+        let title = self
+            .subject
+            .clone()
+            .unwrap_or_else(|| "(no subject)".to_string());
+        let mut content = self.body.clone();
+        if !self.attachments.is_empty() {
+            content.push_str("\n\n## Attachments\n\n");
+            for a in &self.attachments {
+                let key = attachment::blob_key(&a.name, &a.data);
+                let target = attachment::link_target(path, &key);
+                content.push_str(&format!("- [{}]({target})\n", a.name));
+            }
+        }
+
+        let mut mem = Mem::new(PathBuf::from(path), title, content.trim_start().to_string());
+        if let Some(date) = self.date {
+            mem.created_at = date;
+            mem.updated_at = date;
+        }
+        if let Some(from) = &self.from {
+            mem.set_field("from", from.as_str());
+        }
+        if let Some(id) = &self.message_id {
+            mem.set_field("message-id", id.as_str());
+        }
+        mem
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MULTIPART: &str = "From: Ana Lima <ana@example.com>\r\n\
+        To: team@example.com\r\n\
+        Subject: =?UTF-8?Q?Decision:_move_to_Postgres_=E2=9C=94?=\r\n\
+        Date: Tue, 3 Mar 2026 14:30:00 +0100\r\n\
+        Message-ID: <abc@example.com>\r\n\
+        MIME-Version: 1.0\r\n\
+        Content-Type: multipart/mixed; boundary=\"b1\"\r\n\
+        \r\n\
+        --b1\r\n\
+        Content-Type: text/plain; charset=utf-8\r\n\
+        Content-Transfer-Encoding: quoted-printable\r\n\
+        \r\n\
+        We agreed to move to Postgres =E2=80=94 see the notes.\r\n\
+        \r\n\
+        --b1\r\n\
+        Content-Type: application/pdf; name=\"meeting notes.pdf\"\r\n\
+        Content-Disposition: attachment; filename=\"meeting notes.pdf\"\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        JVBERi0xLjQ=\r\n\
+        --b1--\r\n";
+
+    #[test]
+    fn test_parse_multipart() {
+        let email = parse(MULTIPART.as_bytes()).unwrap();
+        assert_eq!(
+            email.subject.as_deref(),
+            Some("Decision: move to Postgres ✔")
+        );
+        assert_eq!(email.from.as_deref(), Some("Ana Lima <ana@example.com>"));
+        assert_eq!(
+            email.date.unwrap().to_rfc3339(),
+            "2026-03-03T13:30:00+00:00"
+        );
+        assert_eq!(email.message_id.as_deref(), Some("abc@example.com"));
+        assert_eq!(email.body, "We agreed to move to Postgres — see the notes.");
+        assert_eq!(
+            email.attachments,
+            [Attachment {
+                name: "meeting-notes.pdf".to_string(),
+                data: b"%PDF-1.4".to_vec(),
+            }]
+        );
+
+        assert_eq!(
+            email.default_path(),
+            "email/2026-03-03-decision-move-to-postgres"
+        );
+        let mem = email.to_mem("decisions/postgres");
+        assert_eq!(mem.title, "Decision: move to Postgres ✔");
+        assert_eq!(mem.created_at, email.date.unwrap());
+        assert_eq!(mem.field("from"), Some("Ana Lima <ana@example.com>"));
+        let blob = attachment::blob_key("meeting-notes.pdf", b"%PDF-1.4");
+        assert!(mem.content.ends_with(&format!(
+            "## Attachments\n\n- [meeting-notes.pdf](../{blob})\n"
+        )));
+    }
+
+    #[test]
+    fn test_parse_html_only() {
+        let raw = "From: ops@example.com\n\
+                   Content-Type: text/html\n\
+                   \n\
+                   <p>Deploys <b>paused</b> until Monday.</p>\n";
+        let email = parse(raw.as_bytes()).unwrap();
+        assert_eq!(email.from.as_deref(), Some("ops@example.com"));
+        assert_eq!(email.subject, None);
+        assert_eq!(email.body, "Deploys **paused** until Monday.");
+        assert_eq!(email.to_mem("a").title, "(no subject)");
+        assert!(parse(b"").is_err());
+    }
+}
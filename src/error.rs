@@ -0,0 +1,102 @@
+use std::fmt;
+
+/// Errors callers may want to tell apart (the CLI maps them to exit codes).
+///
+/// These travel inside `anyhow::Error`; use `downcast_ref::<MemError>()` to
+/// inspect them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemError {
+    /// No mem at this path; `suggestions` are known paths close to it
+    NotFound {
+        path: String,
+        suggestions: Vec<String>,
+    },
+
+    /// A case-insensitive lookup matched more than one mem
+    Ambiguous {
+        path: String,
+        candidates: Vec<String>,
+    },
+
+    /// The path is empty
+    EmptyPath,
+
+    /// The path is absolute rather than relative to the store
+    AbsolutePath { path: String },
+
+    /// The path has a `..` segment that would escape the store
+    ParentSegment { path: String },
+
+    /// No store at `location`, or no .mems/ above the current directory
+    StoreNotFound { location: Option<String> },
+
+    /// A check such as lint found problems
+    CheckFailed { check: &'static str, issues: usize },
+
+    /// The mem is locked against changes
+    Locked { path: String },
+
+    /// Content from `source` isn't UTF-8 text (`binary` if it looks like a
+    /// binary file), so it can't be a mem
+    NotText { source: String, binary: bool },
+}
+
+impl fmt::Display for MemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemError::NotFound { path, suggestions } => {
+                write!(f, "mem not found: {path}")?;
+                if !suggestions.is_empty() {
+                    write!(f, " (did you mean {}?)", suggestions.join(", "))?;
+                }
+                Ok(())
+            }
+            MemError::Ambiguous { path, candidates } => write!(
+                f,
+                "{path} matches several mems; did you mean one of: {}?",
+                candidates.join(", ")
+            ),
+            MemError::EmptyPath => write!(f, "mem path is empty"),
+            MemError::AbsolutePath { path } => {
+                write!(f, "mem path must be relative to the store: {path}")
+            }
+            MemError::ParentSegment { path } => {
+                write!(f, "mem path must not contain '..': {path}")
+            }
+            MemError::StoreNotFound {
+                location: Some(location),
+            } => {
+                write!(f, "directory not found: {location}")
+            }
+            MemError::StoreNotFound { location: None } => {
+                write!(
+                    f,
+                    "no .mems/ directory found (run `mem init` to create one)"
+                )
+            }
+            MemError::CheckFailed { check, issues } => {
+                write!(f, "{check} failed with {issues} issues")
+            }
+            MemError::Locked { path } => write!(
+                f,
+                "{path} is locked (pass --force-unlock, or run `mem unlock {path}`)"
+            ),
+            MemError::NotText {
+                source,
+                binary: true,
+            } => write!(
+                f,
+                "{source} is a binary file, not text; attach it to a mem with `mem attach <mem> <file>`"
+            ),
+            MemError::NotText {
+                source,
+                binary: false,
+            } => write!(
+                f,
+                "{source} is not valid UTF-8 text; convert it (e.g. with iconv) or attach it to a mem with `mem attach <mem> <file>`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MemError {}
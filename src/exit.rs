@@ -0,0 +1,84 @@
+use crate::conflict::MergeConflict;
+use crate::error::MemError;
+
+/// The command succeeded.
+pub const SUCCESS: u8 = 0;
+/// Any error without a more specific code.
+pub const ERROR: u8 = 1;
+/// Invalid arguments or mem paths.
+pub const USAGE: u8 = 2;
+/// The mem (or id) does not exist, or a path matched several mems.
+pub const NOT_FOUND: u8 = 3;
+/// A check (`lint`, `check-refs`, ...) found problems.
+pub const CHECK_FAILED: u8 = 4;
+/// A mem has unresolved merge conflict markers.
+pub const CONFLICT: u8 = 5;
+/// No store was found.
+pub const NO_STORE: u8 = 6;
+/// The mem is locked against changes.
+pub const LOCKED: u8 = 7;
+
+/// Exit code table for `--help`.
+pub const HELP: &str = "Exit codes:
+  0  success
+  1  error
+  2  usage error (bad arguments or mem path)
+  3  mem not found
+  4  check failed (lint, check-refs, verify)
+  5  unresolved merge conflict
+  6  no store found
+  7  mem is locked";
+
+/// Exit code for an error, from the first recognized error in its chain.
+pub fn code(err: &anyhow::Error) -> u8 {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<MemError>() {
+            return match e {
+                MemError::NotFound { .. } | MemError::Ambiguous { .. } => NOT_FOUND,
+                MemError::EmptyPath
+                | MemError::AbsolutePath { .. }
+                | MemError::ParentSegment { .. } => USAGE,
+                MemError::StoreNotFound { .. } => NO_STORE,
+                MemError::CheckFailed { .. } => CHECK_FAILED,
+                MemError::Locked { .. } => LOCKED,
+                MemError::NotText { .. } => ERROR,
+            };
+        }
+        if cause.is::<MergeConflict>() {
+            return CONFLICT;
+        }
+    }
+    ERROR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn test_code() {
+        let not_found: anyhow::Error = MemError::NotFound {
+            path: "x".to_string(),
+            suggestions: Vec::new(),
+        }
+        .into();
+        assert_eq!(code(&not_found), NOT_FOUND);
+
+        // Context wrapping doesn't hide the cause
+        let conflict = Err::<(), _>(MergeConflict {
+            path: "x".to_string(),
+        })
+        .context("failed to read")
+        .unwrap_err();
+        assert_eq!(code(&conflict), CONFLICT);
+
+        let locked: anyhow::Error = MemError::Locked {
+            path: "x".to_string(),
+        }
+        .into();
+        assert_eq!(code(&locked), LOCKED);
+
+        assert_eq!(code(&anyhow!("boom")), ERROR);
+    }
+}
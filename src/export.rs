@@ -0,0 +1,352 @@
+use crate::links;
+use crate::mem::Mem;
+use crate::progress::Progress;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// File in an export directory listing the files the last export wrote, so
+/// an incremental export deletes only those.
+pub const MANIFEST_FILE: &str = ".mem-export.json";
+
+/// What [`MANIFEST_FILE`] holds.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ExportManifest {
+    /// Exported files, relative to the export directory
+    files: BTreeSet<String>,
+}
+
+impl ExportManifest {
+    /// The manifest in `out`, if an export wrote one there.
+    fn load(out: &Path) -> Result<Option<ExportManifest>> {
+        let file = out.join(MANIFEST_FILE);
+        if !file.is_file() {
+            return Ok(None);
+        }
+        let data = fs::read(&file).with_context(|| format!("failed to read {}", file.display()))?;
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| anyhow!("invalid {}: {e}", file.display()))
+    }
+
+    fn save(&self, out: &Path) -> Result<()> {
+        let file = out.join(MANIFEST_FILE);
+        fs::create_dir_all(out).with_context(|| format!("failed to create {}", out.display()))?;
+        fs::write(&file, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("failed to write {}", file.display()))
+    }
+}
+
+/// Whether `out` holds an earlier export that `sync_dir` can update.
+pub fn is_export(out: &Path) -> bool {
+    out.join(MANIFEST_FILE).is_file()
+}
+
+/// Write mems into `out` as `.md` files mirroring the store hierarchy.
+///
+/// With `plain`, files have no frontmatter (see `to_plain`); otherwise they
+/// are copies of the mem files. Records them in [`MANIFEST_FILE`] for later
+/// incremental exports. Returns the number of files written.
+pub fn export_dir(mems: &[Mem], out: &Path, plain: bool, progress: &dyn Progress) -> Result<usize> {
+    let paths = id_paths(mems);
+    let mut manifest = ExportManifest::default();
+    progress.start("Exporting", mems.len());
+    for mem in mems {
+        let name = format!("{}.md", path_of(mem));
+        let file = out.join(&name);
+        create_parent(&file)?;
+        fs::write(&file, render(mem, plain, &paths)?)
+            .with_context(|| format!("failed to write {}", file.display()))?;
+        progress.advance(&path_of(mem));
+        manifest.files.insert(name);
+    }
+    progress.finish();
+    manifest.save(out)?;
+    Ok(mems.len())
+}
+
+/// What an incremental export changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncSummary {
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// Bring `out` in line with `mems` as `export_dir` would, but only rewrite
+/// files whose content differs and delete the files of mems no longer
+/// exported, so unchanged files keep their timestamps. Only files the last
+/// export listed in [`MANIFEST_FILE`] are deleted; anything else in `out` is
+/// left alone.
+pub fn sync_dir(
+    mems: &[Mem],
+    out: &Path,
+    plain: bool,
+    progress: &dyn Progress,
+) -> Result<SyncSummary> {
+    let paths = id_paths(mems);
+    let previous = ExportManifest::load(out)?.unwrap_or_default();
+    let mut manifest = ExportManifest::default();
+    let mut summary = SyncSummary::default();
+
+    progress.start("Exporting", mems.len());
+    for mem in mems {
+        let path = path_of(mem);
+        let name = format!("{path}.md");
+        let file = out.join(&name);
+        let content = render(mem, plain, &paths)?;
+        if fs::read(&file).is_ok_and(|old| old == content.as_bytes()) {
+            summary.unchanged += 1;
+        } else {
+            create_parent(&file)?;
+            fs::write(&file, content)
+                .with_context(|| format!("failed to write {}", file.display()))?;
+            summary.written += 1;
+        }
+        progress.advance(&path);
+        manifest.files.insert(name);
+    }
+    progress.finish();
+
+    for name in previous.files.difference(&manifest.files) {
+        if remove_exported(out, name)? {
+            summary.removed += 1;
+        }
+    }
+    manifest.save(out)?;
+    Ok(summary)
+}
+
+/// Delete the exported file `name` from `out`, if it is still there, and the
+/// directories it leaves empty. Returns whether there was a file to delete.
+fn remove_exported(out: &Path, name: &str) -> Result<bool> {
+    // Manifests are hand-editable; never follow one outside the export
+    let relative = Path::new(name);
+    if !relative
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+    {
+        return Ok(false);
+    }
+    let file = out.join(relative);
+    if !file.is_file() {
+        return Ok(false);
+    }
+    fs::remove_file(&file).with_context(|| format!("failed to remove {}", file.display()))?;
+    let mut dir = file.parent();
+    while let Some(d) = dir.filter(|d| *d != out) {
+        if fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+    Ok(true)
+}
+
+/// Mem paths by uppercased id, for resolving links by id.
+fn id_paths(mems: &[Mem]) -> HashMap<String, String> {
+    mems.iter()
+        .filter_map(|m| Some((m.id.as_ref()?.to_uppercase(), path_of(m))))
+        .collect()
+}
+
+/// The exported file for `mem`.
+fn render(mem: &Mem, plain: bool, paths: &HashMap<String, String>) -> Result<String> {
+    if plain {
+        Ok(to_plain(mem, paths))
+    } else {
+        mem.serialize()
+    }
+}
+
+fn create_parent(file: &Path) -> Result<()> {
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+/// A mem as plain markdown: the title as an H1, the content, and the tags in
+/// a footer. Links by id become relative links when `paths` (uppercased id to
+/// mem path) knows the target.
+pub fn to_plain(mem: &Mem, paths: &HashMap<String, String>) -> String {
+    let content = resolve_id_links(&path_of(mem), mem.content.trim_end(), paths);
+    let mut out = format!("# {}\n\n{content}\n", mem.title);
+    if !mem.tags.is_empty() {
+        let tags: Vec<String> = mem.tags.iter().map(|t| format!("`{t}`")).collect();
+        out.push_str(&format!("\n---\n\nTags: {}\n", tags.join(", ")));
+    }
+    out
+}
+
+/// Replace `[[id:<ULID>|label]]` with `[label](relative.md)`.
+fn resolve_id_links(from: &str, content: &str, paths: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("[[id:") {
+        let Some(len) = rest[start..].find("]]") else {
+            break;
+        };
+        let inner = &rest[start + 5..start + len];
+        let (id, label) = inner.split_once('|').unwrap_or((inner, inner));
+        out.push_str(&rest[..start]);
+        match paths.get(&id.trim().to_uppercase()) {
+            Some(path) => out.push_str(&format!(
+                "[{}]({})",
+                label.trim(),
+                links::relative_target(from, path)
+            )),
+            None => out.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Check that `out` is empty or missing, so an export never mixes with
+/// unrelated files.
+pub fn check_target(out: &Path) -> Result<()> {
+    if out.is_file() {
+        return Err(anyhow!("not a directory: {}", out.display()));
+    }
+    if out.is_dir() && fs::read_dir(out)?.next().is_some() {
+        return Err(anyhow!(
+            "directory not empty: {} (use --force to write into it)",
+            out.display()
+        ));
+    }
+    Ok(())
+}
+
+fn path_of(mem: &Mem) -> String {
+    mem.path_str().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Silent;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn mem(path: &str, title: &str, content: &str) -> Mem {
+        Mem::new(PathBuf::from(path), title.to_string(), content.to_string())
+    }
+
+    #[test]
+    fn test_to_plain() {
+        let target = mem("arch/db", "DB", "x");
+        let paths = HashMap::from([(
+            target.id.clone().unwrap().to_uppercase(),
+            "arch/db".to_string(),
+        )]);
+        let source = mem(
+            "guides/setup",
+            "Setup",
+            &format!(
+                "See [[id:{}|the DB]] and [[id:01MISSING]].\n",
+                target.id.as_ref().unwrap().to_lowercase()
+            ),
+        )
+        .with_tags(vec!["ops".to_string(), "db".to_string()]);
+
+        assert_eq!(
+            to_plain(&source, &paths),
+            "# Setup\n\nSee [the DB](../arch/db.md) and [[id:01MISSING]].\n\n---\n\nTags: `ops`, `db`\n"
+        );
+        assert_eq!(to_plain(&target, &paths), "# DB\n\nx\n");
+    }
+
+    #[test]
+    fn test_export_dir() {
+        let temp = TempDir::new().unwrap();
+        let out = temp.path().join("out");
+        let mems = vec![mem("a", "A", "one"), mem("b/c", "C", "two")];
+
+        check_target(&out).unwrap();
+        assert_eq!(export_dir(&mems, &out, true, &Silent).unwrap(), 2);
+        assert_eq!(
+            fs::read_to_string(out.join("b/c.md")).unwrap(),
+            "# C\n\ntwo\n"
+        );
+        assert!(check_target(&out).is_err());
+        assert!(is_export(&out));
+
+        export_dir(&mems, &out, false, &Silent).unwrap();
+        let raw = fs::read_to_string(out.join("a.md")).unwrap();
+        assert!(raw.starts_with("---\n") && raw.contains("title: A"));
+    }
+
+    #[test]
+    fn test_sync_dir() {
+        let temp = TempDir::new().unwrap();
+        let out = temp.path();
+        let mems = vec![
+            mem("a", "A", "one"),
+            mem("b/c", "C", "two"),
+            mem("d/e", "E", "x"),
+        ];
+        let summary = sync_dir(&mems, out, true, &Silent).unwrap();
+        assert_eq!(
+            (summary.written, summary.unchanged, summary.removed),
+            (3, 0, 0)
+        );
+
+        // Unchanged files aren't rewritten; stale ones go, files the export
+        // didn't write stay
+        fs::write(out.join("README.txt"), "hand-written").unwrap();
+        fs::write(out.join("setup.md"), "hand-written").unwrap();
+        fs::write(out.join("d/notes.md"), "hand-written").unwrap();
+        let before = fs::metadata(out.join("a.md")).unwrap().modified().unwrap();
+        let mems = vec![mem("a", "A", "one"), mem("b/c", "C", "edited")];
+        let summary = sync_dir(&mems, out, true, &Silent).unwrap();
+        assert_eq!(
+            summary,
+            SyncSummary {
+                written: 1,
+                unchanged: 1,
+                removed: 1
+            }
+        );
+        let after = fs::metadata(out.join("a.md")).unwrap().modified().unwrap();
+        assert_eq!(before, after);
+        assert_eq!(
+            fs::read_to_string(out.join("b/c.md")).unwrap(),
+            "# C\n\nedited\n"
+        );
+        assert!(!out.join("d/e.md").exists());
+        assert!(out.join("d/notes.md").exists());
+        assert!(out.join("README.txt").exists() && out.join("setup.md").exists());
+
+        let mems = vec![mem("a", "A", "one")];
+        assert_eq!(sync_dir(&mems, out, true, &Silent).unwrap().removed, 1);
+        assert!(!out.join("b").exists());
+    }
+
+    #[test]
+    fn test_export_reports_progress() {
+        #[derive(Default)]
+        struct Recorder(std::cell::RefCell<Vec<String>>);
+        impl Progress for Recorder {
+            fn start(&self, label: &str, total: usize) {
+                self.0.borrow_mut().push(format!("{label} {total}"));
+            }
+            fn advance(&self, item: &str) {
+                self.0.borrow_mut().push(item.to_string());
+            }
+            fn finish(&self) {
+                self.0.borrow_mut().push("done".to_string());
+            }
+        }
+
+        let temp = TempDir::new().unwrap();
+        let mems = vec![mem("a", "A", "one"), mem("b/c", "C", "two")];
+        let recorder = Recorder::default();
+        export_dir(&mems, temp.path(), true, &recorder).unwrap();
+        assert_eq!(recorder.0.into_inner(), ["Exporting 2", "a", "b/c", "done"]);
+    }
+}
@@ -0,0 +1,186 @@
+//! Ordering search results from several stores (`find` with more than one
+//! `--dir`, or nested stores): per-store weights from the `search:` config,
+//! stores named with `--prefer` first, and results interleaved by score or
+//! grouped by store.
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// How results from several stores are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Each store's results together, stores in order of preference
+    Grouped,
+    /// All results by weighted score, whatever the store
+    Interleaved,
+}
+
+/// Ranking across stores, under `search:` in the config of the first store.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SearchConfig {
+    /// Score multipliers by store label (default 1)
+    pub store_weights: BTreeMap<String, f32>,
+
+    /// Layout when `find` doesn't get `--layout` (default: grouped for
+    /// keyword search, interleaved for semantic search)
+    pub layout: Option<Layout>,
+}
+
+/// How to order results from the stores in play.
+#[derive(Debug, Clone)]
+pub struct Ranking {
+    /// Store labels in the order the stores were given
+    stores: Vec<String>,
+    weights: BTreeMap<String, f32>,
+    prefer: Vec<String>,
+    pub layout: Layout,
+}
+
+impl Ranking {
+    /// A ranking of results from `stores` (labels, in the order given), laid
+    /// out as `layout`, else as configured, else as `default`. Every store in
+    /// `prefer` must be one of them.
+    pub fn new(
+        stores: Vec<String>,
+        config: &SearchConfig,
+        prefer: &[String],
+        layout: Option<Layout>,
+        default: Layout,
+    ) -> Result<Self> {
+        if let Some(unknown) = prefer.iter().find(|p| !stores.contains(p)) {
+            return Err(anyhow!(
+                "no store labeled '{unknown}' (stores: {})",
+                stores.join(", ")
+            ));
+        }
+        Ok(Self {
+            weights: config.store_weights.clone(),
+            prefer: prefer.to_vec(),
+            layout: layout.or(config.layout).unwrap_or(default),
+            stores,
+        })
+    }
+
+    fn weight(&self, store: &str) -> f32 {
+        self.weights.get(store).copied().unwrap_or(1.0)
+    }
+
+    /// Position of a store in the order stores are shown: preferred stores
+    /// as given, then the rest by weight, then as given.
+    fn store_rank(&self, store: &str) -> (usize, Reverse<Weight>, usize) {
+        let preferred = self
+            .prefer
+            .iter()
+            .position(|p| p == store)
+            .unwrap_or(self.prefer.len());
+        let given = self
+            .stores
+            .iter()
+            .position(|s| s == store)
+            .unwrap_or(self.stores.len());
+        (preferred, Reverse(Weight(self.weight(store))), given)
+    }
+
+    /// Result indexes best first, for results given as (store label, score).
+    /// Ties keep the order given.
+    pub fn order(&self, results: &[(&str, f32)]) -> Vec<usize> {
+        let key = |i: usize| {
+            let (store, score) = results[i];
+            (self.store_rank(store), Weight(score * self.weight(store)))
+        };
+        let mut order: Vec<usize> = (0..results.len()).collect();
+        order.sort_by(|&a, &b| {
+            let ((rank_a, score_a), (rank_b, score_b)) = (key(a), key(b));
+            match self.layout {
+                Layout::Grouped => rank_a.cmp(&rank_b).then(score_b.cmp(&score_a)),
+                Layout::Interleaved => (rank_a.0.cmp(&rank_b.0))
+                    .then(score_b.cmp(&score_a))
+                    .then(rank_a.2.cmp(&rank_b.2)),
+            }
+        });
+        order
+    }
+}
+
+/// An `f32` that sorts (NaN last).
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Weight(f32);
+
+impl Eq for Weight {}
+
+impl PartialOrd for Weight {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Weight {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranking(config: &str, prefer: &[&str], layout: Option<Layout>) -> Ranking {
+        let config: SearchConfig = serde_yaml::from_str(config).unwrap();
+        let stores = ["team", "vendor", "personal"].map(String::from).to_vec();
+        let prefer: Vec<String> = prefer.iter().map(|p| p.to_string()).collect();
+        Ranking::new(stores, &config, &prefer, layout, Layout::Grouped).unwrap()
+    }
+
+    const RESULTS: &[(&str, f32)] = &[
+        ("team", 0.4),
+        ("team", 0.9),
+        ("vendor", 1.0),
+        ("personal", 0.5),
+        ("personal", 0.5),
+    ];
+
+    #[test]
+    fn test_grouped() {
+        // Stores as given, best first within each
+        assert_eq!(ranking("{}", &[], None).order(RESULTS), [1, 0, 2, 3, 4]);
+        // Heavier stores first, and --prefer before anything
+        let weighted = ranking("store-weights: {personal: 2, vendor: 0.5}", &[], None);
+        assert_eq!(weighted.order(RESULTS), [3, 4, 1, 0, 2]);
+        let preferred = ranking("store-weights: {personal: 2}", &["vendor"], None);
+        assert_eq!(preferred.order(RESULTS), [2, 3, 4, 1, 0]);
+    }
+
+    #[test]
+    fn test_interleaved() {
+        let interleaved = ranking("layout: interleaved", &[], None);
+        assert_eq!(interleaved.layout, Layout::Interleaved);
+        assert_eq!(interleaved.order(RESULTS), [2, 1, 3, 4, 0]);
+
+        let weighted = ranking(
+            "store-weights: {vendor: 0.3}",
+            &[],
+            Some(Layout::Interleaved),
+        );
+        assert_eq!(weighted.order(RESULTS), [1, 3, 4, 0, 2]);
+        let preferred = ranking("{}", &["personal"], Some(Layout::Interleaved));
+        assert_eq!(preferred.order(RESULTS), [3, 4, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_unknown_store() {
+        let err = Ranking::new(
+            vec!["team".to_string()],
+            &SearchConfig::default(),
+            &["other".to_string()],
+            None,
+            Layout::Grouped,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "no store labeled 'other' (stores: team)");
+    }
+}
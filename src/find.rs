@@ -0,0 +1,126 @@
+//! Keyword search over one store (`mem find`): the mems whose title and
+//! content match a [`Query`], with their scores. Ordering results from
+//! several stores, deduping, and output are left to the caller.
+
+use crate::kind;
+use crate::mem::Mem;
+use crate::query::Query;
+use crate::storage::Storage;
+use anyhow::Result;
+use clap::ValueEnum;
+
+/// Which mems a search covers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SearchIn {
+    #[default]
+    Active,
+    Archive,
+    All,
+}
+
+/// What `find` searches, and in what.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Filter<'a> {
+    /// Match the title alone, not the content
+    pub title_only: bool,
+
+    /// Only mems of this kind
+    pub kind: Option<&'a str>,
+
+    /// Only mems under this path (`""` for all)
+    pub under: &'a str,
+    pub search_in: SearchIn,
+}
+
+/// A mem that matched.
+#[derive(Debug, Clone)]
+pub struct Found {
+    pub mem: Mem,
+    pub archived: bool,
+    pub score: f32,
+}
+
+/// Mems in `storage` matching `query`, active ones first, each in path order.
+pub fn find(storage: &Storage, query: &Query, filter: &Filter) -> Result<Vec<Found>> {
+    let mut mems: Vec<(Mem, bool)> = Vec::new();
+    if filter.search_in != SearchIn::Archive {
+        let active = storage.list_mems_under(filter.under)?;
+        mems.extend(active.into_iter().map(|m| (m, false)));
+    }
+    if filter.search_in != SearchIn::Active {
+        let archived = storage.archived_mems_under(filter.under)?;
+        mems.extend(archived.into_iter().map(|m| (m, true)));
+    }
+
+    let mut found = Vec::new();
+    for (mem, archived) in mems {
+        if filter.kind.is_some_and(|k| kind::of(&mem) != k) {
+            continue;
+        }
+        let content = if filter.title_only { "" } else { &mem.content };
+        if query.matches(&format!("{}\n{content}", mem.title)) {
+            let score = query.score(&mem.title, content);
+            found.push(Found {
+                mem,
+                archived,
+                score,
+            });
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        for (path, title, content) in [
+            ("ops/deploy", "Deploy", "Run the database migration first."),
+            ("ops/legacy", "Legacy deploy", "Old database migration."),
+            ("notes/db", "Database notes", "Indexes."),
+        ] {
+            let mem = Mem::new(PathBuf::from(path), title.to_string(), content.to_string());
+            storage.write_mem(&mem).unwrap();
+        }
+        storage.archive_mem("ops/legacy", None).unwrap();
+
+        let query = Query::parse("database");
+        let paths = |filter: &Filter| -> Vec<(String, bool)> {
+            find(&storage, &query, filter)
+                .unwrap()
+                .into_iter()
+                .map(|f| (f.mem.path_str(), f.archived))
+                .collect()
+        };
+        assert_eq!(
+            paths(&Filter::default()),
+            [
+                ("notes/db".to_string(), false),
+                ("ops/deploy".to_string(), false)
+            ]
+        );
+        let everywhere = Filter {
+            under: "ops",
+            search_in: SearchIn::All,
+            ..Filter::default()
+        };
+        assert_eq!(
+            paths(&everywhere),
+            [
+                ("ops/deploy".to_string(), false),
+                ("ops/legacy".to_string(), true)
+            ]
+        );
+        let titles = Filter {
+            title_only: true,
+            ..Filter::default()
+        };
+        assert_eq!(paths(&titles), [("notes/db".to_string(), false)]);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::text;
+
+/// How many suggestions to offer for an unknown path.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Edit distance between two strings, counting characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+/// Known paths close to `query`, best first.
+///
+/// A candidate is close if it starts with the query, or if the whole path or
+/// its last segment is within a few edits of the query's (about one edit per
+/// three characters). Comparison ignores case.
+pub fn suggestions<'a>(query: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let query = text::fold(query);
+    let query_name = last_segment(&query);
+
+    let mut scored: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let lower = text::fold(candidate);
+            if lower.starts_with(&query) {
+                return Some((0, candidate));
+            }
+            let whole = levenshtein(&query, &lower);
+            let name = levenshtein(query_name, last_segment(&lower));
+            let score = if whole <= threshold(&query) {
+                whole
+            } else if name <= threshold(query_name) {
+                // Right name in the wrong directory ranks below typos in place
+                name + 1
+            } else {
+                return None;
+            };
+            Some((score, candidate))
+        })
+        .collect();
+
+    scored.sort();
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, c)| c.to_string())
+        .collect()
+}
+
+/// The first known tag one edit away from `tag`: a differently cased form,
+/// or one insertion, deletion, substitution, or swap of adjacent characters.
+pub fn near_tag<'a>(tag: &str, known: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let lower = text::fold(tag);
+    known.into_iter().find(|candidate| {
+        let other = text::fold(candidate);
+        *candidate != tag && (levenshtein(&lower, &other) <= 1 || is_swap(&lower, &other))
+    })
+}
+
+/// Whether `b` is `a` with two adjacent characters swapped.
+fn is_swap(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len() != b.len() {
+        return false;
+    }
+    let diffs: Vec<usize> = (0..a.len()).filter(|&i| a[i] != b[i]).collect();
+    matches!(diffs[..], [i, j] if j == i + 1 && a[i] == b[j] && a[j] == b[i])
+}
+
+fn last_segment(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn threshold(text: &str) -> usize {
+    (text.chars().count() / 3).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("adr-001", "adr-001"), 0);
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let known = [
+            "arch/decisions/adr-001",
+            "arch/decisions/adr-002",
+            "guides/setup",
+            "old/setup",
+        ];
+        assert_eq!(
+            suggestions("arch/decisons/adr-001", known),
+            vec!["arch/decisions/adr-001", "arch/decisions/adr-002"]
+        );
+        assert_eq!(
+            suggestions("guides/stup", known),
+            vec!["guides/setup", "old/setup"]
+        );
+        assert_eq!(
+            suggestions("arch/", known),
+            vec!["arch/decisions/adr-001", "arch/decisions/adr-002"]
+        );
+        assert!(suggestions("completely/different", known).is_empty());
+    }
+
+    #[test]
+    fn test_near_tag() {
+        let known = ["infra", "database", "Ops"];
+        assert_eq!(near_tag("infar", known), Some("infra"));
+        assert_eq!(near_tag("infr", known), Some("infra"));
+        assert_eq!(near_tag("databse", known), Some("database"));
+        assert_eq!(near_tag("ops", known), Some("Ops"));
+        assert_eq!(near_tag("infra", known), None);
+        assert_eq!(near_tag("fnira", known), None);
+    }
+}
@@ -0,0 +1,315 @@
+//! Retention policies applied by `mem gc`: archiving mems nobody has updated
+//! in a while, and purging archived mems past a retention window. With
+//! `--redirects` it also deletes redirect stubs nothing links to, and with
+//! `--attachments` attachment blobs nothing links to.
+
+use crate::attachment;
+use crate::dates;
+use crate::mem::Mem;
+use crate::redirect;
+use crate::schema;
+use crate::storage::ARCHIVED_AT_FIELD;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Retention settings, under `retention:` in the store config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct RetentionConfig {
+    /// Rules for archiving mems that have not been updated
+    pub policies: Vec<ArchivePolicy>,
+
+    /// How long archived mems are kept before `gc` purges them, e.g. `730d`
+    /// (forever when unset)
+    pub purge_archived_after: Option<String>,
+}
+
+/// Archive mems under a prefix, or with a tag, once they go unchanged for
+/// `auto-archive-after`. A policy with both needs both to match; one with
+/// neither applies to every mem.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ArchivePolicy {
+    pub prefix: Option<String>,
+    pub tag: Option<String>,
+
+    /// Time without an update before archiving, e.g. `90d` or `52w`
+    pub auto_archive_after: String,
+}
+
+impl ArchivePolicy {
+    fn matches(&self, mem: &Mem) -> bool {
+        self.prefix
+            .as_ref()
+            .is_none_or(|p| schema::under(&mem.path_str(), p))
+            && self.tag.as_ref().is_none_or(|t| mem.tags.contains(t))
+    }
+
+    /// What the policy applies to, for reports.
+    fn describe(&self) -> String {
+        match (&self.prefix, &self.tag) {
+            (Some(prefix), Some(tag)) => format!("{prefix} tagged {tag}"),
+            (Some(prefix), None) => prefix.clone(),
+            (None, Some(tag)) => format!("tag {tag}"),
+            (None, None) => "all mems".to_string(),
+        }
+    }
+}
+
+fn parse(value: &str, setting: &str) -> Result<Duration> {
+    dates::parse_duration(value)
+        .ok_or_else(|| anyhow!("invalid {setting}: {value} (use e.g. 90d, 12w, or 36h)"))
+}
+
+/// A mem `gc` archives or purges, and why.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Collected {
+    pub path: String,
+    pub reason: String,
+}
+
+/// What a `gc` run does.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Plan {
+    /// Active mems to archive
+    pub archive: Vec<Collected>,
+
+    /// Archived mems to delete for good
+    pub purge: Vec<Collected>,
+
+    /// Redirect stubs to delete
+    pub redirects: Vec<Collected>,
+
+    /// Attachment blobs to delete
+    pub attachments: Vec<Collected>,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.archive.is_empty()
+            && self.purge.is_empty()
+            && self.redirects.is_empty()
+            && self.attachments.is_empty()
+    }
+
+    /// Also delete the redirect stubs in `mems` that no mem links to, rather
+    /// than archiving them. Pinned and locked stubs are kept.
+    pub fn remove_redirects(&mut self, mems: &[Mem]) {
+        let kept: HashSet<String> = mems
+            .iter()
+            .filter(|m| m.is_pinned() || m.is_locked())
+            .map(Mem::path_str)
+            .collect();
+        for (path, to) in redirect::unlinked(mems) {
+            if kept.contains(&path) {
+                continue;
+            }
+            self.archive.retain(|c| c.path != path);
+            self.redirects.push(Collected {
+                reason: format!("redirects to {to}, and nothing links to it"),
+                path,
+            });
+        }
+    }
+
+    /// Also delete the attachment `blobs` that no mem, active or archived,
+    /// links to once the plan has run: links from the stubs and archived
+    /// mems it deletes don't count.
+    pub fn remove_attachments(&mut self, blobs: Vec<String>, mems: &[Mem], archived: &[Mem]) {
+        let deleted = |items: &[Collected], mem: &Mem| {
+            let path = mem.path_str();
+            items.iter().any(|c| c.path == path)
+        };
+        let linking: Vec<Mem> = mems
+            .iter()
+            .filter(|m| !deleted(&self.redirects, m))
+            .chain(archived.iter().filter(|m| !deleted(&self.purge, m)))
+            .cloned()
+            .collect();
+        for key in attachment::unreferenced(blobs, &linking) {
+            self.attachments.push(Collected {
+                path: key,
+                reason: "no mem links to it".to_string(),
+            });
+        }
+    }
+}
+
+/// Work out which mems the policies archive and which archived mems are past
+/// retention. Pinned, locked, and generated mems are never archived, and
+/// archived mems without an `archived-at` field are kept.
+pub fn plan(
+    config: &RetentionConfig,
+    mems: &[Mem],
+    archived: &[Mem],
+    now: DateTime<Utc>,
+) -> Result<Plan> {
+    let policies = config
+        .policies
+        .iter()
+        .map(|p| Ok((p, parse(&p.auto_archive_after, "auto-archive-after")?)))
+        .collect::<Result<Vec<_>>>()?;
+    let retention = config
+        .purge_archived_after
+        .as_deref()
+        .map(|value| parse(value, "purge-archived-after"))
+        .transpose()?;
+
+    let mut plan = Plan::default();
+    for mem in mems {
+        if mem.is_pinned() || mem.is_locked() || mem.is_generated() {
+            continue;
+        }
+        // Of several matching policies, the shortest applies
+        let policy = policies
+            .iter()
+            .filter(|(p, _)| p.matches(mem))
+            .min_by_key(|(_, after)| *after);
+        if let Some((policy, after)) = policy {
+            if now - mem.updated_at > *after {
+                plan.archive.push(Collected {
+                    path: mem.path_str(),
+                    reason: format!(
+                        "not updated in {} days, policy {}: {}",
+                        (now - mem.updated_at).num_days(),
+                        policy.describe(),
+                        policy.auto_archive_after
+                    ),
+                });
+            }
+        }
+    }
+
+    if let Some(retention) = retention {
+        for mem in archived {
+            let Some(at) = mem
+                .field(ARCHIVED_AT_FIELD)
+                .and_then(dates::parse_timestamp)
+            else {
+                continue;
+            };
+            if now - at > retention {
+                plan.purge.push(Collected {
+                    path: mem.path_str(),
+                    reason: format!("archived {} days ago", (now - at).num_days()),
+                });
+            }
+        }
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::path::PathBuf;
+
+    fn mem(path: &str, tags: &[&str], days_old: i64, now: DateTime<Utc>) -> Mem {
+        let mut mem = Mem::new(PathBuf::from(path), path.to_string(), "x".to_string())
+            .with_tags(tags.iter().map(|t| t.to_string()).collect());
+        mem.updated_at = now - Duration::days(days_old);
+        mem
+    }
+
+    #[test]
+    fn test_plan() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        let config: RetentionConfig = serde_yaml::from_str(
+            "policies:\n\
+             - prefix: scratch\n  auto-archive-after: 30d\n\
+             - tag: temp\n  auto-archive-after: 1w\n\
+             purge-archived-after: 52w\n",
+        )
+        .unwrap();
+
+        let mut pinned = mem("scratch/keep", &[], 100, now);
+        pinned.set_pinned(true);
+        let mems = vec![
+            mem("scratch/old", &[], 40, now),
+            mem("scratch/new", &[], 10, now),
+            mem("notes/party", &["temp"], 10, now),
+            mem("notes/plan", &[], 400, now),
+            pinned,
+        ];
+        let mut gone = mem("old/gone", &[], 900, now);
+        gone.set_field(ARCHIVED_AT_FIELD, "2024-01-01T00:00:00Z");
+        let mut recent = mem("old/recent", &[], 900, now);
+        recent.set_field(ARCHIVED_AT_FIELD, "2025-05-01T00:00:00Z");
+        let undated = mem("old/undated", &[], 900, now);
+
+        let plan = plan(&config, &mems, &[gone, recent, undated], now).unwrap();
+        let paths: Vec<&str> = plan.archive.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, ["scratch/old", "notes/party"]);
+        assert_eq!(
+            plan.archive[1].reason,
+            "not updated in 10 days, policy tag temp: 1w"
+        );
+        assert_eq!(
+            plan.purge,
+            vec![Collected {
+                path: "old/gone".to_string(),
+                reason: "archived 517 days ago".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_remove_redirects() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        let config: RetentionConfig =
+            serde_yaml::from_str("policies:\n- prefix: scratch\n  auto-archive-after: 30d\n")
+                .unwrap();
+        let target = mem("notes/new", &[], 0, now);
+        let mut stub = redirect::stub("scratch/old", &target);
+        stub.updated_at = now - Duration::days(40);
+        let mut locked = redirect::stub("notes/locked", &target);
+        locked.set_locked(true);
+        let mems = vec![target.clone(), stub, locked];
+
+        let mut plan = plan(&config, &mems, &[], now).unwrap();
+        assert_eq!(plan.archive.len(), 1);
+        plan.remove_redirects(&mems);
+        assert!(plan.archive.is_empty());
+        assert_eq!(
+            plan.redirects,
+            vec![Collected {
+                path: "scratch/old".to_string(),
+                reason: "redirects to notes/new, and nothing links to it".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_remove_attachments() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        let config: RetentionConfig = serde_yaml::from_str("purge-archived-after: 30d\n").unwrap();
+        let blob = |n: u8| attachment::blob_key("a.png", &[n]);
+        let mut linking = mem("notes/a", &[], 0, now);
+        linking.content = format!("![a](../{})", blob(1));
+        let mut purged = mem("old/b", &[], 0, now);
+        purged.content = format!("![b](../{})", blob(2));
+        purged.set_field(ARCHIVED_AT_FIELD, "2025-01-01T00:00:00Z");
+        let mut kept = mem("old/c", &[], 0, now);
+        kept.content = format!("![c](../{})", blob(3));
+        let mems = vec![linking];
+        let archived = vec![purged, kept];
+
+        let mut plan = plan(&config, &mems, &archived, now).unwrap();
+        plan.remove_attachments((1..=4).map(blob).collect(), &mems, &archived);
+        let paths: Vec<String> = plan.attachments.iter().map(|c| c.path.clone()).collect();
+        assert_eq!(paths, [blob(2), blob(4)]);
+    }
+
+    #[test]
+    fn test_invalid_duration() {
+        let config = RetentionConfig {
+            purge_archived_after: Some("1y".to_string()),
+            ..RetentionConfig::default()
+        };
+        let err = plan(&config, &[], &[], Utc::now()).unwrap_err();
+        assert!(err.to_string().contains("invalid purge-archived-after: 1y"));
+    }
+}
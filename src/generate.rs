@@ -0,0 +1,311 @@
+//! Mems generated from project artifacts (`mem generate`), kept current by
+//! rerunning the generator instead of by hand.
+
+use crate::links;
+use crate::mem::Mem;
+use crate::storage::Storage;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Frontmatter field marking a generated mem (`generated: true`).
+pub const GENERATED_FIELD: &str = "generated";
+
+/// Directory of the per-crate mems written by `generate from-cargo`.
+pub const CRATES_DIR: &str = "generated/crates";
+
+/// Path of the workspace overview written by `generate from-cargo`.
+pub const WORKSPACE_PATH: &str = "generated/workspace";
+
+/// A mem a generator wants in the store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generated {
+    pub path: String,
+    pub title: String,
+    pub content: String,
+    pub tags: Vec<String>,
+}
+
+/// What `apply` did with a mem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Created,
+    Updated,
+    Unchanged,
+    /// A generated mem the generator no longer produces
+    Removed,
+    /// A hand-written mem is in the way and was left alone
+    Skipped,
+}
+
+impl Outcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Created => "Created",
+            Outcome::Updated => "Updated",
+            Outcome::Unchanged => "Unchanged",
+            Outcome::Removed => "Removed",
+            Outcome::Skipped => "Skipped (not generated)",
+        }
+    }
+}
+
+/// `cargo metadata` output, as far as it is used here.
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_root: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    name: String,
+    version: String,
+    description: Option<String>,
+    #[serde(default)]
+    features: BTreeMap<String, Vec<String>>,
+    targets: Vec<Target>,
+    manifest_path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>,
+    src_path: PathBuf,
+}
+
+/// Describe a Cargo workspace, by default the one containing the current
+/// directory: one mem per crate, plus an overview listing them.
+pub fn from_cargo(manifest_path: Option<&Path>) -> Result<Vec<Generated>> {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut command = Command::new(cargo);
+    command.args(["metadata", "--no-deps", "--format-version", "1"]);
+    if let Some(path) = manifest_path {
+        command.arg("--manifest-path").arg(path);
+    }
+    let output = command
+        .output()
+        .context("failed to run cargo (is it installed?)")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("cargo metadata failed: {}", stderr.trim()));
+    }
+    let metadata: Metadata =
+        serde_json::from_slice(&output.stdout).context("invalid cargo metadata output")?;
+    Ok(describe_workspace(&metadata))
+}
+
+fn describe_workspace(metadata: &Metadata) -> Vec<Generated> {
+    let relative = |path: &Path| {
+        path.strip_prefix(&metadata.workspace_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    };
+    let mut packages: Vec<&Package> = metadata.packages.iter().collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut overview = String::from("Crates in this workspace:\n\n");
+    let mut mems = Vec::new();
+    for package in packages {
+        let path = format!("{CRATES_DIR}/{}", package.name);
+        let description = package.description.as_deref().unwrap_or("No description");
+        let _ = writeln!(
+            overview,
+            "- [{}]({}) {}: {description}",
+            package.name,
+            links::relative_target(WORKSPACE_PATH, &path),
+            package.version
+        );
+
+        let mut content = format!(
+            "{description}\n\nVersion {}, manifest `{}`.\n",
+            package.version,
+            relative(&package.manifest_path)
+        );
+        for (heading, kinds) in [
+            (
+                "Library",
+                &["lib", "rlib", "cdylib", "dylib", "staticlib", "proc-macro"][..],
+            ),
+            ("Binaries", &["bin"][..]),
+        ] {
+            let targets: Vec<&Target> = package
+                .targets
+                .iter()
+                .filter(|t| t.kind.iter().any(|k| kinds.contains(&k.as_str())))
+                .collect();
+            if !targets.is_empty() {
+                let _ = writeln!(content, "\n## {heading}\n");
+                for target in targets {
+                    let _ = writeln!(
+                        content,
+                        "- `{}` (`{}`)",
+                        target.name,
+                        relative(&target.src_path)
+                    );
+                }
+            }
+        }
+        if !package.features.is_empty() {
+            content.push_str("\n## Features\n\n");
+            for (feature, enables) in &package.features {
+                if enables.is_empty() {
+                    let _ = writeln!(content, "- `{feature}`");
+                } else {
+                    let _ = writeln!(content, "- `{feature}`: enables {}", enables.join(", "));
+                }
+            }
+        }
+
+        mems.push(Generated {
+            path,
+            title: format!("{} crate", package.name),
+            content,
+            tags: vec!["generated".to_string(), "crate".to_string()],
+        });
+    }
+
+    mems.push(Generated {
+        path: WORKSPACE_PATH.to_string(),
+        title: "Cargo workspace".to_string(),
+        content: overview,
+        tags: vec!["generated".to_string()],
+    });
+    mems
+}
+
+/// Write generated mems into the store, updating only those whose content
+/// changed and removing generated mems under `prune_dir` that are no longer
+/// produced. Hand-written mems are never touched.
+pub fn apply(
+    storage: &Storage,
+    generated: &[Generated],
+    prune_dir: &str,
+) -> Result<Vec<(String, Outcome)>> {
+    let mut outcomes = Vec::new();
+    for item in generated {
+        let outcome = if storage.exists(&item.path) {
+            let mut mem = storage.read_mem(&item.path)?;
+            if !mem.is_generated() {
+                Outcome::Skipped
+            } else if mem.title == item.title
+                && mem.content == item.content
+                && mem.tags == item.tags
+            {
+                Outcome::Unchanged
+            } else {
+                mem.title = item.title.clone();
+                mem.content = item.content.clone();
+                mem.tags = item.tags.clone();
+                mem.touch();
+                storage.write_mem(&mem)?;
+                Outcome::Updated
+            }
+        } else {
+            let mut mem = Mem::new(
+                PathBuf::from(&item.path),
+                item.title.clone(),
+                item.content.clone(),
+            )
+            .with_tags(item.tags.clone());
+            mem.extra
+                .insert(GENERATED_FIELD.to_string(), serde_yaml::Value::Bool(true));
+            storage.write_mem(&mem)?;
+            Outcome::Created
+        };
+        outcomes.push((item.path.clone(), outcome));
+    }
+
+    let wanted: HashSet<&str> = generated.iter().map(|g| g.path.as_str()).collect();
+    for mem in storage.list_mems_under(prune_dir)? {
+        let path = mem.path_str();
+        if mem.is_generated() && !wanted.contains(path.as_str()) {
+            storage.delete_mem(&path)?;
+            outcomes.push((path, Outcome::Removed));
+        }
+    }
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn metadata() -> Metadata {
+        serde_json::from_str(
+            r#"{
+                "workspace_root": "/ws",
+                "packages": [{
+                    "name": "widget",
+                    "version": "0.2.0",
+                    "description": "Widget renderer",
+                    "features": {"default": ["svg"], "svg": []},
+                    "manifest_path": "/ws/widget/Cargo.toml",
+                    "targets": [
+                        {"name": "widget", "kind": ["lib"], "src_path": "/ws/widget/src/lib.rs"},
+                        {"name": "widgetctl", "kind": ["bin"], "src_path": "/ws/widget/src/main.rs"},
+                        {"name": "it", "kind": ["test"], "src_path": "/ws/widget/tests/it.rs"}
+                    ]
+                }]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_describe_workspace() {
+        let mems = describe_workspace(&metadata());
+        assert_eq!(mems.len(), 2);
+        assert_eq!(mems[0].path, "generated/crates/widget");
+        assert_eq!(
+            mems[0].content,
+            "Widget renderer\n\nVersion 0.2.0, manifest `widget/Cargo.toml`.\n\n\
+             ## Library\n\n- `widget` (`widget/src/lib.rs`)\n\n\
+             ## Binaries\n\n- `widgetctl` (`widget/src/main.rs`)\n\n\
+             ## Features\n\n- `default`: enables svg\n- `svg`\n"
+        );
+        assert!(mems[1]
+            .content
+            .contains("- [widget](crates/widget.md) 0.2.0: Widget renderer"));
+    }
+
+    #[test]
+    fn test_apply() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let mut generated = describe_workspace(&metadata());
+        storage
+            .write_mem(&Mem::new(
+                PathBuf::from("generated/crates/notes"),
+                "Notes".to_string(),
+                "hand-written".to_string(),
+            ))
+            .unwrap();
+
+        let outcomes = apply(&storage, &generated, CRATES_DIR).unwrap();
+        assert!(outcomes.iter().all(|(_, o)| *o == Outcome::Created));
+        assert!(storage
+            .read_mem("generated/workspace")
+            .unwrap()
+            .is_generated());
+
+        generated[0].content.push_str("More.\n");
+        let outcomes = apply(&storage, &generated, CRATES_DIR).unwrap();
+        assert_eq!(outcomes[0].1, Outcome::Updated);
+        assert_eq!(outcomes[1].1, Outcome::Unchanged);
+
+        // Crates that went away are removed; hand-written mems stay
+        let outcomes = apply(&storage, &generated[1..], CRATES_DIR).unwrap();
+        assert_eq!(
+            outcomes.last().unwrap(),
+            &("generated/crates/widget".to_string(), Outcome::Removed)
+        );
+        assert!(storage.exists("generated/crates/notes"));
+    }
+}
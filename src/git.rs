@@ -0,0 +1,330 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Run a git command in `dir` and return its stdout.
+fn run(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .context("failed to run git (is it installed?)")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args.join(" "), stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Top-level directory of the git work tree containing `dir`.
+pub fn toplevel(dir: &Path) -> Result<PathBuf> {
+    let out = run(dir, &["rev-parse", "--show-toplevel"])?;
+    Ok(PathBuf::from(out.trim()))
+}
+
+/// Create a git repository in `dir`.
+pub fn init(dir: &Path) -> Result<()> {
+    run(dir, &["init", "--quiet"]).map(drop)
+}
+
+/// Files under `dir` that differ from `reference` in the work tree, including
+/// deleted and untracked files. Returned paths are absolute.
+pub fn changed_since(dir: &Path, reference: &str) -> Result<Vec<PathBuf>> {
+    let top = toplevel(dir)?;
+    let dir = dir.to_string_lossy();
+
+    let diff = run(
+        &top,
+        &["diff", "--name-only", "--no-renames", reference, "--", &dir],
+    )?;
+    let untracked = run(
+        &top,
+        &["ls-files", "--others", "--exclude-standard", "--", &dir],
+    )?;
+
+    let mut files: Vec<PathBuf> = diff
+        .lines()
+        .chain(untracked.lines())
+        .filter(|l| !l.is_empty())
+        .map(|l| top.join(l))
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// First and last commit times of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileTimes {
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+/// Author times of the first and latest commits touching each file under
+/// `dir`, from a single `git log` walk. Paths are absolute; renames are not
+/// followed, so a moved file's history starts at the move.
+pub fn file_times(dir: &Path) -> Result<HashMap<PathBuf, FileTimes>> {
+    let top = toplevel(dir)?;
+    let log = run(
+        &top,
+        &[
+            "log",
+            "--format=%x00%aI",
+            "--name-only",
+            "--no-renames",
+            "--",
+            &dir.to_string_lossy(),
+        ],
+    )?;
+
+    // Newest commit first: the first time seen is the update, the last the creation
+    let mut times: HashMap<PathBuf, FileTimes> = HashMap::new();
+    for commit in log.split('\0').filter(|c| !c.trim().is_empty()) {
+        let mut lines = commit.lines();
+        let date = lines.next().unwrap_or_default();
+        let when = DateTime::parse_from_rfc3339(date.trim())
+            .map_err(|e| anyhow!("unexpected git date {date}: {e}"))?
+            .with_timezone(&Utc);
+        for file in lines.filter(|l| !l.is_empty()) {
+            times
+                .entry(top.join(file))
+                .and_modify(|t| t.created = when)
+                .or_insert(FileTimes {
+                    created: when,
+                    updated: when,
+                });
+        }
+    }
+    Ok(times)
+}
+
+/// A commit that changed a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Commit {
+    /// Abbreviated commit hash
+    pub commit: String,
+    pub author: String,
+    pub time: DateTime<Utc>,
+    pub subject: String,
+}
+
+/// Commits that changed `file`, newest first, following renames.
+pub fn log(file: &Path) -> Result<Vec<Commit>> {
+    let dir = file.parent().ok_or_else(|| anyhow!("invalid path"))?;
+    let out = run(
+        dir,
+        &[
+            "log",
+            "--follow",
+            "--format=%h%x00%an%x00%aI%x00%s",
+            "--",
+            &file.to_string_lossy(),
+        ],
+    )?;
+    out.lines()
+        .filter(|l| !l.is_empty())
+        .map(|line| {
+            let mut fields = line.splitn(4, '\0');
+            let mut next = || fields.next().unwrap_or_default().to_string();
+            let (commit, author, date, subject) = (next(), next(), next(), next());
+            let time = DateTime::parse_from_rfc3339(&date)
+                .map_err(|e| anyhow!("unexpected git date {date}: {e}"))?
+                .with_timezone(&Utc);
+            Ok(Commit {
+                commit,
+                author,
+                time,
+                subject,
+            })
+        })
+        .collect()
+}
+
+/// Attribution for one line of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlameLine {
+    /// Abbreviated commit hash (all zeros for uncommitted changes)
+    pub commit: String,
+    pub author: String,
+    pub time: DateTime<Utc>,
+
+    /// 1-based line number in the current file
+    pub line: usize,
+    pub text: String,
+}
+
+/// Per-line attribution of a file in the work tree, via `git blame`.
+pub fn blame(file: &Path) -> Result<Vec<BlameLine>> {
+    let dir = file.parent().ok_or_else(|| anyhow!("invalid path"))?;
+    let out = run(
+        dir,
+        &["blame", "--line-porcelain", "--", &file.to_string_lossy()],
+    )?;
+    parse_blame(&out)
+}
+
+fn parse_blame(porcelain: &str) -> Result<Vec<BlameLine>> {
+    let mut lines = Vec::new();
+    let mut commit = String::new();
+    let mut line = 0;
+    let mut author = String::new();
+    let mut time = DateTime::<Utc>::UNIX_EPOCH;
+    for entry in porcelain.lines() {
+        if let Some(text) = entry.strip_prefix('\t') {
+            lines.push(BlameLine {
+                commit: commit.clone(),
+                author: author.clone(),
+                time,
+                line,
+                text: text.to_string(),
+            });
+        } else if let Some(name) = entry.strip_prefix("author ") {
+            author = name.to_string();
+        } else if let Some(seconds) = entry.strip_prefix("author-time ") {
+            let seconds: i64 = seconds
+                .parse()
+                .map_err(|_| anyhow!("unexpected git blame time: {seconds}"))?;
+            time = DateTime::from_timestamp(seconds, 0)
+                .ok_or_else(|| anyhow!("unexpected git blame time: {seconds}"))?;
+        } else {
+            // Header: <sha> <original line> <final line> [<group size>]
+            let mut fields = entry.split(' ');
+            let sha = fields.next().unwrap_or_default();
+            if sha.len() == 40 && sha.chars().all(|c| c.is_ascii_hexdigit()) {
+                commit = sha[..8].to_string();
+                line = fields.nth(1).and_then(|l| l.parse().ok()).unwrap_or(0);
+            }
+        }
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        run(dir, args).unwrap();
+    }
+
+    #[test]
+    fn test_changed_since() {
+        let temp = TempDir::new().unwrap();
+        let repo = temp.path().canonicalize().unwrap();
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["config", "user.email", "t@example.com"]);
+        git(&repo, &["config", "user.name", "t"]);
+
+        let mems = repo.join(".mems");
+        fs::create_dir(&mems).unwrap();
+        fs::write(mems.join("kept.md"), "a").unwrap();
+        fs::write(mems.join("edited.md"), "a").unwrap();
+        fs::write(mems.join("deleted.md"), "a").unwrap();
+        fs::write(repo.join("outside.md"), "a").unwrap();
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-q", "-m", "base"]);
+
+        fs::write(mems.join("edited.md"), "b").unwrap();
+        fs::remove_file(mems.join("deleted.md")).unwrap();
+        fs::write(mems.join("new.md"), "c").unwrap();
+        fs::write(repo.join("outside.md"), "b").unwrap();
+
+        let changed = changed_since(&mems, "HEAD").unwrap();
+        assert_eq!(
+            changed,
+            vec![
+                mems.join("deleted.md"),
+                mems.join("edited.md"),
+                mems.join("new.md")
+            ]
+        );
+
+        assert!(changed_since(&mems, "no-such-ref").is_err());
+    }
+
+    #[test]
+    fn test_file_times() {
+        let temp = TempDir::new().unwrap();
+        let repo = temp.path().canonicalize().unwrap();
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["config", "user.email", "t@example.com"]);
+        git(&repo, &["config", "user.name", "t"]);
+
+        let mems = repo.join(".mems");
+        fs::create_dir(&mems).unwrap();
+        let commit = |date: &str| {
+            git(&repo, &["add", "-A"]);
+            git(&repo, &["commit", "-q", "-m", date, "--date", date]);
+        };
+        fs::write(mems.join("a.md"), "1").unwrap();
+        fs::write(mems.join("b.md"), "1").unwrap();
+        commit("2024-01-01T00:00:00Z");
+        fs::write(mems.join("a.md"), "2").unwrap();
+        commit("2024-06-01T00:00:00Z");
+
+        let times = file_times(&mems).unwrap();
+        let a = times[&mems.join("a.md")];
+        assert_eq!(a.created.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert_eq!(a.updated.to_rfc3339(), "2024-06-01T00:00:00+00:00");
+        assert_eq!(times[&mems.join("b.md")].updated, a.created);
+    }
+
+    #[test]
+    fn test_blame() {
+        let temp = TempDir::new().unwrap();
+        let repo = temp.path().canonicalize().unwrap();
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["config", "user.email", "t@example.com"]);
+        git(&repo, &["config", "user.name", "Ana"]);
+
+        let file = repo.join("a.md");
+        fs::write(&file, "one\ntwo\n").unwrap();
+        git(&repo, &["add", "-A"]);
+        git(
+            &repo,
+            &["commit", "-q", "-m", "a", "--date", "2024-01-01T00:00:00Z"],
+        );
+        fs::write(&file, "one\n2\nthree\n").unwrap();
+
+        let lines = blame(&file).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].author, "Ana");
+        assert_eq!(lines[0].time.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert_eq!((lines[0].line, lines[0].text.as_str()), (1, "one"));
+        assert_eq!(lines[1].commit, "00000000");
+        assert_eq!((lines[2].line, lines[2].text.as_str()), (3, "three"));
+    }
+
+    #[test]
+    fn test_log() {
+        let temp = TempDir::new().unwrap();
+        let repo = temp.path().canonicalize().unwrap();
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["config", "user.email", "t@example.com"]);
+        git(&repo, &["config", "user.name", "Ana"]);
+
+        let file = repo.join("a.md");
+        let commit = |message: &str, date: &str| {
+            git(&repo, &["add", "-A"]);
+            git(&repo, &["commit", "-q", "-m", message, "--date", date]);
+        };
+        fs::write(&file, "one\n").unwrap();
+        commit("Add a", "2024-01-01T00:00:00Z");
+        fs::write(repo.join("b.md"), "other\n").unwrap();
+        commit("Add b", "2024-02-01T00:00:00Z");
+        fs::write(&file, "two\n").unwrap();
+        commit("Edit a: more", "2024-03-01T00:00:00Z");
+
+        let commits = log(&file).unwrap();
+        let subjects: Vec<&str> = commits.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, ["Edit a: more", "Add a"]);
+        assert_eq!(commits[0].author, "Ana");
+        assert_eq!(commits[1].time.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+    }
+}
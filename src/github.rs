@@ -0,0 +1,558 @@
+//! Importing GitHub issue and pull request discussions
+//! (`mem import github`), and bringing them up to date (`mem refresh`).
+//!
+//! A thread becomes one mem: the description, then every comment, review,
+//! and review comment in order. The mem records the thread's URL as
+//! `source`, which is how `refresh` finds what to fetch again (see
+//! [`crate::refresh`]).
+//!
+//! Requests go to `GITHUB_API_URL` (default `https://api.github.com`),
+//! authenticated with `GITHUB_TOKEN` or `GH_TOKEN` when set.
+
+use crate::mem::{slugify, Mem};
+use crate::refresh;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Default API endpoint.
+const API_URL: &str = "https://api.github.com";
+
+/// Items per page of a list request (the API's maximum).
+const PAGE_SIZE: usize = 100;
+
+/// Pages read from one list before giving up.
+const MAX_PAGES: usize = 50;
+
+#[derive(Debug, Clone, Deserialize)]
+struct User {
+    login: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Label {
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Milestone {
+    number: u64,
+    title: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PullRequest {
+    merged_at: Option<DateTime<Utc>>,
+}
+
+/// An issue or pull request as the issues API returns it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub html_url: String,
+    state: String,
+    user: Option<User>,
+    body: Option<String>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    milestone: Option<Milestone>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    pull_request: Option<PullRequest>,
+}
+
+/// A comment, review, or review comment.
+#[derive(Debug, Clone, Deserialize)]
+struct Comment {
+    user: Option<User>,
+    body: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+
+    /// Reviews have a submission time and a verdict instead
+    submitted_at: Option<DateTime<Utc>>,
+    state: Option<String>,
+
+    /// File a review comment is on
+    path: Option<String>,
+}
+
+/// One entry of a discussion, ready to render.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    author: String,
+    time: DateTime<Utc>,
+
+    /// What kind of entry it is beyond a plain comment, e.g. "approved"
+    note: Option<String>,
+    body: String,
+}
+
+/// An issue or pull request with its discussion, oldest first.
+#[derive(Debug, Clone)]
+pub struct Thread {
+    pub issue: Issue,
+    entries: Vec<Entry>,
+}
+
+/// A repository as `owner/name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repo {
+    pub owner: String,
+    pub name: String,
+}
+
+impl std::str::FromStr for Repo {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim_end_matches('/').split_once('/') {
+            Some((owner, name)) if !owner.is_empty() && !name.is_empty() && !name.contains('/') => {
+                Ok(Repo {
+                    owner: owner.to_string(),
+                    name: name.to_string(),
+                })
+            }
+            _ => Err(anyhow!("invalid repository '{s}': expected owner/name")),
+        }
+    }
+}
+
+/// The repository and number of a GitHub issue or pull request URL, e.g.
+/// `https://github.com/owner/name/pull/12`.
+pub fn parse_url(url: &str) -> Option<(Repo, u64)> {
+    let rest = url.split_once("://")?.1;
+    let parts: Vec<&str> = rest.split(['/', '#', '?']).collect();
+    match parts[..] {
+        [_, owner, name, "issues" | "pull", number, ..] => Some((
+            Repo {
+                owner: owner.to_string(),
+                name: name.to_string(),
+            },
+            number.parse().ok()?,
+        )),
+        _ => None,
+    }
+}
+
+/// Which issues to import by label or milestone.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub label: Option<String>,
+
+    /// Milestone title or number
+    pub milestone: Option<String>,
+}
+
+/// A GitHub API client.
+pub struct Client {
+    base: String,
+    token: Option<String>,
+}
+
+impl Client {
+    /// A client for `GITHUB_API_URL`, with the token from the environment.
+    pub fn from_env() -> Self {
+        let base = std::env::var("GITHUB_API_URL").unwrap_or_else(|_| API_URL.to_string());
+        let token = std::env::var("GITHUB_TOKEN")
+            .or_else(|_| std::env::var("GH_TOKEN"))
+            .ok()
+            .filter(|t| !t.is_empty());
+        Self::new(&base, token)
+    }
+
+    pub fn new(base: &str, token: Option<String>) -> Self {
+        Self {
+            base: base.trim_end_matches('/').to_string(),
+            token,
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let url = format!("{}{path}", self.base);
+        let mut request = ureq::get(&url)
+            .set("Accept", "application/vnd.github+json")
+            .set("User-Agent", "mem");
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {token}"));
+        }
+        request
+            .call()
+            .map_err(|e| match e {
+                ureq::Error::Status(404, _) => anyhow!("not found on GitHub: {path}"),
+                e => anyhow!("GitHub request failed: {e}"),
+            })?
+            .into_json()
+            .with_context(|| format!("invalid GitHub response for {path}"))
+    }
+
+    /// Every item of a list, page by page.
+    fn get_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        for page in 1..=MAX_PAGES {
+            let batch: Vec<T> = self.get(&format!(
+                "{path}{separator}per_page={PAGE_SIZE}&page={page}"
+            ))?;
+            let done = batch.len() < PAGE_SIZE;
+            items.extend(batch);
+            if done {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Fetch an issue or pull request with its whole discussion.
+    pub fn thread(&self, repo: &Repo, number: u64) -> Result<Thread> {
+        let prefix = format!("/repos/{}/{}", repo.owner, repo.name);
+        let issue: Issue = self.get(&format!("{prefix}/issues/{number}"))?;
+        let mut comments: Vec<(Comment, Option<String>)> = self
+            .get_all(&format!("{prefix}/issues/{number}/comments"))?
+            .into_iter()
+            .map(|c| (c, None))
+            .collect();
+        if issue.pull_request.is_some() {
+            for review in self.get_all::<Comment>(&format!("{prefix}/pulls/{number}/reviews"))? {
+                let verdict = match review.state.as_deref() {
+                    Some("APPROVED") => "approved",
+                    Some("CHANGES_REQUESTED") => "requested changes",
+                    Some("DISMISSED") => "review dismissed",
+                    _ => "reviewed",
+                };
+                comments.push((review, Some(verdict.to_string())));
+            }
+            for comment in self.get_all::<Comment>(&format!("{prefix}/pulls/{number}/comments"))? {
+                let note = comment.path.as_ref().map(|path| format!("on `{path}`"));
+                comments.push((comment, note));
+            }
+        }
+        Ok(Thread::new(issue, comments))
+    }
+
+    /// Numbers of the issues and pull requests matching `filter`.
+    pub fn matching(&self, repo: &Repo, filter: &Filter) -> Result<Vec<u64>> {
+        let prefix = format!("/repos/{}/{}", repo.owner, repo.name);
+        let mut query = vec!["state=all".to_string()];
+        if let Some(label) = &filter.label {
+            query.push(format!("labels={}", encode(label)));
+        }
+        if let Some(milestone) = &filter.milestone {
+            let number = match milestone.parse::<u64>() {
+                Ok(number) => number,
+                Err(_) => self
+                    .get_all::<Milestone>(&format!("{prefix}/milestones?state=all"))?
+                    .into_iter()
+                    .find(|m| m.title == *milestone)
+                    .map(|m| m.number)
+                    .ok_or_else(|| {
+                        anyhow!("no milestone '{milestone}' in {}/{}", repo.owner, repo.name)
+                    })?,
+            };
+            query.push(format!("milestone={number}"));
+        }
+        let issues: Vec<Issue> = self.get_all(&format!("{prefix}/issues?{}", query.join("&")))?;
+        Ok(issues.into_iter().map(|i| i.number).collect())
+    }
+}
+
+/// Percent-encode a query value.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+impl Thread {
+    fn new(issue: Issue, comments: Vec<(Comment, Option<String>)>) -> Self {
+        let mut entries: Vec<Entry> = comments
+            .into_iter()
+            .filter_map(|(comment, note)| {
+                let time = comment.created_at.or(comment.submitted_at)?;
+                let body = comment.body.unwrap_or_default().trim().to_string();
+                // A review with nothing to say but "commented" adds nothing
+                if body.is_empty() && note.as_deref().is_none_or(|n| n == "reviewed") {
+                    return None;
+                }
+                Some(Entry {
+                    author: comment
+                        .user
+                        .map_or_else(|| "ghost".to_string(), |u| u.login),
+                    time,
+                    note,
+                    body,
+                })
+            })
+            .collect();
+        entries.sort_by_key(|e| e.time);
+        Thread { issue, entries }
+    }
+
+    /// `open`, `closed`, or `merged`.
+    fn state(&self) -> &str {
+        match &self.issue.pull_request {
+            Some(PullRequest { merged_at: Some(_) }) => "merged",
+            _ => &self.issue.state,
+        }
+    }
+
+    /// Default mem name: the number and a slug of the title.
+    pub fn default_path(&self) -> String {
+        format!("{}-{}", self.issue.number, slugify(&self.issue.title))
+    }
+
+    /// The thread as a mem at `path`, synced at `now`.
+    pub fn to_mem(&self, path: &str, now: DateTime<Utc>) -> Mem {
+        let issue = &self.issue;
+        let mut content = match issue.body.as_deref().map(str::trim) {
+            Some(body) if !body.is_empty() => format!("{body}\n"),
+            _ => "_No description._\n".to_string(),
+        };
+        if !self.entries.is_empty() {
+            content.push_str("\n## Discussion\n");
+            for entry in &self.entries {
+                content.push_str(&format!(
+                    "\n**@{}** · {}",
+                    entry.author,
+                    entry.time.format("%Y-%m-%d %H:%M")
+                ));
+                if let Some(note) = &entry.note {
+                    content.push_str(&format!(" · {note}"));
+                }
+                content.push('\n');
+                if !entry.body.is_empty() {
+                    content.push_str(&format!("\n{}\n", entry.body));
+                }
+            }
+        }
+
+        let mut mem = Mem::new(
+            PathBuf::from(path),
+            format!("{} (#{})", issue.title, issue.number),
+            content,
+        );
+        mem.created_at = issue.created_at;
+        mem.updated_at = issue.updated_at;
+        self.set_fields(&mut mem);
+        refresh::mark_synced(&mut mem, now);
+        mem
+    }
+
+    /// Set the fields recording where the mem came from and the thread's
+    /// state.
+    fn set_fields(&self, mem: &mut Mem) {
+        let issue = &self.issue;
+        mem.set_field("source", issue.html_url.as_str());
+        mem.set_field("state", self.state());
+        if let Some(user) = &issue.user {
+            mem.set_field("author", user.login.as_str());
+        }
+        if let Some(milestone) = &issue.milestone {
+            mem.set_field("milestone", milestone.title.as_str());
+        }
+        if !issue.labels.is_empty() {
+            mem.extra.insert(
+                "labels".to_string(),
+                serde_yaml::Value::Sequence(
+                    issue
+                        .labels
+                        .iter()
+                        .map(|l| serde_yaml::Value::String(l.name.clone()))
+                        .collect(),
+                ),
+            );
+        }
+    }
+
+    /// `mem` brought up to date with the thread: its content, title,
+    /// timestamps, and fields replaced, its id, tags, and other fields kept.
+    pub fn refresh(&self, mem: &Mem, now: DateTime<Utc>) -> Mem {
+        let fresh = self.to_mem(&mem.path_str(), now);
+        let mut refreshed = mem.clone();
+        refreshed.title = fresh.title;
+        refreshed.content = fresh.content;
+        refreshed.updated_at = fresh.updated_at.max(mem.updated_at);
+        refreshed.extra.remove("labels");
+        refreshed.extra.remove("milestone");
+        refreshed.extra.extend(fresh.extra);
+        refreshed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    const ISSUE: &str = r#"{
+        "number": 7, "title": "Use SQS for jobs", "state": "closed",
+        "html_url": "https://github.com/acme/api/pull/7",
+        "user": {"login": "ana"}, "body": "Proposal: move jobs to SQS.",
+        "labels": [{"name": "decision"}], "milestone": {"number": 2, "title": "v2"},
+        "created_at": "2024-03-01T10:00:00Z", "updated_at": "2024-03-04T10:00:00Z",
+        "pull_request": {"merged_at": "2024-03-04T10:00:00Z"}
+    }"#;
+
+    /// Serve canned JSON by request path (query included) until the test
+    /// ends. Returns the base URL.
+    fn serve(routes: Vec<(&'static str, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(&stream);
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                }
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match routes.iter().find(|(route, _)| *route == path) {
+                    Some((_, body)) => ("200 OK", body.as_str()),
+                    None => ("404 Not Found", "{}"),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        base
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "acme/api".parse::<Repo>().unwrap(),
+            Repo {
+                owner: "acme".to_string(),
+                name: "api".to_string()
+            }
+        );
+        assert!("acme".parse::<Repo>().is_err());
+        assert!("acme/api/x".parse::<Repo>().is_err());
+
+        let (repo, number) = parse_url("https://github.com/acme/api/pull/7#discussion").unwrap();
+        assert_eq!((repo.name.as_str(), number), ("api", 7));
+        assert_eq!(
+            parse_url("https://github.com/acme/api/issues/12")
+                .unwrap()
+                .1,
+            12
+        );
+        assert_eq!(parse_url("https://example.com/a"), None);
+        assert_eq!(encode("needs review"), "needs%20review");
+    }
+
+    #[test]
+    fn test_thread_from_api() {
+        let base = serve(vec![
+            ("/repos/acme/api/issues/7", ISSUE.to_string()),
+            (
+                "/repos/acme/api/issues/7/comments?per_page=100&page=1",
+                r#"[{"user": {"login": "bo"}, "body": "What about retries?", "created_at": "2024-03-02T09:00:00Z"}]"#
+                .to_string(),
+            ),
+            (
+                "/repos/acme/api/pulls/7/reviews?per_page=100&page=1",
+                r#"[{"user": {"login": "cy"}, "body": "", "state": "APPROVED", "submitted_at": "2024-03-03T12:00:00Z"},
+                    {"user": {"login": "bo"}, "body": "", "state": "COMMENTED", "submitted_at": "2024-03-02T09:30:00Z"}]"#
+                .to_string(),
+            ),
+            (
+                "/repos/acme/api/pulls/7/comments?per_page=100&page=1",
+                r#"[{"user": {"login": "bo"}, "body": "Visibility timeout of 5m.", "path": "jobs/sqs.rs", "created_at": "2024-03-02T09:30:00Z"}]"#
+                .to_string(),
+            ),
+        ]);
+        let client = Client::new(&base, None);
+        let repo: Repo = "acme/api".parse().unwrap();
+        let thread = client.thread(&repo, 7).unwrap();
+        assert_eq!(thread.default_path(), "7-use-sqs-for-jobs");
+
+        let mem = thread.to_mem("github/api/7", at("2024-05-01T00:00:00Z"));
+        assert_eq!(mem.title, "Use SQS for jobs (#7)");
+        assert_eq!(mem.created_at, at("2024-03-01T10:00:00Z"));
+        assert_eq!(
+            mem.field("source"),
+            Some("https://github.com/acme/api/pull/7")
+        );
+        assert_eq!(mem.field("state"), Some("merged"));
+        assert_eq!(mem.field("milestone"), Some("v2"));
+        assert_eq!(
+            mem.content,
+            "Proposal: move jobs to SQS.\n\n## Discussion\n\
+             \n**@bo** · 2024-03-02 09:00\n\nWhat about retries?\n\
+             \n**@bo** · 2024-03-02 09:30 · on `jobs/sqs.rs`\n\nVisibility timeout of 5m.\n\
+             \n**@cy** · 2024-03-03 12:00 · approved\n"
+        );
+
+        let err = client.thread(&repo, 8).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "not found on GitHub: /repos/acme/api/issues/8"
+        );
+    }
+
+    #[test]
+    fn test_matching_and_refresh() {
+        let base = serve(vec![
+            (
+                "/repos/acme/api/milestones?state=all&per_page=100&page=1",
+                r#"[{"number": 2, "title": "v2"}]"#.to_string(),
+            ),
+            (
+                "/repos/acme/api/issues?state=all&labels=decision&milestone=2&per_page=100&page=1",
+                format!("[{ISSUE}]"),
+            ),
+        ]);
+        let client = Client::new(&base, None);
+        let filter = Filter {
+            label: Some("decision".to_string()),
+            milestone: Some("v2".to_string()),
+        };
+        let repo = "acme/api".parse().unwrap();
+        assert_eq!(client.matching(&repo, &filter).unwrap(), [7]);
+        let missing = Filter {
+            milestone: Some("v9".to_string()),
+            ..Filter::default()
+        };
+        assert!(client.matching(&repo, &missing).is_err());
+
+        let issue: Issue = serde_json::from_str(ISSUE).unwrap();
+        let thread = Thread::new(issue, Vec::new());
+        let mut mem = thread.to_mem("a", at("2024-05-01T00:00:00Z"));
+        mem.tags = vec!["queues".to_string()];
+        mem.set_field("owner", "ana");
+        mem.content = "old".to_string();
+        let refreshed = thread.refresh(&mem, at("2024-06-01T00:00:00Z"));
+        assert_eq!(refreshed.id, mem.id);
+        assert_eq!(refreshed.tags, mem.tags);
+        assert_eq!(refreshed.owner(), Some("ana"));
+        assert_eq!(refreshed.content, "Proposal: move jobs to SQS.\n");
+        assert_eq!(
+            refreshed.field("synced-at"),
+            Some("2024-06-01T00:00:00+00:00")
+        );
+    }
+}
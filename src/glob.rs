@@ -0,0 +1,62 @@
+//! Shell-style path patterns for picking several mems at once, e.g.
+//! `runbooks/*` or `**/deploy-*`.
+//!
+//! `*` matches any run of characters within one path segment, `?` one
+//! character, and a `**` segment any number of segments.
+
+/// Whether `path` contains pattern characters, rather than naming one mem.
+pub fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+/// Whether the mem path `path` matches `pattern`.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    matches_segments(&pattern, &path)
+}
+
+fn matches_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches_segments(rest, &path[skip..])),
+        Some((first, rest)) => path.split_first().is_some_and(|(segment, path)| {
+            matches_segment(
+                &first.chars().collect::<Vec<_>>(),
+                &segment.chars().collect::<Vec<_>>(),
+            ) && matches_segments(rest, path)
+        }),
+    }
+}
+
+fn matches_segment(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| matches_segment(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && matches_segment(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && matches_segment(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("runbooks/*", "runbooks/deploy"));
+        assert!(!matches("runbooks/*", "runbooks/db/restore"));
+        assert!(matches("runbooks/**", "runbooks/db/restore"));
+        assert!(matches("**/deploy-*", "ops/runbooks/deploy-web"));
+        assert!(matches("**/deploy-*", "deploy-api"));
+        assert!(matches("notes/day-?", "notes/day-1"));
+        assert!(!matches("notes/day-?", "notes/day-10"));
+        assert!(!matches("runbooks/*", "notes/deploy"));
+    }
+
+    #[test]
+    fn test_is_pattern() {
+        assert!(is_pattern("runbooks/*"));
+        assert!(!is_pattern("runbooks/deploy"));
+    }
+}
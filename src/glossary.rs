@@ -0,0 +1,219 @@
+//! Glossaries: mems of kind `glossary`, or under `glossary/`, defining the
+//! terms and acronyms a team uses (`mem def`).
+//!
+//! A glossary mem defines a term with a heading followed by text, or with a
+//! line starting `**Term**:` (also `-` or `—` after the bold term). A mem
+//! under `glossary/` with neither defines its title.
+
+use crate::dictionary;
+use crate::kind;
+use crate::links;
+use crate::mem::Mem;
+use crate::structure;
+use serde::Serialize;
+
+/// `kind` of a glossary mem.
+pub const GLOSSARY_KIND: &str = "glossary";
+
+/// Directory whose mems are glossaries whatever their kind.
+pub const GLOSSARY_DIR: &str = "glossary";
+
+/// A term defined in a glossary.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Term {
+    /// The term as written, e.g. `RTO (Recovery Time Objective)`
+    pub term: String,
+    pub definition: String,
+
+    /// Mem defining the term
+    pub path: String,
+
+    /// 1-based line of the definition within the content
+    pub line: usize,
+
+    #[serde(skip)]
+    id: Option<String>,
+}
+
+impl Term {
+    /// Names the term goes by: the whole term, and for `RTO (Recovery Time
+    /// Objective)` both `RTO` and `Recovery Time Objective`.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = vec![self.term.as_str()];
+        if let Some((short, rest)) = self.term.split_once(" (") {
+            names.push(short.trim());
+            if let Some(long) = rest.strip_suffix(')') {
+                names.push(long.trim());
+            }
+        }
+        names
+    }
+
+    /// Whether `mem` links to the mem defining this term, by path or id.
+    pub fn is_linked_from(&self, mem: &Mem) -> bool {
+        let by_id = self
+            .id
+            .as_ref()
+            .map(|id| format!("{}{}", crate::id::ID_PREFIX, id.to_uppercase()));
+        links::targets(mem)
+            .iter()
+            .any(|t| *t == self.path || Some(t) == by_id.as_ref())
+    }
+}
+
+/// Whether a mem is a glossary.
+pub fn is_glossary(mem: &Mem) -> bool {
+    kind::of(mem) == GLOSSARY_KIND
+        || mem
+            .path
+            .to_string_lossy()
+            .starts_with(&format!("{GLOSSARY_DIR}/"))
+}
+
+/// Terms a glossary mem defines, in document order.
+pub fn terms(mem: &Mem) -> Vec<Term> {
+    let path = mem.path_str();
+    let term = |term: &str, definition: String, line| Term {
+        term: term.to_string(),
+        definition,
+        path: path.clone(),
+        line,
+        id: mem.id.clone(),
+    };
+    let lines: Vec<&str> = mem.content.lines().collect();
+    let headings = structure::headings(&mem.content);
+
+    let mut terms = Vec::new();
+    for (line, text) in structure::prose_lines(&mem.content) {
+        let text = text.trim_start();
+        let text = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| text.strip_prefix(marker))
+            .unwrap_or(text);
+        let Some((name, rest)) = text
+            .strip_prefix("**")
+            .or_else(|| text.strip_prefix("__"))
+            .and_then(|rest| rest.split_once("**").or_else(|| rest.split_once("__")))
+        else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        let definition = rest
+            .strip_prefix(':')
+            .or_else(|| rest.strip_prefix('-'))
+            .or_else(|| rest.strip_prefix('—'))
+            .map(str::trim);
+        if let Some(definition) = definition.filter(|d| !d.is_empty()) {
+            terms.push(term(name.trim(), definition.to_string(), line));
+        }
+    }
+    for (i, heading) in headings.iter().enumerate() {
+        // The text up to the next heading or bold term; a heading without
+        // text only groups the terms under it
+        let end = headings
+            .get(i + 1)
+            .map(|h| h.line)
+            .into_iter()
+            .chain(terms.iter().map(|t| t.line))
+            .filter(|&line| line > heading.line)
+            .min()
+            .map_or(lines.len(), |line| line - 1);
+        let definition = lines[heading.line..end].join("\n").trim().to_string();
+        if !definition.is_empty() {
+            terms.push(term(&heading.text, definition, heading.line));
+        }
+    }
+    terms.sort_by_key(|t| t.line);
+
+    if terms.is_empty() && path.starts_with(&format!("{GLOSSARY_DIR}/")) {
+        let definition = mem.content.trim();
+        if !definition.is_empty() {
+            terms.push(term(&mem.title, definition.to_string(), 1));
+        }
+    }
+    terms
+}
+
+/// Terms defined by a store's glossary mems.
+pub fn all_terms(mems: &[Mem]) -> Vec<Term> {
+    mems.iter()
+        .filter(|m| is_glossary(m))
+        .flat_map(terms)
+        .collect()
+}
+
+/// Terms one of whose names is `query`, compared case-insensitively.
+pub fn lookup<'a>(terms: &'a [Term], query: &str) -> Vec<&'a Term> {
+    let query = query.trim();
+    terms
+        .iter()
+        .filter(|t| t.names().iter().any(|n| n.eq_ignore_ascii_case(query)))
+        .collect()
+}
+
+/// The first use of a term in content, as a whole word with the case it is
+/// defined in, outside code: the name used and its 1-based line.
+pub fn first_use<'a>(term: &'a Term, content: &str) -> Option<(&'a str, usize)> {
+    for (line, text) in structure::prose_lines(content) {
+        let code = dictionary::code_spans(text);
+        for name in term.names() {
+            let used = text.match_indices(name).any(|(start, _)| {
+                let range = start..start + name.len();
+                dictionary::is_word(text, &range) && !code.iter().any(|c| c.contains(&start))
+            });
+            if used {
+                return Some((name, line));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn glossary() -> Mem {
+        Mem::new(
+            PathBuf::from("ops/terms"),
+            "Terms".to_string(),
+            "# Glossary\n\n## RTO (Recovery Time Objective)\n\nHow long a restore may take.\n\n\
+             - **SLO**: Service level objective.\n- **Toil** — Manual, repetitive work.\n"
+                .to_string(),
+        )
+    }
+
+    #[test]
+    fn test_terms() {
+        let terms = terms(&glossary());
+        let names: Vec<&str> = terms.iter().map(|t| t.term.as_str()).collect();
+        assert_eq!(names, ["RTO (Recovery Time Objective)", "SLO", "Toil"]);
+        assert_eq!(terms[0].definition, "How long a restore may take.");
+        assert_eq!(terms[1].definition, "Service level objective.");
+        assert_eq!(terms[1].line, 7);
+
+        let single = Mem::new(
+            PathBuf::from("glossary/mttr"),
+            "MTTR".to_string(),
+            "Mean time to recovery.".to_string(),
+        );
+        assert_eq!(super::terms(&single)[0].term, "MTTR");
+    }
+
+    #[test]
+    fn test_lookup() {
+        let terms = terms(&glossary());
+        assert_eq!(lookup(&terms, "rto").len(), 1);
+        assert_eq!(lookup(&terms, "recovery time objective").len(), 1);
+        assert!(lookup(&terms, "RPO").is_empty());
+    }
+
+    #[test]
+    fn test_first_use() {
+        let terms = terms(&glossary());
+        let content = "Our `RTO` is short.\n\nThe RTO is 4h; see BRTO.\n";
+        assert_eq!(first_use(&terms[0], content), Some(("RTO", 3)));
+        assert_eq!(first_use(&terms[1], "slo is lowercase"), None);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::text;
+use std::ops::Range;
+
+/// ANSI escapes used to highlight matches on a terminal.
+pub const ANSI_START: &str = "\x1b[1;33m";
+pub const ANSI_END: &str = "\x1b[0m";
+
+/// ANSI escape that greys out text, ended by `ANSI_END`.
+pub const ANSI_DIM: &str = "\x1b[2m";
+
+/// Byte ranges of the non-overlapping, case-insensitive occurrences of
+/// `query` in `text`.
+pub fn find_matches(text: &str, query: &str) -> Vec<Range<usize>> {
+    let needle: Vec<char> = query.chars().flat_map(text::fold_char).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    let mut next = 0;
+    for (start, _) in text.char_indices() {
+        if start < next {
+            continue;
+        }
+        let mut matched = 0;
+        for (offset, c) in text[start..].char_indices() {
+            let lower = text::fold_char(c);
+            if !needle[matched..].starts_with(&lower) {
+                break;
+            }
+            matched += lower.len();
+            if matched == needle.len() {
+                next = start + offset + c.len_utf8();
+                matches.push(start..next);
+                break;
+            }
+        }
+    }
+    matches
+}
+
+/// Byte ranges of the occurrences of any of `terms` in `text`, in order,
+/// preferring the longer of overlapping matches.
+pub fn find_all(text: &str, terms: &[&str]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = terms.iter().flat_map(|t| find_matches(text, t)).collect();
+    ranges.sort_by_key(|r| (r.start, std::cmp::Reverse(r.end)));
+    let mut matches: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        if matches.last().is_none_or(|last| range.start >= last.end) {
+            matches.push(range);
+        }
+    }
+    matches
+}
+
+/// Wrap each occurrence of any of `terms` in `text` with `before` and
+/// `after`.
+pub fn highlight(text: &str, terms: &[&str], before: &str, after: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for range in find_all(text, terms) {
+        out.push_str(&text[last..range.start]);
+        out.push_str(before);
+        out.push_str(&text[range.clone()]);
+        out.push_str(after);
+        last = range.end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// The first line of `text` containing any of `terms`, trimmed and cut to
+/// about `width` characters around the first match.
+pub fn snippet(text: &str, terms: &[&str], width: usize) -> Option<String> {
+    let (line, range) = text.lines().find_map(|line| {
+        let line = line.trim();
+        find_all(line, terms).first().map(|r| (line, r.clone()))
+    })?;
+    let chars = line.chars().count();
+    if chars <= width {
+        return Some(line.to_string());
+    }
+
+    // Start a third of the way before the match, so context on both sides shows
+    let match_start = line[..range.start].chars().count();
+    let start = match_start.saturating_sub(width / 3).min(chars - width);
+    let mut out: String = line.chars().skip(start).take(width).collect();
+    if start > 0 {
+        out.insert(0, '…');
+    }
+    if start + width < chars {
+        out.push('…');
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_matches() {
+        assert_eq!(find_matches("Rust and rust", "RUST"), vec![0..4, 9..13]);
+        assert_eq!(find_matches("aaaa", "aa"), vec![0..2, 2..4]);
+        assert_eq!(find_matches("Ünïcode ünï", "ÜNÏ"), vec![0..5, 10..15]);
+        assert_eq!(
+            find_matches("cafe\u{301} caf\u{e9}", "CAFÉ"),
+            vec![0..6, 7..12]
+        );
+        assert_eq!(find_matches("Straße", "strasse"), vec![0..7]);
+        assert!(find_matches("text", "").is_empty());
+        assert!(find_matches("te", "text").is_empty());
+    }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("Rotate the Postgres keys", &["postgres"], "[", "]"),
+            "Rotate the [Postgres] keys"
+        );
+        assert_eq!(
+            highlight(
+                "Rotate the Postgres keys",
+                &["key", "rotate", "keys"],
+                "[",
+                "]"
+            ),
+            "[Rotate] the Postgres [keys]"
+        );
+        assert_eq!(highlight("no match", &["x"], "[", "]"), "no match");
+    }
+
+    #[test]
+    fn test_snippet() {
+        let text = "# Title\n\n  Short line with postgres  \nother postgres";
+        assert_eq!(
+            snippet(text, &["postgres"], 80).as_deref(),
+            Some("Short line with postgres")
+        );
+        assert_eq!(snippet(text, &["mysql"], 80), None);
+        assert_eq!(
+            snippet(text, &["other", "line"], 80).as_deref(),
+            Some("Short line with postgres")
+        );
+
+        let long = format!("{} needle {}", "a".repeat(50), "b".repeat(50));
+        assert_eq!(
+            snippet(&long, &["needle"], 20).as_deref(),
+            Some(format!("…{} needle {}…", "a".repeat(5), "b".repeat(7)).as_str())
+        );
+    }
+}
@@ -0,0 +1,553 @@
+//! Minimal HTML to markdown conversion for clipping web pages, and escaping
+//! text for the HTML pages mem renders.
+//!
+//! This is deliberately forgiving rather than spec-complete: it handles the
+//! common content elements (headings, paragraphs, links, lists, code, quotes,
+//! emphasis, images) and drops scripts, styles, and other non-content markup.
+
+/// Elements whose content is never rendered.
+const SKIPPED: &[&str] = &[
+    "script", "style", "noscript", "svg", "template", "iframe", "head", "nav", "footer", "form",
+];
+
+/// Elements that start a new block.
+const BLOCKS: &[&str] = &[
+    "p", "div", "section", "article", "main", "header", "aside", "table", "tr", "figure", "dl",
+    "dt", "dd",
+];
+
+/// A converted page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    /// Contents of `<title>`, falling back to the first `<h1>`
+    pub title: Option<String>,
+
+    /// Markdown body
+    pub markdown: String,
+}
+
+struct Tag {
+    name: String,
+    closing: bool,
+    attrs: Vec<(String, String)>,
+}
+
+impl Tag {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Markdown writer tracking block and inline state.
+struct Writer<'a> {
+    out: String,
+    base_url: &'a str,
+    quote_depth: usize,
+    pre: bool,
+    lists: Vec<Option<usize>>,
+    links: Vec<Option<String>>,
+    skip: Option<(String, usize)>,
+    title: Option<String>,
+    in_title: bool,
+    first_h1: Option<String>,
+    h1_start: Option<usize>,
+}
+
+impl Writer<'_> {
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n') || self.out.ends_with("> ")
+    }
+
+    fn newline(&mut self) {
+        self.out.truncate(self.out.trim_end_matches(' ').len());
+        self.out.push('\n');
+        for _ in 0..self.quote_depth {
+            self.out.push_str("> ");
+        }
+    }
+
+    fn ensure_line(&mut self) {
+        if !self.at_line_start() {
+            self.newline();
+        }
+    }
+
+    fn blank_line(&mut self) {
+        if self.out.is_empty() {
+            return;
+        }
+        self.ensure_line();
+        let trimmed = self.out.trim_end_matches(['>', ' ']);
+        if !trimmed.ends_with("\n\n") && !trimmed.is_empty() {
+            self.newline();
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.in_title {
+            self.title
+                .get_or_insert_with(String::new)
+                .push_str(&collapse(text));
+            return;
+        }
+        if self.pre {
+            self.out.push_str(text);
+            return;
+        }
+        let collapsed = collapse(text);
+        let collapsed = if self.at_line_start() || self.out.ends_with(' ') {
+            collapsed.trim_start()
+        } else {
+            collapsed.as_str()
+        };
+        self.out.push_str(collapsed);
+    }
+
+    fn tag(&mut self, tag: &Tag) {
+        let name = tag.name.as_str();
+
+        if let Some((skipped, depth)) = &mut self.skip {
+            if name == skipped {
+                if tag.closing {
+                    *depth -= 1;
+                    if *depth == 0 {
+                        self.skip = None;
+                    }
+                } else {
+                    *depth += 1;
+                }
+            }
+            // The page title lives in <head>
+            if name == "title" {
+                self.in_title = !tag.closing;
+            }
+            return;
+        }
+
+        if SKIPPED.contains(&name) && !tag.closing {
+            self.skip = Some((name.to_string(), 1));
+            return;
+        }
+
+        match (name, tag.closing) {
+            ("title", closing) => self.in_title = !closing,
+            (h, false) if is_heading(h) => {
+                self.blank_line();
+                let level = h[1..].parse::<usize>().unwrap_or(1);
+                self.out.push_str(&"#".repeat(level));
+                self.out.push(' ');
+                if level == 1 && self.first_h1.is_none() {
+                    self.h1_start = Some(self.out.len());
+                }
+            }
+            (h, true) if is_heading(h) => {
+                if let Some(start) = self.h1_start.take() {
+                    self.first_h1 = Some(self.out[start..].trim().to_string());
+                }
+                self.blank_line();
+            }
+            ("br", _) => self.newline(),
+            ("hr", _) => {
+                self.blank_line();
+                self.out.push_str("---");
+                self.blank_line();
+            }
+            ("pre", false) => {
+                self.blank_line();
+                self.out.push_str("```\n");
+                self.pre = true;
+            }
+            ("pre", true) => {
+                self.pre = false;
+                if !self.out.ends_with('\n') {
+                    self.out.push('\n');
+                }
+                self.out.push_str("```");
+                self.blank_line();
+            }
+            ("code", _) if !self.pre => self.out.push('`'),
+            ("strong" | "b", _) => self.out.push_str("**"),
+            ("em" | "i", _) => self.out.push('*'),
+            ("blockquote", false) => {
+                self.blank_line();
+                self.quote_depth += 1;
+                self.out.push_str("> ");
+            }
+            ("blockquote", true) => {
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.blank_line();
+            }
+            ("ul", false) => {
+                self.ensure_line();
+                self.lists.push(None);
+            }
+            ("ol", false) => {
+                self.ensure_line();
+                self.lists.push(Some(0));
+            }
+            ("ul" | "ol", true) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            }
+            ("li", false) => {
+                self.ensure_line();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{n}. ")
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.out.push_str(&indent);
+                self.out.push_str(&marker);
+            }
+            ("a", false) => {
+                let href = tag
+                    .attr("href")
+                    .filter(|h| !h.starts_with('#') && !h.starts_with("javascript:"))
+                    .map(|h| resolve_url(self.base_url, h));
+                if href.is_some() {
+                    self.out.push('[');
+                }
+                self.links.push(href);
+            }
+            ("a", true) => {
+                if let Some(Some(href)) = self.links.pop() {
+                    self.out.push_str(&format!("]({href})"));
+                }
+            }
+            ("img", _) => {
+                if let Some(src) = tag.attr("src") {
+                    let alt = tag.attr("alt").unwrap_or("");
+                    let src = resolve_url(self.base_url, src);
+                    self.out.push_str(&format!("![{alt}]({src})"));
+                }
+            }
+            ("td" | "th", false) if !self.at_line_start() => self.out.push_str(" | "),
+            (block, _) if BLOCKS.contains(&block) => self.blank_line(),
+            _ => {}
+        }
+    }
+}
+
+/// Convert an HTML document to markdown, resolving relative links against `base_url`.
+pub fn to_markdown(html: &str, base_url: &str) -> Page {
+    let mut writer = Writer {
+        out: String::new(),
+        base_url,
+        quote_depth: 0,
+        pre: false,
+        lists: Vec::new(),
+        links: Vec::new(),
+        skip: None,
+        title: None,
+        in_title: false,
+        first_h1: None,
+        h1_start: None,
+    };
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        match rest.find('<') {
+            Some(0) => {
+                if let Some(after) = rest.strip_prefix("<!--") {
+                    rest = after.find("-->").map(|i| &after[i + 3..]).unwrap_or("");
+                    continue;
+                }
+                let end = match rest.find('>') {
+                    Some(end) => end,
+                    None => break,
+                };
+                if let Some(tag) = parse_tag(&rest[1..end]) {
+                    writer.tag(&tag);
+                }
+                rest = &rest[end + 1..];
+            }
+            Some(next) => {
+                let text = decode_entities(&rest[..next]);
+                if writer.skip.is_none() || writer.in_title {
+                    writer.text(&text);
+                }
+                rest = &rest[next..];
+            }
+            None => {
+                if writer.skip.is_none() {
+                    writer.text(&decode_entities(rest));
+                }
+                break;
+            }
+        }
+    }
+
+    let title = writer
+        .title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .or(writer.first_h1);
+    Page {
+        title,
+        markdown: tidy(&writer.out),
+    }
+}
+
+fn is_heading(name: &str) -> bool {
+    name.len() == 2 && name.starts_with('h') && name[1..].chars().all(|c| ('1'..='6').contains(&c))
+}
+
+/// Parse the inside of `<...>`; returns `None` for doctypes and processing instructions.
+fn parse_tag(inner: &str) -> Option<Tag> {
+    let inner = inner.trim().trim_end_matches('/');
+    if inner.starts_with('!') || inner.starts_with('?') {
+        return None;
+    }
+    let (closing, inner) = match inner.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, inner),
+    };
+
+    let name_end = inner
+        .find(|c: char| c.is_whitespace())
+        .unwrap_or(inner.len());
+    let name = inner[..name_end].to_lowercase();
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut attrs = Vec::new();
+    let mut rest = inner[name_end..].trim_start();
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = rest[..key_end].to_lowercase();
+        rest = rest[key_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (v, remaining) = match after_eq.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let body = &after_eq[1..];
+                    let close = body.find(q).unwrap_or(body.len());
+                    (&body[..close], body.get(close + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                    (&after_eq[..end], &after_eq[end..])
+                }
+            };
+            value = decode_entities(v);
+            rest = remaining.trim_start();
+        }
+        if !key.is_empty() {
+            attrs.push((key, value));
+        }
+    }
+
+    Some(Tag {
+        name,
+        closing,
+        attrs,
+    })
+}
+
+/// Collapse runs of whitespace into single spaces.
+fn collapse(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_space {
+                out.push(' ');
+            }
+            last_space = true;
+        } else {
+            out.push(c);
+            last_space = false;
+        }
+    }
+    out
+}
+
+/// Decode the common named entities and numeric character references.
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                "mdash" => Some('—'),
+                "ndash" => Some('–'),
+                "hellip" => Some('…'),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Escape text for an HTML element or quoted attribute.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Resolve a possibly-relative URL against the page URL.
+pub fn resolve_url(base: &str, href: &str) -> String {
+    if href.contains("://") || href.starts_with("mailto:") {
+        return href.to_string();
+    }
+
+    let scheme_end = base.find("://").map(|i| i + 3).unwrap_or(0);
+    let scheme = &base[..scheme_end.saturating_sub(3)];
+    if let Some(rest) = href.strip_prefix("//") {
+        return format!("{scheme}://{rest}");
+    }
+
+    let host_end = base[scheme_end..]
+        .find('/')
+        .map(|i| scheme_end + i)
+        .unwrap_or(base.len());
+    let origin = &base[..host_end];
+    if href.starts_with('/') {
+        return format!("{origin}{href}");
+    }
+
+    // Relative to the base's directory (ignoring query and fragment)
+    let path = base[host_end..].split(['?', '#']).next().unwrap_or("");
+    let dir = path.rfind('/').map(|i| &path[..=i]).unwrap_or("/");
+    format!("{origin}{dir}{href}")
+}
+
+/// Trim trailing spaces and collapse runs of blank lines.
+fn tidy(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut blank = 0;
+    let mut in_fence = false;
+    for line in markdown.lines() {
+        if line.starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let line = if in_fence { line } else { line.trim_end() };
+        if line.trim_start_matches(['>', ' ']).is_empty() && !in_fence {
+            blank += 1;
+            if blank > 1 {
+                continue;
+            }
+            out.push('\n');
+            continue;
+        }
+        blank = 0;
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic_document() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Guide &amp; Notes</title><style>body { color: red }</style></head>
+<body>
+<nav><a href="/">Home</a></nav>
+<h1>Getting   Started</h1>
+<p>Read the <a href="docs/intro.html">intro</a> and <strong>then</strong> <em>run</em> <code>make</code>.</p>
+<ul><li>One</li><li>Two <a href="https://other.org/x">x</a></li></ul>
+<ol><li>First</li><li>Second</li></ol>
+<pre><code>fn main() {
+    println!("hi");
+}</code></pre>
+<blockquote><p>Quoted</p></blockquote>
+<script>alert("no")</script>
+</body></html>"#;
+
+        let page = to_markdown(html, "https://example.com/guide/start.html");
+        assert_eq!(page.title.as_deref(), Some("Guide & Notes"));
+        let md = page.markdown;
+        assert!(md.starts_with("# Getting Started"));
+        assert!(md.contains(
+            "Read the [intro](https://example.com/guide/docs/intro.html) and **then** *run* `make`."
+        ));
+        assert!(md.contains("- One\n- Two [x](https://other.org/x)"));
+        assert!(md.contains("1. First\n2. Second"));
+        assert!(md.contains("```\nfn main() {\n    println!(\"hi\");\n}\n```"));
+        assert!(md.contains("> Quoted"));
+        assert!(!md.contains("alert"));
+        assert!(!md.contains("Home"));
+        assert!(!md.contains("color"));
+    }
+
+    #[test]
+    fn test_title_falls_back_to_h1() {
+        let page = to_markdown("<h1>Only Heading</h1><p>x</p>", "https://a.io/");
+        assert_eq!(page.title.as_deref(), Some("Only Heading"));
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(
+            decode_entities("a &lt;b&gt; &#65;&#x42; &bogus; &"),
+            "a <b> AB &bogus; &"
+        );
+        assert_eq!(
+            escape("<a href=\"x\">R&D</a>"),
+            "&lt;a href=&quot;x&quot;&gt;R&amp;D&lt;/a&gt;"
+        );
+        assert_eq!(decode_entities(&escape("a < \"b\" & c")), "a < \"b\" & c");
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        let base = "https://example.com/a/b.html?q=1";
+        assert_eq!(resolve_url(base, "c.html"), "https://example.com/a/c.html");
+        assert_eq!(resolve_url(base, "/root"), "https://example.com/root");
+        assert_eq!(resolve_url(base, "//cdn.io/x"), "https://cdn.io/x");
+        assert_eq!(resolve_url(base, "http://x.io"), "http://x.io");
+        assert_eq!(
+            resolve_url("https://example.com", "p"),
+            "https://example.com/p"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Prefix for referring to a mem by id instead of path, e.g. `id:01J9...`.
+pub const ID_PREFIX: &str = "id:";
+
+/// Crockford base32, as used by ULIDs.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Generate a new ULID: 48 bits of milliseconds since the epoch followed by
+/// 80 random bits, as 26 Crockford base32 characters. IDs sort by creation time.
+pub fn generate() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let random = (u128::from(random_u64()) << 64 | u128::from(random_u64())) & ((1 << 80) - 1);
+    let value = (u128::from(millis & ((1 << 48) - 1)) << 80) | random;
+
+    (0..26)
+        .map(|i| ALPHABET[((value >> (125 - 5 * i)) & 31) as usize] as char)
+        .collect()
+}
+
+/// Whether a string is a well-formed ULID.
+pub fn is_valid(id: &str) -> bool {
+    id.len() == 26
+        && id.bytes().all(|b| ALPHABET.contains(&b.to_ascii_uppercase()))
+        // The first character only carries 3 bits
+        && id.as_bytes()[0] <= b'7'
+}
+
+/// The id in an `id:<ulid>` reference, if it is one.
+pub fn parse_reference(reference: &str) -> Option<&str> {
+    reference.strip_prefix(ID_PREFIX)
+}
+
+/// 64 random bits from the standard library's per-process hash keys.
+fn random_u64() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0),
+    );
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let a = generate();
+        let b = generate();
+        assert!(is_valid(&a), "{a}");
+        assert_ne!(a, b);
+        // Same-millisecond ids share the timestamp prefix
+        assert!(a[..6] <= b[..6]);
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(is_valid("01ARZ3NDEKTSV4RRFFQ69G5FAV"));
+        assert!(is_valid("01arz3ndektsv4rrffq69g5fav"));
+        assert!(!is_valid("01ARZ3NDEKTSV4RRFFQ69G5FA"));
+        assert!(!is_valid("01ARZ3NDEKTSV4RRFFQ69G5FAU"));
+        assert!(!is_valid("81ARZ3NDEKTSV4RRFFQ69G5FAV"));
+    }
+}
@@ -0,0 +1,120 @@
+//! `.memsignore`: paths every command skips when listing mems, written in
+//! gitignore syntax.
+//!
+//! Each line is a pattern (`#` starts a comment, `!` re-includes). A pattern
+//! ending in `/` only matches directories; one containing another `/` is
+//! relative to the store root, and any other matches at every level. Patterns
+//! apply to mem paths with or without the `.md` extension.
+
+use crate::glob;
+
+/// Ignore file at the root of a store.
+pub const IGNORE_FILE: &str = ".memsignore";
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    /// Pattern anchored at the store root
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+}
+
+/// Parsed `.memsignore` rules.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ignore {
+    rules: Vec<Rule>,
+}
+
+impl Ignore {
+    pub fn parse(text: &str) -> Ignore {
+        let rules = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let (dir_only, line) = match line.strip_suffix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let pattern = match line.strip_prefix('/') {
+                    Some(rest) => rest.to_string(),
+                    None if line.contains('/') => line.to_string(),
+                    None => format!("**/{line}"),
+                };
+                (!line.is_empty()).then_some(Rule {
+                    pattern,
+                    negated,
+                    dir_only,
+                })
+            })
+            .collect();
+        Ignore { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether the last rule matching `path` ignores it, if any matches.
+    fn decide(&self, path: &str, is_dir: bool) -> Option<bool> {
+        let file = format!("{path}.md");
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.dir_only)
+                    && (glob::matches(&rule.pattern, path)
+                        || (!is_dir && glob::matches(&rule.pattern, &file)))
+            })
+            .map(|rule| !rule.negated)
+    }
+
+    /// Whether the mem at `path` is ignored. As with git, a mem under an
+    /// ignored directory can't be re-included.
+    pub fn is_ignored(&self, path: &str) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+        let segments: Vec<&str> = path.split('/').collect();
+        for depth in 1..segments.len() {
+            if self.decide(&segments[..depth].join("/"), true) == Some(true) {
+                return true;
+            }
+        }
+        self.decide(path, false) == Some(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ignored() {
+        let ignore = Ignore::parse(
+            "# scratch areas\nscratch/\n/generated/**\n*.draft\ntmp-*\n!tmp-keep\n\nnotes/*.md\n",
+        );
+        assert!(ignore.is_ignored("scratch/a"));
+        assert!(ignore.is_ignored("team/scratch/b"));
+        assert!(!ignore.is_ignored("scratch"));
+        assert!(ignore.is_ignored("generated/api/index"));
+        assert!(!ignore.is_ignored("docs/generated/x"));
+        assert!(ignore.is_ignored("ideas/plan.draft"));
+        assert!(ignore.is_ignored("tmp-1"));
+        assert!(!ignore.is_ignored("tmp-keep"));
+        assert!(ignore.is_ignored("notes/todo"));
+        assert!(!ignore.is_ignored("notes/sub/todo"));
+        assert!(!ignore.is_ignored("arch/overview"));
+    }
+
+    #[test]
+    fn test_ignored_dir_stays_ignored() {
+        let ignore = Ignore::parse("scratch/\n!scratch/keep\n");
+        assert!(ignore.is_ignored("scratch/keep"));
+        assert!(Ignore::parse("# nothing\n").is_empty());
+    }
+}
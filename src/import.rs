@@ -0,0 +1,741 @@
+use crate::dates;
+use crate::links;
+use crate::mem::{self, slugify, Mem};
+use crate::progress::Progress;
+use crate::text;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use clap::ValueEnum;
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Note tools `import` understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// zk notebooks: YAML frontmatter, `[[id]]` and `[title](id)` links
+    Zk,
+    /// Dendron vaults: dot-notation hierarchies (`a.b.c.md`), `[[label|a.b.c]]` links
+    Dendron,
+    /// Foam workspaces: `[[note-name]]` links, generated link references
+    Foam,
+    /// Logseq graphs: `pages/` and `journals/` outlines with `key:: value` properties
+    Logseq,
+}
+
+/// Frontmatter fields turned into mem fields rather than kept as extras.
+const CONSUMED_FIELDS: &[&str] = &[
+    "id", "title", "tags", "keywords", "date", "created", "updated",
+];
+
+/// Logseq block properties that only matter to Logseq itself.
+const LOGSEQ_INTERNAL: &[&str] = &["id", "collapsed"];
+
+/// Result of converting a directory of notes.
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub mems: Vec<Mem>,
+
+    /// Files that could not be converted, with the reason
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// A note as read from the other tool, before links are rewritten.
+#[derive(Debug)]
+struct Note {
+    /// Source path relative to the import root, without `.md`
+    source: String,
+    /// Mem path
+    path: String,
+    /// Lowercased names wikilinks may use for this note
+    names: Vec<String>,
+    title: String,
+    tags: Vec<String>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    extra: BTreeMap<String, Value>,
+    body: String,
+}
+
+/// Convert every note under `dir` into mems, rewriting wikilinks and links
+/// between notes into relative mem links.
+///
+/// Timestamps come from the note's metadata where the tool keeps them, and
+/// the file's mtime otherwise. Every mem gets a fresh id.
+pub fn import_dir(dir: &Path, format: Format, progress: &dyn Progress) -> Result<Imported> {
+    let mut files = Vec::new();
+    if format == Format::Logseq {
+        let (pages, journals) = (dir.join("pages"), dir.join("journals"));
+        if !pages.is_dir() && !journals.is_dir() {
+            return Err(anyhow!(
+                "not a Logseq graph (no pages/ or journals/): {}",
+                dir.display()
+            ));
+        }
+        for sub in [pages, journals] {
+            if sub.is_dir() {
+                collect_files(&sub, &mut files)?;
+            }
+        }
+    } else {
+        collect_files(dir, &mut files)?;
+    }
+
+    let mut imported = Imported::default();
+    let mut notes = Vec::new();
+    let mut used = HashSet::new();
+    progress.start("Reading", files.len());
+    for file in files {
+        let source = file
+            .strip_prefix(dir)
+            .unwrap_or(&file)
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/");
+        match read_note(&file, source, format) {
+            Ok(mut note) => {
+                note.path = unique_path(&note.path, &mut used);
+                notes.push(note);
+            }
+            Err(e) => imported.skipped.push((file.clone(), e.to_string())),
+        }
+        progress.advance(&file.display().to_string());
+    }
+    progress.finish();
+
+    let resolver = Resolver::new(&notes);
+    for note in notes {
+        let content = resolver.rewrite(&note, format);
+        let mut mem = Mem::new(PathBuf::from(&note.path), note.title, content).with_tags(note.tags);
+        let updated_at = note.updated_at.unwrap_or(mem.updated_at);
+        mem.created_at = note.created_at.unwrap_or(updated_at).min(updated_at);
+        mem.updated_at = updated_at;
+        mem.extra = note.extra;
+        imported.mems.push(mem);
+    }
+    Ok(imported)
+}
+
+/// Markdown files under `dir`, sorted, skipping hidden directories.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("failed to read {}", dir.display()))?
+        .collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if name.ends_with(".md") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn read_note(file: &Path, source: String, format: Format) -> Result<Note> {
+    let data = fs::read(file).with_context(|| format!("failed to read {}", file.display()))?;
+    let text = text::decode(data, &file.display().to_string())?;
+    let text = text.replace("\r\n", "\n");
+    let mtime = fs::metadata(file)
+        .and_then(|m| m.modified())
+        .ok()
+        .map(DateTime::<Utc>::from);
+
+    let (fields, body) = match format {
+        Format::Logseq => split_properties(&text),
+        _ => split_frontmatter(&text)?,
+    };
+    let body = match format {
+        Format::Logseq => outline_to_markdown(body),
+        Format::Foam => strip_link_references(body),
+        Format::Zk | Format::Dendron => body.to_string(),
+    };
+
+    let stem = source.rsplit('/').next().unwrap_or(&source).to_string();
+    let mut note = Note {
+        path: String::new(),
+        names: Vec::new(),
+        title: string_field(&fields, "title").unwrap_or_default(),
+        tags: tags_field(&fields, "tags"),
+        created_at: fields
+            .get("created")
+            .or_else(|| fields.get("date"))
+            .and_then(parse_time),
+        updated_at: fields.get("updated").and_then(parse_time).or(mtime),
+        extra: fields
+            .iter()
+            .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v.clone())))
+            .filter(|(k, _)| !CONSUMED_FIELDS.contains(&k.as_str()))
+            .collect(),
+        body,
+        source,
+    };
+    note.tags.extend(tags_field(&fields, "keywords"));
+
+    // The name a tool shows for the note, and the mem path segments
+    let (name, segments): (String, Vec<String>) = match format {
+        Format::Zk | Format::Foam => (
+            stem.clone(),
+            note.source.split('/').map(String::from).collect(),
+        ),
+        Format::Dendron => {
+            let mut segments: Vec<String> = note.source.split('/').map(String::from).collect();
+            let last = segments.pop().unwrap_or_default();
+            segments.extend(last.split('.').map(String::from));
+            (stem.clone(), segments)
+        }
+        Format::Logseq => match journal_date(&note.source) {
+            Some(date) => {
+                let name = date.format("%Y-%m-%d").to_string();
+                note.created_at = note
+                    .created_at
+                    .or(date.and_hms_opt(0, 0, 0).map(|d| d.and_utc()));
+                note.names.push(journal_title(date).to_lowercase());
+                (name.clone(), vec!["journals".to_string(), name])
+            }
+            None => {
+                let name = stem
+                    .replace("___", "/")
+                    .replace("%2F", "/")
+                    .replace("%2f", "/");
+                let segments = name.split('/').map(String::from).collect();
+                (name, segments)
+            }
+        },
+    };
+    let segments: Vec<String> = segments.iter().map(|s| slugify(s)).collect();
+    note.path = segments.join("/");
+
+    if note.title.is_empty() {
+        if let Some(heading) = leading_heading(&note.body) {
+            note.title = heading.to_string();
+            note.body = note
+                .body
+                .trim_start()
+                .split_once('\n')
+                .map_or("", |(_, rest)| rest)
+                .to_string();
+        } else if format == Format::Dendron {
+            note.title = name.rsplit('.').next().unwrap_or(&name).to_string();
+        } else {
+            note.title = name.rsplit('/').next().unwrap_or(&name).to_string();
+        }
+    }
+    note.body = note.body.trim().to_string();
+
+    note.names.push(name.to_lowercase());
+    note.names.push(note.source.to_lowercase());
+    note.names.push(note.title.to_lowercase());
+    for alias in tags_field(&fields, "alias")
+        .into_iter()
+        .chain(tags_field(&fields, "aliases"))
+    {
+        note.names.push(alias.to_lowercase());
+    }
+    note.extra.remove("alias");
+    note.extra.remove("aliases");
+    Ok(note)
+}
+
+/// Date of a Logseq journal page (`journals/2025_01_15.md`). Other pages
+/// keep namespaces in the file name as `a___b.md` (or `a%2Fb.md` in older
+/// graphs).
+fn journal_date(source: &str) -> Option<NaiveDate> {
+    let journal = source.strip_prefix("journals/")?;
+    NaiveDate::parse_from_str(journal, "%Y_%m_%d").ok()
+}
+
+/// Logseq's default journal page title, e.g. `Jan 15th, 2025`.
+fn journal_title(date: NaiveDate) -> String {
+    let day = date.day();
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{} {day}{suffix}, {}", date.format("%b"), date.year())
+}
+
+/// Append `-2`, `-3`, ... to a path already taken by another note.
+pub(crate) fn unique_path(path: &str, used: &mut HashSet<String>) -> String {
+    let mut candidate = path.to_string();
+    let mut n = 1;
+    while !used.insert(candidate.clone()) {
+        n += 1;
+        candidate = format!("{path}-{n}");
+    }
+    candidate
+}
+
+/// Split optional YAML frontmatter from the body.
+fn split_frontmatter(text: &str) -> Result<(Mapping, &str)> {
+    if !text.starts_with("---\n") && !text.starts_with("---\r\n") {
+        return Ok((Mapping::new(), text));
+    }
+    let Some((yaml, body)) = mem::split_frontmatter(text) else {
+        return Ok((Mapping::new(), text));
+    };
+    let fields = match serde_yaml::from_str::<Value>(yaml)
+        .map_err(|e| anyhow!("invalid frontmatter YAML: {e}"))?
+    {
+        Value::Mapping(fields) => fields,
+        Value::Null => Mapping::new(),
+        _ => return Err(anyhow!("frontmatter is not a mapping")),
+    };
+    Ok((fields, body))
+}
+
+/// Split Logseq page properties (`key:: value` lines before the first
+/// block) from the outline.
+fn split_properties(text: &str) -> (Mapping, &str) {
+    let mut fields = Mapping::new();
+    let mut rest = text;
+    while let Some(line) = rest.lines().next() {
+        let Some((key, value)) = property(line.trim_start_matches("- ")) else {
+            break;
+        };
+        let value = value.trim();
+        let value = match key {
+            "tags" | "alias" => Value::Sequence(
+                value
+                    .split(',')
+                    .map(|v| v.trim().trim_start_matches('#'))
+                    .map(|v| v.trim_start_matches("[[").trim_end_matches("]]"))
+                    .filter(|v| !v.is_empty())
+                    .map(|v| Value::String(v.to_string()))
+                    .collect(),
+            ),
+            _ => Value::String(value.to_string()),
+        };
+        fields.insert(Value::String(key.to_string()), value);
+        rest = rest.get(line.len() + 1..).unwrap_or_default();
+    }
+    (fields, rest)
+}
+
+/// A `key:: value` property line.
+fn property(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line
+        .split_once(":: ")
+        .or_else(|| line.strip_suffix("::").map(|key| (key, "")))?;
+    let valid = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    valid.then_some((key, value))
+}
+
+/// Convert a Logseq outline into markdown: top-level blocks become
+/// paragraphs, nested blocks become (nested) list items, and internal block
+/// properties are dropped.
+fn outline_to_markdown(outline: &str) -> String {
+    let mut out = String::new();
+    let mut level = 0;
+    for line in outline.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let depth = line[..indent].matches('\t').count() + line[..indent].matches("  ").count();
+        let text = line.trim();
+
+        if let Some((key, _)) = property(text) {
+            if LOGSEQ_INTERNAL.contains(&key) {
+                continue;
+            }
+        }
+
+        let block = text.strip_prefix("- ").or((text == "-").then_some(""));
+        match block {
+            Some(block) => {
+                level = depth;
+                if level == 0 {
+                    if !out.is_empty() {
+                        out.push('\n');
+                    }
+                    out.push_str(block);
+                } else {
+                    out.push_str(&"  ".repeat(level - 1));
+                    out.push_str("- ");
+                    out.push_str(block);
+                }
+            }
+            // Continuation of the current block
+            None if level == 0 || text.is_empty() => out.push_str(text),
+            None => {
+                out.push_str(&"  ".repeat(level));
+                out.push_str(text);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Drop Foam's generated `[//begin]` ... `[//end]` link reference block.
+fn strip_link_references(body: &str) -> String {
+    let mut out = String::new();
+    let mut skipping = false;
+    for line in body.lines() {
+        if line.starts_with("[//begin]:") {
+            skipping = true;
+        } else if line.starts_with("[//end]:") {
+            skipping = false;
+        } else if !skipping {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Text of a `# Heading` on the first non-empty line.
+fn leading_heading(body: &str) -> Option<&str> {
+    let first = body.trim_start().lines().next()?;
+    first
+        .strip_prefix("# ")
+        .map(str::trim)
+        .filter(|h| !h.is_empty())
+}
+
+fn string_field(fields: &Mapping, key: &str) -> Option<String> {
+    match fields.get(key)? {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// A list field given as a sequence or a comma/space separated string.
+fn tags_field(fields: &Mapping, key: &str) -> Vec<String> {
+    match fields.get(key) {
+        Some(Value::Sequence(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect(),
+        Some(Value::String(s)) => s
+            .split([',', ' '])
+            .map(|v| v.trim().trim_start_matches('#').to_string())
+            .filter(|v| !v.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A timestamp as RFC 3339, `YYYY-MM-DD[ HH:MM:SS]`, or epoch milliseconds
+/// (Dendron).
+fn parse_time(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::Number(n) => Utc.timestamp_millis_opt(n.as_i64()?).single(),
+        Value::String(s) => dates::parse_timestamp(s).or_else(|| {
+            NaiveDateTime::parse_from_str(s.trim(), "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|d| d.and_utc())
+        }),
+        _ => None,
+    }
+}
+
+/// Maps the ways notes refer to each other onto mem paths.
+struct Resolver {
+    /// Lowercased wikilink name -> mem path
+    by_name: HashMap<String, String>,
+    /// Source path (without `.md`) -> mem path
+    by_source: HashMap<String, String>,
+}
+
+impl Resolver {
+    fn new(notes: &[Note]) -> Self {
+        let mut by_name = HashMap::new();
+        let mut by_source = HashMap::new();
+        for note in notes {
+            by_source.insert(note.source.clone(), note.path.clone());
+            for name in &note.names {
+                // First note wins when names collide
+                by_name
+                    .entry(name.clone())
+                    .or_insert_with(|| note.path.clone());
+            }
+        }
+        Self { by_name, by_source }
+    }
+
+    /// Rewrite a note's links, leaving fenced code and unresolved links alone.
+    fn rewrite(&self, note: &Note, format: Format) -> String {
+        let mut out = String::new();
+        let mut in_fence = false;
+        for line in note.body.lines() {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+            if in_fence {
+                out.push_str(line);
+            } else {
+                let line = self.rewrite_wikilinks(line, note, format);
+                out.push_str(&self.rewrite_markdown_links(&line, note));
+            }
+            out.push('\n');
+        }
+        out.trim_end().to_string()
+    }
+
+    /// `[[target|label]]` (Dendron: `[[label|target]]`) and `![[embed]]` to
+    /// `[label](relative.md)`. Logseq's `#[[tag]]` is left as is.
+    fn rewrite_wikilinks(&self, line: &str, note: &Note, format: Format) -> String {
+        let mut out = String::new();
+        let mut rest = line;
+        while let Some(start) = rest.find("[[") {
+            let Some(len) = rest[start..].find("]]") else {
+                break;
+            };
+            let inner = &rest[start + 2..start + len];
+            let before = &rest[..start];
+            let replacement = if before.ends_with('#') {
+                None
+            } else {
+                let (target, label) = match (inner.split_once('|'), format) {
+                    (Some((label, target)), Format::Dendron) => (target, label),
+                    (Some((target, label)), _) => (target, label),
+                    (None, _) => (inner, inner),
+                };
+                let target = target.split('#').next().unwrap_or_default().trim();
+                self.by_name.get(&target.to_lowercase()).map(|path| {
+                    format!(
+                        "[{}]({})",
+                        label.trim(),
+                        links::relative_target(&note.path, path)
+                    )
+                })
+            };
+            match replacement {
+                Some(link) => {
+                    out.push_str(before.strip_suffix('!').unwrap_or(before));
+                    out.push_str(&link);
+                }
+                None => out.push_str(&rest[..start + len + 2]),
+            }
+            rest = &rest[start + len + 2..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Point `[text](other-note)` links (with or without `.md`, relative to
+    /// the note) at the new mem path.
+    fn rewrite_markdown_links(&self, line: &str, note: &Note) -> String {
+        let mut out = line.to_string();
+        let dir = note.source.rsplit_once('/').map_or("", |(dir, _)| dir);
+        for link in links::extract_links(line) {
+            if link.target.contains("://") || link.target.starts_with(['#', '/']) {
+                continue;
+            }
+            let target = link.target.split('#').next().unwrap_or_default();
+            let target = target.strip_suffix(".md").unwrap_or(target);
+            let source = normalize(&format!("{dir}/{target}"));
+            if let Some(path) = self.by_source.get(&source) {
+                out = out.replace(
+                    &format!("]({})", link.target),
+                    &format!("]({})", links::relative_target(&note.path, path)),
+                );
+            }
+        }
+        out
+    }
+}
+
+/// Resolve `.` and `..` segments of a `/`-separated path.
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Silent;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, path: &str, content: &str) {
+        let file = dir.join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, content).unwrap();
+    }
+
+    fn find<'a>(imported: &'a Imported, path: &str) -> &'a Mem {
+        imported
+            .mems
+            .iter()
+            .find(|m| m.path == Path::new(path))
+            .unwrap_or_else(|| panic!("no mem {path}"))
+    }
+
+    #[test]
+    fn test_import_zk() {
+        let temp = TempDir::new().unwrap();
+        write(
+            temp.path(),
+            "8ok1.md",
+            "---\ntitle: Retry policy\ndate: 2024-03-01 10:30:00\ntags: [infra]\nauthor: sam\n---\n\nSee [[x2b9]] and [setup](guides/setup).\n",
+        );
+        write(temp.path(), "x2b9.md", "# Backoff\n\nExponential.\n");
+        write(
+            temp.path(),
+            "guides/setup.md",
+            "# Setup\nBack to [[8ok1|retries]].\n",
+        );
+        write(temp.path(), ".zk/templates/default.md", "# {{title}}\n");
+
+        let imported = import_dir(temp.path(), Format::Zk, &Silent).unwrap();
+        assert_eq!(imported.mems.len(), 3);
+
+        let retry = find(&imported, "8ok1");
+        assert_eq!(retry.title, "Retry policy");
+        assert_eq!(retry.tags, vec!["infra"]);
+        assert_eq!(retry.created_at.to_rfc3339(), "2024-03-01T10:30:00+00:00");
+        assert_eq!(retry.field("author"), Some("sam"));
+        assert_eq!(
+            retry.content,
+            "See [x2b9](x2b9.md) and [setup](guides/setup.md)."
+        );
+
+        let backoff = find(&imported, "x2b9");
+        assert_eq!(backoff.title, "Backoff");
+        assert_eq!(backoff.content, "Exponential.");
+        assert_eq!(
+            find(&imported, "guides/setup").content,
+            "Back to [retries](../8ok1.md)."
+        );
+    }
+
+    #[test]
+    fn test_split_frontmatter_crlf() {
+        let (fields, body) = split_frontmatter("---\r\ntitle: Notes\r\n---\r\nBody\r\n").unwrap();
+        assert_eq!(fields.get("title").and_then(Value::as_str), Some("Notes"));
+        assert_eq!(body, "Body\r\n");
+        let (fields, body) = split_frontmatter("---- not frontmatter\n").unwrap();
+        assert!(fields.is_empty());
+        assert_eq!(body, "---- not frontmatter\n");
+    }
+
+    #[test]
+    fn test_import_dendron() {
+        let temp = TempDir::new().unwrap();
+        write(
+            temp.path(),
+            "arch.decisions.adr-001.md",
+            "---\nid: abc123\ntitle: Database Choice\ndesc: ''\nupdated: 1700000000000\ncreated: 1690000000000\n---\n\nSee [[the overview|arch]].\n",
+        );
+        write(
+            temp.path(),
+            "arch.md",
+            "---\nid: def\ntitle: Arch\n---\nLinks to [[arch.decisions.adr-001#why]].\n",
+        );
+
+        let imported = import_dir(temp.path(), Format::Dendron, &Silent).unwrap();
+        let adr = find(&imported, "arch/decisions/adr-001");
+        assert_eq!(adr.title, "Database Choice");
+        assert_eq!(adr.created_at.timestamp_millis(), 1690000000000);
+        assert_eq!(adr.updated_at.timestamp_millis(), 1700000000000);
+        assert_eq!(adr.content, "See [the overview](../../arch.md).");
+        assert!(adr.field("id").is_none() && adr.id.is_some());
+        assert_eq!(
+            find(&imported, "arch").content,
+            "Links to [arch.decisions.adr-001#why](arch/decisions/adr-001.md)."
+        );
+    }
+
+    #[test]
+    fn test_import_foam() {
+        let temp = TempDir::new().unwrap();
+        write(
+            temp.path(),
+            "notes/Project Plan.md",
+            "# Project Plan\n\nDepends on [[budget]] and ![[missing]].\n\n[//begin]: # \"Autogenerated link references\"\n[budget]: ../budget.md \"Budget\"\n[//end]: # \"Autogenerated link references\"\n",
+        );
+        write(
+            temp.path(),
+            "budget.md",
+            "---\ntags: money, q3\n---\nNumbers.\n",
+        );
+
+        let imported = import_dir(temp.path(), Format::Foam, &Silent).unwrap();
+        let plan = find(&imported, "notes/project-plan");
+        assert_eq!(plan.title, "Project Plan");
+        assert_eq!(
+            plan.content,
+            "Depends on [budget](../budget.md) and ![[missing]]."
+        );
+        let budget = find(&imported, "budget");
+        assert_eq!(budget.title, "budget");
+        assert_eq!(budget.tags, vec!["money", "q3"]);
+    }
+
+    #[test]
+    fn test_import_logseq() {
+        let temp = TempDir::new().unwrap();
+        write(
+            temp.path(),
+            "pages/project___alpha.md",
+            "title:: project/alpha\ntags:: [[work]], #q3\nalias:: Alpha\n\n- Kickoff notes\n  id:: 650c1f2e-0000\n\t- Owner is [[Sam]]\n\t\t- Since [[Jan 15th, 2024]]\n- ## Risks\n\t- None yet\n",
+        );
+        write(temp.path(), "pages/Sam.md", "- Works on [[alpha]]\n");
+        write(
+            temp.path(),
+            "journals/2024_01_15.md",
+            "- Started #[[project/alpha]]\n",
+        );
+        write(temp.path(), "logseq/bak/pages/old.md", "- ignored\n");
+
+        let imported = import_dir(temp.path(), Format::Logseq, &Silent).unwrap();
+        assert_eq!(imported.mems.len(), 3);
+
+        let alpha = find(&imported, "project/alpha");
+        assert_eq!(alpha.title, "project/alpha");
+        assert_eq!(alpha.tags, vec!["work", "q3"]);
+        assert_eq!(
+            alpha.content,
+            "Kickoff notes\n- Owner is [Sam](../sam.md)\n  - Since [Jan 15th, 2024](../journals/2024-01-15.md)\n\n## Risks\n- None yet"
+        );
+        assert_eq!(
+            find(&imported, "sam").content,
+            "Works on [alpha](project/alpha.md)"
+        );
+
+        let journal = find(&imported, "journals/2024-01-15");
+        assert_eq!(journal.title, "2024-01-15");
+        assert_eq!(journal.created_at.to_rfc3339(), "2024-01-15T00:00:00+00:00");
+        assert_eq!(journal.content, "Started #[[project/alpha]]");
+    }
+
+    #[test]
+    fn test_import_skips_bad_frontmatter() {
+        let temp = TempDir::new().unwrap();
+        write(temp.path(), "ok.md", "Fine\n");
+        write(temp.path(), "bad.md", "---\ntitle: [unclosed\n---\nBody\n");
+        fs::write(temp.path().join("diagram.md"), b"\0\x01\x02").unwrap();
+
+        let imported = import_dir(temp.path(), Format::Zk, &Silent).unwrap();
+        assert_eq!(imported.mems.len(), 1);
+        assert_eq!(imported.skipped.len(), 2);
+        assert!(imported.skipped[0].0.ends_with("bad.md"));
+        assert!(imported.skipped[1].0.ends_with("diagram.md"));
+        assert!(imported.skipped[1].1.contains("binary file"));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::dictionary::code_spans;
+use crate::id;
+use crate::mem::Mem;
+use crate::storage::{normalize_path, Storage};
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+/// An include directive in mem content: `{{include: path}}` or `![[path]]`.
+/// Targets are mem paths from the store root, or `id:<ULID>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Include {
+    pub target: String,
+
+    /// 1-based line number within the content
+    pub line: usize,
+}
+
+/// Include directives outside fenced code blocks and inline code.
+pub fn extract(content: &str) -> Vec<Include> {
+    let mut includes = Vec::new();
+    let mut in_fence = false;
+    for (i, line) in content.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            continue;
+        }
+        includes.extend(directives(line).into_iter().map(|(_, _, target)| Include {
+            target: target.to_string(),
+            line: i + 1,
+        }));
+    }
+    includes
+}
+
+/// The directives in a line outside inline code: byte ranges and targets.
+fn directives(line: &str) -> Vec<(usize, usize, &str)> {
+    let code = code_spans(line);
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some((start, end, target)) = next_directive(&line[offset..]) {
+        let (start, end) = (offset + start, offset + end);
+        if !code.iter().any(|c| c.start < end && start < c.end) {
+            found.push((start, end, target));
+        }
+        offset = end;
+    }
+    found
+}
+
+/// The first directive in `text`: its byte range and target.
+///
+/// `![[...]]` embeds of non-markdown files (e.g. `![[diagram.png]]`) are not
+/// includes.
+fn next_directive(text: &str) -> Option<(usize, usize, &str)> {
+    let include = text.find("{{include:").and_then(|start| {
+        let len = text[start..].find("}}")?;
+        Some((start, start + len + 2, &text[start + 10..start + len]))
+    });
+    let embed = text.find("![[").and_then(|start| {
+        let len = text[start..].find("]]")?;
+        let inner = &text[start + 3..start + len];
+        Some((
+            start,
+            start + len + 2,
+            inner.split('|').next().unwrap_or(inner),
+        ))
+    });
+
+    let (start, end, target) = match (include, embed) {
+        (Some(a), Some(b)) => {
+            if a.0 < b.0 {
+                a
+            } else {
+                b
+            }
+        }
+        (a, b) => a.or(b)?,
+    };
+    let target = target.trim();
+    let target = target.strip_suffix(".md").unwrap_or(target);
+    let name = target.rsplit('/').next().unwrap_or(target);
+    if target.is_empty() || (id::parse_reference(target).is_none() && name.contains('.')) {
+        // Not an include; look past it
+        return next_directive(&text[end..]).map(|(s, e, t)| (end + s, end + e, t));
+    }
+    Some((start, end, target))
+}
+
+/// The mem path an include target refers to, if that mem exists.
+pub fn resolve(storage: &Storage, target: &str) -> Result<Option<String>> {
+    match id::parse_reference(target) {
+        Some(id) => storage.path_for_id(id),
+        None => {
+            let path = normalize_path(target)?;
+            Ok(storage.exists(&path).then_some(path))
+        }
+    }
+}
+
+/// A mem's content with include directives replaced by the (recursively
+/// expanded) content of the included mems.
+///
+/// A directive whose target doesn't resolve is left as written (`mem lint`
+/// reports it); fails on include cycles.
+pub fn expand(storage: &Storage, mem: &Mem) -> Result<String> {
+    let mut stack = vec![mem.path_str()];
+    expand_content(storage, &mem.content, &mut stack)
+}
+
+fn expand_content(storage: &Storage, content: &str, stack: &mut Vec<String>) -> Result<String> {
+    let mut out = String::new();
+    let mut in_fence = false;
+    for line in content.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            out.push_str(line);
+            continue;
+        }
+
+        let mut written = 0;
+        for (start, end, target) in directives(line) {
+            let Ok(Some(path)) = resolve(storage, target) else {
+                continue;
+            };
+            out.push_str(&line[written..start]);
+            written = end;
+            if let Some(pos) = stack.iter().position(|p| *p == path) {
+                let mut chain = stack[pos..].to_vec();
+                chain.push(path);
+                return Err(anyhow!("include cycle: {}", chain.join(" -> ")));
+            }
+
+            let included = storage.read_mem(&path)?;
+            stack.push(path);
+            out.push_str(&expand_content(
+                storage,
+                included.content.trim_end(),
+                stack,
+            )?);
+            stack.pop();
+        }
+        out.push_str(&line[written..]);
+    }
+    Ok(out)
+}
+
+/// An include cycle through `path`, as the chain of paths starting and
+/// ending with it. Missing targets are ignored.
+pub fn find_cycle(storage: &Storage, path: &str) -> Result<Option<Vec<String>>> {
+    let mut chain = vec![path.to_string()];
+    let mut seen = HashSet::new();
+    Ok(visit(storage, path, &mut chain, &mut seen)?.then_some(chain))
+}
+
+fn visit(
+    storage: &Storage,
+    start: &str,
+    chain: &mut Vec<String>,
+    seen: &mut HashSet<String>,
+) -> Result<bool> {
+    let current = chain.last().cloned().unwrap_or_default();
+    let Ok(mem) = storage.read_mem(&current) else {
+        return Ok(false);
+    };
+    for include in extract(&mem.content) {
+        let Some(target) = resolve(storage, &include.target)? else {
+            continue;
+        };
+        if target == start {
+            chain.push(target);
+            return Ok(true);
+        }
+        if seen.insert(target.clone()) {
+            chain.push(target);
+            if visit(storage, start, chain, seen)? {
+                return Ok(true);
+            }
+            chain.pop();
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn add(storage: &Storage, path: &str, content: &str) -> Mem {
+        let mem = Mem::new(PathBuf::from(path), path.to_string(), content.to_string());
+        storage.write_mem(&mem).unwrap();
+        mem
+    }
+
+    #[test]
+    fn test_extract() {
+        let content =
+            "{{include: a/b}} and ![[c|C]]\n![[diagram.png]] ![[d.md]] `![[f]]`\n```\n{{include: e}}\n```";
+        let targets: Vec<(String, usize)> = extract(content)
+            .into_iter()
+            .map(|i| (i.target, i.line))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                ("a/b".to_string(), 1),
+                ("c".to_string(), 1),
+                ("d".to_string(), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let part = add(&storage, "parts/intro", "Hello\n");
+        add(&storage, "parts/body", "Body with ![[parts/intro]]");
+        let overview = add(
+            &storage,
+            "overview",
+            &format!(
+                "# Overview\n{{{{include: parts/body}}}}\n\nBy id: {{{{include: id:{}}}}}\n",
+                part.id.unwrap()
+            ),
+        );
+
+        assert_eq!(
+            expand(&storage, &overview).unwrap(),
+            "# Overview\nBody with Hello\n\nBy id: Hello\n"
+        );
+
+        // Unresolved directives and ones in code stay as written
+        let broken = add(
+            &storage,
+            "broken",
+            "{{include: nope}} ![[Some Note]] `{{include: parts/intro}}`\n",
+        );
+        assert_eq!(expand(&storage, &broken).unwrap(), broken.content);
+    }
+
+    #[test]
+    fn test_cycles() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let a = add(&storage, "a", "A ![[b]]");
+        add(&storage, "b", "B ![[c]]");
+        add(&storage, "c", "C {{include: a}}");
+        add(&storage, "d", "D ![[a]]");
+
+        let err = expand(&storage, &a).unwrap_err().to_string();
+        assert_eq!(err, "include cycle: a -> b -> c -> a");
+        assert_eq!(
+            find_cycle(&storage, "b").unwrap(),
+            Some(vec![
+                "b".to_string(),
+                "c".to_string(),
+                "a".to_string(),
+                "b".to_string()
+            ])
+        );
+        assert_eq!(find_cycle(&storage, "d").unwrap(), None);
+    }
+}
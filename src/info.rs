@@ -0,0 +1,200 @@
+//! Store metadata in `.mems/store.yaml`: the store's name, description,
+//! owners, and policies, and the version of the store format it was written
+//! in. `mem init` creates it, `mem info` shows it, and commands listing
+//! several stores label each by its name.
+
+use crate::storage::Storage;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Metadata file inside .mems/.
+pub const STORE_FILE: &str = "store.yaml";
+
+/// Version of the store format this build writes. Bumped when stores need
+/// migrating, so tools can tell which layout a store is in.
+pub const STORE_VERSION: u32 = 1;
+
+/// Stores without a version predate it and are in the first format.
+fn first_version() -> u32 {
+    1
+}
+
+/// What `store.yaml` holds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct StoreInfo {
+    /// Label of the store in output covering several
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Who is responsible for the store
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+
+    #[serde(default = "first_version")]
+    pub schema_version: u32,
+
+    /// Agreements about the store, by name, e.g. `review: quarterly`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub policies: BTreeMap<String, String>,
+}
+
+impl Default for StoreInfo {
+    fn default() -> Self {
+        Self {
+            name: None,
+            description: None,
+            owners: Vec::new(),
+            schema_version: STORE_VERSION,
+            policies: BTreeMap::new(),
+        }
+    }
+}
+
+impl StoreInfo {
+    /// The metadata of a store on any backend, if it has any.
+    pub fn for_storage(storage: &Storage) -> Result<Option<Self>> {
+        let Some(content) = storage.read_file(STORE_FILE)? else {
+            return Ok(None);
+        };
+        if content.trim().is_empty() {
+            return Ok(Some(Self::default()));
+        }
+        serde_yaml::from_str(&content)
+            .map(Some)
+            .map_err(|e| anyhow!("invalid {STORE_FILE}: {e}"))
+    }
+
+    /// Write the metadata into the store at `root`.
+    pub fn write(&self, root: &Path) -> Result<()> {
+        let yaml = serde_yaml::to_string(self)?;
+        fs::write(
+            root.join(STORE_FILE),
+            format!("# About this store, shown by `mem info`\n{yaml}"),
+        )
+        .with_context(|| format!("failed to write {STORE_FILE}"))
+    }
+
+    /// Whether the store is in a newer format than this build knows.
+    pub fn is_newer(&self) -> bool {
+        self.schema_version > STORE_VERSION
+    }
+}
+
+/// Label stores listed together: each by its name, if `store.yaml` gives
+/// one, else by its fallback (a path or directory name, distinct among
+/// them). A name that would label two stores labels neither, and a
+/// `store.yaml` that doesn't parse is warned about and passed over, so every
+/// label stays unique and one broken store doesn't stop the rest.
+pub fn labels(stores: Vec<(Storage, String)>) -> Vec<(String, Storage)> {
+    let names: Vec<Option<String>> = stores
+        .iter()
+        .map(
+            |(storage, fallback)| match StoreInfo::for_storage(storage) {
+                Ok(info) => info
+                    .and_then(|info| info.name)
+                    .filter(|name| !name.trim().is_empty()),
+                Err(e) => {
+                    eprintln!("warning: store {fallback}: {e:#}");
+                    None
+                }
+            },
+        )
+        .collect();
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (name, (_, fallback)) in names.iter().zip(&stores) {
+        *counts
+            .entry(name.as_deref().unwrap_or(fallback))
+            .or_default() += 1;
+    }
+    let unique: Vec<bool> = names
+        .iter()
+        .zip(&stores)
+        .map(|(name, (_, fallback))| counts[name.as_deref().unwrap_or(fallback)] == 1)
+        .collect();
+    names
+        .into_iter()
+        .zip(unique)
+        .zip(stores)
+        .map(|((name, unique), (storage, fallback))| match name {
+            Some(name) if unique => (name, storage),
+            _ => (fallback, storage),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_and_read() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        assert_eq!(StoreInfo::for_storage(&storage).unwrap(), None);
+
+        let info = StoreInfo {
+            name: Some("billing".to_string()),
+            owners: vec!["sam".to_string()],
+            ..StoreInfo::default()
+        };
+        info.write(temp.path()).unwrap();
+        let text = fs::read_to_string(temp.path().join(STORE_FILE)).unwrap();
+        assert!(text.contains("schema-version: 1"), "{text}");
+        assert_eq!(StoreInfo::for_storage(&storage).unwrap(), Some(info));
+    }
+
+    #[test]
+    fn test_labels() {
+        let temp = TempDir::new().unwrap();
+        let store = |dir: &str, yaml: &str| {
+            let root = temp.path().join(dir);
+            fs::create_dir_all(&root).unwrap();
+            if !yaml.is_empty() {
+                fs::write(root.join(STORE_FILE), yaml).unwrap();
+            }
+            (Storage::new(root), dir.to_string())
+        };
+        let stores = vec![
+            store("x/billing", "name: billing\n"),
+            store("y/billing", "name: billing\n"),
+            store("payments", "name: pay\n"),
+            store("broken", "owners: sam\n"),
+            store("plain", ""),
+        ];
+        let labels: Vec<String> = labels(stores).into_iter().map(|(l, _)| l).collect();
+        assert_eq!(labels, ["x/billing", "y/billing", "pay", "broken", "plain"]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        fs::write(
+            temp.path().join(STORE_FILE),
+            "description: Payments\npolicies:\n  review: quarterly\n",
+        )
+        .unwrap();
+        let info = StoreInfo::for_storage(&storage).unwrap().unwrap();
+        assert_eq!(info.schema_version, 1);
+        assert_eq!(info.policies["review"], "quarterly");
+        assert!(!info.is_newer());
+
+        fs::write(temp.path().join(STORE_FILE), "schema-version: 99\n").unwrap();
+        assert!(StoreInfo::for_storage(&storage)
+            .unwrap()
+            .unwrap()
+            .is_newer());
+
+        fs::write(temp.path().join(STORE_FILE), "owners: sam\n").unwrap();
+        let err = StoreInfo::for_storage(&storage).unwrap_err();
+        assert!(err.to_string().starts_with("invalid store.yaml"), "{err}");
+    }
+}
@@ -0,0 +1,215 @@
+use crate::mem::{content_checksum, Mem};
+use crate::storage::Storage;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::fs;
+
+/// How far a file's mtime may run ahead of its `updated-at` before the file
+/// counts as modified outside mem (writes and clocks are not instantaneous).
+const MTIME_TOLERANCE_SECS: i64 = 120;
+
+/// What `verify` found wrong with a mem file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Problem {
+    /// The file no longer parses as a mem
+    Unparseable,
+    /// The content does not match the checksum in the frontmatter
+    ChecksumMismatch,
+    /// No checksum, and the file changed after its `updated-at`
+    ModifiedOutside,
+    /// `updated-at` is before `created-at` or in the future
+    BadTimestamps,
+}
+
+impl Problem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Problem::Unparseable => "cannot be parsed",
+            Problem::ChecksumMismatch => "content does not match checksum",
+            Problem::ModifiedOutside => "file modified after updated-at",
+            Problem::BadTimestamps => "inconsistent timestamps",
+        }
+    }
+
+    /// Whether `bless` can accept the file as it is.
+    pub fn blessable(&self) -> bool {
+        *self != Problem::Unparseable
+    }
+}
+
+/// A mem that failed verification.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub path: String,
+    pub problem: Problem,
+}
+
+/// Result of verifying a store.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Mems checked
+    pub checked: usize,
+
+    /// Mems without a checksum (only timestamps can be checked)
+    pub unprotected: usize,
+
+    pub findings: Vec<Finding>,
+}
+
+/// Verify every active mem: checksums against content, and timestamps
+/// against each other and (for local stores) the file's mtime.
+///
+/// A matching checksum wins over the mtime, so files merely touched (e.g. by
+/// a git checkout) are not reported.
+pub fn verify(storage: &Storage) -> Result<Report> {
+    let mut report = Report::default();
+    let now = Utc::now();
+
+    for path in storage.mem_keys("")? {
+        report.checked += 1;
+        let problem = match storage.read_mem(&path) {
+            Err(_) => Some(Problem::Unparseable),
+            Ok(mem) => {
+                if mem.checksum.is_none() {
+                    report.unprotected += 1;
+                }
+                check(&mem, file_mtime(storage, &path), now)
+            }
+        };
+        if let Some(problem) = problem {
+            report.findings.push(Finding { path, problem });
+        }
+    }
+    Ok(report)
+}
+
+fn check(mem: &Mem, mtime: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<Problem> {
+    let tolerance = Duration::seconds(MTIME_TOLERANCE_SECS);
+    match &mem.checksum {
+        Some(checksum) if *checksum != content_checksum(&mem.content) => {
+            return Some(Problem::ChecksumMismatch);
+        }
+        Some(_) => {}
+        None => {
+            if mtime.is_some_and(|mtime| mtime > mem.updated_at + tolerance) {
+                return Some(Problem::ModifiedOutside);
+            }
+        }
+    }
+
+    if mem.updated_at < mem.created_at || mem.updated_at > now + tolerance {
+        return Some(Problem::BadTimestamps);
+    }
+    None
+}
+
+/// Accept a mem's current content: refresh its checksum and set
+/// `updated-at` to now (and `created-at` too, if it was in the future).
+pub fn bless(storage: &Storage, path: &str) -> Result<()> {
+    let mut mem = storage.read_mem(path)?;
+    let now = Utc::now();
+    mem.created_at = mem.created_at.min(now);
+    mem.updated_at = now;
+    mem.tampered = false;
+    storage.write_mem(&mem)
+}
+
+/// Last modification time of a mem file in a local store.
+fn file_mtime(storage: &Storage, path: &str) -> Option<DateTime<Utc>> {
+    storage.local_root().ok()?;
+    let modified = fs::metadata(storage.mem_path(path)).ok()?.modified().ok()?;
+    Some(modified.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn protected(content: &str) -> Mem {
+        let mut mem = Mem::new(
+            PathBuf::from("note"),
+            "Note".to_string(),
+            content.to_string(),
+        );
+        mem.checksum = Some(content_checksum(content));
+        mem
+    }
+
+    #[test]
+    fn test_check() {
+        let now = Utc::now();
+        let later = Some(now + Duration::hours(1));
+
+        let mem = protected("hello");
+        assert_eq!(check(&mem, later, now), None);
+
+        let mut tampered = mem.clone();
+        tampered.content = "hellO".to_string();
+        assert_eq!(check(&tampered, None, now), Some(Problem::ChecksumMismatch));
+
+        let mut unprotected = mem.clone();
+        unprotected.checksum = None;
+        assert_eq!(check(&unprotected, Some(now), now), None);
+        assert_eq!(
+            check(&unprotected, later, now),
+            Some(Problem::ModifiedOutside)
+        );
+
+        let mut skewed = mem;
+        skewed.updated_at = now + Duration::days(1);
+        assert_eq!(check(&skewed, None, now), Some(Problem::BadTimestamps));
+    }
+
+    #[test]
+    fn test_verify_and_bless() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        storage.write_mem(&protected("hello")).unwrap();
+        assert!(verify(&storage).unwrap().findings.is_empty());
+
+        let file = storage.mem_path("note");
+        let raw = fs::read_to_string(&file).unwrap();
+        assert!(raw.contains(&content_checksum("hello")));
+        fs::write(&file, raw.replace("hello", "mangled")).unwrap();
+        fs::write(temp.path().join("broken.md"), "no frontmatter").unwrap();
+
+        let report = verify(&storage).unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(
+            report.findings,
+            vec![
+                Finding {
+                    path: "broken".to_string(),
+                    problem: Problem::Unparseable,
+                },
+                Finding {
+                    path: "note".to_string(),
+                    problem: Problem::ChecksumMismatch,
+                },
+            ]
+        );
+
+        // Rewriting a tampered mem does not bless it
+        let mut mem = storage.read_mem("note").unwrap();
+        assert!(mem.tampered);
+        mem.tags.push("seen".to_string());
+        storage.write_mem(&mem).unwrap();
+        assert_eq!(verify(&storage).unwrap().findings.len(), 2);
+
+        bless(&storage, "note").unwrap();
+        let report = verify(&storage).unwrap();
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(storage.read_mem("note").unwrap().content, "mangled");
+
+        // Edits made through mem keep the checksum current
+        let mut mem = storage.read_mem("note").unwrap();
+        assert!(!mem.tampered);
+        mem.content = "edited".to_string();
+        storage.write_mem(&mem).unwrap();
+        assert_eq!(verify(&storage).unwrap().findings.len(), 1);
+    }
+}
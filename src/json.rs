@@ -0,0 +1,190 @@
+//! The JSON form of a mem, as printed by `--json`, `--output json`, and
+//! `--template`: a stable contract for tools built on `mem`.
+//!
+//! Fields may be added within a schema version; renaming or removing one, or
+//! changing its type, bumps [`SCHEMA_VERSION`]. `mem schema` prints the JSON
+//! Schema.
+
+use crate::mem::Mem;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Version of the [`MemRecord`] format.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A mem as JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemRecord {
+    /// [`SCHEMA_VERSION`] of the tool that wrote the record
+    #[serde(default = "schema_version")]
+    pub schema_version: u32,
+
+    pub path: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    pub title: String,
+
+    #[serde(serialize_with = "rfc3339")]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(serialize_with = "rfc3339")]
+    pub updated_at: DateTime<Utc>,
+
+    pub tags: Vec<String>,
+
+    /// Frontmatter fields beyond the built-in ones, such as `owner`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, serde_json::Value>,
+
+    pub content: String,
+
+    /// Bytes of content
+    pub size: usize,
+
+    pub words: usize,
+
+    /// Estimated LLM tokens of content
+    pub tokens: usize,
+}
+
+fn schema_version() -> u32 {
+    SCHEMA_VERSION
+}
+
+/// Timestamps as `to_rfc3339` writes them (`+00:00`), as they always were.
+fn rfc3339<S: Serializer>(time: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&time.to_rfc3339())
+}
+
+impl From<&Mem> for MemRecord {
+    fn from(mem: &Mem) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            path: mem.path_str(),
+            id: mem.id.clone(),
+            title: mem.title.clone(),
+            created_at: mem.created_at,
+            updated_at: mem.updated_at,
+            tags: mem.tags.clone(),
+            fields: mem
+                .extra
+                .iter()
+                .map(|(k, v)| (k.clone(), serde_json::to_value(v).unwrap_or_default()))
+                .collect(),
+            content: mem.content.clone(),
+            size: mem.size(),
+            words: mem.word_count(),
+            tokens: mem.estimated_tokens(),
+        }
+    }
+}
+
+impl MemRecord {
+    /// The mem the record describes. `size`, `words`, and `tokens` are
+    /// derived from the content, so they are ignored.
+    pub fn into_mem(self) -> Result<Mem> {
+        let mut mem = Mem::new(PathBuf::from(self.path), self.title, self.content);
+        mem.id = self.id;
+        mem.created_at = self.created_at;
+        mem.updated_at = self.updated_at;
+        mem.tags = self.tags;
+        for (key, value) in self.fields {
+            mem.extra.insert(key, serde_yaml::to_value(value)?);
+        }
+        Ok(mem)
+    }
+}
+
+/// JSON Schema (draft 2020-12) of [`MemRecord`].
+pub fn schema() -> serde_json::Value {
+    let timestamp = serde_json::json!({ "type": "string", "format": "date-time" });
+    let count = serde_json::json!({ "type": "integer", "minimum": 0 });
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "MemRecord",
+        "description": "A mem as printed by `mem --json`",
+        "type": "object",
+        "required": [
+            "schema_version", "path", "title", "created_at", "updated_at",
+            "tags", "content", "size", "words", "tokens"
+        ],
+        "properties": {
+            "schema_version": {
+                "const": SCHEMA_VERSION,
+                "description": "Version of this format; bumped on incompatible changes"
+            },
+            "path": {
+                "type": "string",
+                "description": "Path within the store, without .md, with forward slashes"
+            },
+            "id": { "type": "string", "description": "Stable ULID, for links by id" },
+            "title": { "type": "string" },
+            "created_at": timestamp,
+            "updated_at": timestamp,
+            "tags": { "type": "array", "items": { "type": "string" } },
+            "fields": {
+                "type": "object",
+                "description": "Frontmatter fields beyond the built-in ones, such as owner"
+            },
+            "content": { "type": "string", "description": "Markdown after the frontmatter" },
+            "size": count,
+            "words": count,
+            "tokens": count,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mem() -> Mem {
+        let mut mem = Mem::new(
+            PathBuf::from("ops/deploy"),
+            "Deploy".to_string(),
+            "Ship it.\n".to_string(),
+        )
+        .with_tags(vec!["ops".to_string()]);
+        mem.set_field("owner", "sam");
+        mem
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let record = MemRecord::from(&mem());
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.starts_with(r#"{"schema_version":1,"path":"ops/deploy""#));
+        assert!(json.contains(&format!(
+            r#""created_at":"{}""#,
+            record.created_at.to_rfc3339()
+        )));
+        let parsed: MemRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, record);
+
+        let mem = parsed.into_mem().unwrap();
+        assert_eq!(mem.owner(), Some("sam"));
+        assert_eq!(MemRecord::from(&mem), record);
+    }
+
+    #[test]
+    fn test_schema_matches_record() {
+        let schema = schema();
+        let properties = schema["properties"].as_object().unwrap();
+        let record = serde_json::to_value(MemRecord::from(&mem())).unwrap();
+        let fields: Vec<&String> = record.as_object().unwrap().keys().collect();
+        for field in &fields {
+            assert!(
+                properties.contains_key(*field),
+                "{field} is not in the schema"
+            );
+        }
+        for required in schema["required"].as_array().unwrap() {
+            assert!(fields.contains(&&required.as_str().unwrap().to_string()));
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Mem kinds (the `kind` field): notes, ADRs, runbooks, logs, glossaries,
+//! and generated mems, each with its own template, required sections,
+//! staleness threshold, and place in `tree`.
+
+use crate::config::Config;
+use crate::glossary::GLOSSARY_KIND;
+use crate::log::LOG_KIND;
+use crate::mem::Mem;
+use crate::runbook::RUNBOOK_KIND;
+use crate::scaffold::Variable;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Kind of mems without a `kind` field.
+pub const DEFAULT_KIND: &str = "note";
+
+/// Kind of mems written by `mem generate`.
+pub const GENERATED_KIND: &str = "generated";
+
+/// Settings for one kind, under `kinds:` in the store config. Unset fields
+/// keep the built-in defaults for the kind.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct KindConfig {
+    /// Content of new mems of this kind added without any
+    pub template: Option<String>,
+
+    /// Frontmatter fields set on new mems of this kind
+    pub fields: Option<BTreeMap<String, String>>,
+
+    /// Values `add` asks for and substitutes for `{{ name }}` in the
+    /// template and fields
+    pub variables: Option<Vec<Variable>>,
+
+    /// Section headings every mem of this kind must have, checked by `lint`
+    pub required_headings: Option<Vec<String>>,
+
+    /// Days without update before a mem counts as stale (0 for never)
+    pub stale_days: Option<u32>,
+
+    /// Where mems of this kind sort among their siblings in `tree` (lower
+    /// first, default 0)
+    pub order: Option<i32>,
+}
+
+impl KindConfig {
+    /// Fill unset fields from `fallback`.
+    fn or(self, fallback: KindConfig) -> KindConfig {
+        KindConfig {
+            template: self.template.or(fallback.template),
+            fields: self.fields.or(fallback.fields),
+            variables: self.variables.or(fallback.variables),
+            required_headings: self.required_headings.or(fallback.required_headings),
+            stale_days: self.stale_days.or(fallback.stale_days),
+            order: self.order.or(fallback.order),
+        }
+    }
+
+    pub fn required_headings(&self) -> &[String] {
+        self.required_headings.as_deref().unwrap_or_default()
+    }
+
+    pub fn variables(&self) -> &[Variable] {
+        self.variables.as_deref().unwrap_or_default()
+    }
+
+    pub fn order(&self) -> i32 {
+        self.order.unwrap_or(0)
+    }
+}
+
+/// Defaults for the kinds mem knows about.
+fn builtin(kind: &str) -> KindConfig {
+    let headings = |names: &[&str]| Some(names.iter().map(|n| n.to_string()).collect());
+    match kind {
+        // Decisions are superseded, not kept up to date
+        "adr" => KindConfig {
+            template: Some("## Context\n\n## Decision\n\n## Consequences\n".to_string()),
+            required_headings: headings(&["Context", "Decision", "Consequences"]),
+            stale_days: Some(0),
+            ..KindConfig::default()
+        },
+        RUNBOOK_KIND => KindConfig {
+            template: Some("## When to use\n\n## Steps\n\n## Verification\n".to_string()),
+            required_headings: headings(&["Steps"]),
+            stale_days: Some(60),
+            ..KindConfig::default()
+        },
+        GLOSSARY_KIND => KindConfig {
+            stale_days: Some(365),
+            ..KindConfig::default()
+        },
+        // Logs and generated mems are history or rebuilt, never edited to
+        // stay current; they sort after hand-written mems
+        LOG_KIND | GENERATED_KIND => KindConfig {
+            stale_days: Some(0),
+            order: Some(1),
+            ..KindConfig::default()
+        },
+        _ => KindConfig::default(),
+    }
+}
+
+/// The kind of a mem: its `kind` field, `generated` for generated mems, and
+/// `note` otherwise.
+pub fn of(mem: &Mem) -> &str {
+    if mem.is_generated() {
+        return GENERATED_KIND;
+    }
+    mem.kind().unwrap_or(DEFAULT_KIND)
+}
+
+/// The settings of a kind: its entry in the config over the built-in
+/// defaults.
+pub fn settings(config: &Config, kind: &str) -> KindConfig {
+    let configured = config.kinds.get(kind).cloned().unwrap_or_default();
+    configured.or(builtin(kind))
+}
+
+/// Days before `mem` counts as stale, or `None` if its kind never goes
+/// stale. `default` applies to kinds without a threshold of their own.
+pub fn stale_days(config: &Config, mem: &Mem, default: u32) -> Option<u32> {
+    match settings(config, of(mem)).stale_days {
+        Some(0) => None,
+        Some(days) => Some(days),
+        None => Some(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn mem(kind: Option<&str>) -> Mem {
+        let mut mem = Mem::new(PathBuf::from("a"), "A".to_string(), String::new());
+        if let Some(kind) = kind {
+            mem.set_field("kind", kind);
+        }
+        mem
+    }
+
+    #[test]
+    fn test_of() {
+        assert_eq!(of(&mem(None)), "note");
+        assert_eq!(of(&mem(Some("adr"))), "adr");
+        let mut generated = mem(None);
+        generated
+            .extra
+            .insert("generated".to_string(), serde_yaml::Value::Bool(true));
+        assert_eq!(of(&generated), "generated");
+    }
+
+    #[test]
+    fn test_config_overrides_builtin() {
+        let config: Config = serde_yaml::from_str(
+            "kinds:\n  runbook:\n    stale-days: 30\n  adr:\n    required-headings: []\n",
+        )
+        .unwrap();
+
+        let runbook = settings(&config, "runbook");
+        assert_eq!(runbook.stale_days, Some(30));
+        assert_eq!(runbook.required_headings(), ["Steps"]);
+        assert!(settings(&config, "adr").required_headings().is_empty());
+
+        assert_eq!(stale_days(&config, &mem(Some("runbook")), 90), Some(30));
+        assert_eq!(stale_days(&config, &mem(None), 90), Some(90));
+        assert_eq!(stale_days(&config, &mem(Some("log")), 90), None);
+        assert_eq!(stale_days(&config, &mem(None), 0), Some(0));
+    }
+}
@@ -1,2 +1,86 @@
+pub mod activity;
+pub mod alias;
+pub mod analyzer;
+pub mod ask;
+pub mod attachment;
+pub mod backend;
+pub mod backup;
+pub mod bench;
+pub mod blame;
+pub mod bundle;
+pub mod cache;
+pub mod capture;
+pub mod chat;
+pub mod config;
+pub mod conflict;
+pub mod dates;
+pub mod decision;
+pub mod dictionary;
+pub mod diff;
+pub mod digest;
+pub mod directory;
+pub mod discover;
+pub mod dump;
+pub mod email;
+pub mod error;
+pub mod exit;
+pub mod export;
+pub mod federation;
+pub mod find;
+pub mod fuzzy;
+pub mod gc;
+pub mod generate;
+pub mod git;
+pub mod github;
+pub mod glob;
+pub mod glossary;
+pub mod hash;
+pub mod highlight;
+pub mod html;
+pub mod id;
+pub mod ignore;
+pub mod import;
+pub mod include;
+pub mod info;
+pub mod integrity;
+pub mod json;
+pub mod kind;
+pub mod linkcheck;
+pub mod links;
+pub mod lint;
+pub mod log;
+pub mod manifest;
 pub mod mem;
+pub mod meta;
+pub mod notify;
+pub mod preamble;
+pub mod progress;
+pub mod proposal;
+pub mod query;
+pub mod redirect;
+pub mod refresh;
+pub mod refs;
+pub mod related;
+pub mod replace;
+pub mod review;
+pub mod runbook;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod scaffold;
+pub mod schema;
+pub mod scope;
+pub mod semantic;
+pub mod serve;
+pub mod starter;
 pub mod storage;
+pub mod structure;
+pub mod sync;
+pub mod tasks;
+pub mod template;
+pub mod text;
+pub mod timestamps;
+pub mod tree;
+pub mod upcoming;
+pub mod usage;
+#[cfg(feature = "web")]
+pub mod web;
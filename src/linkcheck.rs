@@ -0,0 +1,259 @@
+//! External (http/https) link checking for `lint --check-external`.
+
+use crate::links::{self, Link};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Cache of link check results inside .mems/.
+pub const LINK_CACHE_FILE: &str = ".linkcache";
+
+/// Result of requesting a URL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkStatus {
+    /// HTTP status code, when the server answered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
+    /// Why the request failed, when it did not get an answer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    pub checked_at: DateTime<Utc>,
+}
+
+impl LinkStatus {
+    /// Whether the link is dead: no answer, or an error status other than
+    /// rate limiting.
+    pub fn is_dead(&self) -> bool {
+        self.status.is_none_or(|s| s >= 400 && s != 429)
+    }
+
+    /// Short reason for reports: the status code or the failure.
+    pub fn reason(&self) -> String {
+        match (self.status, &self.error) {
+            (Some(status), _) => status.to_string(),
+            (None, Some(error)) => error.clone(),
+            (None, None) => "no response".to_string(),
+        }
+    }
+}
+
+/// Previous results by URL, so reruns skip recently checked links.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkCache {
+    pub links: BTreeMap<String, LinkStatus>,
+}
+
+impl LinkCache {
+    /// Load the cache of a store, or an empty one.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(LINK_CACHE_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(&path).context("failed to read link cache")?;
+        serde_json::from_str(&text).context("invalid link cache")
+    }
+
+    pub fn save(&self, root: &Path) -> Result<()> {
+        fs::write(
+            root.join(LINK_CACHE_FILE),
+            serde_json::to_string_pretty(self)?,
+        )
+        .context("failed to write link cache")
+    }
+}
+
+/// How to check links.
+#[derive(Debug, Clone, Copy)]
+pub struct CheckOptions {
+    /// Requests in flight at once
+    pub concurrency: usize,
+
+    /// Seconds to wait for each request
+    pub timeout_secs: u64,
+
+    /// Hours a cached result stays valid
+    pub cache_hours: u32,
+}
+
+/// http(s) links in markdown content. A link title after the URL is dropped.
+pub fn external_links(content: &str) -> Vec<Link> {
+    links::extract_links(content)
+        .into_iter()
+        .filter_map(|mut link| {
+            let url = link.target.split_whitespace().next()?.to_string();
+            (url.starts_with("http://") || url.starts_with("https://")).then(|| {
+                link.target = url;
+                link
+            })
+        })
+        .collect()
+}
+
+/// Check `urls`, reusing cached results younger than `cache_hours` and
+/// recording new ones in `cache`.
+pub fn check(urls: &[String], cache: &mut LinkCache, options: CheckOptions, now: DateTime<Utc>) {
+    let max_age = Duration::hours(i64::from(options.cache_hours));
+    let mut pending: Vec<&String> = urls
+        .iter()
+        .filter(|url| {
+            cache
+                .links
+                .get(url.as_str())
+                .is_none_or(|s| now - s.checked_at > max_age)
+        })
+        .collect();
+    pending.sort();
+    pending.dedup();
+    if pending.is_empty() {
+        return;
+    }
+
+    let agent = ureq::AgentBuilder::new()
+        .timeout(std::time::Duration::from_secs(options.timeout_secs))
+        .build();
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..options.concurrency.clamp(1, pending.len()) {
+            scope.spawn(|| {
+                while let Some(url) = pending.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let status = probe(&agent, url, now);
+                    results
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push((url.to_string(), status));
+                }
+            });
+        }
+    });
+    cache
+        .links
+        .extend(results.into_inner().unwrap_or_else(|e| e.into_inner()));
+}
+
+/// Request a URL with HEAD, falling back to GET for servers that refuse HEAD.
+fn probe(agent: &ureq::Agent, url: &str, now: DateTime<Utc>) -> LinkStatus {
+    let status = |result: Result<ureq::Response, ureq::Error>| match result {
+        Ok(response) => (Some(response.status()), None),
+        Err(ureq::Error::Status(code, _)) => (Some(code), None),
+        Err(ureq::Error::Transport(e)) => (None, Some(e.kind().to_string())),
+    };
+    let mut result = status(agent.head(url).call());
+    if matches!(result.0, Some(403 | 405 | 501)) {
+        result = status(agent.get(url).call());
+    }
+    LinkStatus {
+        status: result.0,
+        error: result.1,
+        checked_at: now,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use tempfile::TempDir;
+
+    /// Serve `/ok` with 200, `/no-head` with 405 to HEAD and 200 to GET, and
+    /// everything else with 404. Returns the base URL.
+    fn serve(requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(&stream);
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                }
+                let status = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+                    [_, "/ok", ..] | ["GET", "/no-head", ..] => "200 OK",
+                    ["HEAD", "/no-head", ..] => "405 Method Not Allowed",
+                    _ => "404 Not Found",
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+        });
+        base
+    }
+
+    fn options() -> CheckOptions {
+        CheckOptions {
+            concurrency: 2,
+            timeout_secs: 5,
+            cache_hours: 24,
+        }
+    }
+
+    #[test]
+    fn test_external_links() {
+        let links =
+            external_links("[a](https://example.com \"Title\") [b](other.md) [c](http://x.test/p)");
+        let urls: Vec<&str> = links.iter().map(|l| l.target.as_str()).collect();
+        assert_eq!(urls, vec!["https://example.com", "http://x.test/p"]);
+    }
+
+    #[test]
+    fn test_check_and_cache() {
+        // /ok, HEAD and GET /no-head, /gone
+        let base = serve(4);
+        let urls: Vec<String> = ["/ok", "/no-head", "/gone", "/ok"]
+            .iter()
+            .map(|p| format!("{base}{p}"))
+            .collect();
+        let now = Utc::now();
+        let mut cache = LinkCache::default();
+        check(&urls, &mut cache, options(), now);
+
+        let status = |path: &str| cache.links[&format!("{base}{path}")].clone();
+        assert_eq!(status("/ok").status, Some(200));
+        assert_eq!(status("/no-head").status, Some(200));
+        assert_eq!(status("/gone").status, Some(404));
+        assert!(status("/gone").is_dead() && !status("/ok").is_dead());
+
+        // Fresh results are reused without requests (the server is done)
+        check(&urls, &mut cache, options(), now + Duration::hours(1));
+        assert_eq!(cache.links.len(), 3);
+
+        let temp = TempDir::new().unwrap();
+        cache.save(temp.path()).unwrap();
+        assert_eq!(LinkCache::load(temp.path()).unwrap().links, cache.links);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+
+        let mut cache = LinkCache::default();
+        check(
+            std::slice::from_ref(&url),
+            &mut cache,
+            options(),
+            Utc::now(),
+        );
+        assert!(cache.links[&url].status.is_none());
+        assert!(cache.links[&url].is_dead());
+    }
+}
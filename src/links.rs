@@ -0,0 +1,426 @@
+use crate::attachment::retarget_links;
+use crate::id::ID_PREFIX;
+use crate::mem::Mem;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+/// A markdown inline link found in mem content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    /// Link text between the brackets
+    pub text: String,
+
+    /// Raw link target between the parentheses
+    pub target: String,
+
+    /// 1-based line number within the content
+    pub line: usize,
+}
+
+/// Extract `[text](target)` links from markdown content.
+pub fn extract_links(content: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+
+    for (line_idx, line) in content.lines().enumerate() {
+        // Simple regex-free link extraction: find [text](target) patterns
+        let mut chars = line.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if c != '[' {
+                continue;
+            }
+
+            // Find closing ]
+            let mut depth = 1;
+            let mut j = i + 1;
+            for (idx, ch) in chars.by_ref() {
+                j = idx;
+                if ch == '[' {
+                    depth += 1;
+                } else if ch == ']' {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+
+            // Check for (
+            if let Some(&(_, '(')) = chars.peek() {
+                chars.next();
+                let start = j + 2;
+                let mut end = start;
+                for (idx, ch) in chars.by_ref() {
+                    if ch == ')' {
+                        end = idx;
+                        break;
+                    }
+                }
+                if end > start {
+                    links.push(Link {
+                        text: line[i + 1..j].to_string(),
+                        target: line[start..end].to_string(),
+                        line: line_idx + 1,
+                    });
+                }
+            }
+        }
+    }
+
+    links
+}
+
+/// A `[[id:<ulid>]]` (or `[[id:<ulid>|label]]`) link found in mem content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdLink {
+    /// Referenced mem id
+    pub id: String,
+
+    /// 1-based line number within the content
+    pub line: usize,
+}
+
+/// Extract links by id from markdown content.
+pub fn extract_id_links(content: &str) -> Vec<IdLink> {
+    let mut links = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let mut rest = line;
+        while let Some(start) = rest.find("[[id:") {
+            rest = &rest[start + 5..];
+            let Some(end) = rest.find("]]") else {
+                break;
+            };
+            let id = rest[..end].split('|').next().unwrap_or_default().trim();
+            if !id.is_empty() {
+                links.push(IdLink {
+                    id: id.to_string(),
+                    line: i + 1,
+                });
+            }
+            rest = &rest[end + 2..];
+        }
+    }
+    links
+}
+
+/// A link to a mem in another store: `[[store:path]]` (or
+/// `[[store:path|label]]`), or a markdown link to `store:path.md`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreLink {
+    /// Label of the store, e.g. the package name
+    pub store: String,
+
+    /// Mem path within that store
+    pub path: String,
+
+    /// 1-based line number within the content
+    pub line: usize,
+}
+
+/// Split a cross-store reference `store:path`. References by id, URLs, and
+/// store parts that look like paths are not cross-store references.
+pub fn parse_store_reference(reference: &str) -> Option<(&str, &str)> {
+    let (store, path) = reference.split_once(':')?;
+    let store_ok = !store.is_empty()
+        && format!("{store}:") != ID_PREFIX
+        && !store.contains(['/', '\\', ' ', '.']);
+    let path = path.trim();
+    (store_ok && !path.is_empty() && !path.starts_with('/')).then_some((store, path))
+}
+
+/// Extract cross-store links from markdown content (`![[...]]` includes are
+/// not links), in document order.
+pub fn extract_store_links(content: &str) -> Vec<StoreLink> {
+    let mut links: Vec<StoreLink> = extract_links(content)
+        .iter()
+        .filter_map(|link| {
+            let reference = link.target.strip_suffix(".md")?;
+            let (store, path) = parse_store_reference(reference)?;
+            Some(StoreLink {
+                store: store.to_string(),
+                path: path.to_string(),
+                line: link.line,
+            })
+        })
+        .collect();
+    for (i, line) in content.lines().enumerate() {
+        let mut rest = line;
+        while let Some(start) = rest.find("[[") {
+            let embedded = rest[..start].ends_with('!');
+            rest = &rest[start + 2..];
+            let Some(end) = rest.find("]]") else {
+                break;
+            };
+            let reference = rest[..end].split('|').next().unwrap_or_default().trim();
+            if let Some((store, path)) = parse_store_reference(reference).filter(|_| !embedded) {
+                links.push(StoreLink {
+                    store: store.to_string(),
+                    path: path.to_string(),
+                    line: i + 1,
+                });
+            }
+            rest = &rest[end + 2..];
+        }
+    }
+    links.sort_by_key(|l| l.line);
+    links
+}
+
+/// Point cross-store links to `from` in any of the `stores` (names of one
+/// store) at `to` instead. Returns the new content if anything changed.
+pub fn rewrite_store_links(content: &str, stores: &[&str], from: &str, to: &str) -> Option<String> {
+    let mut rewritten = content.to_string();
+    for store in stores {
+        for (old, new) in [
+            (format!("[[{store}:{from}]]"), format!("[[{store}:{to}]]")),
+            (format!("[[{store}:{from}|"), format!("[[{store}:{to}|")),
+            (
+                format!("]({store}:{from}.md)"),
+                format!("]({store}:{to}.md)"),
+            ),
+        ] {
+            rewritten = rewritten.replace(&old, &new);
+        }
+    }
+    (rewritten != content).then_some(rewritten)
+}
+
+/// Resolve a relative `.md` link target to a mem path, relative to the linking mem.
+///
+/// Returns `None` for external links, cross-store links, and non-markdown
+/// targets.
+pub fn resolve_internal(mem_path: &Path, target: &str) -> Option<String> {
+    let reference = target.strip_suffix(".md")?;
+    if target.starts_with("http") || parse_store_reference(reference).is_some() {
+        return None;
+    }
+
+    let mem_dir = mem_path.parent().unwrap_or(Path::new(""));
+    let joined = mem_dir.join(target.trim_end_matches(".md"));
+
+    // Lexically normalize so `../x` links compare equal to mem paths
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    Some(normalized.to_string_lossy().to_string())
+}
+
+/// Relative `.md` link target from one mem to another (the inverse of
+/// `resolve_internal`).
+pub fn relative_target(from: &str, to: &str) -> String {
+    let from_dir: Vec<&str> = from.split('/').collect();
+    let from_dir = &from_dir[..from_dir.len() - 1];
+    let to: Vec<&str> = to.split('/').collect();
+
+    let common = from_dir
+        .iter()
+        .zip(&to[..to.len() - 1])
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut parts = vec![".."; from_dir.len() - common];
+    parts.extend(&to[common..]);
+    format!("{}.md", parts.join("/"))
+}
+
+/// `content` of a mem moved from `from` to `to` with its relative links to
+/// other mems pointed at them from the new place, or `None` if none change.
+pub fn relocate(content: &str, from: &Path, to: &str) -> Option<String> {
+    let mut relocated = content.to_string();
+    let mut seen = HashSet::new();
+    for link in extract_links(content) {
+        if !seen.insert(link.target.clone()) {
+            continue;
+        }
+        let Some(target) = resolve_internal(from, &link.target) else {
+            continue;
+        };
+        let fixed = relative_target(to, &target);
+        if fixed != link.target {
+            relocated = retarget_links(&relocated, &link.target, &fixed);
+        }
+    }
+    (relocated != content).then_some(relocated)
+}
+
+/// What a mem links to, deduplicated and sorted: mem paths for relative
+/// links and `id:<ULID>` (uppercased) for links by id.
+pub fn targets(mem: &Mem) -> Vec<String> {
+    let mut targets: Vec<String> = extract_links(&mem.content)
+        .iter()
+        .filter_map(|l| resolve_internal(&mem.path, &l.target))
+        .chain(
+            extract_id_links(&mem.content)
+                .iter()
+                .map(|l| format!("{ID_PREFIX}{}", l.id.to_uppercase())),
+        )
+        .collect();
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+/// Paths of the mems that link to `path`, by path or by id.
+pub fn backlinks(mems: &[Mem], path: &str) -> Vec<String> {
+    let by_id = mems
+        .iter()
+        .find(|m| m.path_str() == path)
+        .and_then(|m| m.id.as_ref())
+        .map(|id| format!("{ID_PREFIX}{}", id.to_uppercase()));
+
+    mems.iter()
+        .filter(|m| {
+            targets(m)
+                .iter()
+                .any(|t| t == path || Some(t) == by_id.as_ref())
+        })
+        .map(|m| m.path_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links() {
+        let content = "See [the [nested] doc](a/b.md) and [site](https://x.io).\n\nAlso [c](c.md)";
+        let links = extract_links(content);
+
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].text, "the [nested] doc");
+        assert_eq!(links[0].target, "a/b.md");
+        assert_eq!(links[0].line, 1);
+        assert_eq!(links[1].target, "https://x.io");
+        assert_eq!(links[2].target, "c.md");
+        assert_eq!(links[2].line, 3);
+    }
+
+    #[test]
+    fn test_extract_id_links() {
+        let links =
+            extract_id_links("See [[id:01ABC]] and\n[[id: 01DEF | the def]] [[id:]] [[other]]");
+        assert_eq!(
+            links,
+            vec![
+                IdLink {
+                    id: "01ABC".to_string(),
+                    line: 1
+                },
+                IdLink {
+                    id: "01DEF".to_string(),
+                    line: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_store_links() {
+        let links = extract_store_links(
+            "Uses [[billing:invoices/retries|retries]] and [[id:01ABC]].\n\
+             ![[billing:shared/footer]] [[https://example.com]] [[notes]]\n\
+             [Ledger](billing:ledger.md), [mail](mailto:a@example.com)",
+        );
+        assert_eq!(
+            links,
+            vec![
+                StoreLink {
+                    store: "billing".to_string(),
+                    path: "invoices/retries".to_string(),
+                    line: 1
+                },
+                StoreLink {
+                    store: "billing".to_string(),
+                    path: "ledger".to_string(),
+                    line: 3
+                },
+            ]
+        );
+        assert_eq!(resolve_internal(Path::new("a"), "billing:ledger.md"), None);
+        assert_eq!(parse_store_reference("id:01ABC"), None);
+        assert_eq!(parse_store_reference("./a:b"), None);
+    }
+
+    #[test]
+    fn test_rewrite_store_links() {
+        let content = "[[billing:old]], [[billing:old|it]], [x](billing:old.md), [[billing:older]]";
+        assert_eq!(
+            rewrite_store_links(content, &["billing"], "old", "new").unwrap(),
+            "[[billing:new]], [[billing:new|it]], [x](billing:new.md), [[billing:older]]"
+        );
+        assert_eq!(
+            rewrite_store_links(content, &["search"], "old", "new"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_ignores_plain_brackets() {
+        assert!(extract_links("[not a link] and [empty]()").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_internal() {
+        let mem = Path::new("arch/decisions/adr-001");
+        assert_eq!(
+            resolve_internal(mem, "adr-002.md").as_deref(),
+            Some("arch/decisions/adr-002")
+        );
+        assert_eq!(
+            resolve_internal(mem, "../overview.md").as_deref(),
+            Some("arch/overview")
+        );
+        assert_eq!(resolve_internal(mem, "https://x.io/a.md"), None);
+        assert_eq!(resolve_internal(mem, "image.png"), None);
+    }
+
+    #[test]
+    fn test_relative_target() {
+        assert_eq!(relative_target("a/b/c", "a/b/d"), "d.md");
+        assert_eq!(relative_target("a/b/c", "a/x"), "../x.md");
+        assert_eq!(relative_target("top", "a/b"), "a/b.md");
+        assert_eq!(relative_target("a/b", "a"), "../a.md");
+        let target = relative_target("a/b/c", "x/y");
+        assert_eq!(
+            resolve_internal(Path::new("a/b/c"), &target).as_deref(),
+            Some("x/y")
+        );
+    }
+
+    #[test]
+    fn test_relocate() {
+        let content = "See [c](../a/c.md), [c again](../a/c.md), [d](d.md#top), and [web](https://x.io/e.md).";
+        assert_eq!(
+            relocate(content, Path::new("notes/x"), "notes/x/part").as_deref(),
+            Some("See [c](../../a/c.md), [c again](../../a/c.md), [d](d.md#top), and [web](https://x.io/e.md).")
+        );
+        assert_eq!(relocate(content, Path::new("notes/x"), "notes/y"), None);
+    }
+
+    #[test]
+    fn test_backlinks() {
+        let mem = |path: &str, content: &str| {
+            Mem::new(PathBuf::from(path), path.to_string(), content.to_string())
+        };
+        let mems = vec![
+            mem("a/one", "See [two](two.md) and [two again](./two.md)"),
+            mem("b", "See [two](a/two.md)"),
+            mem("a/two", "No links"),
+        ];
+
+        assert_eq!(targets(&mems[0]), vec!["a/two"]);
+        assert_eq!(backlinks(&mems, "a/two"), vec!["a/one", "b"]);
+        assert!(backlinks(&mems, "b").is_empty());
+
+        let mut mems = mems;
+        mems[1].id = Some("01ARZ3NDEKTSV4RRFFQ69G5FAV".to_string());
+        mems.push(mem("c", "Refers to [[id:01arz3ndektsv4rrffq69g5fav|b]]"));
+        assert_eq!(backlinks(&mems, "b"), vec!["c"]);
+    }
+}
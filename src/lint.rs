@@ -0,0 +1,1290 @@
+use crate::config::{Config, LintConfig};
+use crate::conflict::{self, MergeConflict};
+use crate::dates;
+use crate::dictionary::Dictionary;
+use crate::discover;
+use crate::error::MemError;
+use crate::git;
+use crate::glossary::{self, Term};
+use crate::include;
+use crate::linkcheck::{self, CheckOptions, LinkCache};
+use crate::links;
+use crate::mem::{self, Mem};
+use crate::progress::Progress;
+use crate::redirect;
+use crate::schema::{self, Violation};
+use crate::storage::Storage;
+use crate::structure;
+use crate::timestamps::{self, History, Problem};
+use anyhow::Result;
+use chrono::Utc;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// How serious a lint finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Lowercase name, as used in output formats.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// A severity set for a rule in the lint config, or `off` to disable it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    Warning,
+    Error,
+}
+
+/// A lint rule with its default severity.
+#[derive(Debug, Clone, Copy)]
+pub struct Rule {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub severity: Severity,
+}
+
+pub const NOT_TEXT: Rule = Rule {
+    id: "not-text",
+    description: "Mem file is binary or not valid UTF-8",
+    severity: Severity::Error,
+};
+
+pub const MERGE_CONFLICT: Rule = Rule {
+    id: "merge-conflict",
+    description: "Mem file has unresolved git conflict markers",
+    severity: Severity::Error,
+};
+
+pub const EMPTY_TITLE: Rule = Rule {
+    id: "empty-title",
+    description: "Mem has an empty title",
+    severity: Severity::Error,
+};
+
+pub const EMPTY_CONTENT: Rule = Rule {
+    id: "empty-content",
+    description: "Mem has no content",
+    severity: Severity::Warning,
+};
+
+pub const BROKEN_LINK: Rule = Rule {
+    id: "broken-link",
+    description: "Internal link points to a mem that does not exist",
+    severity: Severity::Error,
+};
+
+pub const BROKEN_ID_LINK: Rule = Rule {
+    id: "broken-id-link",
+    description: "Link by id points to an id no mem has",
+    severity: Severity::Error,
+};
+
+pub const DUPLICATE_ID: Rule = Rule {
+    id: "duplicate-id",
+    description: "Mem has the same id as another mem",
+    severity: Severity::Error,
+};
+
+pub const BROKEN_INCLUDE: Rule = Rule {
+    id: "broken-include",
+    description: "Include directive points to a mem that does not exist",
+    severity: Severity::Error,
+};
+
+pub const INCLUDE_CYCLE: Rule = Rule {
+    id: "include-cycle",
+    description: "Include directives form a cycle",
+    severity: Severity::Error,
+};
+
+pub const BROKEN_REDIRECT: Rule = Rule {
+    id: "broken-redirect",
+    description: "Redirect stub points to a mem that does not exist",
+    severity: Severity::Error,
+};
+
+pub const REDIRECT_CHAIN: Rule = Rule {
+    id: "redirect-chain",
+    description: "Redirect stub points to another redirect stub",
+    severity: Severity::Warning,
+};
+
+pub const MISSING_FIELD: Rule = Rule {
+    id: "missing-field",
+    description: "Mem lacks a frontmatter field its schema requires",
+    severity: Severity::Error,
+};
+
+pub const INVALID_STATUS: Rule = Rule {
+    id: "invalid-status",
+    description: "Mem status is not one its schema allows",
+    severity: Severity::Error,
+};
+
+pub const UNKNOWN_TAG: Rule = Rule {
+    id: "unknown-tag",
+    description: "Mem has a tag its schema does not allow",
+    severity: Severity::Error,
+};
+
+pub const MISSING_HEADING: Rule = Rule {
+    id: "missing-heading",
+    description: "Mem lacks a section heading its schema requires",
+    severity: Severity::Error,
+};
+
+pub const SKIPPED_HEADING_LEVEL: Rule = Rule {
+    id: "skipped-heading-level",
+    description: "Heading is more than one level deeper than the one before",
+    severity: Severity::Warning,
+};
+
+pub const LONG_PARAGRAPH: Rule = Rule {
+    id: "long-paragraph",
+    description: "Paragraph has more words than the configured limit",
+    severity: Severity::Warning,
+};
+
+pub const LARGE_MEM: Rule = Rule {
+    id: "large-mem",
+    description: "Mem file is larger than the configured max-mem-kb",
+    severity: Severity::Warning,
+};
+
+pub const TIMESTAMP_ORDER: Rule = Rule {
+    id: "timestamp-order",
+    description: "Mem was updated before it was created",
+    severity: Severity::Error,
+};
+
+pub const FUTURE_TIMESTAMP: Rule = Rule {
+    id: "future-timestamp",
+    description: "Frontmatter timestamp is in the future",
+    severity: Severity::Warning,
+};
+
+pub const NON_UTC_TIMESTAMP: Rule = Rule {
+    id: "non-utc-timestamp",
+    description: "Frontmatter timestamp has an offset other than UTC",
+    severity: Severity::Warning,
+};
+
+pub const TIMESTAMP_BEFORE_HISTORY: Rule = Rule {
+    id: "timestamp-before-history",
+    description: "updated-at is far older than the file's last git commit",
+    severity: Severity::Warning,
+};
+
+pub const TERMINOLOGY: Rule = Rule {
+    id: "terminology",
+    description: "Mem uses a term the project dictionary replaces or bans",
+    severity: Severity::Warning,
+};
+
+pub const UNLINKED_TERM: Rule = Rule {
+    id: "unlinked-term",
+    description: "Mem uses a glossary term without linking to its definition",
+    severity: Severity::Warning,
+};
+
+pub const DEAD_LINK: Rule = Rule {
+    id: "dead-link",
+    description: "External link does not answer or returns an error status",
+    severity: Severity::Warning,
+};
+
+/// All rules, for output formats that describe them up front.
+pub const RULES: &[Rule] = &[
+    NOT_TEXT,
+    MERGE_CONFLICT,
+    EMPTY_TITLE,
+    EMPTY_CONTENT,
+    BROKEN_LINK,
+    BROKEN_ID_LINK,
+    DUPLICATE_ID,
+    BROKEN_INCLUDE,
+    INCLUDE_CYCLE,
+    BROKEN_REDIRECT,
+    REDIRECT_CHAIN,
+    MISSING_FIELD,
+    INVALID_STATUS,
+    UNKNOWN_TAG,
+    MISSING_HEADING,
+    SKIPPED_HEADING_LEVEL,
+    LONG_PARAGRAPH,
+    LARGE_MEM,
+    TIMESTAMP_ORDER,
+    FUTURE_TIMESTAMP,
+    NON_UTC_TIMESTAMP,
+    TIMESTAMP_BEFORE_HISTORY,
+    TERMINOLOGY,
+    UNLINKED_TERM,
+    DEAD_LINK,
+];
+
+/// A single lint finding.
+#[derive(Debug, Clone)]
+pub struct Issue {
+    /// Store label (empty for the default store)
+    pub store: String,
+
+    /// Mem path within the store
+    pub path: String,
+
+    /// File on disk
+    pub file: PathBuf,
+
+    /// 1-based line in the file
+    pub line: usize,
+
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+
+    /// Mem path (or `id:<ULID>`) the issue refers to, for link rules
+    pub target: Option<String>,
+}
+
+/// Lint every mem in a store, returning the number checked and the issues found.
+pub fn lint_storage(
+    label: &str,
+    storage: &Storage,
+    progress: &dyn Progress,
+) -> Result<(usize, Vec<Issue>)> {
+    let mems = storage.list_mems()?;
+    let ids = known_ids(&mems);
+    let terms = glossary::all_terms(&mems);
+    let history = History::load(storage);
+    let mut issues = unreadable(label, storage, &mems, None)?;
+    issues.extend(duplicate_ids(label, storage, &mems, None));
+    progress.start("Linting", mems.len());
+    for mem in &mems {
+        issues.extend(lint_mem(label, storage, mem, &ids, &terms, &history));
+        progress.advance(&mem.path_str());
+    }
+    progress.finish();
+    Ok((mems.len(), issues))
+}
+
+/// Issues for mem files (or only those in `only`) that can't be read as
+/// text or have conflicts in their frontmatter, which listing skips and so
+/// `mems`, the mems read, leaves out.
+fn unreadable(
+    label: &str,
+    storage: &Storage,
+    mems: &[Mem],
+    only: Option<&HashSet<String>>,
+) -> Result<Vec<Issue>> {
+    let read: HashSet<String> = mems.iter().map(Mem::path_str).collect();
+    let mut issues = Vec::new();
+    for path in storage.mem_keys("")? {
+        if read.contains(&path) || only.is_some_and(|only| !only.contains(&path)) {
+            continue;
+        }
+        let e = match storage.read_source(&path) {
+            Ok(source) => {
+                if let Some(line) = conflict::first_conflict(&source) {
+                    issues.push(Issue {
+                        store: label.to_string(),
+                        file: storage.mem_path(&path),
+                        message: MergeConflict { path: path.clone() }.to_string(),
+                        path,
+                        line,
+                        rule: MERGE_CONFLICT.id,
+                        severity: MERGE_CONFLICT.severity,
+                        target: None,
+                    });
+                }
+                continue;
+            }
+            Err(e) => e,
+        };
+        if let Some(e @ MemError::NotText { .. }) = e.downcast_ref::<MemError>() {
+            issues.push(Issue {
+                store: label.to_string(),
+                file: storage.mem_path(&path),
+                path,
+                line: 1,
+                rule: NOT_TEXT.id,
+                severity: NOT_TEXT.severity,
+                message: e.to_string(),
+                target: None,
+            });
+        }
+    }
+    if let Ok(config) = Config::for_storage(storage) {
+        apply_levels(&mut issues, &config.lint.rules);
+    }
+    Ok(issues)
+}
+
+/// Issues for mems (or only those in `only`) whose id another mem has too,
+/// such as a copy a file sync tool made, which lookups by id can't tell apart.
+fn duplicate_ids(
+    label: &str,
+    storage: &Storage,
+    mems: &[Mem],
+    only: Option<&HashSet<String>>,
+) -> Vec<Issue> {
+    let mut by_id: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for mem in mems {
+        if let Some(id) = &mem.id {
+            by_id
+                .entry(id.to_uppercase())
+                .or_default()
+                .push(mem.path_str());
+        }
+    }
+
+    let mut issues = Vec::new();
+    for (id, paths) in by_id.iter().filter(|(_, paths)| paths.len() > 1) {
+        for path in paths {
+            if only.is_some_and(|only| !only.contains(path)) {
+                continue;
+            }
+            let others: Vec<&str> = paths
+                .iter()
+                .filter(|p| *p != path)
+                .map(String::as_str)
+                .collect();
+            let raw = storage.read_source(path).unwrap_or_default();
+            issues.push(Issue {
+                store: label.to_string(),
+                path: path.clone(),
+                file: storage.mem_path(path),
+                line: field_line(&raw, "id"),
+                rule: DUPLICATE_ID.id,
+                severity: DUPLICATE_ID.severity,
+                message: format!("id {id} is also used by {}", others.join(", ")),
+                target: None,
+            });
+        }
+    }
+    if let Ok(config) = Config::for_storage(storage) {
+        apply_levels(&mut issues, &config.lint.rules);
+    }
+    issues
+}
+
+/// Mem paths changed since a git ref, or updated at/after a timestamp.
+///
+/// With a git ref, deleted and untracked mems are included, so callers can
+/// also check links that point at mems which no longer exist.
+pub fn changed_mems(storage: &Storage, since: &str) -> Result<HashSet<String>> {
+    if let Some(timestamp) = dates::parse_timestamp(since) {
+        return Ok(storage
+            .list_mems()?
+            .into_iter()
+            .filter(|m| m.updated_at >= timestamp)
+            .map(|m| m.path_str())
+            .collect());
+    }
+
+    // git reports canonical paths, so compare against a canonical root
+    let canonical = Storage::new(storage.local_root()?.canonicalize()?);
+    Ok(git::changed_since(canonical.root(), since)?
+        .iter()
+        .filter_map(|f| canonical.path_for_file(f))
+        .collect())
+}
+
+/// Lint only changed mems, plus links from unchanged mems into changed ones.
+///
+/// Returns the number of changed mems checked and the issues found.
+pub fn lint_changed(
+    label: &str,
+    storage: &Storage,
+    changed: &HashSet<String>,
+    progress: &dyn Progress,
+) -> Result<(usize, Vec<Issue>)> {
+    let mems = storage.list_mems()?;
+    let ids = known_ids(&mems);
+    let terms = glossary::all_terms(&mems);
+    let history = History::load(storage);
+    let mut checked = 0;
+    let mut issues = unreadable(label, storage, &mems, Some(changed))?;
+    issues.extend(duplicate_ids(label, storage, &mems, Some(changed)));
+
+    progress.start("Linting", mems.len());
+    for mem in &mems {
+        let path = mem.path_str();
+        if changed.contains(&path) {
+            checked += 1;
+            issues.extend(lint_mem(label, storage, mem, &ids, &terms, &history));
+        } else {
+            // Only links are checked: those into changed mems may have been
+            // broken by a rename or delete. Deleted ids can't be traced to a
+            // path, so broken links by id are always reported.
+            let raw = storage.read_source(&path).unwrap_or_default();
+            let file = storage.mem_path(&path);
+            let offset = mem::content_line_offset(&raw);
+            check_links(
+                storage,
+                mem,
+                &ids,
+                offset,
+                &mut |rule, line, message, target| {
+                    let into_changed = target.as_ref().is_some_and(|t| changed.contains(t));
+                    if rule.id == BROKEN_ID_LINK.id || into_changed {
+                        issues.push(Issue {
+                            store: label.to_string(),
+                            path: path.clone(),
+                            file: file.clone(),
+                            line,
+                            rule: rule.id,
+                            severity: rule.severity,
+                            message,
+                            target,
+                        });
+                    }
+                },
+            );
+        }
+        progress.advance(&path);
+    }
+    progress.finish();
+
+    Ok((checked, issues))
+}
+
+/// Report `mem`'s broken links, by path and by id. Its content starts after
+/// line `offset` of its file.
+fn check_links(
+    storage: &Storage,
+    mem: &Mem,
+    ids: &HashSet<String>,
+    offset: usize,
+    report: &mut dyn FnMut(Rule, usize, String, Option<String>),
+) {
+    // Check for broken internal links
+    for link in links::extract_links(&mem.content) {
+        if let Some(target) = links::resolve_internal(&mem.path, &link.target) {
+            if !storage.exists(&target) {
+                report(
+                    BROKEN_LINK,
+                    offset + link.line,
+                    format!("broken link to {}", link.target),
+                    Some(target),
+                );
+            }
+        }
+    }
+
+    // Check for links by id that resolve to no mem
+    for link in links::extract_id_links(&mem.content) {
+        let id = link.id.to_uppercase();
+        if !ids.contains(&id) {
+            report(
+                BROKEN_ID_LINK,
+                offset + link.line,
+                format!("broken link to id:{}", link.id),
+                Some(format!("{}{id}", crate::id::ID_PREFIX)),
+            );
+        }
+    }
+}
+
+/// Uppercased ids of a store's mems, for checking links by id.
+pub fn known_ids(mems: &[Mem]) -> HashSet<String> {
+    mems.iter()
+        .filter_map(|m| m.id.as_ref())
+        .map(|id| id.to_uppercase())
+        .collect()
+}
+
+/// Lint a single mem. `ids` are the store's [`known_ids`], `terms` the
+/// terms its glossaries define, and `history` its git commit times.
+pub fn lint_mem(
+    label: &str,
+    storage: &Storage,
+    mem: &Mem,
+    ids: &HashSet<String>,
+    terms: &[Term],
+    history: &History,
+) -> Vec<Issue> {
+    let path = mem.path_str();
+    let file = storage.mem_path(&path);
+    let raw = storage.read_source(&path).unwrap_or_default();
+    let offset = mem::content_line_offset(&raw);
+
+    let mut issues = Vec::new();
+    let mut report = |rule: Rule, line: usize, message: String, target: Option<String>| {
+        issues.push(Issue {
+            store: label.to_string(),
+            path: path.clone(),
+            file: file.clone(),
+            line,
+            rule: rule.id,
+            severity: rule.severity,
+            message,
+            target,
+        });
+    };
+
+    // Check for conflict markers git left in the content
+    if let Some(line) = conflict::first_conflict(&raw) {
+        report(
+            MERGE_CONFLICT,
+            line,
+            MergeConflict { path: path.clone() }.to_string(),
+            None,
+        );
+    }
+
+    // Check for empty title
+    if mem.title.trim().is_empty() {
+        let line = raw
+            .lines()
+            .position(|l| l.starts_with("title:"))
+            .map(|i| i + 1)
+            .unwrap_or(1);
+        report(EMPTY_TITLE, line, "empty title".to_string(), None);
+    }
+
+    // Check for empty content
+    if mem.content.trim().is_empty() {
+        report(
+            EMPTY_CONTENT,
+            offset.max(1),
+            "empty content".to_string(),
+            None,
+        );
+    }
+
+    check_links(storage, mem, ids, offset, &mut report);
+
+    // Check include targets, and cycles through this mem
+    let includes = include::extract(&mem.content);
+    for directive in &includes {
+        if include::resolve(storage, &directive.target)
+            .ok()
+            .flatten()
+            .is_none()
+        {
+            report(
+                BROKEN_INCLUDE,
+                offset + directive.line,
+                format!("broken include of {}", directive.target),
+                Some(directive.target.clone()),
+            );
+        }
+    }
+    if let Ok(Some(chain)) = include::find_cycle(storage, &path) {
+        let line = includes
+            .iter()
+            .find(|d| include::resolve(storage, &d.target).ok().flatten().as_ref() == chain.get(1))
+            .map_or(offset.max(1), |d| offset + d.line);
+        report(
+            INCLUDE_CYCLE,
+            line,
+            format!("include cycle: {}", chain.join(" -> ")),
+            None,
+        );
+    }
+
+    // Check that a redirect stub leads straight to a mem
+    if let Some(to) = redirect::target(mem) {
+        let line = field_line(&raw, redirect::REDIRECT_FIELD);
+        if !storage.exists(to) {
+            report(
+                BROKEN_REDIRECT,
+                line,
+                format!("redirect to {to}, which does not exist"),
+                Some(to.to_string()),
+            );
+        } else if let Some(next) = storage
+            .read_mem(to)
+            .ok()
+            .as_ref()
+            .and_then(redirect::target)
+        {
+            report(
+                REDIRECT_CHAIN,
+                line,
+                format!("redirect to {to}, which redirects to {next}"),
+                Some(to.to_string()),
+            );
+        }
+    }
+
+    // Check heading structure, paragraph length, and file size
+    let config = Config::for_storage(storage);
+    for (heading, previous) in structure::skipped_levels(&structure::headings(&mem.content)) {
+        report(
+            SKIPPED_HEADING_LEVEL,
+            offset + heading.line,
+            format!(
+                "heading level skips from h{previous} to h{}: {}",
+                heading.level, heading.text
+            ),
+            None,
+        );
+    }
+    let max_words = config
+        .as_ref()
+        .map_or(LintConfig::default().max_paragraph_words, |c| {
+            c.lint.max_paragraph_words
+        });
+    for paragraph in structure::paragraphs(&mem.content) {
+        if paragraph.words > max_words {
+            report(
+                LONG_PARAGRAPH,
+                offset + paragraph.line,
+                format!(
+                    "paragraph has {} words (limit {max_words})",
+                    paragraph.words
+                ),
+                None,
+            );
+        }
+    }
+
+    let max_bytes = config
+        .as_ref()
+        .map_or(Config::default().max_mem_bytes(), Config::max_mem_bytes);
+    if let Some(max_bytes) = max_bytes.filter(|&max| raw.len() as u64 > max) {
+        report(
+            LARGE_MEM,
+            1,
+            format!(
+                "mem is {} KB (limit {} KB); split it or link to the file instead",
+                raw.len().div_ceil(1024),
+                max_bytes / 1024
+            ),
+            None,
+        );
+    }
+
+    // Check that frontmatter timestamps are sane
+    let max_gap_days = config
+        .as_ref()
+        .map_or(LintConfig::default().max_history_gap_days, |c| {
+            c.lint.max_history_gap_days
+        });
+    let last_commit = history.last_commit(&path);
+    for problem in timestamps::check(mem, &raw, Utc::now(), last_commit, max_gap_days) {
+        let rule = match problem {
+            Problem::Order => TIMESTAMP_ORDER,
+            Problem::Future(_) => FUTURE_TIMESTAMP,
+            Problem::NotUtc(..) => NON_UTC_TIMESTAMP,
+            Problem::BeforeHistory(_) => TIMESTAMP_BEFORE_HISTORY,
+        };
+        report(
+            rule,
+            field_line(&raw, problem.field()),
+            problem.message(mem),
+            None,
+        );
+    }
+
+    // Check terminology against the project dictionary
+    if let Ok(Some(dictionary)) = Dictionary::load(storage) {
+        for hit in dictionary.check(&mem.content) {
+            report(TERMINOLOGY, offset + hit.line, hit.message(), None);
+        }
+    }
+
+    // Check that glossary terms link to their definitions
+    if !glossary::is_glossary(mem) {
+        for term in terms {
+            if let Some((name, line)) = glossary::first_use(term, &mem.content) {
+                if !term.is_linked_from(mem) {
+                    report(
+                        UNLINKED_TERM,
+                        offset + line,
+                        format!("{name:?} is defined in {}; link to it", term.path),
+                        Some(term.path.clone()),
+                    );
+                }
+            }
+        }
+    }
+
+    // Check the schema for this mem
+    let schema = config
+        .as_ref()
+        .map_err(|e| anyhow::anyhow!("{e}"))
+        .and_then(|config| schema::for_mem(storage, config, mem));
+    match schema {
+        Ok(schema) => {
+            for violation in schema.check(mem) {
+                let (rule, line) = match &violation {
+                    Violation::MissingField(_) => (MISSING_FIELD, 1),
+                    Violation::InvalidStatus(_) => (INVALID_STATUS, field_line(&raw, "status")),
+                    Violation::UnknownTag(tag) => (UNKNOWN_TAG, tag_line(&raw, tag)),
+                    Violation::MissingHeading(_) => (MISSING_HEADING, offset.max(1)),
+                };
+                report(rule, line, violation.message(&schema), None);
+            }
+        }
+        Err(e) => report(MISSING_FIELD, 1, e.to_string(), None),
+    }
+
+    if let Ok(config) = &config {
+        apply_levels(&mut issues, &config.lint.rules);
+    }
+    issues
+}
+
+/// Check the external links of a store's mems (or only those in `only`).
+///
+/// Results are cached in `.mems/.linkcache` for local stores, so reruns
+/// within `lint.external-cache-hours` make no requests.
+pub fn lint_external(
+    label: &str,
+    storage: &Storage,
+    only: Option<&HashSet<String>>,
+) -> Result<Vec<Issue>> {
+    let config = Config::for_storage(storage)?;
+    let root = storage.local_root().ok();
+    let mut cache = match root {
+        Some(root) => LinkCache::load(root)?,
+        None => LinkCache::default(),
+    };
+
+    let mut found = Vec::new();
+    for mem in storage.list_mems()? {
+        let path = mem.path_str();
+        if only.is_some_and(|only| !only.contains(&path)) {
+            continue;
+        }
+        let raw = storage.read_source(&path).unwrap_or_default();
+        let offset = mem::content_line_offset(&raw);
+        for link in linkcheck::external_links(&mem.content) {
+            found.push((path.clone(), offset + link.line, link.target));
+        }
+    }
+
+    let urls: Vec<String> = found.iter().map(|(_, _, url)| url.clone()).collect();
+    let options = CheckOptions {
+        concurrency: config.lint.external_concurrency,
+        timeout_secs: config.lint.external_timeout_secs,
+        cache_hours: config.lint.external_cache_hours,
+    };
+    linkcheck::check(&urls, &mut cache, options, chrono::Utc::now());
+    if let Some(root) = root {
+        cache.save(root)?;
+    }
+
+    let mut issues: Vec<Issue> = found
+        .into_iter()
+        .filter_map(|(path, line, url)| {
+            let status = cache.links.get(&url).filter(|s| s.is_dead())?;
+            Some(Issue {
+                store: label.to_string(),
+                file: storage.mem_path(&path),
+                path,
+                line,
+                rule: DEAD_LINK.id,
+                severity: DEAD_LINK.severity,
+                message: format!("dead link to {url} ({})", status.reason()),
+                target: Some(url),
+            })
+        })
+        .collect();
+    apply_levels(&mut issues, &config.lint.rules);
+    Ok(issues)
+}
+
+/// Check the cross-store links (`store:path`) in a store's mems (or only
+/// those in `only`) against the other stores in play.
+pub fn lint_store_links(
+    label: &str,
+    storage: &Storage,
+    stores: &[(String, Storage)],
+    only: Option<&HashSet<String>>,
+) -> Result<Vec<Issue>> {
+    let mut issues = Vec::new();
+    for mem in storage.list_mems()? {
+        let path = mem.path_str();
+        if only.is_some_and(|only| !only.contains(&path)) {
+            continue;
+        }
+        let links = links::extract_store_links(&mem.content);
+        if links.is_empty() {
+            continue;
+        }
+        let offset = mem::content_line_offset(&storage.read_source(&path).unwrap_or_default());
+        for link in links {
+            let exists = stores.iter().any(|(l, s)| {
+                discover::names(l, s.root()).contains(&link.store) && s.exists(&link.path)
+            });
+            if !exists {
+                let target = format!("{}:{}", link.store, link.path);
+                issues.push(Issue {
+                    store: label.to_string(),
+                    path: path.clone(),
+                    file: storage.mem_path(&path),
+                    line: offset + link.line,
+                    rule: BROKEN_LINK.id,
+                    severity: BROKEN_LINK.severity,
+                    message: format!("broken link to {target}"),
+                    target: Some(target),
+                });
+            }
+        }
+    }
+    apply_levels(&mut issues, &Config::for_storage(storage)?.lint.rules);
+    Ok(issues)
+}
+
+/// Apply the project dictionary's replacements to a store's mems (or only
+/// those in `only`), returning each fixed mem path with its number of fixes.
+pub fn fix_terms(
+    storage: &Storage,
+    only: Option<&HashSet<String>>,
+) -> Result<Vec<(String, usize)>> {
+    let Some(dictionary) = Dictionary::load(storage)? else {
+        return Ok(Vec::new());
+    };
+    let mut fixed = Vec::new();
+    for mut mem in storage.list_mems()? {
+        let path = mem.path_str();
+        // Locked mems are reported but left for a person to change
+        if only.is_some_and(|only| !only.contains(&path)) || mem.is_locked() {
+            continue;
+        }
+        let (content, count) = dictionary.fix(&mem.content);
+        if count > 0 {
+            mem.content = content;
+            mem.touch();
+            storage.write_mem(&mem)?;
+            fixed.push((path, count));
+        }
+    }
+    Ok(fixed)
+}
+
+/// Repair the frontmatter timestamps of a store's mems (or only those in
+/// `only`), returning each fixed mem path with its number of fixes.
+pub fn fix_timestamps(
+    storage: &Storage,
+    only: Option<&HashSet<String>>,
+) -> Result<Vec<(String, usize)>> {
+    let max_gap_days = Config::for_storage(storage)?.lint.max_history_gap_days;
+    let history = History::load(storage);
+    let now = Utc::now();
+    let mut fixed = Vec::new();
+    for mut mem in storage.list_mems()? {
+        let path = mem.path_str();
+        if only.is_some_and(|only| !only.contains(&path)) || mem.is_locked() {
+            continue;
+        }
+        let raw = storage.read_source(&path)?;
+        let problems = timestamps::check(&mem, &raw, now, history.last_commit(&path), max_gap_days);
+        if !problems.is_empty() {
+            timestamps::repair(&mut mem, &problems, now);
+            storage.write_mem(&mem)?;
+            fixed.push((path, problems.len()));
+        }
+    }
+    Ok(fixed)
+}
+
+/// Override issue severities with the levels set in the lint config,
+/// dropping issues from rules turned `off`.
+fn apply_levels(issues: &mut Vec<Issue>, levels: &BTreeMap<String, RuleLevel>) {
+    issues.retain_mut(|issue| match levels.get(issue.rule) {
+        Some(RuleLevel::Off) => false,
+        Some(RuleLevel::Warning) => {
+            issue.severity = Severity::Warning;
+            true
+        }
+        Some(RuleLevel::Error) => {
+            issue.severity = Severity::Error;
+            true
+        }
+        None => true,
+    });
+}
+
+/// 1-based line of a top-level frontmatter field, or 1 if absent.
+fn field_line(raw: &str, key: &str) -> usize {
+    raw.lines()
+        .position(|l| {
+            l.strip_prefix(key)
+                .is_some_and(|rest| rest.starts_with(':'))
+        })
+        .map_or(1, |i| i + 1)
+}
+
+/// 1-based line of a tag in the frontmatter `tags:` list, or of the list.
+fn tag_line(raw: &str, tag: &str) -> usize {
+    let start = field_line(raw, "tags");
+    raw.lines()
+        .enumerate()
+        .skip(start)
+        .take_while(|(_, l)| l.starts_with(' ') || l.starts_with('-'))
+        .find(|(_, l)| l.trim_start_matches([' ', '-']).trim_matches(['\'', '"']) == tag)
+        .map_or(start, |(i, _)| i + 1)
+}
+
+/// Render a file path relative to `base` when possible, with forward slashes.
+pub fn display_path(file: &Path, base: &Path) -> String {
+    file.strip_prefix(base)
+        .unwrap_or(file)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Render issues as GitHub Actions workflow commands (PR annotations).
+pub fn to_github(issues: &[Issue], base: &Path) -> String {
+    let mut out = String::new();
+    for issue in issues {
+        out.push_str(&format!(
+            "::{} file={},line={},title={}::{}\n",
+            issue.severity.as_str(),
+            display_path(&issue.file, base),
+            issue.line,
+            issue.rule,
+            escape_github(&issue.message)
+        ));
+    }
+    out
+}
+
+fn escape_github(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Render issues as a SARIF 2.1.0 log for code scanning.
+pub fn to_sarif(issues: &[Issue], base: &Path) -> serde_json::Value {
+    let rules: Vec<serde_json::Value> = RULES
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "id": r.id,
+                "shortDescription": { "text": r.description },
+                "defaultConfiguration": { "level": r.severity.as_str() },
+            })
+        })
+        .collect();
+
+    let results: Vec<serde_json::Value> = issues
+        .iter()
+        .map(|i| {
+            serde_json::json!({
+                "ruleId": i.rule,
+                "level": i.severity.as_str(),
+                "message": { "text": i.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": display_path(&i.file, base) },
+                        "region": { "startLine": i.line },
+                    }
+                }],
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "mem",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Silent;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup_storage() -> (TempDir, Storage) {
+        let temp = TempDir::new().unwrap();
+        let mems_dir = temp.path().join(".mems");
+        fs::create_dir(&mems_dir).unwrap();
+        (temp, Storage::new(mems_dir))
+    }
+
+    #[test]
+    fn test_lint_reports_lines_and_severities() {
+        let (_temp, storage) = setup_storage();
+        storage
+            .write_mem(&Mem::new(
+                PathBuf::from("doc"),
+                "Doc".to_string(),
+                "Intro\n\nSee [missing](missing.md)".to_string(),
+            ))
+            .unwrap();
+        storage
+            .write_mem(&Mem::new(
+                PathBuf::from("empty"),
+                "Empty".to_string(),
+                String::new(),
+            ))
+            .unwrap();
+
+        let (checked, issues) = lint_storage("", &storage, &Silent).unwrap();
+        assert_eq!(checked, 2);
+
+        let broken = issues.iter().find(|i| i.rule == "broken-link").unwrap();
+        assert_eq!(broken.severity, Severity::Error);
+        let raw = fs::read_to_string(&broken.file).unwrap();
+        assert!(raw
+            .lines()
+            .nth(broken.line - 1)
+            .unwrap()
+            .contains("missing.md"));
+
+        let empty = issues.iter().find(|i| i.rule == "empty-content").unwrap();
+        assert_eq!(empty.severity, Severity::Warning);
+        assert_eq!(empty.path, "empty");
+    }
+
+    #[test]
+    fn test_lint_not_text() {
+        let (_temp, storage) = setup_storage();
+        storage
+            .write_mem(&Mem::new(PathBuf::from("ok"), "Ok".into(), "Fine".into()))
+            .unwrap();
+        fs::write(storage.mem_path("latin1"), b"---\ntitle: caf\xe9\n---\n").unwrap();
+        fs::write(storage.mem_path("image"), b"\x89PNG\r\n\x1a\n\0\0\0").unwrap();
+
+        let (checked, issues) = lint_storage("", &storage, &Silent).unwrap();
+        assert_eq!(checked, 1);
+        let not_text: Vec<(&str, &str)> = issues
+            .iter()
+            .filter(|i| i.rule == "not-text")
+            .map(|i| (i.path.as_str(), i.message.as_str()))
+            .collect();
+        assert_eq!(not_text.len(), 2);
+        assert!(not_text[0].1.contains("binary file"), "{not_text:?}");
+        assert!(not_text[1].1.contains("not valid UTF-8"), "{not_text:?}");
+    }
+
+    #[test]
+    fn test_lint_merge_conflicts() {
+        let (_temp, storage) = setup_storage();
+        let header =
+            "---\ntitle: T\ncreated-at: 2025-01-01T00:00:00Z\nupdated-at: 2025-01-01T00:00:00Z\n";
+        let block = "<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> main\n";
+        fs::write(
+            storage.mem_path("content"),
+            format!("{header}---\nIntro\n{block}"),
+        )
+        .unwrap();
+        fs::write(
+            storage.mem_path("frontmatter"),
+            format!("{header}{block}---\nIntro\n"),
+        )
+        .unwrap();
+        fs::write(
+            storage.mem_path("example"),
+            format!("{header}---\n```\n{block}```\n"),
+        )
+        .unwrap();
+
+        // A conflict in the content leaves the mem readable
+        assert_eq!(storage.read_mem("content").unwrap().title, "T");
+        assert!(storage.read_mem("example").is_ok());
+        let (checked, issues) = lint_storage("", &storage, &Silent).unwrap();
+        assert_eq!(checked, 2);
+        let conflicts: Vec<(&str, usize)> = issues
+            .iter()
+            .filter(|i| i.rule == MERGE_CONFLICT.id)
+            .map(|i| (i.path.as_str(), i.line))
+            .collect();
+        assert_eq!(conflicts, [("frontmatter", 5), ("content", 7)]);
+    }
+
+    #[test]
+    fn test_lint_changed_checks_inbound_links() {
+        let (_temp, storage) = setup_storage();
+        for (path, content) in [
+            ("changed", "Links to [nowhere](nowhere.md)"),
+            (
+                "unchanged",
+                "Links to [gone](gone.md) and [other](other.md)",
+            ),
+            ("untouched", ""),
+        ] {
+            storage
+                .write_mem(&Mem::new(
+                    PathBuf::from(path),
+                    path.to_string(),
+                    content.to_string(),
+                ))
+                .unwrap();
+        }
+
+        // "gone" was deleted in the change set; "other" was always missing
+        let changed: HashSet<String> = ["changed", "gone"].iter().map(|s| s.to_string()).collect();
+        let (checked, issues) = lint_changed("", &storage, &changed, &Silent).unwrap();
+
+        assert_eq!(checked, 1);
+        let found: Vec<_> = issues
+            .iter()
+            .map(|i| (i.path.as_str(), i.target.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("changed", Some("nowhere")), ("unchanged", Some("gone"))]
+        );
+    }
+
+    #[test]
+    fn test_lint_id_links() {
+        let (_temp, storage) = setup_storage();
+        let target = Mem::new(PathBuf::from("target"), "T".to_string(), "x".to_string());
+        let id = target.id.clone().unwrap();
+        storage.write_mem(&target).unwrap();
+        storage
+            .write_mem(&Mem::new(
+                PathBuf::from("source"),
+                "S".to_string(),
+                format!(
+                    "[[id:{}]]\n[[id:01ARZ3NDEKTSV4RRFFQ69G5FAV|gone]]",
+                    id.to_lowercase()
+                ),
+            ))
+            .unwrap();
+
+        let (_, issues) = lint_storage("", &storage, &Silent).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, "broken-id-link");
+        assert_eq!(
+            issues[0].target.as_deref(),
+            Some("id:01ARZ3NDEKTSV4RRFFQ69G5FAV")
+        );
+        let raw = fs::read_to_string(&issues[0].file).unwrap();
+        assert!(raw
+            .lines()
+            .nth(issues[0].line - 1)
+            .unwrap()
+            .contains("gone"));
+    }
+
+    #[test]
+    fn test_lint_duplicate_ids() {
+        let (_temp, storage) = setup_storage();
+        let mem = Mem::new(PathBuf::from("notes"), "Notes".to_string(), "x".to_string());
+        let id = mem.id.clone().unwrap();
+        storage.write_mem(&mem).unwrap();
+        let mut copy = mem.clone();
+        copy.path = PathBuf::from("notes.conflict-20250101");
+        storage.write_mem(&copy).unwrap();
+
+        let (_, issues) = lint_storage("", &storage, &Silent).unwrap();
+        let found: Vec<(&str, &str, usize)> = issues
+            .iter()
+            .map(|i| (i.rule, i.path.as_str(), i.line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("duplicate-id", "notes", 2),
+                ("duplicate-id", "notes.conflict-20250101", 2)
+            ]
+        );
+        assert!(issues[0].message.contains("notes.conflict-20250101"));
+
+        let err = storage.path_for_id(&id).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(MemError::Ambiguous { .. })
+        ));
+    }
+
+    #[test]
+    fn test_lint_structure_and_levels() {
+        let (_temp, storage) = setup_storage();
+        fs::create_dir(storage.root().join("adr")).unwrap();
+        storage
+            .write_mem(&Mem::new(
+                PathBuf::from("adr/001"),
+                "ADR".to_string(),
+                format!("## Context\n\n{}\n\n#### Detail\n", "word ".repeat(12)),
+            ))
+            .unwrap();
+        let rules = |config: &str| {
+            fs::write(storage.root().join(crate::config::CONFIG_FILE), config).unwrap();
+            let (_, issues) = lint_storage("", &storage, &Silent).unwrap();
+            issues
+                .iter()
+                .map(|i| (i.rule, i.severity, i.line))
+                .collect::<Vec<_>>()
+        };
+
+        let schema =
+            "schemas:\n  - prefix: adr\n    required-headings: [\"## Context\", \"## Decision\"]\n";
+        let offset = mem::content_line_offset(&storage.read_source("adr/001").unwrap());
+        assert_eq!(
+            rules(&format!("{schema}lint:\n  max-paragraph-words: 10\n")),
+            vec![
+                (SKIPPED_HEADING_LEVEL.id, Severity::Warning, offset + 5),
+                (LONG_PARAGRAPH.id, Severity::Warning, offset + 3),
+                (MISSING_HEADING.id, Severity::Error, offset),
+            ]
+        );
+        assert_eq!(
+            rules(&format!(
+                "{schema}lint:\n  rules:\n    skipped-heading-level: off\n    missing-heading: warning\n"
+            )),
+            vec![(MISSING_HEADING.id, Severity::Warning, offset)]
+        );
+    }
+
+    #[test]
+    fn test_changed_mems_by_timestamp() {
+        let (_temp, storage) = setup_storage();
+        let mut old = Mem::new(PathBuf::from("old"), "Old".to_string(), "x".to_string());
+        old.updated_at = dates::parse_timestamp("2024-01-01").unwrap();
+        storage.write_mem(&old).unwrap();
+        storage
+            .write_mem(&Mem::new(
+                PathBuf::from("new"),
+                "New".to_string(),
+                "x".to_string(),
+            ))
+            .unwrap();
+
+        let changed = changed_mems(&storage, "2025-01-01").unwrap();
+        assert_eq!(changed, HashSet::from(["new".to_string()]));
+    }
+
+    #[test]
+    fn test_github_format() {
+        let issue = Issue {
+            store: String::new(),
+            path: "doc".to_string(),
+            file: PathBuf::from("/repo/.mems/doc.md"),
+            line: 7,
+            rule: "broken-link",
+            severity: Severity::Error,
+            message: "broken link to x.md".to_string(),
+            target: Some("x".to_string()),
+        };
+        assert_eq!(
+            to_github(&[issue], Path::new("/repo")),
+            "::error file=.mems/doc.md,line=7,title=broken-link::broken link to x.md\n"
+        );
+    }
+}
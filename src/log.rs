@@ -0,0 +1,124 @@
+//! Append-only log mems (`kind: log`): running notes such as on-call logs
+//! and experiment journals, written one timestamped entry at a time.
+//!
+//! Each entry is a top-level bullet, `- **2025-03-01 14:05 UTC** message`,
+//! with any further lines of the message indented beneath it.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// `kind` of a log mem.
+pub const LOG_KIND: &str = "log";
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M UTC";
+
+/// An entry of a log mem.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Entry {
+    /// When the entry was written, as recorded
+    pub time: String,
+    pub message: String,
+}
+
+impl Entry {
+    /// The entry as written in the log. Lines after the first are indented
+    /// to stay part of the bullet.
+    pub fn to_markdown(&self) -> String {
+        let message = self.message.trim().replace('\n', "\n  ");
+        format!("- **{}** {message}\n", self.time)
+    }
+}
+
+/// Format a new entry written at `at`.
+pub fn format_entry(at: DateTime<Utc>, message: &str) -> String {
+    Entry {
+        time: at.format(TIME_FORMAT).to_string(),
+        message: message.to_string(),
+    }
+    .to_markdown()
+}
+
+/// Append an entry to log content, after whatever is already there. Prose
+/// before the first entry is separated from it by a blank line.
+pub fn append(content: &mut String, entry: &str) {
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    let after_entry = content
+        .lines()
+        .next_back()
+        .is_some_and(|line| line.starts_with("- **") || line.starts_with("  "));
+    if !content.trim().is_empty() && !after_entry && !content.ends_with("\n\n") {
+        content.push('\n');
+    }
+    content.push_str(entry);
+}
+
+/// Entries in the order they were written. Text outside entries (an intro
+/// paragraph, headings) is ignored.
+pub fn entries(content: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut in_entry = false;
+    for line in content.lines() {
+        if let Some((time, message)) = line
+            .strip_prefix("- **")
+            .and_then(|rest| rest.split_once("** "))
+        {
+            entries.push(Entry {
+                time: time.to_string(),
+                message: message.to_string(),
+            });
+            in_entry = true;
+        } else if let (true, Some(more)) = (in_entry, line.strip_prefix("  ")) {
+            if let Some(entry) = entries.last_mut() {
+                entry.message.push('\n');
+                entry.message.push_str(more);
+            }
+        } else {
+            in_entry = false;
+        }
+    }
+    entries
+}
+
+/// The newest `count` entries, oldest of them first.
+pub fn last(content: &str, count: usize) -> Vec<Entry> {
+    let mut entries = entries(content);
+    let skip = entries.len().saturating_sub(count);
+    entries.split_off(skip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_append_and_read() {
+        let at = Utc.with_ymd_and_hms(2025, 3, 1, 14, 5, 0).unwrap();
+        let mut content = "On-call notes for March.".to_string();
+        append(&mut content, &format_entry(at, "Paged for disk usage"));
+        append(
+            &mut content,
+            &format_entry(at, "Cleaned /var/log\nUsage back to 40%\n"),
+        );
+        assert_eq!(
+            content,
+            "On-call notes for March.\n\n\
+             - **2025-03-01 14:05 UTC** Paged for disk usage\n\
+             - **2025-03-01 14:05 UTC** Cleaned /var/log\n  Usage back to 40%\n"
+        );
+
+        let entries = entries(&content);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].message, "Cleaned /var/log\nUsage back to 40%");
+        assert_eq!(
+            last(&content, 1),
+            vec![Entry {
+                time: "2025-03-01 14:05 UTC".to_string(),
+                message: "Cleaned /var/log\nUsage back to 40%".to_string()
+            }]
+        );
+        assert_eq!(last(&content, 5).len(), 2);
+    }
+}
@@ -1,28 +1,142 @@
-use anyhow::{anyhow, Result};
-use clap::{Parser, Subcommand};
-use mem::mem::Mem;
-use mem::storage::Storage;
+use anyhow::{anyhow, Context as _, Result};
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use mem::activity;
+use mem::alias;
+use mem::analyzer::Analyzer;
+use mem::ask;
+use mem::attachment;
+use mem::backup;
+use mem::bench::{self, Shape};
+use mem::blame;
+use mem::bundle::{self, Bundle};
+use mem::cache::{Cache, CACHE_FILE};
+use mem::capture;
+use mem::chat;
+use mem::config::Config;
+use mem::conflict::{self, Strategy};
+use mem::dates;
+use mem::decision::{self, Decision};
+use mem::diff;
+use mem::digest;
+use mem::directory;
+use mem::discover;
+use mem::dump;
+use mem::email;
+use mem::error::MemError;
+use mem::exit;
+use mem::export;
+use mem::federation::{Layout, Ranking};
+use mem::find;
+use mem::fuzzy;
+use mem::gc;
+use mem::generate;
+use mem::git;
+use mem::github;
+use mem::glob;
+use mem::glossary;
+use mem::hash;
+use mem::highlight;
+use mem::html;
+use mem::id;
+use mem::import;
+use mem::include;
+use mem::info::{self, StoreInfo, STORE_FILE, STORE_VERSION};
+use mem::integrity;
+use mem::json::{self, MemRecord};
+use mem::kind;
+use mem::links;
+use mem::lint::{self, Severity};
+use mem::log;
+use mem::manifest::{self, Manifest};
+use mem::mem::{content_line_offset, Mem};
+use mem::meta;
+use mem::notify;
+use mem::preamble;
+use mem::progress::{Bar, Progress, Silent};
+use mem::proposal;
+use mem::query::Query;
+use mem::redirect;
+use mem::refresh;
+use mem::refs;
+use mem::related;
+use mem::replace;
+use mem::review;
+use mem::runbook;
+use mem::scaffold;
+use mem::schema::{self, Schema, Violation};
+use mem::semantic;
+use mem::serve;
+use mem::starter::{self, Structure};
+use mem::storage::{normalize_path, Storage, ARCHIVED_AT_FIELD, ARCHIVE_REASON_FIELD};
+use mem::sync::{self, Action};
+use mem::tasks;
+use mem::template::Template;
+use mem::text;
+use mem::timestamps::{self, TimestampSource};
+use mem::tree;
+use mem::upcoming;
+use mem::usage::{Usage, USAGE_FILE};
 use serde::Serialize;
-use std::io::{self, Read};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, IsTerminal, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+use tracing_subscriber::EnvFilter;
 
 #[derive(Parser)]
 #[command(name = "mem")]
 #[command(about = "A markdown-based knowledge tracking CLI for projects")]
 #[command(version)]
+#[command(after_help = exit::HELP)]
 struct Cli {
     /// Specify .mems/ directories to search (can be repeated)
     #[arg(long = "dir", global = true)]
     dirs: Vec<PathBuf>,
 
+    /// Store to use instead of the nearest .mems/ (a directory, or s3://bucket/prefix)
+    #[arg(long, global = true, env = "MEM_STORE")]
+    store: Option<String>,
+
+    /// Only print data, not status messages like "Created: path"
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log file operations and timings to stderr (-vv for more; MEM_LOG overrides)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Don't draw progress bars for long operations (only drawn on a terminal)
+    #[arg(long, global = true)]
+    no_progress: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Initialize a new .mems/ directory
-    Init,
+    /// Initialize a new .mems/ directory (with --dir, in that directory)
+    Init {
+        /// Start with a recommended hierarchy of index mems and kind templates
+        #[arg(long, value_enum, value_name = "STRUCTURE")]
+        with_structure: Option<Structure>,
+
+        /// Ignore the store's local files (cache, index) in .gitignore, running git init if needed
+        #[arg(long)]
+        git: bool,
+
+        /// Name of the store in store.yaml (default: the directory holding it)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Description of the store in store.yaml
+        #[arg(long)]
+        description: Option<String>,
+    },
 
     /// Add a new mem
     Add {
@@ -41,26 +155,77 @@ enum Commands {
         #[arg(long)]
         tags: Option<String>,
 
+        /// Owner (defaults to the directory's owner, if any)
+        #[arg(long)]
+        owner: Option<String>,
+
+        /// Kind of mem, e.g. adr or runbook; without content, starts from the kind's template
+        #[arg(long, visible_alias = "template")]
+        kind: Option<String>,
+
+        /// Value of a variable the kind's template declares (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", conflicts_with = "upsert")]
+        vars: Vec<String>,
+
         /// Overwrite if exists
         #[arg(short, long)]
         force: bool,
+        /// Create the mem, or update it if it exists; unchanged mems are left alone
+        #[arg(long, conflicts_with = "force")]
+        upsert: bool,
+        /// The content is a whole document; use its frontmatter (title, tags, fields)
+        #[arg(long, conflicts_with = "upsert")]
+        with_frontmatter: bool,
+        /// Overwrite or update the mem even if it is locked
+        #[arg(long)]
+        force_unlock: bool,
+        /// Save even if the mem is larger than max-mem-kb, without asking
+        #[arg(long)]
+        allow_large: bool,
+        /// Output format for the result
+        #[arg(long, value_enum, default_value = "text")]
+        output: OutputFormat,
     },
 
     /// Show a mem's content
     Show {
-        /// Path of the mem
-        path: String,
+        /// Paths of the mems, id:<ULID>, or glob patterns like 'runbooks/*'
+        #[arg(required = true)]
+        paths: Vec<String>,
+
+        /// If the path doesn't exist but exactly one close match does, use it
+        #[arg(long)]
+        fuzzy: bool,
+
+        /// Expand `{{include: path}}` and `![[path]]` directives
+        #[arg(long)]
+        render: bool,
+
+        /// Only print the newest N entries of a log mem
+        #[arg(long, value_name = "N")]
+        last: Option<usize>,
+
+        /// Show an archived mem, by its path before archiving
+        #[arg(long, conflicts_with = "fuzzy")]
+        archived: bool,
 
         /// Output as JSON
         #[arg(long)]
         json: bool,
+
+        #[command(flatten)]
+        template: TemplateArgs,
     },
 
     /// Edit an existing mem
     Edit {
-        /// Path of the mem
+        /// Path of the mem, or id:<ULID>
         path: String,
 
+        /// If the path doesn't exist but exactly one close match does, use it
+        #[arg(long)]
+        fuzzy: bool,
+
         /// New content
         #[arg(short, long)]
         content: Option<String>,
@@ -72,12 +237,91 @@ enum Commands {
         /// New tags (comma-separated)
         #[arg(long)]
         tags: Option<String>,
+        /// Refresh updated-at even when nothing changes
+        #[arg(long)]
+        force_touch: bool,
+        /// Change the mem even if it is locked
+        #[arg(long)]
+        force_unlock: bool,
+        /// Save even if the mem is larger than max-mem-kb, without asking
+        #[arg(long)]
+        allow_large: bool,
+        /// Output format for the result
+        #[arg(long, value_enum, default_value = "text")]
+        output: OutputFormat,
     },
 
     /// Remove a mem
     Rm {
-        /// Path of the mem
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// If the path doesn't exist but exactly one close match does, use it
+        #[arg(long)]
+        fuzzy: bool,
+        /// Change the mem even if it is locked
+        #[arg(long)]
+        force_unlock: bool,
+        /// Output format for the result
+        #[arg(long, value_enum, default_value = "text")]
+        output: OutputFormat,
+    },
+
+    /// Move a mem to a new path, keeping its id
+    Mv {
+        /// Path of the mem, or id:<ULID>
+        from: String,
+
+        /// New path
+        to: String,
+        /// Leave a stub at the old path that redirects to the new one
+        #[arg(long)]
+        redirect: bool,
+        /// Change the mem even if it is locked
+        #[arg(long)]
+        force_unlock: bool,
+        /// Output format for the result
+        #[arg(long, value_enum, default_value = "text")]
+        output: OutputFormat,
+    },
+
+    /// Attach files to a mem, storing each file once however many mems link to it
+    Attach {
+        /// Path of the mem, or id:<ULID>
         path: String,
+
+        /// Files to attach
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Change the mem even if it is locked
+        #[arg(long)]
+        force_unlock: bool,
+    },
+
+    /// Replace text in the content of mems, all at once
+    Replace {
+        /// Text to find
+        pattern: String,
+
+        /// Text to replace it with (with --regex, `$1` or `${name}` inserts a group)
+        replacement: String,
+
+        /// Treat the pattern as a regular expression
+        #[arg(long)]
+        regex: bool,
+
+        /// Only replace in mems under this path
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Show a diff of each change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Change locked mems too (they are skipped otherwise)
+        #[arg(long)]
+        force_unlock: bool,
     },
 
     /// List mems
@@ -85,9 +329,48 @@ enum Commands {
         /// Path to list under (optional)
         path: Option<String>,
 
+        /// Show size, word count, and estimated tokens
+        #[arg(short, long)]
+        long: bool,
+
+        /// Only list mems with this owner
+        #[arg(long)]
+        owner: Option<String>,
+
+        /// Only list pinned mems
+        #[arg(long)]
+        pinned: bool,
+
+        /// Only list mems of this kind (`note` for mems without one)
+        #[arg(long)]
+        kind: Option<String>,
+
+        /// Only list mems whose frontmatter matches: field=value, field!=value, or field (set)
+        #[arg(long = "where", value_name = "CONDITION")]
+        conditions: Vec<meta::Condition>,
+
+        /// List in path order even inside a scoped directory
+        #[arg(long)]
+        no_scope: bool,
+
+        /// List archived mems instead, by their paths before archiving, with when and why
+        #[arg(long)]
+        archived: bool,
+
+        /// List the mems shown most often, most first (needs `mem usage enable`)
+        #[arg(long, conflicts_with = "archived")]
+        hot: bool,
+
+        /// List the mems of every .mems/ under the repository, labeled by package
+        #[arg(long)]
+        recursive_discover: bool,
+
         /// Output as JSON
         #[arg(long)]
         json: bool,
+
+        #[command(flatten)]
+        template: TemplateArgs,
     },
 
     /// Search mems by content
@@ -95,6 +378,71 @@ enum Commands {
         /// Search query
         query: String,
 
+        /// Only search mems under this path, e.g. runbooks/
+        path: Option<String>,
+
+        /// Only search mems under this path (same as the positional path)
+        #[arg(long = "path", value_name = "PATH", conflicts_with = "path")]
+        under: Option<String>,
+
+        /// Rank by embedding similarity combined with keyword matches
+        #[arg(long)]
+        semantic: bool,
+
+        /// Match titles only
+        #[arg(long, conflicts_with = "semantic")]
+        title_only: bool,
+
+        /// Maximum number of semantic results
+        #[arg(long, default_value = "10")]
+        limit: usize,
+
+        /// Only search mems of this kind
+        #[arg(long)]
+        kind: Option<String>,
+
+        /// Where to search: active mems, the archive, or both
+        #[arg(
+            long = "in",
+            value_enum,
+            default_value = "active",
+            conflicts_with_all = ["semantic", "open"]
+        )]
+        search_in: find::SearchIn,
+
+        /// Also search archived mems (same as --in all)
+        #[arg(long, conflicts_with_all = ["semantic", "open", "search_in"])]
+        include_archived: bool,
+
+        /// List identical mems found in several stores separately
+        #[arg(long, conflicts_with = "semantic")]
+        no_dedupe: bool,
+
+        /// Rank results from this store first (repeatable, in order)
+        #[arg(long, value_name = "LABEL")]
+        prefer: Vec<String>,
+
+        /// Group results from several stores by store, or interleave them by score
+        #[arg(long, value_enum)]
+        layout: Option<Layout>,
+
+        /// Open the only result in $VISUAL/$EDITOR (or show it); pick on a terminal when several match
+        #[arg(long, conflicts_with = "json")]
+        open: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        #[command(flatten)]
+        template: TemplateArgs,
+    },
+
+    /// Look up a term's definition in the glossaries
+    Def {
+        /// Term or acronym, e.g. RTO
+        term: String,
+
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -104,613 +452,6672 @@ enum Commands {
     Tree {
         /// Path to show tree from (optional)
         path: Option<String>,
+
+        /// Only show directories this many levels deep
+        #[arg(long, value_name = "N")]
+        depth: Option<usize>,
+
+        /// Show how many mems each directory holds
+        #[arg(long)]
+        counts: bool,
+
+        /// Show when mems, and anything under each directory, were last updated
+        #[arg(long)]
+        updated: bool,
+
+        /// Output the hierarchy as nested JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Show archived mems instead, by their paths before archiving
+        #[arg(long)]
+        archived: bool,
+
+        /// Show archived mems alongside active ones, marked as archived
+        #[arg(long, conflicts_with = "archived")]
+        all: bool,
     },
 
+    /// Print the mem prefix the current directory is scoped to
+    Scope,
+
     /// List stale mems not updated recently
     Stale {
-        /// Days threshold (default: 90)
+        /// Days threshold for kinds without their own (default: 90)
         #[arg(long, default_value = "90")]
         days: u32,
 
+        /// Only list mems of this kind
+        #[arg(long)]
+        kind: Option<String>,
+
+        /// Where to take mem timestamps from
+        #[arg(long, value_enum, default_value = "frontmatter")]
+        timestamps: TimestampSource,
+
+        /// Group stale mems by owner
+        #[arg(long)]
+        by_owner: bool,
+
         /// Output as JSON
         #[arg(long)]
         json: bool,
+
+        #[command(flatten)]
+        template: TemplateArgs,
     },
 
-    /// Validate all mems
-    Lint,
+    /// Set the owner of a mem, or of every mem under a directory
+    Assign {
+        /// Path of the mem or directory
+        path: String,
 
-    /// Archive a mem
-    Archive {
+        /// New owner
+        owner: String,
+
+        /// Output the assigned paths as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Change locked mems too
+        #[arg(long)]
+        force_unlock: bool,
+    },
+
+    /// Read and write frontmatter fields
+    Meta {
+        #[command(subcommand)]
+        command: MetaCommand,
+    },
+
+    /// Pin a mem so it is listed first by ls and tree
+    Pin {
         /// Path of the mem
         path: String,
+
+        /// Change the mem even if it is locked
+        #[arg(long)]
+        force_unlock: bool,
     },
 
-    /// Dump all mems under a path as concatenated markdown
-    Dump {
-        /// Path prefix to dump (defaults to all mems)
-        path: Option<String>,
+    /// Lock a mem against edits, moves, and removal
+    Lock {
+        /// Path of the mem
+        path: String,
     },
-}
 
-/// JSON representation for mem output.
-#[derive(Serialize)]
-struct MemJson {
-    path: String,
-    title: String,
-    created_at: String,
-    updated_at: String,
-    tags: Vec<String>,
-    content: String,
-}
+    /// Unlock a locked mem
+    Unlock {
+        /// Path of the mem
+        path: String,
+    },
 
-impl From<&Mem> for MemJson {
-    fn from(mem: &Mem) -> Self {
-        Self {
-            path: mem.path.to_string_lossy().to_string(),
-            title: mem.title.clone(),
-            created_at: mem.created_at.to_rfc3339(),
-            updated_at: mem.updated_at.to_rfc3339(),
-            tags: mem.tags.clone(),
-            content: mem.content.clone(),
-        }
-    }
-}
+    /// Unpin a mem
+    Unpin {
+        /// Path of the mem
+        path: String,
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+        /// Change the mem even if it is locked
+        #[arg(long)]
+        force_unlock: bool,
+    },
 
-    match cli.command {
-        Commands::Init => cmd_init()?,
-        Commands::Add {
-            path,
-            content,
-            title,
-            tags,
-            force,
-        } => cmd_add(&path, content, title, tags, force)?,
-        Commands::Show { path, json } => cmd_show(&path, json)?,
-        Commands::Edit {
-            path,
-            content,
-            title,
-            tags,
-        } => cmd_edit(&path, content, title, tags)?,
-        Commands::Rm { path } => cmd_rm(&path)?,
-        Commands::Ls { path, json } => cmd_ls(path.as_deref(), json, &cli.dirs)?,
-        Commands::Find { query, json } => cmd_find(&query, json, &cli.dirs)?,
-        Commands::Tree { path } => cmd_tree(path.as_deref(), &cli.dirs)?,
-        Commands::Stale { days, json } => cmd_stale(days, json, &cli.dirs)?,
-        Commands::Lint => cmd_lint(&cli.dirs)?,
-        Commands::Archive { path } => cmd_archive(&path)?,
-        Commands::Dump { path } => cmd_dump(path.as_deref(), &cli.dirs)?,
-    }
+    /// Check mem files against their checksums and timestamps
+    Verify {
+        /// Accept the current content of changed mems (refreshes checksum and updated-at)
+        #[arg(long)]
+        update: bool,
 
-    Ok(())
-}
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
-/// Get storages from explicit dirs or find default .mems/
-fn get_storages(dirs: &[PathBuf]) -> Result<Vec<(String, Storage)>> {
-    if dirs.is_empty() {
-        let storage = Storage::find()?;
-        Ok(vec![("".to_string(), storage)])
-    } else {
-        let mut storages = Vec::new();
-        for dir in dirs {
-            if !dir.exists() {
-                return Err(anyhow!("directory not found: {}", dir.display()));
-            }
-            let label = dir.to_string_lossy().to_string();
-            storages.push((label, Storage::new(dir.clone())));
-        }
-        Ok(storages)
-    }
-}
+    /// Validate all mems
+    Lint {
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: LintFormat,
 
-fn cmd_init() -> Result<()> {
-    Storage::init()?;
-    println!("Initialized .mems/ directory");
-    Ok(())
-}
+        /// Lowest severity that fails the run
+        #[arg(long, value_enum, default_value = "warning")]
+        severity_threshold: Severity,
 
-fn cmd_add(
-    path: &str,
-    content: Option<String>,
-    title: Option<String>,
-    tags: Option<String>,
-    force: bool,
-) -> Result<()> {
-    let storage = Storage::find()?;
+        /// Only lint mems changed since a git ref or timestamp (plus links into them)
+        #[arg(long, value_name = "REF|TIMESTAMP")]
+        changed_since: Option<String>,
 
-    // Check if mem already exists
-    if storage.exists(path) && !force {
-        return Err(anyhow!(
-            "mem already exists: {path} (use --force to overwrite)"
-        ));
-    }
+        /// Replace terms mapped in .mems/dictionary.txt and repair frontmatter
+        /// timestamps before linting
+        #[arg(long)]
+        fix: bool,
 
-    // Get content from flag or stdin
-    let content = match content {
-        Some(c) => c,
-        None => {
-            // Try reading from stdin
-            let mut buf = String::new();
-            io::stdin().read_to_string(&mut buf)?;
-            if buf.is_empty() {
-                return Err(anyhow!("no content provided (use -c or pipe via stdin)"));
-            }
-            buf
-        }
-    };
+        /// Also request http(s) links and report dead ones
+        #[arg(long)]
+        check_external: bool,
+    },
 
-    // Derive title from path if not provided
-    let title = title.unwrap_or_else(|| {
-        path.rsplit('/')
-            .next()
-            .unwrap_or(path)
-            .replace(['-', '_'], " ")
-    });
+    /// Archive a mem (stored compressed under archive/)
+    Archive {
+        #[command(subcommand)]
+        command: Option<ArchiveCommand>,
 
-    // Parse tags
-    let tags: Vec<String> = tags
-        .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
-        .unwrap_or_default();
+        /// Path of the mem, or id:<ULID>
+        path: Option<String>,
+        /// Why the mem is archived, kept in its archive-reason field
+        #[arg(long)]
+        reason: Option<String>,
+        /// Change the mem even if it is locked
+        #[arg(long)]
+        force_unlock: bool,
+        /// Output format for the result
+        #[arg(long, value_enum, default_value = "text")]
+        output: OutputFormat,
+    },
 
-    let mem = Mem::new(PathBuf::from(path), title, content).with_tags(tags);
-    storage.write_mem(&mem)?;
+    /// Dump all mems under a path as concatenated markdown
+    Dump {
+        /// Path prefix to dump (defaults to all mems)
+        path: Option<String>,
 
-    println!("Created: {path}");
-    Ok(())
-}
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        out: Option<PathBuf>,
 
-fn cmd_show(path: &str, json: bool) -> Result<()> {
-    let storage = Storage::find()?;
-    let mem = storage.read_mem(path)?;
+        /// Compress the output with gzip
+        #[arg(long)]
+        gzip: bool,
 
-    if json {
-        let json_output = MemJson::from(&mem);
-        println!("{}", serde_json::to_string_pretty(&json_output)?);
-    } else {
-        println!("# {}", mem.title);
-        println!();
-        if !mem.tags.is_empty() {
-            println!("Tags: {}", mem.tags.join(", "));
-            println!();
-        }
-        println!("{}", mem.content);
-    }
+        /// Order of mems: by path, or most recently updated first
+        #[arg(long, value_enum, default_value = "path")]
+        order: dump::Order,
 
-    Ok(())
-}
+        /// Start with a manifest of the mems dumped and their hashes
+        #[arg(long)]
+        manifest: bool,
 
-fn cmd_edit(
-    path: &str,
-    content: Option<String>,
-    title: Option<String>,
-    tags: Option<String>,
-) -> Result<()> {
-    let storage = Storage::find()?;
-    let mut mem = storage.read_mem(path)?;
+        /// Only dump mems changed since this manifest (or dump made with
+        /// --manifest); implies --manifest
+        #[arg(long, value_name = "OLD_MANIFEST")]
+        diff: Option<PathBuf>,
 
-    // Update fields if provided
-    if let Some(c) = content {
-        mem.content = c;
-    }
-    if let Some(t) = title {
-        mem.title = t;
-    }
-    if let Some(t) = tags {
-        mem.tags = t.split(',').map(|s| s.trim().to_string()).collect();
-    }
+        /// Start with a preamble naming the store, with counts and an outline
+        /// (template: dump.header in config)
+        #[arg(long)]
+        with_header: bool,
+    },
 
-    // Update timestamp
-    mem.touch();
+    /// Export mems to other formats
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
 
-    storage.write_mem(&mem)?;
-    println!("Updated: {path}");
+    /// Archive mems per the retention policies and purge old archived mems
+    Gc {
+        /// Show what would be archived and purged without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Also delete redirect stubs left by `mv --redirect` that no mem links to
+        #[arg(long)]
+        redirects: bool,
+
+        /// Also delete attachment blobs that no mem, active or archived, links to
+        #[arg(long)]
+        attachments: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Split a mem into child mems by top-level headings
+    Split {
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// Overwrite existing child mems
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Suggest mems related to a mem
+    Related {
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// Maximum number of suggestions
+        #[arg(long, default_value = "10")]
+        limit: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Answer a question from the mems using a configured LLM
+    Ask {
+        /// The question to answer
+        question: String,
+
+        /// Only print the retrieved mems, without calling the LLM
+        #[arg(long)]
+        retrieve_only: bool,
+
+        /// Maximum number of mems to retrieve
+        #[arg(long, default_value = "5")]
+        limit: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Create a mem from a web page
+    Clip {
+        /// URL to fetch
+        url: String,
+
+        /// Path for the mem
+        path: String,
+
+        /// Title (defaults to the page title)
+        #[arg(short, long)]
+        title: Option<String>,
+
+        /// Tags (comma-separated)
+        #[arg(long)]
+        tags: Option<String>,
+
+        /// Annotation placed above the clipped content
+        #[arg(short, long)]
+        note: Option<String>,
+
+        /// Overwrite if exists
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Import notes from zk, Dendron, Foam, or Logseq, chat threads, or a mem bundle
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Import {
+        #[command(subcommand)]
+        bundle: Option<ImportCommand>,
+
+        /// Tool the notes come from
+        #[arg(long, value_enum, required = true)]
+        format: Option<import::Format>,
+
+        /// Notebook, vault, workspace, or graph directory
+        #[arg(required = true)]
+        dir: Option<PathBuf>,
+
+        /// Place imported mems under this path
+        #[arg(long, value_name = "PATH")]
+        into: Option<String>,
+
+        /// Overwrite existing mems
+        #[arg(short, long)]
+        force: bool,
+
+        /// With --force, overwrite locked mems too
+        #[arg(long)]
+        force_unlock: bool,
+
+        /// List the mems that would be created without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Fetch mems again from their source: a GitHub thread, web page, or file
+    #[command(group(ArgGroup::new("target").required(true).args(["path", "all"])))]
+    Refresh {
+        /// Mem or directory to refresh
+        path: Option<String>,
+
+        /// Refresh every mem with a source
+        #[arg(long)]
+        all: bool,
+
+        /// Overwrite edits made since the mem was last synced
+        #[arg(short, long)]
+        force: bool,
+
+        /// Show a diff of each change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Refresh locked mems too (they are skipped otherwise)
+        #[arg(long)]
+        force_unlock: bool,
+
+        /// Read file sources outside the directory holding the store
+        #[arg(long)]
+        allow_outside: bool,
+    },
+
+    /// Report stale and due mems for cron/systemd timers
+    Notify {
+        /// Output format
+        #[arg(long, value_enum, default_value = "plain")]
+        format: NotifyFormat,
+
+        /// Days threshold for staleness (default: from config, else 90)
+        #[arg(long)]
+        days: Option<u32>,
+
+        /// Webhook URL (default: notify.webhook-url from config)
+        #[arg(long)]
+        webhook_url: Option<String>,
+
+        /// Where to take mem timestamps from
+        #[arg(long, value_enum, default_value = "frontmatter")]
+        timestamps: TimestampSource,
+    },
+
+    /// Summarize new, edited, newly stale, and archived mems for a period
+    Digest {
+        /// Start of the period: a duration back from now (12h, 3d, 1w) or a date
+        #[arg(long, default_value = "1w")]
+        since: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: DigestFormat,
+
+        /// Days threshold for staleness (default: from config, else 90)
+        #[arg(long)]
+        days: Option<u32>,
+
+        /// Where to take mem timestamps from
+        #[arg(long, value_enum, default_value = "frontmatter")]
+        timestamps: TimestampSource,
+    },
+
+    /// Show how many mems were created and edited each month of the past year
+    Activity {
+        /// Only count mems under this path
+        path: Option<String>,
+
+        /// Show a calendar heatmap of each day instead of monthly totals
+        #[arg(long)]
+        heatmap: bool,
+
+        /// Output daily counts as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Where to take mem timestamps from
+        #[arg(long, value_enum, default_value = "frontmatter")]
+        timestamps: TimestampSource,
+    },
+
+    /// List decisions from ADRs and decision records, oldest first
+    Decisions {
+        /// Only list decisions under this path
+        path: Option<String>,
+
+        /// Only decisions dated since a duration back (12h, 3d, 1w) or a date
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Output a markdown decision log linking each mem
+        #[arg(long, conflicts_with = "json")]
+        markdown: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show today's mems to review, picked by staleness and importance
+    Review {
+        #[command(subcommand)]
+        command: Option<ReviewCommand>,
+
+        /// Number of mems to show (default: from config, else 3)
+        #[arg(long)]
+        count: Option<usize>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List open task items (- [ ]) across mems
+    Tasks {
+        #[command(subcommand)]
+        command: Option<TasksCommand>,
+
+        /// Only list tasks in this mem or under this directory
+        path: Option<String>,
+
+        /// Include checked-off tasks
+        #[arg(long)]
+        all: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List mems referring to dates (@YYYY-MM-DD or `dates:`) coming up soon
+    Upcoming {
+        /// Days ahead to look
+        #[arg(long, default_value = "30")]
+        days: u32,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show what store.yaml says about each store, with its location and size
+    Info {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show store statistics
+    Stats {
+        /// List mems ordered by size, largest first
+        #[arg(long)]
+        by_size: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List tags with the number of mems using each
+    Tags {
+        /// List uses of tags outside the allowed vocabulary instead
+        #[arg(long)]
+        unknown: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Append a timestamped entry to a log mem, creating it if needed
+    LogEntry {
+        /// Path of the log mem
+        path: String,
+
+        /// Entry text (read from stdin if omitted)
+        message: Option<String>,
+    },
+
+    /// Run a command and record its output in a mem (appending if it exists)
+    Capture {
+        /// Path of the mem
+        path: String,
+
+        /// Command to run, after `--`
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+
+        /// Title for a new mem (defaults to last path segment)
+        #[arg(short, long)]
+        title: Option<String>,
+
+        /// Tags for a new mem (comma-separated)
+        #[arg(long)]
+        tags: Option<String>,
+    },
+
+    /// Create a mem from an email read from stdin (raw RFC 822)
+    CaptureEmail {
+        /// Path of the mem (default: email/<date>-<subject>)
+        path: Option<String>,
+
+        /// Title (defaults to the subject)
+        #[arg(short, long)]
+        title: Option<String>,
+
+        /// Tags (comma-separated)
+        #[arg(long)]
+        tags: Option<String>,
+
+        /// Overwrite if exists
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Step through a runbook, recording the run in a log mem
+    Run {
+        /// Path of the runbook (a mem with `kind: runbook`)
+        path: String,
+
+        /// Offer to run fenced shell blocks, asking before each one
+        #[arg(long)]
+        exec: bool,
+
+        /// Log mem to record the run in (default: runs/<path>)
+        #[arg(long, value_name = "PATH")]
+        log: Option<String>,
+    },
+
+    /// Create or refresh mems describing project artifacts
+    Generate {
+        #[command(subcommand)]
+        command: GenerateCommand,
+    },
+
+    /// Show the differences between two mems, or a mem and a file
+    #[command(group(ArgGroup::new("other").required(true).args(["new", "against"])))]
+    Diff {
+        /// Path of the mem, or id:<ULID>
+        old: String,
+
+        /// Path of the mem to compare with
+        new: Option<String>,
+
+        /// Compare with a file instead (`-` for stdin)
+        #[arg(long)]
+        against: Option<PathBuf>,
+
+        /// Show changed words within lines instead of changed lines
+        #[arg(long)]
+        words: bool,
+
+        /// Unchanged lines to show around each change
+        #[arg(long, default_value = "3")]
+        context: usize,
+
+        /// Output hunks (or word changes) as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Propose new content for a mem, to be accepted or rejected later
+    Propose {
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// Read the new content from a file (default: stdin)
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        /// Why the change is proposed
+        #[arg(short, long)]
+        message: Option<String>,
+
+        /// Who proposes it
+        #[arg(long)]
+        author: Option<String>,
+    },
+
+    /// List, show, and diff pending proposals
+    Proposals {
+        #[command(subcommand)]
+        command: ProposalsCommand,
+    },
+
+    /// Apply a proposal to its mem
+    Accept {
+        /// Proposal id (a unique prefix is enough)
+        id: String,
+
+        /// Apply even if the mem changed since the proposal was made
+        #[arg(long)]
+        force: bool,
+        /// Change the mem even if it is locked
+        #[arg(long)]
+        force_unlock: bool,
+    },
+
+    /// Discard a proposal
+    Reject {
+        /// Proposal id (a unique prefix is enough)
+        id: String,
+    },
+
+    /// Show who last changed each line or section of a mem
+    Blame {
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// Summarize by heading instead of by line
+        #[arg(long)]
+        sections: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List mems that link to a mem
+    Backlinks {
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Verify that `mem:<path-or-id>` markers in source comments point at mems
+    CheckRefs {
+        /// Source file or directory to scan
+        #[arg(long, default_value = ".")]
+        src: PathBuf,
+
+        /// Accept references to archived mems
+        #[arg(long)]
+        allow_archived: bool,
+
+        /// Also fail on references to mems not updated in this many days
+        #[arg(long, value_name = "DAYS")]
+        stale: Option<u32>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List code locations that reference a mem with a `mem:` marker
+    Backrefs {
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// Source file or directory to scan
+        #[arg(long, default_value = ".")]
+        src: PathBuf,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List mems with unresolved git merge conflicts
+    Conflicts {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Resolve a mem's git merge conflict
+    #[command(group(ArgGroup::new("strategy").required(true).args(["ours", "theirs", "union_tags"])))]
+    Resolve {
+        /// Path of the mem
+        path: String,
+
+        /// Keep our title and content
+        #[arg(long)]
+        ours: bool,
+
+        /// Keep their title and content
+        #[arg(long)]
+        theirs: bool,
+
+        /// Merge frontmatter only (fails if the content conflicts)
+        #[arg(long)]
+        union_tags: bool,
+    },
+
+    /// Serve the store over HTTP (JSON API)
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:4141")]
+        addr: String,
+
+        /// Also serve the browser UI at /
+        #[cfg(feature = "web")]
+        #[arg(long)]
+        web: bool,
+    },
+
+    /// Manage the SQLite metadata cache (.mems/.cache.db)
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+
+    /// Check and repair the semantic search index (.mems/.index/)
+    Index {
+        #[command(subcommand)]
+        command: IndexCommand,
+    },
+
+    /// Track locally which mems are shown, for `ls --hot` and `related` (opt-in)
+    Usage {
+        #[command(subcommand)]
+        command: UsageCommand,
+    },
+
+    /// Two-way sync with another .mems/ directory
+    Sync {
+        /// The other store (a .mems/ directory or a directory containing one)
+        remote: PathBuf,
+
+        /// Show what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Create, verify, restore, and prune store snapshots
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+
+    /// Time list, find, lint, and dump on a synthetic store (JSON report)
+    Bench {
+        /// Number of mems to generate
+        #[arg(long, default_value_t = 1000)]
+        mems: usize,
+
+        /// Directory levels above each mem
+        #[arg(long, default_value_t = 3)]
+        depth: usize,
+
+        /// Approximate content size of each mem, in bytes
+        #[arg(long, default_value_t = 2000)]
+        size: usize,
+
+        /// Times to run each operation
+        #[arg(long, default_value_t = 3)]
+        runs: usize,
+
+        /// Generate the store here and keep it (default: a temporary directory)
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+
+    /// Print the JSON Schema of mems in --json output
+    Schema,
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Create or rebuild the cache; later commands use it automatically
+    Build,
+
+    /// Show whether the cache is enabled and how many mems it holds
+    Status,
+
+    /// Delete the cache and go back to reading files directly
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum IndexCommand {
+    /// Show the index's size, age, and how far it has drifted from the mems
+    Status {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Bring the index up to date, embedding only new and changed mems
+    Rebuild {
+        /// Discard the index and embed every mem again
+        #[arg(long)]
+        full: bool,
+    },
+
+    /// List mems the index lacks or has out of date (fails if there are any)
+    Verify {
+        /// Repair what's found, as rebuild does
+        #[arg(long)]
+        repair: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum UsageCommand {
+    /// Start recording each time `show` shows a mem, in .mems/.usage.json
+    Enable,
+
+    /// Show whether usage is tracked and how much is recorded
+    Status,
+
+    /// Stop tracking and delete everything recorded
+    Disable,
+}
+
+#[derive(Subcommand)]
+enum MetaCommand {
+    /// Print a field of a mem, or all its fields
+    Get {
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// Field name (all fields when omitted)
+        field: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Set a field; values are parsed as YAML (3, true, [a, b]) or kept as text
+    Set {
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// Field name
+        field: String,
+
+        /// New value
+        value: String,
+
+        /// Change the mem even if it is locked, or change its `locked` field
+        #[arg(long)]
+        force_unlock: bool,
+    },
+
+    /// Remove a field
+    Unset {
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// Field name
+        field: String,
+
+        /// Change the mem even if it is locked, or remove its `locked` field
+        #[arg(long)]
+        force_unlock: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReviewCommand {
+    /// Mark mems as reviewed, pushing their next review further out
+    Done {
+        /// Paths of the reviewed mems
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum GenerateCommand {
+    /// Describe the Cargo workspace: crates, binaries, and features
+    FromCargo {
+        /// Cargo.toml of the workspace (default: found from the current directory)
+        #[arg(long)]
+        manifest_path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProposalsCommand {
+    /// List pending proposals, oldest first
+    Ls {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a proposal and its proposed content
+    Show {
+        /// Proposal id (a unique prefix is enough)
+        id: String,
+    },
+
+    /// Diff a mem's current content against a proposal
+    Diff {
+        /// Proposal id (a unique prefix is enough)
+        id: String,
+
+        /// Show changed words within lines instead of changed lines
+        #[arg(long)]
+        words: bool,
+
+        /// Output hunks (or word changes) as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ArchiveCommand {
+    /// Compress archived mems stored uncompressed
+    Compact,
+}
+
+#[derive(Subcommand)]
+enum TasksCommand {
+    /// Check off a task
+    Done {
+        /// Path of the mem, or id:<ULID>
+        path: String,
+
+        /// Line of the task, as printed by mem tasks
+        line: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportCommand {
+    /// Write mems as markdown files into a directory mirroring the hierarchy
+    Dir {
+        /// Output directory (must be empty unless --force)
+        out: PathBuf,
+
+        /// Only export mems under this path
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Drop frontmatter: title as an H1 and tags as a footer
+        #[arg(long)]
+        plain: bool,
+
+        /// Write into a non-empty directory, overwriting files
+        #[arg(short, long)]
+        force: bool,
+
+        /// Update a previous export in place: rewrite only changed files and
+        /// delete those of mems no longer exported
+        #[arg(long)]
+        incremental: bool,
+    },
+
+    /// Write one mem with its attachments and history to a portable bundle
+    Mem {
+        /// Mem to export
+        path: String,
+
+        /// Bundle file (default: <name>.memx in the current directory)
+        #[arg(long)]
+        out: Option<PathBuf>,
+
+        /// Overwrite an existing bundle file
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+/// Where `import slack`, `import discord`, and `import github` put mems and
+/// what they overwrite.
+#[derive(Args)]
+struct ImportArgs {
+    /// Place imported mems under this path (default: slack/, discord/, or github/<repo>/)
+    #[arg(long, value_name = "PATH")]
+    into: Option<String>,
+
+    /// Overwrite existing mems
+    #[arg(short, long)]
+    force: bool,
+
+    /// With --force, overwrite locked mems too
+    #[arg(long)]
+    force_unlock: bool,
+
+    /// List the mems that would be created without writing them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Subcommand)]
+enum ImportCommand {
+    /// Import Slack threads: a workspace export directory or a channel's JSON history
+    Slack {
+        /// Export directory or JSON file
+        export: PathBuf,
+
+        #[command(flatten)]
+        args: ImportArgs,
+    },
+
+    /// Import Discord threads from DiscordChatExporter JSON (a file or a directory of them)
+    Discord {
+        /// Export file or directory
+        export: PathBuf,
+
+        #[command(flatten)]
+        args: ImportArgs,
+    },
+
+    /// Import a GitHub issue or pull request discussion, or every one with a label or milestone
+    #[command(group(ArgGroup::new("select").required(true).multiple(true).args(["issue", "label", "milestone"])))]
+    Github {
+        /// Repository as owner/name
+        #[arg(long, value_name = "OWNER/NAME")]
+        repo: github::Repo,
+
+        /// Issue or pull request number
+        #[arg(long, value_name = "N", conflicts_with_all = ["label", "milestone"])]
+        issue: Option<u64>,
+
+        /// Import every issue and pull request with this label
+        #[arg(long)]
+        label: Option<String>,
+
+        /// Import every issue and pull request in this milestone (title or number)
+        #[arg(long)]
+        milestone: Option<String>,
+
+        #[command(flatten)]
+        args: ImportArgs,
+    },
+
+    /// Unpack a single-mem bundle written by `export mem`
+    Memx {
+        /// Bundle file
+        file: PathBuf,
+
+        /// Where to put the mem (default: its path in the store it came from)
+        dest: Option<String>,
+
+        /// Overwrite an existing mem or attachment
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupCommand {
+    /// Write a compressed snapshot of the whole store
+    Create {
+        /// Output file (default: .mems/.backups/mems-<timestamp>.tar.zst)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Check a snapshot's checksums without restoring it
+    Verify {
+        /// Snapshot file
+        file: PathBuf,
+    },
+
+    /// Replace the store's contents with a snapshot
+    Restore {
+        /// Snapshot file
+        file: PathBuf,
+
+        /// Restore even if the store already has mems
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Delete old snapshots from .mems/.backups/
+    Prune {
+        /// Number of most recent snapshots to keep
+        #[arg(long, default_value = "10")]
+        keep: usize,
+    },
+}
+
+/// Output formats for `notify`.
+#[derive(Clone, Copy, ValueEnum)]
+enum NotifyFormat {
+    Plain,
+    Json,
+    Webhook,
+}
+
+/// Output formats for `digest`.
+#[derive(Clone, Copy, ValueEnum)]
+enum DigestFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+/// Output formats for `lint`.
+#[derive(Clone, Copy, ValueEnum)]
+enum LintFormat {
+    Text,
+    Sarif,
+    Github,
+}
+
+/// `--template` and `--template-file`, for commands that list mems.
+#[derive(Args)]
+struct TemplateArgs {
+    /// Format each mem with a Jinja-style template, e.g. '{{ path }}: {{ title }}',
+    /// with the fields of --json
+    #[arg(long, conflicts_with_all = ["json", "template_file"])]
+    template: Option<String>,
+
+    /// Read the --template from a file
+    #[arg(long, value_name = "FILE", conflicts_with = "json")]
+    template_file: Option<PathBuf>,
+}
+
+/// How commands that list mems print them.
+enum Listing {
+    Text,
+    Json,
+    /// Each mem through a template, with the fields of its JSON
+    Template(Box<Template>),
+}
+
+impl Listing {
+    fn new(json: bool, args: TemplateArgs) -> Result<Listing> {
+        let source = match (args.template, args.template_file) {
+            (Some(source), _) => source,
+            (None, Some(file)) => std::fs::read_to_string(&file)
+                .with_context(|| format!("failed to read {}", file.display()))?,
+            (None, None) if json => return Ok(Listing::Json),
+            (None, None) => return Ok(Listing::Text),
+        };
+        Ok(Listing::Template(Box::new(Template::new(source)?)))
+    }
+
+    fn is_text(&self) -> bool {
+        matches!(self, Listing::Text)
+    }
+
+    /// Print `items`, the JSON form of each mem, as a JSON array or through
+    /// the template, each rendering on its own line.
+    fn print<T: Serialize>(&self, items: &[T]) -> Result<()> {
+        match self {
+            Listing::Template(template) => {
+                let mut out = io::stdout().lock();
+                for item in items {
+                    writeln!(out, "{}", template.render(item)?)?;
+                }
+            }
+            _ => println!("{}", serde_json::to_string_pretty(items)?),
+        }
+        Ok(())
+    }
+}
+
+/// Output formats for commands that change mems.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// JSON summary of an operation that leaves no mem behind (rm, archive).
+#[derive(Serialize)]
+struct OperationJson {
+    action: &'static str,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+}
+
+/// JSON output of `add --upsert`: what happened, and the mem.
+#[derive(Serialize)]
+struct UpsertJson {
+    /// `created`, `updated`, or `unchanged`
+    action: &'static str,
+    #[serde(flatten)]
+    mem: MemRecord,
+}
+
+/// JSON output of `find`: the mem, where it was found when searching the
+/// archive, and identical copies in other stores.
+#[derive(Serialize)]
+struct FoundJson {
+    /// `active` or `archive`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<&'static str>,
+
+    /// Other stores with the same mem, as `label:path`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    duplicates: Vec<String>,
+
+    #[serde(flatten)]
+    mem: MemRecord,
+}
+
+/// JSON representation of stale mems sharing an owner.
+#[derive(Serialize)]
+struct OwnerGroupJson {
+    owner: Option<String>,
+    mems: Vec<MemRecord>,
+}
+
+/// JSON representation for a ranked search hit.
+#[derive(Serialize)]
+struct ScoredJson {
+    #[serde(flatten)]
+    mem: MemRecord,
+    score: f32,
+}
+
+/// JSON representation for a related-mem suggestion.
+#[derive(Serialize)]
+struct RelatedJson {
+    path: String,
+    title: String,
+    score: f32,
+    tag_score: f32,
+    link_score: f32,
+    content_score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage_score: Option<f32>,
+    linked: bool,
+}
+
+/// JSON representation for a tag and its usage count.
+#[derive(Serialize)]
+struct TagJson {
+    tag: String,
+    count: usize,
+}
+
+/// JSON representation of a tag outside the allowed vocabulary.
+#[derive(Serialize)]
+struct UnknownTagJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    store: Option<String>,
+    path: String,
+    tag: String,
+    suggestion: Option<String>,
+}
+
+/// JSON representation for an answer from `ask`.
+#[derive(Serialize)]
+struct AnswerJson {
+    answer: String,
+    sources: Vec<String>,
+}
+
+/// JSON representation for store statistics.
+#[derive(Serialize)]
+struct InfoJson {
+    name: Option<String>,
+    description: Option<String>,
+    location: String,
+    owners: Vec<String>,
+
+    /// Store format version, if the store has a store.yaml
+    schema_version: Option<u32>,
+
+    /// Whether this build knows the store's format
+    supported: bool,
+    policies: BTreeMap<String, String>,
+    mems: usize,
+    archived: usize,
+}
+
+#[derive(Serialize)]
+struct StatsJson {
+    mems: usize,
+    size: usize,
+    words: usize,
+    tokens: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    by_size: Option<Vec<SizeJson>>,
+}
+
+/// JSON representation for a mem's size entry.
+#[derive(Serialize)]
+struct SizeJson {
+    path: String,
+    title: String,
+    size: usize,
+    words: usize,
+    tokens: usize,
+}
+
+impl From<&Mem> for SizeJson {
+    fn from(mem: &Mem) -> Self {
+        Self {
+            path: mem.path_str(),
+            title: mem.title.clone(),
+            size: mem.size(),
+            words: mem.word_count(),
+            tokens: mem.estimated_tokens(),
+        }
+    }
+}
+
+/// Print a status message (not command output), unless `--quiet`.
+macro_rules! status {
+    ($ctx:expr, $($arg:tt)*) => {
+        if !$ctx.quiet {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// What the global flags ask of every command, passed down from `run`.
+struct Context {
+    /// Store location from `--store`
+    store: Option<String>,
+
+    /// Whether `--quiet` was given
+    quiet: bool,
+
+    /// Whether progress bars may be drawn: no `--no-progress`, `--quiet`, or
+    /// `-v` (whose log lines would break the bar up)
+    progress: bool,
+}
+
+impl Context {
+    fn new(cli: &Cli) -> Context {
+        Context {
+            store: cli.store.clone(),
+            quiet: cli.quiet,
+            progress: !cli.no_progress && !cli.quiet && cli.verbose == 0,
+        }
+    }
+
+    /// Progress reporting for a long operation: a bar when allowed and
+    /// stderr is a terminal, otherwise nothing.
+    fn progress(&self) -> Box<dyn Progress> {
+        if self.progress && io::stderr().is_terminal() {
+            Box::new(Bar::new())
+        } else {
+            Box::new(Silent)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = match expand_args() {
+        Ok(Some(args)) => Cli::parse_from(args),
+        Ok(None) => Cli::parse(),
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            return ExitCode::from(exit::code(&e));
+        }
+    };
+    init_logging(cli.verbose);
+
+    let start = Instant::now();
+    let result = run(cli);
+    tracing::debug!(
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "command finished"
+    );
+
+    match result {
+        Ok(()) => ExitCode::from(exit::SUCCESS),
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitCode::from(exit::code(&e))
+        }
+    }
+}
+
+/// The command line with the store's aliases and default flags expanded, or
+/// `None` to parse it as given (arguments that aren't UTF-8, or no store).
+fn expand_args() -> Result<Option<Vec<String>>> {
+    let Some(args) = std::env::args_os()
+        .map(|arg| arg.into_string().ok())
+        .collect::<Option<Vec<String>>>()
+    else {
+        return Ok(None);
+    };
+    let store = args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .zip(args.iter().skip(1))
+        .find_map(|(arg, next)| match arg.as_str() {
+            "--store" => Some(next.clone()),
+            _ => arg.strip_prefix("--store=").map(str::to_string),
+        })
+        .or_else(|| std::env::var("MEM_STORE").ok());
+    let storage = match store {
+        Some(location) => Storage::open(&location),
+        None => Storage::find(),
+    };
+    // Without a readable config there is nothing to expand; the command
+    // reports the problem itself if it needs the store
+    let Ok(config) = storage.and_then(|storage| Config::for_storage(&storage)) else {
+        return Ok(None);
+    };
+    if config.aliases.is_empty() && config.defaults.is_empty() {
+        return Ok(None);
+    }
+    alias::expand(args, &config.aliases, &config.defaults, &Cli::command()).map(Some)
+}
+
+/// Log to stderr at the level `-v` asks for, or as configured by `MEM_LOG`
+/// (e.g. `MEM_LOG=mem=trace`). Nothing is set up otherwise.
+fn init_logging(verbose: u8) {
+    let filter = match std::env::var("MEM_LOG") {
+        Ok(spec) => EnvFilter::new(spec),
+        Err(_) => match verbose {
+            0 => return,
+            1 => EnvFilter::new("mem=debug"),
+            _ => EnvFilter::new("mem=trace"),
+        },
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .init();
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let ctx = &Context::new(&cli);
+    match cli.command {
+        Commands::Init {
+            with_structure,
+            git,
+            name,
+            description,
+        } => {
+            let info = StoreInfo {
+                name,
+                description,
+                ..StoreInfo::default()
+            };
+            cmd_init(ctx, with_structure, git, info, &cli.dirs)?
+        }
+        Commands::Add {
+            path,
+            content,
+            title,
+            tags,
+            owner,
+            kind,
+            vars,
+            force,
+            upsert,
+            with_frontmatter,
+            force_unlock,
+            allow_large,
+            output,
+        } => cmd_add(
+            ctx,
+            &path,
+            content,
+            NewFields {
+                title,
+                tags,
+                owner,
+                kind,
+                vars,
+            },
+            AddOptions {
+                force,
+                upsert,
+                with_frontmatter,
+                force_unlock,
+                allow_large,
+            },
+            output,
+        )?,
+        Commands::Show {
+            paths,
+            fuzzy,
+            render,
+            last,
+            archived,
+            json,
+            template,
+        } => cmd_show(
+            ctx,
+            &paths,
+            ShowOptions {
+                fuzzy,
+                render,
+                last,
+                archived,
+                listing: Listing::new(json, template)?,
+            },
+            &cli.dirs,
+        )?,
+        Commands::Edit {
+            path,
+            fuzzy,
+            content,
+            title,
+            tags,
+            force_touch,
+            force_unlock,
+            allow_large,
+            output,
+        } => cmd_edit(
+            ctx,
+            &path,
+            fuzzy,
+            EditFields {
+                content,
+                title,
+                tags,
+            },
+            EditOptions {
+                force_touch,
+                force_unlock,
+                allow_large,
+            },
+            output,
+        )?,
+        Commands::Rm {
+            path,
+            fuzzy,
+            force_unlock,
+            output,
+        } => cmd_rm(ctx, &path, fuzzy, force_unlock, output)?,
+        Commands::Mv {
+            from,
+            to,
+            redirect,
+            force_unlock,
+            output,
+        } => cmd_mv(ctx, &from, &to, redirect, force_unlock, output, &cli.dirs)?,
+        Commands::Attach {
+            path,
+            files,
+            force_unlock,
+        } => cmd_attach(ctx, &path, &files, force_unlock)?,
+        Commands::Replace {
+            pattern,
+            replacement,
+            regex,
+            path,
+            dry_run,
+            force_unlock,
+        } => cmd_replace(
+            ctx,
+            &replace::Pattern::new(&pattern, regex)?,
+            &replacement,
+            path.as_deref(),
+            dry_run,
+            force_unlock,
+        )?,
+        Commands::Ls {
+            path,
+            long,
+            owner,
+            pinned,
+            kind,
+            conditions,
+            no_scope,
+            archived,
+            hot,
+            recursive_discover,
+            json,
+            template,
+        } => cmd_ls(
+            path.as_deref(),
+            LsOptions {
+                long,
+                archived,
+                hot,
+                scoped: !no_scope,
+            },
+            &LsFilter {
+                owner: owner.as_deref(),
+                pinned,
+                kind: kind.as_deref(),
+                conditions: &conditions,
+            },
+            &Listing::new(json, template)?,
+            get_storages_discovering(ctx, &cli.dirs, recursive_discover)?,
+        )?,
+        Commands::Find {
+            query,
+            path,
+            under,
+            semantic,
+            title_only,
+            limit,
+            kind,
+            search_in,
+            include_archived,
+            no_dedupe,
+            prefer,
+            layout,
+            open,
+            json,
+            template,
+        } => {
+            let listing = Listing::new(json, template)?;
+            let under = path.or(under).map(|p| p.trim_end_matches('/').to_string());
+            let filter = FindFilter {
+                title_only,
+                kind: kind.as_deref(),
+                under: under.as_deref(),
+                search_in: if include_archived {
+                    find::SearchIn::All
+                } else {
+                    search_in
+                },
+                dedupe: !no_dedupe,
+                prefer: &prefer,
+                layout,
+            };
+            if semantic {
+                cmd_find_semantic(ctx, &query, limit, filter, open, &listing, &cli.dirs)?
+            } else {
+                cmd_find(ctx, &query, filter, open, &listing, &cli.dirs)?
+            }
+        }
+        Commands::Def { term, json } => cmd_def(ctx, &term, json, &cli.dirs)?,
+        Commands::Tree {
+            path,
+            depth,
+            counts,
+            updated,
+            json,
+            archived,
+            all,
+        } => cmd_tree(
+            ctx,
+            path.as_deref(),
+            TreeOptions {
+                depth,
+                counts,
+                updated,
+                json,
+                archived,
+                all,
+            },
+            &cli.dirs,
+        )?,
+        Commands::Scope => cmd_scope(ctx)?,
+        Commands::Stale {
+            days,
+            kind,
+            timestamps,
+            by_owner,
+            json,
+            template,
+        } => cmd_stale(
+            ctx,
+            days,
+            kind.as_deref(),
+            timestamps,
+            by_owner,
+            &Listing::new(json, template)?,
+            &cli.dirs,
+        )?,
+        Commands::Digest {
+            since,
+            format,
+            days,
+            timestamps,
+        } => cmd_digest(ctx, &since, format, days, timestamps, &cli.dirs)?,
+        Commands::Activity {
+            path,
+            heatmap,
+            json,
+            timestamps,
+        } => cmd_activity(ctx, path.as_deref(), heatmap, json, timestamps, &cli.dirs)?,
+        Commands::Decisions {
+            path,
+            since,
+            markdown,
+            json,
+        } => cmd_decisions(
+            ctx,
+            path.as_deref(),
+            since.as_deref(),
+            markdown,
+            json,
+            &cli.dirs,
+        )?,
+        Commands::Review {
+            command,
+            count,
+            json,
+        } => cmd_review(ctx, command, count, json)?,
+        Commands::Tasks {
+            command,
+            path,
+            all,
+            json,
+        } => cmd_tasks(ctx, command, path.as_deref(), all, json, &cli.dirs)?,
+        Commands::Upcoming { days, json } => cmd_upcoming(ctx, days, json, &cli.dirs)?,
+        Commands::Assign {
+            path,
+            owner,
+            json,
+            force_unlock,
+        } => cmd_assign(ctx, &path, &owner, json, force_unlock)?,
+        Commands::Meta { command } => cmd_meta(ctx, command)?,
+        Commands::Pin { path, force_unlock } => cmd_pin(ctx, &path, true, force_unlock)?,
+        Commands::Unpin { path, force_unlock } => cmd_pin(ctx, &path, false, force_unlock)?,
+        Commands::Lock { path } => cmd_lock(ctx, &path, true)?,
+        Commands::Unlock { path } => cmd_lock(ctx, &path, false)?,
+        Commands::Lint {
+            format,
+            severity_threshold,
+            changed_since,
+            fix,
+            check_external,
+        } => cmd_lint(
+            ctx,
+            format,
+            severity_threshold,
+            changed_since.as_deref(),
+            fix,
+            check_external,
+            &cli.dirs,
+        )?,
+        Commands::Verify { update, json } => cmd_verify(ctx, update, json, &cli.dirs)?,
+        Commands::Archive {
+            command,
+            path,
+            reason,
+            force_unlock,
+            output,
+        } => cmd_archive(
+            ctx,
+            command,
+            path.as_deref(),
+            reason.as_deref(),
+            force_unlock,
+            output,
+        )?,
+        Commands::Dump {
+            path,
+            out,
+            gzip,
+            order,
+            manifest,
+            diff,
+            with_header,
+        } => cmd_dump(
+            ctx,
+            path.as_deref(),
+            DumpOptions {
+                out,
+                gzip,
+                order,
+                manifest: manifest || diff.is_some(),
+                diff,
+                with_header,
+            },
+            &cli.dirs,
+        )?,
+        Commands::Export { command } => cmd_export(ctx, command)?,
+        Commands::Gc {
+            dry_run,
+            redirects,
+            attachments,
+            json,
+        } => cmd_gc(ctx, dry_run, redirects, attachments, json)?,
+        Commands::Split { path, force } => cmd_split(ctx, &path, force)?,
+        Commands::Related { path, limit, json } => cmd_related(ctx, &path, limit, json)?,
+        Commands::Ask {
+            question,
+            retrieve_only,
+            limit,
+            json,
+        } => cmd_ask(ctx, &question, retrieve_only, limit, json, &cli.dirs)?,
+        Commands::Import {
+            bundle: Some(ImportCommand::Memx { file, dest, force }),
+            ..
+        } => cmd_import_bundle(ctx, &file, dest.as_deref(), force)?,
+        Commands::Import {
+            bundle: Some(ImportCommand::Slack { export, args }),
+            ..
+        } => {
+            let imported = chat::import_slack(&export)?;
+            write_imported(
+                ctx,
+                imported,
+                &export,
+                args.into.as_deref().or(Some("slack")),
+                args.force,
+                args.force_unlock,
+                args.dry_run,
+            )?
+        }
+        Commands::Import {
+            bundle: Some(ImportCommand::Discord { export, args }),
+            ..
+        } => {
+            let imported = chat::import_discord(&export)?;
+            write_imported(
+                ctx,
+                imported,
+                &export,
+                args.into.as_deref().or(Some("discord")),
+                args.force,
+                args.force_unlock,
+                args.dry_run,
+            )?
+        }
+        Commands::Import {
+            bundle:
+                Some(ImportCommand::Github {
+                    repo,
+                    issue,
+                    label,
+                    milestone,
+                    args,
+                }),
+            ..
+        } => {
+            let filter = github::Filter { label, milestone };
+            let imported = import_github(ctx, &repo, issue, &filter)?;
+            let default_into = format!("github/{}", repo.name);
+            write_imported(
+                ctx,
+                imported,
+                Path::new(&format!("{}/{}", repo.owner, repo.name)),
+                args.into.as_deref().or(Some(&default_into)),
+                args.force,
+                args.force_unlock,
+                args.dry_run,
+            )?
+        }
+        Commands::Refresh {
+            path,
+            all: _,
+            force,
+            dry_run,
+            force_unlock,
+            allow_outside,
+        } => cmd_refresh(
+            ctx,
+            path.as_deref(),
+            force,
+            dry_run,
+            force_unlock,
+            allow_outside,
+        )?,
+        Commands::Import {
+            bundle: None,
+            format,
+            dir,
+            into,
+            force,
+            force_unlock,
+            dry_run,
+        } => {
+            let (Some(format), Some(dir)) = (format, dir) else {
+                unreachable!("clap requires --format and the directory");
+            };
+            cmd_import(
+                ctx,
+                format,
+                &dir,
+                into.as_deref(),
+                force,
+                force_unlock,
+                dry_run,
+            )?
+        }
+        Commands::Clip {
+            url,
+            path,
+            title,
+            tags,
+            note,
+            force,
+        } => cmd_clip(ctx, &url, &path, title, tags, note, force)?,
+        Commands::Notify {
+            format,
+            days,
+            webhook_url,
+            timestamps,
+        } => cmd_notify(ctx, format, days, webhook_url, timestamps, &cli.dirs)?,
+        Commands::Info { json } => cmd_info(ctx, json, &cli.dirs)?,
+        Commands::Stats { by_size, json } => cmd_stats(ctx, by_size, json, &cli.dirs)?,
+        Commands::Tags { unknown, json } => {
+            if unknown {
+                cmd_unknown_tags(ctx, json, &cli.dirs)?
+            } else {
+                cmd_tags(ctx, json, &cli.dirs)?
+            }
+        }
+        Commands::LogEntry { path, message } => cmd_log_entry(ctx, &path, message)?,
+        Commands::Capture {
+            path,
+            command,
+            title,
+            tags,
+        } => cmd_capture(ctx, &path, &command, title, tags)?,
+        Commands::CaptureEmail {
+            path,
+            title,
+            tags,
+            force,
+        } => cmd_capture_email(ctx, path, title, tags, force)?,
+        Commands::Run { path, exec, log } => cmd_run(ctx, &path, exec, log.as_deref())?,
+        Commands::Generate { command } => cmd_generate(ctx, command)?,
+        Commands::Diff {
+            old,
+            new,
+            against,
+            words,
+            context,
+            json,
+        } => cmd_diff(
+            ctx,
+            &old,
+            new.as_deref(),
+            against.as_deref(),
+            words,
+            context,
+            json,
+        )?,
+        Commands::Propose {
+            path,
+            file,
+            message,
+            author,
+        } => cmd_propose(ctx, &path, file.as_deref(), message, author)?,
+        Commands::Proposals { command } => cmd_proposals(ctx, command)?,
+        Commands::Accept {
+            id,
+            force,
+            force_unlock,
+        } => cmd_accept(ctx, &id, force, force_unlock)?,
+        Commands::Reject { id } => cmd_reject(ctx, &id)?,
+        Commands::Blame {
+            path,
+            sections,
+            json,
+        } => cmd_blame(ctx, &path, sections, json)?,
+        Commands::Backlinks { path, json } => cmd_backlinks(ctx, &path, json, &cli.dirs)?,
+        Commands::CheckRefs {
+            src,
+            allow_archived,
+            stale,
+            json,
+        } => cmd_check_refs(ctx, &src, allow_archived, stale, json)?,
+        Commands::Backrefs { path, src, json } => cmd_backrefs(ctx, &path, &src, json)?,
+        Commands::Conflicts { json } => cmd_conflicts(ctx, json, &cli.dirs)?,
+        Commands::Resolve {
+            path,
+            ours,
+            theirs,
+            union_tags: _,
+        } => {
+            let strategy = if ours {
+                Strategy::Ours
+            } else if theirs {
+                Strategy::Theirs
+            } else {
+                Strategy::UnionTags
+            };
+            cmd_resolve(ctx, &path, strategy)?
+        }
+        #[cfg(feature = "web")]
+        Commands::Serve { addr, web } => cmd_serve(ctx, &addr, web)?,
+        #[cfg(not(feature = "web"))]
+        Commands::Serve { addr } => cmd_serve(ctx, &addr, false)?,
+        Commands::Cache { command } => cmd_cache(ctx, command)?,
+        Commands::Index { command } => cmd_index(ctx, command)?,
+        Commands::Usage { command } => cmd_usage(ctx, command)?,
+        Commands::Sync { remote, dry_run } => cmd_sync(ctx, &remote, dry_run)?,
+        Commands::Backup { command } => cmd_backup(ctx, command)?,
+        Commands::Bench {
+            mems,
+            depth,
+            size,
+            runs,
+            path,
+        } => cmd_bench(Shape { mems, depth, size }, runs, path)?,
+        Commands::Schema => println!("{}", serde_json::to_string_pretty(&json::schema())?),
+    }
+
+    Ok(())
+}
+
+/// Open the `--store` location, or find the default .mems/.
+fn find_storage(ctx: &Context) -> Result<Storage> {
+    match ctx.store.as_deref() {
+        Some(location) => Storage::open(location),
+        None => Storage::find(),
+    }
+}
+
+/// Get storages from explicit dirs or find default .mems/, or every store in
+/// the repository with `discover-nested`
+fn get_storages(ctx: &Context, dirs: &[PathBuf]) -> Result<Vec<(String, Storage)>> {
+    get_storages_discovering(ctx, dirs, false)
+}
+
+/// Like `get_storages`, discovering nested stores when `discover` is set.
+fn get_storages_discovering(
+    ctx: &Context,
+    dirs: &[PathBuf],
+    discover: bool,
+) -> Result<Vec<(String, Storage)>> {
+    if dirs.is_empty() {
+        let storage = find_storage(ctx);
+        let remote = ctx.store.is_some();
+        let nested = match &storage {
+            Ok(storage) if !remote => Config::for_storage(storage)?.discover_nested,
+            _ => false,
+        };
+        if !remote && (discover || nested) {
+            let start = match &storage {
+                Ok(storage) => storage
+                    .local_root()?
+                    .canonicalize()?
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default(),
+                Err(_) => std::env::current_dir()?,
+            };
+            let found = discover::find_stores(&discover::root(&start))?;
+            if !found.is_empty() {
+                return Ok(info::labels(
+                    found
+                        .into_iter()
+                        .map(|f| (Storage::new(f.dir), f.label))
+                        .collect(),
+                ));
+            }
+        }
+        Ok(vec![("".to_string(), storage?)])
+    } else {
+        let mut storages = Vec::new();
+        for dir in dirs {
+            if !dir.exists() {
+                return Err(MemError::StoreNotFound {
+                    location: Some(dir.display().to_string()),
+                }
+                .into());
+            }
+            storages.push((Storage::new(dir.clone()), dir.to_string_lossy().to_string()));
+        }
+        Ok(info::labels(storages))
+    }
+}
+
+/// The store a reference is in and the reference within it: for
+/// `store:path`, the store of that name among the `--dir` stores (or the
+/// nested stores without any), otherwise the default store.
+fn storage_for(ctx: &Context, reference: &str, dirs: &[PathBuf]) -> Result<(Storage, String)> {
+    if let Some((name, path)) = links::parse_store_reference(reference) {
+        if let Some((_, storage)) =
+            get_storages_discovering(ctx, dirs, true)?
+                .into_iter()
+                .find(|(label, storage)| {
+                    discover::names(label, storage.root())
+                        .iter()
+                        .any(|n| n == name)
+                })
+        {
+            return Ok((storage, path.to_string()));
+        }
+    }
+    Ok((find_storage(ctx)?, reference.to_string()))
+}
+
+/// Names cross-store links use for `storage`, if it is one of `storages`.
+fn store_names(storages: &[(String, Storage)], storage: &Storage) -> Result<Vec<String>> {
+    let root = storage.root().canonicalize()?;
+    Ok(storages
+        .iter()
+        .find(|(_, s)| s.root().canonicalize().is_ok_and(|r| r == root))
+        .map(|(label, s)| discover::names(label, s.root()))
+        .unwrap_or_default())
+}
+
+/// Resolve a mem reference; with `fuzzy`, fall back to the only close match.
+fn resolve_mem(ctx: &Context, storage: &Storage, reference: &str, fuzzy: bool) -> Result<String> {
+    match storage.resolve(reference) {
+        Err(e) if fuzzy => match e.downcast_ref::<MemError>() {
+            Some(MemError::NotFound { suggestions, .. }) if suggestions.len() == 1 => {
+                if !ctx.quiet {
+                    eprintln!("Using {}", suggestions[0]);
+                }
+                Ok(suggestions[0].clone())
+            }
+            _ => Err(e),
+        },
+        result => result,
+    }
+}
+
+/// Create a store: in `dirs[0]` if it's a `.mems` directory, else in a
+/// `.mems` directory inside it or the current directory.
+fn cmd_init(
+    ctx: &Context,
+    structure: Option<Structure>,
+    git: bool,
+    mut info: StoreInfo,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let mems_dir = match dirs {
+        [] => std::env::current_dir()?.join(".mems"),
+        [dir] if dir.file_name().is_some_and(|n| n == ".mems") => dir.clone(),
+        [dir] => dir.join(".mems"),
+        _ => return Err(anyhow!("init takes at most one --dir")),
+    };
+    let storage = Storage::init_at(&mems_dir)?;
+    if info.name.is_none() {
+        info.name = mems_dir
+            .canonicalize()?
+            .parent()
+            .and_then(Path::file_name)
+            .map(|n| n.to_string_lossy().to_string());
+    }
+    info.write(&mems_dir)?;
+    status!(ctx, "Initialized {}", mems_dir.display());
+
+    if let Some(structure) = structure {
+        for file in starter::scaffold(&storage, structure)? {
+            status!(ctx, "Created: {file}");
+        }
+    }
+
+    if git {
+        let project = mems_dir
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        if git::toplevel(project).is_err() {
+            git::init(project)?;
+            status!(ctx, "Initialized git repository in {}", project.display());
+        }
+        let added = starter::ignore_local_files(project, ".mems")?;
+        if !added.is_empty() {
+            status!(ctx, "Added to .gitignore: {}", added.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Frontmatter for a new mem given on the command line.
+struct NewFields {
+    title: Option<String>,
+    tags: Option<String>,
+    owner: Option<String>,
+    kind: Option<String>,
+    /// `--var` assignments for the kind's template
+    vars: Vec<String>,
+}
+
+/// How `add` treats existing mems, the content given, and its size.
+struct AddOptions {
+    force: bool,
+    upsert: bool,
+    with_frontmatter: bool,
+    force_unlock: bool,
+    allow_large: bool,
+}
+
+fn cmd_add(
+    ctx: &Context,
+    path: &str,
+    content: Option<String>,
+    fields: NewFields,
+    options: AddOptions,
+    output: OutputFormat,
+) -> Result<()> {
+    let AddOptions {
+        force,
+        upsert,
+        with_frontmatter,
+        force_unlock,
+        allow_large,
+    } = options;
+    let storage = find_storage(ctx)?;
+    let config = Config::for_storage(&storage)?;
+    let path = &normalize_path(path)?;
+    if upsert && storage.exists(path) {
+        return upsert_mem(ctx, &storage, path, content, fields, &options, output);
+    }
+    let NewFields {
+        title,
+        tags,
+        owner,
+        kind,
+        vars,
+    } = fields;
+
+    // Check if mem already exists; overwriting it keeps its id
+    let mut existing_id = None;
+    if storage.exists(path) {
+        if !force {
+            return Err(anyhow!(
+                "mem already exists: {path} (use --force to overwrite)"
+            ));
+        }
+        // A file that no longer parses as a mem can't be locked
+        if let Ok(existing) = storage.read_mem(path) {
+            check_unlocked(&existing, force_unlock)?;
+            existing_id = existing.id;
+        }
+    }
+
+    // Prompt for required fields and template variables only when stdin is
+    // free and a terminal
+    let mut interactive = io::stdin().is_terminal();
+
+    // Get content from flag or stdin, or start from the kind's template
+    let settings = kind
+        .as_deref()
+        .map(|kind| kind::settings(&config, kind))
+        .unwrap_or_default();
+    let mut from_template = false;
+    let content = match content {
+        Some(c) => text::decode(c.into_bytes(), "--content")?,
+        None => {
+            // Try reading from stdin, unless a template can stand in for a terminal
+            let mut buf = String::new();
+            if settings.template.is_none() || !io::stdin().is_terminal() {
+                buf = read_stdin()?;
+                interactive = false;
+            }
+            match settings.template.clone() {
+                _ if !buf.is_empty() => buf,
+                Some(template) => {
+                    from_template = true;
+                    template
+                }
+                None => return Err(anyhow!("no content provided (use -c or pipe via stdin)")),
+            }
+        }
+    };
+
+    let assignments = vars
+        .iter()
+        .map(|v| scaffold::parse_assignment(v))
+        .collect::<Result<BTreeMap<_, _>>>()?;
+    // Variables are asked for only when the template is the content; given
+    // content still has the values given, and defaults, for the fields
+    let (content, values) = if from_template {
+        let values = scaffold::values(settings.variables(), assignments, |variable| {
+            ask_variable(variable, interactive)
+        })?;
+        (scaffold::substitute(&content, &values), values)
+    } else {
+        let values = scaffold::given_values(settings.variables(), assignments)?;
+        (content, values)
+    };
+
+    // Frontmatter in the document, then flags, then the title from the path
+    let mut mem = if with_frontmatter {
+        Mem::from_document(PathBuf::from(path), &content)?
+    } else {
+        Mem::new(PathBuf::from(path), String::new(), content)
+    };
+    if existing_id.is_some() {
+        mem.id = existing_id;
+    } else if let Some(id) = mem.id.clone().filter(|_| with_frontmatter) {
+        // An id from the document may belong to another mem already
+        if storage.id_taken(&id, path)? {
+            mem.id = Some(id::generate());
+            eprintln!("warning: id {id} is used by another mem; gave {path} a new id");
+        }
+    }
+    if let Some(title) = title {
+        mem.title = title;
+    }
+    if mem.title.is_empty() {
+        mem.title = path
+            .rsplit('/')
+            .next()
+            .unwrap_or(path)
+            .replace(['-', '_'], " ");
+    }
+    if let Some(tags) = tags {
+        mem.tags = text::parse_tags(&tags);
+    }
+
+    let schema = schema::for_path(&storage, &config, path)?;
+    warn_unknown_tags(&storage, &schema, &mem.tags)?;
+
+    for (name, value) in settings.fields.iter().flatten() {
+        // A field waiting on a variable with no value is left out
+        let value = scaffold::substitute(value, &values);
+        if !scaffold::mentions_any(&value, settings.variables()) {
+            mem.set_field(name, value);
+        }
+    }
+    if let Some(owner) = owner {
+        mem.set_field("owner", owner);
+    }
+    if let Some(kind) = kind {
+        mem.set_field("kind", kind);
+    }
+    directory::apply_defaults(&mut mem, &directory::effective(&storage, path)?);
+    prefill_fields(&mut mem, &schema, interactive)?;
+    if with_frontmatter {
+        // A document brought in whole must meet the schema as given
+        let problems: Vec<String> = schema
+            .check(&mem)
+            .iter()
+            .filter(|v| matches!(v, Violation::MissingField(_) | Violation::InvalidStatus(_)))
+            .map(|v| v.message(&schema))
+            .collect();
+        if !problems.is_empty() {
+            return Err(anyhow!(
+                "invalid frontmatter for {path}: {}",
+                problems.join("; ")
+            ));
+        }
+    }
+    check_size(&config, &mem, allow_large)?;
+    storage.write_mem(&mem)?;
+
+    match output {
+        OutputFormat::Json if upsert => print_upsert_json("created", &mem)?,
+        OutputFormat::Json => print_mem_json(&mem)?,
+        OutputFormat::Text => status!(ctx, "Created: {path}"),
+    }
+    Ok(())
+}
+
+/// Update an existing mem for `add --upsert`: its content (from `-c` or
+/// stdin) and any fields given, leaving it untouched when nothing changes.
+fn upsert_mem(
+    ctx: &Context,
+    storage: &Storage,
+    path: &str,
+    content: Option<String>,
+    fields: NewFields,
+    options: &AddOptions,
+    output: OutputFormat,
+) -> Result<()> {
+    let mut mem = storage.read_mem(path)?;
+    check_unlocked(&mem, options.force_unlock)?;
+    let before = mem.serialize()?;
+
+    let content = match content {
+        Some(c) => Some(text::decode(c.into_bytes(), "--content")?),
+        None if !io::stdin().is_terminal() => Some(read_stdin()?).filter(|b| !b.is_empty()),
+        None => None,
+    };
+    if let Some(content) = content {
+        mem.content = content;
+    }
+    if let Some(title) = fields.title {
+        mem.title = title;
+    }
+    if let Some(tags) = fields.tags {
+        mem.tags = text::parse_tags(&tags);
+    }
+    if let Some(owner) = fields.owner {
+        mem.set_field("owner", owner);
+    }
+    if let Some(kind) = fields.kind {
+        mem.set_field("kind", kind);
+    }
+
+    let action = if mem.serialize()? == before {
+        "unchanged"
+    } else {
+        check_size(&Config::for_storage(storage)?, &mem, options.allow_large)?;
+        mem.touch();
+        storage.write_mem(&mem)?;
+        "updated"
+    };
+    match output {
+        OutputFormat::Json => print_upsert_json(action, &mem)?,
+        OutputFormat::Text if action == "updated" => status!(ctx, "Updated: {path}"),
+        OutputFormat::Text => status!(ctx, "Unchanged: {path}"),
+    }
+    Ok(())
+}
+
+fn print_upsert_json(action: &'static str, mem: &Mem) -> Result<()> {
+    let json = UpsertJson {
+        action,
+        mem: MemRecord::from(mem),
+    };
+    println!("{}", serde_json::to_string_pretty(&json)?);
+    Ok(())
+}
+
+/// Warn about tags outside the allowed vocabulary, or (without one) a tag
+/// one edit away from a tag already in use, such as `infar` for `infra`.
+fn warn_unknown_tags(storage: &Storage, schema: &Schema, tags: &[String]) -> Result<()> {
+    let known: Vec<String> = if schema.allowed_tags.is_empty() {
+        storage.tag_counts()?.into_iter().map(|(t, _)| t).collect()
+    } else {
+        schema.allowed_tags.clone()
+    };
+    for tag in tags {
+        if known.iter().any(|k| text::same(k, tag)) {
+            continue;
+        }
+        match fuzzy::near_tag(tag, known.iter().map(String::as_str)) {
+            Some(near) => eprintln!("Unknown tag {tag} (did you mean {near}?)"),
+            None if !schema.allowed_tags.is_empty() => {
+                eprintln!("Unknown tag {tag} (not in the allowed tags)")
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Ask on the terminal for a template variable until the answer is valid.
+/// `None` (so the default applies) when not interactive or left blank.
+fn ask_variable(variable: &scaffold::Variable, interactive: bool) -> Result<Option<String>> {
+    if !interactive {
+        return Ok(None);
+    }
+    loop {
+        eprint!("{}", variable.question());
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            if variable.default.is_some() {
+                return Ok(None);
+            }
+            eprintln!("{} is required", variable.name);
+            continue;
+        }
+        match variable.validate(answer) {
+            Ok(_) => return Ok(Some(answer.to_string())),
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
+
+/// Fill in frontmatter fields the schema requires. Interactively, prompt for
+/// each one; otherwise only `status` is set, to the first allowed status.
+fn prefill_fields(mem: &mut Mem, schema: &Schema, interactive: bool) -> Result<()> {
+    for violation in schema.check(mem) {
+        let Violation::MissingField(field) = violation else {
+            continue;
+        };
+        if matches!(field.as_str(), "title" | "tags" | "id" | "checksum") {
+            continue;
+        }
+        let default = if field == "status" {
+            schema.allowed_statuses.first().cloned()
+        } else {
+            None
+        };
+
+        let value = if interactive {
+            let choices = if field == "status" && !schema.allowed_statuses.is_empty() {
+                format!(" ({})", schema.allowed_statuses.join(", "))
+            } else {
+                String::new()
+            };
+            let hint = default
+                .as_ref()
+                .map(|d| format!(" [{d}]"))
+                .unwrap_or_default();
+            eprint!("{field}{choices}{hint}: ");
+            io::stderr().flush()?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            Some(answer.trim().to_string())
+                .filter(|a| !a.is_empty())
+                .or(default)
+        } else {
+            default
+        };
+        if let Some(value) = value {
+            mem.set_field(&field, value);
+        }
+    }
+    Ok(())
+}
+
+/// Options of `show`.
+struct ShowOptions {
+    fuzzy: bool,
+    render: bool,
+    last: Option<usize>,
+    archived: bool,
+    listing: Listing,
+}
+
+fn cmd_show(ctx: &Context, paths: &[String], options: ShowOptions, dirs: &[PathBuf]) -> Result<()> {
+    let ShowOptions {
+        fuzzy,
+        render,
+        last,
+        archived,
+        listing,
+    } = options;
+    let several = paths.len() > 1 || paths.iter().any(|p| glob::is_pattern(p));
+    let mut shown = Vec::new();
+    for path in paths {
+        shown.push(mems_to_show(ctx, path, fuzzy, archived, dirs)?);
+    }
+
+    if let Some(count) = last {
+        let [(_, mems)] = shown.as_slice() else {
+            return Err(anyhow!("--last needs a single log mem"));
+        };
+        let [mem] = mems.as_slice() else {
+            return Err(anyhow!("--last needs a single log mem"));
+        };
+        let path = mem.path_str();
+        if mem.kind() != Some(log::LOG_KIND) {
+            return Err(anyhow!(
+                "--last needs a log mem, and {path} is not one (kind: log)"
+            ));
+        }
+        let entries = log::last(&mem.content, count);
+        if !listing.is_text() {
+            listing.print(&entries)?;
+        } else {
+            for entry in &entries {
+                print!("{}", entry.to_markdown());
+            }
+        }
+        return Ok(());
+    }
+    if render {
+        for (storage, mems) in &mut shown {
+            for mem in mems {
+                mem.content = include::expand(storage, mem)?;
+            }
+        }
+    }
+    let mems: Vec<&Mem> = shown.iter().flat_map(|(_, mems)| mems).collect();
+
+    if !listing.is_text() {
+        let json_output: Vec<MemRecord> = mems.iter().map(|mem| MemRecord::from(*mem)).collect();
+        if several || !matches!(listing, Listing::Json) {
+            listing.print(&json_output)?;
+        } else {
+            println!("{}", serde_json::to_string_pretty(&json_output[0])?);
+        }
+    } else {
+        for (i, mem) in mems.iter().enumerate() {
+            if several {
+                if i > 0 {
+                    println!();
+                }
+                print_divider(&mem.path_str())?;
+            }
+            print_mem(mem);
+        }
+    }
+
+    if !archived {
+        for (storage, mems) in &shown {
+            track_usage(storage, mems);
+        }
+    }
+    Ok(())
+}
+
+/// Record that `mems` were shown, if the store tracks usage. Failing to
+/// record is only a warning.
+fn track_usage(storage: &Storage, mems: &[Mem]) {
+    let Ok(root) = storage.local_root() else {
+        return;
+    };
+    let recorded = Usage::open(root).and_then(|usage| match usage {
+        Some(mut usage) => {
+            usage.record(&mems.iter().collect::<Vec<_>>(), chrono::Utc::now());
+            usage.save(root)
+        }
+        None => Ok(()),
+    });
+    if let Err(e) = recorded {
+        eprintln!("warning: failed to record usage: {e}");
+    }
+}
+
+/// The store and mems a `show` argument names: one mem, or every mem
+/// matching a glob pattern in path order.
+fn mems_to_show(
+    ctx: &Context,
+    reference: &str,
+    fuzzy: bool,
+    archived: bool,
+    dirs: &[PathBuf],
+) -> Result<(Storage, Vec<Mem>)> {
+    let (storage, path) = storage_for(ctx, reference, dirs)?;
+    if glob::is_pattern(&path) {
+        let mut mems = if archived {
+            storage.archived_mems()?
+        } else {
+            storage.list_mems()?
+        };
+        mems.retain(|m| glob::matches(&path, &m.path_str()));
+        if mems.is_empty() {
+            return Err(anyhow!("no mems match {reference}"));
+        }
+        mems.sort_by(|a, b| a.path.cmp(&b.path));
+        return Ok((storage, mems));
+    }
+    let mem = if archived {
+        storage.read_archived(&path)?
+    } else {
+        let path = resolve_mem(ctx, &storage, &path, fuzzy)?;
+        let (mem, stubs) = redirect::follow(&storage, storage.read_mem(&path)?)?;
+        if !stubs.is_empty() {
+            eprintln!("Redirected from {path} to {}", mem.path_str());
+        }
+        mem
+    };
+    Ok((storage, vec![mem]))
+}
+
+/// Section divider naming a mem, between mems printed together.
+fn print_divider(path: &str) -> io::Result<()> {
+    dump::write_divider(&mut io::stdout(), path)
+}
+
+fn print_mem(mem: &Mem) {
+    println!("# {}", mem.title);
+    println!();
+    if !mem.tags.is_empty() {
+        println!("Tags: {}", mem.tags.join(", "));
+        println!();
+    }
+    println!("{}", mem.content);
+}
+
+/// Changes `edit` makes, each only when given.
+struct EditFields {
+    content: Option<String>,
+    title: Option<String>,
+    tags: Option<String>,
+}
+
+/// Checks `edit` skips or forces.
+struct EditOptions {
+    force_touch: bool,
+    force_unlock: bool,
+    allow_large: bool,
+}
+
+fn cmd_edit(
+    ctx: &Context,
+    path: &str,
+    fuzzy: bool,
+    fields: EditFields,
+    options: EditOptions,
+    output: OutputFormat,
+) -> Result<()> {
+    let EditOptions {
+        force_touch,
+        force_unlock,
+        allow_large,
+    } = options;
+    let storage = find_storage(ctx)?;
+    let path = &resolve_mem(ctx, &storage, path, fuzzy)?;
+    let mut mem = storage.read_mem(path)?;
+    check_unlocked(&mem, force_unlock)?;
+    let before = (mem.title.clone(), mem.tags.clone(), mem.content.clone());
+
+    // Update fields if provided
+    if let Some(c) = fields.content {
+        mem.content = text::decode(c.into_bytes(), "--content")?;
+    }
+    if let Some(t) = fields.title {
+        mem.title = t;
+    }
+    if let Some(t) = fields.tags {
+        mem.tags = text::parse_tags(&t);
+    }
+
+    if before != (mem.title.clone(), mem.tags.clone(), mem.content.clone()) {
+        check_size(&Config::for_storage(&storage)?, &mem, allow_large)?;
+    }
+    // Re-applying identical content leaves the file, and updated-at, alone
+    let written = if force_touch {
+        mem.touch();
+        storage.write_mem(&mem)?;
+        true
+    } else {
+        storage.write_mem_if_changed(&mut mem)?
+    };
+    match output {
+        OutputFormat::Json => print_mem_json(&mem)?,
+        OutputFormat::Text if written => status!(ctx, "Updated: {path}"),
+        OutputFormat::Text => status!(ctx, "Unchanged: {path}"),
+    }
+    Ok(())
+}
+
+fn cmd_rm(
+    ctx: &Context,
+    path: &str,
+    fuzzy: bool,
+    force_unlock: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &resolve_mem(ctx, &storage, path, fuzzy)?;
+    let mem = storage.read_mem(path).ok();
+    if let Some(mem) = &mem {
+        check_unlocked(mem, force_unlock)?;
+    }
+    let id = mem.and_then(|m| m.id);
+    storage.delete_mem(path)?;
+    match output {
+        OutputFormat::Json => print_operation_json("deleted", path, id)?,
+        OutputFormat::Text => status!(ctx, "Deleted: {path}"),
+    }
+    Ok(())
+}
+
+fn cmd_mv(
+    ctx: &Context,
+    from: &str,
+    to: &str,
+    redirect: bool,
+    force_unlock: bool,
+    output: OutputFormat,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let (storage, from) = storage_for(ctx, from, dirs)?;
+    let from = storage.resolve(&from)?;
+    check_unlocked(&storage.read_mem(&from)?, force_unlock)?;
+    let to = normalize_path(to)?;
+    // Moving a mem back to where it left a redirect replaces the stub
+    let stub = storage
+        .exists(&to)
+        .then(|| storage.read_mem(&to).ok())
+        .flatten();
+    if stub.as_ref().and_then(redirect::target) == Some(from.as_str()) {
+        storage.delete_mem(&to)?;
+    }
+    storage.move_mem(&from, &to)?;
+    let mut moved = storage.read_mem(&to)?;
+    // Links to attachments are relative, so follow the mem to its new place
+    if let Some(content) = attachment::relink(&moved.content, &to) {
+        moved.content = content;
+        storage.write_mem(&moved)?;
+    }
+    if redirect {
+        storage.write_mem(&redirect::stub(&from, &moved))?;
+    }
+
+    // Stubs that redirected to the old path skip straight to the new one
+    for stub in storage.list_mems()? {
+        if redirect::target(&stub) != Some(from.as_str()) || stub.path_str() == from {
+            continue;
+        }
+        let path = stub.path_str();
+        if stub.is_locked() {
+            eprintln!("warning: not updating locked redirect {path}");
+            continue;
+        }
+        storage.write_mem(&redirect::retarget(&stub, &moved))?;
+        status!(ctx, "Updated redirect at {path}");
+    }
+
+    // Links to the mem from other stores in play name its old path
+    let storages = get_storages(ctx, dirs)?;
+    if storages.len() > 1 {
+        let names = store_names(&storages, &storage)?;
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        for (label, other) in &storages {
+            for mut mem in other.list_mems()? {
+                let Some(content) = links::rewrite_store_links(&mem.content, &names, &from, &to)
+                else {
+                    continue;
+                };
+                let path = mem.path_str();
+                if mem.is_locked() {
+                    eprintln!("warning: not updating links in locked mem [{label}] {path}");
+                    continue;
+                }
+                mem.content = content;
+                other.write_mem(&mem)?;
+                status!(ctx, "Updated links in [{label}] {path}");
+            }
+        }
+    }
+
+    match output {
+        OutputFormat::Json => print_mem_json(&storage.read_mem(&to)?)?,
+        OutputFormat::Text => {
+            status!(ctx, "Moved: {from} -> {to}");
+            if redirect {
+                status!(ctx, "Left a redirect at {from}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_replace(
+    ctx: &Context,
+    pattern: &replace::Pattern,
+    replacement: &str,
+    path: Option<&str>,
+    dry_run: bool,
+    force_unlock: bool,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let prefix = path.map(normalize_path).transpose()?.unwrap_or_default();
+    let mut changes = replace::plan(&storage, &prefix, pattern, replacement)?;
+    changes.retain(|change| {
+        let locked = change.before.is_locked() && !force_unlock;
+        if locked {
+            eprintln!(
+                "warning: not replacing in locked mem {}",
+                change.before.path_str()
+            );
+        }
+        !locked
+    });
+
+    if dry_run {
+        for change in &changes {
+            let path = change.after.path_str();
+            let hunks = diff::hunks(&change.before.content, &change.after.content, 3);
+            print!("{}", diff::unified(&hunks, &path, &path, use_color()));
+        }
+    } else {
+        replace::apply(&storage, &changes)?;
+        for change in &changes {
+            status!(
+                ctx,
+                "Replaced {} in {}",
+                change.count,
+                change.after.path_str()
+            );
+        }
+    }
+
+    let matches: usize = changes.iter().map(|c| c.count).sum();
+    let verb = if dry_run { "Would replace" } else { "Replaced" };
+    status!(
+        ctx,
+        "{verb} {matches} match{} in {} mem{}",
+        if matches == 1 { "" } else { "es" },
+        changes.len(),
+        if changes.len() == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
+
+/// Refuse to change a locked mem unless `--force-unlock` was given.
+fn check_unlocked(mem: &Mem, force_unlock: bool) -> Result<()> {
+    if mem.is_locked() && !force_unlock {
+        return Err(MemError::Locked {
+            path: mem.path_str(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Refuse to change a field of a locked mem, or the `locked` field itself
+/// (that's for `mem lock` and `mem unlock`), unless `--force-unlock` was given.
+fn check_field_unlocked(mem: &Mem, field: &str, force_unlock: bool) -> Result<()> {
+    check_unlocked(mem, force_unlock)?;
+    if field == "locked" && !force_unlock {
+        return Err(anyhow!(
+            "locked is changed with mem lock and mem unlock (pass --force-unlock to change it here)"
+        ));
+    }
+    Ok(())
+}
+
+/// Refuse to save a mem larger than `max-mem-kb` unless `--allow-large` was
+/// given or, on a terminal, the user agrees.
+fn check_size(config: &Config, mem: &Mem, allow_large: bool) -> Result<()> {
+    let Some(max) = config.max_mem_bytes() else {
+        return Ok(());
+    };
+    let size = mem.serialize()?.len() as u64;
+    if size <= max || allow_large {
+        return Ok(());
+    }
+    let message = format!(
+        "{} is {} KB, over the {} KB limit (max-mem-kb)",
+        mem.path_str(),
+        size.div_ceil(1024),
+        max / 1024
+    );
+    if io::stdin().is_terminal() && io::stderr().is_terminal() {
+        eprint!("{message}. Save anyway? [y/N] ");
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            return Ok(());
+        }
+    }
+    Err(anyhow!("{message}; use --allow-large to save it anyway"))
+}
+
+/// Print a mem resulting from a change, for `--output json`.
+fn print_mem_json(mem: &Mem) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&MemRecord::from(mem))?);
+    Ok(())
+}
+
+/// Print the summary of a change that leaves no mem behind, for `--output json`.
+fn print_operation_json(action: &'static str, path: &str, id: Option<String>) -> Result<()> {
+    let summary = OperationJson {
+        action,
+        path: path.to_string(),
+        id,
+    };
+    println!("{}", serde_json::to_string_pretty(&summary)?);
+    Ok(())
+}
+
+/// Which mems `ls` lists.
+struct LsFilter<'a> {
+    owner: Option<&'a str>,
+    pinned: bool,
+    kind: Option<&'a str>,
+    conditions: &'a [meta::Condition],
+}
+
+impl LsFilter<'_> {
+    fn matches(&self, mem: &Mem) -> bool {
+        self.owner.is_none_or(|o| mem.owner() == Some(o))
+            && (!self.pinned || mem.is_pinned())
+            && self.kind.is_none_or(|k| kind::of(mem) == k)
+            && self.conditions.iter().all(|c| c.matches(mem))
+    }
+}
+
+/// What `ls` lists and how.
+struct LsOptions {
+    long: bool,
+    archived: bool,
+    hot: bool,
+    scoped: bool,
+}
+
+fn cmd_ls(
+    path: Option<&str>,
+    options: LsOptions,
+    filter: &LsFilter,
+    listing: &Listing,
+    storages: Vec<(String, Storage)>,
+) -> Result<()> {
+    let LsOptions {
+        long,
+        archived,
+        hot,
+        scoped,
+    } = options;
+    let multi = storages.len() > 1;
+    // How often each mem was shown, by (store label, path), for --hot
+    let mut shown = std::collections::HashMap::new();
+
+    let mut all_mems: Vec<(String, Mem)> = Vec::new();
+    // Directory descriptions by (store label, directory)
+    let mut descriptions = std::collections::HashMap::new();
+    // Mems under the current directory's scope, by (store label, path)
+    let mut in_scope = BTreeSet::new();
+    for (label, storage) in &storages {
+        // Only sizes, JSON, and templates need content; skip reading it for large mems
+        let mems = if archived {
+            storage.archived_mems_under(path.unwrap_or_default())?
+        } else if long || !listing.is_text() {
+            storage.list_mems_under(path.unwrap_or_default())?
+        } else {
+            storage.list_headers_under(path.unwrap_or_default())?
+        };
+        let scope = if scoped { storage.cwd_scope()? } else { None };
+        let usage = if hot {
+            let root = storage.local_root()?;
+            Some(Usage::open(root)?.ok_or_else(|| {
+                anyhow!(
+                    "usage tracking is off for {} (run `mem usage enable`)",
+                    root.display()
+                )
+            })?)
+        } else {
+            None
+        };
+        for mem in mems {
+            if !filter.matches(&mem) {
+                continue;
+            }
+            if let Some(usage) = &usage {
+                let Some(record) = usage.get(&mem) else {
+                    continue;
+                };
+                shown.insert((label.clone(), mem.path_str()), record.clone());
+            }
+            let path_str = mem.path_str();
+            if scope.as_ref().is_some_and(|s| schema::under(&path_str, s)) {
+                in_scope.insert((label.clone(), path_str));
+            }
+            let path_str = mem.path_str();
+            if let Some((dir, _)) = path_str.rsplit_once('/').filter(|_| !archived) {
+                if let std::collections::hash_map::Entry::Vacant(entry) =
+                    descriptions.entry((label.clone(), dir.to_string()))
+                {
+                    entry.insert(directory::load(storage, dir)?.description);
+                }
+            }
+            all_mems.push((label.clone(), mem));
+        }
+    }
+    // Pinned mems first, then those in scope, otherwise in path order
+    all_mems.sort_by_key(|(label, mem)| {
+        let key = (label.clone(), mem.path_str());
+        (!mem.is_pinned(), !in_scope.contains(&key))
+    });
+    if hot {
+        // Most shown first, most recently shown among equals
+        all_mems.sort_by_key(|(label, mem)| {
+            let record = &shown[&(label.clone(), mem.path_str())];
+            (
+                std::cmp::Reverse(record.count),
+                std::cmp::Reverse(record.last_shown),
+            )
+        });
+    }
+
+    if !listing.is_text() {
+        let json_output: Vec<MemRecord> =
+            all_mems.iter().map(|(_, m)| MemRecord::from(m)).collect();
+        listing.print(&json_output)?;
+    } else if all_mems.is_empty() {
+        println!("No mems found");
+    } else {
+        if long {
+            println!("{:>8} {:>7} {:>7}  PATH", "SIZE", "WORDS", "TOKENS");
+        }
+        for (label, mem) in &all_mems {
+            let path_str = mem.path_str();
+            let tags = if mem.tags.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", mem.tags.join(", "))
+            };
+            let prefix = if multi {
+                format!("[{label}] ")
+            } else {
+                String::new()
+            };
+            // Describe each directory before its first unpinned mem
+            if let Some((dir, _)) = path_str.rsplit_once('/').filter(|_| !mem.is_pinned()) {
+                let key = (label.clone(), dir.to_string());
+                if let Some(Some(description)) = descriptions.remove(&key) {
+                    let pad = if long { " ".repeat(26) } else { String::new() };
+                    println!("{pad}{prefix}{dir}/: {description}");
+                }
+            }
+            let columns = if long {
+                format!(
+                    "{:>8} {:>7} {:>7}  ",
+                    mem.size(),
+                    mem.word_count(),
+                    mem.estimated_tokens()
+                )
+            } else {
+                String::new()
+            };
+            let pin = if mem.is_pinned() { " (pinned)" } else { "" };
+            let why = match shown.get(&(label.clone(), path_str.clone())) {
+                Some(record) => format!(
+                    " (shown {} time{}, last {})",
+                    record.count,
+                    if record.count == 1 { "" } else { "s" },
+                    record.last_shown.format("%Y-%m-%d")
+                ),
+                None if archived => archive_note(mem),
+                None => String::new(),
+            };
+            println!("{columns}{prefix}{path_str}: {}{tags}{pin}{why}", mem.title);
+        }
+    }
+
+    Ok(())
+}
+
+/// When and why an archived mem was archived, e.g.
+/// ` (archived 2025-03-12: superseded by adr-017)`.
+fn archive_note(mem: &Mem) -> String {
+    let at = mem
+        .field(ARCHIVED_AT_FIELD)
+        .and_then(dates::parse_timestamp)
+        .map(|at| format!(" {}", at.format("%Y-%m-%d")))
+        .unwrap_or_default();
+    match mem.field(ARCHIVE_REASON_FIELD) {
+        Some(reason) => format!(" (archived{at}: {reason})"),
+        None => format!(" (archived{at})"),
+    }
+}
+
+fn cmd_scope(ctx: &Context) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    match storage.cwd_scope()? {
+        Some(prefix) => println!("{prefix}"),
+        None => println!("No scope for this directory"),
+    }
+    Ok(())
+}
+
+fn cmd_archive(
+    ctx: &Context,
+    command: Option<ArchiveCommand>,
+    path: Option<&str>,
+    reason: Option<&str>,
+    force_unlock: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    if let Some(ArchiveCommand::Compact) = command {
+        let compacted = storage.compact_archive()?;
+        match output {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&compacted)?),
+            OutputFormat::Text if compacted.is_empty() => {
+                status!(ctx, "Nothing to compact: archived mems are all compressed")
+            }
+            OutputFormat::Text => {
+                for item in &compacted {
+                    status!(
+                        ctx,
+                        "Compressed archive/{}: {} -> {} bytes",
+                        item.path,
+                        item.before,
+                        item.after
+                    );
+                }
+                let before: u64 = compacted.iter().map(|c| c.before).sum();
+                let after: u64 = compacted.iter().map(|c| c.after).sum();
+                status!(
+                    ctx,
+                    "Compressed {} archived mems, {before} -> {after} bytes",
+                    compacted.len()
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    let path = path.ok_or_else(|| anyhow!("missing path of the mem to archive"))?;
+    let path = &storage.resolve(path)?;
+    let mem = storage.read_mem(path).ok();
+    if let Some(mem) = &mem {
+        check_unlocked(mem, force_unlock)?;
+    }
+    let id = mem.and_then(|m| m.id);
+    let reason = reason.map(str::trim).filter(|r| !r.is_empty());
+    storage.archive_mem(path, reason)?;
+    match output {
+        OutputFormat::Json => print_operation_json("archived", path, id)?,
+        OutputFormat::Text => status!(ctx, "Archived: {path}"),
+    }
+    Ok(())
+}
+
+/// JSON output of `gc`.
+#[derive(Serialize)]
+struct GcJson {
+    dry_run: bool,
+    #[serde(flatten)]
+    plan: gc::Plan,
+}
+
+fn cmd_gc(
+    ctx: &Context,
+    dry_run: bool,
+    redirects: bool,
+    attachments: bool,
+    json: bool,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let config = Config::for_storage(&storage)?;
+    let mems = storage.list_mems()?;
+    let archived = storage.archived_mems()?;
+    let mut plan = gc::plan(&config.retention, &mems, &archived, chrono::Utc::now())?;
+    if redirects {
+        plan.remove_redirects(&mems);
+    }
+    if attachments {
+        plan.remove_attachments(attachment::blobs(&storage)?, &mems, &archived);
+    }
+
+    if !dry_run {
+        for item in &plan.archive {
+            storage.archive_mem(&item.path, Some(&item.reason))?;
+        }
+        for item in &plan.purge {
+            storage.purge_archived(&item.path)?;
+        }
+        for item in &plan.redirects {
+            storage.delete_mem(&item.path)?;
+        }
+        for item in &plan.attachments {
+            storage.delete_file(&item.path)?;
+        }
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&GcJson { dry_run, plan })?
+        );
+    } else if plan.is_empty() {
+        println!("Nothing to archive or purge");
+    } else if dry_run {
+        for item in &plan.archive {
+            println!("Would archive {}: {}", item.path, item.reason);
+        }
+        for item in &plan.purge {
+            println!("Would purge archive/{}: {}", item.path, item.reason);
+        }
+        for item in &plan.redirects {
+            println!("Would delete redirect {}: {}", item.path, item.reason);
+        }
+        for item in &plan.attachments {
+            println!("Would delete attachment {}: {}", item.path, item.reason);
+        }
+    } else {
+        for item in &plan.archive {
+            status!(ctx, "Archived {}: {}", item.path, item.reason);
+        }
+        for item in &plan.purge {
+            status!(ctx, "Purged archive/{}: {}", item.path, item.reason);
+        }
+        for item in &plan.redirects {
+            status!(ctx, "Deleted redirect {}: {}", item.path, item.reason);
+        }
+        for item in &plan.attachments {
+            status!(ctx, "Deleted attachment {}: {}", item.path, item.reason);
+        }
+    }
+    Ok(())
+}
+
+fn cmd_attach(ctx: &Context, path: &str, files: &[PathBuf], force_unlock: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &storage.resolve(path)?;
+    let mut mem = storage.read_mem(path)?;
+    check_unlocked(&mem, force_unlock)?;
+
+    let mut links = Vec::new();
+    let mut attached = Vec::new();
+    for file in files {
+        let data =
+            std::fs::read(file).with_context(|| format!("failed to read {}", file.display()))?;
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow!("not a file: {}", file.display()))?;
+        let (key, written) = attachment::store(&storage, &name, &data)?;
+        let target = attachment::link_target(path, &key);
+        let image = ["png", "jpg", "jpeg", "gif", "svg", "webp"]
+            .iter()
+            .any(|ext| key.ends_with(&format!(".{ext}")));
+        links.push(format!(
+            "{}[{name}]({target})",
+            if image { "!" } else { "" }
+        ));
+        let stored = if written { "stored" } else { "already stored" };
+        attached.push(format!("Attached {name} to {path} ({stored} as {key})"));
+    }
+
+    let content = mem.content.trim_end();
+    mem.content = match content {
+        "" => format!("{}\n", links.join("\n")),
+        _ => format!("{content}\n\n{}\n", links.join("\n")),
+    };
+    mem.touch();
+    storage.write_mem(&mem)?;
+    for line in attached {
+        status!(ctx, "{line}");
+    }
+    Ok(())
+}
+
+fn cmd_split(ctx: &Context, path: &str, force: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &storage.resolve(path)?;
+    let mem = storage.read_mem(path)?;
+
+    let (mut index, children) = mem
+        .split_by_headings()
+        .ok_or_else(|| anyhow!("nothing to split: {path} has no top-level headings"))?;
+
+    // Refuse to clobber existing children before writing anything
+    if !force {
+        for child in &children {
+            let child_path = child.path_str();
+            if storage.exists(&child_path) {
+                return Err(anyhow!(
+                    "mem already exists: {child_path} (use --force to overwrite)"
+                ));
+            }
+        }
+    }
+
+    for child in &children {
+        storage.write_mem(child)?;
+        status!(ctx, "Created: {}", child.path_str());
+    }
+
+    index.touch();
+    storage.write_mem(&index)?;
+    status!(ctx, "Split {path} into {} mems", children.len());
+    Ok(())
+}
+
+fn cmd_clip(
+    ctx: &Context,
+    url: &str,
+    path: &str,
+    title: Option<String>,
+    tags: Option<String>,
+    note: Option<String>,
+    force: bool,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &normalize_path(path)?;
+
+    if storage.exists(path) && !force {
+        return Err(anyhow!(
+            "mem already exists: {path} (use --force to overwrite)"
+        ));
+    }
+
+    let body = ureq::get(url)
+        .call()
+        .map_err(|e| anyhow!("failed to fetch {url}: {e}"))?
+        .into_string()
+        .map_err(|e| anyhow!("failed to read {url}: {e}"))?;
+    let page = html::to_markdown(&body, url);
+
+    let title = title.or(page.title).unwrap_or_else(|| {
+        path.rsplit('/')
+            .next()
+            .unwrap_or(path)
+            .replace(['-', '_'], " ")
+    });
+
+    let content = match note {
+        Some(note) => format!("{}\n\n---\n\n{}", note.trim(), page.markdown),
+        None => page.markdown,
+    };
+
+    let tags = tags.as_deref().map(text::parse_tags).unwrap_or_default();
+
+    let mut mem = Mem::new(PathBuf::from(path), title, content).with_tags(tags);
+    mem.set_field("source", url);
+    mem.set_field("fetched-at", mem.created_at.to_rfc3339());
+    let fetched_at = mem.created_at;
+    refresh::mark_synced(&mut mem, fetched_at);
+    storage.write_mem(&mem)?;
+
+    status!(ctx, "Clipped: {url} -> {path}");
+    Ok(())
+}
+
+fn cmd_import(
+    ctx: &Context,
+    format: import::Format,
+    dir: &Path,
+    into: Option<&str>,
+    force: bool,
+    force_unlock: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let imported = import::import_dir(dir, format, ctx.progress().as_ref())?;
+    write_imported(ctx, imported, dir, into, force, force_unlock, dry_run)
+}
+
+/// Write the mems of an import under `into`, or list them with `dry_run`.
+fn write_imported(
+    ctx: &Context,
+    mut imported: import::Imported,
+    from: &Path,
+    into: Option<&str>,
+    force: bool,
+    force_unlock: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let into = into.map(normalize_path).transpose()?;
+    let progress = ctx.progress();
+    for (file, reason) in &imported.skipped {
+        eprintln!("Skipped {}: {reason}", file.display());
+    }
+    if let Some(into) = &into {
+        for mem in &mut imported.mems {
+            mem.path = PathBuf::from(format!("{}/{}", into.trim_end_matches('/'), mem.path_str()));
+        }
+    }
+
+    let mut existing = imported
+        .mems
+        .iter()
+        .map(|m| m.path_str())
+        .filter(|p| storage.exists(p));
+    if !force {
+        if let Some(existing) = existing.next() {
+            return Err(anyhow!(
+                "mem already exists: {existing} (use --force to overwrite)"
+            ));
+        }
+    }
+    for path in existing {
+        if let Ok(mem) = storage.read_mem(&path) {
+            check_unlocked(&mem, force_unlock)?;
+        }
+    }
+
+    if dry_run {
+        for mem in &imported.mems {
+            println!("{}", mem.path_str());
+        }
+    } else {
+        progress.start("Importing", imported.mems.len());
+        for mem in &imported.mems {
+            storage.write_mem(mem)?;
+            progress.advance(&mem.path_str());
+        }
+        progress.finish();
+    }
+
+    if !dry_run {
+        status!(
+            ctx,
+            "Imported {} mems from {}",
+            imported.mems.len(),
+            from.display()
+        );
+    }
+    Ok(())
+}
+
+/// Fetch the GitHub threads to import, as mems relative to where they go.
+fn import_github(
+    ctx: &Context,
+    repo: &github::Repo,
+    issue: Option<u64>,
+    filter: &github::Filter,
+) -> Result<import::Imported> {
+    let client = github::Client::from_env();
+    let numbers = match issue {
+        Some(number) => vec![number],
+        None => client.matching(repo, filter)?,
+    };
+    let now = chrono::Utc::now();
+    let progress = ctx.progress();
+    progress.start("Fetching", numbers.len());
+    let mut mems = Vec::new();
+    for number in numbers {
+        let thread = client.thread(repo, number)?;
+        mems.push(thread.to_mem(&thread.default_path(), now));
+        progress.advance(&format!("#{number}"));
+    }
+    progress.finish();
+    Ok(import::Imported {
+        mems,
+        skipped: Vec::new(),
+    })
+}
+
+fn cmd_refresh(
+    ctx: &Context,
+    path: Option<&str>,
+    force: bool,
+    dry_run: bool,
+    force_unlock: bool,
+    allow_outside: bool,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let mems = match path.map(normalize_path).transpose()? {
+        Some(path) if storage.exists(&path) => vec![storage.read_mem(&path)?],
+        Some(path) => storage.list_mems_under(&path)?,
+        None => storage.list_mems()?,
+    };
+    // Relative file sources are relative to the directory holding the store
+    let base = match storage.local_root() {
+        Ok(root) => root.parent().unwrap_or(root).to_path_buf(),
+        Err(_) => std::env::current_dir()?,
+    };
+    let now = chrono::Utc::now();
+
+    let mut refreshed = 0;
+    let mut failed = 0;
+    for mem in mems {
+        let Some(source) = refresh::Source::of(&mem) else {
+            continue;
+        };
+        let path = mem.path_str();
+        if mem.is_locked() && !force_unlock {
+            eprintln!("warning: not refreshing locked mem {path}");
+            continue;
+        }
+        let fresh = match source.fetch(&mem, &base, now, allow_outside) {
+            Ok(fresh) => fresh,
+            Err(e) => {
+                eprintln!("warning: could not refresh {path}: {e:#}");
+                failed += 1;
+                continue;
+            }
+        };
+
+        if fresh.content == mem.content && fresh.title == mem.title {
+            // Record the sync of a mem that didn't have one
+            if refresh::is_edited(&mem) && !dry_run {
+                storage.write_mem(&fresh)?;
+            }
+            status!(ctx, "Up to date: {path}");
+            continue;
+        }
+        let edited = refresh::is_edited(&mem);
+        if dry_run {
+            let hunks = diff::hunks(&mem.content, &fresh.content, 3);
+            print!("{}", diff::unified(&hunks, &path, &path, use_color()));
+        }
+        if edited && !force {
+            eprintln!(
+                "warning: {path} was edited since it was last synced; not refreshing (use --force to overwrite)"
+            );
+            continue;
+        }
+        if !dry_run {
+            storage.write_mem(&fresh)?;
+            status!(ctx, "Refreshed: {path}");
+        }
+        refreshed += 1;
+    }
+
+    let verb = if dry_run {
+        "Would refresh"
+    } else {
+        "Refreshed"
+    };
+    status!(
+        ctx,
+        "{verb} {refreshed} mem{}",
+        if refreshed == 1 { "" } else { "s" }
+    );
+    if failed > 0 {
+        return Err(anyhow!(
+            "failed to refresh {failed} mem{}",
+            if failed == 1 { "" } else { "s" }
+        ));
+    }
+    Ok(())
+}
+
+fn cmd_import_bundle(ctx: &Context, file: &Path, dest: Option<&str>, force: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let bundle = Bundle::read(file)?;
+    let imported = bundle::import(&storage, &bundle, dest, force)?;
+    if let Some(id) = &imported.replaced_id {
+        eprintln!("warning: id {id} is used by another mem; gave the import a new id");
+    }
+    let (attachments, commits) = (imported.attachments.len(), bundle.manifest.history.len());
+    status!(
+        ctx,
+        "Imported: {} ({attachments} attachment{}, {commits} commit{} of history from {})",
+        imported.path,
+        if attachments == 1 { "" } else { "s" },
+        if commits == 1 { "" } else { "s" },
+        bundle.manifest.path
+    );
+    Ok(())
+}
+
+/// Which mems `find` searches, in what, and how results from several
+/// stores are ordered.
+struct FindFilter<'a> {
+    title_only: bool,
+    kind: Option<&'a str>,
+    under: Option<&'a str>,
+    search_in: find::SearchIn,
+    dedupe: bool,
+    prefer: &'a [String],
+    layout: Option<Layout>,
+}
+
+/// How to order `find` results from `storages`, weighted as the first
+/// store's config says; `default` is the layout when none is asked for.
+fn find_ranking(
+    storages: &[(String, Storage)],
+    filter: &FindFilter,
+    default: Layout,
+) -> Result<Ranking> {
+    let config = match storages.first() {
+        Some((_, storage)) => Config::for_storage(storage)?.search,
+        None => Default::default(),
+    };
+    let labels = storages.iter().map(|(label, _)| label.clone()).collect();
+    Ranking::new(labels, &config, filter.prefer, filter.layout, default)
+}
+
+/// `items` in the order of the indexes in `order`.
+fn reorder<T>(items: Vec<T>, order: &[usize]) -> Vec<T> {
+    let mut items: Vec<Option<T>> = items.into_iter().map(Some).collect();
+    order.iter().filter_map(|&i| items[i].take()).collect()
+}
+
+fn cmd_find(
+    ctx: &Context,
+    query: &str,
+    filter: FindFilter,
+    open: bool,
+    listing: &Listing,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+    let ranking = find_ranking(&storages, &filter, Layout::Grouped)?;
+    let FindFilter {
+        title_only,
+        kind,
+        under,
+        search_in,
+        dedupe,
+        ..
+    } = filter;
+    let under = under.unwrap_or_default();
+
+    // Case-insensitive search for the query's terms in title and content
+    let parsed = Query::parse(query);
+    let terms = parsed.terms();
+    let search = find::Filter {
+        title_only,
+        kind,
+        under,
+        search_in,
+    };
+    let mut matches: Vec<(String, Mem)> = Vec::new();
+    let mut scores: Vec<f32> = Vec::new();
+    // Indexes of the matches that are archived
+    let mut archived = BTreeSet::new();
+
+    for (label, storage) in &storages {
+        for found in find::find(storage, &parsed, &search)? {
+            if found.archived {
+                archived.insert(matches.len());
+            }
+            scores.push(found.score);
+            matches.push((label.clone(), found.mem));
+        }
+    }
+
+    // Best first across stores, before deduping so the copy kept is the
+    // best-ranked one
+    if multi {
+        let results: Vec<(&str, f32)> = matches
+            .iter()
+            .zip(&scores)
+            .map(|((label, _), &score)| (label.as_str(), score))
+            .collect();
+        let order = ranking.order(&results);
+        archived = (order.iter().enumerate())
+            .filter(|(_, i)| archived.contains(i))
+            .map(|(new, _)| new)
+            .collect();
+        matches = reorder(matches, &order);
+    }
+
+    let duplicates = if multi && dedupe {
+        dedupe_matches(&mut matches, &mut archived)
+    } else {
+        BTreeMap::new()
+    };
+
+    if !listing.is_text() {
+        let json_output: Vec<FoundJson> = matches
+            .iter()
+            .enumerate()
+            .map(|(i, (_, m))| FoundJson {
+                location: match (search_in, archived.contains(&i)) {
+                    (find::SearchIn::Active, _) => None,
+                    (_, true) => Some("archive"),
+                    (_, false) => Some("active"),
+                },
+                duplicates: duplicates
+                    .get(&i)
+                    .into_iter()
+                    .flatten()
+                    .map(|(label, path)| format!("{label}:{path}"))
+                    .collect(),
+                mem: MemRecord::from(m),
+            })
+            .collect();
+        listing.print(&json_output)?;
+    } else if matches.is_empty() {
+        println!("No matches found for: {query}");
+    } else if open {
+        open_result(ctx, &storages, &matches)?;
+    } else {
+        let (start, end) = if use_color() {
+            (highlight::ANSI_START, highlight::ANSI_END)
+        } else {
+            ("", "")
+        };
+        for (i, (label, mem)) in matches.iter().enumerate() {
+            let path_str = mem.path_str();
+            let prefix = if multi {
+                format!("[{label}] ")
+            } else {
+                String::new()
+            };
+            let title = highlight::highlight(&mem.title, &terms, start, end);
+            let marker = if archived.contains(&i) {
+                " (archived)"
+            } else {
+                ""
+            };
+            println!("{prefix}{path_str}{marker}: {title}");
+            if let Some(copies) = duplicates.get(&i) {
+                let copies: Vec<String> = copies
+                    .iter()
+                    .map(|(label, path)| format!("[{label}] {path}"))
+                    .collect();
+                println!("    Also in: {}", copies.join(", "));
+            }
+            // The first matching line of content, for context
+            if let Some(snippet) =
+                highlight::snippet(&mem.content, &terms, 80).filter(|_| !title_only)
+            {
+                println!("    {}", highlight::highlight(&snippet, &terms, start, end));
+            }
+        }
+        if !archived.is_empty() {
+            status!(
+                ctx,
+                "\nView an archived mem with: mem show --archived <path>"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether to color stdout: on a terminal unless `NO_COLOR` is set, or
+/// always when `CLICOLOR_FORCE` is set.
+fn use_color() -> bool {
+    if std::env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+        return true;
+    }
+    std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stdout().is_terminal()
+}
+
+/// Collapse identical matches (same title and content) from several stores,
+/// such as vendored docs, into the first. Returns the store label and path
+/// of the copies dropped, by the index of the match kept.
+fn dedupe_matches(
+    matches: &mut Vec<(String, Mem)>,
+    archived: &mut BTreeSet<usize>,
+) -> BTreeMap<usize, Vec<(String, String)>> {
+    let mut duplicates: BTreeMap<usize, Vec<(String, String)>> = BTreeMap::new();
+    let mut kept_by_hash = std::collections::HashMap::new();
+    let mut kept = Vec::new();
+    let mut kept_archived = BTreeSet::new();
+    for (i, (label, mem)) in std::mem::take(matches).into_iter().enumerate() {
+        let hash =
+            hash::sha256_hex(text::nfc(&format!("{}\n{}", mem.title, mem.content)).as_bytes());
+        if let Some(&first) = kept_by_hash.get(&hash) {
+            let path = mem.path_str();
+            duplicates.entry(first).or_default().push((label, path));
+            continue;
+        }
+        kept_by_hash.insert(hash, kept.len());
+        if archived.contains(&i) {
+            kept_archived.insert(kept.len());
+        }
+        kept.push((label, mem));
+    }
+    *matches = kept;
+    *archived = kept_archived;
+    duplicates
+}
+
+/// Open a search result for `find --open`: the only one, or one picked from a
+/// numbered list when run on a terminal.
+fn open_result(
+    ctx: &Context,
+    storages: &[(String, Storage)],
+    results: &[(String, Mem)],
+) -> Result<()> {
+    let index = match results.len() {
+        1 => 0,
+        n if io::stdin().is_terminal() => {
+            for (i, (label, mem)) in results.iter().enumerate() {
+                let prefix = if storages.len() > 1 {
+                    format!("[{label}] ")
+                } else {
+                    String::new()
+                };
+                eprintln!("{:>3}. {prefix}{}: {}", i + 1, mem.path_str(), mem.title);
+            }
+            eprint!("Open which? [1-{n}]: ");
+            io::stderr().flush()?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            match answer.trim().parse::<usize>() {
+                Ok(choice) if (1..=n).contains(&choice) => choice - 1,
+                _ => return Err(anyhow!("invalid choice: {}", answer.trim())),
+            }
+        }
+        n => {
+            return Err(anyhow!(
+                "{n} mems match; narrow the query or run on a terminal to pick one"
+            ))
+        }
+    };
+
+    let (label, mem) = &results[index];
+    let storage = storages
+        .iter()
+        .find(|(l, _)| l == label)
+        .map(|(_, s)| s)
+        .ok_or_else(|| anyhow!("store not found: {label}"))?;
+    open_mem(ctx, storage, mem)
+}
+
+/// Open a mem in `$VISUAL` or `$EDITOR` when on a terminal, refreshing
+/// `updated-at` if it was changed; otherwise print it.
+fn open_mem(ctx: &Context, storage: &Storage, mem: &Mem) -> Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|e| !e.trim().is_empty());
+    let interactive = io::stdin().is_terminal() && io::stdout().is_terminal();
+    // Locked mems open read-only
+    let (Some(editor), true, Ok(_), false) =
+        (editor, interactive, storage.local_root(), mem.is_locked())
+    else {
+        print_mem(mem);
+        return Ok(());
+    };
+
+    let path = mem.path_str();
+    let file = storage.mem_path(&path);
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or_default();
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(&file)
+        .status()
+        .with_context(|| format!("failed to run editor: {program}"))?;
+    if !status.success() {
+        return Err(anyhow!("editor exited with {status}"));
+    }
+
+    let mut edited = storage.read_mem(&path)?;
+    if edited.title != mem.title || edited.tags != mem.tags || edited.content != mem.content {
+        edited.touch();
+        storage.write_mem(&edited)?;
+        status!(ctx, "Updated: {path}");
+    }
+    Ok(())
+}
+
+fn cmd_find_semantic(
+    ctx: &Context,
+    query: &str,
+    limit: usize,
+    filter: FindFilter,
+    open: bool,
+    listing: &Listing,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+    let ranking = find_ranking(&storages, &filter, Layout::Interleaved)?;
+    let FindFilter { kind, under, .. } = filter;
+
+    // Each store keeps its own embedding cache and backend config
+    let mut hits: Vec<(String, Mem, f32)> = Vec::new();
+    for (label, storage) in &storages {
+        let config = Config::for_storage(storage)?;
+        let embedder = semantic::embedder_from_config(&config)?;
+        let mut mems = storage.list_mems_under(under.unwrap_or_default())?;
+        mems.retain(|mem| kind.is_none_or(|k| kind::of(mem) == k));
+        let analyzer = Analyzer::new(&config.analyzer);
+        let ranked =
+            semantic::hybrid_search(storage.root(), &mems, query, embedder.as_ref(), &analyzer)?;
+        for (idx, score) in ranked {
+            hits.push((label.clone(), mems[idx].clone(), score));
+        }
+    }
+
+    hits.sort_by(|a, b| b.2.total_cmp(&a.2));
+    if multi {
+        let results: Vec<(&str, f32)> = hits.iter().map(|(l, _, s)| (l.as_str(), *s)).collect();
+        let order = ranking.order(&results);
+        hits = reorder(hits, &order);
+    }
+    hits.truncate(limit);
+
+    if !listing.is_text() {
+        let json_output: Vec<ScoredJson> = hits
+            .iter()
+            .map(|(_, m, score)| ScoredJson {
+                mem: MemRecord::from(m),
+                score: *score,
+            })
+            .collect();
+        listing.print(&json_output)?;
+    } else if hits.is_empty() {
+        println!("No matches found for: {query}");
+    } else if open {
+        let results: Vec<(String, Mem)> = hits.into_iter().map(|(l, m, _)| (l, m)).collect();
+        open_result(ctx, &storages, &results)?;
+    } else {
+        for (label, mem, score) in &hits {
+            let path_str = mem.path_str();
+            let prefix = if multi {
+                format!("[{label}] ")
+            } else {
+                String::new()
+            };
+            println!("{prefix}{path_str}: {} ({score:.2})", mem.title);
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_related(ctx: &Context, path: &str, limit: usize, json: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &storage.resolve(path)?;
+    // Read first so a missing mem reports "not found" rather than an empty list
+    storage.read_mem(path)?;
+
+    let mems = storage.list_mems()?;
+    let target = mems
+        .iter()
+        .position(|m| m.path_str() == path.as_str())
+        .ok_or_else(|| anyhow!("mem not found: {path}"))?;
+
+    let config = Config::for_storage(&storage)?;
+    let embedder = semantic::embedder_from_config(&config)?;
+    let mut index = semantic::EmbeddingIndex::open(storage.root(), embedder.as_ref());
+    index.update(&mems, embedder.as_ref())?;
+    index.save()?;
+
+    let usage = Usage::open(storage.root())?;
+    let mut relations = related::related(target, &mems, &index, usage.as_ref());
+    relations.truncate(limit);
+
+    if json {
+        let json_output: Vec<RelatedJson> = relations
+            .iter()
+            .map(|r| RelatedJson {
+                path: mems[r.index].path_str(),
+                title: mems[r.index].title.clone(),
+                score: r.score,
+                tag_score: r.tags,
+                link_score: r.links,
+                content_score: r.content,
+                usage_score: usage.as_ref().map(|_| r.usage),
+                linked: r.linked,
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json_output)?);
+    } else if relations.is_empty() {
+        println!("No related mems found for: {path}");
+    } else {
+        println!("Related to {path}:");
+        for r in &relations {
+            let mem = &mems[r.index];
+            // Unlinked suggestions are candidates for new links
+            let marker = if r.linked { " [linked]" } else { "" };
+            let used = match usage {
+                Some(_) => format!(", usage {:.2}", r.usage),
+                None => String::new(),
+            };
+            println!(
+                "  {:.2}  {}: {} (tags {:.2}, links {:.2}, content {:.2}{used}){marker}",
+                r.score,
+                mem.path_str(),
+                mem.title,
+                r.tags,
+                r.links,
+                r.content
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_ask(
+    ctx: &Context,
+    question: &str,
+    retrieve_only: bool,
+    limit: usize,
+    json: bool,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+
+    let mut hits: Vec<(String, Mem, f32)> = Vec::new();
+    for (label, storage) in &storages {
+        let config = Config::for_storage(storage)?;
+        let embedder = semantic::embedder_from_config(&config)?;
+        let mems = storage.list_mems()?;
+        let analyzer = Analyzer::new(&config.analyzer);
+        let ranked = ask::retrieve(
+            storage.root(),
+            &mems,
+            question,
+            embedder.as_ref(),
+            &analyzer,
+            limit,
+        )?;
+        for (idx, score) in ranked {
+            hits.push((label.clone(), mems[idx].clone(), score));
+        }
+    }
+    hits.sort_by(|a, b| b.2.total_cmp(&a.2));
+    hits.truncate(limit);
+
+    if retrieve_only {
+        if json {
+            let json_output: Vec<ScoredJson> = hits
+                .iter()
+                .map(|(_, m, score)| ScoredJson {
+                    mem: MemRecord::from(m),
+                    score: *score,
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&json_output)?);
+        } else if hits.is_empty() {
+            println!("No relevant mems found for: {question}");
+        } else {
+            for (label, mem, score) in &hits {
+                let prefix = if multi {
+                    format!("[{label}] ")
+                } else {
+                    String::new()
+                };
+                println!("{prefix}{}: {} ({score:.2})", mem.path_str(), mem.title);
+            }
+        }
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        return Err(anyhow!("no relevant mems found for: {question}"));
+    }
+
+    // The LLM endpoint comes from the first store's config
+    let config = Config::for_storage(&storages[0].1)?;
+    let context: Vec<&Mem> = hits.iter().map(|(_, m, _)| m).collect();
+    let (messages, sources) =
+        ask::build_messages(question, &context, config.llm.max_context_tokens);
+    let answer = ask::complete(&config.llm, &messages)?;
+
+    if json {
+        let json_output = AnswerJson { answer, sources };
+        println!("{}", serde_json::to_string_pretty(&json_output)?);
+    } else {
+        println!("{}", answer.trim());
+        println!();
+        println!("Sources:");
+        for source in &sources {
+            println!("  - {source}");
+        }
+    }
+
+    Ok(())
+}
+
+/// JSON representation of a glossary definition.
+#[derive(Serialize)]
+struct DefinitionJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    store: Option<String>,
+    #[serde(flatten)]
+    term: glossary::Term,
+}
+
+fn cmd_def(ctx: &Context, term: &str, json: bool, dirs: &[PathBuf]) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+
+    let mut found = Vec::new();
+    let mut names = Vec::new();
+    for (label, storage) in &storages {
+        let terms = glossary::all_terms(&storage.list_mems()?);
+        for entry in glossary::lookup(&terms, term) {
+            // Report lines in the file, as lint does
+            let mut entry = entry.clone();
+            entry.line += content_line_offset(&storage.read_source(&entry.path)?);
+            found.push(DefinitionJson {
+                store: multi.then(|| label.clone()),
+                term: entry,
+            });
+        }
+        names.extend(terms.iter().flat_map(|t| t.names()).map(str::to_string));
+    }
+
+    if found.is_empty() {
+        let near = fuzzy::suggestions(term, names.iter().map(String::as_str));
+        let hint = if near.is_empty() {
+            String::new()
+        } else {
+            format!(" (did you mean {}?)", near.join(", "))
+        };
+        return Err(anyhow!("no glossary entry for {term}{hint}"));
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&found)?);
+    } else {
+        for (i, entry) in found.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            let prefix = entry
+                .store
+                .as_ref()
+                .map(|label| format!("[{label}] "))
+                .unwrap_or_default();
+            println!("{prefix}{}: {}", entry.term.path, entry.term.term);
+            for line in entry.term.definition.lines() {
+                if line.is_empty() {
+                    println!();
+                } else {
+                    println!("  {line}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// How `tree` prints the hierarchy.
+struct TreeOptions {
+    depth: Option<usize>,
+    counts: bool,
+    updated: bool,
+    json: bool,
+    archived: bool,
+    all: bool,
+}
+
+fn cmd_tree(
+    ctx: &Context,
+    path: Option<&str>,
+    options: TreeOptions,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+
+    let mut roots = Vec::new();
+    for (label, storage) in &storages {
+        let mut mems = Vec::new();
+        if !options.archived {
+            let active = storage.list_headers_under(path.unwrap_or_default())?;
+            mems.extend(active.into_iter().map(|m| (m, false)));
+        }
+        if options.archived || options.all {
+            let archived = storage
+                .archived_mems()?
+                .into_iter()
+                .filter(|m| path.is_none_or(|p| schema::under(&m.path_str(), p)));
+            mems.extend(archived.map(|m| (m, true)));
+        }
+        if mems.is_empty() && (multi || !options.json) {
+            continue;
+        }
+
+        let root_name = match (multi, path) {
+            (true, _) => label.as_str(),
+            (false, Some(path)) => path,
+            (false, None) if options.archived => ".mems/archive",
+            (false, None) => ".mems",
+        };
+        let mut root = tree::build(storage, &mems, root_name)?;
+        if let Some(depth) = options.depth {
+            root.truncate(depth);
+        }
+        roots.push(root);
+    }
+
+    if options.json {
+        if multi {
+            println!("{}", serde_json::to_string_pretty(&roots)?);
+        } else {
+            println!("{}", serde_json::to_string_pretty(&roots[0])?);
+        }
+        return Ok(());
+    }
+    if roots.is_empty() {
+        println!("No mems found");
+    }
+    for (idx, root) in roots.iter().enumerate() {
+        // Add separator between directories
+        if idx > 0 {
+            println!();
+        }
+        println!("{}/{}", root.name, tree_details(root, &options));
+        print_tree(root, "", &options);
+    }
+
+    Ok(())
+}
+
+/// What `--counts` and `--updated` add after a directory.
+fn tree_details(dir: &tree::Dir, options: &TreeOptions) -> String {
+    let mut details = Vec::new();
+    if options.counts {
+        let noun = if dir.count == 1 { "mem" } else { "mems" };
+        details.push(format!("{} {noun}", dir.count));
+    }
+    if let Some(updated_at) = dir.updated_at.filter(|_| options.updated) {
+        details.push(format!("updated {}", updated_at.format("%Y-%m-%d")));
+    }
+    if details.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", details.join(", "))
+    }
+}
+
+fn print_tree(dir: &tree::Dir, prefix: &str, options: &TreeOptions) {
+    let color = options.all && use_color();
+    let total = dir.mems.len() + dir.dirs.len();
+    let mut idx = 0;
+    let print_item = |mem: &tree::Entry, idx: usize| {
+        let connector = if idx == total {
+            "└── "
+        } else {
+            "├── "
+        };
+        let pin = if mem.pinned { " (pinned)" } else { "" };
+        let updated = if options.updated {
+            format!(" (updated {})", mem.updated_at.format("%Y-%m-%d"))
+        } else {
+            String::new()
+        };
+        let line = format!("{} - {}{pin}{updated}", mem.name, mem.title);
+        if !options.all || !mem.archived {
+            println!("{prefix}{connector}{line}");
+        } else if color {
+            println!(
+                "{prefix}{connector}{}{line} (archived){}",
+                highlight::ANSI_DIM,
+                highlight::ANSI_END
+            );
+        } else {
+            println!("{prefix}{connector}{line} (archived)");
+        }
+    };
+
+    // Print pinned mems first
+    for mem in dir.mems.iter().filter(|m| m.pinned) {
+        idx += 1;
+        print_item(mem, idx);
+    }
+
+    // Then subdirectories
+    for subdir in &dir.dirs {
+        idx += 1;
+        let is_last = idx == total;
+        let connector = if is_last { "└── " } else { "├── " };
+        let details = tree_details(subdir, options);
+        match &subdir.description {
+            Some(description) => {
+                println!(
+                    "{prefix}{connector}{}/ - {description}{details}",
+                    subdir.name
+                )
+            }
+            None => println!("{prefix}{connector}{}/{details}", subdir.name),
+        }
+
+        let new_prefix = if is_last {
+            format!("{prefix}    ")
+        } else {
+            format!("{prefix}│   ")
+        };
+        print_tree(subdir, &new_prefix, options);
+    }
+
+    // Then the remaining items
+    for mem in dir.mems.iter().filter(|m| !m.pinned) {
+        idx += 1;
+        print_item(mem, idx);
+    }
+}
+
+fn cmd_stale(
+    ctx: &Context,
+    days: u32,
+    kind: Option<&str>,
+    timestamps: TimestampSource,
+    by_owner: bool,
+    listing: &Listing,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+
+    let now = chrono::Utc::now();
+
+    let mut stale: Vec<(String, Mem)> = Vec::new();
+    for (label, storage) in &storages {
+        let config = Config::for_storage(storage)?;
+        let mut mems = storage.list_mems()?;
+        timestamps::apply(timestamps, storage, &mut mems)?;
+        for mem in mems {
+            if kind.is_some_and(|k| kind::of(&mem) != k) {
+                continue;
+            }
+            // Kinds such as logs and generated mems never go stale
+            let Some(threshold) = kind::stale_days(&config, &mem, days) else {
+                continue;
+            };
+            if now - mem.updated_at > chrono::Duration::days(i64::from(threshold)) {
+                stale.push((label.clone(), mem));
+            }
+        }
+    }
+
+    if by_owner {
+        // Owned groups by name, then unowned mems
+        let mut groups: BTreeMap<Option<String>, Vec<&(String, Mem)>> = BTreeMap::new();
+        for entry in &stale {
+            groups
+                .entry(entry.1.owner().map(str::to_string))
+                .or_default()
+                .push(entry);
+        }
+        let mut groups: Vec<_> = groups.into_iter().collect();
+        if groups.first().is_some_and(|(owner, _)| owner.is_none()) {
+            groups.rotate_left(1);
+        }
+
+        if let Listing::Template(_) = listing {
+            let json_output: Vec<MemRecord> = groups
+                .iter()
+                .flat_map(|(_, mems)| mems.iter().map(|(_, m)| MemRecord::from(m)))
+                .collect();
+            listing.print(&json_output)?;
+        } else if !listing.is_text() {
+            let json_output: Vec<OwnerGroupJson> = groups
+                .iter()
+                .map(|(owner, mems)| OwnerGroupJson {
+                    owner: owner.clone(),
+                    mems: mems.iter().map(|(_, m)| MemRecord::from(m)).collect(),
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&json_output)?);
+        } else if stale.is_empty() {
+            println!("No stale mems (threshold: {days} days)");
+        } else {
+            println!("Stale mems (not updated in {days}+ days):");
+            for (owner, mems) in &groups {
+                println!(
+                    "{} ({}):",
+                    owner.as_deref().unwrap_or("(unowned)"),
+                    mems.len()
+                );
+                for (label, mem) in mems {
+                    print_stale(label, mem, multi, now);
+                }
+            }
+        }
+    } else if !listing.is_text() {
+        let json_output: Vec<MemRecord> = stale.iter().map(|(_, m)| MemRecord::from(m)).collect();
+        listing.print(&json_output)?;
+    } else if stale.is_empty() {
+        println!("No stale mems (threshold: {days} days)");
+    } else {
+        println!("Stale mems (not updated in {days}+ days):");
+        for (label, mem) in &stale {
+            print_stale(label, mem, multi, now);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_stale(label: &str, mem: &Mem, multi: bool, now: chrono::DateTime<chrono::Utc>) {
+    let path_str = mem.path_str();
+    let days_old = (now - mem.updated_at).num_days();
+    let prefix = if multi {
+        format!("[{label}] ")
+    } else {
+        String::new()
+    };
+    println!("  {prefix}{path_str}: {} ({days_old} days)", mem.title);
+}
+
+fn cmd_assign(
+    ctx: &Context,
+    path: &str,
+    owner: &str,
+    json: bool,
+    force_unlock: bool,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &normalize_path(path)?;
+
+    // A mem, or every mem under a directory
+    let mut mems = if storage.exists(path) {
+        vec![storage.read_mem(path)?]
+    } else {
+        storage.list_mems_under(path)?
+    };
+    if mems.is_empty() {
+        storage.resolve(path)?;
+    }
+
+    // Refuse before writing anything if a mem to reassign is locked
+    for mem in mems.iter().filter(|m| m.owner() != Some(owner)) {
+        check_unlocked(mem, force_unlock)?;
+    }
+
+    // Ownership is metadata: leave updated-at alone so stale mems stay stale
+    let mut assigned = Vec::new();
+    for mem in &mut mems {
+        if mem.owner() != Some(owner) {
+            mem.set_field("owner", owner);
+            storage.write_mem(mem)?;
+            assigned.push(mem.path_str());
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&assigned)?);
+    } else {
+        for path in &assigned {
+            status!(ctx, "Assigned: {path} -> {owner}");
+        }
+        if assigned.is_empty() {
+            status!(ctx, "Already owned by {owner}");
+        }
+    }
+    Ok(())
+}
+
+fn cmd_meta(ctx: &Context, command: MetaCommand) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    match command {
+        MetaCommand::Get { path, field, json } => {
+            let mem = storage.read_mem(&storage.resolve(&path)?)?;
+            match field {
+                Some(field) => {
+                    let values = meta::values(&mem, &field)
+                        .ok_or_else(|| anyhow!("{path} has no field {field}"))?;
+                    if json {
+                        let value = match mem.extra.get(&field) {
+                            Some(value) => serde_json::to_value(value)?,
+                            None if field == "tags" => serde_json::to_value(&values)?,
+                            None => serde_json::to_value(values.join(", "))?,
+                        };
+                        println!("{}", serde_json::to_string_pretty(&value)?);
+                    } else {
+                        println!("{}", values.join(", "));
+                    }
+                }
+                None if json => {
+                    println!("{}", serde_json::to_string_pretty(&mem.extra)?);
+                }
+                None => {
+                    for (key, value) in &mem.extra {
+                        println!("{key}: {}", meta::display(value));
+                    }
+                }
+            }
+        }
+        MetaCommand::Set {
+            path,
+            field,
+            value,
+            force_unlock,
+        } => {
+            let path = storage.resolve(&path)?;
+            let mut mem = storage.read_mem(&path)?;
+            check_field_unlocked(&mem, &field, force_unlock)?;
+            meta::set(&mut mem, &field, meta::parse_value(&value))?;
+            storage.write_mem(&mem)?;
+            status!(ctx, "Set {field} on {path}");
+        }
+        MetaCommand::Unset {
+            path,
+            field,
+            force_unlock,
+        } => {
+            let path = storage.resolve(&path)?;
+            let mut mem = storage.read_mem(&path)?;
+            check_field_unlocked(&mem, &field, force_unlock)?;
+            if meta::unset(&mut mem, &field)? {
+                storage.write_mem(&mem)?;
+                status!(ctx, "Removed {field} from {path}");
+            } else {
+                status!(ctx, "{path} has no field {field}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_lock(ctx: &Context, path: &str, locked: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &storage.resolve(path)?;
+    let mut mem = storage.read_mem(path)?;
+    if mem.is_locked() == locked {
+        status!(
+            ctx,
+            "Already {}: {path}",
+            if locked { "locked" } else { "unlocked" }
+        );
+        return Ok(());
+    }
+
+    mem.set_locked(locked);
+    storage.write_mem(&mem)?;
+    status!(
+        ctx,
+        "{}: {path}",
+        if locked { "Locked" } else { "Unlocked" }
+    );
+    Ok(())
+}
+
+fn cmd_pin(ctx: &Context, path: &str, pinned: bool, force_unlock: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &storage.resolve(path)?;
+    let mut mem = storage.read_mem(path)?;
+    if mem.is_pinned() == pinned {
+        status!(
+            ctx,
+            "Already {}: {path}",
+            if pinned { "pinned" } else { "unpinned" }
+        );
+        return Ok(());
+    }
+
+    check_unlocked(&mem, force_unlock)?;
+
+    // Like ownership, pinning is metadata and leaves updated-at alone
+    mem.set_pinned(pinned);
+    storage.write_mem(&mem)?;
+    status!(
+        ctx,
+        "{}: {path}",
+        if pinned { "Pinned" } else { "Unpinned" }
+    );
+    Ok(())
+}
+
+fn cmd_digest(
+    ctx: &Context,
+    since: &str,
+    format: DigestFormat,
+    days: Option<u32>,
+    timestamps: TimestampSource,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+    let days = match days {
+        Some(days) => days,
+        None => Config::for_storage(&storages[0].1)?.notify.stale_days,
+    };
+
+    let now = chrono::Utc::now();
+    let start = dates::parse_since(since, now)
+        .ok_or_else(|| anyhow!("invalid --since: {since} (use e.g. 1w, 3d, 12h, or a date)"))?;
+    let mut digest = digest::Digest::new(start, now);
+    for (label, storage) in &storages {
+        let label = if multi { label.as_str() } else { "" };
+        let mut mems = storage.list_mems()?;
+        timestamps::apply(timestamps, storage, &mut mems)?;
+
+        // Mems archived before `archived-at` existed: when git added them to archive/
+        let archive_times = match storage.local_root() {
+            Ok(root) => root
+                .join("archive")
+                .canonicalize()
+                .ok()
+                .and_then(|dir| git::file_times(&dir).ok().map(|t| (dir, t))),
+            Err(_) => None,
+        };
+        let archived_at = |mem: &Mem| {
+            let (dir, times) = archive_times.as_ref()?;
+            let file = dir.join(format!("{}.md", mem.path_str()));
+            times.get(&file).map(|t| t.created)
+        };
+        let config = Config::for_storage(storage)?;
+        digest.evaluate(
+            label,
+            &mems,
+            &storage.archived_mems()?,
+            archived_at,
+            |mem: &Mem| kind::stale_days(&config, mem, days),
+        );
+    }
+
+    match format {
+        DigestFormat::Markdown => print!("{}", digest.to_markdown()),
+        DigestFormat::Html => print!("{}", digest.to_html()),
+        DigestFormat::Json => println!("{}", serde_json::to_string_pretty(&digest)?),
+    }
+    Ok(())
+}
+
+fn cmd_activity(
+    ctx: &Context,
+    path: Option<&str>,
+    heatmap: bool,
+    json: bool,
+    timestamps: TimestampSource,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let prefix = path.map(normalize_path).transpose()?;
+    let mut activity = activity::Activity::new(chrono::Utc::now().date_naive());
+    for (_, storage) in get_storages(ctx, dirs)? {
+        let mut mems = match &prefix {
+            Some(prefix) => storage.list_mems_under(prefix)?,
+            None => storage.list_mems()?,
+        };
+        timestamps::apply(timestamps, &storage, &mut mems)?;
+        for mem in &mems {
+            activity.add(mem);
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&activity)?);
+    } else if heatmap {
+        print!("{}", activity.render_heatmap());
+    } else {
+        print!("{}", activity.render_months());
+    }
+    Ok(())
+}
+
+fn cmd_decisions(
+    ctx: &Context,
+    path: Option<&str>,
+    since: Option<&str>,
+    markdown: bool,
+    json: bool,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let prefix = path.map(normalize_path).transpose()?;
+    let since = since
+        .map(|since| {
+            dates::parse_since(since, chrono::Utc::now()).ok_or_else(|| {
+                anyhow!("invalid --since: {since} (use e.g. 1w, 3d, 12h, or a date)")
+            })
+        })
+        .transpose()?
+        .map(|since| since.date_naive());
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+
+    let mut decisions = Vec::new();
+    for (label, storage) in &storages {
+        let label = if multi { label.as_str() } else { "" };
+        let mems = match &prefix {
+            Some(prefix) => storage.list_mems_under(prefix)?,
+            None => storage.list_mems()?,
+        };
+        decisions.extend(
+            mems.iter()
+                .filter_map(|mem| Decision::of(label, mem))
+                .filter(|d| since.is_none_or(|since| d.date >= since)),
+        );
+    }
+    decision::sort(&mut decisions);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&decisions)?);
+    } else if markdown {
+        print!("{}", decision::render_markdown(&decisions));
+    } else if decisions.is_empty() {
+        println!("No decisions found");
+    } else {
+        print!("{}", decision::render_text(&decisions));
+    }
+    Ok(())
+}
+
+fn cmd_review(
+    ctx: &Context,
+    command: Option<ReviewCommand>,
+    count: Option<usize>,
+    json: bool,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let root = storage.local_root()?;
+    let mut history = review::History::load(root)?;
+    let now = chrono::Utc::now();
+
+    if let Some(ReviewCommand::Done { paths }) = command {
+        for path in &paths {
+            let mem = storage.read_mem(&storage.resolve(path)?)?;
+            history.mark_reviewed(&mem, now);
+            status!(ctx, "Reviewed: {}", mem.path_str());
+        }
+        return history.save(root);
+    }
+
+    let mut config = Config::for_storage(&storage)?.review;
+    if let Some(count) = count {
+        config.per_day = count;
+    }
+    let picks = history.pick(&storage.list_mems()?, now, &config);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&picks)?);
+    } else if picks.is_empty() {
+        println!("Nothing to review today");
+    } else {
+        println!("To review today:");
+        for pick in &picks {
+            let seen = match pick.last_reviewed {
+                Some(_) => format!("{} days since last seen", pick.days_unseen),
+                None => format!("{} days since update, never reviewed", pick.days_unseen),
+            };
+            println!("  {}: {} ({seen})", pick.path, pick.title);
+        }
+        status!(ctx, "\nMark as reviewed with: mem review done <path>...");
+    }
+    Ok(())
+}
+
+fn cmd_notify(
+    ctx: &Context,
+    format: NotifyFormat,
+    days: Option<u32>,
+    webhook_url: Option<String>,
+    timestamps: TimestampSource,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+    let config = Config::for_storage(&storages[0].1)?;
+    let days = days.unwrap_or(config.notify.stale_days);
+
+    let now = chrono::Utc::now();
+    let mut report = notify::Report::default();
+    for (label, storage) in &storages {
+        let label = if multi { label.as_str() } else { "" };
+        let mut mems = storage.list_mems()?;
+        timestamps::apply(timestamps, storage, &mut mems)?;
+        let config = Config::for_storage(storage)?;
+        report.evaluate(label, &mems, now, |mem: &Mem| {
+            kind::stale_days(&config, mem, days)
+        });
+    }
+
+    match format {
+        // Silent when there is nothing to do, so cron only mails on findings
+        NotifyFormat::Plain => print!("{}", report.to_text()),
+        NotifyFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        NotifyFormat::Webhook => {
+            let url = webhook_url.or(config.notify.webhook_url).ok_or_else(|| {
+                anyhow!("no webhook URL (use --webhook-url or set notify.webhook-url in config)")
+            })?;
+            if report.is_empty() {
+                status!(ctx, "Nothing to notify");
+            } else {
+                notify::post_webhook(&url, &report.to_text())?;
+                status!(
+                    ctx,
+                    "Sent notification ({} due, {} stale)",
+                    report.due.len(),
+                    report.stale.len()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_lint(
+    ctx: &Context,
+    format: LintFormat,
+    threshold: Severity,
+    changed_since: Option<&str>,
+    fix: bool,
+    check_external: bool,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+
+    let mut issues = Vec::new();
+    let mut total_mems = 0;
+    let progress = ctx.progress();
+
+    for (label, storage) in &storages {
+        let changed = match changed_since {
+            Some(since) => Some(lint::changed_mems(storage, since)?),
+            None => None,
+        };
+        if fix {
+            let prefix = if multi {
+                format!("[{label}] ")
+            } else {
+                String::new()
+            };
+            let terms = lint::fix_terms(storage, changed.as_ref())?;
+            let timestamps = lint::fix_timestamps(storage, changed.as_ref())?;
+            let fixed = terms
+                .into_iter()
+                .map(|(path, count)| (path, count, "terms"))
+                .chain(
+                    timestamps
+                        .into_iter()
+                        .map(|(path, count)| (path, count, "timestamps")),
+                );
+            for (path, count, what) in fixed {
+                // stderr, so --format github/sarif output stays parseable
+                if !ctx.quiet {
+                    eprintln!("Fixed {count} {what} in {prefix}{path}");
+                }
+            }
+        }
+        let (checked, found) = match &changed {
+            Some(changed) => lint::lint_changed(label, storage, changed, progress.as_ref())?,
+            None => lint::lint_storage(label, storage, progress.as_ref())?,
+        };
+        total_mems += checked;
+        issues.extend(found);
+        if check_external {
+            issues.extend(lint::lint_external(label, storage, changed.as_ref())?);
+        }
+        // Stores in play link to each other as `store:path`
+        if multi {
+            issues.extend(lint::lint_store_links(
+                label,
+                storage,
+                &storages,
+                changed.as_ref(),
+            )?);
+        }
+    }
+
+    let failing = issues.iter().filter(|i| i.severity >= threshold).count();
+    let cwd = std::env::current_dir()?;
+
+    match format {
+        LintFormat::Text => {
+            if issues.is_empty() {
+                println!("No issues found ({total_mems} mems checked)");
+            } else {
+                println!("Found {} issues:", issues.len());
+                for issue in &issues {
+                    let prefix = if multi {
+                        format!("[{}] ", issue.store)
+                    } else {
+                        String::new()
+                    };
+                    println!(
+                        "  {prefix}{}:{}: {}: {}",
+                        issue.path,
+                        issue.line,
+                        issue.severity.as_str(),
+                        issue.message
+                    );
+                }
+            }
+        }
+        LintFormat::Github => print!("{}", lint::to_github(&issues, &cwd)),
+        LintFormat::Sarif => println!(
+            "{}",
+            serde_json::to_string_pretty(&lint::to_sarif(&issues, &cwd))?
+        ),
+    }
+
+    if failing > 0 {
+        Err(MemError::CheckFailed {
+            check: "lint",
+            issues: failing,
+        }
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+/// JSON representation of a task item.
+#[derive(Serialize)]
+struct TaskJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    store: Option<String>,
+    path: String,
+    title: String,
+    /// Line in the mem file
+    line: usize,
+    done: bool,
+    text: String,
+}
+
+fn cmd_tasks(
+    ctx: &Context,
+    command: Option<TasksCommand>,
+    path: Option<&str>,
+    all: bool,
+    json: bool,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    if let Some(TasksCommand::Done { path, line }) = command {
+        let storage = find_storage(ctx)?;
+        let path = storage.resolve(&path)?;
+        let offset = content_line_offset(&storage.read_source(&path)?);
+        let mut mem = storage.read_mem(&path)?;
+        check_unlocked(&mem, false)?;
+        let task = tasks::extract(&mem.content)
+            .into_iter()
+            .find(|t| t.line + offset == line)
+            .ok_or_else(|| anyhow!("no task on line {line} of {path}"))?;
+        if task.done {
+            status!(ctx, "Already done: {}", task.text);
+            return Ok(());
+        }
+        mem.content = tasks::set_done(&mem.content, task.line, true)?;
+        mem.touch();
+        storage.write_mem(&mem)?;
+        status!(ctx, "Done: {}", task.text);
+        return Ok(());
+    }
+
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+
+    let path = path.map(normalize_path).transpose()?;
+    let mut found = Vec::new();
+    for (label, storage) in &storages {
+        let mems = match &path {
+            Some(path) if storage.exists(path) => vec![storage.read_mem(path)?],
+            Some(path) => storage.list_mems_under(path)?,
+            None => storage.list_mems()?,
+        };
+        for mem in mems {
+            let mem_path = mem.path_str();
+            let offset = content_line_offset(&storage.read_source(&mem_path)?);
+            for task in tasks::extract(&mem.content) {
+                if all || !task.done {
+                    found.push(TaskJson {
+                        store: multi.then(|| label.clone()),
+                        path: mem_path.clone(),
+                        title: mem.title.clone(),
+                        line: task.line + offset,
+                        done: task.done,
+                        text: task.text,
+                    });
+                }
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&found)?);
+    } else if found.is_empty() {
+        println!("No open tasks");
+    } else {
+        for task in &found {
+            let prefix = task
+                .store
+                .as_ref()
+                .map(|label| format!("[{label}] "))
+                .unwrap_or_default();
+            let mark = if task.done { "x" } else { " " };
+            println!(
+                "{prefix}{}:{}: [{mark}] {}",
+                task.path, task.line, task.text
+            );
+        }
+    }
+    Ok(())
+}
+
+/// JSON representation of an upcoming date.
+#[derive(Serialize)]
+struct UpcomingJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    store: Option<String>,
+    path: String,
+    title: String,
+    days: i64,
+    #[serde(flatten)]
+    event: upcoming::Event,
+}
+
+fn cmd_upcoming(ctx: &Context, days: u32, json: bool, dirs: &[PathBuf]) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+    let today = chrono::Utc::now().date_naive();
+
+    let mut found = Vec::new();
+    for (label, storage) in &storages {
+        for mem in storage.list_mems()? {
+            let path = mem.path_str();
+            let mut offset = None;
+            for mut event in upcoming::events(&mem) {
+                let away = (event.date - today).num_days();
+                if !(0..=i64::from(days)).contains(&away) {
+                    continue;
+                }
+                if event.label.is_empty() {
+                    event.label = mem.title.clone();
+                }
+                // Report lines in the file, as lint does
+                if let Some(line) = event.line.as_mut() {
+                    if offset.is_none() {
+                        offset = Some(content_line_offset(&storage.read_source(&path)?));
+                    }
+                    *line += offset.unwrap_or_default();
+                }
+                found.push(UpcomingJson {
+                    store: multi.then(|| label.clone()),
+                    path: path.clone(),
+                    title: mem.title.clone(),
+                    days: away,
+                    event,
+                });
+            }
+        }
+    }
+    found.sort_by(|a, b| (a.event.date, &a.path).cmp(&(b.event.date, &b.path)));
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&found)?);
+    } else if found.is_empty() {
+        println!("Nothing coming up in the next {days} days");
+    } else {
+        println!("Coming up in the next {days} days:");
+        for entry in &found {
+            let prefix = entry
+                .store
+                .as_ref()
+                .map(|label| format!("[{label}] "))
+                .unwrap_or_default();
+            let when = match entry.days {
+                0 => "today".to_string(),
+                1 => "tomorrow".to_string(),
+                d => format!("in {d} days"),
+            };
+            println!(
+                "  {} ({when})  {prefix}{}: {}",
+                entry.event.date, entry.path, entry.event.label
+            );
+        }
+    }
+    Ok(())
+}
+
+/// JSON representation of a `verify` finding.
+#[derive(Serialize)]
+struct VerifyIssueJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    store: Option<String>,
+    #[serde(flatten)]
+    finding: integrity::Finding,
+    blessed: bool,
+}
+
+fn cmd_verify(ctx: &Context, update: bool, json: bool, dirs: &[PathBuf]) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+
+    let mut issues = Vec::new();
+    let mut checked = 0;
+    let mut unprotected = 0;
+    for (label, storage) in &storages {
+        let report = integrity::verify(storage)?;
+        checked += report.checked;
+        unprotected += report.unprotected;
+        for finding in report.findings {
+            let blessed = update && finding.problem.blessable();
+            if blessed {
+                integrity::bless(storage, &finding.path)?;
+            }
+            issues.push(VerifyIssueJson {
+                store: multi.then(|| label.clone()),
+                finding,
+                blessed,
+            });
+        }
+    }
+
+    let failing = issues.iter().filter(|i| !i.blessed).count();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&issues)?);
+    } else if issues.is_empty() {
+        println!("All {checked} mems verified ({unprotected} without checksum)");
+    } else {
+        println!("Found {} mems that fail verification:", issues.len());
+        for issue in &issues {
+            let prefix = match &issue.store {
+                Some(store) => format!("[{store}] "),
+                None => String::new(),
+            };
+            let suffix = if issue.blessed { " (updated)" } else { "" };
+            println!(
+                "  {prefix}{}: {}{suffix}",
+                issue.finding.path,
+                issue.finding.problem.as_str()
+            );
+        }
+        let blessed = issues.len() - failing;
+        if blessed > 0 {
+            status!(ctx, "Updated {blessed} mems");
+        }
+    }
+
+    if failing > 0 {
+        Err(MemError::CheckFailed {
+            check: "verify",
+            issues: failing,
+        }
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+/// How `dump` writes its output.
+struct DumpOptions {
+    out: Option<PathBuf>,
+    gzip: bool,
+    order: dump::Order,
+    manifest: bool,
+    diff: Option<PathBuf>,
+    with_header: bool,
+}
+
+fn cmd_dump(
+    ctx: &Context,
+    path: Option<&str>,
+    options: DumpOptions,
+    dirs: &[PathBuf],
+) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let old = options.diff.as_deref().map(read_manifest).transpose()?;
+    let sink: Box<dyn Write> = match &options.out {
+        Some(file) => Box::new(
+            std::fs::File::create(file)
+                .with_context(|| format!("failed to create {}", file.display()))?,
+        ),
+        None if options.gzip && io::stdout().is_terminal() => {
+            return Err(anyhow!(
+                "refusing to write gzip to a terminal; use --out or redirect stdout"
+            ))
+        }
+        None => Box::new(io::stdout().lock()),
+    };
+    let out = io::BufWriter::new(sink);
+
+    let out = if options.gzip {
+        let mut encoder = GzEncoder::new(out, Compression::default());
+        write_dump(&mut encoder, &storages, path, &options, old.as_ref())?;
+        encoder.finish()?
+    } else {
+        let mut out = out;
+        write_dump(&mut out, &storages, path, &options, old.as_ref())?;
+        out
+    };
+    out.into_inner().map_err(|e| e.into_error())?.flush()?;
+    Ok(())
+}
+
+/// Read a manifest for `dump --diff`: bare JSON, or a dump made with
+/// `--manifest`, gzipped or not.
+fn read_manifest(file: &Path) -> Result<Manifest> {
+    let data = std::fs::read(file).with_context(|| format!("failed to read {}", file.display()))?;
+    let text = if data.starts_with(&[0x1f, 0x8b]) {
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(&data[..]).read_to_string(&mut text)?;
+        text
+    } else {
+        String::from_utf8(data).map_err(|_| anyhow!("{} is not a manifest", file.display()))?
+    };
+    Manifest::parse(&text).with_context(|| format!("failed to read {}", file.display()))
+}
+
+/// Write the mems under `path` in each store to `out`, one at a time. With
+/// a manifest or header, a first pass hashes or counts every mem so they can
+/// lead; with `old`, only mems changed since it are written.
+fn write_dump(
+    out: &mut dyn Write,
+    storages: &[(String, Storage)],
+    path: Option<&str>,
+    options: &DumpOptions,
+    old: Option<&Manifest>,
+) -> Result<()> {
+    let store = |label: &str| (storages.len() > 1).then(|| label.to_string());
+    let mut wanted: Option<BTreeSet<(Option<String>, String)>> = None;
+
+    // Frontmatter and estimated tokens of each mem, by store, for the header
+    let mut dumped: BTreeMap<String, Vec<(Mem, usize)>> = BTreeMap::new();
+    let mut entries = Vec::new();
+    if options.manifest || options.with_header {
+        for_each_section(storages, path, options.order, |label, mem, section| {
+            if options.with_header {
+                let mut header = mem.clone();
+                header.content.clear();
+                dumped
+                    .entry(label.to_string())
+                    .or_default()
+                    .push((header, mem.estimated_tokens()));
+            }
+            entries.push(manifest::Entry::new(
+                store(label).as_deref(),
+                &mem.path_str(),
+                &section,
+                mem.updated_at,
+            ));
+            Ok(())
+        })?;
+    }
+
+    if options.manifest {
+        let mut manifest = Manifest::new(entries);
+        if let Some(old) = old {
+            let (changed, removed) = manifest.changed_since(old);
+            wanted = Some(
+                changed
+                    .iter()
+                    .map(|e| (e.store.clone(), e.path.clone()))
+                    .collect(),
+            );
+            let removed = removed.into_iter().cloned().collect();
+            manifest.removed = removed;
+        }
+        out.write_all(manifest.header()?.as_bytes())?;
+    }
+
+    if options.with_header {
+        if let Some(wanted) = &wanted {
+            for (label, mems) in &mut dumped {
+                mems.retain(|(mem, _)| wanted.contains(&(store(label), mem.path_str())));
+            }
+        }
+        out.write_all(dump_header(storages, dumped)?.as_bytes())?;
+    }
+
+    let mut current: Option<String> = None;
+    for_each_section(storages, path, options.order, |label, mem, section| {
+        if let Some(wanted) = &wanted {
+            if !wanted.contains(&(store(label), mem.path_str())) {
+                return Ok(());
+            }
+        }
+
+        // Multi-dir header
+        if storages.len() > 1 && current.as_deref() != Some(label) {
+            if current.is_some() {
+                writeln!(out)?;
+            }
+            writeln!(out, "<!-- ═══ {label} ═══ -->")?;
+            writeln!(out)?;
+        }
+        current = Some(label.to_string());
+
+        out.write_all(section.as_bytes())?;
+        Ok(())
+    })
+}
+
+/// The `--with-header` preamble for the `dumped` mems of each store, by
+/// label.
+fn dump_header(
+    storages: &[(String, Storage)],
+    mut dumped: BTreeMap<String, Vec<(Mem, usize)>>,
+) -> Result<String> {
+    let mut stores = Vec::new();
+    for (label, storage) in storages {
+        let mems = dumped.remove(label).unwrap_or_default();
+        let info = StoreInfo::for_storage(storage)?.unwrap_or_default();
+        // Several stores are labeled already; one is named by its
+        // store.yaml or the directory holding it
+        let name = if !label.is_empty() {
+            label.clone()
+        } else if let Some(name) = info.name {
+            name
+        } else {
+            let root = storage.root();
+            discover::names(label, &root.canonicalize().unwrap_or(root.to_path_buf()))
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| ".mems".to_string())
+        };
+        let tokens = mems.iter().map(|(_, tokens)| tokens).sum();
+        let mems: Vec<(Mem, bool)> = mems.into_iter().map(|(mem, _)| (mem, false)).collect();
+        stores.push(preamble::StoreHeader {
+            outline: tree::build(storage, &mems, &name)?.outline(),
+            name,
+            description: info.description,
+            mems: mems.len(),
+            tokens,
+        });
+    }
+    let config = match storages.first() {
+        Some((_, storage)) => Config::for_storage(storage)?,
+        None => Config::default(),
+    };
+    preamble::Header::new(stores, chrono::Utc::now()).render(config.dump.header.as_deref())
+}
+
+/// Call `each` with the label of the store, the mem, and its section of
+/// the dump, for every mem under `path` in dump order.
+fn for_each_section(
+    storages: &[(String, Storage)],
+    path: Option<&str>,
+    order: dump::Order,
+    mut each: impl FnMut(&str, &Mem, String) -> Result<()>,
+) -> Result<()> {
+    for (label, storage) in storages {
+        dump::for_each_section(storage, path.unwrap_or(""), order, |mem, section| {
+            each(label, mem, section)
+        })?;
+    }
+    Ok(())
+}
+
+fn cmd_info(ctx: &Context, json: bool, dirs: &[PathBuf]) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let mut infos = Vec::new();
+    for (label, storage) in &storages {
+        let file = StoreInfo::for_storage(storage)?;
+        let info = file.clone().unwrap_or_default();
+        let name = info
+            .name
+            .clone()
+            .or_else(|| (!label.is_empty()).then(|| label.clone()));
+        infos.push(InfoJson {
+            name,
+            description: info.description.clone(),
+            location: storage.root().display().to_string(),
+            owners: info.owners.clone(),
+            schema_version: file.map(|f| f.schema_version),
+            supported: !info.is_newer(),
+            policies: info.policies,
+            mems: storage.iter_mems_under("")?.count(),
+            archived: storage.archived_mems_under("")?.len(),
+        });
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&infos)?);
+        return Ok(());
+    }
+
+    for (i, info) in infos.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "Name:        {}",
+            info.name.as_deref().unwrap_or("(unnamed)")
+        );
+        if let Some(description) = &info.description {
+            println!("Description: {description}");
+        }
+        if !info.owners.is_empty() {
+            println!("Owners:      {}", info.owners.join(", "));
+        }
+        println!("Location:    {}", info.location);
+        match info.schema_version {
+            Some(version) if !info.supported => println!(
+                "Format:      version {version} (newer than this mem supports, {STORE_VERSION})"
+            ),
+            Some(version) => println!("Format:      version {version}"),
+            None => println!("Format:      unversioned (no {STORE_FILE})"),
+        }
+        println!("Mems:        {} ({} archived)", info.mems, info.archived);
+        if !info.policies.is_empty() {
+            println!("Policies:");
+            for (name, policy) in &info.policies {
+                println!("  {name}: {policy}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_stats(ctx: &Context, by_size: bool, json: bool, dirs: &[PathBuf]) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+
+    let mut all_mems: Vec<(String, Mem)> = Vec::new();
+    for (label, storage) in &storages {
+        for mem in storage.list_mems()? {
+            all_mems.push((label.clone(), mem));
+        }
+    }
+
+    let size: usize = all_mems.iter().map(|(_, m)| m.size()).sum();
+    let words: usize = all_mems.iter().map(|(_, m)| m.word_count()).sum();
+    let tokens: usize = all_mems.iter().map(|(_, m)| m.estimated_tokens()).sum();
+
+    // Largest first, ties broken by path
+    all_mems.sort_by(|(_, a), (_, b)| b.size().cmp(&a.size()).then(a.path.cmp(&b.path)));
+
+    if json {
+        let json_output = StatsJson {
+            mems: all_mems.len(),
+            size,
+            words,
+            tokens,
+            by_size: by_size.then(|| all_mems.iter().map(|(_, m)| SizeJson::from(m)).collect()),
+        };
+        println!("{}", serde_json::to_string_pretty(&json_output)?);
+        return Ok(());
+    }
+
+    println!("Mems:    {}", all_mems.len());
+    println!("Size:    {size} bytes");
+    println!("Words:   {words}");
+    println!("Tokens:  ~{tokens}");
+
+    if by_size && !all_mems.is_empty() {
+        println!();
+        println!("{:>8} {:>7} {:>7}  PATH", "SIZE", "WORDS", "TOKENS");
+        for (label, mem) in &all_mems {
+            let prefix = if multi {
+                format!("[{label}] ")
+            } else {
+                String::new()
+            };
+            println!(
+                "{:>8} {:>7} {:>7}  {prefix}{}",
+                mem.size(),
+                mem.word_count(),
+                mem.estimated_tokens(),
+                mem.path_str()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_tags(ctx: &Context, json: bool, dirs: &[PathBuf]) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for (_, storage) in &storages {
+        for (tag, count) in storage.tag_counts()? {
+            *counts.entry(tag).or_default() += count;
+        }
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    if json {
+        let json_output: Vec<TagJson> = counts
+            .into_iter()
+            .map(|(tag, count)| TagJson { tag, count })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json_output)?);
+    } else if counts.is_empty() {
+        println!("No tags found");
+    } else {
+        for (tag, count) in &counts {
+            println!("{count:>5}  {tag}");
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_unknown_tags(ctx: &Context, json: bool, dirs: &[PathBuf]) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
+
+    let mut unknown = Vec::new();
+    for (label, storage) in &storages {
+        let config = Config::for_storage(storage)?;
+        for mem in storage.list_mems()? {
+            let path = mem.path_str();
+            let schema = schema::for_path(storage, &config, &path)?;
+            for violation in schema.check(&mem) {
+                if let Violation::UnknownTag(tag) = violation {
+                    let suggestion =
+                        fuzzy::near_tag(&tag, schema.allowed_tags.iter().map(String::as_str))
+                            .map(str::to_string);
+                    unknown.push(UnknownTagJson {
+                        store: multi.then(|| label.clone()),
+                        path: path.clone(),
+                        tag,
+                        suggestion,
+                    });
+                }
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&unknown)?);
+    } else if unknown.is_empty() {
+        println!("No unknown tags");
+    } else {
+        for entry in &unknown {
+            let prefix = entry
+                .store
+                .as_ref()
+                .map(|s| format!("[{s}] "))
+                .unwrap_or_default();
+            let hint = entry
+                .suggestion
+                .as_ref()
+                .map(|s| format!(" (did you mean {s}?)"))
+                .unwrap_or_default();
+            println!("{prefix}{}: {}{hint}", entry.path, entry.tag);
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_log_entry(ctx: &Context, path: &str, message: Option<String>) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &normalize_path(path)?;
+    let message = match message {
+        Some(message) => message,
+        None => read_stdin()?,
+    };
+    if message.trim().is_empty() {
+        return Err(anyhow!(
+            "no entry text provided (pass it as an argument or via stdin)"
+        ));
+    }
+    append_log_entry(&storage, path, &message)?;
+    status!(ctx, "Logged to {path}");
+    Ok(())
+}
+
+/// Append an entry written now to the log mem at `path`, creating the log if
+/// it doesn't exist.
+fn append_log_entry(storage: &Storage, path: &str, message: &str) -> Result<()> {
+    let entry = log::format_entry(chrono::Utc::now(), message);
+    let mem = if storage.exists(path) {
+        let mut mem = storage.read_mem(path)?;
+        check_unlocked(&mem, false)?;
+        if mem.kind() != Some(log::LOG_KIND) {
+            return Err(anyhow!(
+                "{path} is not a log mem (mark it with `mem meta set {path} kind log`)"
+            ));
+        }
+        log::append(&mut mem.content, &entry);
+        mem.touch();
+        mem
+    } else {
+        let title = path
+            .rsplit('/')
+            .next()
+            .unwrap_or(path)
+            .replace(['-', '_'], " ");
+        let mut mem = Mem::new(PathBuf::from(path), title, entry);
+        mem.set_field("kind", log::LOG_KIND);
+        directory::apply_defaults(&mut mem, &directory::effective(storage, path)?);
+        mem
+    };
+    storage.write_mem(&mem)
+}
+
+fn cmd_run(ctx: &Context, path: &str, exec: bool, log_path: Option<&str>) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = storage.resolve(path)?;
+    let mem = storage.read_mem(&path)?;
+    if kind::of(&mem) != runbook::RUNBOOK_KIND {
+        return Err(anyhow!(
+            "{path} is not a runbook (mark it with `mem meta set {path} kind runbook`)"
+        ));
+    }
+    let steps = runbook::steps(&mem.content);
+    if steps.is_empty() {
+        return Err(anyhow!(
+            "no steps in {path} (write them as a numbered list or fenced code blocks)"
+        ));
+    }
+    let log_path = match log_path {
+        Some(p) => normalize_path(p)?,
+        None => format!("{}/{path}", runbook::RUNS_DIR),
+    };
+    // Fail before the run rather than lose its record
+    if storage.exists(&log_path) && storage.read_mem(&log_path)?.kind() != Some(log::LOG_KIND) {
+        return Err(anyhow!("{log_path} is not a log mem"));
+    }
+
+    let offset = content_line_offset(&storage.read_source(&path)?);
+    let outcomes = runbook::walk(
+        &steps,
+        &mut io::stdin().lock(),
+        &mut io::stdout(),
+        exec,
+        offset,
+    )?;
+
+    append_log_entry(
+        &storage,
+        &log_path,
+        &runbook::summary(&path, &steps, &outcomes),
+    )?;
+    status!(
+        ctx,
+        "\nWent through {} of {} steps; recorded the run in {log_path}",
+        outcomes.len(),
+        steps.len()
+    );
+    Ok(())
+}
+
+fn cmd_capture(
+    ctx: &Context,
+    path: &str,
+    command: &[String],
+    title: Option<String>,
+    tags: Option<String>,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &normalize_path(path)?;
+    let existing = if storage.exists(path) {
+        let mem = storage.read_mem(path)?;
+        check_unlocked(&mem, false)?;
+        Some(mem)
+    } else {
+        None
+    };
+
+    let capture = capture::Capture::run(command)?;
+    print!("{}", capture.stdout);
+    eprint!("{}", capture.stderr);
+    let section = capture.to_markdown();
+
+    let mem = match existing {
+        Some(mut mem) => {
+            if !mem.content.is_empty() && !mem.content.ends_with("\n\n") {
+                mem.content.push_str(if mem.content.ends_with('\n') {
+                    "\n"
+                } else {
+                    "\n\n"
+                });
+            }
+            mem.content.push_str(&section);
+            mem.touch();
+            mem
+        }
+        None => {
+            let title = title.unwrap_or_else(|| {
+                path.rsplit('/')
+                    .next()
+                    .unwrap_or(path)
+                    .replace(['-', '_'], " ")
+            });
+            let tags = tags.as_deref().map(text::parse_tags).unwrap_or_default();
+            let mut mem = Mem::new(PathBuf::from(path), title, section).with_tags(tags);
+            directory::apply_defaults(&mut mem, &directory::effective(&storage, path)?);
+            mem
+        }
+    };
+    storage.write_mem(&mem)?;
+
+    let code = capture.code.map_or("killed by a signal".to_string(), |c| {
+        format!("exit code {c}")
+    });
+    if !ctx.quiet {
+        eprintln!("Captured `{}` ({code}) in {path}", capture.command_line());
+    }
+    Ok(())
+}
+
+fn cmd_capture_email(
+    ctx: &Context,
+    path: Option<String>,
+    title: Option<String>,
+    tags: Option<String>,
+    force: bool,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let mut raw = Vec::new();
+    io::stdin()
+        .read_to_end(&mut raw)
+        .context("failed to read email from stdin")?;
+    let email = email::parse(&raw)?;
+
+    let path = normalize_path(&path.unwrap_or_else(|| email.default_path()))?;
+    if storage.exists(&path) && !force {
+        return Err(anyhow!(
+            "mem already exists: {path} (use --force to overwrite)"
+        ));
+    }
+
+    let mut mem = email.to_mem(&path);
+    if let Some(title) = title {
+        mem.title = title;
+    }
+    mem.tags = tags.as_deref().map(text::parse_tags).unwrap_or_default();
+    let mut stored = 0;
+    for a in &email.attachments {
+        let (_, written) = attachment::store(&storage, &a.name, &a.data)?;
+        stored += usize::from(written);
+    }
+    storage.write_mem(&mem)?;
+
+    match (email.attachments.len(), stored) {
+        (0, _) => status!(ctx, "Captured: {path}"),
+        (n, stored) => {
+            let noun = if n == 1 { "attachment" } else { "attachments" };
+            match n - stored {
+                0 => status!(ctx, "Captured: {path} ({n} {noun})"),
+                old => status!(ctx, "Captured: {path} ({n} {noun}, {old} already stored)"),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_generate(ctx: &Context, command: GenerateCommand) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let (generated, prune_dir) = match command {
+        GenerateCommand::FromCargo { manifest_path } => (
+            generate::from_cargo(manifest_path.as_deref())?,
+            generate::CRATES_DIR,
+        ),
+    };
+
+    let outcomes = generate::apply(&storage, &generated, prune_dir)?;
+    let mut unchanged = 0;
+    for (path, outcome) in &outcomes {
+        match outcome {
+            generate::Outcome::Unchanged => unchanged += 1,
+            outcome => status!(ctx, "{}: {path}", outcome.as_str()),
+        }
+    }
+    if unchanged > 0 {
+        status!(ctx, "{unchanged} mems unchanged");
+    }
+    Ok(())
+}
+
+fn cmd_diff(
+    ctx: &Context,
+    old: &str,
+    new: Option<&str>,
+    against: Option<&Path>,
+    words: bool,
+    context: usize,
+    json: bool,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let old = storage.resolve(old)?;
+    let old_content = storage.read_mem(&old)?.content;
+
+    let (new, new_content) = match (new, against) {
+        (Some(new), _) => {
+            let new = storage.resolve(new)?;
+            let content = storage.read_mem(&new)?.content;
+            (new, content)
+        }
+        (None, Some(file)) => (file.display().to_string(), read_content(Some(file))?),
+        (None, None) => unreachable!("clap requires a second mem or --against"),
+    };
+
+    print_diff(
+        ctx,
+        (&old, &old_content),
+        (&new, &new_content),
+        words,
+        context,
+        json,
+    )
+}
+
+/// Read all of stdin as mem text, refusing binary or non-UTF-8 input.
+fn read_stdin() -> Result<String> {
+    let mut data = Vec::new();
+    io::stdin().read_to_end(&mut data)?;
+    Ok(text::decode(data, "stdin")?)
+}
+
+/// Read replacement content from a file, or stdin for `-` or no file. A
+/// whole mem file counts as its content.
+fn read_content(file: Option<&Path>) -> Result<String> {
+    let text = match file {
+        Some(file) if file != Path::new("-") => {
+            let data = std::fs::read(file)
+                .with_context(|| format!("failed to read {}", file.display()))?;
+            text::decode(data, &file.display().to_string())?
+        }
+        _ => read_stdin()?,
+    };
+    Ok(match Mem::parse(PathBuf::new(), &text) {
+        Ok(mem) => mem.content,
+        Err(_) => text,
+    })
+}
+
+/// Print a line (or word) diff between two texts, each given with its label.
+fn print_diff(
+    ctx: &Context,
+    (old_label, old): (&str, &str),
+    (new_label, new): (&str, &str),
+    words: bool,
+    context: usize,
+    json: bool,
+) -> Result<()> {
+    if words {
+        let changes = diff::words(old, new);
+        if json {
+            println!("{}", serde_json::to_string_pretty(&changes)?);
+        } else if old == new {
+            status!(ctx, "No differences");
+        } else {
+            let text = diff::render_words(&changes, use_color());
+            print!("{text}");
+            if !text.ends_with('\n') {
+                println!();
+            }
+        }
+        return Ok(());
+    }
+
+    let hunks = diff::hunks(old, new, context);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&hunks)?);
+    } else if hunks.is_empty() {
+        status!(ctx, "No differences");
+    } else {
+        print!(
+            "{}",
+            diff::unified(&hunks, old_label, new_label, use_color())
+        );
+    }
+    Ok(())
+}
+
+fn cmd_propose(
+    ctx: &Context,
+    path: &str,
+    file: Option<&Path>,
+    message: Option<String>,
+    author: Option<String>,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let root = storage.local_root()?;
+    let mem = storage.read_mem(&storage.resolve(path)?)?;
+
+    let content = read_content(file)?;
+    if content == mem.content {
+        return Err(anyhow!("proposed content matches {}", mem.path_str()));
+    }
+    let mut proposal = proposal::Proposal::new(&mem, content);
+    proposal.message = message;
+    proposal.author = author;
+    proposal.save(root)?;
+    status!(ctx, "Proposed {} for {}", proposal.id, proposal.path);
+    status!(ctx, "Review with: mem proposals diff {}", proposal.id);
+    Ok(())
+}
+
+/// JSON representation of a pending proposal, without its content.
+#[derive(Serialize)]
+struct ProposalJson {
+    id: String,
+    path: String,
+    created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    outdated: bool,
+}
+
+fn cmd_proposals(ctx: &Context, command: ProposalsCommand) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let root = storage.local_root()?;
+    // Whether the mem changed (or went away) since the proposal was made
+    let outdated = |proposal: &proposal::Proposal| {
+        storage
+            .read_mem(&proposal.path)
+            .map_or(true, |mem| proposal.is_outdated(&mem))
+    };
+
+    match command {
+        ProposalsCommand::Ls { json } => {
+            let proposals = proposal::list(root)?;
+            if json {
+                let output: Vec<ProposalJson> = proposals
+                    .iter()
+                    .map(|p| ProposalJson {
+                        id: p.id.clone(),
+                        path: p.path.clone(),
+                        created_at: p.created_at.to_rfc3339(),
+                        author: p.author.clone(),
+                        message: p.message.clone(),
+                        outdated: outdated(p),
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else if proposals.is_empty() {
+                println!("No pending proposals");
+            } else {
+                for p in &proposals {
+                    let by = p
+                        .author
+                        .as_ref()
+                        .map(|a| format!(" by {a}"))
+                        .unwrap_or_default();
+                    let message = p
+                        .message
+                        .as_ref()
+                        .map(|m| format!(": {m}"))
+                        .unwrap_or_default();
+                    let note = if outdated(p) { " (outdated)" } else { "" };
+                    println!(
+                        "{}  {}  {}{by}{message}{note}",
+                        p.id,
+                        p.created_at.format("%Y-%m-%d %H:%M"),
+                        p.path
+                    );
+                }
+            }
+        }
+        ProposalsCommand::Show { id } => {
+            let p = proposal::find(root, &id)?;
+            println!("Proposal: {}", p.id);
+            println!("Mem: {}", p.path);
+            println!("Created: {}", p.created_at.format("%Y-%m-%d %H:%M"));
+            if let Some(author) = &p.author {
+                println!("Author: {author}");
+            }
+            if let Some(message) = &p.message {
+                println!("Message: {message}");
+            }
+            if outdated(&p) {
+                println!("Outdated: the mem changed since this was proposed");
+            }
+            println!("---");
+            print!("{}", p.content);
+            if !p.content.ends_with('\n') {
+                println!();
+            }
+        }
+        ProposalsCommand::Diff { id, words, json } => {
+            let p = proposal::find(root, &id)?;
+            let current = storage.read_mem(&p.path)?;
+            print_diff(
+                ctx,
+                (&p.path, &current.content),
+                (&format!("proposal {}", p.id), &p.content),
+                words,
+                3,
+                json,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn cmd_accept(ctx: &Context, id: &str, force: bool, force_unlock: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let root = storage.local_root()?;
+    let p = proposal::find(root, id)?;
+    let mut mem = storage.read_mem(&p.path)?;
+    check_unlocked(&mem, force_unlock)?;
+    if p.is_outdated(&mem) && !force {
+        return Err(anyhow!(
+            "{} changed since proposal {} was made; review with mem proposals diff {} and pass --force to apply it anyway",
+            p.path,
+            p.id,
+            p.id
+        ));
+    }
+
+    mem.content = p.content.clone();
+    mem.touch();
+    storage.write_mem(&mem)?;
+    p.remove(root)?;
+    status!(ctx, "Applied {} to {}", p.id, p.path);
     Ok(())
 }
 
-fn cmd_rm(path: &str) -> Result<()> {
-    let storage = Storage::find()?;
-    storage.delete_mem(path)?;
-    println!("Deleted: {path}");
+fn cmd_reject(ctx: &Context, id: &str) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let root = storage.local_root()?;
+    let p = proposal::find(root, id)?;
+    p.remove(root)?;
+    status!(ctx, "Rejected {} for {}", p.id, p.path);
     Ok(())
 }
 
-fn cmd_ls(path: Option<&str>, json: bool, dirs: &[PathBuf]) -> Result<()> {
-    let storages = get_storages(dirs)?;
-    let multi = storages.len() > 1;
+fn cmd_blame(ctx: &Context, path: &str, sections: bool, json: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = &storage.resolve(path)?;
+    let root = storage.local_root()?;
 
-    let mut all_mems: Vec<(String, Mem)> = Vec::new();
-    for (label, storage) in &storages {
-        let mems = match path {
-            Some(p) => storage.list_mems_under(p)?,
-            None => storage.list_mems()?,
-        };
-        for mem in mems {
-            all_mems.push((label.clone(), mem));
+    if git::toplevel(root).is_err() {
+        // Without git, snapshots only tell when the whole file changed
+        let versions = blame::snapshot_history(root, path)?;
+        if json {
+            let output = serde_json::json!({ "source": "snapshots", "versions": versions });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else if versions.is_empty() {
+            println!("No history for {path} (not in git, and no backup snapshot contains it)");
+        } else {
+            status!(
+                ctx,
+                "Not in git; changes to {path} between backup snapshots:"
+            );
+            for version in &versions {
+                let name = version
+                    .snapshot
+                    .file_name()
+                    .map(|n| n.to_string_lossy())
+                    .unwrap_or_default();
+                println!(
+                    "{}  {:<8}  {name}",
+                    version.taken.format("%Y-%m-%d %H:%M"),
+                    version.change.as_str()
+                );
+            }
         }
+        return Ok(());
     }
 
-    if json {
-        let json_output: Vec<MemJson> = all_mems.iter().map(|(_, m)| MemJson::from(m)).collect();
-        println!("{}", serde_json::to_string_pretty(&json_output)?);
-    } else if all_mems.is_empty() {
-        println!("No mems found");
+    let lines = git::blame(&storage.mem_path(path))?;
+    let offset = content_line_offset(&storage.read_source(path)?);
+    if sections {
+        let sections = blame::sections(&lines, offset);
+        if json {
+            let output = serde_json::json!({ "source": "git", "sections": sections });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            for section in &sections {
+                let heading = if section.heading.is_empty() {
+                    "(top)"
+                } else {
+                    section.heading.as_str()
+                };
+                println!(
+                    "{}  {}  {:<16}  {heading} (lines {}-{})",
+                    section.commit,
+                    section.time.format("%Y-%m-%d"),
+                    section.author,
+                    section.start,
+                    section.end
+                );
+            }
+        }
     } else {
-        for (label, mem) in &all_mems {
-            let path_str = mem.path.to_string_lossy();
-            let tags = if mem.tags.is_empty() {
-                String::new()
-            } else {
-                format!(" [{}]", mem.tags.join(", "))
-            };
-            let prefix = if multi {
-                format!("[{label}] ")
-            } else {
-                String::new()
-            };
-            println!("{prefix}{path_str}: {}{tags}", mem.title);
+        let lines: Vec<&git::BlameLine> = lines.iter().filter(|l| l.line > offset).collect();
+        if json {
+            let output = serde_json::json!({ "source": "git", "lines": lines });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            for line in lines {
+                println!(
+                    "{}  {}  {:<16}  {:>4}  {}",
+                    line.commit,
+                    line.time.format("%Y-%m-%d"),
+                    line.author,
+                    line.line,
+                    line.text
+                );
+            }
         }
     }
-
-    Ok(())
-}
-
-fn cmd_archive(path: &str) -> Result<()> {
-    let storage = Storage::find()?;
-    storage.archive_mem(path)?;
-    println!("Archived: {path}");
     Ok(())
 }
 
-fn cmd_find(query: &str, json: bool, dirs: &[PathBuf]) -> Result<()> {
-    let storages = get_storages(dirs)?;
-    let multi = storages.len() > 1;
-
-    // Case-insensitive substring search on title and content
-    let query_lower = query.to_lowercase();
-    let mut matches: Vec<(String, Mem)> = Vec::new();
+fn cmd_backlinks(ctx: &Context, path: &str, json: bool, dirs: &[PathBuf]) -> Result<()> {
+    let (storage, path) = storage_for(ctx, path, dirs)?;
+    let path = &storage.resolve(&path)?;
+    let mut backlinks = storage.backlinks(path)?;
 
-    for (label, storage) in &storages {
-        let mems = storage.list_mems()?;
-        for mem in mems {
-            if mem.title.to_lowercase().contains(&query_lower)
-                || mem.content.to_lowercase().contains(&query_lower)
-            {
-                matches.push((label.clone(), mem));
+    // Links from other stores in play, listed as `store:path`
+    let storages = get_storages(ctx, dirs)?;
+    if storages.len() > 1 {
+        let names = store_names(&storages, &storage)?;
+        for (label, other) in &storages {
+            let name = discover::names(label, other.root())
+                .pop()
+                .unwrap_or_default();
+            for mem in other.list_mems()? {
+                let links_here = links::extract_store_links(&mem.content)
+                    .iter()
+                    .any(|l| names.contains(&l.store) && l.path == *path);
+                if links_here {
+                    backlinks.push(format!("{name}:{}", mem.path_str()));
+                }
             }
         }
     }
 
     if json {
-        let json_output: Vec<MemJson> = matches.iter().map(|(_, m)| MemJson::from(m)).collect();
-        println!("{}", serde_json::to_string_pretty(&json_output)?);
-    } else if matches.is_empty() {
-        println!("No matches found for: {query}");
+        println!("{}", serde_json::to_string_pretty(&backlinks)?);
+    } else if backlinks.is_empty() {
+        println!("No mems link to {path}");
     } else {
-        for (label, mem) in &matches {
-            let path_str = mem.path.to_string_lossy();
-            let prefix = if multi {
-                format!("[{label}] ")
-            } else {
-                String::new()
-            };
-            println!("{prefix}{path_str}: {}", mem.title);
+        for source in &backlinks {
+            println!("{source}");
         }
     }
 
     Ok(())
 }
 
-fn cmd_tree(path: Option<&str>, dirs: &[PathBuf]) -> Result<()> {
-    let storages = get_storages(dirs)?;
-    let multi = storages.len() > 1;
+/// JSON representation of a `check-refs` finding.
+#[derive(Serialize)]
+struct RefIssueJson<'a> {
+    #[serde(flatten)]
+    reference: &'a refs::CodeRef,
+    problem: refs::Problem,
+}
 
-    let mut any_found = false;
-    for (idx, (label, storage)) in storages.iter().enumerate() {
-        let mems = match path {
-            Some(p) => storage.list_mems_under(p)?,
-            None => storage.list_mems()?,
-        };
+fn cmd_check_refs(
+    ctx: &Context,
+    src: &Path,
+    allow_archived: bool,
+    stale: Option<u32>,
+    json: bool,
+) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let code_refs = refs::scan(src)?;
 
-        if mems.is_empty() {
-            continue;
+    let mut issues = Vec::new();
+    for reference in &code_refs {
+        if let Some(problem) = refs::check(&storage, &reference.target, allow_archived, stale)? {
+            issues.push(RefIssueJson { reference, problem });
         }
-        any_found = true;
+    }
 
-        // Add separator between directories
-        if multi && idx > 0 {
-            println!();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&issues)?);
+    } else if issues.is_empty() {
+        println!("All {} mem references resolve", code_refs.len());
+    } else {
+        println!("Found {} bad mem references:", issues.len());
+        for issue in &issues {
+            println!(
+                "  {}:{}: mem:{} ({})",
+                issue.reference.file.display(),
+                issue.reference.line,
+                issue.reference.target,
+                issue.problem.as_str()
+            );
         }
+    }
 
-        // Build tree structure: map parent path -> mems at that level
-        let mut tree: std::collections::BTreeMap<String, Vec<&Mem>> =
-            std::collections::BTreeMap::new();
-        // Track all directory paths that exist
-        let mut all_dirs: std::collections::BTreeSet<String> = std::collections::BTreeSet::new();
-
-        for mem in &mems {
-            let path_str = mem.path.to_string_lossy().to_string();
-            let parts: Vec<&str> = path_str.split('/').collect();
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(MemError::CheckFailed {
+            check: "check-refs",
+            issues: issues.len(),
+        }
+        .into())
+    }
+}
 
-            // Add all parent directories to the set
-            for i in 1..parts.len() {
-                all_dirs.insert(parts[..i].join("/"));
-            }
+fn cmd_backrefs(ctx: &Context, path: &str, src: &Path, json: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let path = storage.resolve(path)?;
 
-            // Group by parent path
-            if parts.len() == 1 {
-                tree.entry(String::new()).or_default().push(mem);
-            } else {
-                let parent = parts[..parts.len() - 1].join("/");
-                tree.entry(parent).or_default().push(mem);
-            }
+    let mut backrefs = Vec::new();
+    for reference in refs::scan(src)? {
+        if refs::resolve(&storage, &reference.target)?.as_deref() == Some(path.as_str()) {
+            backrefs.push(reference);
         }
-
-        // Print tree with box-drawing characters
-        let root_name = if multi {
-            label.as_str()
-        } else {
-            path.unwrap_or(".mems")
-        };
-        print_tree(&tree, &all_dirs, "", "", root_name);
     }
 
-    if !any_found {
-        println!("No mems found");
+    if json {
+        println!("{}", serde_json::to_string_pretty(&backrefs)?);
+    } else if backrefs.is_empty() {
+        println!("No code references {path}");
+    } else {
+        for reference in &backrefs {
+            println!("{}:{}", reference.file.display(), reference.line);
+        }
     }
 
     Ok(())
 }
 
-fn print_tree(
-    tree: &std::collections::BTreeMap<String, Vec<&Mem>>,
-    all_dirs: &std::collections::BTreeSet<String>,
-    parent: &str,
-    prefix: &str,
-    root_name: &str,
-) {
-    // Get items at this level
-    let items = tree.get(parent).map(|v| v.as_slice()).unwrap_or(&[]);
+fn cmd_conflicts(ctx: &Context, json: bool, dirs: &[PathBuf]) -> Result<()> {
+    let storages = get_storages(ctx, dirs)?;
+    let multi = storages.len() > 1;
 
-    // Get subdirectories at this level (direct children only)
-    let subdirs: Vec<&String> = all_dirs
-        .iter()
-        .filter(|d| {
-            if parent.is_empty() {
-                !d.contains('/')
+    let mut conflicted = Vec::new();
+    for (label, storage) in &storages {
+        for path in storage.conflicted_mems()? {
+            conflicted.push(if multi {
+                format!("[{label}] {path}")
             } else {
-                d.starts_with(&format!("{parent}/"))
-                    && d[parent.len() + 1..].split('/').count() == 1
-            }
-        })
-        .collect();
-
-    if prefix.is_empty() {
-        println!("{root_name}/");
+                path
+            });
+        }
     }
 
-    let total = items.len() + subdirs.len();
-    let mut idx = 0;
-
-    // Print subdirectories first
-    for subdir in &subdirs {
-        idx += 1;
-        let is_last = idx == total;
-        let connector = if is_last { "└── " } else { "├── " };
-        let dir_name = if parent.is_empty() {
-            subdir.as_str()
-        } else {
-            &subdir[parent.len() + 1..]
-        };
-        println!("{prefix}{connector}{dir_name}/");
-
-        let new_prefix = if is_last {
-            format!("{prefix}    ")
-        } else {
-            format!("{prefix}│   ")
-        };
-        print_tree(tree, all_dirs, subdir, &new_prefix, root_name);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&conflicted)?);
+    } else if conflicted.is_empty() {
+        println!("No merge conflicts");
+    } else {
+        for path in &conflicted {
+            println!("{path}");
+        }
     }
 
-    // Print items
-    for mem in items {
-        idx += 1;
-        let is_last = idx == total;
-        let connector = if is_last { "└── " } else { "├── " };
-        let name = mem
-            .path
-            .file_name()
-            .map(|n| n.to_string_lossy())
-            .unwrap_or_default();
-        println!("{prefix}{connector}{name} - {}", mem.title);
-    }
+    Ok(())
 }
 
-fn cmd_stale(days: u32, json: bool, dirs: &[PathBuf]) -> Result<()> {
-    let storages = get_storages(dirs)?;
-    let multi = storages.len() > 1;
+fn cmd_resolve(ctx: &Context, path: &str, strategy: Strategy) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let text = storage.read_source(path)?;
+    if !conflict::has_markers(&text) {
+        return Err(anyhow!("no merge conflict in {path}"));
+    }
 
-    let now = chrono::Utc::now();
-    let threshold = chrono::Duration::days(i64::from(days));
+    let mem = conflict::resolve(Path::new(path), &text, strategy)?;
+    storage.write_mem(&mem)?;
+    status!(ctx, "Resolved: {path}");
+    Ok(())
+}
 
-    let mut stale: Vec<(String, Mem)> = Vec::new();
-    for (label, storage) in &storages {
-        let mems = storage.list_mems()?;
-        for mem in mems {
-            if now - mem.updated_at > threshold {
-                stale.push((label.clone(), mem));
+fn cmd_export(ctx: &Context, command: ExportCommand) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    match command {
+        ExportCommand::Dir {
+            out,
+            path,
+            plain,
+            force,
+            incremental,
+        } => {
+            // An incremental export updates an earlier one; the first export
+            // into a directory still needs it empty
+            let updating = incremental && export::is_export(&out);
+            if !force && !updating {
+                export::check_target(&out)?;
+            }
+            let mut mems = match path {
+                Some(p) => storage.list_mems_under(&normalize_path(&p)?)?,
+                None => storage.list_mems()?,
+            };
+            if plain {
+                for mem in &mut mems {
+                    mem.content = include::expand(&storage, mem)?;
+                }
+            }
+            if incremental {
+                let summary = export::sync_dir(&mems, &out, plain, ctx.progress().as_ref())?;
+                status!(
+                    ctx,
+                    "Exported {} mems to {} ({} written, {} unchanged, {} removed)",
+                    mems.len(),
+                    out.display(),
+                    summary.written,
+                    summary.unchanged,
+                    summary.removed
+                );
+            } else {
+                let count = export::export_dir(&mems, &out, plain, ctx.progress().as_ref())?;
+                status!(ctx, "Exported {count} mems to {}", out.display());
+            }
+        }
+        ExportCommand::Mem { path, out, force } => {
+            let bundle = Bundle::collect(&storage, &path)?;
+            let out = out.unwrap_or_else(|| {
+                let name = bundle.manifest.path.rsplit('/').next().unwrap_or_default();
+                PathBuf::from(format!("{name}.{}", bundle::EXTENSION))
+            });
+            if !force && out.exists() {
+                return Err(anyhow!(
+                    "{} already exists (use --force to overwrite)",
+                    out.display()
+                ));
             }
+            bundle.write(&out)?;
+            let attachments = bundle.manifest.attachments.len();
+            let commits = bundle.manifest.history.len();
+            status!(
+                ctx,
+                "Exported {} to {} ({attachments} attachment{}, {commits} commit{} of history)",
+                bundle.manifest.path,
+                out.display(),
+                if attachments == 1 { "" } else { "s" },
+                if commits == 1 { "" } else { "s" }
+            );
         }
     }
+    Ok(())
+}
 
-    if json {
-        let json_output: Vec<MemJson> = stale.iter().map(|(_, m)| MemJson::from(m)).collect();
-        println!("{}", serde_json::to_string_pretty(&json_output)?);
-    } else if stale.is_empty() {
-        println!("No stale mems (threshold: {days} days)");
+fn cmd_serve(ctx: &Context, addr: &str, web: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("failed to listen on {addr}"))?;
+    let url = format!("http://{}", listener.local_addr()?);
+    if web {
+        println!("Serving mems at {url}/");
     } else {
-        println!("Stale mems (not updated in {days}+ days):");
-        for (label, mem) in &stale {
-            let path_str = mem.path.to_string_lossy();
-            let days_old = (now - mem.updated_at).num_days();
-            let prefix = if multi {
-                format!("[{label}] ")
+        println!("Serving the mem API at {url}/api/mems");
+    }
+    io::stdout().flush()?;
+    serve::run(&storage, listener, web)
+}
+
+fn cmd_cache(ctx: &Context, command: CacheCommand) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let root = storage.local_root()?;
+    match command {
+        CacheCommand::Build => {
+            let mut cache = Cache::create(root)?;
+            let count = cache.refresh(&storage, ctx.progress().as_ref())?;
+            status!(
+                ctx,
+                "Cached {count} mems in {}",
+                root.join(CACHE_FILE).display()
+            );
+        }
+        CacheCommand::Status => match storage.cache()? {
+            Some(cache) => println!("Cache enabled: {} mems", cache.len()?),
+            None => println!("Cache disabled (run `mem cache build` to enable)"),
+        },
+        CacheCommand::Clear => {
+            let path = root.join(CACHE_FILE);
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+                status!(ctx, "Removed {}", path.display());
             } else {
-                String::new()
-            };
-            println!("  {prefix}{path_str}: {} ({days_old} days)", mem.title);
+                status!(ctx, "No cache to remove");
+            }
         }
     }
-
     Ok(())
 }
 
-fn cmd_lint(dirs: &[PathBuf]) -> Result<()> {
-    let storages = get_storages(dirs)?;
-    let multi = storages.len() > 1;
+/// `index status --json`.
+#[derive(Serialize)]
+struct IndexStatusJson {
+    path: String,
+    exists: bool,
+    bytes: u64,
+    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    backend: String,
 
-    let mut issues = Vec::new();
-    let mut total_mems = 0;
+    /// Analyzer the vectors depend on (local backend)
+    analyzer: Option<String>,
+    indexed: usize,
 
-    for (label, storage) in &storages {
-        let mems = storage.list_mems()?;
-        total_mems += mems.len();
+    /// Why the index on disk can't be used, if it can't
+    discarded: Option<String>,
 
-        for mem in &mems {
-            let path_str = mem.path.to_string_lossy();
-            let prefix = if multi {
-                format!("[{label}] ")
-            } else {
-                String::new()
-            };
+    #[serde(flatten)]
+    drift: semantic::Drift,
+}
 
-            // Check for empty title
-            if mem.title.trim().is_empty() {
-                issues.push(format!("{prefix}{path_str}: empty title"));
-            }
-
-            // Check for empty content
-            if mem.content.trim().is_empty() {
-                issues.push(format!("{prefix}{path_str}: empty content"));
-            }
-
-            // Check for broken internal links
-            for line in mem.content.lines() {
-                // Simple regex-free link extraction: find [text](path.md) patterns
-                let mut chars = line.char_indices().peekable();
-                while let Some((i, c)) = chars.next() {
-                    if c == '[' {
-                        // Find closing ]
-                        let mut depth = 1;
-                        let mut j = i + 1;
-                        for (idx, ch) in chars.by_ref() {
-                            j = idx;
-                            if ch == '[' {
-                                depth += 1;
-                            } else if ch == ']' {
-                                depth -= 1;
-                                if depth == 0 {
-                                    break;
-                                }
-                            }
-                        }
-                        // Check for (
-                        if let Some(&(_, '(')) = chars.peek() {
-                            chars.next();
-                            let start = j + 2;
-                            let mut end = start;
-                            for (idx, ch) in chars.by_ref() {
-                                if ch == ')' {
-                                    end = idx;
-                                    break;
-                                }
-                            }
-                            let link = &line[start..end];
-                            // Check if it's a relative .md link
-                            if link.ends_with(".md") && !link.starts_with("http") {
-                                // Resolve relative to mem's directory
-                                let mem_dir = mem.path.parent().unwrap_or(std::path::Path::new(""));
-                                let link_path = mem_dir.join(link.trim_end_matches(".md"));
-                                let link_str = link_path.to_string_lossy().to_string();
-                                if !storage.exists(&link_str) {
-                                    issues
-                                        .push(format!("{prefix}{path_str}: broken link to {link}"));
-                                }
-                            }
-                        }
-                    }
+fn cmd_index(ctx: &Context, command: IndexCommand) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let config = Config::for_storage(&storage)?;
+    let embedder = semantic::embedder_from_config(&config)?;
+    let mut index = semantic::EmbeddingIndex::open(storage.root(), embedder.as_ref());
+    let mems = storage.list_mems()?;
+    let drift = index.drift(&mems);
+
+    match command {
+        IndexCommand::Status { json } => {
+            let file = std::fs::metadata(index.path()).ok();
+            let status = IndexStatusJson {
+                path: index.path().display().to_string(),
+                exists: file.is_some(),
+                bytes: file.as_ref().map_or(0, |f| f.len()),
+                updated_at: file
+                    .and_then(|f| f.modified().ok())
+                    .map(chrono::DateTime::from),
+                backend: index.backend().to_string(),
+                analyzer: index.analyzer().map(str::to_string),
+                indexed: index.len(),
+                discarded: index.discarded().map(str::to_string),
+                drift,
+            };
+            if json {
+                println!("{}", serde_json::to_string_pretty(&status)?);
+                return Ok(());
+            }
+            if !status.exists {
+                println!("No index yet (semantic find, related, and ask build it; or run `mem index rebuild`)");
+                return Ok(());
+            }
+            let updated = status
+                .updated_at
+                .map(|t| format!(", updated {}", t.format("%Y-%m-%d %H:%M")))
+                .unwrap_or_default();
+            println!(
+                "Index:    {} ({} bytes{updated})",
+                status.path, status.bytes
+            );
+            if let Some(reason) = &status.discarded {
+                println!("Unusable: {reason} (run `mem index rebuild`)");
+            }
+            println!("Backend:  {}", status.backend);
+            if let Some(analyzer) = &status.analyzer {
+                println!("Analyzer: {analyzer}");
+            }
+            let drift = &status.drift;
+            println!(
+                "Mems:     {} indexed: {} fresh, {} stale, {} missing, {} orphaned",
+                status.indexed,
+                drift.fresh,
+                drift.stale.len(),
+                drift.missing.len(),
+                drift.orphaned.len()
+            );
+        }
+        IndexCommand::Rebuild { full } => {
+            if full {
+                index.clear();
+                index.update(&mems, embedder.as_ref())?;
+                index.save()?;
+                status!(ctx, "Embedded {} mems", mems.len());
+            } else {
+                index.update(&mems, embedder.as_ref())?;
+                index.save()?;
+                status!(
+                    ctx,
+                    "Embedded {} mems and removed {} vectors ({} up to date)",
+                    drift.stale.len() + drift.missing.len(),
+                    drift.orphaned.len(),
+                    drift.fresh
+                );
+            }
+        }
+        IndexCommand::Verify { repair } => {
+            if let Some(reason) = index.discarded() {
+                println!("index unusable: {reason}");
+            }
+            for (problem, paths) in [
+                ("stale", &drift.stale),
+                ("missing", &drift.missing),
+                ("orphaned", &drift.orphaned),
+            ] {
+                for path in paths {
+                    println!("{problem}: {path}");
+                }
+            }
+            let issues = drift.stale.len() + drift.missing.len() + drift.orphaned.len();
+            if issues == 0 && index.discarded().is_none() {
+                status!(ctx, "Index matches all {} mems", mems.len());
+            } else if repair {
+                index.update(&mems, embedder.as_ref())?;
+                index.save()?;
+                status!(ctx, "Repaired {issues} index entries");
+            } else {
+                return Err(MemError::CheckFailed {
+                    check: "index verify",
+                    issues: issues.max(1),
                 }
+                .into());
             }
         }
     }
+    Ok(())
+}
 
-    if issues.is_empty() {
-        println!("No issues found ({total_mems} mems checked)");
-        Ok(())
-    } else {
-        println!("Found {} issues:", issues.len());
-        for issue in &issues {
-            println!("  {issue}");
+fn cmd_usage(ctx: &Context, command: UsageCommand) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let root = storage.local_root()?;
+    match command {
+        UsageCommand::Enable => {
+            let created = Usage::enable(root)?;
+            status!(ctx, "Tracking usage in {}", root.join(USAGE_FILE).display());
+
+            // Usage is per person: keep it out of the repository
+            let project = root
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            if created && git::toplevel(project).is_ok() {
+                let store = root.file_name().unwrap_or_default().to_string_lossy();
+                let added = starter::ignore_files(project, &store, vec![USAGE_FILE.to_string()])?;
+                if !added.is_empty() {
+                    status!(ctx, "Added to .gitignore: {}", added.join(", "));
+                }
+            }
+        }
+        UsageCommand::Status => match Usage::open(root)? {
+            Some(usage) => {
+                let shown: u64 = usage.mems.values().map(|r| r.count).sum();
+                println!(
+                    "Usage tracking enabled: {} mems shown {shown} times",
+                    usage.mems.len()
+                );
+            }
+            None => println!("Usage tracking disabled (run `mem usage enable` to enable)"),
+        },
+        UsageCommand::Disable => {
+            if Usage::disable(root)? {
+                status!(
+                    ctx,
+                    "Stopped tracking usage and removed {}",
+                    root.join(USAGE_FILE).display()
+                );
+            } else {
+                status!(ctx, "Usage tracking is already off");
+            }
         }
-        Err(anyhow!("lint failed with {} issues", issues.len()))
     }
+    Ok(())
 }
 
-fn cmd_dump(path: Option<&str>, dirs: &[PathBuf]) -> Result<()> {
-    let storages = get_storages(dirs)?;
-    let mut first = true;
-
-    for (label, storage) in &storages {
-        let mems = match path {
-            Some(p) => storage.list_mems_under(p)?,
-            None => storage.list_mems()?,
-        };
-
-        if mems.is_empty() {
-            continue;
-        }
+fn cmd_sync(ctx: &Context, remote: &Path, dry_run: bool) -> Result<()> {
+    let storage = find_storage(ctx)?;
+    let remote = if remote.join(".mems").is_dir() {
+        remote.join(".mems")
+    } else {
+        remote.to_path_buf()
+    };
+    if !remote.is_dir() {
+        return Err(anyhow!("directory not found: {}", remote.display()));
+    }
 
-        // Multi-dir header
-        if storages.len() > 1 && !first {
-            println!();
-        }
-        if storages.len() > 1 {
-            println!("<!-- ═══ {label} ═══ -->");
-            println!();
+    let actions = sync::sync(storage.local_root()?, &remote, dry_run)?;
+    let (mut pushed, mut pulled, mut deleted, mut conflicts) = (0, 0, 0, 0);
+    for action in &actions {
+        match action {
+            Action::Push(path) => {
+                pushed += 1;
+                println!("Pushed: {path}");
+            }
+            Action::Pull(path) => {
+                pulled += 1;
+                println!("Pulled: {path}");
+            }
+            Action::DeleteLocal(path) => {
+                deleted += 1;
+                println!("Deleted locally: {path}");
+            }
+            Action::DeleteRemote(path) => {
+                deleted += 1;
+                println!("Deleted remotely: {path}");
+            }
+            Action::Conflict {
+                path,
+                copy,
+                local_wins,
+            } => {
+                conflicts += 1;
+                let (kept, saved) = if *local_wins {
+                    ("local", "remote")
+                } else {
+                    ("remote", "local")
+                };
+                println!("Conflict: {path} (kept {kept}, {saved} version saved as {copy})");
+            }
         }
-        first = false;
+    }
 
-        for mem in &mems {
-            let path_str = mem.path.to_string_lossy();
+    let verb = if dry_run { "Would sync" } else { "Synced" };
+    status!(
+        ctx,
+        "{verb}: {pushed} pushed, {pulled} pulled, {deleted} deleted, {conflicts} conflicts"
+    );
+    Ok(())
+}
 
-            // Section divider with path
-            println!(
-                "<!-- ═══════════════════════════════════════════════════════════════════ -->"
+fn cmd_backup(ctx: &Context, command: BackupCommand) -> Result<()> {
+    match command {
+        BackupCommand::Create { out } => {
+            let storage = find_storage(ctx)?;
+            let root = storage.local_root()?;
+            let out = out.unwrap_or_else(|| backup::default_path(root, chrono::Utc::now()));
+            let manifest = backup::create(root, &out)?;
+            status!(
+                ctx,
+                "Backed up {} files to {}",
+                manifest.files.len(),
+                out.display()
             );
-            println!("<!-- {path_str} -->");
+        }
+        BackupCommand::Verify { file } => {
+            let manifest = backup::verify(&file)?;
             println!(
-                "<!-- ═══════════════════════════════════════════════════════════════════ -->"
+                "OK: {} files from {}",
+                manifest.files.len(),
+                manifest.created_at.to_rfc3339()
             );
-            println!();
-
-            // Title as H1
-            println!("# {}", mem.title);
-            println!();
-
-            // Tags if present
-            if !mem.tags.is_empty() {
-                println!("Tags: {}", mem.tags.join(", "));
-                println!();
+        }
+        BackupCommand::Restore { file, force } => {
+            // Restoring into a fresh checkout creates the store
+            let storage = match find_storage(ctx) {
+                Ok(storage) => storage,
+                Err(_) => Storage::new(std::env::current_dir()?.join(".mems")),
+            };
+            let root = storage.local_root()?;
+            if !force && root.exists() && !storage.list_mems()?.is_empty() {
+                return Err(anyhow!(
+                    "store is not empty (use --force to replace its contents)"
+                ));
             }
-
-            // Content
-            println!("{}", mem.content);
-            println!();
+            let manifest = backup::restore(&file, root)?;
+            status!(
+                ctx,
+                "Restored {} files from {}",
+                manifest.files.len(),
+                manifest.created_at.to_rfc3339()
+            );
         }
+        BackupCommand::Prune { keep } => {
+            let storage = find_storage(ctx)?;
+            let removed = backup::prune(storage.local_root()?, keep)?;
+            for path in &removed {
+                println!("Removed: {}", path.display());
+            }
+            status!(ctx, "{} backups removed", removed.len());
+        }
+    }
+    Ok(())
+}
+
+fn cmd_bench(shape: Shape, runs: usize, path: Option<PathBuf>) -> Result<()> {
+    let (root, keep) = match path {
+        Some(path) => (path, true),
+        None => (
+            std::env::temp_dir().join(format!("mem-bench-{}", std::process::id())),
+            false,
+        ),
+    };
+    if root.exists() && std::fs::read_dir(&root)?.next().is_some() {
+        return Err(anyhow!(
+            "{} is not empty; bench needs a fresh directory",
+            root.display()
+        ));
+    }
+    let storage = Storage::new(root.clone());
+
+    let report = (|| {
+        let start = Instant::now();
+        bench::generate(&storage, shape)?;
+        let generate_ms = start.elapsed().as_secs_f64() * 1000.0;
+        Ok::<_, anyhow::Error>(bench::Report {
+            shape,
+            generate_ms,
+            timings: bench::run(&storage, runs)?,
+        })
+    })();
+    if !keep {
+        let _ = std::fs::remove_dir_all(&root);
     }
 
+    println!("{}", serde_json::to_string_pretty(&report?)?);
     Ok(())
 }
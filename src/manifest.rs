@@ -0,0 +1,170 @@
+//! Manifests for `mem dump --manifest`: which mems a dump holds, with a hash
+//! of each, so a consumer can tell when its packed context is out of date
+//! and `mem dump --diff` can emit only what changed since.
+
+use crate::hash::sha256_hex;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// First line of the manifest header at the top of a dump.
+pub const HEADER_START: &str = "<!-- mem-manifest";
+
+/// Last line of the manifest header.
+pub const HEADER_END: &str = "-->";
+
+/// What a dump contains.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Hash of every entry's store, path, and hash: equal manifests dumped
+    /// equal mems
+    pub store_hash: String,
+
+    /// Mems in dump order
+    pub mems: Vec<Entry>,
+
+    /// Mems in the manifest a diff was taken against that are gone
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<Entry>,
+}
+
+/// A mem in a dump.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Label of the mem's store, when a dump covers several
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store: Option<String>,
+
+    pub path: String,
+
+    /// SHA-256 of the mem's section of the dump
+    pub hash: String,
+
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Entry {
+    pub fn new(store: Option<&str>, path: &str, section: &str, updated_at: DateTime<Utc>) -> Entry {
+        Entry {
+            store: store.map(str::to_string),
+            path: path.to_string(),
+            hash: sha256_hex(section.as_bytes()),
+            updated_at,
+        }
+    }
+
+    /// Identifies the mem across manifests.
+    pub fn key(&self) -> (Option<&str>, &str) {
+        (self.store.as_deref(), &self.path)
+    }
+}
+
+impl Manifest {
+    pub fn new(mems: Vec<Entry>) -> Manifest {
+        let listing: String = mems
+            .iter()
+            .map(|e| {
+                format!(
+                    "{}\t{}\t{}\n",
+                    e.store.as_deref().unwrap_or(""),
+                    e.path,
+                    e.hash
+                )
+            })
+            .collect();
+        Manifest {
+            store_hash: sha256_hex(listing.as_bytes()),
+            mems,
+            removed: Vec::new(),
+        }
+    }
+
+    /// The manifest as the header of a dump: pretty JSON in an HTML comment.
+    pub fn header(&self) -> Result<String> {
+        // `>` only occurs inside JSON strings, so escaping it is lossless and
+        // keeps a path from closing the comment early
+        let json = serde_json::to_string_pretty(self)?.replace('>', "\\u003e");
+        Ok(format!("{HEADER_START}\n{json}\n{HEADER_END}\n\n"))
+    }
+
+    /// Read a manifest from a dump made with `--manifest`, or from the bare
+    /// JSON.
+    pub fn parse(text: &str) -> Result<Manifest> {
+        let text = text.trim_start_matches('\u{feff}');
+        let json = match text.strip_prefix(HEADER_START) {
+            Some(rest) => {
+                let end = rest
+                    .find(&format!("\n{HEADER_END}"))
+                    .ok_or_else(|| anyhow!("unterminated manifest header"))?;
+                &rest[..end]
+            }
+            None => text,
+        };
+        serde_json::from_str(json).map_err(|e| anyhow!("invalid manifest: {e}"))
+    }
+
+    /// Entries that are new or whose hash changed since `old`, and `old`
+    /// entries that are gone.
+    pub fn changed_since<'a>(&'a self, old: &'a Manifest) -> (Vec<&'a Entry>, Vec<&'a Entry>) {
+        let before: BTreeMap<_, &str> = old.mems.iter().map(|e| (e.key(), &*e.hash)).collect();
+        let now: BTreeSet<_> = self.mems.iter().map(Entry::key).collect();
+        let changed = self
+            .mems
+            .iter()
+            .filter(|e| before.get(&e.key()) != Some(&&*e.hash))
+            .collect();
+        let removed = old
+            .mems
+            .iter()
+            .filter(|e| !now.contains(&e.key()))
+            .collect();
+        (changed, removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(path: &str, section: &str) -> Entry {
+        let at = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        Entry::new(None, path, section, at)
+    }
+
+    #[test]
+    fn test_store_hash() {
+        let a = Manifest::new(vec![entry("a", "one"), entry("b", "two")]);
+        let same = Manifest::new(vec![entry("a", "one"), entry("b", "two")]);
+        let edited = Manifest::new(vec![entry("a", "one"), entry("b", "2")]);
+        assert_eq!(a.store_hash, same.store_hash);
+        assert_ne!(a.store_hash, edited.store_hash);
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        let manifest = Manifest::new(vec![entry("odd-->path", "x")]);
+        let header = manifest.header().unwrap();
+        assert!(header.starts_with("<!-- mem-manifest\n{"));
+        assert_eq!(header.matches("-->").count(), 1);
+
+        let dump = format!("{header}# A\n\nBody\n");
+        assert_eq!(Manifest::parse(&dump).unwrap(), manifest);
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert_eq!(Manifest::parse(&json).unwrap(), manifest);
+        assert!(Manifest::parse("# Not a manifest").is_err());
+    }
+
+    #[test]
+    fn test_changed_since() {
+        let old = Manifest::new(vec![entry("a", "1"), entry("b", "2"), entry("c", "3")]);
+        let new = Manifest::new(vec![entry("a", "1"), entry("b", "two"), entry("d", "4")]);
+        let (changed, removed) = new.changed_since(&old);
+        let paths = |entries: Vec<&Entry>| -> Vec<String> {
+            entries.iter().map(|e| e.path.clone()).collect()
+        };
+        assert_eq!(paths(changed), ["b", "d"]);
+        assert_eq!(paths(removed), ["c"]);
+    }
+}
@@ -1,11 +1,21 @@
+use crate::attachment;
+use crate::conflict::{self, MergeConflict};
+use crate::dates;
+use crate::hash::sha256_hex;
+use crate::id;
+use crate::links;
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// Frontmatter fields for YAML serialization.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Frontmatter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+
     title: String,
 
     #[serde(rename = "created-at")]
@@ -16,6 +26,12 @@ struct Frontmatter {
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     tags: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+
+    #[serde(flatten)]
+    extra: BTreeMap<String, serde_yaml::Value>,
 }
 
 /// A memory document with YAML frontmatter and markdown content.
@@ -24,6 +40,9 @@ pub struct Mem {
     /// Relative path within .mems/ (without .md extension)
     pub path: PathBuf,
 
+    /// Stable identifier (ULID) assigned at creation; kept across moves
+    pub id: Option<String>,
+
     /// Title (required)
     pub title: String,
 
@@ -36,20 +55,36 @@ pub struct Mem {
     /// Optional tags
     pub tags: Vec<String>,
 
+    /// Content checksum (`sha256:<hex>`), recomputed on every write unless
+    /// `tampered`; `None` leaves the mem unprotected
+    pub checksum: Option<String>,
+
+    /// The checksum did not match the content when the mem was read. Such a
+    /// checksum is written back unchanged, so `verify` keeps reporting the
+    /// mem until it is blessed
+    pub tampered: bool,
+
+    /// Additional frontmatter fields, preserved across rewrites
+    pub extra: BTreeMap<String, serde_yaml::Value>,
+
     /// Markdown content (not in frontmatter)
     pub content: String,
 }
 
 impl Mem {
-    /// Create a new Mem with current timestamp.
+    /// Create a new Mem with current timestamp and a fresh id.
     pub fn new(path: PathBuf, title: String, content: String) -> Self {
         let now = Utc::now();
         Self {
             path,
+            id: Some(id::generate()),
             title,
             created_at: now,
             updated_at: now,
             tags: Vec::new(),
+            checksum: None,
+            tampered: false,
+            extra: BTreeMap::new(),
             content,
         }
     }
@@ -60,11 +95,185 @@ impl Mem {
         self
     }
 
+    /// Get an additional frontmatter field as a string, if it is one.
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.extra.get(key).and_then(|v| v.as_str())
+    }
+
+    /// Set an additional frontmatter field to a string value.
+    pub fn set_field(&mut self, key: &str, value: impl Into<String>) {
+        self.extra
+            .insert(key.to_string(), serde_yaml::Value::String(value.into()));
+    }
+
+    /// Who is responsible for keeping the mem current (the `owner` field).
+    pub fn owner(&self) -> Option<&str> {
+        self.field("owner")
+    }
+
+    /// The mem's path as shown in output and JSON: `/`-separated on every
+    /// platform.
+    pub fn path_str(&self) -> String {
+        self.path.to_string_lossy().replace('\\', "/")
+    }
+
+    /// What sort of mem this is (the `kind` field), e.g. `log`.
+    pub fn kind(&self) -> Option<&str> {
+        self.field("kind")
+    }
+
+    /// Whether the mem is pinned (the `pinned` field), listing it first.
+    pub fn is_pinned(&self) -> bool {
+        self.extra
+            .get("pinned")
+            .and_then(serde_yaml::Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Pin or unpin the mem. Unpinning drops the field.
+    pub fn set_pinned(&mut self, pinned: bool) {
+        if pinned {
+            self.extra
+                .insert("pinned".to_string(), serde_yaml::Value::Bool(true));
+        } else {
+            self.extra.remove("pinned");
+        }
+    }
+
+    /// Whether the mem is locked (the `locked` field) against edits, moves,
+    /// and removal.
+    pub fn is_locked(&self) -> bool {
+        self.extra
+            .get("locked")
+            .and_then(serde_yaml::Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Lock or unlock the mem. Unlocking drops the field.
+    pub fn set_locked(&mut self, locked: bool) {
+        if locked {
+            self.extra
+                .insert("locked".to_string(), serde_yaml::Value::Bool(true));
+        } else {
+            self.extra.remove("locked");
+        }
+    }
+
+    /// Whether the mem was written by `mem generate` (the `generated` field)
+    /// rather than by hand.
+    pub fn is_generated(&self) -> bool {
+        self.extra
+            .get("generated")
+            .and_then(serde_yaml::Value::as_bool)
+            .unwrap_or(false)
+    }
+
     /// Update the updated_at timestamp.
     pub fn touch(&mut self) {
         self.updated_at = Utc::now();
     }
 
+    /// Content length in bytes.
+    pub fn size(&self) -> usize {
+        self.content.len()
+    }
+
+    /// Number of whitespace-separated words in the content.
+    pub fn word_count(&self) -> usize {
+        self.content.split_whitespace().count()
+    }
+
+    /// Rough token estimate for LLM context budgets (~4 characters per token).
+    pub fn estimated_tokens(&self) -> usize {
+        self.content.chars().count().div_ceil(4)
+    }
+
+    /// Split content on top-level (`# `) headings into child mems under this mem's path.
+    ///
+    /// Returns the rewritten index mem (preamble plus links to each child) and the
+    /// children, which inherit this mem's timestamps and tags, with relative links
+    /// pointed at their targets from one level deeper. Returns `None` when the
+    /// content has no top-level headings.
+    pub fn split_by_headings(&self) -> Option<(Mem, Vec<Mem>)> {
+        let mut preamble = String::new();
+        let mut sections: Vec<(String, String)> = Vec::new();
+        let mut in_fence = false;
+
+        for line in self.content.lines() {
+            // Headings inside fenced code blocks are not structure
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+            if !in_fence {
+                if let Some(heading) = line.strip_prefix("# ") {
+                    sections.push((heading.trim().to_string(), String::new()));
+                    continue;
+                }
+            }
+            let body = match sections.last_mut() {
+                Some((_, body)) => body,
+                None => &mut preamble,
+            };
+            body.push_str(line);
+            body.push('\n');
+        }
+
+        if sections.is_empty() {
+            return None;
+        }
+
+        let dir_name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut index = preamble.trim().to_string();
+        if !index.is_empty() {
+            index.push_str("\n\n");
+        }
+
+        let mut used = std::collections::HashSet::new();
+        let mut children = Vec::new();
+        for (heading, body) in sections {
+            // Disambiguate repeated headings
+            let base = slugify(&heading);
+            let mut slug = base.clone();
+            let mut n = 1;
+            while !used.insert(slug.clone()) {
+                n += 1;
+                slug = format!("{base}-{n}");
+            }
+
+            index.push_str(&format!("- [{heading}]({dir_name}/{slug}.md)\n"));
+            let path = self.path.join(&slug);
+            // Children sit a level deeper, so relative links are pointed anew
+            let mut content = body.trim().to_string();
+            let child_path = path.to_string_lossy().to_string();
+            if let Some(relocated) = links::relocate(&content, &self.path, &child_path) {
+                content = relocated;
+            }
+            if let Some(relinked) = attachment::relink(&content, &child_path) {
+                content = relinked;
+            }
+            children.push(Self {
+                path,
+                id: Some(id::generate()),
+                title: heading,
+                created_at: self.created_at,
+                updated_at: self.updated_at,
+                tags: self.tags.clone(),
+                checksum: None,
+                tampered: false,
+                extra: BTreeMap::new(),
+                content,
+            });
+        }
+
+        let mut parent = self.clone();
+        parent.content = index;
+        Some((parent, children))
+    }
+
     /// Parse a Mem from file content.
     ///
     /// Expected format:
@@ -80,41 +289,131 @@ impl Mem {
     /// Markdown content here
     /// ```
     pub fn parse(path: PathBuf, content: &str) -> Result<Self> {
+        // Conflicts in the content leave a readable mem (lint reports them);
+        // ones that break the frontmatter make it unreadable until resolved
+        let conflicted = || -> anyhow::Error {
+            MergeConflict {
+                path: path.to_string_lossy().to_string(),
+            }
+            .into()
+        };
+
         // Find frontmatter delimiters
         if !content.starts_with("---") {
+            if conflict::has_markers(content) {
+                return Err(conflicted());
+            }
             return Err(anyhow!("missing frontmatter: file must start with ---"));
         }
-
-        // Find the closing delimiter
-        let rest = &content[3..];
-        let end_pos = rest
-            .find("\n---")
-            .ok_or_else(|| anyhow!("missing frontmatter: no closing --- found"))?;
-
-        let yaml_content = rest[..end_pos].trim_start_matches('\n');
-        let markdown_content = rest[end_pos + 4..].trim_start_matches('\n');
+        let Some((yaml_content, markdown_content)) = split_frontmatter(content) else {
+            if conflict::has_markers(content) {
+                return Err(conflicted());
+            }
+            return Err(anyhow!("missing frontmatter: no closing --- found"));
+        };
+        if conflict::has_markers(yaml_content) {
+            return Err(conflicted());
+        }
 
         // Parse YAML frontmatter
-        let frontmatter: Frontmatter = serde_yaml::from_str(yaml_content)
-            .map_err(|e| anyhow!("invalid frontmatter YAML: {e}"))?;
+        let frontmatter: Frontmatter = match serde_yaml::from_str(yaml_content) {
+            Ok(frontmatter) => frontmatter,
+            Err(_) if conflict::has_markers(content) => return Err(conflicted()),
+            Err(e) => return Err(anyhow!("invalid frontmatter YAML: {e}")),
+        };
 
         Ok(Self {
             path,
+            id: frontmatter.id,
             title: frontmatter.title,
             created_at: frontmatter.created_at,
             updated_at: frontmatter.updated_at,
             tags: frontmatter.tags,
+            tampered: is_tampered(&frontmatter.checksum, markdown_content),
+            checksum: frontmatter.checksum,
+            extra: frontmatter.extra,
             content: markdown_content.to_string(),
         })
     }
 
+    /// Build a new mem from a whole document whose frontmatter may be partial
+    /// or missing, as piped to `add --with-frontmatter`. Missing timestamps
+    /// are now and a missing id is generated; a missing title is left empty
+    /// for the caller to fill. Any checksum is dropped.
+    pub fn from_document(path: PathBuf, document: &str) -> Result<Self> {
+        let document = document.replace("\r\n", "\n");
+        let mut mem = Mem::new(path, String::new(), document.clone());
+        let Some((yaml, body)) = document
+            .strip_prefix("---\n")
+            .and_then(|rest| rest.split_once("\n---"))
+        else {
+            return Ok(mem);
+        };
+        mem.content = body.trim_start_matches('\n').to_string();
+
+        let mut fields: BTreeMap<String, serde_yaml::Value> = match serde_yaml::from_str(yaml) {
+            Ok(serde_yaml::Value::Null) => BTreeMap::new(),
+            Ok(value) => serde_yaml::from_value(value)
+                .map_err(|_| anyhow!("invalid frontmatter: not a mapping of fields"))?,
+            Err(e) => return Err(anyhow!("invalid frontmatter YAML: {e}")),
+        };
+        let mut text = |key: &str| -> Result<Option<String>> {
+            match fields.remove(key) {
+                None | Some(serde_yaml::Value::Null) => Ok(None),
+                Some(serde_yaml::Value::String(s)) => Ok(Some(s)),
+                Some(serde_yaml::Value::Number(n)) => Ok(Some(n.to_string())),
+                Some(_) => Err(anyhow!("invalid frontmatter: {key} must be text")),
+            }
+        };
+        if let Some(id) = text("id")? {
+            mem.id = Some(id);
+        }
+        if let Some(title) = text("title")? {
+            mem.title = title.trim().to_string();
+        }
+        for (key, timestamp) in [
+            ("created-at", &mut mem.created_at),
+            ("updated-at", &mut mem.updated_at),
+        ] {
+            if let Some(value) = text(key)? {
+                *timestamp = dates::parse_timestamp(&value).ok_or_else(|| {
+                    anyhow!("invalid frontmatter: {key} is not a timestamp: {value}")
+                })?;
+            }
+        }
+        text("checksum")?;
+        mem.tags = match fields.remove("tags") {
+            None | Some(serde_yaml::Value::Null) => Vec::new(),
+            Some(serde_yaml::Value::Sequence(items)) => items
+                .iter()
+                .map(|v| v.as_str().map(|t| t.trim().to_string()))
+                .collect::<Option<_>>()
+                .ok_or_else(|| anyhow!("invalid frontmatter: tags must be text"))?,
+            Some(serde_yaml::Value::String(s)) => s
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            Some(_) => return Err(anyhow!("invalid frontmatter: tags must be a list")),
+        };
+        mem.extra = fields;
+        Ok(mem)
+    }
+
     /// Serialize the Mem to file content.
     pub fn serialize(&self) -> Result<String> {
         let frontmatter = Frontmatter {
+            id: self.id.clone(),
             title: self.title.clone(),
             created_at: self.created_at,
             updated_at: self.updated_at,
             tags: self.tags.clone(),
+            checksum: match &self.checksum {
+                Some(checksum) if self.tampered => Some(checksum.clone()),
+                Some(_) => Some(content_checksum(&self.content)),
+                None => None,
+            },
+            extra: self.extra.clone(),
         };
 
         let yaml = serde_yaml::to_string(&frontmatter)
@@ -124,6 +423,59 @@ impl Mem {
     }
 }
 
+/// Checksum of mem content, as stored in the `checksum` field.
+pub fn content_checksum(content: &str) -> String {
+    format!("sha256:{}", sha256_hex(content.as_bytes()))
+}
+
+/// Whether a stored checksum does not match the content it was read with.
+pub fn is_tampered(checksum: &Option<String>, content: &str) -> bool {
+    checksum
+        .as_ref()
+        .is_some_and(|checksum| *checksum != content_checksum(content))
+}
+
+/// Number of file lines preceding the markdown content (frontmatter and delimiters).
+///
+/// Content line `n` (1-based) is file line `content_line_offset(raw) + n`.
+pub fn content_line_offset(raw: &str) -> usize {
+    let Some((_, content)) = split_frontmatter(raw) else {
+        return 0;
+    };
+    raw[..raw.len() - content.len()].matches('\n').count()
+}
+
+/// Split a file into its frontmatter YAML and the content after the
+/// closing `---`, with LF or CRLF line endings. `None` unless the file
+/// starts with `---` and the frontmatter is closed.
+pub fn split_frontmatter(raw: &str) -> Option<(&str, &str)> {
+    let rest = raw.strip_prefix("---")?;
+    let end = rest.find("\n---")?;
+    let yaml = rest[..end].trim_start_matches(['\r', '\n']);
+    // The rest of the delimiter line and any blank lines before the content
+    let content = rest[end + 4..].trim_start_matches(['\r', '\n']);
+    Some((yaml, content))
+}
+
+/// Convert free text (e.g. a heading) into a path segment: lowercase
+/// alphanumerics separated by single dashes.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-').to_string();
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,6 +493,8 @@ Hello, world!"#;
         assert_eq!(mem.title, "Test Document");
         assert_eq!(mem.content, "Hello, world!");
         assert!(mem.tags.is_empty());
+        // Mems created before ids existed have none until one is assigned
+        assert_eq!(mem.id, None);
     }
 
     #[test]
@@ -195,6 +549,28 @@ More content."#;
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_from_document() {
+        let doc = "---\r\ntitle: Imported\r\ntags: a, b\r\nstatus: draft\r\ncreated-at: 2024-05-01\r\n---\r\n\r\nBody.";
+        let mem = Mem::from_document(PathBuf::from("x"), doc).unwrap();
+        assert_eq!(mem.title, "Imported");
+        assert_eq!(mem.tags, ["a", "b"]);
+        assert_eq!(mem.field("status"), Some("draft"));
+        assert_eq!(mem.created_at.to_rfc3339(), "2024-05-01T00:00:00+00:00");
+        assert_eq!(mem.content, "Body.");
+        assert!(mem.id.is_some());
+
+        let plain = Mem::from_document(PathBuf::from("x"), "No frontmatter.").unwrap();
+        assert_eq!(
+            (plain.title.as_str(), plain.content.as_str()),
+            ("", "No frontmatter.")
+        );
+
+        let err =
+            Mem::from_document(PathBuf::from("x"), "---\nupdated-at: soon\n---\n").unwrap_err();
+        assert!(err.to_string().contains("updated-at is not a timestamp"));
+    }
+
     #[test]
     fn test_serialize_roundtrip() {
         let original = Mem::new(
@@ -207,6 +583,8 @@ More content."#;
         let serialized = original.serialize().unwrap();
         let parsed = Mem::parse(PathBuf::from("test/doc"), &serialized).unwrap();
 
+        assert_eq!(parsed.id, original.id);
+        assert!(parsed.serialize().unwrap().starts_with("---\nid: "));
         assert_eq!(parsed.title, original.title);
         assert_eq!(parsed.tags, original.tags);
         assert_eq!(parsed.content, original.content);
@@ -217,6 +595,43 @@ More content."#;
         );
     }
 
+    #[test]
+    fn test_extra_fields_roundtrip() {
+        let content = r#"---
+title: With Extras
+created-at: 2025-01-19T12:00:00Z
+updated-at: 2025-01-19T12:00:00Z
+source: https://example.com/page
+priority: 3
+---
+Body"#;
+
+        let mut mem = Mem::parse(PathBuf::from("test"), content).unwrap();
+        assert_eq!(mem.field("source"), Some("https://example.com/page"));
+        assert_eq!(mem.field("priority"), None);
+
+        mem.set_field("status", "accepted");
+        let reparsed = Mem::parse(PathBuf::from("test"), &mem.serialize().unwrap()).unwrap();
+        assert_eq!(reparsed.field("status"), Some("accepted"));
+        assert_eq!(reparsed.extra["priority"], serde_yaml::Value::from(3));
+    }
+
+    #[test]
+    fn test_pinned() {
+        let mut mem = Mem::new(PathBuf::from("test"), "Title".to_string(), String::new());
+        assert!(!mem.is_pinned());
+
+        mem.set_pinned(true);
+        let serialized = mem.serialize().unwrap();
+        assert!(serialized.contains("pinned: true"));
+        assert!(Mem::parse(PathBuf::from("test"), &serialized)
+            .unwrap()
+            .is_pinned());
+
+        mem.set_pinned(false);
+        assert!(!mem.serialize().unwrap().contains("pinned"));
+    }
+
     #[test]
     fn test_new_sets_timestamps() {
         let mem = Mem::new(
@@ -246,4 +661,95 @@ More content."#;
         assert!(mem.updated_at > original_updated);
         assert_eq!(mem.created_at.timestamp(), original_updated.timestamp());
     }
+
+    #[test]
+    fn test_size_metrics() {
+        let mem = Mem::new(
+            PathBuf::from("test"),
+            "Title".to_string(),
+            "one two  three\nfour".to_string(),
+        );
+
+        assert_eq!(mem.size(), 19);
+        assert_eq!(mem.word_count(), 4);
+        assert_eq!(mem.estimated_tokens(), 5);
+    }
+
+    #[test]
+    fn test_content_line_offset() {
+        let raw = "---\ntitle: T\ncreated-at: x\n---\n\nBody line";
+        assert_eq!(content_line_offset(raw), 5);
+        assert_eq!(raw.lines().nth(5), Some("Body line"));
+        assert_eq!(content_line_offset("no frontmatter"), 0);
+
+        let raw = "---\r\ntitle: T\r\n---\r\n\r\nBody line";
+        assert_eq!(content_line_offset(raw), 4);
+        assert_eq!(raw.lines().nth(4), Some("Body line"));
+    }
+
+    #[test]
+    fn test_parse_crlf() {
+        let raw = "---\r\ntitle: Windows\r\ncreated-at: 2025-01-19T12:00:00Z\r\nupdated-at: 2025-01-19T12:00:00Z\r\ntags: [a]\r\n---\r\nLine one\r\nLine two";
+        let mem = Mem::parse(PathBuf::from("win"), raw).unwrap();
+        assert_eq!(mem.title, "Windows");
+        assert_eq!(mem.tags, ["a"]);
+        assert_eq!(mem.content, "Line one\r\nLine two");
+        assert!(split_frontmatter("---\r\ntitle: T\r\n").is_none());
+    }
+
+    #[test]
+    fn test_path_str() {
+        let mem = Mem::new(PathBuf::from("a\\b").join("c"), "T".into(), String::new());
+        assert_eq!(mem.path_str(), "a/b/c");
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("On-call Escalation!"), "on-call-escalation");
+        assert_eq!(slugify("  API v2 "), "api-v2");
+        assert_eq!(slugify("???"), "section");
+    }
+
+    #[test]
+    fn test_split_by_headings() {
+        let mut mem = Mem::new(
+            PathBuf::from("guides/setup"),
+            "Setup".to_string(),
+            "Intro text.\n\n# Install\nRun it.\n```sh\n# not a heading\n```\n# Configure\nEdit it.\n# Install\nAgain, see [faq](../faq.md)."
+                .to_string(),
+        )
+        .with_tags(vec!["ops".to_string()]);
+        mem.checksum = Some("sha256:stale".to_string());
+        mem.tampered = true;
+
+        let (index, children) = mem.split_by_headings().unwrap();
+        let paths: Vec<_> = children.iter().map(|c| c.path.to_str().unwrap()).collect();
+        assert_eq!(
+            paths,
+            [
+                "guides/setup/install",
+                "guides/setup/configure",
+                "guides/setup/install-2"
+            ]
+        );
+        assert!(children[0].content.contains("# not a heading"));
+        assert_eq!(children[1].content, "Edit it.");
+        assert_eq!(children[1].tags, vec!["ops"]);
+        assert_eq!(children[1].created_at, mem.created_at);
+        assert_eq!(children[1].checksum, None);
+        assert!(!children[1].tampered);
+        assert_eq!(children[2].content, "Again, see [faq](../../faq.md).");
+        assert!(index.content.starts_with("Intro text."));
+        assert!(index.content.contains("- [Configure](setup/configure.md)"));
+    }
+
+    #[test]
+    fn test_split_without_headings() {
+        let mem = Mem::new(
+            PathBuf::from("flat"),
+            "Flat".to_string(),
+            "## Only subheadings".to_string(),
+        );
+        assert!(mem.split_by_headings().is_none());
+    }
 }
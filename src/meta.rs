@@ -0,0 +1,185 @@
+//! Generic frontmatter fields: reading and writing them by name, and
+//! `ls --where` conditions.
+
+use crate::mem::Mem;
+use anyhow::{anyhow, Result};
+use serde_yaml::Value;
+use std::str::FromStr;
+
+/// Fields `mem` manages itself; `meta set` refuses to change them.
+pub const BUILT_IN_FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "created-at",
+    "updated-at",
+    "tags",
+    "checksum",
+];
+
+/// Parse a field value from the command line as a YAML scalar or flow
+/// collection (`3`, `true`, `[a, b]`), falling back to a plain string.
+pub fn parse_value(text: &str) -> Value {
+    match serde_yaml::from_str::<Value>(text) {
+        Ok(Value::Null) | Ok(Value::Mapping(_)) | Ok(Value::Tagged(_)) | Err(_) => {
+            Value::String(text.to_string())
+        }
+        Ok(value) => value,
+    }
+}
+
+/// Render a field value for display: scalars as written, lists joined with
+/// commas, anything else as YAML.
+pub fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Sequence(items) => items.iter().map(display).collect::<Vec<_>>().join(", "),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+/// The values of a field as strings: one per list item, or a single value.
+/// Built-in fields are included (`tags` yields each tag).
+pub fn values(mem: &Mem, key: &str) -> Option<Vec<String>> {
+    match key {
+        "id" => mem.id.clone().map(|id| vec![id]),
+        "title" => Some(vec![mem.title.clone()]),
+        "created-at" => Some(vec![mem.created_at.to_rfc3339()]),
+        "updated-at" => Some(vec![mem.updated_at.to_rfc3339()]),
+        "tags" => (!mem.tags.is_empty()).then(|| mem.tags.clone()),
+        "checksum" => mem.checksum.clone().map(|c| vec![c]),
+        other => match mem.extra.get(other)? {
+            Value::Null => None,
+            Value::Sequence(items) => Some(items.iter().map(display).collect()),
+            value => Some(vec![display(value)]),
+        },
+    }
+}
+
+/// Set a non-built-in field.
+pub fn set(mem: &mut Mem, key: &str, value: Value) -> Result<()> {
+    check_key(key)?;
+    mem.extra.insert(key.to_string(), value);
+    Ok(())
+}
+
+/// Remove a non-built-in field, returning whether it was present.
+pub fn unset(mem: &mut Mem, key: &str) -> Result<bool> {
+    check_key(key)?;
+    Ok(mem.extra.remove(key).is_some())
+}
+
+fn check_key(key: &str) -> Result<()> {
+    if key.trim().is_empty() {
+        return Err(anyhow!("field name is empty"));
+    }
+    if BUILT_IN_FIELDS.contains(&key) {
+        return Err(anyhow!("{key} is a built-in field (use mem edit)"));
+    }
+    Ok(())
+}
+
+/// A `--where` filter on a frontmatter field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// `field=value`: some value of the field equals `value`
+    Equals(String, String),
+
+    /// `field!=value`: no value of the field equals `value`
+    NotEquals(String, String),
+
+    /// `field`: the field is set
+    Present(String),
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let condition = if let Some((key, value)) = text.split_once("!=") {
+            Condition::NotEquals(key.trim().to_string(), value.trim().to_string())
+        } else if let Some((key, value)) = text.split_once('=') {
+            Condition::Equals(key.trim().to_string(), value.trim().to_string())
+        } else {
+            Condition::Present(text.trim().to_string())
+        };
+        match &condition {
+            Condition::Equals(key, _) | Condition::NotEquals(key, _) | Condition::Present(key)
+                if key.is_empty() =>
+            {
+                Err(format!("missing field name in {text:?}"))
+            }
+            _ => Ok(condition),
+        }
+    }
+}
+
+impl Condition {
+    /// Whether a mem satisfies the condition. Values compare as text.
+    pub fn matches(&self, mem: &Mem) -> bool {
+        match self {
+            Condition::Equals(key, value) => {
+                values(mem, key).is_some_and(|values| values.contains(value))
+            }
+            Condition::NotEquals(key, value) => {
+                values(mem, key).is_none_or(|values| !values.contains(value))
+            }
+            Condition::Present(key) => values(mem, key).is_some(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn mem() -> Mem {
+        let mut mem = Mem::new(PathBuf::from("svc"), "Service".to_string(), String::new())
+            .with_tags(vec!["ops".to_string()]);
+        set(&mut mem, "priority", parse_value("high")).unwrap();
+        set(&mut mem, "tier", parse_value("2")).unwrap();
+        set(&mut mem, "teams", parse_value("[web, db]")).unwrap();
+        mem
+    }
+
+    #[test]
+    fn test_values() {
+        let mem = mem();
+        assert_eq!(mem.extra["tier"], Value::from(2));
+        assert_eq!(values(&mem, "priority"), Some(vec!["high".to_string()]));
+        assert_eq!(values(&mem, "tier"), Some(vec!["2".to_string()]));
+        assert_eq!(display(&mem.extra["teams"]), "web, db");
+        assert_eq!(values(&mem, "tags"), Some(vec!["ops".to_string()]));
+        assert_eq!(values(&mem, "missing"), None);
+        assert_eq!(parse_value("a: b"), Value::from("a: b"));
+    }
+
+    #[test]
+    fn test_set_rejects_built_ins() {
+        let mut mem = mem();
+        assert!(set(&mut mem, "title", parse_value("x")).is_err());
+        assert!(unset(&mut mem, "priority").unwrap());
+        assert!(!unset(&mut mem, "priority").unwrap());
+    }
+
+    #[test]
+    fn test_conditions() {
+        let mem = mem();
+        let check = |text: &str| text.parse::<Condition>().unwrap().matches(&mem);
+        assert!(check("priority=high"));
+        assert!(check("tier=2"));
+        assert!(check("teams=db"));
+        assert!(check("tags=ops"));
+        assert!(!check("priority!=high"));
+        assert!(check("owner!=alice"));
+        assert!(check("tier"));
+        assert!(!check("owner"));
+        assert!("=x".parse::<Condition>().is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use crate::dates::parse_date;
+use crate::mem::Mem;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Frontmatter field holding a review due date (`YYYY-MM-DD` or RFC 3339).
+pub const DUE_FIELD: &str = "due";
+
+/// A mem needing attention.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    /// Store label (empty for the default store)
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub store: String,
+    pub path: String,
+    pub title: String,
+    /// Days since last update (stale) or days past the due date (due)
+    pub days: i64,
+}
+
+/// Mems that are stale or due for review.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub stale: Vec<Entry>,
+    pub due: Vec<Entry>,
+}
+
+impl Report {
+    /// Whether there is nothing to report.
+    pub fn is_empty(&self) -> bool {
+        self.stale.is_empty() && self.due.is_empty()
+    }
+
+    /// Add the stale and due mems of one store. `stale_days` gives each mem's
+    /// threshold, or `None` for mems that never go stale.
+    pub fn evaluate(
+        &mut self,
+        label: &str,
+        mems: &[Mem],
+        now: DateTime<Utc>,
+        stale_days: impl Fn(&Mem) -> Option<u32>,
+    ) {
+        for mem in mems {
+            let entry = |days| Entry {
+                store: label.to_string(),
+                path: mem.path_str(),
+                title: mem.title.clone(),
+                days,
+            };
+
+            let stale = stale_days(mem)
+                .is_some_and(|days| now - mem.updated_at > chrono::Duration::days(i64::from(days)));
+            if stale {
+                self.stale.push(entry((now - mem.updated_at).num_days()));
+            }
+            if let Some(due) = mem.field(DUE_FIELD).and_then(parse_date) {
+                let overdue = (now.date_naive() - due).num_days();
+                if overdue >= 0 {
+                    self.due.push(entry(overdue));
+                }
+            }
+        }
+    }
+
+    /// Render as plain text suitable for a notification body.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let name = |e: &Entry| {
+            if e.store.is_empty() {
+                e.path.clone()
+            } else {
+                format!("[{}] {}", e.store, e.path)
+            }
+        };
+
+        if !self.due.is_empty() {
+            out.push_str(&format!("{} mems due for review:\n", self.due.len()));
+            for e in &self.due {
+                let when = match e.days {
+                    0 => "due today".to_string(),
+                    d => format!("{d} days overdue"),
+                };
+                out.push_str(&format!("  {}: {} ({when})\n", name(e), e.title));
+            }
+        }
+        if !self.stale.is_empty() {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("{} stale mems:\n", self.stale.len()));
+            for e in &self.stale {
+                out.push_str(&format!(
+                    "  {}: {} ({} days since update)\n",
+                    name(e),
+                    e.title,
+                    e.days
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// POST a text message to a Slack- or Teams-style incoming webhook.
+pub fn post_webhook(url: &str, text: &str) -> Result<()> {
+    ureq::post(url)
+        .send_json(serde_json::json!({ "text": text }))
+        .map_err(|e| anyhow!("webhook delivery failed: {e}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::path::PathBuf;
+
+    fn mem(path: &str, updated: DateTime<Utc>, due: Option<&str>) -> Mem {
+        let mut mem = Mem::new(PathBuf::from(path), path.to_string(), String::new());
+        mem.updated_at = updated;
+        if let Some(due) = due {
+            mem.set_field(DUE_FIELD, due);
+        }
+        mem
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let mems = vec![
+            mem("old", now - chrono::Duration::days(200), None),
+            mem("fresh", now, Some("2025-05-30")),
+            mem("later", now, Some("2025-07-01")),
+            mem("today", now, Some("2025-06-01T08:00:00Z")),
+        ];
+
+        let mut report = Report::default();
+        report.evaluate("", &mems, now, |_| Some(90));
+
+        assert_eq!(report.stale.len(), 1);
+        assert_eq!(report.stale[0].path, "old");
+        assert_eq!(report.stale[0].days, 200);
+
+        let due: Vec<_> = report
+            .due
+            .iter()
+            .map(|e| (e.path.as_str(), e.days))
+            .collect();
+        assert_eq!(due, vec![("fresh", 2), ("today", 0)]);
+
+        let text = report.to_text();
+        assert!(text.contains("2 mems due for review"));
+        assert!(text.contains("fresh: fresh (2 days overdue)"));
+        assert!(text.contains("today: today (due today)"));
+        assert!(text.contains("old: old (200 days since update)"));
+    }
+}
@@ -0,0 +1,153 @@
+//! The header `dump --with-header` writes before the mems: which store they
+//! come from, when the dump was made, how much it holds, and an outline of
+//! the hierarchy, so a model reading the dump knows what follows before it
+//! reaches the documents.
+//!
+//! The header is an output template (see [`crate::template`]); `dump.header`
+//! in the config replaces [`DEFAULT_HEADER`].
+
+use crate::template::Template;
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+/// Header written when the config doesn't set `dump.header`.
+pub const DEFAULT_HEADER: &str = "# {{ name }}
+{% if description %}
+{{ description }}
+{% endif %}
+Generated {{ generated_at }} from {{ mems }} mems (~{{ tokens }} tokens). \
+Each mem follows under a comment giving its path.
+
+## Outline
+
+{{ outline }}";
+
+/// What a header template can use for one store.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoreHeader {
+    pub name: String,
+    pub description: Option<String>,
+
+    /// Mems dumped from the store
+    pub mems: usize,
+
+    /// Estimated tokens of their content
+    pub tokens: usize,
+
+    /// The store's hierarchy (see [`crate::tree::Dir::outline`])
+    pub outline: String,
+}
+
+/// What a header template can use: totals over every store dumped, and
+/// each store as `stores`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Header {
+    /// Names of the stores, comma-separated
+    pub name: String,
+
+    /// The store's description, when only one is dumped
+    pub description: Option<String>,
+
+    /// RFC 3339
+    pub generated_at: String,
+    pub mems: usize,
+    pub tokens: usize,
+
+    /// The outline of the one store, or of each store under its name
+    pub outline: String,
+    pub stores: Vec<StoreHeader>,
+}
+
+impl Header {
+    pub fn new(stores: Vec<StoreHeader>, generated_at: DateTime<Utc>) -> Header {
+        let names: Vec<&str> = stores.iter().map(|s| s.name.as_str()).collect();
+        let outline = match stores.as_slice() {
+            [store] => store.outline.clone(),
+            _ => stores
+                .iter()
+                .map(|store| {
+                    let mut outline = format!("- {}/ ({} mems)\n", store.name, store.mems);
+                    for line in store.outline.lines() {
+                        outline.push_str(&format!("  {line}\n"));
+                    }
+                    outline
+                })
+                .collect(),
+        };
+        Header {
+            name: names.join(", "),
+            description: match stores.as_slice() {
+                [store] => store.description.clone(),
+                _ => None,
+            },
+            generated_at: generated_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            mems: stores.iter().map(|s| s.mems).sum(),
+            tokens: stores.iter().map(|s| s.tokens).sum(),
+            outline,
+            stores,
+        }
+    }
+
+    /// Render with `template` (or [`DEFAULT_HEADER`]), ending in a blank
+    /// line to set it apart from the first mem.
+    pub fn render(&self, template: Option<&str>) -> Result<String> {
+        let template = Template::new(template.unwrap_or(DEFAULT_HEADER).to_string())?;
+        let mut header = template.render(self)?.trim_end().to_string();
+        header.push_str("\n\n");
+        Ok(header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str, description: Option<&str>) -> StoreHeader {
+        StoreHeader {
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            mems: 2,
+            tokens: 30,
+            outline: "- ops/ (2 mems)\n  - deploy: Deploy\n".to_string(),
+        }
+    }
+
+    fn at() -> DateTime<Utc> {
+        "2026-03-01T09:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn test_default_header() {
+        let header = Header::new(vec![store("billing", Some("Payments"))], at());
+        assert_eq!(
+            header.render(None).unwrap(),
+            "# billing\n\nPayments\n\n\
+             Generated 2026-03-01T09:00:00Z from 2 mems (~30 tokens). \
+             Each mem follows under a comment giving its path.\n\n\
+             ## Outline\n\n\
+             - ops/ (2 mems)\n  - deploy: Deploy\n\n"
+        );
+    }
+
+    #[test]
+    fn test_several_stores() {
+        let header = Header::new(
+            vec![store("billing", Some("Payments")), store("search", None)],
+            at(),
+        );
+        assert_eq!(header.name, "billing, search");
+        assert_eq!(header.description, None);
+        assert_eq!((header.mems, header.tokens), (4, 60));
+        assert!(header.outline.starts_with(
+            "- billing/ (2 mems)\n  - ops/ (2 mems)\n    - deploy: Deploy\n- search/"
+        ));
+
+        let custom = "{% for store in stores %}{{ store.name }}: {{ store.mems }}\n{% endfor %}";
+        assert_eq!(
+            header.render(Some(custom)).unwrap(),
+            "billing: 2\nsearch: 2\n\n"
+        );
+        assert!(header.render(Some("{{ name ")).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! Progress reporting for bulk operations: import, export, cache builds, and
+//! lint. Library code reports through [`Progress`], so each front end can
+//! show it its own way; the CLI draws a [`Bar`] on a terminal.
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Receives progress from a bulk operation, one step at a time.
+pub trait Progress {
+    /// A step over `total` items begins, e.g. `("Importing", 400)`.
+    fn start(&self, label: &str, total: usize);
+
+    /// `item` (usually a mem path) is done.
+    fn advance(&self, item: &str);
+
+    /// The step is over.
+    fn finish(&self);
+}
+
+/// Reports nothing.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Silent;
+
+impl Progress for Silent {
+    fn start(&self, _label: &str, _total: usize) {}
+    fn advance(&self, _item: &str) {}
+    fn finish(&self) {}
+}
+
+/// Columns of the bar itself.
+const BAR_WIDTH: usize = 24;
+
+/// Characters of the current item shown after the bar.
+const ITEM_WIDTH: usize = 40;
+
+/// Least time between redraws.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A progress bar redrawn in place on stderr, which should be a terminal.
+#[derive(Debug, Default)]
+pub struct Bar {
+    state: RefCell<BarState>,
+}
+
+#[derive(Debug, Default)]
+struct BarState {
+    label: String,
+    total: usize,
+    done: usize,
+    drawn_at: Option<Instant>,
+}
+
+impl Bar {
+    pub fn new() -> Bar {
+        Bar::default()
+    }
+}
+
+impl Progress for Bar {
+    fn start(&self, label: &str, total: usize) {
+        *self.state.borrow_mut() = BarState {
+            label: label.to_string(),
+            total,
+            ..BarState::default()
+        };
+    }
+
+    fn advance(&self, item: &str) {
+        let mut state = self.state.borrow_mut();
+        state.done += 1;
+        let due = state
+            .drawn_at
+            .is_none_or(|at| at.elapsed() >= REDRAW_INTERVAL);
+        if due || state.done == state.total {
+            state.drawn_at = Some(Instant::now());
+            let line = render(&state.label, state.done, state.total, item);
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[K{line}");
+            let _ = stderr.flush();
+        }
+    }
+
+    fn finish(&self) {
+        if self.state.borrow().drawn_at.is_some() {
+            eprint!("\r\x1b[K");
+        }
+        *self.state.borrow_mut() = BarState::default();
+    }
+}
+
+/// One line of a progress bar, e.g. `Importing [======    ] 120/400 notes/a`.
+fn render(label: &str, done: usize, total: usize, item: &str) -> String {
+    let filled = (done * BAR_WIDTH).checked_div(total).unwrap_or(BAR_WIDTH);
+    let filled = filled.min(BAR_WIDTH);
+    let chars = item.chars().count();
+    let item = if chars > ITEM_WIDTH {
+        let tail: String = item.chars().skip(chars - (ITEM_WIDTH - 3)).collect();
+        format!("...{tail}")
+    } else {
+        item.to_string()
+    };
+    format!(
+        "{label} [{}{}] {done}/{total} {item}",
+        "=".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render("Importing", 6, 24, "notes/a"),
+            "Importing [======                  ] 6/24 notes/a"
+        );
+        assert_eq!(
+            render("Linting", 0, 0, ""),
+            format!("Linting [{}] 0/0 ", "=".repeat(BAR_WIDTH))
+        );
+        let long = render("Exporting", 1, 1, &"x/".repeat(40));
+        assert!(long.ends_with(&format!(" 1/1 .../{}", "x/".repeat(18))));
+    }
+}
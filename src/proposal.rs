@@ -0,0 +1,144 @@
+//! Pending edits to mems that a person reviews before they land
+//! (`mem propose`, `mem accept`, `mem reject`).
+
+use crate::id;
+use crate::mem::{content_checksum, Mem};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory inside .mems/ holding one JSON file per pending proposal.
+pub const PROPOSALS_DIR: &str = ".proposals";
+
+/// A proposed replacement for a mem's content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Proposal {
+    /// ULID, so proposals sort by creation time
+    pub id: String,
+    pub path: String,
+    pub content: String,
+
+    /// Checksum of the mem's content when the proposal was made, to detect
+    /// edits that landed since
+    pub base_checksum: String,
+
+    pub created_at: DateTime<Utc>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl Proposal {
+    /// Propose `content` as the new content of `mem`.
+    pub fn new(mem: &Mem, content: String) -> Self {
+        Self {
+            id: id::generate(),
+            path: mem.path_str(),
+            content,
+            base_checksum: content_checksum(&mem.content),
+            created_at: Utc::now(),
+            author: None,
+            message: None,
+        }
+    }
+
+    /// Whether the mem has been edited since the proposal was made.
+    pub fn is_outdated(&self, mem: &Mem) -> bool {
+        content_checksum(&mem.content) != self.base_checksum
+    }
+
+    fn file(root: &Path, id: &str) -> PathBuf {
+        root.join(PROPOSALS_DIR).join(format!("{id}.json"))
+    }
+
+    pub fn save(&self, root: &Path) -> Result<()> {
+        fs::create_dir_all(root.join(PROPOSALS_DIR))
+            .context("failed to create proposals directory")?;
+        fs::write(
+            Self::file(root, &self.id),
+            serde_json::to_string_pretty(self)?,
+        )
+        .context("failed to write proposal")
+    }
+
+    /// Delete the proposal once accepted or rejected.
+    pub fn remove(&self, root: &Path) -> Result<()> {
+        fs::remove_file(Self::file(root, &self.id)).context("failed to remove proposal")
+    }
+}
+
+/// Pending proposals, oldest first.
+pub fn list(root: &Path) -> Result<Vec<Proposal>> {
+    let dir = root.join(PROPOSALS_DIR);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut proposals = Vec::new();
+    for entry in fs::read_dir(&dir).context("failed to read proposals directory")? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == "json") {
+            let text = fs::read_to_string(&path).context("failed to read proposal")?;
+            let proposal: Proposal = serde_json::from_str(&text)
+                .with_context(|| format!("invalid proposal {}", path.display()))?;
+            proposals.push(proposal);
+        }
+    }
+    proposals.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(proposals)
+}
+
+/// The proposal whose id starts with `prefix` (case-insensitive), which must
+/// be unambiguous.
+pub fn find(root: &Path, prefix: &str) -> Result<Proposal> {
+    let prefix = prefix.to_ascii_uppercase();
+    let mut matches: Vec<Proposal> = list(root)?
+        .into_iter()
+        .filter(|p| !prefix.is_empty() && p.id.starts_with(&prefix))
+        .collect();
+    match matches.len() {
+        0 => Err(anyhow!("no proposal {prefix}")),
+        1 => Ok(matches.remove(0)),
+        n => Err(anyhow!(
+            "{n} proposals start with {prefix}; use more of the id"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_find_remove() {
+        let temp = TempDir::new().unwrap();
+        let mut mem = Mem::new(
+            PathBuf::from("runbooks/db"),
+            "DB".to_string(),
+            "old".to_string(),
+        );
+        let proposal = Proposal::new(&mem, "new".to_string());
+        proposal.save(temp.path()).unwrap();
+        let other = Proposal::new(&mem, "newer".to_string());
+        other.save(temp.path()).unwrap();
+
+        let found = find(temp.path(), &proposal.id.to_lowercase()).unwrap();
+        assert_eq!(found, proposal);
+        assert_eq!(list(temp.path()).unwrap().len(), 2);
+        assert!(find(temp.path(), "").is_err());
+        assert!(find(temp.path(), "Z").is_err());
+
+        assert!(!proposal.is_outdated(&mem));
+        mem.content = "edited".to_string();
+        assert!(proposal.is_outdated(&mem));
+
+        proposal.remove(temp.path()).unwrap();
+        assert_eq!(list(temp.path()).unwrap(), vec![other]);
+    }
+}
@@ -0,0 +1,197 @@
+//! Keyword queries for `mem find`: words that must all appear, quoted
+//! phrases, `OR` between alternatives, and `-word` exclusions, e.g.
+//! `postgres migration -legacy` or `"disaster recovery" OR failover`.
+//!
+//! Terms match case-insensitively anywhere in the text, so `postgres` also
+//! finds `PostgreSQL`, and regardless of Unicode normalization form. Common
+//! words like `the` or `how` are ignored unless quoted, or unless the query
+//! has nothing else.
+
+use crate::semantic::STOPWORDS;
+use crate::text;
+
+/// A parsed search query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    /// Alternatives separated by `OR`, each a list of case-folded terms
+    /// that must all appear
+    alternatives: Vec<Vec<String>>,
+
+    /// Case-folded terms that must not appear
+    excluded: Vec<String>,
+}
+
+/// A word or phrase of a query as written.
+struct Token {
+    text: String,
+    quoted: bool,
+    negated: bool,
+}
+
+fn tokens(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let negated = c == '-';
+        if negated {
+            chars.next();
+        }
+        let quoted = chars.peek() == Some(&'"');
+        let text: String = if quoted {
+            chars.next();
+            chars.by_ref().take_while(|&c| c != '"').collect()
+        } else {
+            let mut text = String::new();
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                text.push(c);
+            }
+            text
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+        tokens.push(Token {
+            text,
+            quoted,
+            negated,
+        });
+    }
+    tokens
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Query {
+        let mut parsed = Query::default();
+        let mut alternative: Vec<Token> = Vec::new();
+        let mut alternatives = Vec::new();
+        for token in tokens(query) {
+            if token.text == "OR" && !token.quoted && !token.negated {
+                alternatives.push(std::mem::take(&mut alternative));
+            } else if token.negated {
+                parsed.excluded.push(text::fold(&token.text));
+            } else {
+                alternative.push(token);
+            }
+        }
+        alternatives.push(alternative);
+
+        for alternative in alternatives {
+            let is_stopword =
+                |t: &Token| !t.quoted && STOPWORDS.contains(&text::fold(&t.text).as_str());
+            let keep_all = alternative.iter().all(is_stopword);
+            let terms: Vec<String> = alternative
+                .iter()
+                .filter(|t| keep_all || !is_stopword(t))
+                .map(|t| text::fold(&t.text))
+                .collect();
+            if !terms.is_empty() {
+                parsed.alternatives.push(terms);
+            }
+        }
+        parsed
+    }
+
+    /// Whether `text` matches: one alternative has all its terms in it, and
+    /// no excluded term is. A query with no terms matches everything not
+    /// excluded.
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text::fold(text);
+        let included = self.alternatives.is_empty()
+            || self
+                .alternatives
+                .iter()
+                .any(|terms| terms.iter().all(|t| text.contains(t.as_str())));
+        included && !self.excluded.iter().any(|t| text.contains(t.as_str()))
+    }
+
+    /// How well a match fits, from 0 to 1: the share of the query's terms
+    /// found, a term in the title counting twice what one in the content
+    /// does. Used to rank matches from several stores against each other.
+    pub fn score(&self, title: &str, content: &str) -> f32 {
+        let terms = self.terms();
+        if terms.is_empty() {
+            return 0.0;
+        }
+        let title = text::fold(title);
+        let content = text::fold(content);
+        let points: usize = terms
+            .iter()
+            .map(|t| 2 * usize::from(title.contains(t)) + usize::from(content.contains(t)))
+            .sum();
+        points as f32 / (3 * terms.len()) as f32
+    }
+
+    /// Terms a match may contain, for highlighting.
+    pub fn terms(&self) -> Vec<&str> {
+        let mut terms: Vec<&str> = self
+            .alternatives
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        terms.sort_unstable();
+        terms.dedup();
+        terms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let query = Query::parse(r#"how to run the "disaster recovery" drill OR failover -legacy"#);
+        assert_eq!(
+            query.alternatives,
+            vec![
+                vec![
+                    "run".to_string(),
+                    "disaster recovery".into(),
+                    "drill".into()
+                ],
+                vec!["failover".to_string()],
+            ]
+        );
+        assert_eq!(query.excluded, ["legacy"]);
+        assert_eq!(
+            Query::parse("the").alternatives,
+            vec![vec!["the".to_string()]]
+        );
+        assert_eq!(Query::parse("  ").alternatives, Vec::<Vec<String>>::new());
+    }
+
+    #[test]
+    fn test_score() {
+        let query = Query::parse("postgres migration");
+        assert_eq!(query.score("Postgres migration", "postgres migration"), 1.0);
+        assert_eq!(query.score("Postgres", "a migration"), 0.5);
+        assert_eq!(query.score("Notes", "postgres"), 1.0 / 6.0);
+        assert_eq!(Query::parse("").score("a", "b"), 0.0);
+    }
+
+    #[test]
+    fn test_matches() {
+        let query = Query::parse("postgres migration -legacy");
+        assert!(query.matches("PostgreSQL schema migration"));
+        assert!(!query.matches("postgres only"));
+        assert!(!query.matches("legacy postgres migration"));
+
+        let query = Query::parse(r#""on call" OR pager"#);
+        assert!(query.matches("The on call rota"));
+        assert!(query.matches("Pager duty"));
+        assert!(!query.matches("call on me"));
+
+        let query = Query::parse("caf\u{e9} -STRASSE");
+        assert!(query.matches("Cafe\u{301} opening hours"));
+        assert!(!query.matches("Café on the Straße"));
+
+        assert!(Query::parse("").matches("anything"));
+        assert!(!Query::parse("-draft").matches("a draft"));
+        assert_eq!(Query::parse("b a OR a").terms(), ["a", "b"]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Redirect stubs left behind by `mem mv --redirect`: a mem at the old path
+//! whose `redirect-to` field names the new one, so references to the old path
+//! from outside the store still lead somewhere. `mem show` follows them,
+//! `mem lint` reports chains and dangling ones, and `mem gc --redirects`
+//! removes those nothing links to.
+
+use crate::id::ID_PREFIX;
+use crate::links;
+use crate::mem::Mem;
+use crate::storage::Storage;
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Frontmatter field naming where a stub redirects to.
+pub const REDIRECT_FIELD: &str = "redirect-to";
+
+/// Redirects followed before giving up.
+const MAX_HOPS: usize = 16;
+
+/// Where `mem` redirects to, if it is a stub.
+pub fn target(mem: &Mem) -> Option<&str> {
+    mem.field(REDIRECT_FIELD)
+}
+
+/// A stub at `from` redirecting to `moved`, which links to it for readers
+/// of the file itself.
+pub fn stub(from: &str, moved: &Mem) -> Mem {
+    let to = moved.path_str();
+    let content = format!(
+        "Moved to [{}]({}).\n",
+        moved.title,
+        links::relative_target(from, &to)
+    );
+    let mut mem = Mem::new(PathBuf::from(from), moved.title.clone(), content);
+    mem.set_field(REDIRECT_FIELD, to);
+    mem
+}
+
+/// `stub` pointed at `moved` instead, keeping its id and creation time.
+pub fn retarget(stub: &Mem, moved: &Mem) -> Mem {
+    let mut mem = self::stub(&stub.path_str(), moved);
+    mem.id = stub.id.clone();
+    mem.created_at = stub.created_at;
+    mem
+}
+
+/// The mem `mem` redirects to, following stubs until one isn't, with the
+/// paths of the stubs passed through.
+pub fn follow(storage: &Storage, mut mem: Mem) -> Result<(Mem, Vec<String>)> {
+    let mut stubs: Vec<String> = Vec::new();
+    while let Some(to) = target(&mem).map(str::to_string) {
+        let from = mem.path_str();
+        stubs.push(from.clone());
+        if stubs.contains(&to) || stubs.len() > MAX_HOPS {
+            return Err(anyhow!("redirect loop: {} -> {to}", stubs.join(" -> ")));
+        }
+        if !storage.exists(&to) {
+            return Err(anyhow!(
+                "broken redirect: {from} redirects to {to}, which does not exist"
+            ));
+        }
+        mem = storage.read_mem(&to)?;
+    }
+    Ok((mem, stubs))
+}
+
+/// Stubs in `mems` that no mem links to, by path or by id, with where they
+/// redirect.
+pub fn unlinked(mems: &[Mem]) -> Vec<(String, String)> {
+    let linked: HashSet<String> = mems.iter().flat_map(links::targets).collect();
+    mems.iter()
+        .filter_map(|mem| {
+            let to = target(mem)?;
+            let path = mem.path_str();
+            let by_id = mem
+                .id
+                .as_ref()
+                .map(|id| format!("{ID_PREFIX}{}", id.to_uppercase()));
+            let is_linked = linked.contains(&path) || by_id.is_some_and(|id| linked.contains(&id));
+            (!is_linked).then(|| (path, to.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn mem(path: &str, content: &str) -> Mem {
+        Mem::new(PathBuf::from(path), path.to_string(), content.to_string())
+    }
+
+    #[test]
+    fn test_stub_and_follow() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let moved = mem("guides/setup", "Steps");
+        storage.write_mem(&moved).unwrap();
+
+        let stub = stub("setup", &moved);
+        assert_eq!(target(&stub), Some("guides/setup"));
+        assert_eq!(stub.content, "Moved to [guides/setup](guides/setup.md).\n");
+        assert_ne!(stub.id, moved.id);
+        storage.write_mem(&stub).unwrap();
+        storage.write_mem(&super::stub("old/setup", &stub)).unwrap();
+        let retargeted = retarget(&stub, &mem("docs/setup", ""));
+        assert_eq!(retargeted.id, stub.id);
+        assert_eq!(target(&retargeted), Some("docs/setup"));
+        assert_eq!(
+            retargeted.content,
+            "Moved to [docs/setup](docs/setup.md).\n"
+        );
+
+        let (found, stubs) = follow(&storage, storage.read_mem("old/setup").unwrap()).unwrap();
+        assert_eq!(found.content, "Steps");
+        assert_eq!(stubs, ["old/setup", "setup"]);
+        let (found, stubs) = follow(&storage, moved.clone()).unwrap();
+        assert_eq!(
+            (found.path_str(), stubs.len()),
+            ("guides/setup".to_string(), 0)
+        );
+
+        storage.delete_mem("guides/setup").unwrap();
+        let err = follow(&storage, stub.clone()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "broken redirect: setup redirects to guides/setup, which does not exist"
+        );
+
+        let mut looped = mem("a", "");
+        looped.set_field(REDIRECT_FIELD, "a");
+        let err = follow(&storage, looped).unwrap_err();
+        assert_eq!(err.to_string(), "redirect loop: a -> a");
+    }
+
+    #[test]
+    fn test_unlinked() {
+        let target_mem = mem("new", "");
+        let linked = stub("linked", &target_mem);
+        let by_id = stub("by-id", &target_mem);
+        let lonely = stub("lonely", &target_mem);
+        let mems = vec![
+            mem(
+                "index",
+                &format!("[a](linked.md) [[id:{}]]", by_id.id.clone().unwrap()),
+            ),
+            target_mem,
+            linked,
+            by_id,
+            lonely,
+        ];
+        assert_eq!(unlinked(&mems), [("lonely".to_string(), "new".to_string())]);
+    }
+}
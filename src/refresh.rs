@@ -0,0 +1,196 @@
+//! Bringing mems back in line with where they came from (`mem refresh`).
+//!
+//! A mem's `source` field names where it came from: a GitHub issue or pull
+//! request, a web page, or a file. Whenever a mem is written from its source
+//! it records `synced-at` and `synced-hash`, a hash of the content as
+//! written, so a later refresh can tell whether the mem was edited since and
+//! leave those edits alone.
+
+use crate::github;
+use crate::hash::sha256_hex;
+use crate::html;
+use crate::mem::Mem;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// Hex digits of the content hash kept in `synced-hash`.
+const HASH_LEN: usize = 16;
+
+/// Where a mem came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// A GitHub issue or pull request
+    Github(github::Repo, u64),
+
+    /// A web page
+    Url(String),
+
+    /// A file: absolute, or relative to the directory holding the store
+    File(PathBuf),
+}
+
+impl Source {
+    /// The source named by a mem's `source` field, if it is one that can be
+    /// fetched again (imports from chat exports name only the tool).
+    pub fn of(mem: &Mem) -> Option<Source> {
+        Source::parse(mem.field("source")?)
+    }
+
+    pub fn parse(source: &str) -> Option<Source> {
+        if let Some(path) = source.strip_prefix("file://") {
+            return Some(Source::File(PathBuf::from(path)));
+        }
+        if source.starts_with("http://") || source.starts_with("https://") {
+            return Some(match github::parse_url(source) {
+                Some((repo, number)) => Source::Github(repo, number),
+                None => Source::Url(source.to_string()),
+            });
+        }
+        if let Some(rest) = source.strip_prefix("~/") {
+            let home = std::env::var_os("HOME")?;
+            return Some(Source::File(Path::new(&home).join(rest)));
+        }
+        if ["/", "./", "../"].iter().any(|p| source.starts_with(p)) {
+            return Some(Source::File(PathBuf::from(source)));
+        }
+        None
+    }
+
+    /// `mem` with its content, and whatever else the source decides, fetched
+    /// again. Relative file sources are resolved against `base`, and file
+    /// sources outside it (`~/.ssh/id_rsa`, `../../etc/passwd`) are refused
+    /// unless `allow_outside`, so a shared mem can't pull in private files.
+    pub fn fetch(
+        &self,
+        mem: &Mem,
+        base: &Path,
+        now: DateTime<Utc>,
+        allow_outside: bool,
+    ) -> Result<Mem> {
+        let mut fresh = match self {
+            Source::Github(repo, number) => github::Client::from_env()
+                .thread(repo, *number)?
+                .refresh(mem, now),
+            Source::Url(url) => {
+                let body = ureq::get(url)
+                    .call()
+                    .map_err(|e| anyhow!("failed to fetch {url}: {e}"))?
+                    .into_string()
+                    .map_err(|e| anyhow!("failed to read {url}: {e}"))?;
+                let mut fresh = mem.clone();
+                fresh.content = html::to_markdown(&body, url).markdown;
+                fresh
+            }
+            Source::File(path) => {
+                let path = base.join(path);
+                let resolved = path
+                    .canonicalize()
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                if !allow_outside && !resolved.starts_with(base.canonicalize()?) {
+                    return Err(anyhow!(
+                        "{} is outside {} (use --allow-outside to read it)",
+                        path.display(),
+                        base.display()
+                    ));
+                }
+                let mut fresh = mem.clone();
+                fresh.content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                fresh
+            }
+        };
+        mark_synced(&mut fresh, now);
+        Ok(fresh)
+    }
+}
+
+fn content_hash(content: &str) -> String {
+    sha256_hex(content.as_bytes())[..HASH_LEN].to_string()
+}
+
+/// Record that `mem`'s content is what its source had at `now`.
+pub fn mark_synced(mem: &mut Mem, now: DateTime<Utc>) {
+    mem.set_field("synced-at", now.to_rfc3339());
+    mem.set_field("synced-hash", content_hash(&mem.content));
+}
+
+/// Whether `mem`'s content changed since it was last synced. A mem with no
+/// record of its sync counts as edited, since there is no telling.
+pub fn is_edited(mem: &Mem) -> bool {
+    mem.field("synced-hash") != Some(content_hash(&mem.content).as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Source::parse("https://github.com/acme/api/issues/3"),
+            Some(Source::Github("acme/api".parse().unwrap(), 3))
+        );
+        assert_eq!(
+            Source::parse("https://example.com/a"),
+            Some(Source::Url("https://example.com/a".to_string()))
+        );
+        assert_eq!(
+            Source::parse("file:///tmp/notes.md"),
+            Some(Source::File(PathBuf::from("/tmp/notes.md")))
+        );
+        assert_eq!(
+            Source::parse("./docs/adr.md"),
+            Some(Source::File(PathBuf::from("./docs/adr.md")))
+        );
+        assert_eq!(Source::parse("slack"), None);
+    }
+
+    #[test]
+    fn test_file_refresh() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("adr.md"), "Use Postgres.\n").unwrap();
+
+        let mut mem = Mem::new(PathBuf::from("adr"), "ADR".to_string(), "old".to_string());
+        mem.set_field("source", "./adr.md");
+        assert!(is_edited(&mem));
+        mark_synced(&mut mem, at("2024-01-01T00:00:00Z"));
+        assert!(!is_edited(&mem));
+
+        let source = Source::of(&mem).unwrap();
+        let fresh = source
+            .fetch(&mem, dir.path(), at("2024-02-01T00:00:00Z"), false)
+            .unwrap();
+        assert_eq!(fresh.content, "Use Postgres.\n");
+        assert_eq!(fresh.title, "ADR");
+        assert_eq!(fresh.field("synced-at"), Some("2024-02-01T00:00:00+00:00"));
+        assert!(!is_edited(&fresh));
+
+        mem.content.push_str(" edited");
+        assert!(is_edited(&mem));
+        assert!(Source::File(PathBuf::from("missing.md"))
+            .fetch(&mem, dir.path(), Utc::now(), false)
+            .is_err());
+    }
+
+    #[test]
+    fn test_file_outside_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        std::fs::write(dir.path().join("secret"), "key\n").unwrap();
+        let mem = Mem::new(PathBuf::from("s"), "S".to_string(), String::new());
+
+        for source in ["../secret", dir.path().join("secret").to_str().unwrap()] {
+            let source = Source::File(PathBuf::from(source));
+            let err = source.fetch(&mem, &project, Utc::now(), false).unwrap_err();
+            assert!(err.to_string().contains("is outside"), "{err}");
+            let fresh = source.fetch(&mem, &project, Utc::now(), true).unwrap();
+            assert_eq!(fresh.content, "key\n");
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::id;
+use crate::storage::Storage;
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Marker that references a mem from a source comment, e.g.
+/// `// See mem:arch/decisions/adr-001` or `# mem:id:01J9...`.
+pub const MARKER: &str = "mem:";
+
+/// Directories never scanned for references (hidden directories are also skipped).
+const SKIP_DIRS: &[&str] = &["target", "node_modules"];
+
+/// Tokens that start a comment in common languages.
+const COMMENT_STARTS: &[&str] = &["//", "/*", "#", "--", ";", "<!--", "%", "\"\"\"", "* "];
+
+/// A `mem:` marker in a source file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CodeRef {
+    pub file: PathBuf,
+
+    /// 1-based line number
+    pub line: usize,
+
+    /// Mem path or `id:<ULID>`
+    pub target: String,
+}
+
+/// Why a reference does not check out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Problem {
+    Missing,
+    Archived,
+    Stale,
+}
+
+impl Problem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Problem::Missing => "not found",
+            Problem::Archived => "archived",
+            Problem::Stale => "stale",
+        }
+    }
+}
+
+/// Find `mem:` markers in comments of every text file under `src`.
+pub fn scan(src: &Path) -> Result<Vec<CodeRef>> {
+    let mut refs = Vec::new();
+    if src.is_file() {
+        scan_file(src, &mut refs);
+    } else {
+        scan_dir(src, &mut refs)?;
+    }
+    Ok(refs)
+}
+
+fn scan_dir(dir: &Path, refs: &mut Vec<CodeRef>) -> Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("failed to read {}", dir.display()))?
+        .collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if path.is_dir() {
+            if !name.starts_with('.') && !SKIP_DIRS.contains(&name.as_str()) {
+                scan_dir(&path, refs)?;
+            }
+        } else {
+            scan_file(&path, refs);
+        }
+    }
+    Ok(())
+}
+
+/// Collect markers from one file, skipping files that aren't UTF-8 text.
+fn scan_file(file: &Path, refs: &mut Vec<CodeRef>) {
+    let Ok(text) = fs::read_to_string(file) else {
+        return;
+    };
+    for (i, line) in text.lines().enumerate() {
+        for target in extract(line) {
+            refs.push(CodeRef {
+                file: file.to_path_buf(),
+                line: i + 1,
+                target,
+            });
+        }
+    }
+}
+
+/// Mem references in the comment part of a line.
+pub fn extract(line: &str) -> Vec<String> {
+    let Some(comment) = COMMENT_STARTS
+        .iter()
+        .filter_map(|c| line.find(c))
+        .min()
+        .map(|start| &line[start..])
+    else {
+        return Vec::new();
+    };
+
+    let mut targets = Vec::new();
+    let mut rest = comment;
+    while let Some(pos) = rest.find(MARKER) {
+        let preceded_by_word = rest[..pos]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_');
+        rest = &rest[pos + MARKER.len()..];
+        if preceded_by_word {
+            continue;
+        }
+
+        let end = rest
+            .find(|c: char| !(c.is_alphanumeric() || "-_./:".contains(c)))
+            .unwrap_or(rest.len());
+        // Trailing punctuation belongs to the sentence, not the path
+        let target = rest[..end].trim_end_matches(['.', ':', '/']);
+        if !target.is_empty() {
+            targets.push(target.to_string());
+        }
+        rest = &rest[end..];
+    }
+    targets
+}
+
+/// The mem path a reference points at, if the mem exists (and isn't archived).
+pub fn resolve(storage: &Storage, target: &str) -> Result<Option<String>> {
+    match id::parse_reference(target) {
+        Some(id) => storage.path_for_id(id),
+        None => Ok(storage.exists(target).then(|| target.to_string())),
+    }
+}
+
+/// Check a reference, optionally treating archived mems and mems not updated
+/// within `stale_days` as problems.
+pub fn check(
+    storage: &Storage,
+    target: &str,
+    allow_archived: bool,
+    stale_days: Option<u32>,
+) -> Result<Option<Problem>> {
+    let Some(path) = resolve(storage, target)? else {
+        // Archived mems are only found by path; ids are not indexed in the archive
+        let archived = id::parse_reference(target).is_none() && storage.is_archived(target);
+        return Ok(match (archived, allow_archived) {
+            (true, true) => None,
+            (true, false) => Some(Problem::Archived),
+            (false, _) => Some(Problem::Missing),
+        });
+    };
+
+    if let Some(days) = stale_days {
+        let mem = storage.read_mem(&path)?;
+        if Utc::now() - mem.updated_at > Duration::days(i64::from(days)) {
+            return Ok(Some(Problem::Stale));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::Mem;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_extract() {
+        assert_eq!(
+            extract("let x = 1; // see mem:arch/adr-001."),
+            vec!["arch/adr-001"]
+        );
+        assert_eq!(
+            extract("# mem:id:01ARZ3NDEKTSV4RRFFQ69G5FAV and mem:guides/setup"),
+            vec!["id:01ARZ3NDEKTSV4RRFFQ69G5FAV", "guides/setup"]
+        );
+        // Only comments count, and the marker must start a word
+        assert!(extract("let s = \"mem:not/a/comment\";").is_empty());
+        assert!(extract("// xmem:nope").is_empty());
+    }
+
+    #[test]
+    fn test_scan_and_check() {
+        let temp = TempDir::new().unwrap();
+        let mems_dir = temp.path().join(".mems");
+        fs::create_dir(&mems_dir).unwrap();
+        let storage = Storage::new(mems_dir);
+
+        let mut old = Mem::new(PathBuf::from("old"), "Old".to_string(), "x".to_string());
+        old.updated_at = Utc::now() - Duration::days(400);
+        storage.write_mem(&old).unwrap();
+        storage
+            .write_mem(&Mem::new(
+                PathBuf::from("gone"),
+                "Gone".to_string(),
+                "x".to_string(),
+            ))
+            .unwrap();
+        storage.archive_mem("gone", None).unwrap();
+
+        let src = temp.path().join("src");
+        fs::create_dir_all(src.join("target")).unwrap();
+        fs::write(src.join("main.rs"), "fn main() {}\n// mem:old mem:gone\n").unwrap();
+        fs::write(src.join("target/skip.rs"), "// mem:skipped\n").unwrap();
+
+        let refs = scan(&src).unwrap();
+        assert_eq!(
+            refs.iter()
+                .map(|r| (r.line, r.target.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(2, "old"), (2, "gone")]
+        );
+
+        assert_eq!(check(&storage, "old", false, None).unwrap(), None);
+        assert_eq!(
+            check(&storage, "old", false, Some(90)).unwrap(),
+            Some(Problem::Stale)
+        );
+        assert_eq!(
+            check(&storage, "gone", false, None).unwrap(),
+            Some(Problem::Archived)
+        );
+        assert_eq!(check(&storage, "gone", true, None).unwrap(), None);
+        assert_eq!(
+            check(&storage, "nope", true, None).unwrap(),
+            Some(Problem::Missing)
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::links;
+use crate::mem::Mem;
+use crate::semantic::{self, EmbeddingIndex};
+use crate::usage::Usage;
+use std::collections::{HashMap, HashSet};
+
+/// Weight of tag overlap in the combined score.
+const TAG_WEIGHT: f32 = 0.3;
+
+/// Weight of link proximity in the combined score.
+const LINK_WEIGHT: f32 = 0.3;
+
+/// Weight of content similarity in the combined score.
+const CONTENT_WEIGHT: f32 = 0.4;
+
+/// Share of the combined score given to co-access when usage is tracked;
+/// the other weights scale down to make room.
+const USAGE_WEIGHT: f32 = 0.2;
+
+/// A candidate related mem with its score breakdown.
+#[derive(Debug, Clone)]
+pub struct Relation {
+    /// Index into the mems slice
+    pub index: usize,
+
+    /// Combined weighted score in [0, 1]
+    pub score: f32,
+
+    /// Jaccard overlap of tags
+    pub tags: f32,
+
+    /// 1.0 for a direct link either way, 0.5 for a shared neighbor
+    pub links: f32,
+
+    /// Embedding cosine similarity
+    pub content: f32,
+
+    /// How often the two are shown together (see [`Usage::co_access`]), 0
+    /// without usage tracking
+    pub usage: f32,
+
+    /// Whether the two mems already link to each other
+    pub linked: bool,
+}
+
+/// Rank mems related to `mems[target]`, best first, dropping zero scores.
+/// With `usage`, mems often shown together with the target rank higher.
+///
+/// `index` must already hold vectors for the mems (see [`EmbeddingIndex::update`]).
+pub fn related(
+    target: usize,
+    mems: &[Mem],
+    index: &EmbeddingIndex,
+    usage: Option<&Usage>,
+) -> Vec<Relation> {
+    let paths: Vec<String> = mems.iter().map(|m| m.path_str()).collect();
+    let known: HashSet<&str> = paths.iter().map(String::as_str).collect();
+
+    // Outgoing internal links per mem, restricted to mems that exist
+    let outgoing: Vec<HashSet<String>> = mems
+        .iter()
+        .map(|m| {
+            links::extract_links(&m.content)
+                .iter()
+                .filter_map(|l| links::resolve_internal(&m.path, &l.target))
+                .filter(|p| known.contains(p.as_str()))
+                .collect()
+        })
+        .collect();
+    let mut incoming: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for (from, targets) in paths.iter().zip(&outgoing) {
+        for to in targets {
+            incoming
+                .entry(to.as_str())
+                .or_default()
+                .insert(from.as_str());
+        }
+    }
+    let neighbors = |i: usize| -> HashSet<&str> {
+        let mut set: HashSet<&str> = outgoing[i].iter().map(String::as_str).collect();
+        if let Some(from) = incoming.get(paths[i].as_str()) {
+            set.extend(from.iter().copied());
+        }
+        set
+    };
+
+    let target_mem = &mems[target];
+    let target_tags: HashSet<&String> = target_mem.tags.iter().collect();
+    let target_neighbors = neighbors(target);
+    let target_vector = index.vector(&paths[target]);
+
+    let mut relations: Vec<Relation> = (0..mems.len())
+        .filter(|&i| i != target)
+        .map(|i| {
+            let mem = &mems[i];
+
+            let tags: HashSet<&String> = mem.tags.iter().collect();
+            let union = target_tags.union(&tags).count();
+            let tag_score = if union == 0 {
+                0.0
+            } else {
+                target_tags.intersection(&tags).count() as f32 / union as f32
+            };
+
+            let linked =
+                outgoing[target].contains(&paths[i]) || outgoing[i].contains(&paths[target]);
+            let link_score = if linked {
+                1.0
+            } else if !target_neighbors.is_disjoint(&neighbors(i)) {
+                0.5
+            } else {
+                0.0
+            };
+
+            let content_score = match (target_vector, index.vector(&paths[i])) {
+                (Some(a), Some(b)) => semantic::cosine(a, b).max(0.0),
+                _ => 0.0,
+            };
+
+            let mut score =
+                TAG_WEIGHT * tag_score + LINK_WEIGHT * link_score + CONTENT_WEIGHT * content_score;
+            let usage_score = usage.map_or(0.0, |u| u.co_access(target_mem, mem));
+            if usage.is_some() {
+                score = (1.0 - USAGE_WEIGHT) * score + USAGE_WEIGHT * usage_score;
+            }
+
+            Relation {
+                index: i,
+                score,
+                tags: tag_score,
+                links: link_score,
+                content: content_score,
+                usage: usage_score,
+                linked,
+            }
+        })
+        .filter(|r| r.score > 0.0)
+        .collect();
+
+    relations.sort_by(|a, b| b.score.total_cmp(&a.score));
+    relations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::HashEmbedder;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn mem(path: &str, content: &str, tags: &[&str]) -> Mem {
+        Mem::new(PathBuf::from(path), path.to_string(), content.to_string())
+            .with_tags(tags.iter().map(|t| t.to_string()).collect())
+    }
+
+    fn indexed(mems: &[Mem]) -> (TempDir, EmbeddingIndex) {
+        let temp = TempDir::new().unwrap();
+        let embedder = HashEmbedder::default();
+        let mut index = EmbeddingIndex::open(temp.path(), &embedder);
+        index.update(mems, &embedder).unwrap();
+        (temp, index)
+    }
+
+    #[test]
+    fn test_related_scores_components() {
+        let mems = vec![
+            mem(
+                "db/postgres",
+                "We use postgres. See [pool](pooling.md).",
+                &["db", "infra"],
+            ),
+            mem("db/pooling", "Connection pooling for postgres.", &["db"]),
+            mem("ui/theme", "Dark mode colors.", &["ui"]),
+        ];
+        let (_temp, index) = indexed(&mems);
+
+        let relations = related(0, &mems, &index, None);
+        assert_eq!(relations[0].index, 1);
+        assert!(relations[0].linked);
+        assert_eq!(relations[0].links, 1.0);
+        assert_eq!(relations[0].tags, 0.5);
+
+        let theme = relations.iter().find(|r| r.index == 2);
+        assert!(theme.map(|r| r.score < relations[0].score).unwrap_or(true));
+    }
+
+    #[test]
+    fn test_shared_neighbor_proximity() {
+        let mems = vec![
+            mem("a", "Links to [hub](hub.md)", &[]),
+            mem("b", "Also links to [hub](hub.md)", &[]),
+            mem("hub", "Hub", &[]),
+        ];
+        let (_temp, index) = indexed(&mems);
+
+        let relations = related(0, &mems, &index, None);
+        let b = relations.iter().find(|r| r.index == 1).unwrap();
+        assert_eq!(b.links, 0.5);
+        assert!(!b.linked);
+    }
+
+    #[test]
+    fn test_usage_weight() {
+        let mems = vec![
+            mem("deploy", "Deploy steps.", &[]),
+            mem("rollback", "Rollback steps.", &[]),
+            mem("oncall", "Pager rota.", &[]),
+        ];
+        let (_temp, index) = indexed(&mems);
+        let mut usage = Usage::default();
+        let now = chrono::Utc::now();
+        usage.record(&[&mems[0], &mems[2]], now);
+
+        let with = related(0, &mems, &index, Some(&usage));
+        let oncall = with.iter().find(|r| r.index == 2).unwrap();
+        assert_eq!(oncall.usage, 1.0);
+        let without = related(0, &mems, &index, None);
+        let before = without
+            .iter()
+            .find(|r| r.index == 2)
+            .map_or(0.0, |r| r.score);
+        assert!(oncall.score > before);
+    }
+}
@@ -0,0 +1,212 @@
+//! `mem replace`: find and replace text across mems. Only content changes,
+//! so frontmatter stays valid, and every replacement is worked out before
+//! any mem is written.
+
+use crate::mem::Mem;
+use crate::storage::Storage;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+/// What to look for.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Exact text
+    Literal(String),
+
+    /// A regular expression; the replacement may refer to groups as `$1`
+    /// or `${name}`
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str, regex: bool) -> Result<Pattern> {
+        if pattern.is_empty() {
+            return Err(anyhow!("empty pattern"));
+        }
+        if !regex {
+            return Ok(Pattern::Literal(pattern.to_string()));
+        }
+        let re = Regex::new(pattern).map_err(|e| anyhow!("invalid regex: {e}"))?;
+        if re.is_match("") {
+            return Err(anyhow!("regex {pattern:?} matches empty text"));
+        }
+        Ok(Pattern::Regex(re))
+    }
+
+    /// `text` with every match replaced, and the number of matches.
+    pub fn replace(&self, text: &str, replacement: &str) -> (String, usize) {
+        match self {
+            Pattern::Literal(pattern) => {
+                let count = text.matches(pattern.as_str()).count();
+                (text.replace(pattern.as_str(), replacement), count)
+            }
+            Pattern::Regex(re) => {
+                let count = re.find_iter(text).count();
+                (re.replace_all(text, replacement).into_owned(), count)
+            }
+        }
+    }
+}
+
+/// A mem the replacement changes.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub before: Mem,
+
+    /// The mem's file as it was, to put back byte for byte
+    pub source: String,
+
+    /// The mem with its content replaced and updated-at refreshed
+    pub after: Mem,
+
+    /// Matches replaced
+    pub count: usize,
+}
+
+/// Changes replacing `pattern` with `replacement` in the mems under
+/// `prefix`, in path order. Nothing is written.
+pub fn plan(
+    storage: &Storage,
+    prefix: &str,
+    pattern: &Pattern,
+    replacement: &str,
+) -> Result<Vec<Change>> {
+    let mut changes = Vec::new();
+    for mem in storage.iter_mems_under(prefix)? {
+        let (content, count) = pattern.replace(&mem.content, replacement);
+        if count == 0 || content == mem.content {
+            continue;
+        }
+        let source = storage.read_source(&mem.path_str())?;
+        let mut after = mem.clone();
+        after.content = content;
+        after.touch();
+        changes.push(Change {
+            before: mem,
+            source,
+            after,
+            count,
+        });
+    }
+    Ok(changes)
+}
+
+/// Write every change. If a write fails, the files of the mems already
+/// written are put back as they were, so the store ends up with all changes
+/// or none.
+pub fn apply(storage: &Storage, changes: &[Change]) -> Result<()> {
+    for (i, change) in changes.iter().enumerate() {
+        if let Err(e) = storage.write_mem(&change.after) {
+            let path = change.after.path_str();
+            for done in &changes[..i] {
+                let restored = done.before.path_str();
+                storage
+                    .write_source(&restored, &done.source)
+                    .map_err(|restore| {
+                        anyhow!(
+                            "failed to write {path}: {e}; restoring {restored} also failed: {restore}"
+                        )
+                    })?;
+            }
+            return Err(anyhow!("failed to write {path}: {e}; no mems were changed"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn store(mems: &[(&str, &str)]) -> (TempDir, Storage) {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        for (path, content) in mems {
+            let mem = Mem::new(
+                PathBuf::from(path),
+                "Title".to_string(),
+                content.to_string(),
+            );
+            storage.write_mem(&mem).unwrap();
+        }
+        (temp, storage)
+    }
+
+    #[test]
+    fn test_pattern() {
+        let literal = Pattern::new("a.b", false).unwrap();
+        assert_eq!(
+            literal.replace("a.b axb a.b", "c"),
+            ("c axb c".to_string(), 2)
+        );
+
+        let regex = Pattern::new(r"(\w+)-svc", true).unwrap();
+        assert_eq!(
+            regex.replace("auth-svc, billing-svc", "svc-$1"),
+            ("svc-auth, svc-billing".to_string(), 2)
+        );
+
+        assert!(Pattern::new("", false).is_err());
+        assert!(Pattern::new("(", true).is_err());
+        assert!(Pattern::new("x*", true).is_err());
+    }
+
+    #[test]
+    fn test_plan_and_apply() {
+        let (_temp, storage) = store(&[
+            ("ops/deploy", "Call old-api first.\nThen old-api again.\n"),
+            ("ops/rollback", "Nothing to see.\n"),
+            ("notes/api", "old-api is deprecated.\n"),
+        ]);
+        let pattern = Pattern::new("old-api", false).unwrap();
+
+        let changes = plan(&storage, "ops", &pattern, "new-api").unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].count, 2);
+        assert_eq!(changes[0].after.path_str(), "ops/deploy");
+        // Planning writes nothing
+        assert!(storage
+            .read_mem("ops/deploy")
+            .unwrap()
+            .content
+            .contains("old-api"));
+
+        apply(&storage, &changes).unwrap();
+        let mem = storage.read_mem("ops/deploy").unwrap();
+        assert_eq!(mem.content, "Call new-api first.\nThen new-api again.\n");
+        assert_eq!(mem.title, "Title");
+        assert!(storage
+            .read_mem("notes/api")
+            .unwrap()
+            .content
+            .contains("old-api"));
+
+        let all = plan(&storage, "", &pattern, "new-api").unwrap();
+        let paths: Vec<String> = all.iter().map(|c| c.after.path_str()).collect();
+        assert_eq!(paths, ["notes/api"]);
+    }
+
+    #[test]
+    fn test_apply_restores_files() {
+        let (temp, storage) = store(&[("b", "old-api\n")]);
+        // Hand-written frontmatter that writing the parsed mem would reformat
+        let source = "---\ntitle:   A   # first\ncreated-at: 2025-01-01T00:00:00Z\n\
+                      updated-at: 2025-01-01T00:00:00Z\n---\nold-api\n";
+        std::fs::write(temp.path().join("a.md"), source).unwrap();
+        let pattern = Pattern::new("old-api", false).unwrap();
+        let changes = plan(&storage, "", &pattern, "new-api").unwrap();
+        assert_eq!(changes.len(), 2);
+
+        // b can't be written once it's a directory
+        std::fs::remove_file(temp.path().join("b.md")).unwrap();
+        std::fs::create_dir(temp.path().join("b.md")).unwrap();
+        let err = apply(&storage, &changes).unwrap_err().to_string();
+        assert!(err.contains("no mems were changed"), "{err}");
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("a.md")).unwrap(),
+            source
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::config::ReviewConfig;
+use crate::hash::sha256_hex;
+use crate::mem::Mem;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Review history file inside .mems/.
+pub const REVIEW_FILE: &str = ".review.json";
+
+/// Days until a mem comes up again after its first review; each later
+/// review doubles the interval, up to the maximum.
+const FIRST_INTERVAL_DAYS: i64 = 7;
+const MAX_INTERVAL_DAYS: i64 = 365;
+
+/// How much more often mems with an important tag come up.
+const IMPORTANCE_WEIGHT: f64 = 3.0;
+
+/// When a mem was last reviewed and when it is due again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub last_reviewed: DateTime<Utc>,
+    pub interval_days: i64,
+    pub reviews: u32,
+}
+
+/// Review history of a store, keyed by mem id (or path for mems without one).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    pub mems: BTreeMap<String, Record>,
+}
+
+/// A mem picked for review.
+#[derive(Debug, Clone, Serialize)]
+pub struct Pick {
+    pub path: String,
+    pub title: String,
+    pub last_reviewed: Option<DateTime<Utc>>,
+
+    /// Days since the mem was last updated or reviewed, whichever is later
+    pub days_unseen: i64,
+}
+
+fn key(mem: &Mem) -> String {
+    mem.id.clone().unwrap_or_else(|| mem.path_str())
+}
+
+impl History {
+    /// Load the history of a store, or an empty one.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(REVIEW_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(&path).context("failed to read review history")?;
+        serde_json::from_str(&text).context("invalid review history")
+    }
+
+    pub fn save(&self, root: &Path) -> Result<()> {
+        fs::write(root.join(REVIEW_FILE), serde_json::to_string_pretty(self)?)
+            .context("failed to write review history")
+    }
+
+    /// Record a review of `mem` at `now`: the first puts the next one
+    /// [`FIRST_INTERVAL_DAYS`] out, and each after doubles the interval.
+    pub fn mark_reviewed(&mut self, mem: &Mem, now: DateTime<Utc>) {
+        match self.mems.get_mut(&key(mem)) {
+            Some(record) => {
+                record.last_reviewed = now;
+                record.interval_days = (record.interval_days * 2).min(MAX_INTERVAL_DAYS);
+                record.reviews += 1;
+            }
+            None => {
+                self.mems.insert(
+                    key(mem),
+                    Record {
+                        last_reviewed: now,
+                        interval_days: FIRST_INTERVAL_DAYS,
+                        reviews: 1,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Up to `config.per_day` mems to review on `now`'s day.
+    ///
+    /// Mems are due when never reviewed or once their interval has passed.
+    /// Due mems are ranked by how long they have gone unseen, weighted up
+    /// for important tags and shuffled by a per-day jitter, so the same day
+    /// always gives the same picks.
+    pub fn pick(&self, mems: &[Mem], now: DateTime<Utc>, config: &ReviewConfig) -> Vec<Pick> {
+        let day = now.format("%Y-%m-%d").to_string();
+        let mut due: Vec<(f64, Pick)> = mems
+            .iter()
+            .filter(|mem| !mem.is_generated())
+            .filter_map(|mem| {
+                let record = self.mems.get(&key(mem));
+                if record.is_some_and(|r| now < r.last_reviewed + Duration::days(r.interval_days)) {
+                    return None;
+                }
+                let seen = record.map_or(mem.updated_at, |r| r.last_reviewed.max(mem.updated_at));
+                let days_unseen = (now - seen).num_days().max(0);
+                let importance = if mem.tags.iter().any(|t| config.important_tags.contains(t)) {
+                    IMPORTANCE_WEIGHT
+                } else {
+                    1.0
+                };
+                let score = (days_unseen + 1) as f64 * importance * jitter(&day, &key(mem));
+                let pick = Pick {
+                    path: mem.path_str(),
+                    title: mem.title.clone(),
+                    last_reviewed: record.map(|r| r.last_reviewed),
+                    days_unseen,
+                };
+                Some((score, pick))
+            })
+            .collect();
+
+        due.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.path.cmp(&b.1.path)));
+        due.into_iter()
+            .take(config.per_day)
+            .map(|(_, pick)| pick)
+            .collect()
+    }
+}
+
+/// A factor in [0.5, 1.5) that is stable for a day and mem.
+fn jitter(day: &str, key: &str) -> f64 {
+    let hash = sha256_hex(format!("{day}:{key}").as_bytes());
+    let bits = u32::from_str_radix(&hash[..8], 16).unwrap_or(0);
+    0.5 + f64::from(bits) / f64::from(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        format!("2025-03-{day:02}T12:00:00Z").parse().unwrap()
+    }
+
+    fn mem(path: &str, updated: u32, tags: &[&str]) -> Mem {
+        let mut mem = Mem::new(PathBuf::from(path), path.to_string(), String::new())
+            .with_tags(tags.iter().map(|t| t.to_string()).collect());
+        mem.created_at = at(1);
+        mem.updated_at = at(updated);
+        mem
+    }
+
+    fn config(per_day: usize) -> ReviewConfig {
+        ReviewConfig {
+            per_day,
+            important_tags: vec!["critical".to_string()],
+        }
+    }
+
+    fn paths(picks: &[Pick]) -> Vec<&str> {
+        picks.iter().map(|p| p.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_pick_prefers_unseen_and_important() {
+        let mems = vec![
+            mem("fresh", 30, &[]),
+            mem("old", 1, &[]),
+            mem("recent-but-critical", 25, &["critical"]),
+        ];
+        let history = History::default();
+        let picks = history.pick(&mems, at(31), &config(2));
+        let mut picked = paths(&picks);
+        picked.sort();
+        assert_eq!(picked, vec!["old", "recent-but-critical"]);
+        assert_eq!(
+            picks.iter().find(|p| p.path == "old").unwrap().days_unseen,
+            30
+        );
+
+        // Stable within a day
+        assert_eq!(
+            paths(&history.pick(&mems, at(31), &config(2))),
+            paths(&picks)
+        );
+    }
+
+    #[test]
+    fn test_intervals() {
+        let mems = vec![mem("a", 1, &[])];
+        let mut history = History::default();
+        history.mark_reviewed(&mems[0], at(10));
+        assert_eq!(history.mems[mems[0].id.as_ref().unwrap()].interval_days, 7);
+        assert!(history.pick(&mems, at(16), &config(5)).is_empty());
+        assert_eq!(history.pick(&mems, at(17), &config(5)).len(), 1);
+
+        history.mark_reviewed(&mems[0], at(17));
+        let record = &history.mems[mems[0].id.as_ref().unwrap()];
+        assert_eq!((record.interval_days, record.reviews), (14, 2));
+        assert!(history.pick(&mems, at(30), &config(5)).is_empty());
+        assert_eq!(history.pick(&mems, at(31), &config(5)).len(), 1);
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let temp = TempDir::new().unwrap();
+        assert!(History::load(temp.path()).unwrap().mems.is_empty());
+
+        let mut history = History::default();
+        history.mark_reviewed(&mem("a", 1, &[]), at(10));
+        history.save(temp.path()).unwrap();
+        assert_eq!(History::load(temp.path()).unwrap().mems, history.mems);
+    }
+}
@@ -0,0 +1,306 @@
+//! Stepping through runbooks (`mem run`): numbered steps and fenced code
+//! blocks, one at a time, optionally running shell blocks.
+
+use crate::capture::Capture;
+use crate::structure;
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+/// `kind` of a runbook mem.
+pub const RUNBOOK_KIND: &str = "runbook";
+
+/// Directory of the log mems `mem run` records runs in, one per runbook.
+pub const RUNS_DIR: &str = "runs";
+
+/// Fence languages run as shell commands.
+const SHELL_LANGUAGES: &[&str] = &["sh", "bash", "shell", "zsh"];
+
+/// A step of a runbook.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// A numbered list item, with any lines indented under it
+    Instruction { line: usize, text: String },
+
+    /// A fenced code block
+    Code {
+        line: usize,
+        language: String,
+        code: String,
+    },
+}
+
+impl Step {
+    /// 1-based line within the content where the step starts.
+    pub fn line(&self) -> usize {
+        match self {
+            Step::Instruction { line, .. } | Step::Code { line, .. } => *line,
+        }
+    }
+
+    /// Whether the step is a shell block `mem run --exec` can run.
+    pub fn is_shell(&self) -> bool {
+        matches!(self, Step::Code { language, .. } if SHELL_LANGUAGES.contains(&language.as_str()))
+    }
+
+    /// One line describing the step, for the run log.
+    pub fn summary(&self) -> String {
+        match self {
+            Step::Instruction { text, .. } => text.lines().next().unwrap_or_default().to_string(),
+            Step::Code { code, .. } => {
+                format!("`{}`", code.lines().next().unwrap_or_default().trim())
+            }
+        }
+    }
+}
+
+/// What happened to a step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Done,
+    Skipped,
+    /// Run by `mem run`, with its exit code (`None` when killed by a signal)
+    Ran(Option<i32>),
+}
+
+impl Outcome {
+    fn describe(&self) -> String {
+        match self {
+            Outcome::Done => "done".to_string(),
+            Outcome::Skipped => "skipped".to_string(),
+            Outcome::Ran(Some(code)) => format!("ran, exit code {code}"),
+            Outcome::Ran(None) => "ran, killed by a signal".to_string(),
+        }
+    }
+}
+
+/// Numbered list item text, if `line` starts one.
+fn numbered(line: &str) -> Option<&str> {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    line[digits..]
+        .strip_prefix(". ")
+        .or_else(|| line[digits..].strip_prefix(") "))
+        .map(str::trim)
+}
+
+/// The steps of a runbook in order: top-level numbered list items and fenced
+/// code blocks (including those indented under an item).
+pub fn steps(content: &str) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut in_item = false;
+    for (line, text) in structure::prose_lines(content) {
+        let trimmed = text.trim_start();
+        let indented = text.len() - trimmed.len() >= 2;
+        if let Some(item) = numbered(text) {
+            steps.push(Step::Instruction {
+                line,
+                text: item.to_string(),
+            });
+            in_item = true;
+        } else if in_item && indented && !trimmed.is_empty() {
+            if let Some(Step::Instruction { text, .. }) = steps.last_mut() {
+                text.push('\n');
+                text.push_str(trimmed);
+            }
+        } else if !trimmed.is_empty() {
+            in_item = false;
+        }
+    }
+    steps.extend(
+        structure::code_blocks(content)
+            .into_iter()
+            .map(|block| Step::Code {
+                line: block.line,
+                language: block.language,
+                code: block.code,
+            }),
+    );
+    steps.sort_by_key(Step::line);
+    steps
+}
+
+/// Ask a question on `out` and read the trimmed, lowercased answer; `None`
+/// at end of input.
+fn ask(input: &mut impl BufRead, out: &mut impl Write, question: &str) -> Result<Option<String>> {
+    write!(out, "{question}")?;
+    out.flush()?;
+    let mut answer = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        writeln!(out)?;
+        return Ok(None);
+    }
+    Ok(Some(answer.trim().to_lowercase()))
+}
+
+/// Step through `steps`, showing each on `out` and reading what happened from
+/// `input`. With `exec`, shell blocks can be run and their output is shown.
+/// Returns the outcomes of the steps reached; answering `q`, or the end of
+/// input, stops early. `offset` turns content lines into file lines.
+pub fn walk(
+    steps: &[Step],
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    exec: bool,
+    offset: usize,
+) -> Result<Vec<Outcome>> {
+    let mut outcomes = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        writeln!(
+            out,
+            "\nStep {} of {} (line {}):",
+            i + 1,
+            steps.len(),
+            offset + step.line()
+        )?;
+        match step {
+            Step::Instruction { text, .. } => {
+                for line in text.lines() {
+                    writeln!(out, "  {line}")?;
+                }
+            }
+            Step::Code { code, .. } => {
+                for line in code.lines() {
+                    writeln!(out, "    {line}")?;
+                }
+            }
+        }
+
+        let runnable = exec && step.is_shell();
+        let question = if runnable {
+            "Run it? [y]es, [d]one by hand, [s]kip, [q]uit: "
+        } else {
+            "[Enter] when done, [s]kip, [q]uit: "
+        };
+        let outcome = loop {
+            let Some(answer) = ask(input, out, question)? else {
+                return Ok(outcomes);
+            };
+            match answer.as_str() {
+                "y" | "yes" if runnable => {
+                    let Step::Code { code, .. } = step else {
+                        unreachable!("only code steps are runnable");
+                    };
+                    let command = ["sh", "-c", code].map(str::to_string);
+                    let capture = Capture::run(&command)?;
+                    write!(out, "{}{}", capture.stdout, capture.stderr)?;
+                    break Outcome::Ran(capture.code);
+                }
+                "" if !runnable => break Outcome::Done,
+                "d" | "done" => break Outcome::Done,
+                "s" | "skip" => break Outcome::Skipped,
+                "q" | "quit" => return Ok(outcomes),
+                _ => {}
+            }
+        };
+
+        let failed = matches!(outcome, Outcome::Ran(code) if code != Some(0));
+        outcomes.push(outcome);
+        if failed {
+            let answer = ask(input, out, "The command failed. Continue? [y/N]: ")?;
+            if !matches!(answer.as_deref(), Some("y" | "yes")) {
+                break;
+            }
+        }
+    }
+    Ok(outcomes)
+}
+
+/// A log entry recording a run of the runbook at `path`.
+pub fn summary(path: &str, steps: &[Step], outcomes: &[Outcome]) -> String {
+    let mut message = if outcomes.len() == steps.len() {
+        format!("Ran {path}: all {} steps", steps.len())
+    } else {
+        format!(
+            "Ran {path}: stopped after {} of {} steps",
+            outcomes.len(),
+            steps.len()
+        )
+    };
+    for (i, (step, outcome)) in steps.iter().zip(outcomes).enumerate() {
+        let _ = write!(
+            message,
+            "\n- {}. {} ({})",
+            i + 1,
+            step.summary(),
+            outcome.describe()
+        );
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUNBOOK: &str = "Restart the app.\n\n\
+        1. Drain traffic from the node\n   in the load balancer.\n\
+        2. Stop the app:\n\n   ```sh\n   echo stopped\n   ```\n\n\
+        3. Check the dashboard.\n";
+
+    #[test]
+    fn test_steps() {
+        let steps = steps(RUNBOOK);
+        assert_eq!(
+            steps,
+            vec![
+                Step::Instruction {
+                    line: 3,
+                    text: "Drain traffic from the node\nin the load balancer.".to_string()
+                },
+                Step::Instruction {
+                    line: 5,
+                    text: "Stop the app:".to_string()
+                },
+                Step::Code {
+                    line: 7,
+                    language: "sh".to_string(),
+                    code: "echo stopped\n".to_string()
+                },
+                Step::Instruction {
+                    line: 11,
+                    text: "Check the dashboard.".to_string()
+                },
+            ]
+        );
+        assert!(steps[2].is_shell());
+        assert_eq!(steps[2].summary(), "`echo stopped`");
+
+        // Indentation that isn't ASCII doesn't split a character
+        let indented = super::steps("1. Stop:\n ```sh\n\u{a0}echo é\n ```\n");
+        assert_eq!(indented.len(), 2);
+        assert_eq!(indented[1].summary(), "`echo é`");
+    }
+
+    #[test]
+    fn test_walk() {
+        let steps = steps(RUNBOOK);
+        let mut out = Vec::new();
+        let outcomes = walk(&steps, &mut "\ns\ny\n".as_bytes(), &mut out, true, 4).unwrap();
+        assert_eq!(
+            outcomes,
+            vec![Outcome::Done, Outcome::Skipped, Outcome::Ran(Some(0))]
+        );
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Step 3 of 4 (line 11):\n    echo stopped\n"));
+        assert!(out.contains("[q]uit: stopped\n"));
+
+        assert_eq!(
+            summary("runbooks/app", &steps, &outcomes),
+            "Ran runbooks/app: stopped after 3 of 4 steps\n\
+             - 1. Drain traffic from the node (done)\n\
+             - 2. Stop the app: (skipped)\n\
+             - 3. `echo stopped` (ran, exit code 0)"
+        );
+    }
+
+    #[test]
+    fn test_walk_stops_on_failure() {
+        let steps = steps("```sh\nexit 3\n```\n\n1. Next\n");
+        let mut out = Vec::new();
+        let outcomes = walk(&steps, &mut "y\nn\n".as_bytes(), &mut out, true, 0).unwrap();
+        assert_eq!(outcomes, vec![Outcome::Ran(Some(3))]);
+    }
+}
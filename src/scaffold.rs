@@ -0,0 +1,278 @@
+//! Template variables: values a kind declares (`variables:` in its config)
+//! that `mem add` asks for, checks, and substitutes for `{{ name }}` in the
+//! kind's template and frontmatter fields.
+
+use crate::dates;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Type of a variable's value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    /// Any text
+    #[default]
+    Text,
+
+    /// A date, `YYYY-MM-DD` or RFC 3339, stored as `YYYY-MM-DD`
+    Date,
+}
+
+/// A value a kind's template asks for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Variable {
+    pub name: String,
+
+    /// Question asked for the value (default: the name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+
+    #[serde(default, rename = "type")]
+    pub value_type: VariableType,
+
+    /// Allowed values, if only some are
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+
+    /// Value when none is given; variables without one are required
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+impl Variable {
+    /// The prompt shown on a terminal, e.g. `Status (draft, review) [draft]: `.
+    pub fn question(&self) -> String {
+        let mut question = self.prompt.clone().unwrap_or_else(|| self.name.clone());
+        if !self.choices.is_empty() {
+            question.push_str(&format!(" ({})", self.choices.join(", ")));
+        } else if self.value_type == VariableType::Date {
+            question.push_str(" (YYYY-MM-DD)");
+        }
+        if let Some(default) = &self.default {
+            question.push_str(&format!(" [{default}]"));
+        }
+        question.push_str(": ");
+        question
+    }
+
+    /// Check `value` against the variable's type and choices, returning it
+    /// as stored.
+    pub fn validate(&self, value: &str) -> Result<String> {
+        let value = value.trim();
+        if !self.choices.is_empty() && !self.choices.iter().any(|c| c == value) {
+            return Err(anyhow!(
+                "{} must be one of {}, not {value:?}",
+                self.name,
+                self.choices.join(", ")
+            ));
+        }
+        match self.value_type {
+            VariableType::Text => Ok(value.to_string()),
+            VariableType::Date => dates::parse_date(value)
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .ok_or_else(|| anyhow!("{} must be a date (YYYY-MM-DD), not {value:?}", self.name)),
+        }
+    }
+}
+
+/// Error for a given value that no variable is declared for.
+fn check_declared(variables: &[Variable], given: &BTreeMap<String, String>) -> Result<()> {
+    if let Some(unknown) = given
+        .keys()
+        .find(|name| !variables.iter().any(|v| &v.name == *name))
+    {
+        let declared: Vec<&str> = variables.iter().map(|v| v.name.as_str()).collect();
+        return Err(anyhow!(
+            "unknown template variable {unknown} (declared: {})",
+            if declared.is_empty() {
+                "none".to_string()
+            } else {
+                declared.join(", ")
+            }
+        ));
+    }
+    Ok(())
+}
+
+/// Values for `variables`: from `given` (`--var name=value`), else from
+/// `ask` (which returns `None` when it can't ask), else the default.
+pub fn values(
+    variables: &[Variable],
+    mut given: BTreeMap<String, String>,
+    mut ask: impl FnMut(&Variable) -> Result<Option<String>>,
+) -> Result<BTreeMap<String, String>> {
+    check_declared(variables, &given)?;
+
+    let mut values = BTreeMap::new();
+    for variable in variables {
+        let value = match given.remove(&variable.name) {
+            Some(value) => Some(value),
+            None => ask(variable)?
+                .filter(|v| !v.trim().is_empty())
+                .or_else(|| variable.default.clone()),
+        };
+        let value = value.ok_or_else(|| {
+            anyhow!(
+                "missing value for template variable {name} (use --var {name}=...)",
+                name = variable.name
+            )
+        })?;
+        values.insert(variable.name.clone(), variable.validate(&value)?);
+    }
+    Ok(values)
+}
+
+/// Values for `variables` that need no asking: those in `given`, else the
+/// defaults. Variables with neither are left out rather than required.
+pub fn given_values(
+    variables: &[Variable],
+    mut given: BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>> {
+    check_declared(variables, &given)?;
+
+    let mut values = BTreeMap::new();
+    for variable in variables {
+        let value = given
+            .remove(&variable.name)
+            .or_else(|| variable.default.clone());
+        if let Some(value) = value {
+            values.insert(variable.name.clone(), variable.validate(&value)?);
+        }
+    }
+    Ok(values)
+}
+
+/// `{{ name }}` and `{{name}}`.
+fn placeholders(name: &str) -> [String; 2] {
+    [format!("{{{{ {name} }}}}"), format!("{{{{{name}}}}}")]
+}
+
+/// `text` with `{{ name }}` (or `{{name}}`) replaced by each value. Other
+/// braces are left alone.
+pub fn substitute(text: &str, values: &BTreeMap<String, String>) -> String {
+    let mut text = text.to_string();
+    for (name, value) in values {
+        for placeholder in placeholders(name) {
+            text = text.replace(&placeholder, value);
+        }
+    }
+    text
+}
+
+/// Whether `text` still has a placeholder for any of `variables`.
+pub fn mentions_any(text: &str, variables: &[Variable]) -> bool {
+    variables
+        .iter()
+        .flat_map(|v| placeholders(&v.name))
+        .any(|placeholder| text.contains(&placeholder))
+}
+
+/// Parse `--var name=value`.
+pub fn parse_assignment(assignment: &str) -> Result<(String, String)> {
+    let (name, value) = assignment
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected name=value, got {assignment:?}"))?;
+    if name.trim().is_empty() {
+        return Err(anyhow!("missing variable name in {assignment:?}"));
+    }
+    Ok((name.trim().to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Vec<Variable> {
+        serde_yaml::from_str(
+            "- name: owner\n  prompt: Decision owner\n\
+             - name: status\n  choices: [draft, review]\n  default: draft\n\
+             - name: due\n  type: date\n",
+        )
+        .unwrap()
+    }
+
+    fn given(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_question() {
+        let vars = variables();
+        assert_eq!(vars[0].question(), "Decision owner: ");
+        assert_eq!(vars[1].question(), "status (draft, review) [draft]: ");
+        assert_eq!(vars[2].question(), "due (YYYY-MM-DD): ");
+    }
+
+    #[test]
+    fn test_validate() {
+        let vars = variables();
+        assert_eq!(vars[1].validate("review").unwrap(), "review");
+        let err = vars[1].validate("done").unwrap_err().to_string();
+        assert_eq!(err, r#"status must be one of draft, review, not "done""#);
+        assert_eq!(
+            vars[2].validate("2026-03-01T10:00:00Z").unwrap(),
+            "2026-03-01"
+        );
+        assert!(vars[2].validate("next week").is_err());
+    }
+
+    #[test]
+    fn test_values() {
+        let vars = variables();
+        let mut asked = Vec::new();
+        let values = values(&vars, given(&[("due", "2026-03-01")]), |v| {
+            asked.push(v.name.clone());
+            Ok(Some(if v.name == "owner" { "sam" } else { "" }.to_string()))
+        })
+        .unwrap();
+        assert_eq!(asked, ["owner", "status"]);
+        assert_eq!(
+            values,
+            given(&[("owner", "sam"), ("status", "draft"), ("due", "2026-03-01")])
+        );
+
+        // Without a way to ask, required variables must be given
+        let err = super::values(&vars, given(&[]), |_| Ok(None)).unwrap_err();
+        assert!(err.to_string().contains("--var owner="), "{err}");
+        let err = super::values(&vars, given(&[("onwer", "sam")]), |_| Ok(None)).unwrap_err();
+        assert!(err.to_string().contains("unknown template variable onwer"));
+    }
+
+    #[test]
+    fn test_given_values() {
+        let vars = variables();
+        assert_eq!(
+            given_values(&vars, given(&[("due", "2026-03-01T10:00:00Z")])).unwrap(),
+            given(&[("status", "draft"), ("due", "2026-03-01")])
+        );
+        assert!(given_values(&vars, given(&[("status", "done")])).is_err());
+        assert!(given_values(&vars, given(&[("onwer", "sam")])).is_err());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let values = given(&[("owner", "sam")]);
+        assert_eq!(
+            substitute("{{ owner }} and {{owner}}; {{ other }} {", &values),
+            "sam and sam; {{ other }} {"
+        );
+        let vars = variables();
+        assert!(mentions_any("Owner: {{owner}}", &vars));
+        assert!(!mentions_any("Owner: sam, {{ other }}", &vars));
+    }
+
+    #[test]
+    fn test_parse_assignment() {
+        assert_eq!(
+            parse_assignment("owner=sam=x").unwrap(),
+            ("owner".to_string(), "sam=x".to_string())
+        );
+        assert!(parse_assignment("owner").is_err());
+        assert!(parse_assignment("=sam").is_err());
+    }
+}
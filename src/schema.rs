@@ -0,0 +1,311 @@
+use crate::config::Config;
+use crate::directory;
+use crate::fuzzy;
+use crate::kind;
+use crate::mem::Mem;
+use crate::storage::Storage;
+use crate::structure;
+use crate::text;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Frontmatter and section rules for the mems under a path prefix.
+///
+/// Declared in `_dir.yaml` files or under `schemas:` in the store config.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Schema {
+    /// Frontmatter fields every mem must have
+    pub required_fields: Vec<String>,
+
+    /// Values the `status` field may take (any when empty)
+    pub allowed_statuses: Vec<String>,
+
+    /// Tags mems may use (any when empty)
+    pub allowed_tags: Vec<String>,
+
+    /// Section headings every mem must have, e.g. `## Decision` (or
+    /// `Decision` for any level)
+    pub required_headings: Vec<String>,
+}
+
+/// A schema in the store config, applying to mems under `prefix`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PrefixSchema {
+    /// Path prefix such as `arch/decisions` (`""` for the whole store)
+    pub prefix: String,
+
+    #[serde(flatten)]
+    pub schema: Schema,
+}
+
+/// A way a mem breaks its schema.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    MissingField(String),
+    InvalidStatus(String),
+    UnknownTag(String),
+    MissingHeading(String),
+}
+
+impl Violation {
+    /// Human-readable description, as used in lint messages.
+    pub fn message(&self, schema: &Schema) -> String {
+        match self {
+            Violation::MissingField(field) => format!("missing required field: {field}"),
+            Violation::InvalidStatus(status) => format!(
+                "status {status:?} is not one of: {}",
+                schema.allowed_statuses.join(", ")
+            ),
+            Violation::UnknownTag(tag) => {
+                match fuzzy::near_tag(tag, schema.allowed_tags.iter().map(String::as_str)) {
+                    Some(near) => format!("tag {tag:?} is not allowed here (did you mean {near}?)"),
+                    None => format!("tag {tag:?} is not allowed here"),
+                }
+            }
+            Violation::MissingHeading(heading) => format!("missing required heading: {heading}"),
+        }
+    }
+}
+
+impl Schema {
+    /// Layer a more specific schema on top: required fields and headings
+    /// accumulate, and its non-empty allowed lists replace the current ones.
+    fn merge(&mut self, other: Schema) {
+        for field in other.required_fields {
+            if !self.required_fields.contains(&field) {
+                self.required_fields.push(field);
+            }
+        }
+        for heading in other.required_headings {
+            if !self.required_headings.contains(&heading) {
+                self.required_headings.push(heading);
+            }
+        }
+        if !other.allowed_statuses.is_empty() {
+            self.allowed_statuses = other.allowed_statuses;
+        }
+        if !other.allowed_tags.is_empty() {
+            self.allowed_tags = other.allowed_tags;
+        }
+    }
+
+    /// Ways `mem` breaks this schema. `title` and `tags` count as missing
+    /// when empty; other required fields must be present and non-empty.
+    pub fn check(&self, mem: &Mem) -> Vec<Violation> {
+        let mut violations: Vec<Violation> = self
+            .required_fields
+            .iter()
+            .filter(|field| match field.as_str() {
+                "title" => mem.title.trim().is_empty(),
+                "tags" => mem.tags.is_empty(),
+                "id" => mem.id.is_none(),
+                "created-at" | "updated-at" => false,
+                "checksum" => mem.checksum.is_none(),
+                other => mem
+                    .extra
+                    .get(other)
+                    .is_none_or(|v| v.is_null() || v.as_str().is_some_and(str::is_empty)),
+            })
+            .map(|field| Violation::MissingField(field.clone()))
+            .collect();
+
+        if let Some(status) = mem.extra.get("status") {
+            let status = match status.as_str() {
+                Some(s) => s.to_string(),
+                None => serde_yaml::to_string(status)
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+            };
+            if !self.allowed_statuses.is_empty() && !self.allowed_statuses.contains(&status) {
+                violations.push(Violation::InvalidStatus(status));
+            }
+        }
+
+        if !self.allowed_tags.is_empty() {
+            for tag in &mem.tags {
+                if !self.allowed_tags.iter().any(|t| text::same(t, tag)) {
+                    violations.push(Violation::UnknownTag(tag.clone()));
+                }
+            }
+        }
+
+        if !self.required_headings.is_empty() {
+            let headings = structure::headings(&mem.content);
+            for required in &self.required_headings {
+                if !headings.iter().any(|h| h.matches(required)) {
+                    violations.push(Violation::MissingHeading(required.clone()));
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Whether a path falls under a prefix (`""` matches everything).
+pub fn under(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_matches('/');
+    prefix.is_empty()
+        || path == prefix
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// The schema for a mem path, combining the store-wide tag vocabulary, config
+/// schemas whose prefix matches, and the `_dir.yaml` files of its ancestor
+/// directories.
+///
+/// Less specific prefixes apply first; at the same depth, `_dir.yaml` comes
+/// after the config.
+pub fn for_path(storage: &Storage, config: &Config, path: &str) -> Result<Schema> {
+    let vocabulary = Schema {
+        allowed_tags: config.tags.allowed.clone(),
+        ..Schema::default()
+    };
+    let mut layers: Vec<(usize, Schema)> = vec![(0, vocabulary)];
+    layers.extend(
+        config
+            .schemas
+            .iter()
+            .filter(|s| under(path, &s.prefix))
+            .map(|s| (depth(&s.prefix), s.schema.clone())),
+    );
+    let parts: Vec<&str> = path.split('/').collect();
+    for n in 0..parts.len() {
+        let meta = directory::load(storage, &parts[..n].join("/"))?;
+        layers.push((n, meta.schema));
+    }
+    // Stable sort keeps config layers ahead of directory layers at each depth
+    layers.sort_by_key(|(depth, _)| *depth);
+
+    let mut schema = Schema::default();
+    for (_, layer) in layers {
+        schema.merge(layer);
+    }
+    Ok(schema)
+}
+
+/// The schema for a mem: that of its path, on top of the headings its kind
+/// requires.
+pub fn for_mem(storage: &Storage, config: &Config, mem: &Mem) -> Result<Schema> {
+    let mut schema = Schema {
+        required_headings: kind::settings(config, kind::of(mem))
+            .required_headings()
+            .to_vec(),
+        ..Schema::default()
+    };
+    schema.merge(for_path(storage, config, &mem.path_str())?);
+    Ok(schema)
+}
+
+fn depth(prefix: &str) -> usize {
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() {
+        0
+    } else {
+        prefix.split('/').count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn schema(required: &[&str], statuses: &[&str], tags: &[&str]) -> Schema {
+        let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect();
+        Schema {
+            required_fields: strings(required),
+            allowed_statuses: strings(statuses),
+            allowed_tags: strings(tags),
+            required_headings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let schema = schema(&["status", "deciders"], &["proposed", "accepted"], &["adr"]);
+        let mut mem = Mem::new(PathBuf::from("adr"), "ADR".to_string(), "x".to_string())
+            .with_tags(vec!["adr".to_string(), "misc".to_string()]);
+        mem.set_field("status", "done");
+        assert_eq!(
+            schema.check(&mem),
+            vec![
+                Violation::MissingField("deciders".to_string()),
+                Violation::InvalidStatus("done".to_string()),
+                Violation::UnknownTag("misc".to_string()),
+            ]
+        );
+        assert_eq!(
+            Violation::InvalidStatus("done".to_string()).message(&schema),
+            "status \"done\" is not one of: proposed, accepted"
+        );
+
+        mem.set_field("status", "accepted");
+        mem.set_field("deciders", "ana, raj");
+        mem.tags.pop();
+        assert!(schema.check(&mem).is_empty());
+    }
+
+    #[test]
+    fn test_check_headings() {
+        let schema = Schema {
+            required_headings: vec!["## Context".to_string(), "Decision".to_string()],
+            ..Schema::default()
+        };
+        let mut mem = Mem::new(
+            PathBuf::from("adr"),
+            "ADR".to_string(),
+            "## Context\n\n```\n## Decision\n```".to_string(),
+        );
+        assert_eq!(
+            schema.check(&mem),
+            vec![Violation::MissingHeading("Decision".to_string())]
+        );
+
+        mem.content.push_str("\n### Decision\n");
+        assert!(schema.check(&mem).is_empty());
+    }
+
+    #[test]
+    fn test_for_path_layers_config_and_directories() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        fs::create_dir_all(temp.path().join("arch/decisions")).unwrap();
+        fs::write(
+            temp.path().join("arch/decisions").join(directory::DIR_FILE),
+            "required-fields: [deciders]\nallowed-statuses: [proposed, accepted]\n",
+        )
+        .unwrap();
+        let config: Config = serde_yaml::from_str(
+            "tags:\n  allowed: [adr, ops]\nschemas:\n  - prefix: arch/\n    required-fields: [status]\n    allowed-statuses: [draft]\n  - prefix: guides\n    allowed-tags: [howto]\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            for_path(&storage, &config, "arch/decisions/adr-001").unwrap(),
+            schema(
+                &["status", "deciders"],
+                &["proposed", "accepted"],
+                &["adr", "ops"]
+            )
+        );
+        assert_eq!(
+            for_path(&storage, &config, "arch/overview").unwrap(),
+            schema(&["status"], &["draft"], &["adr", "ops"])
+        );
+        assert_eq!(
+            for_path(&storage, &config, "guides/setup").unwrap(),
+            schema(&[], &[], &["howto"])
+        );
+        assert_eq!(
+            for_path(&storage, &config, "architecture").unwrap(),
+            schema(&[], &[], &["adr", "ops"])
+        );
+    }
+}
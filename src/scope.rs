@@ -0,0 +1,48 @@
+//! Scoping commands to the mems of the directory they run in, through the
+//! `scopes:` mapping from repository directories to mem prefixes (e.g.
+//! `src/payments: services/payments`).
+
+use crate::schema;
+use std::collections::BTreeMap;
+
+/// The mem prefix for a directory relative to the repository root: the one
+/// mapped to the deepest configured directory containing it.
+pub fn prefix_for<'a>(scopes: &'a BTreeMap<String, String>, dir: &str) -> Option<&'a str> {
+    scopes
+        .iter()
+        .filter(|(mapped, _)| schema::under(dir, mapped))
+        .max_by_key(|(mapped, _)| mapped.trim_matches('/').len())
+        .map(|(_, prefix)| prefix.trim_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_for() {
+        let scopes: BTreeMap<String, String> = [
+            ("src/payments", "services/payments"),
+            ("src/payments/ledger/", "services/ledger"),
+            ("docs", "guides"),
+        ]
+        .into_iter()
+        .map(|(d, p)| (d.to_string(), p.to_string()))
+        .collect();
+
+        assert_eq!(
+            prefix_for(&scopes, "src/payments"),
+            Some("services/payments")
+        );
+        assert_eq!(
+            prefix_for(&scopes, "src/payments/api/v2"),
+            Some("services/payments")
+        );
+        assert_eq!(
+            prefix_for(&scopes, "src/payments/ledger"),
+            Some("services/ledger")
+        );
+        assert_eq!(prefix_for(&scopes, "src/payments-old"), None);
+        assert_eq!(prefix_for(&scopes, ""), None);
+    }
+}
@@ -0,0 +1,648 @@
+use crate::analyzer::Analyzer;
+use crate::config::{Config, EmbeddingsConfig};
+use crate::mem::Mem;
+use crate::text;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::debug;
+
+/// Directory inside .mems/ holding derived indexes.
+pub const INDEX_DIR: &str = ".index";
+
+/// Embedding cache file inside the index directory.
+const EMBEDDINGS_FILE: &str = "embeddings";
+
+/// Weight of the semantic score in hybrid ranking (keyword gets the rest).
+const SEMANTIC_WEIGHT: f32 = 0.7;
+
+/// Hits scoring below this are dropped from hybrid results.
+const MIN_SCORE: f32 = 0.1;
+
+/// Words too common to carry meaning in hashed embeddings.
+pub const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "do", "does", "for", "from", "how", "in",
+    "is", "it", "of", "on", "or", "that", "the", "this", "to", "we", "what", "when", "where",
+    "which", "who", "why", "with",
+];
+
+/// A backend that turns text into fixed-size vectors.
+pub trait Embedder {
+    /// Identifier stored with cached vectors; changing it invalidates the cache.
+    fn id(&self) -> String;
+
+    /// Embed a batch of texts, returning one vector per input.
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+
+    /// The analyzer the vectors depend on, if they depend on one.
+    fn analyzer(&self) -> Option<&Analyzer> {
+        None
+    }
+}
+
+/// Build the embedder selected in config.
+pub fn embedder_from_config(config: &Config) -> Result<Box<dyn Embedder>> {
+    match config.embeddings.backend.as_str() {
+        "local" => Ok(Box::new(HashEmbedder::new(Analyzer::new(&config.analyzer)))),
+        "api" => Ok(Box::new(ApiEmbedder::from_config(&config.embeddings))),
+        other => Err(anyhow!(
+            "unknown embeddings backend: {other} (expected local or api)"
+        )),
+    }
+}
+
+/// Local embedder using feature hashing of words and character trigrams.
+///
+/// Needs no model download and captures lexical similarity (shared stems,
+/// reordered phrases), which is a large step up from exact substrings.
+pub struct HashEmbedder {
+    dims: usize,
+    analyzer: Analyzer,
+}
+
+impl Default for HashEmbedder {
+    fn default() -> Self {
+        Self::new(Analyzer::default())
+    }
+}
+
+impl HashEmbedder {
+    pub fn new(analyzer: Analyzer) -> Self {
+        Self {
+            dims: 512,
+            analyzer,
+        }
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut counts: HashMap<usize, f32> = HashMap::new();
+        let mut add = |feature: &str, weight: f32| {
+            let h = fnv1a64(feature.as_bytes());
+            let idx = (h % self.dims as u64) as usize;
+            let sign = if (h >> 63) == 0 { 1.0 } else { -1.0 };
+            *counts.entry(idx).or_default() += sign * weight;
+        };
+
+        for token in self.analyzer.terms(text) {
+            add(&token, 1.0);
+
+            // Trigrams let "rotate" and "rotation" land near each other
+            let padded: Vec<char> = format!("<{token}>").chars().collect();
+            for window in padded.windows(3) {
+                add(&window.iter().collect::<String>(), 0.5);
+            }
+        }
+
+        let mut vector = vec![0.0; self.dims];
+        for (idx, count) in counts {
+            // Sublinear term frequency
+            vector[idx] = count.signum() * (1.0 + count.abs().ln());
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl Embedder for HashEmbedder {
+    fn id(&self) -> String {
+        format!("local-hash-{}", self.dims)
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|t| self.embed_one(t)).collect())
+    }
+
+    fn analyzer(&self) -> Option<&Analyzer> {
+        Some(&self.analyzer)
+    }
+}
+
+/// Embedder calling an OpenAI-compatible `/embeddings` endpoint.
+pub struct ApiEmbedder {
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl ApiEmbedder {
+    /// Build from config, reading the API key from the configured env var.
+    pub fn from_config(config: &EmbeddingsConfig) -> Self {
+        Self {
+            url: config.url.clone(),
+            model: config.model.clone(),
+            api_key: std::env::var(&config.api_key_env).ok(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingDatum>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingDatum {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl Embedder for ApiEmbedder {
+    fn id(&self) -> String {
+        format!("api:{}", self.model)
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(64) {
+            let mut request = ureq::post(&self.url);
+            if let Some(key) = &self.api_key {
+                request = request.set("Authorization", &format!("Bearer {key}"));
+            }
+            let response: EmbeddingResponse = request
+                .send_json(serde_json::json!({ "model": self.model, "input": batch }))
+                .map_err(|e| anyhow!("embeddings request failed: {e}"))?
+                .into_json()
+                .context("invalid embeddings response")?;
+
+            let mut data = response.data;
+            if data.len() != batch.len() {
+                return Err(anyhow!(
+                    "embeddings response had {} vectors for {} inputs",
+                    data.len(),
+                    batch.len()
+                ));
+            }
+            data.sort_by_key(|d| d.index);
+            for datum in data {
+                let mut vector = datum.embedding;
+                normalize(&mut vector);
+                vectors.push(vector);
+            }
+        }
+        Ok(vectors)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EmbeddingCache {
+    backend: String,
+
+    /// Analyzer the vectors were built with, for backends that use one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    analyzer: Option<String>,
+    entries: BTreeMap<String, CachedEmbedding>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedEmbedding {
+    hash: String,
+    vector: Vec<f32>,
+}
+
+/// Embedding vectors for a store, cached in `.mems/.index/embeddings`.
+pub struct EmbeddingIndex {
+    path: PathBuf,
+    cache: EmbeddingCache,
+    dirty: bool,
+
+    /// Why the cache on disk was discarded, if it was
+    discarded: Option<String>,
+}
+
+/// How an embedding index compares with the mems it should cover.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Drift {
+    /// Mems whose vector is current
+    pub fresh: usize,
+
+    /// Mems changed since they were embedded
+    pub stale: Vec<String>,
+
+    /// Mems without a vector
+    pub missing: Vec<String>,
+
+    /// Vectors of mems that no longer exist
+    pub orphaned: Vec<String>,
+}
+
+impl Drift {
+    pub fn is_clean(&self) -> bool {
+        self.stale.is_empty() && self.missing.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+impl EmbeddingIndex {
+    /// Open the cache for a store root; a cache from another backend or
+    /// analyzer is discarded.
+    pub fn open(root: &Path, embedder: &dyn Embedder) -> Self {
+        let path = root.join(INDEX_DIR).join(EMBEDDINGS_FILE);
+        let backend = embedder.id();
+        let analyzer = embedder.analyzer().map(Analyzer::id);
+
+        // A corrupt or foreign cache is simply rebuilt
+        let (cache, discarded) = match fs::read_to_string(&path) {
+            Err(_) => (None, None),
+            Ok(text) => match serde_json::from_str::<EmbeddingCache>(&text) {
+                Err(e) => (None, Some(format!("unreadable: {e}"))),
+                Ok(c) if c.backend != backend => {
+                    let reason = format!("built with backend {}", c.backend);
+                    (None, Some(reason))
+                }
+                Ok(c) if c.analyzer != analyzer => {
+                    let built = c.analyzer.as_deref().unwrap_or("an older analyzer");
+                    (None, Some(format!("built with {built}")))
+                }
+                Ok(c) => (Some(c), None),
+            },
+        };
+        let cache = cache.unwrap_or(EmbeddingCache {
+            backend,
+            analyzer,
+            entries: BTreeMap::new(),
+        });
+
+        Self {
+            path,
+            cache,
+            // Saving replaces a discarded cache even if nothing is embedded
+            dirty: discarded.is_some(),
+            discarded,
+        }
+    }
+
+    /// Where the cache is stored.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Id of the embedder the vectors come from.
+    pub fn backend(&self) -> &str {
+        &self.cache.backend
+    }
+
+    /// Id of the analyzer the vectors depend on, if they depend on one.
+    pub fn analyzer(&self) -> Option<&str> {
+        self.cache.analyzer.as_deref()
+    }
+
+    /// Number of mems with a vector.
+    pub fn len(&self) -> usize {
+        self.cache.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.entries.is_empty()
+    }
+
+    /// Why the cache on disk couldn't be used (it was unreadable or built
+    /// by another backend), if it couldn't. Such a cache counts as empty.
+    pub fn discarded(&self) -> Option<&str> {
+        self.discarded.as_deref()
+    }
+
+    /// Compare the cached vectors with `mems`, without embedding anything.
+    pub fn drift(&self, mems: &[Mem]) -> Drift {
+        let mut drift = Drift::default();
+        let mut live = HashSet::new();
+        for mem in mems {
+            let path = mem.path_str();
+            match self.cache.entries.get(&path) {
+                None => drift.missing.push(path.clone()),
+                Some(entry) if entry.hash != text_hash(&embedding_text(mem)) => {
+                    drift.stale.push(path.clone())
+                }
+                Some(_) => drift.fresh += 1,
+            }
+            live.insert(path);
+        }
+        drift.orphaned = self
+            .cache
+            .entries
+            .keys()
+            .filter(|path| !live.contains(*path))
+            .cloned()
+            .collect();
+        drift
+    }
+
+    /// Drop every vector, so the next update embeds every mem again.
+    pub fn clear(&mut self) {
+        self.dirty |= !self.cache.entries.is_empty() || self.discarded.is_some();
+        self.cache.entries.clear();
+    }
+
+    /// Embed new or changed mems and drop entries for mems that no longer exist.
+    pub fn update(&mut self, mems: &[Mem], embedder: &dyn Embedder) -> Result<()> {
+        let live: HashSet<String> = mems.iter().map(|m| m.path_str()).collect();
+        let before = self.cache.entries.len();
+        self.cache.entries.retain(|path, _| live.contains(path));
+        self.dirty |= self.cache.entries.len() != before;
+
+        let mut pending = Vec::new();
+        for mem in mems {
+            let text = embedding_text(mem);
+            let hash = text_hash(&text);
+            let path = mem.path_str();
+            if self.cache.entries.get(&path).map(|e| &e.hash) != Some(&hash) {
+                pending.push((path, hash, text));
+            }
+        }
+
+        debug!(
+            mems = mems.len(),
+            pending = pending.len(),
+            "updating embedding index"
+        );
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let texts: Vec<String> = pending.iter().map(|(_, _, t)| t.clone()).collect();
+        let start = Instant::now();
+        let vectors = embedder.embed(&texts)?;
+        debug!(
+            count = texts.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "embedded mems"
+        );
+        for ((path, hash, _), vector) in pending.into_iter().zip(vectors) {
+            self.cache
+                .entries
+                .insert(path, CachedEmbedding { hash, vector });
+        }
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Cached vector for a mem path.
+    pub fn vector(&self, path: &str) -> Option<&[f32]> {
+        self.cache.entries.get(path).map(|e| e.vector.as_slice())
+    }
+
+    /// Persist the cache if it changed.
+    pub fn save(&self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context("failed to create index directory")?;
+        }
+        let json = serde_json::to_string(&self.cache)?;
+        debug!(path = %self.path.display(), "saving embedding index");
+        fs::write(&self.path, json).context("failed to write embeddings cache")
+    }
+}
+
+/// Rank mems against a query by combining embedding similarity with keyword
+/// overlap, comparing the terms `analyzer` finds.
+///
+/// Refreshes and saves the store's embedding cache as a side effect. Returns
+/// `(mem index, score)` pairs, best first.
+pub fn hybrid_search(
+    root: &Path,
+    mems: &[Mem],
+    query: &str,
+    embedder: &dyn Embedder,
+    analyzer: &Analyzer,
+) -> Result<Vec<(usize, f32)>> {
+    let mut index = EmbeddingIndex::open(root, embedder);
+    index.update(mems, embedder)?;
+    index.save()?;
+
+    let query_vector = embedder
+        .embed(&[query.to_string()])?
+        .pop()
+        .ok_or_else(|| anyhow!("embedder returned no vector for query"))?;
+    let query_terms = analyzer.terms(query);
+
+    let mut scored: Vec<(usize, f32)> = mems
+        .iter()
+        .enumerate()
+        .filter_map(|(i, mem)| {
+            let semantic = index
+                .vector(&mem.path_str())
+                .map(|v| cosine(v, &query_vector).max(0.0))
+                .unwrap_or(0.0);
+            let score = SEMANTIC_WEIGHT * semantic
+                + (1.0 - SEMANTIC_WEIGHT) * keyword_score(analyzer, &query_terms, mem);
+            (score >= MIN_SCORE).then_some((i, score))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(scored)
+}
+
+/// Fraction of query terms (as `analyzer` found them) present in a mem's
+/// title, tags, or content.
+pub fn keyword_score(analyzer: &Analyzer, query_terms: &[String], mem: &Mem) -> f32 {
+    if query_terms.is_empty() {
+        return 0.0;
+    }
+    let words: HashSet<String> = analyzer.terms(&embedding_text(mem)).into_iter().collect();
+    let hits = query_terms.iter().filter(|t| words.contains(*t)).count();
+    hits as f32 / query_terms.len() as f32
+}
+
+/// Case-folded alphanumeric words of a text.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text::fold(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Cosine similarity of two vectors.
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let na: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let nb: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if na == 0.0 || nb == 0.0 {
+        0.0
+    } else {
+        dot / (na * nb)
+    }
+}
+
+/// Text fed to the embedder for a mem.
+fn embedding_text(mem: &Mem) -> String {
+    format!("{}\n{}\n{}", mem.title, mem.tags.join(" "), mem.content)
+}
+
+/// Hash of a mem's embedding text, recorded with its vector to tell when the
+/// mem changed.
+fn text_hash(text: &str) -> String {
+    format!("{:016x}", fnv1a64(text.as_bytes()))
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm: f32 = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for x in vector.iter_mut() {
+            *x /= norm;
+        }
+    }
+}
+
+/// 64-bit FNV-1a hash (stable across runs and platforms).
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AnalyzerConfig, Stemming};
+    use tempfile::TempDir;
+
+    fn mem(path: &str, title: &str, content: &str) -> Mem {
+        Mem::new(PathBuf::from(path), title.to_string(), content.to_string())
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Rotate DB-credentials, quickly!"),
+            vec!["rotate", "db", "credentials", "quickly"]
+        );
+    }
+
+    #[test]
+    fn test_hash_embedder_similarity() {
+        let embedder = HashEmbedder::default();
+        let vectors = embedder
+            .embed(&[
+                "rotating database credentials".to_string(),
+                "how to rotate the database password credential".to_string(),
+                "frontend button colors".to_string(),
+            ])
+            .unwrap();
+
+        let related = cosine(&vectors[0], &vectors[1]);
+        let unrelated = cosine(&vectors[0], &vectors[2]);
+        assert!(related > unrelated);
+    }
+
+    #[test]
+    fn test_hybrid_search_ranks_relevant_first() {
+        let temp = TempDir::new().unwrap();
+        let mems = vec![
+            mem("ui/colors", "Button colors", "Primary buttons are blue."),
+            mem(
+                "ops/secrets",
+                "Secret rotation",
+                "Database credentials are rotated monthly via the vault.",
+            ),
+        ];
+
+        let embedder = HashEmbedder::default();
+        let results = hybrid_search(
+            temp.path(),
+            &mems,
+            "how do we rotate database credentials",
+            &embedder,
+            &Analyzer::default(),
+        )
+        .unwrap();
+
+        assert_eq!(results[0].0, 1);
+        assert!(temp.path().join(".index/embeddings").exists());
+    }
+
+    #[test]
+    fn test_index_drops_removed_and_refreshes_changed() {
+        let temp = TempDir::new().unwrap();
+        let embedder = HashEmbedder::default();
+
+        let mut index = EmbeddingIndex::open(temp.path(), &embedder);
+        index
+            .update(&[mem("a", "A", "alpha"), mem("b", "B", "beta")], &embedder)
+            .unwrap();
+        index.save().unwrap();
+        let before = index.vector("a").unwrap().to_vec();
+
+        let mut index = EmbeddingIndex::open(temp.path(), &embedder);
+        index
+            .update(&[mem("a", "A", "something else")], &embedder)
+            .unwrap();
+        assert!(index.vector("b").is_none());
+        assert_ne!(index.vector("a").unwrap(), before.as_slice());
+    }
+
+    #[test]
+    fn test_drift() {
+        let temp = TempDir::new().unwrap();
+        let embedder = HashEmbedder::default();
+        let mut index = EmbeddingIndex::open(temp.path(), &embedder);
+        index
+            .update(&[mem("a", "A", "alpha"), mem("b", "B", "beta")], &embedder)
+            .unwrap();
+        index.save().unwrap();
+
+        let now = [
+            mem("a", "A", "alpha"),
+            mem("c", "C", "gamma"),
+            mem("b", "B", "new"),
+        ];
+        let index = EmbeddingIndex::open(temp.path(), &embedder);
+        let drift = index.drift(&now);
+        assert_eq!(
+            drift,
+            Drift {
+                fresh: 1,
+                stale: vec!["b".to_string()],
+                missing: vec!["c".to_string()],
+                orphaned: vec![],
+            }
+        );
+        assert!(!drift.is_clean());
+        assert_eq!(index.discarded(), None);
+
+        fs::write(index.path(), "{").unwrap();
+        let mut index = EmbeddingIndex::open(temp.path(), &embedder);
+        assert!(index.discarded().unwrap().starts_with("unreadable"));
+        assert_eq!(index.drift(&now).missing.len(), 3);
+        index.update(&now, &embedder).unwrap();
+        assert_eq!(index.drift(&now[..1]).orphaned, ["b", "c"]);
+    }
+
+    #[test]
+    fn test_unknown_backend() {
+        let config = Config {
+            embeddings: EmbeddingsConfig {
+                backend: "onnx".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(embedder_from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_index_records_analyzer() {
+        let temp = TempDir::new().unwrap();
+        let embedder = HashEmbedder::default();
+        let mut index = EmbeddingIndex::open(temp.path(), &embedder);
+        index.update(&[mem("a", "A", "alpha")], &embedder).unwrap();
+        index.save().unwrap();
+        assert_eq!(index.analyzer(), Some(Analyzer::default().id().as_str()));
+
+        // Vectors from another analyzer don't compare, so they're dropped
+        let stemming = HashEmbedder::new(Analyzer::new(&AnalyzerConfig {
+            stemming: Stemming::English,
+            ..Default::default()
+        }));
+        let index = EmbeddingIndex::open(temp.path(), &stemming);
+        assert!(index.is_empty());
+        assert!(index.discarded().unwrap().contains("stemming=none"));
+    }
+}
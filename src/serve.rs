@@ -0,0 +1,469 @@
+use crate::error::MemError;
+use crate::storage::Storage;
+use crate::text;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Largest request body accepted (mem edits).
+const MAX_BODY: usize = 10 * 1024 * 1024;
+
+/// Connections served at once, each on its own worker thread.
+const WORKERS: usize = 8;
+
+/// How long a connection may stall reading a request or writing a response
+/// before it is dropped, so a slow client can't hold a worker.
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A parsed HTTP request.
+#[derive(Debug, Clone, Default)]
+pub struct Request {
+    pub method: String,
+
+    /// Percent-decoded path, without the query string
+    pub path: String,
+
+    pub query: HashMap<String, String>,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// An HTTP response.
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: &impl Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec_pretty(value).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message.into() }))
+    }
+
+    /// Map an error to a status: unknown mems are 404, bad paths 400.
+    fn from_error(e: &anyhow::Error) -> Self {
+        let status = match e.downcast_ref::<MemError>() {
+            Some(MemError::NotFound { .. } | MemError::Ambiguous { .. }) => 404,
+            Some(
+                MemError::EmptyPath
+                | MemError::AbsolutePath { .. }
+                | MemError::ParentSegment { .. },
+            ) => 400,
+            _ => 500,
+        };
+        Self::error(status, e.to_string())
+    }
+}
+
+/// A mem in API responses.
+#[derive(Debug, Serialize)]
+struct ApiMem {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    title: String,
+    created_at: String,
+    updated_at: String,
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+}
+
+impl ApiMem {
+    fn new(mem: &crate::mem::Mem, with_content: bool) -> Self {
+        Self {
+            path: mem.path_str(),
+            id: mem.id.clone(),
+            title: mem.title.clone(),
+            created_at: mem.created_at.to_rfc3339(),
+            updated_at: mem.updated_at.to_rfc3339(),
+            tags: mem.tags.clone(),
+            content: with_content.then(|| mem.content.clone()),
+        }
+    }
+}
+
+/// Body of `PUT /api/mems/<path>`; absent fields are left unchanged.
+#[derive(Debug, Deserialize)]
+struct MemUpdate {
+    title: Option<String>,
+    content: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+/// Serve the store's HTTP API (and, with `web`, the browser UI) until the
+/// process is stopped, on [`WORKERS`] threads that each accept connections.
+pub fn run(storage: &Storage, listener: TcpListener, web: bool) -> Result<()> {
+    std::thread::scope(|scope| {
+        for _ in 0..WORKERS {
+            scope.spawn(|| accept(storage, &listener, web));
+        }
+    });
+    Ok(())
+}
+
+fn accept(storage: &Storage, listener: &TcpListener, web: bool) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(error = %e, "failed to accept connection");
+                continue;
+            }
+        };
+        if let Err(e) = serve_connection(storage, stream, web) {
+            debug!(error = %e, "connection failed");
+        }
+    }
+}
+
+fn serve_connection(storage: &Storage, mut stream: TcpStream, web: bool) -> Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let response = match read_request(&mut BufReader::new(&stream)) {
+        Ok(request) => {
+            let response = handle(storage, &request, web);
+            debug!(
+                method = request.method,
+                path = request.path,
+                status = response.status,
+                "handled request"
+            );
+            response
+        }
+        Err(e) => Response::error(400, e.to_string()),
+    };
+    write_response(&mut stream, &response)
+}
+
+/// Read one request from a connection.
+pub fn read_request(reader: &mut impl BufRead) -> Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(anyhow!("malformed request line"));
+    };
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: percent_decode(path)?,
+        query: query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                Ok((
+                    percent_decode(&key.replace('+', " "))?,
+                    percent_decode(&value.replace('+', " "))?,
+                ))
+            })
+            .collect::<Result<_>>()?,
+        ..Request::default()
+    };
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => length = value.trim().parse().context("bad content-length")?,
+            "content-type" => request.content_type = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+
+    if length > MAX_BODY {
+        return Err(anyhow!("request body too large"));
+    }
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body)?;
+    Ok(request)
+}
+
+fn write_response(stream: &mut impl Write, response: &Response) -> Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        415 => "Unsupported Media Type",
+        423 => "Locked",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {reason}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    Ok(stream.flush()?)
+}
+
+/// Route a request.
+///
+/// - `GET /api/mems`: every mem, without content
+/// - `GET /api/mems/<path>`: one mem (`<path>` may be `id:<ULID>`)
+/// - `PUT /api/mems/<path>`: update title, content, or tags (JSON body)
+/// - `GET /api/search?q=<text>`: mems whose title or content contains the text
+///
+/// With `web`, `/` serves the browser UI and `GET /api/render/<path>` a mem's
+/// content as HTML.
+#[cfg_attr(not(feature = "web"), allow(unused_variables))]
+pub fn handle(storage: &Storage, request: &Request, web: bool) -> Response {
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/api/mems") => list(storage),
+        ("GET", "/api/search") => search(storage, request),
+        (method, path) if path.starts_with("/api/mems/") => {
+            let reference = &path["/api/mems/".len()..];
+            match method {
+                "GET" => show(storage, reference),
+                "PUT" => update(storage, reference, request),
+                _ => Ok(Response::error(405, "method not allowed")),
+            }
+        }
+        #[cfg(feature = "web")]
+        ("GET", "/") if web => Ok(Response {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            body: crate::web::INDEX_HTML.as_bytes().to_vec(),
+        }),
+        #[cfg(feature = "web")]
+        ("GET", path) if web && path.starts_with("/api/render/") => {
+            render(storage, &path["/api/render/".len()..])
+        }
+        _ => Ok(Response::error(404, "not found")),
+    };
+    result.unwrap_or_else(|e| Response::from_error(&e))
+}
+
+fn list(storage: &Storage) -> Result<Response> {
+    let mems: Vec<ApiMem> = storage
+        .list_mems()?
+        .iter()
+        .map(|m| ApiMem::new(m, false))
+        .collect();
+    Ok(Response::json(200, &mems))
+}
+
+fn search(storage: &Storage, request: &Request) -> Result<Response> {
+    let query = request
+        .query
+        .get("q")
+        .map(|q| text::fold(q))
+        .unwrap_or_default();
+    let mems: Vec<ApiMem> = storage
+        .list_mems()?
+        .iter()
+        .filter(|m| {
+            text::fold(&m.title).contains(&query) || text::fold(&m.content).contains(&query)
+        })
+        .map(|m| ApiMem::new(m, false))
+        .collect();
+    Ok(Response::json(200, &mems))
+}
+
+fn show(storage: &Storage, reference: &str) -> Result<Response> {
+    let mem = storage.read_mem(&storage.resolve(reference)?)?;
+    Ok(Response::json(200, &ApiMem::new(&mem, true)))
+}
+
+fn update(storage: &Storage, reference: &str, request: &Request) -> Result<Response> {
+    // Browsers can't send a cross-origin JSON PUT without a CORS preflight,
+    // which this server never approves
+    let is_json = request
+        .content_type
+        .as_deref()
+        .is_some_and(|t| t.starts_with("application/json"));
+    if !is_json {
+        return Ok(Response::error(415, "expected application/json"));
+    }
+    let update: MemUpdate =
+        serde_json::from_slice(&request.body).map_err(|e| anyhow!("invalid JSON body: {e}"))?;
+
+    let mut mem = storage.read_mem(&storage.resolve(reference)?)?;
+    if mem.is_locked() {
+        let path = mem.path_str();
+        return Ok(Response::error(423, format!("{path} is locked")));
+    }
+    if let Some(title) = update.title {
+        mem.title = title;
+    }
+    if let Some(content) = update.content {
+        mem.content = content;
+    }
+    if let Some(tags) = update.tags {
+        mem.tags = tags;
+    }
+    mem.touch();
+    storage.write_mem(&mem)?;
+    Ok(Response::json(200, &ApiMem::new(&mem, true)))
+}
+
+#[cfg(feature = "web")]
+fn render(storage: &Storage, reference: &str) -> Result<Response> {
+    let mut mem = storage.read_mem(&storage.resolve(reference)?)?;
+    mem.content = crate::include::expand(storage, &mem)?;
+    Ok(Response {
+        status: 200,
+        content_type: "text/html; charset=utf-8",
+        body: crate::web::markdown_to_html(&mem.content, &mem.path).into_bytes(),
+    })
+}
+
+fn percent_decode(text: &str) -> Result<String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| anyhow!("bad percent-encoding in {text}"))?;
+            out.push(hex);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).context("request path is not UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::Mem;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn request(method: &str, path: &str, body: &str) -> Request {
+        let raw = format!(
+            "{method} {path} HTTP/1.1\r\nHost: x\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        read_request(&mut raw.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_read_request() {
+        let req = request("GET", "/api/mems/a%20b?q=x+y&flag", "");
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.path, "/api/mems/a b");
+        assert_eq!(req.query["q"], "x y");
+        assert_eq!(req.query["flag"], "");
+
+        let req = request("PUT", "/api/mems/a", "{\"title\":\"T\"}");
+        assert_eq!(req.body, b"{\"title\":\"T\"}");
+        assert!(read_request(&mut "garbage\r\n\r\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_handle() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let mem = Mem::new(
+            PathBuf::from("guides/setup"),
+            "Setup".to_string(),
+            "Install it".to_string(),
+        );
+        storage.write_mem(&mem).unwrap();
+
+        let json = |response: Response| -> serde_json::Value {
+            serde_json::from_slice(&response.body).unwrap()
+        };
+
+        let list = json(handle(&storage, &request("GET", "/api/mems", ""), false));
+        assert_eq!(list[0]["path"], "guides/setup");
+        assert!(list[0].get("content").is_none());
+
+        let by_id = format!("/api/mems/id:{}", mem.id.unwrap());
+        assert_eq!(
+            json(handle(&storage, &request("GET", &by_id, ""), false))["content"],
+            "Install it"
+        );
+
+        let found = json(handle(
+            &storage,
+            &request("GET", "/api/search?q=INSTALL", ""),
+            false,
+        ));
+        assert_eq!(found.as_array().unwrap().len(), 1);
+
+        let updated = handle(
+            &storage,
+            &request("PUT", "/api/mems/guides/setup", "{\"content\":\"Run it\"}"),
+            false,
+        );
+        assert_eq!(updated.status, 200);
+        assert_eq!(storage.read_mem("guides/setup").unwrap().content, "Run it");
+
+        let missing = handle(&storage, &request("GET", "/api/mems/nope", ""), false);
+        assert_eq!(missing.status, 404);
+        let escape = handle(&storage, &request("GET", "/api/mems/../x", ""), false);
+        assert_eq!(escape.status, 400);
+        assert_eq!(
+            handle(&storage, &request("GET", "/", ""), false).status,
+            404
+        );
+
+        let mut form = request("PUT", "/api/mems/guides/setup", "content=x");
+        form.content_type = Some("application/x-www-form-urlencoded".to_string());
+        assert_eq!(handle(&storage, &form, false).status, 415);
+
+        let mut locked = storage.read_mem("guides/setup").unwrap();
+        locked.set_locked(true);
+        storage.write_mem(&locked).unwrap();
+        let refused = handle(
+            &storage,
+            &request("PUT", "/api/mems/guides/setup", "{\"content\":\"x\"}"),
+            false,
+        );
+        assert_eq!(refused.status, 423);
+        assert_eq!(storage.read_mem("guides/setup").unwrap().content, "Run it");
+    }
+
+    #[cfg(feature = "web")]
+    #[test]
+    fn test_web_routes() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::new(temp.path().to_path_buf());
+        let mem = Mem::new(PathBuf::from("a"), "A".to_string(), "**hi**".to_string());
+        storage.write_mem(&mem).unwrap();
+
+        let index = handle(&storage, &request("GET", "/", ""), true);
+        assert!(String::from_utf8(index.body)
+            .unwrap()
+            .contains("<title>mem</title>"));
+        let rendered = handle(&storage, &request("GET", "/api/render/a", ""), true);
+        assert_eq!(rendered.body, b"<p><strong>hi</strong></p>\n");
+        assert_eq!(
+            handle(&storage, &request("GET", "/", ""), false).status,
+            404
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Starting a store with `mem init`: a recommended hierarchy of index mems,
+//! directory descriptions, and kind templates (`--with-structure`), and
+//! `.gitignore` entries for the files `mem` derives locally (`--git`).
+
+use crate::cache::CACHE_FILE;
+use crate::config::{Config, CONFIG_FILE};
+use crate::directory::DIR_FILE;
+use crate::glossary::GLOSSARY_KIND;
+use crate::kind;
+use crate::linkcheck::LINK_CACHE_FILE;
+use crate::mem::Mem;
+use crate::semantic::INDEX_DIR;
+use crate::storage::Storage;
+use crate::usage::USAGE_FILE;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A starter hierarchy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Structure {
+    /// Architecture decision records under `decisions/`
+    Adr,
+
+    /// Operational runbooks under `runbooks/`
+    Runbooks,
+
+    /// Decisions, runbooks, an architecture overview, and a glossary, with
+    /// an index at the root
+    Full,
+}
+
+/// A directory of the starter hierarchy.
+struct Section {
+    dir: &'static str,
+    description: &'static str,
+    title: &'static str,
+    index: &'static str,
+}
+
+const DECISIONS: Section = Section {
+    dir: "decisions",
+    description: "Architecture decision records",
+    title: "Decisions",
+    index: "Why the system is the way it is: one mem per decision, never \
+            rewritten once accepted. A later decision that changes course \
+            supersedes the earlier one.\n\n\
+            Record a decision with:\n\n\
+            ```bash\nmem add --kind adr decisions/adr-002-short-title\n```\n\n\
+            ## Decisions\n\n\
+            - [ADR-001: Record architecture decisions](adr-001-record-decisions.md)\n",
+};
+
+const RUNBOOKS: Section = Section {
+    dir: "runbooks",
+    description: "How to operate the system",
+    title: "Runbooks",
+    index: "Step-by-step procedures for operating the system: deploys, \
+            rollbacks, incident response. Each should be runnable by someone \
+            who has never done it before.\n\n\
+            Write a runbook with:\n\n\
+            ```bash\nmem add --kind runbook runbooks/deploy\n```\n\n\
+            ## Runbooks\n\n",
+};
+
+const ARCHITECTURE: Section = Section {
+    dir: "architecture",
+    description: "How the system fits together",
+    title: "Architecture",
+    index: "An overview of the system's components, how they talk to each \
+            other, and where their boundaries are.\n\n\
+            ## Components\n\n\
+            ## Data flow\n\n\
+            ## Boundaries\n",
+};
+
+/// The first ADR: the decision to keep decisions.
+const FIRST_ADR: &str = "## Context\n\n\
+    We need to remember why the system was built the way it was, after the \
+    people who decided have moved on.\n\n\
+    ## Decision\n\n\
+    We record each architecturally significant decision as an ADR under \
+    `decisions/`, with its context, the decision, and its consequences.\n\n\
+    ## Consequences\n\n\
+    Decisions are easy to find and review. An ADR is not edited once \
+    accepted; a new one supersedes it.\n";
+
+const GLOSSARY: &str = "Terms used across the project, one per heading. \
+    `mem def <term>` looks them up.\n\n\
+    ## SLO\n\n\
+    Service level objective: the target level of a service's reliability, \
+    such as 99.9% of requests succeeding over 30 days.\n";
+
+impl Structure {
+    fn sections(self) -> Vec<Section> {
+        match self {
+            Structure::Adr => vec![DECISIONS],
+            Structure::Runbooks => vec![RUNBOOKS],
+            Structure::Full => vec![ARCHITECTURE, DECISIONS, RUNBOOKS],
+        }
+    }
+
+    /// Kinds whose templates the config spells out, for editing.
+    fn kinds(self) -> &'static [&'static str] {
+        match self {
+            Structure::Adr => &["adr"],
+            Structure::Runbooks => &["runbook"],
+            Structure::Full => &["adr", "runbook"],
+        }
+    }
+}
+
+/// A starter config spelling out the built-in templates of `kinds`, so
+/// they can be edited.
+fn starter_config(kinds: &[&str]) -> Result<String> {
+    let defaults = Config::default();
+    let mut templates = BTreeMap::new();
+    for kind in kinds {
+        let template = kind::settings(&defaults, kind).template.unwrap_or_default();
+        templates.insert(*kind, BTreeMap::from([("template", template)]));
+    }
+    let yaml = serde_yaml::to_string(&BTreeMap::from([("kinds", templates)]))?;
+    Ok(format!(
+        "# Templates new mems start from: `mem add --kind <kind> <path>`\n{yaml}"
+    ))
+}
+
+/// Write the starter hierarchy into a new store. Returns the files written,
+/// relative to the store.
+pub fn scaffold(storage: &Storage, structure: Structure) -> Result<Vec<String>> {
+    let root = storage.local_root()?;
+    let mut written = Vec::new();
+    let mut write_mem = |mem: Mem| -> Result<()> {
+        storage.write_mem(&mem)?;
+        written.push(format!("{}.md", mem.path_str()));
+        Ok(())
+    };
+
+    let sections = structure.sections();
+    for section in &sections {
+        fs::create_dir_all(root.join(section.dir))?;
+        fs::write(
+            root.join(section.dir).join(DIR_FILE),
+            format!("description: {}\n", section.description),
+        )
+        .with_context(|| format!("failed to write {}/{DIR_FILE}", section.dir))?;
+        write_mem(Mem::new(
+            PathBuf::from(format!("{}/index", section.dir)),
+            section.title.to_string(),
+            section.index.to_string(),
+        ))?;
+    }
+    if matches!(structure, Structure::Adr | Structure::Full) {
+        let mut adr = Mem::new(
+            PathBuf::from("decisions/adr-001-record-decisions"),
+            "ADR-001: Record architecture decisions".to_string(),
+            FIRST_ADR.to_string(),
+        );
+        adr.set_field("kind", "adr");
+        adr.set_field("status", "accepted");
+        write_mem(adr)?;
+    }
+    if structure == Structure::Full {
+        let mut glossary = Mem::new(
+            PathBuf::from("glossary"),
+            "Glossary".to_string(),
+            GLOSSARY.to_string(),
+        );
+        glossary.set_field("kind", GLOSSARY_KIND);
+        write_mem(glossary)?;
+
+        let mut index = String::from("Start here.\n\n");
+        for section in &sections {
+            index.push_str(&format!(
+                "- [{}]({}/index.md): {}\n",
+                section.title, section.dir, section.description
+            ));
+        }
+        index.push_str("- [Glossary](glossary.md): Terms used across the project\n");
+        let mut index = Mem::new(PathBuf::from("index"), "Index".to_string(), index);
+        index.set_pinned(true);
+        write_mem(index)?;
+    }
+
+    let config = root.join(CONFIG_FILE);
+    if !config.exists() {
+        fs::write(&config, starter_config(structure.kinds())?).context("failed to write config")?;
+        written.push(CONFIG_FILE.to_string());
+    }
+    for section in &sections {
+        written.push(format!("{}/{DIR_FILE}", section.dir));
+    }
+    written.sort();
+    Ok(written)
+}
+
+/// Files `mem` derives or keeps per person inside a store, which don't
+/// belong in version control.
+pub fn local_files() -> Vec<String> {
+    vec![
+        CACHE_FILE.to_string(),
+        format!("{INDEX_DIR}/"),
+        LINK_CACHE_FILE.to_string(),
+        USAGE_FILE.to_string(),
+    ]
+}
+
+/// Add entries for a store's local files to the `.gitignore` in `dir`,
+/// where `store` is the store's path relative to `dir`. Entries already
+/// there are left alone; returns those added.
+pub fn ignore_local_files(dir: &Path, store: &str) -> Result<Vec<String>> {
+    ignore_files(dir, store, local_files())
+}
+
+/// Like [`ignore_local_files`], for just `files` of the store.
+pub fn ignore_files(dir: &Path, store: &str, files: Vec<String>) -> Result<Vec<String>> {
+    let path = dir.join(".gitignore");
+    let existing = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).context("failed to read .gitignore"),
+    };
+    let present: Vec<&str> = existing.lines().map(str::trim).collect();
+    let added: Vec<String> = files
+        .into_iter()
+        .map(|file| format!("{store}/{file}"))
+        .filter(|entry| !present.contains(&entry.as_str()))
+        .collect();
+    if added.is_empty() {
+        return Ok(added);
+    }
+
+    let mut text = existing;
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str("# Local files of the mem store\n");
+    for entry in &added {
+        text.push_str(entry);
+        text.push('\n');
+    }
+    fs::write(&path, text).context("failed to write .gitignore")?;
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint;
+    use crate::progress::Silent;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scaffold_full() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::init_at(&temp.path().join(".mems")).unwrap();
+        let written = scaffold(&storage, Structure::Full).unwrap();
+        for file in [
+            "decisions/adr-001-record-decisions.md",
+            "runbooks/_dir.yaml",
+            "config.yaml",
+        ] {
+            assert!(written.contains(&file.to_string()), "{written:?}");
+        }
+
+        let index = storage.read_mem("index").unwrap();
+        assert!(index.is_pinned());
+        assert!(index.content.contains("[Runbooks](runbooks/index.md)"));
+
+        // The starter store is clean, and its config parses
+        let (_, issues) = lint::lint_storage("", &storage, &Silent).unwrap();
+        assert!(issues.is_empty(), "{issues:?}");
+        let config = Config::for_storage(&storage).unwrap();
+        assert_eq!(
+            kind::settings(&config, "runbook").template.as_deref(),
+            Some("## When to use\n\n## Steps\n\n## Verification\n")
+        );
+    }
+
+    #[test]
+    fn test_scaffold_adr() {
+        let temp = TempDir::new().unwrap();
+        let storage = Storage::init_at(&temp.path().join(".mems")).unwrap();
+        scaffold(&storage, Structure::Adr).unwrap();
+        let paths: Vec<String> = storage
+            .list_mems()
+            .unwrap()
+            .iter()
+            .map(|m| m.path_str())
+            .collect();
+        assert_eq!(
+            paths,
+            ["decisions/adr-001-record-decisions", "decisions/index"]
+        );
+    }
+
+    #[test]
+    fn test_ignore_local_files() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join(".gitignore"), "target\n.mems/.cache.db").unwrap();
+        let added = ignore_local_files(temp.path(), ".mems").unwrap();
+        assert_eq!(
+            added,
+            [".mems/.index/", ".mems/.linkcache", ".mems/.usage.json"]
+        );
+        let text = fs::read_to_string(temp.path().join(".gitignore")).unwrap();
+        assert!(text.starts_with("target\n.mems/.cache.db\n# Local files"));
+        assert!(ignore_local_files(temp.path(), ".mems").unwrap().is_empty());
+    }
+}
@@ -1,20 +1,89 @@
+use crate::backend::{FsBackend, StorageBackend};
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::conflict;
+use crate::error::MemError;
+use crate::fuzzy;
+use crate::hash;
+use crate::id;
+use crate::ignore::{Ignore, IGNORE_FILE};
+use crate::links;
 use crate::mem::Mem;
+use crate::progress::Silent;
+use crate::scope;
+use crate::text;
 use anyhow::{anyhow, Context, Result};
-use std::fs::{self, File};
-use std::io::Write;
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+use tracing::{debug, trace};
 
-/// Storage manager for .mems/ directory.
+/// Frontmatter field recording when a mem was archived.
+pub const ARCHIVED_AT_FIELD: &str = "archived-at";
+
+/// Frontmatter field recording why a mem was archived.
+pub const ARCHIVE_REASON_FIELD: &str = "archive-reason";
+
+/// Suffix of archived mem files, which are compressed with zstd.
+pub const COMPRESSED_SUFFIX: &str = ".md.zst";
+
+/// zstd compression level for archived mems (the library default).
+const COMPRESSION_LEVEL: i32 = 0;
+
+/// Storage manager for a mem store (a .mems/ directory by default).
 #[derive(Debug)]
 pub struct Storage {
-    /// Root directory (.mems/)
+    /// Root directory (.mems/), or the local cache directory of a remote store
     root: PathBuf,
+
+    /// Where the store's files live
+    backend: Box<dyn StorageBackend>,
+
+    /// Whether new writes get a content checksum (`integrity.checksums`),
+    /// loaded on first write
+    checksums: OnceLock<bool>,
+
+    /// Paths skipped when listing mems (`.memsignore`), loaded on first use
+    ignore: OnceLock<Ignore>,
 }
 
 impl Storage {
     /// Create a new Storage pointing to the given root directory.
     pub fn new(root: PathBuf) -> Self {
-        Self { root }
+        let backend = Box::new(FsBackend::new(root.clone()));
+        Self::with_backend(root, backend)
+    }
+
+    /// Create a Storage over another backend.
+    ///
+    /// `root` is a local directory for derived data such as search indexes.
+    pub fn with_backend(root: PathBuf, backend: Box<dyn StorageBackend>) -> Self {
+        Self {
+            root,
+            backend,
+            checksums: OnceLock::new(),
+            ignore: OnceLock::new(),
+        }
+    }
+
+    /// Open a store location: a directory, or `s3://bucket/prefix` when built
+    /// with the `s3` feature.
+    pub fn open(location: &str) -> Result<Self> {
+        if let Some(rest) = location.strip_prefix("s3://") {
+            return open_s3(rest);
+        }
+        let root = PathBuf::from(location);
+        if !root.is_dir() {
+            return Err(MemError::StoreNotFound {
+                location: Some(location.to_string()),
+            }
+            .into());
+        }
+        Ok(Self::new(root))
     }
 
     /// Find .mems/ in current or parent directories, or return error.
@@ -28,26 +97,26 @@ impl Storage {
             }
 
             if !current.pop() {
-                return Err(anyhow!(
-                    "no .mems/ directory found (run `mem init` to create one)"
-                ));
+                return Err(MemError::StoreNotFound { location: None }.into());
             }
         }
     }
 
     /// Initialize a new .mems/ directory in the current directory.
     pub fn init() -> Result<Self> {
-        let current = std::env::current_dir()?;
-        let mems_dir = current.join(".mems");
+        Self::init_at(&std::env::current_dir()?.join(".mems"))
+    }
 
+    /// Initialize a new store at `mems_dir`, creating missing parents.
+    pub fn init_at(mems_dir: &Path) -> Result<Self> {
         if mems_dir.exists() {
-            return Err(anyhow!(".mems/ already exists"));
+            return Err(anyhow!("{} already exists", mems_dir.display()));
         }
 
-        fs::create_dir(&mems_dir).context("failed to create .mems/")?;
+        fs::create_dir_all(mems_dir).context("failed to create .mems/")?;
         fs::create_dir(mems_dir.join("archive")).context("failed to create .mems/archive/")?;
 
-        Ok(Self::new(mems_dir))
+        Ok(Self::new(mems_dir.to_path_buf()))
     }
 
     /// Get the root path.
@@ -55,214 +124,712 @@ impl Storage {
         &self.root
     }
 
+    /// The store directory, for operations that only work on local files.
+    pub fn local_root(&self) -> Result<&Path> {
+        self.backend
+            .local_root()
+            .ok_or_else(|| anyhow!("this command requires a local store"))
+    }
+
+    /// The mem prefix the current directory maps to under `scopes`, for a
+    /// local store whose repository (the directory holding it) contains the
+    /// current directory.
+    pub fn cwd_scope(&self) -> Result<Option<String>> {
+        let Some(root) = self.backend.local_root() else {
+            return Ok(None);
+        };
+        let scopes = Config::for_storage(self)?.scopes;
+        if scopes.is_empty() {
+            return Ok(None);
+        }
+        let root = root.canonicalize()?;
+        let Some(repo) = root.parent() else {
+            return Ok(None);
+        };
+        let cwd = std::env::current_dir()?.canonicalize()?;
+        let Ok(relative) = cwd.strip_prefix(repo) else {
+            return Ok(None);
+        };
+        let relative: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        Ok(scope::prefix_for(&scopes, &relative.join("/")).map(str::to_string))
+    }
+
     /// Convert a mem path to a file path.
-    fn mem_path(&self, path: &str) -> PathBuf {
+    pub fn mem_path(&self, path: &str) -> PathBuf {
         self.root.join(format!("{path}.md"))
     }
 
-    /// Write a file atomically (temp file + rename).
-    fn write_atomic(&self, path: &Path, content: &str) -> Result<()> {
-        let parent = path.parent().ok_or_else(|| anyhow!("invalid path"))?;
+    /// Map a file inside the store back to its mem path, if it is an active mem.
+    pub fn path_for_file(&self, file: &Path) -> Option<String> {
+        let rel = file.strip_prefix(&self.root).ok()?;
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        let path = rel.strip_suffix(".md")?;
 
-        // Ensure parent directories exist
-        if !parent.exists() {
-            fs::create_dir_all(parent).context("failed to create parent directories")?;
+        // Archived and hidden files are not active mems
+        if path.starts_with("archive/") || path.split('/').any(|s| s.starts_with('.')) {
+            return None;
         }
+        Some(path.to_string())
+    }
 
-        // Generate temp file name
-        let rand: u32 = rand_u32();
-        let temp_name = format!(
-            "{}.{rand:08x}.tmp",
-            path.file_name().and_then(|n| n.to_str()).unwrap_or("file")
-        );
-        let temp_path = parent.join(temp_name);
+    /// Read a store file that is not a mem (e.g. `config.yaml`), if present.
+    pub fn read_file(&self, name: &str) -> Result<Option<String>> {
+        self.backend
+            .read(name)?
+            .map(|data| String::from_utf8(data).with_context(|| format!("{name} is not UTF-8")))
+            .transpose()
+    }
 
-        // Write to temp file
-        let mut file = File::create(&temp_path).context("failed to create temp file")?;
-        file.write_all(content.as_bytes())
-            .context("failed to write content")?;
-        file.sync_all().context("failed to sync file")?;
-        drop(file);
+    /// Read any store file as bytes (e.g. an image a mem links to), if present.
+    pub fn read_bytes(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        self.backend.read(name)
+    }
 
-        // Atomic rename
-        fs::rename(&temp_path, path).context("failed to rename temp file")?;
+    /// Write a store file that is not a mem, creating parent directories.
+    pub fn write_file(&self, name: &str, data: &[u8]) -> Result<()> {
+        self.backend.write(name, data)
+    }
 
-        Ok(())
+    /// Whether a store file that is not a mem exists.
+    pub fn file_exists(&self, name: &str) -> Result<bool> {
+        self.backend.exists(name)
+    }
+
+    /// Keys of all store files under `prefix`, mems or not.
+    pub fn file_keys(&self, prefix: &str) -> Result<Vec<String>> {
+        self.backend.list(prefix)
+    }
+
+    /// Delete a store file that is not a mem.
+    pub fn delete_file(&self, name: &str) -> Result<()> {
+        self.backend.delete(name)
     }
 
     /// Write a mem to disk.
+    ///
+    /// Mems carrying a checksum get it refreshed, unless it did not match
+    /// when read (see `Mem::tampered`); with `integrity.checksums` enabled,
+    /// mems without one get one added.
     pub fn write_mem(&self, mem: &Mem) -> Result<()> {
-        let path = self.mem_path(mem.path.to_str().ok_or_else(|| anyhow!("invalid path"))?);
-        let content = mem.serialize()?;
-        self.write_atomic(&path, &content)
+        let path = mem.path.to_str().ok_or_else(|| anyhow!("invalid path"))?;
+        let path = normalize_path(path)?;
+        let content = self.render(mem)?;
+        self.backend
+            .write(&format!("{path}.md"), content.as_bytes())?;
+        debug!(path, bytes = content.len(), "wrote mem");
+        self.update_cache(&path);
+        Ok(())
     }
 
-    /// Read a mem from disk.
-    pub fn read_mem(&self, path: &str) -> Result<Mem> {
-        let file_path = self.mem_path(path);
+    /// Touch and write a mem unless its file already holds it, comparing
+    /// content hashes of the serialized mems with `updated-at` left out. An
+    /// unchanged mem keeps the file's `updated-at`. Returns whether it wrote.
+    pub fn write_mem_if_changed(&self, mem: &mut Mem) -> Result<bool> {
+        let path = mem.path.to_str().ok_or_else(|| anyhow!("invalid path"))?;
+        let path = normalize_path(path)?;
+        if let Ok(old) = self.read_mem(&path) {
+            let mut unchanged = mem.clone();
+            unchanged.updated_at = old.updated_at;
+            let old_hash = hash::sha256_hex(self.render(&old)?.as_bytes());
+            if old_hash == hash::sha256_hex(self.render(&unchanged)?.as_bytes()) {
+                debug!(path, "mem unchanged, not writing");
+                mem.updated_at = old.updated_at;
+                return Ok(false);
+            }
+        }
+        mem.touch();
+        self.write_mem(mem)?;
+        Ok(true)
+    }
 
-        if !file_path.exists() {
-            return Err(anyhow!("mem not found: {path}"));
+    /// The file contents `write_mem` writes for a mem.
+    fn render(&self, mem: &Mem) -> Result<String> {
+        if mem.checksum.is_none() && self.checksums_enabled()? {
+            let mut mem = mem.clone();
+            mem.checksum = Some(String::new());
+            return mem.serialize();
         }
+        mem.serialize()
+    }
 
-        let content = fs::read_to_string(&file_path).context("failed to read file")?;
-        Mem::parse(PathBuf::from(path), &content)
+    fn checksums_enabled(&self) -> Result<bool> {
+        if let Some(enabled) = self.checksums.get() {
+            return Ok(*enabled);
+        }
+        let enabled = Config::for_storage(self)?.integrity.checksums;
+        Ok(*self.checksums.get_or_init(|| enabled))
     }
 
-    /// Check if a mem exists.
+    /// Read a mem's raw file contents, including frontmatter.
+    pub fn read_source(&self, path: &str) -> Result<String> {
+        let path = normalize_path(path)?;
+        trace!(path, "reading mem");
+        let data = self
+            .backend
+            .read(&format!("{path}.md"))?
+            .ok_or(MemError::NotFound {
+                path: path.clone(),
+                suggestions: Vec::new(),
+            })?;
+        Ok(text::decode(data, &format!("{path}.md"))?)
+    }
+
+    /// Write a mem's raw file contents as given, such as what `read_source`
+    /// returned, without refreshing its checksum.
+    pub fn write_source(&self, path: &str, source: &str) -> Result<()> {
+        let path = normalize_path(path)?;
+        self.backend
+            .write(&format!("{path}.md"), source.as_bytes())?;
+        debug!(path, bytes = source.len(), "wrote mem source");
+        self.update_cache(&path);
+        Ok(())
+    }
+
+    /// Read a mem from disk.
+    pub fn read_mem(&self, path: &str) -> Result<Mem> {
+        let content = self.read_source(path)?;
+        Mem::parse(PathBuf::from(normalize_path(path)?), &content)
+    }
+
+    /// Read only a mem's frontmatter, leaving its content empty, so a
+    /// listing doesn't load the body of a large mem.
+    pub fn read_mem_header(&self, path: &str) -> Result<Mem> {
+        let path = normalize_path(path)?;
+        let data = self
+            .backend
+            .read_frontmatter(&format!("{path}.md"))?
+            .ok_or(MemError::NotFound {
+                path: path.clone(),
+                suggestions: Vec::new(),
+            })?;
+        let head = text::decode(data, &format!("{path}.md"))?;
+        Mem::parse(PathBuf::from(path), &head)
+    }
+
+    /// Check if a mem exists (invalid paths never do).
     pub fn exists(&self, path: &str) -> bool {
-        self.mem_path(path).exists()
+        normalize_path(path)
+            .map(|path| self.backend.exists(&format!("{path}.md")).unwrap_or(false))
+            .unwrap_or(false)
     }
 
     /// Delete a mem and clean up empty parent directories.
     pub fn delete_mem(&self, path: &str) -> Result<()> {
-        let file_path = self.mem_path(path);
+        let path = self.existing(path)?;
+        self.backend.delete(&format!("{path}.md"))?;
+        debug!(path, "deleted mem");
+        self.update_cache(&path);
+        Ok(())
+    }
 
-        if !file_path.exists() {
-            return Err(anyhow!("mem not found: {path}"));
+    /// Normalize a path, failing if no mem exists there.
+    fn existing(&self, path: &str) -> Result<String> {
+        let path = normalize_path(path)?;
+        if !self.exists(&path) {
+            return Err(MemError::NotFound {
+                path,
+                suggestions: Vec::new(),
+            }
+            .into());
         }
+        Ok(path)
+    }
 
-        fs::remove_file(&file_path).context("failed to delete file")?;
+    /// Find the mem a user-supplied path refers to.
+    ///
+    /// Paths are stored case-sensitively, but when there is no exact match a
+    /// unique case-insensitive match is accepted. A miss suggests close paths.
+    pub fn lookup(&self, path: &str) -> Result<String> {
+        let path = normalize_path(path)?;
+        if self.exists(&path) {
+            return Ok(path);
+        }
 
-        // Clean up empty parent directories (but not .mems/ itself)
-        let mut parent = file_path.parent();
-        while let Some(p) = parent {
-            if p == self.root {
-                break;
-            }
-            if p.read_dir()
-                .map(|mut d| d.next().is_none())
-                .unwrap_or(false)
-            {
-                fs::remove_dir(p).ok();
-                parent = p.parent();
-            } else {
-                break;
+        let known = self.mem_keys("")?;
+        let mut candidates: Vec<String> = known
+            .iter()
+            .filter(|k| text::same(k, &path))
+            .cloned()
+            .collect();
+        match candidates.len() {
+            0 => Err(MemError::NotFound {
+                suggestions: fuzzy::suggestions(&path, known.iter().map(String::as_str)),
+                path,
             }
+            .into()),
+            1 => Ok(candidates.remove(0)),
+            _ => Err(MemError::Ambiguous { path, candidates }.into()),
         }
-
-        Ok(())
     }
 
     /// List all mems in the storage (excluding archive).
     pub fn list_mems(&self) -> Result<Vec<Mem>> {
-        self.list_mems_in(&self.root, "")
+        self.list_mems_under("")
     }
 
     /// List mems under a specific path.
     pub fn list_mems_under(&self, prefix: &str) -> Result<Vec<Mem>> {
-        let dir = self.root.join(prefix);
-        if !dir.exists() {
-            return Ok(Vec::new());
-        }
-        self.list_mems_in(&dir, prefix)
+        self.list_under(prefix, Storage::read_mem)
     }
 
-    fn list_mems_in(&self, dir: &Path, prefix: &str) -> Result<Vec<Mem>> {
-        let mut mems = Vec::new();
+    /// List mems under a path with only their frontmatter read (content is
+    /// empty), for listings that show titles, tags, and fields.
+    pub fn list_headers_under(&self, prefix: &str) -> Result<Vec<Mem>> {
+        self.list_under(prefix, Storage::read_mem_header)
+    }
 
-        if !dir.is_dir() {
+    /// Mems under a path in path order, each read only when the iterator
+    /// reaches it, so streaming a large store holds one mem at a time.
+    pub fn iter_mems_under(&self, prefix: &str) -> Result<impl Iterator<Item = Mem> + '_> {
+        let mut paths = self.mem_keys(prefix)?;
+        paths.sort_by(|a, b| Path::new(a).cmp(Path::new(b)));
+        Ok(self.read_each(paths))
+    }
+
+    /// Read the mems at `paths` lazily and in order, skipping invalid mems
+    /// with a warning as listings do.
+    pub fn read_each(&self, paths: Vec<String>) -> impl Iterator<Item = Mem> + '_ {
+        paths
+            .into_iter()
+            .filter_map(move |path| match self.read_mem(&path) {
+                Ok(mem) => Some(mem),
+                Err(e) => {
+                    eprintln!("warning: skipping invalid mem {path}: {e}");
+                    None
+                }
+            })
+    }
+
+    fn list_under(
+        &self,
+        prefix: &str,
+        read: fn(&Storage, &str) -> Result<Mem>,
+    ) -> Result<Vec<Mem>> {
+        let start = Instant::now();
+        if let Some(cache) = self.cache()? {
+            let mut mems = cache.mems_under(prefix)?;
+            let ignore = self.ignore()?;
+            mems.retain(|m| !ignore.is_ignored(&m.path_str()));
+            debug!(
+                prefix,
+                count = mems.len(),
+                elapsed_ms = start.elapsed().as_millis() as u64,
+                "listed mems from cache"
+            );
             return Ok(mems);
         }
 
-        for entry in fs::read_dir(dir).context("failed to read directory")? {
-            let entry = entry?;
-            let path = entry.path();
-            let name = entry.file_name();
-            let name_str = name.to_string_lossy();
+        let mut mems = Vec::new();
+        for mem_path in self.mem_keys(prefix)? {
+            match read(self, &mem_path) {
+                Ok(mem) => mems.push(mem),
+                Err(e) => {
+                    eprintln!("warning: skipping invalid mem {mem_path}: {e}");
+                }
+            }
+        }
+
+        // Sort by path
+        mems.sort_by(|a, b| a.path.cmp(&b.path));
+        debug!(
+            prefix,
+            count = mems.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "listed mems"
+        );
+
+        Ok(mems)
+    }
 
+    /// The store's `.memsignore` rules (none without the file).
+    pub fn ignore(&self) -> Result<&Ignore> {
+        if let Some(ignore) = self.ignore.get() {
+            return Ok(ignore);
+        }
+        let ignore = match self.backend.read(IGNORE_FILE)? {
+            Some(data) => Ignore::parse(&String::from_utf8_lossy(&data)),
+            None => Ignore::default(),
+        };
+        Ok(self.ignore.get_or_init(|| ignore))
+    }
+
+    /// Paths of the mems under a prefix, without reading them (a cheap
+    /// index for lookups and suggestions). Paths `.memsignore` matches are
+    /// left out.
+    pub fn mem_keys(&self, prefix: &str) -> Result<Vec<String>> {
+        let ignore = self.ignore()?;
+        let mut paths = Vec::new();
+        for key in self.backend.list(prefix)? {
             // Skip archive directory at root level
-            if prefix.is_empty() && name_str == "archive" {
+            if prefix.is_empty() && key.starts_with("archive/") {
                 continue;
             }
 
-            // Skip hidden files and temp files
-            if name_str.starts_with('.') || name_str.ends_with(".tmp") {
-                continue;
+            // Skip anything that is not markdown
+            if let Some(mem_path) = key.strip_suffix(".md") {
+                if !ignore.is_ignored(mem_path) {
+                    paths.push(mem_path.to_string());
+                }
             }
+        }
+        Ok(paths)
+    }
 
-            if path.is_dir() {
-                // Recurse into subdirectory
-                let sub_prefix = if prefix.is_empty() {
-                    name_str.to_string()
-                } else {
-                    format!("{prefix}/{name_str}")
-                };
-                mems.extend(self.list_mems_in(&path, &sub_prefix)?);
-            } else if path.extension().map(|e| e == "md").unwrap_or(false) {
-                // Parse markdown file
-                let mem_path = if prefix.is_empty() {
-                    name_str.trim_end_matches(".md").to_string()
-                } else {
-                    format!("{prefix}/{}", name_str.trim_end_matches(".md"))
-                };
-
-                match self.read_mem(&mem_path) {
-                    Ok(mem) => mems.push(mem),
-                    Err(e) => {
-                        eprintln!("warning: skipping invalid mem {mem_path}: {e}");
-                    }
-                }
+    /// Paths of the mems whose files contain unresolved merge conflicts.
+    pub fn conflicted_mems(&self) -> Result<Vec<String>> {
+        let mut paths = Vec::new();
+        for path in self.mem_keys("")? {
+            if conflict::has_markers(&self.read_source(&path)?) {
+                paths.push(path);
             }
         }
+        Ok(paths)
+    }
 
-        // Sort by path
-        mems.sort_by(|a, b| a.path.cmp(&b.path));
+    /// The metadata cache, if enabled for this store, validated against the files.
+    ///
+    /// Only local stores have a cache.
+    pub fn cache(&self) -> Result<Option<Cache>> {
+        let Some(root) = self.backend.local_root() else {
+            return Ok(None);
+        };
+        let Some(mut cache) = Cache::open(root)? else {
+            return Ok(None);
+        };
+        cache.refresh(self, &Silent)?;
+        Ok(Some(cache))
+    }
 
-        Ok(mems)
+    /// Keep the cache entry for a mem current after a change.
+    ///
+    /// Failures only warn: the cache revalidates itself on the next read.
+    fn update_cache(&self, path: &str) {
+        let Some(root) = self.backend.local_root() else {
+            return;
+        };
+        trace!(path, "updating cache entry");
+        let result = Cache::open(root).and_then(|cache| match cache {
+            Some(mut cache) => cache.sync_path(self, path),
+            None => Ok(()),
+        });
+        if let Err(e) = result {
+            eprintln!("warning: failed to update cache: {e}");
+        }
     }
 
-    /// Move a mem to the archive.
-    pub fn archive_mem(&self, path: &str) -> Result<()> {
-        let src = self.mem_path(path);
-        if !src.exists() {
-            return Err(anyhow!("mem not found: {path}"));
+    /// Every tag with the number of mems using it, most used first.
+    pub fn tag_counts(&self) -> Result<Vec<(String, usize)>> {
+        if let Some(cache) = self.cache()? {
+            return cache.tag_counts();
         }
 
-        let archive_path = self.root.join("archive").join(format!("{path}.md"));
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for mem in self.list_headers_under("")? {
+            let mut tags = mem.tags.clone();
+            tags.sort();
+            tags.dedup();
+            for tag in tags {
+                *counts.entry(text::tag(&tag)).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(counts)
+    }
 
-        // Ensure parent directories exist in archive
-        if let Some(parent) = archive_path.parent() {
-            fs::create_dir_all(parent).context("failed to create archive directories")?;
+    /// Paths of the mems that link to `path`.
+    pub fn backlinks(&self, path: &str) -> Result<Vec<String>> {
+        if let Some(cache) = self.cache()? {
+            return cache.backlinks(path);
         }
+        Ok(links::backlinks(&self.list_mems()?, path))
+    }
 
-        fs::rename(&src, &archive_path).context("failed to move to archive")?;
+    /// Path of the mem with the given id, if any. An id that several mems
+    /// have (such as a copy made outside mem) is ambiguous.
+    pub fn path_for_id(&self, id: &str) -> Result<Option<String>> {
+        let mut paths = match self.cache()? {
+            Some(cache) => cache.paths_for_id(id)?,
+            None => self
+                .list_mems()?
+                .into_iter()
+                .filter(|m| m.id.as_deref().is_some_and(|i| i.eq_ignore_ascii_case(id)))
+                .map(|m| m.path_str())
+                .collect(),
+        };
+        if paths.len() > 1 {
+            return Err(MemError::Ambiguous {
+                path: format!("{}{id}", id::ID_PREFIX),
+                candidates: paths,
+            }
+            .into());
+        }
+        Ok(paths.pop())
+    }
+
+    /// Whether a mem other than the one at `path` has the given id.
+    pub fn id_taken(&self, id: &str, path: &str) -> Result<bool> {
+        match self.path_for_id(id) {
+            Ok(owner) => Ok(owner.is_some_and(|owner| owner != path)),
+            // Several mems share it already
+            Err(e) if matches!(e.downcast_ref(), Some(MemError::Ambiguous { .. })) => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Resolve a mem reference to an existing mem: either a path (see
+    /// [`Storage::lookup`]) or `id:<ulid>`.
+    pub fn resolve(&self, reference: &str) -> Result<String> {
+        match id::parse_reference(reference) {
+            Some(id) => self.path_for_id(id)?.ok_or_else(|| {
+                MemError::NotFound {
+                    path: reference.to_string(),
+                    suggestions: Vec::new(),
+                }
+                .into()
+            }),
+            None => self.lookup(reference),
+        }
+    }
+
+    /// Move a mem to a new path, keeping its id.
+    pub fn move_mem(&self, from: &str, to: &str) -> Result<()> {
+        let from = self.existing(from)?;
+        let to = normalize_path(to)?;
+        if self.exists(&to) {
+            return Err(anyhow!("mem already exists: {to}"));
+        }
+        debug!(from, to, "moving mem");
+        // A symlinked mem moves as a link, still sharing its file
+        if self.backend.is_link(&format!("{from}.md"))? {
+            self.backend
+                .rename(&format!("{from}.md"), &format!("{to}.md"))?;
+            self.update_cache(&from);
+            self.update_cache(&to);
+            return Ok(());
+        }
+        let mut mem = self.read_mem(&from)?;
+        mem.path = PathBuf::from(to);
+        self.write_mem(&mem)?;
+        self.delete_mem(&from)
+    }
 
-        // Clean up empty parent directories
-        let mut parent = src.parent();
-        while let Some(p) = parent {
-            if p == self.root {
-                break;
+    /// Move a mem to the archive, recording when in its `archived-at` field
+    /// and any reason in `archive-reason`. Archived mems are stored
+    /// compressed.
+    ///
+    /// Files that do not parse as mems are archived unchanged.
+    pub fn archive_mem(&self, path: &str, reason: Option<&str>) -> Result<()> {
+        let path = self.existing(path)?;
+        let from = format!("{path}.md");
+        let data = match self.read_mem(&path) {
+            Ok(mut mem) => {
+                mem.set_field(ARCHIVED_AT_FIELD, Utc::now().to_rfc3339());
+                if let Some(reason) = reason {
+                    mem.set_field(ARCHIVE_REASON_FIELD, reason);
+                }
+                mem.serialize()?.into_bytes()
             }
-            if p.read_dir()
-                .map(|mut d| d.next().is_none())
-                .unwrap_or(false)
+            Err(_) => self.backend.read(&from)?.unwrap_or_default(),
+        };
+        self.backend
+            .write(
+                &format!("archive/{path}{COMPRESSED_SUFFIX}"),
+                &zstd::encode_all(data.as_slice(), COMPRESSION_LEVEL)?,
+            )
+            .context("failed to move to archive")?;
+        self.backend.delete(&from)?;
+        debug!(path, "archived mem");
+        self.update_cache(&path);
+        Ok(())
+    }
+
+    /// Key of an archived mem's file: compressed, or plain markdown when
+    /// archived before archives were compressed and not yet compacted.
+    fn archived_key(&self, path: &str) -> Result<Option<String>> {
+        for suffix in [COMPRESSED_SUFFIX, ".md"] {
+            let key = format!("archive/{path}{suffix}");
+            if self.backend.exists(&key)? {
+                return Ok(Some(key));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Archived mem files under a prefix: their paths before archiving, and
+    /// their keys.
+    fn archived_keys(&self, prefix: &str) -> Result<Vec<(String, String)>> {
+        let mut keys = Vec::new();
+        let dir = match prefix.trim_end_matches('/') {
+            "" => "archive".to_string(),
+            prefix => format!("archive/{prefix}"),
+        };
+        for key in self.backend.list(&dir)? {
+            let Some(name) = key.strip_prefix("archive/") else {
+                continue;
+            };
+            if let Some(path) = name
+                .strip_suffix(COMPRESSED_SUFFIX)
+                .or_else(|| name.strip_suffix(".md"))
             {
-                fs::remove_dir(p).ok();
-                parent = p.parent();
-            } else {
-                break;
+                keys.push((path.to_string(), key.clone()));
             }
         }
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Read an archived mem file, decompressing it if needed.
+    fn read_archived_key(&self, path: &str, key: &str) -> Result<Mem> {
+        let data = self
+            .backend
+            .read(key)?
+            .ok_or_else(|| anyhow!("archived mem disappeared: {path}"))?;
+        let data = if key.ends_with(COMPRESSED_SUFFIX) {
+            zstd::decode_all(data.as_slice()).context("failed to decompress archived mem")?
+        } else {
+            data
+        };
+        let source = String::from_utf8(data).context("failed to read file")?;
+        Mem::parse(PathBuf::from(path), &source)
+    }
+
+    /// Whether a mem with this path (before archiving) is in the archive.
+    pub fn is_archived(&self, path: &str) -> bool {
+        normalize_path(path)
+            .map(|path| matches!(self.archived_key(&path), Ok(Some(_))))
+            .unwrap_or(false)
+    }
 
+    /// Read an archived mem by its path before archiving.
+    pub fn read_archived(&self, path: &str) -> Result<Mem> {
+        let path = normalize_path(path)?;
+        let Some(key) = self.archived_key(&path)? else {
+            let known: Vec<String> = self
+                .archived_keys("")?
+                .into_iter()
+                .map(|(p, _)| p)
+                .collect();
+            return Err(MemError::NotFound {
+                suggestions: fuzzy::suggestions(&path, known.iter().map(String::as_str)),
+                path: format!("archive/{path}"),
+            }
+            .into());
+        };
+        self.read_archived_key(&path, &key)
+    }
+
+    /// Delete an archived mem for good, by its path before archiving.
+    pub fn purge_archived(&self, path: &str) -> Result<()> {
+        let path = normalize_path(path)?;
+        if let Some(key) = self.archived_key(&path)? {
+            self.backend.delete(&key)?;
+        }
+        debug!(path, "purged archived mem");
         Ok(())
     }
+
+    /// Archived mems, with their paths as they were before archiving.
+    pub fn archived_mems(&self) -> Result<Vec<Mem>> {
+        self.archived_mems_under("")
+    }
+
+    /// Archived mems whose paths before archiving were under a prefix.
+    pub fn archived_mems_under(&self, prefix: &str) -> Result<Vec<Mem>> {
+        let mut mems = Vec::new();
+        for (path, key) in self.archived_keys(prefix)? {
+            match self.read_archived_key(&path, &key) {
+                Ok(mem) => mems.push(mem),
+                Err(e) => eprintln!("warning: skipping invalid archived mem {key}: {e}"),
+            }
+        }
+        Ok(mems)
+    }
+
+    /// Compress archived mems stored as plain markdown, returning each one
+    /// compressed with its size before and after.
+    pub fn compact_archive(&self) -> Result<Vec<Compacted>> {
+        let mut compacted = Vec::new();
+        for (path, key) in self.archived_keys("")? {
+            if key.ends_with(COMPRESSED_SUFFIX) {
+                continue;
+            }
+            let Some(data) = self.backend.read(&key)? else {
+                continue;
+            };
+            let compressed = zstd::encode_all(data.as_slice(), COMPRESSION_LEVEL)?;
+            self.backend
+                .write(&format!("archive/{path}{COMPRESSED_SUFFIX}"), &compressed)?;
+            self.backend.delete(&key)?;
+            debug!(path, "compressed archived mem");
+            compacted.push(Compacted {
+                path,
+                before: data.len() as u64,
+                after: compressed.len() as u64,
+            });
+        }
+        Ok(compacted)
+    }
+}
+
+/// An archived mem compressed by `Storage::compact_archive`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Compacted {
+    /// Path before archiving
+    pub path: String,
+
+    /// Size in bytes before and after compression
+    pub before: u64,
+    pub after: u64,
 }
 
-/// Simple random u32 using system entropy.
-fn rand_u32() -> u32 {
-    use std::collections::hash_map::RandomState;
-    use std::hash::{BuildHasher, Hasher};
-
-    let state = RandomState::new();
-    let mut hasher = state.build_hasher();
-    hasher.write_u64(
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map(|d| d.as_nanos() as u64)
-            .unwrap_or(0),
-    );
-    hasher.finish() as u32
+/// Normalize a mem path: backslashes become `/`, empty, `.`, and trailing
+/// segments are dropped, and the rest is put in Unicode NFC. Absolute paths and `..` are rejected so a
+/// mem can never be written outside its store.
+pub fn normalize_path(path: &str) -> Result<String, MemError> {
+    let unified = path.replace('\\', "/");
+    let is_drive = unified.len() >= 2
+        && unified.as_bytes()[1] == b':'
+        && unified.as_bytes()[0].is_ascii_alphabetic();
+    if unified.starts_with('/') || is_drive {
+        return Err(MemError::AbsolutePath {
+            path: path.to_string(),
+        });
+    }
+
+    let mut segments = Vec::new();
+    for segment in unified.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                return Err(MemError::ParentSegment {
+                    path: path.to_string(),
+                })
+            }
+            s => segments.push(s),
+        }
+    }
+    if segments.is_empty() {
+        return Err(MemError::EmptyPath);
+    }
+    Ok(text::nfc(&segments.join("/")))
+}
+
+#[cfg(feature = "s3")]
+fn open_s3(location: &str) -> Result<Storage> {
+    let backend = crate::s3::S3Backend::from_env(location)?;
+    let root = backend.cache_dir();
+    Ok(Storage::with_backend(root, Box::new(backend)))
+}
+
+#[cfg(not(feature = "s3"))]
+fn open_s3(_location: &str) -> Result<Storage> {
+    Err(anyhow!(
+        "S3 stores are not supported in this build (rebuild with --features s3)"
+    ))
 }
 
 #[cfg(test)]
@@ -295,6 +862,26 @@ mod tests {
         assert_eq!(loaded.content, "Hello, world!");
     }
 
+    #[test]
+    fn test_write_mem_if_changed() {
+        let (_temp, storage) = setup_storage();
+
+        let mut mem = Mem::new(PathBuf::from("doc"), "Doc".to_string(), "Same.".to_string());
+        assert!(storage.write_mem_if_changed(&mut mem).unwrap());
+        let written = storage.read_source("doc").unwrap();
+
+        // Touching alone does not count as a change
+        let mut touched = storage.read_mem("doc").unwrap();
+        touched.updated_at += chrono::Duration::seconds(60);
+        assert!(!storage.write_mem_if_changed(&mut touched).unwrap());
+        assert_eq!(touched.updated_at, mem.updated_at);
+        assert_eq!(storage.read_source("doc").unwrap(), written);
+
+        mem.content = "Different.".to_string();
+        assert!(storage.write_mem_if_changed(&mut mem).unwrap());
+        assert_eq!(storage.read_mem("doc").unwrap().content, "Different.");
+    }
+
     #[test]
     fn test_write_creates_directories() {
         let (_temp, storage) = setup_storage();
@@ -371,6 +958,43 @@ mod tests {
         assert!(paths.contains(&"doc1"));
     }
 
+    #[test]
+    fn test_iter_mems_under() {
+        let (_temp, storage) = setup_storage();
+        for path in ["a-b", "a/c", "a/b", "z"] {
+            storage
+                .write_mem(&Mem::new(
+                    PathBuf::from(path),
+                    path.to_string(),
+                    String::new(),
+                ))
+                .unwrap();
+        }
+        fs::write(storage.root().join("a/bad.md"), "---\ntitle: [\n---\n").unwrap();
+
+        // Same order as list_mems, and invalid mems are skipped
+        let listed: Vec<_> = storage
+            .list_mems()
+            .unwrap()
+            .into_iter()
+            .map(|m| m.path)
+            .collect();
+        let streamed: Vec<_> = storage
+            .iter_mems_under("")
+            .unwrap()
+            .map(|m| m.path)
+            .collect();
+        assert_eq!(streamed, listed);
+        assert_eq!(streamed.len(), 4);
+
+        let under: Vec<_> = storage
+            .iter_mems_under("a")
+            .unwrap()
+            .map(|m| m.path_str())
+            .collect();
+        assert_eq!(under, ["a/b", "a/c"]);
+    }
+
     #[test]
     fn test_list_mems_excludes_archive() {
         let (_temp, storage) = setup_storage();
@@ -383,7 +1007,7 @@ mod tests {
             ))
             .unwrap();
 
-        storage.archive_mem("active").unwrap();
+        storage.archive_mem("active", None).unwrap();
 
         let mems = storage.list_mems().unwrap();
         assert!(mems.is_empty());
@@ -400,10 +1024,52 @@ mod tests {
         );
 
         storage.write_mem(&mem).unwrap();
-        storage.archive_mem("to-archive").unwrap();
+        storage
+            .archive_mem("to-archive", Some("superseded by adr-017"))
+            .unwrap();
 
         assert!(!storage.exists("to-archive"));
-        assert!(storage.root().join("archive/to-archive.md").exists());
+        assert!(storage.root().join("archive/to-archive.md.zst").exists());
+        assert!(storage.is_archived("to-archive"));
+
+        let archived = storage.archived_mems().unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].path, PathBuf::from("to-archive"));
+        assert_eq!(archived[0].id, mem.id);
+        assert!(archived[0].field(ARCHIVED_AT_FIELD).is_some());
+        assert_eq!(
+            archived[0].field(ARCHIVE_REASON_FIELD),
+            Some("superseded by adr-017")
+        );
+        assert_eq!(
+            storage.read_archived("to-archive").unwrap().content,
+            "Content"
+        );
+
+        storage.purge_archived("to-archive").unwrap();
+        assert!(!storage.is_archived("to-archive"));
+    }
+
+    #[test]
+    fn test_compact_archive() {
+        let (_temp, storage) = setup_storage();
+
+        // Archived as plain markdown, before archives were compressed
+        let mem = Mem::new(
+            PathBuf::from("old"),
+            "Old".to_string(),
+            "Long forgotten. ".repeat(50),
+        );
+        let plain = storage.root().join("archive/old.md");
+        fs::write(&plain, mem.serialize().unwrap()).unwrap();
+        assert_eq!(storage.read_archived("old").unwrap().title, "Old");
+
+        let compacted = storage.compact_archive().unwrap();
+        assert_eq!(compacted.len(), 1);
+        assert!(compacted[0].after < compacted[0].before);
+        assert!(!plain.exists());
+        assert_eq!(storage.read_archived("old").unwrap().content, mem.content);
+        assert!(storage.compact_archive().unwrap().is_empty());
     }
 
     #[test]
@@ -417,10 +1083,127 @@ mod tests {
         );
 
         storage.write_mem(&mem).unwrap();
-        storage.archive_mem("a/b/nested").unwrap();
+        storage.archive_mem("a/b/nested", None).unwrap();
 
         assert!(!storage.exists("a/b/nested"));
-        assert!(storage.root().join("archive/a/b/nested.md").exists());
+        assert!(storage.root().join("archive/a/b/nested.md.zst").exists());
+    }
+
+    #[test]
+    fn test_move_keeps_id() {
+        let (_temp, storage) = setup_storage();
+
+        let mem = Mem::new(
+            PathBuf::from("old/name"),
+            "Moved".to_string(),
+            "Content".to_string(),
+        );
+        let id = mem.id.clone().unwrap();
+        storage.write_mem(&mem).unwrap();
+        storage
+            .write_mem(&Mem::new(
+                PathBuf::from("taken"),
+                "Taken".to_string(),
+                String::new(),
+            ))
+            .unwrap();
+
+        assert!(storage.move_mem("old/name", "taken").is_err());
+        storage.move_mem("old/name", "new/name").unwrap();
+
+        assert!(!storage.exists("old/name"));
+        assert_eq!(storage.read_mem("new/name").unwrap().id, Some(id.clone()));
+        assert_eq!(
+            storage
+                .resolve(&format!("id:{}", id.to_lowercase()))
+                .unwrap(),
+            "new/name"
+        );
+        assert_eq!(storage.resolve("NEW/name").unwrap(), "new/name");
+        assert!(storage.resolve("old/name").is_err());
+        assert!(storage.resolve("id:01ARZ3NDEKTSV4RRFFQ69G5FAV").is_err());
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("a\\b/").unwrap(), "a/b");
+        assert_eq!(normalize_path("./a//b").unwrap(), "a/b");
+        assert_eq!(
+            normalize_path("notes/cafe\u{301}").unwrap(),
+            "notes/caf\u{e9}"
+        );
+        assert_eq!(
+            normalize_path("/etc/passwd"),
+            Err(MemError::AbsolutePath {
+                path: "/etc/passwd".to_string()
+            })
+        );
+        assert_eq!(
+            normalize_path("C:\\x").unwrap_err(),
+            MemError::AbsolutePath {
+                path: "C:\\x".to_string()
+            }
+        );
+        assert_eq!(
+            normalize_path("../escape"),
+            Err(MemError::ParentSegment {
+                path: "../escape".to_string()
+            })
+        );
+        assert_eq!(
+            normalize_path("/"),
+            Err(MemError::AbsolutePath {
+                path: "/".to_string()
+            })
+        );
+        assert_eq!(normalize_path("./"), Err(MemError::EmptyPath));
+    }
+
+    #[test]
+    fn test_lookup_case_insensitive() {
+        let (_temp, storage) = setup_storage();
+        for path in ["Arch/ADR-001", "notes/Todo", "notes/TODO"] {
+            storage
+                .write_mem(&Mem::new(
+                    PathBuf::from(path),
+                    path.to_string(),
+                    String::new(),
+                ))
+                .unwrap();
+        }
+
+        assert_eq!(storage.lookup("arch/adr-001/").unwrap(), "Arch/ADR-001");
+        assert_eq!(storage.lookup("notes/TODO").unwrap(), "notes/TODO");
+        let err = storage.lookup("notes/todo").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MemError>(),
+            Some(&MemError::Ambiguous {
+                path: "notes/todo".to_string(),
+                candidates: vec!["notes/TODO".to_string(), "notes/Todo".to_string()],
+            })
+        );
+        assert!(storage
+            .write_mem(&Mem::new(
+                PathBuf::from("../x"),
+                "X".to_string(),
+                String::new()
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn test_path_for_file() {
+        let (_temp, storage) = setup_storage();
+        let root = storage.root();
+
+        assert_eq!(
+            storage.path_for_file(&root.join("a/b.md")).as_deref(),
+            Some("a/b")
+        );
+        assert_eq!(storage.path_for_file(&root.join("archive/old.md")), None);
+        assert_eq!(storage.path_for_file(&root.join(".index/x.md")), None);
+        assert_eq!(storage.path_for_file(&root.join("notes.txt")), None);
+        assert_eq!(storage.path_for_file(Path::new("/elsewhere/a.md")), None);
     }
 
     #[test]
@@ -0,0 +1,224 @@
+//! Markdown structure of mem content: headings and paragraphs, skipping
+//! fenced code blocks, and the code blocks themselves.
+
+/// A markdown heading.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    /// 1 for `#` through 6 for `######`
+    pub level: usize,
+    pub text: String,
+
+    /// 1-based line within the content
+    pub line: usize,
+}
+
+impl Heading {
+    /// Whether this heading satisfies a required heading such as
+    /// `## Context` (level and text) or `Context` (text at any level).
+    /// Text is compared case-insensitively.
+    pub fn matches(&self, required: &str) -> bool {
+        match parse_heading(required.trim()) {
+            Some((level, text)) => level == self.level && text.eq_ignore_ascii_case(&self.text),
+            None => required.trim().eq_ignore_ascii_case(&self.text),
+        }
+    }
+}
+
+/// A run of prose lines between blank lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paragraph {
+    /// 1-based line within the content where the paragraph starts
+    pub line: usize,
+    pub words: usize,
+}
+
+/// A fenced code block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+    /// 1-based line within the content of the opening fence
+    pub line: usize,
+
+    /// What follows the opening fence, such as `sh`
+    pub language: String,
+
+    /// Lines inside the fence, without the indentation the fence has
+    pub code: String,
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+/// `line` without up to `indent` characters of leading whitespace.
+fn dedent(line: &str, indent: usize) -> &str {
+    let mut rest = line;
+    for _ in 0..indent {
+        let mut chars = rest.chars();
+        match chars.next() {
+            Some(c) if c.is_whitespace() => rest = chars.as_str(),
+            _ => break,
+        }
+    }
+    rest
+}
+
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    line[level..]
+        .strip_prefix(' ')
+        .map(|text| (level, text.trim()))
+}
+
+/// Lines outside fenced code blocks, with their 1-based line numbers.
+pub fn prose_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut in_fence = false;
+    content.lines().enumerate().filter_map(move |(i, line)| {
+        if is_fence(line) {
+            in_fence = !in_fence;
+            return None;
+        }
+        (!in_fence).then_some((i + 1, line))
+    })
+}
+
+/// Fenced code blocks in document order. A fence left open runs to the end
+/// of the content and is not a block.
+pub fn code_blocks(content: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(CodeBlock, usize)> = None;
+    for (i, line) in content.lines().enumerate() {
+        match open.take() {
+            Some((block, _)) if is_fence(line) => blocks.push(block),
+            Some((mut block, indent)) => {
+                block.code.push_str(dedent(line, indent));
+                block.code.push('\n');
+                open = Some((block, indent));
+            }
+            None if is_fence(line) => {
+                let trimmed = line.trim_start();
+                let block = CodeBlock {
+                    line: i + 1,
+                    language: trimmed.trim_start_matches('`').trim().to_string(),
+                    code: String::new(),
+                };
+                open = Some((block, line.chars().count() - trimmed.chars().count()));
+            }
+            None => {}
+        }
+    }
+    blocks
+}
+
+/// Headings in document order.
+pub fn headings(content: &str) -> Vec<Heading> {
+    prose_lines(content)
+        .filter_map(|(line, text)| {
+            parse_heading(text).map(|(level, text)| Heading {
+                level,
+                text: text.to_string(),
+                line,
+            })
+        })
+        .collect()
+}
+
+/// Paragraphs of prose. Headings, list items, tables, and quotes end a
+/// paragraph and are not counted.
+pub fn paragraphs(content: &str) -> Vec<Paragraph> {
+    let mut paragraphs = Vec::new();
+    let mut current: Option<Paragraph> = None;
+    let mut last_line = 0;
+    for (line, text) in prose_lines(content) {
+        let trimmed = text.trim();
+        let is_prose = !trimmed.is_empty()
+            && parse_heading(trimmed).is_none()
+            && !trimmed.starts_with(['-', '*', '+', '|', '>'])
+            && !trimmed
+                .split_once(". ")
+                .is_some_and(|(n, _)| n.chars().all(|c| c.is_ascii_digit()));
+
+        // A skipped fence also breaks the paragraph
+        if !is_prose || line != last_line + 1 {
+            paragraphs.extend(current.take());
+        }
+        if is_prose {
+            let words = trimmed.split_whitespace().count();
+            match current.as_mut() {
+                Some(p) => p.words += words,
+                None => current = Some(Paragraph { line, words }),
+            }
+        }
+        last_line = line;
+    }
+    paragraphs.extend(current);
+    paragraphs
+}
+
+/// Headings that go more than one level deeper than the heading before
+/// them, with that previous level.
+pub fn skipped_levels(headings: &[Heading]) -> Vec<(&Heading, usize)> {
+    headings
+        .windows(2)
+        .filter(|pair| pair[1].level > pair[0].level + 1)
+        .map(|pair| (&pair[1], pair[0].level))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "Intro line one\nline two.\n\n## Context\n\n```\n# not a heading\nfour words in code\n```\n#### Deep\n\n- a list item\n1. numbered\n\nLast paragraph here";
+
+    #[test]
+    fn test_headings() {
+        let headings = headings(CONTENT);
+        let found: Vec<(usize, &str, usize)> = headings
+            .iter()
+            .map(|h| (h.level, h.text.as_str(), h.line))
+            .collect();
+        assert_eq!(found, vec![(2, "Context", 4), (4, "Deep", 10)]);
+
+        assert!(headings[0].matches("## context"));
+        assert!(headings[0].matches("Context"));
+        assert!(!headings[0].matches("### Context"));
+
+        let skipped = skipped_levels(&headings);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!((skipped[0].0.text.as_str(), skipped[0].1), ("Deep", 2));
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        let blocks = code_blocks(CONTENT);
+        assert_eq!(
+            blocks,
+            vec![CodeBlock {
+                line: 6,
+                language: String::new(),
+                code: "# not a heading\nfour words in code\n".to_string(),
+            }]
+        );
+
+        // Indentation is dropped by character, not byte
+        let blocks =
+            code_blocks("\u{a0}\u{a0}```sh\n\u{a0}\u{a0}echo hi\n\u{a0}x\n  ```\n```\nunclosed\n");
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].language, "sh");
+        assert_eq!(blocks[0].code, "echo hi\nx\n");
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        assert_eq!(
+            paragraphs(CONTENT),
+            vec![
+                Paragraph { line: 1, words: 5 },
+                Paragraph { line: 15, words: 3 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,433 @@
+use crate::backend::replace_file;
+use crate::config::CONFIG_FILE;
+use crate::hash::sha256_hex;
+use crate::id;
+use crate::info::STORE_FILE;
+use crate::mem::Mem;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory inside .mems/ holding the last-synced state per peer.
+pub const SYNC_DIR: &str = ".sync";
+
+/// Files at the root of a store that describe that store alone (its identity
+/// and settings), so sync leaves them out.
+const STORE_LOCAL_FILES: &[&str] = &[STORE_FILE, CONFIG_FILE];
+
+/// A file in one store, as seen by sync.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileState {
+    pub hash: String,
+    /// `updated-at` for mems, modification time otherwise
+    pub updated: DateTime<Utc>,
+}
+
+/// What to do with one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Copy local to remote
+    Push(String),
+    /// Copy remote to local
+    Pull(String),
+    /// Remote deleted it; delete locally
+    DeleteLocal(String),
+    /// Local deleted it; delete remotely
+    DeleteRemote(String),
+    /// Both sides changed it; the newer version wins the path and the other
+    /// is written to `copy` on both sides
+    Conflict {
+        path: String,
+        copy: String,
+        local_wins: bool,
+    },
+}
+
+/// Hashes of the files both stores agreed on after the last sync.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SyncState {
+    peer: PathBuf,
+    synced_at: Option<DateTime<Utc>>,
+    files: BTreeMap<String, String>,
+}
+
+/// Files in a store that take part in sync (hidden, temp, and store-local
+/// files excluded).
+pub fn scan(root: &Path) -> Result<BTreeMap<String, FileState>> {
+    let mut files = BTreeMap::new();
+    scan_dir(root, "", &mut files)?;
+    Ok(files)
+}
+
+fn scan_dir(dir: &Path, prefix: &str, files: &mut BTreeMap<String, FileState>) -> Result<()> {
+    for entry in fs::read_dir(dir).context("failed to read directory")? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || name.ends_with(".tmp") {
+            continue;
+        }
+        if prefix.is_empty() && STORE_LOCAL_FILES.contains(&name.as_str()) {
+            continue;
+        }
+
+        let rel = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}/{name}")
+        };
+        let path = entry.path();
+        if path.is_dir() {
+            scan_dir(&path, &rel, files)?;
+            continue;
+        }
+
+        let data = fs::read(&path).with_context(|| format!("failed to read {rel}"))?;
+        let updated = rel
+            .strip_suffix(".md")
+            .and_then(|p| {
+                let text = std::str::from_utf8(&data).ok()?;
+                Mem::parse(PathBuf::from(p), text).ok()
+            })
+            .map(|m| m.updated_at)
+            .or_else(|| entry.metadata().ok()?.modified().ok().map(DateTime::from))
+            .unwrap_or_default();
+        files.insert(
+            rel,
+            FileState {
+                hash: sha256_hex(&data),
+                updated,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Decide what to do for every file, given both sides and the last-synced hashes.
+///
+/// A side whose hash still matches the base is unchanged, so the other side's
+/// edit or delete wins. When both changed, the file conflicts. An edit always
+/// beats a delete.
+pub fn plan(
+    local: &BTreeMap<String, FileState>,
+    remote: &BTreeMap<String, FileState>,
+    base: &BTreeMap<String, String>,
+    now: DateTime<Utc>,
+) -> Vec<Action> {
+    let paths: BTreeSet<&String> = local.keys().chain(remote.keys()).collect();
+    let mut actions = Vec::new();
+
+    for path in paths {
+        let base = base.get(path);
+        let action = match (local.get(path), remote.get(path)) {
+            (Some(l), Some(r)) if l.hash == r.hash => None,
+            (Some(l), Some(r)) => {
+                if base == Some(&l.hash) {
+                    Some(Action::Pull(path.clone()))
+                } else if base == Some(&r.hash) {
+                    Some(Action::Push(path.clone()))
+                } else {
+                    Some(Action::Conflict {
+                        path: path.clone(),
+                        copy: conflict_path(path, now),
+                        local_wins: l.updated >= r.updated,
+                    })
+                }
+            }
+            (Some(l), None) if base == Some(&l.hash) => Some(Action::DeleteLocal(path.clone())),
+            (Some(_), None) => Some(Action::Push(path.clone())),
+            (None, Some(r)) if base == Some(&r.hash) => Some(Action::DeleteRemote(path.clone())),
+            (None, Some(_)) => Some(Action::Pull(path.clone())),
+            (None, None) => None,
+        };
+        actions.extend(action);
+    }
+    actions
+}
+
+/// Name for the losing side of a conflict, e.g. `notes.conflict-20250101T120000.md`.
+fn conflict_path(path: &str, now: DateTime<Utc>) -> String {
+    let stamp = now.format("%Y%m%dT%H%M%S");
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') => format!("{stem}.conflict-{stamp}.{ext}"),
+        _ => format!("{path}.conflict-{stamp}"),
+    }
+}
+
+/// Bidirectionally sync two store roots, returning the actions taken.
+///
+/// With `dry_run`, nothing is written and the planned actions are returned.
+pub fn sync(local: &Path, remote: &Path, dry_run: bool) -> Result<Vec<Action>> {
+    let local_id = local.canonicalize()?;
+    let remote_id = remote.canonicalize()?;
+    if local_id == remote_id {
+        return Err(anyhow!("cannot sync a store with itself"));
+    }
+
+    let base = load_state(local, &remote_id)?.files;
+    let actions = plan(&scan(local)?, &scan(remote)?, &base, Utc::now());
+    if dry_run {
+        return Ok(actions);
+    }
+
+    for action in &actions {
+        match action {
+            Action::Push(path) => copy(local, path, remote, path)?,
+            Action::Pull(path) => copy(remote, path, local, path)?,
+            Action::DeleteLocal(path) => remove(local, path)?,
+            Action::DeleteRemote(path) => remove(remote, path)?,
+            Action::Conflict {
+                path,
+                copy: copy_path,
+                local_wins,
+            } => {
+                let (winner, loser) = if *local_wins {
+                    (local, remote)
+                } else {
+                    (remote, local)
+                };
+                let data =
+                    fs::read(loser.join(path)).with_context(|| format!("failed to read {path}"))?;
+                let data = conflict_copy(data, path);
+                write(loser, copy_path, &data)?;
+                write(winner, copy_path, &data)?;
+                copy(winner, path, loser, path)?;
+            }
+        }
+    }
+
+    // Both sides now match; record that as the base for next time
+    let files: BTreeMap<String, String> = scan(local)?
+        .into_iter()
+        .map(|(path, state)| (path, state.hash))
+        .collect();
+    let now = Some(Utc::now());
+    save_state(
+        local,
+        &SyncState {
+            peer: remote_id.clone(),
+            synced_at: now,
+            files: files.clone(),
+        },
+    )?;
+    save_state(
+        remote,
+        &SyncState {
+            peer: local_id,
+            synced_at: now,
+            files,
+        },
+    )?;
+
+    Ok(actions)
+}
+
+/// The losing side of a conflict as saved to its copy. A mem gets a new id,
+/// so the copy and the mem it was copied from don't share one.
+fn conflict_copy(data: Vec<u8>, path: &str) -> Vec<u8> {
+    let Some(mem_path) = path.strip_suffix(".md") else {
+        return data;
+    };
+    let Ok(text) = std::str::from_utf8(&data) else {
+        return data;
+    };
+    match Mem::parse(PathBuf::from(mem_path), text) {
+        Ok(mut mem) if mem.id.is_some() => {
+            mem.id = Some(id::generate());
+            mem.serialize().map(String::into_bytes).unwrap_or(data)
+        }
+        _ => data,
+    }
+}
+
+/// Copy `from` in store `src` to `to` in store `dest`, writing atomically.
+fn copy(src: &Path, from: &str, dest: &Path, to: &str) -> Result<()> {
+    let data = fs::read(src.join(from)).with_context(|| format!("failed to read {from}"))?;
+    write(dest, to, &data)
+}
+
+/// Write `data` to `to` in store `dest`, atomically.
+fn write(dest: &Path, to: &str, data: &[u8]) -> Result<()> {
+    let target = dest.join(to);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = target.with_extension("tmp");
+    fs::write(&temp, data).with_context(|| format!("failed to write {to}"))?;
+    replace_file(&temp, &target).with_context(|| format!("failed to write {to}"))?;
+    Ok(())
+}
+
+/// Delete a file and any parent directories left empty (but not the root).
+fn remove(root: &Path, path: &str) -> Result<()> {
+    let file = root.join(path);
+    fs::remove_file(&file).with_context(|| format!("failed to delete {path}"))?;
+
+    let mut parent = file.parent();
+    while let Some(dir) = parent {
+        if dir == root || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        parent = dir.parent();
+    }
+    Ok(())
+}
+
+fn state_path(root: &Path, peer: &Path) -> PathBuf {
+    let key = sha256_hex(peer.to_string_lossy().as_bytes());
+    root.join(SYNC_DIR).join(format!("{}.json", &key[..16]))
+}
+
+fn load_state(root: &Path, peer: &Path) -> Result<SyncState> {
+    let path = state_path(root, peer);
+    if !path.exists() {
+        return Ok(SyncState::default());
+    }
+    let text = fs::read_to_string(&path).context("failed to read sync state")?;
+    serde_json::from_str(&text).context("invalid sync state")
+}
+
+fn save_state(root: &Path, state: &SyncState) -> Result<()> {
+    let path = state_path(root, &state.peer);
+    fs::create_dir_all(path.parent().unwrap_or(root))?;
+    fs::write(&path, serde_json::to_string_pretty(state)?).context("failed to write sync state")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn state(hash: &str, day: u32) -> FileState {
+        FileState {
+            hash: hash.to_string(),
+            updated: Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let now = Utc.with_ymd_and_hms(2025, 2, 1, 12, 0, 0).unwrap();
+        let local = BTreeMap::from([
+            ("same.md".to_string(), state("a", 1)),
+            ("local-edit.md".to_string(), state("new", 2)),
+            ("both.md".to_string(), state("mine", 3)),
+            ("remote-deleted.md".to_string(), state("a", 1)),
+            ("new-local.md".to_string(), state("a", 1)),
+        ]);
+        let remote = BTreeMap::from([
+            ("same.md".to_string(), state("a", 1)),
+            ("local-edit.md".to_string(), state("old", 1)),
+            ("both.md".to_string(), state("theirs", 4)),
+            ("local-deleted.md".to_string(), state("a", 1)),
+        ]);
+        let base = BTreeMap::from([
+            ("same.md".to_string(), "a".to_string()),
+            ("local-edit.md".to_string(), "old".to_string()),
+            ("both.md".to_string(), "old".to_string()),
+            ("remote-deleted.md".to_string(), "a".to_string()),
+            ("local-deleted.md".to_string(), "a".to_string()),
+        ]);
+
+        assert_eq!(
+            plan(&local, &remote, &base, now),
+            vec![
+                Action::Conflict {
+                    path: "both.md".to_string(),
+                    copy: "both.conflict-20250201T120000.md".to_string(),
+                    local_wins: false,
+                },
+                Action::DeleteRemote("local-deleted.md".to_string()),
+                Action::Push("local-edit.md".to_string()),
+                Action::Push("new-local.md".to_string()),
+                Action::DeleteLocal("remote-deleted.md".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_writes_conflict_copies() {
+        let temp = TempDir::new().unwrap();
+        let a = temp.path().join("a");
+        let b = temp.path().join("b");
+        fs::create_dir_all(a.join("dir")).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        fs::write(a.join("dir/note.md"), "v1").unwrap();
+
+        sync(&a, &b, false).unwrap();
+        assert_eq!(fs::read_to_string(b.join("dir/note.md")).unwrap(), "v1");
+
+        // Both sides edit the same file: nothing is lost
+        fs::write(a.join("dir/note.md"), "from a").unwrap();
+        fs::write(b.join("dir/note.md"), "from b").unwrap();
+        let actions = sync(&a, &b, false).unwrap();
+        let Action::Conflict { path, copy, .. } = &actions[0] else {
+            panic!("expected a conflict: {actions:?}");
+        };
+        for root in [&a, &b] {
+            let mut versions = vec![
+                fs::read_to_string(root.join(path)).unwrap(),
+                fs::read_to_string(root.join(copy)).unwrap(),
+            ];
+            versions.sort();
+            assert_eq!(versions, vec!["from a", "from b"]);
+        }
+
+        // Afterwards the stores agree and a delete propagates
+        assert!(sync(&a, &b, false).unwrap().is_empty());
+        fs::remove_file(b.join(copy)).unwrap();
+        assert_eq!(
+            sync(&a, &b, false).unwrap(),
+            vec![Action::DeleteLocal(copy.clone())]
+        );
+        assert!(!a.join(copy).exists());
+    }
+
+    #[test]
+    fn test_conflict_copy_gets_a_new_id() {
+        let mem = Mem::new(
+            PathBuf::from("note"),
+            "Note".to_string(),
+            "Body".to_string(),
+        );
+        let data = mem.serialize().unwrap().into_bytes();
+        let copy = conflict_copy(data, "note.md");
+        let copy = Mem::parse(PathBuf::from("note"), std::str::from_utf8(&copy).unwrap()).unwrap();
+        assert!(copy.id.is_some() && copy.id != mem.id);
+        assert_eq!(copy.content, "Body");
+
+        assert_eq!(conflict_copy(b"not a mem".to_vec(), "x.md"), b"not a mem");
+        assert_eq!(conflict_copy(b"id: 1".to_vec(), "x.yaml"), b"id: 1");
+    }
+
+    #[test]
+    fn test_sync_leaves_store_files_alone() {
+        let temp = TempDir::new().unwrap();
+        let a = temp.path().join("a");
+        let b = temp.path().join("b");
+        for (root, name) in [(&a, "laptop"), (&b, "nas")] {
+            fs::create_dir_all(root).unwrap();
+            fs::write(root.join(STORE_FILE), format!("name: {name}\n")).unwrap();
+            fs::write(root.join(CONFIG_FILE), format!("# {name}\n")).unwrap();
+        }
+        fs::write(a.join("note.md"), "v1").unwrap();
+
+        assert_eq!(
+            sync(&a, &b, false).unwrap(),
+            vec![Action::Push("note.md".to_string())]
+        );
+        for (root, name) in [(&a, "laptop"), (&b, "nas")] {
+            assert_eq!(
+                fs::read_to_string(root.join(STORE_FILE)).unwrap(),
+                format!("name: {name}\n")
+            );
+            assert_eq!(
+                fs::read_to_string(root.join(CONFIG_FILE)).unwrap(),
+                format!("# {name}\n")
+            );
+        }
+    }
+}
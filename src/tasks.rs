@@ -0,0 +1,103 @@
+//! Markdown task items (`- [ ] open`, `- [x] done`) in mem content.
+
+use crate::structure;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+/// A task list item.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Task {
+    /// 1-based line within the content
+    pub line: usize,
+    pub done: bool,
+    pub text: String,
+}
+
+/// Split a task line into the text before the checkbox, the box state, and
+/// the text after it.
+fn parse(line: &str) -> Option<(&str, bool, &str)> {
+    let rest = line.trim_start();
+    let marker = if rest.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 || !rest[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        digits + 1
+    };
+    let after_marker = rest[marker..].strip_prefix(' ')?;
+    let done = match after_marker.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let text = &after_marker[3..];
+    if !(text.is_empty() || text.starts_with(' ')) {
+        return None;
+    }
+    let prefix_len = line.len() - after_marker.len();
+    Some((&line[..prefix_len], done, text.trim()))
+}
+
+/// Task items outside code blocks, in document order.
+pub fn extract(content: &str) -> Vec<Task> {
+    structure::prose_lines(content)
+        .filter_map(|(line, text)| {
+            parse(text).map(|(_, done, text)| Task {
+                line,
+                done,
+                text: text.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Check off (or reopen) the task on content line `line`.
+pub fn set_done(content: &str, line: usize, done: bool) -> Result<String> {
+    if !extract(content).iter().any(|t| t.line == line) {
+        return Err(anyhow!("no task on line {line}"));
+    }
+    let mut lines: Vec<String> = content.split('\n').map(str::to_string).collect();
+    let target = &mut lines[line - 1];
+    if let Some((prefix, _, _)) = parse(target) {
+        let mark = if done { "[x]" } else { "[ ]" };
+        let rest = target[prefix.len() + 3..].to_string();
+        *target = format!("{prefix}{mark}{rest}");
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "# Follow-ups\n\n- [ ] Rotate keys\n  * [x] Page secondary\n3. [ ] Numbered\n- [link](x) not a task\n- [ ]no space\n```\n- [ ] in code\n```\n- [X] Done upper";
+
+    #[test]
+    fn test_extract() {
+        let tasks = extract(CONTENT);
+        assert_eq!(tasks.len(), 4);
+        assert_eq!(
+            tasks[0],
+            Task {
+                line: 3,
+                done: false,
+                text: "Rotate keys".to_string()
+            }
+        );
+        assert_eq!((tasks[1].line, tasks[1].done), (4, true));
+        assert_eq!(tasks[2].text, "Numbered");
+        assert_eq!((tasks[3].line, tasks[3].done), (11, true));
+    }
+
+    #[test]
+    fn test_set_done() {
+        let content = set_done(CONTENT, 3, true).unwrap();
+        assert!(content.contains("\n- [x] Rotate keys\n"));
+        let content = set_done(&content, 4, false).unwrap();
+        assert!(content.contains("\n  * [ ] Page secondary\n"));
+        assert!(set_done(CONTENT, 1, true).is_err());
+        assert!(set_done(CONTENT, 9, true).is_err());
+    }
+}
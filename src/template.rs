@@ -0,0 +1,60 @@
+//! Output templates for `--template`: Jinja-style templates such as
+//! `{{ path }}: {{ title }} [{{ tags | join(", ") }}]`, rendered once per mem
+//! with the fields its `--json` output has.
+
+use anyhow::{anyhow, Result};
+use minijinja::Environment;
+use serde::Serialize;
+
+const NAME: &str = "template";
+
+/// A parsed output template.
+pub struct Template {
+    env: Environment<'static>,
+}
+
+impl Template {
+    /// Parse `source`, failing on a syntax error before anything is printed.
+    pub fn new(source: String) -> Result<Template> {
+        let mut env = Environment::new();
+        env.add_template_owned(NAME, source)
+            .map_err(|e| anyhow!("invalid template: {e}"))?;
+        Ok(Template { env })
+    }
+
+    /// Render the template with the fields of `context`. Fields a mem
+    /// doesn't have render as nothing.
+    pub fn render(&self, context: impl Serialize) -> Result<String> {
+        self.env
+            .get_template(NAME)?
+            .render(context)
+            .map_err(|e| anyhow!("template error: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render() {
+        let template =
+            Template::new("{{ path }}: {{ title }} [{{ tags | join(', ') }}]{{ owner }}\n".into())
+                .unwrap();
+        let mem = json!({"path": "a/b", "title": "B", "tags": ["x", "y"]});
+        assert_eq!(template.render(&mem).unwrap(), "a/b: B [x, y]");
+
+        let template = Template::new("{{ fields | tojson }}".into()).unwrap();
+        let mem = json!({"fields": {"owner": "sam"}});
+        assert_eq!(template.render(&mem).unwrap(), r#"{"owner":"sam"}"#);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let err = Template::new("{{ path ".into()).err().unwrap();
+        assert!(err.to_string().starts_with("invalid template:"), "{err}");
+        let template = Template::new("{{ size + title }}".into()).unwrap();
+        assert!(template.render(json!({"size": 1, "title": "x"})).is_err());
+    }
+}
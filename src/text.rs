@@ -0,0 +1,126 @@
+//! Unicode normalization, so text typed one way matches text stored
+//! another: `café` with a precomposed `é` or with `e` and a combining accent,
+//! `Straße` or `STRASSE`.
+//!
+//! Mem paths and tags are stored in NFC (composed) form; search and tag
+//! comparisons also fold case, beyond ASCII. Content that isn't text at all
+//! is refused before it becomes a mem.
+
+use crate::error::MemError;
+use unicode_normalization::UnicodeNormalization;
+
+/// How far into a file to look for NUL bytes when deciding it is binary.
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// `text` in NFC, the composed form.
+pub fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+/// `text` case folded and in NFC, for comparing case-insensitively.
+pub fn fold(text: &str) -> String {
+    text.chars().flat_map(fold_char).nfc().collect()
+}
+
+/// Whether two strings are the same once folded.
+pub fn same(a: &str, b: &str) -> bool {
+    a == b || fold(a) == fold(b)
+}
+
+/// `c` case folded and decomposed (NFD). Folding one character at a time
+/// lets a match be mapped back to the characters of the original text.
+pub fn fold_char(c: char) -> Vec<char> {
+    let mut folded = Vec::new();
+    for c in std::iter::once(c).nfd() {
+        match c {
+            'ß' | 'ẞ' => folded.extend(['s', 's']),
+            'ς' => folded.push('σ'),
+            c => folded.extend(c.to_lowercase()),
+        }
+    }
+    folded
+}
+
+/// Whether `data` looks like a binary file: it has a NUL byte near the
+/// start, the check git uses.
+pub fn is_binary(data: &[u8]) -> bool {
+    data.iter().take(BINARY_SNIFF_LEN).any(|&b| b == 0)
+}
+
+/// Decode `data` (read from `source`) as mem text, refusing binary files
+/// and invalid UTF-8 with an error saying which.
+pub fn decode(data: Vec<u8>, source: &str) -> Result<String, MemError> {
+    let not_text = |binary| MemError::NotText {
+        source: source.to_string(),
+        binary,
+    };
+    if is_binary(&data) {
+        return Err(not_text(true));
+    }
+    String::from_utf8(data).map_err(|_| not_text(false))
+}
+
+/// A tag as stored: trimmed and in NFC.
+pub fn tag(tag: &str) -> String {
+    nfc(tag.trim())
+}
+
+/// Tags from a comma-separated list, such as `--tags`.
+pub fn parse_tags(tags: &str) -> Vec<String> {
+    tags.split(',').map(tag).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSED: &str = "caf\u{e9}";
+    const DECOMPOSED: &str = "cafe\u{301}";
+
+    #[test]
+    fn test_nfc() {
+        assert_ne!(COMPOSED, DECOMPOSED);
+        assert_eq!(nfc(DECOMPOSED), COMPOSED);
+        assert_eq!(nfc(COMPOSED), COMPOSED);
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("CAFE\u{301}"), COMPOSED);
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(fold("STRASSE"), "strasse");
+        assert_eq!(fold("ΟΔΟΣ"), fold("οδος"));
+        assert!(same(COMPOSED, "Café"));
+        assert!(!same(COMPOSED, "cafe"));
+    }
+
+    #[test]
+    fn test_fold_char() {
+        assert_eq!(fold_char('É'), ['e', '\u{301}']);
+        assert_eq!(fold_char('a'), ['a']);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"# Notes\n".to_vec(), "stdin").unwrap(), "# Notes\n");
+        assert_eq!(
+            decode(b"\x89PNG\r\n\x1a\n\0\0".to_vec(), "logo.png"),
+            Err(MemError::NotText {
+                source: "logo.png".to_string(),
+                binary: true
+            })
+        );
+        assert_eq!(
+            decode(b"caf\xe9".to_vec(), "latin1.md"),
+            Err(MemError::NotText {
+                source: "latin1.md".to_string(),
+                binary: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags(" caf\u{65}\u{301} , ops"), [COMPOSED, "ops"]);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::git::{self, FileTimes};
+use crate::mem::Mem;
+use crate::storage::Storage;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// How far ahead of the clock a timestamp may be before it counts as in the
+/// future, allowing for skew between machines.
+const FUTURE_SLACK: Duration = Duration::minutes(5);
+
+/// Where stale/recent calculations take mem timestamps from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TimestampSource {
+    /// `created-at`/`updated-at` in frontmatter
+    #[default]
+    Frontmatter,
+    /// First and latest commits touching the file
+    Git,
+}
+
+/// Replace frontmatter timestamps with ones from `source`.
+///
+/// With git, mems that were never committed keep their frontmatter times.
+pub fn apply(source: TimestampSource, storage: &Storage, mems: &mut [Mem]) -> Result<()> {
+    if source == TimestampSource::Frontmatter {
+        return Ok(());
+    }
+
+    // git reports canonical paths, so compare against a canonical root
+    let canonical = Storage::new(storage.local_root()?.canonicalize()?);
+    let times = git::file_times(canonical.root())?;
+    for mem in mems {
+        let file = canonical.mem_path(&mem.path_str());
+        if let Some(t) = times.get(&file) {
+            mem.created_at = t.created;
+            mem.updated_at = t.updated;
+        }
+    }
+    Ok(())
+}
+
+/// Last commit times of a store's mem files, for checking frontmatter
+/// against git. Empty for stores outside a git work tree.
+#[derive(Debug, Default)]
+pub struct History {
+    /// Canonical store root, as git reports paths
+    root: PathBuf,
+    times: HashMap<PathBuf, FileTimes>,
+}
+
+impl History {
+    pub fn load(storage: &Storage) -> History {
+        let Some(root) = storage
+            .local_root()
+            .ok()
+            .and_then(|r| r.canonicalize().ok())
+        else {
+            return History::default();
+        };
+        let times = git::file_times(&root).unwrap_or_default();
+        History { root, times }
+    }
+
+    /// When the mem at `path` was last committed.
+    pub fn last_commit(&self, path: &str) -> Option<DateTime<Utc>> {
+        let file = self.root.join(format!("{path}.md"));
+        self.times.get(&file).map(|t| t.updated)
+    }
+}
+
+/// Something wrong with a mem's frontmatter timestamps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// `updated-at` is before `created-at`
+    Order,
+
+    /// The field is in the future
+    Future(&'static str),
+
+    /// The field is written with this offset rather than in UTC
+    NotUtc(&'static str, String),
+
+    /// `updated-at` is far older than this last commit of the file
+    BeforeHistory(DateTime<Utc>),
+}
+
+impl Problem {
+    /// The frontmatter field at fault.
+    pub fn field(&self) -> &'static str {
+        match self {
+            Problem::Future(field) | Problem::NotUtc(field, _) => field,
+            Problem::Order | Problem::BeforeHistory(_) => "updated-at",
+        }
+    }
+
+    pub fn message(&self, mem: &Mem) -> String {
+        match self {
+            Problem::Order => format!(
+                "updated-at {} is before created-at {}",
+                day(mem.updated_at),
+                day(mem.created_at)
+            ),
+            Problem::Future(field) => format!("{field} {} is in the future", day(time(mem, field))),
+            Problem::NotUtc(field, value) => format!(
+                "{field} {value} is not in UTC; write it as {}",
+                time(mem, field).format("%Y-%m-%dT%H:%M:%SZ")
+            ),
+            Problem::BeforeHistory(commit) => format!(
+                "updated-at {} is {} days older than the file's last commit ({}); \
+                 the mem changed without updating it",
+                day(mem.updated_at),
+                (*commit - mem.updated_at).num_days(),
+                day(*commit)
+            ),
+        }
+    }
+}
+
+fn day(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d").to_string()
+}
+
+fn time(mem: &Mem, field: &str) -> DateTime<Utc> {
+    if field == "created-at" {
+        mem.created_at
+    } else {
+        mem.updated_at
+    }
+}
+
+/// Problems with the timestamps of `mem`, whose file is `raw`, as of `now`.
+/// `last_commit` is when git last saw the file, and `max_gap_days` how much
+/// older than that `updated-at` may be.
+pub fn check(
+    mem: &Mem,
+    raw: &str,
+    now: DateTime<Utc>,
+    last_commit: Option<DateTime<Utc>>,
+    max_gap_days: u32,
+) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for field in ["created-at", "updated-at"] {
+        if let Some(value) = raw_value(raw, field) {
+            let offset = DateTime::parse_from_rfc3339(value).map(|t| t.offset().local_minus_utc());
+            if offset.is_ok_and(|offset| offset != 0) {
+                problems.push(Problem::NotUtc(field, value.to_string()));
+            }
+        }
+        if time(mem, field) > now + FUTURE_SLACK {
+            problems.push(Problem::Future(field));
+        }
+    }
+    if mem.updated_at < mem.created_at {
+        problems.push(Problem::Order);
+    }
+    if let Some(commit) = last_commit {
+        if commit - mem.updated_at > Duration::days(max_gap_days.into()) {
+            problems.push(Problem::BeforeHistory(commit));
+        }
+    }
+    problems
+}
+
+/// Repair `problems` found by [`check`]: future times become `now`,
+/// `updated-at` catches up with the last commit and then with `created-at`.
+/// Timestamps are always written in UTC, so saving the mem fixes offsets.
+pub fn repair(mem: &mut Mem, problems: &[Problem], now: DateTime<Utc>) {
+    for problem in problems {
+        match problem {
+            Problem::Future("created-at") => mem.created_at = now,
+            Problem::Future(_) => mem.updated_at = now,
+            Problem::BeforeHistory(commit) => mem.updated_at = mem.updated_at.max(*commit),
+            Problem::Order | Problem::NotUtc(..) => {}
+        }
+    }
+    mem.updated_at = mem.updated_at.max(mem.created_at);
+}
+
+/// The value of a top-level frontmatter field as written, unquoted.
+fn raw_value<'a>(raw: &'a str, field: &str) -> Option<&'a str> {
+    let (frontmatter, _) = crate::mem::split_frontmatter(raw)?;
+    frontmatter.lines().find_map(|line| {
+        let value = line.strip_prefix(field)?.strip_prefix(':')?;
+        Some(value.trim().trim_matches(['\'', '"']))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::path::PathBuf;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, day, 0, 0, 0).unwrap()
+    }
+
+    fn mem(created: DateTime<Utc>, updated: DateTime<Utc>) -> Mem {
+        let mut mem = Mem::new(PathBuf::from("a"), "A".to_string(), "x".to_string());
+        mem.created_at = created;
+        mem.updated_at = updated;
+        mem
+    }
+
+    #[test]
+    fn test_check() {
+        let now = at(20);
+        let good = mem(at(1), at(2));
+        let raw = good.serialize().unwrap();
+        assert_eq!(check(&good, &raw, now, Some(at(2)), 365), []);
+
+        let raw = "---\ntitle: A\ncreated-at: 2025-06-01T02:00:00+02:00\n\
+                   updated-at: '2025-05-01T00:00:00Z'\n---\nx\n";
+        let bad = Mem::parse(PathBuf::from("a"), raw).unwrap();
+        assert_eq!(
+            check(&bad, raw, now, None, 365),
+            [
+                Problem::NotUtc("created-at", "2025-06-01T02:00:00+02:00".to_string()),
+                Problem::Order
+            ]
+        );
+        assert_eq!(
+            Problem::NotUtc("created-at", "2025-06-01T02:00:00+02:00".to_string()).message(&bad),
+            "created-at 2025-06-01T02:00:00+02:00 is not in UTC; write it as 2025-06-01T00:00:00Z"
+        );
+
+        let future = mem(at(1), at(25));
+        assert_eq!(
+            check(&future, "", now, None, 365),
+            [Problem::Future("updated-at")]
+        );
+
+        let commit = Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap();
+        let problems = check(&good, "", commit, Some(commit), 365);
+        assert_eq!(problems, [Problem::BeforeHistory(commit)]);
+        assert!(problems[0].message(&good).contains("578 days older"));
+        assert!(check(&good, "", commit, Some(commit), 1000).is_empty());
+    }
+
+    #[test]
+    fn test_repair() {
+        let now = at(20);
+        let mut future = mem(at(25), at(1));
+        let problems = check(&future, "", now, None, 365);
+        repair(&mut future, &problems, now);
+        assert_eq!((future.created_at, future.updated_at), (now, now));
+
+        let mut stale = mem(at(1), at(2));
+        repair(&mut stale, &[Problem::BeforeHistory(at(10))], now);
+        assert_eq!(stale.updated_at, at(10));
+        assert!(check(&stale, "", now, Some(at(10)), 0).is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+//! The directory hierarchy of a store, as shown by `mem tree`.
+
+use crate::config::Config;
+use crate::directory;
+use crate::kind;
+use crate::mem::Mem;
+use crate::storage::Storage;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A directory of mems, with everything under it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Dir {
+    pub name: String,
+
+    /// Path from the store root (`""` for the root)
+    pub path: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Mems anywhere under the directory
+    pub count: usize,
+
+    /// Newest update of a mem anywhere under the directory
+    pub updated_at: Option<DateTime<Utc>>,
+
+    /// Mems directly in the directory, by kind order and then path
+    pub mems: Vec<Entry>,
+
+    pub dirs: Vec<Dir>,
+}
+
+/// A mem in a directory.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub path: String,
+    pub title: String,
+    pub pinned: bool,
+    pub archived: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Dir {
+    fn new(name: &str, path: &str) -> Dir {
+        Dir {
+            name: name.to_string(),
+            path: path.to_string(),
+            description: None,
+            count: 0,
+            updated_at: None,
+            mems: Vec::new(),
+            dirs: Vec::new(),
+        }
+    }
+
+    /// Add a mem `parts` (its path split on `/`) below this directory.
+    fn insert(&mut self, parts: &[&str], entry: Entry) {
+        self.count += 1;
+        self.updated_at = self.updated_at.max(Some(entry.updated_at));
+        let [dir, rest @ ..] = parts else {
+            return;
+        };
+        if rest.is_empty() {
+            self.mems.push(entry);
+            return;
+        }
+        let position = match self.dirs.iter().position(|d| d.name == *dir) {
+            Some(position) => position,
+            None => {
+                let path = if self.path.is_empty() {
+                    dir.to_string()
+                } else {
+                    format!("{}/{dir}", self.path)
+                };
+                self.dirs.push(Dir::new(dir, &path));
+                self.dirs.len() - 1
+            }
+        };
+        self.dirs[position].insert(rest, entry);
+    }
+
+    /// Drop everything more than `depth` levels below this directory,
+    /// keeping the counts and update times of the directories left.
+    pub fn truncate(&mut self, depth: usize) {
+        if depth == 0 {
+            self.mems.clear();
+            self.dirs.clear();
+        }
+        for dir in &mut self.dirs {
+            dir.truncate(depth.saturating_sub(1));
+        }
+    }
+
+    /// The hierarchy under this directory as an indented markdown list of
+    /// directories (with descriptions and counts) and mems (with titles).
+    pub fn outline(&self) -> String {
+        let mut out = String::new();
+        self.write_outline(0, &mut out);
+        out
+    }
+
+    fn write_outline(&self, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        for mem in self.mems.iter().filter(|m| m.pinned) {
+            out.push_str(&format!("{indent}- {}: {}\n", mem.name, mem.title));
+        }
+        for dir in &self.dirs {
+            let noun = if dir.count == 1 { "mem" } else { "mems" };
+            match &dir.description {
+                Some(description) => out.push_str(&format!(
+                    "{indent}- {}/ - {description} ({} {noun})\n",
+                    dir.name, dir.count
+                )),
+                None => out.push_str(&format!("{indent}- {}/ ({} {noun})\n", dir.name, dir.count)),
+            }
+            dir.write_outline(depth + 1, out);
+        }
+        for mem in self.mems.iter().filter(|m| !m.pinned) {
+            out.push_str(&format!("{indent}- {}: {}\n", mem.name, mem.title));
+        }
+    }
+
+    /// Whether the directory lists nothing, although mems are under it.
+    pub fn is_collapsed(&self) -> bool {
+        self.count > 0 && self.mems.is_empty() && self.dirs.is_empty()
+    }
+}
+
+/// The hierarchy of `mems` in `storage`, each paired with whether it is
+/// archived, with directory descriptions from `_dir.yaml` files and mems in
+/// the order `kinds` sets.
+pub fn build(storage: &Storage, mems: &[(Mem, bool)], root_name: &str) -> Result<Dir> {
+    let config = Config::for_storage(storage)?;
+    let mut mems: Vec<&(Mem, bool)> = mems.iter().collect();
+    mems.sort_by_key(|(mem, archived)| {
+        (
+            kind::settings(&config, kind::of(mem)).order(),
+            mem.path.clone(),
+            *archived,
+        )
+    });
+
+    let mut root = Dir::new(root_name, "");
+    for (mem, archived) in mems {
+        let path = mem.path_str();
+        let parts: Vec<&str> = path.split('/').collect();
+        let entry = Entry {
+            name: parts.last().copied().unwrap_or_default().to_string(),
+            path: path.clone(),
+            title: mem.title.clone(),
+            pinned: mem.is_pinned(),
+            archived: *archived,
+            updated_at: mem.updated_at,
+        };
+        root.insert(&parts, entry);
+    }
+    describe(storage, &mut root)?;
+    Ok(root)
+}
+
+fn describe(storage: &Storage, dir: &mut Dir) -> Result<()> {
+    dir.dirs.sort_by(|a, b| a.name.cmp(&b.name));
+    for sub in &mut dir.dirs {
+        sub.description = directory::load(storage, &sub.path)?.description;
+        describe(storage, sub)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(path: &str, day: u32) -> Entry {
+        Entry {
+            name: path.rsplit('/').next().unwrap().to_string(),
+            path: path.to_string(),
+            title: path.to_string(),
+            pinned: false,
+            archived: false,
+            updated_at: Utc.with_ymd_and_hms(2025, 6, day, 0, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_insert_and_truncate() {
+        let mut root = Dir::new(".mems", "");
+        for (path, day) in [("a/b/c", 3), ("a/d", 1), ("e", 2)] {
+            let parts: Vec<&str> = path.split('/').collect();
+            root.insert(&parts, entry(path, day));
+        }
+        assert_eq!(root.count, 3);
+        assert_eq!(root.mems.len(), 1);
+        let a = &root.dirs[0];
+        assert_eq!((a.path.as_str(), a.count), ("a", 2));
+        assert_eq!(a.updated_at, Some(entry("", 3).updated_at));
+        assert_eq!(a.dirs[0].path, "a/b");
+
+        assert_eq!(
+            root.outline(),
+            "- a/ (2 mems)\n  - b/ (1 mem)\n    - c: a/b/c\n  - d: a/d\n- e: e\n"
+        );
+
+        root.truncate(1);
+        let a = &root.dirs[0];
+        assert!(a.is_collapsed());
+        assert_eq!(a.count, 2);
+        assert_eq!(root.mems.len(), 1);
+        assert!(!Dir::new("x", "x").is_collapsed());
+    }
+}
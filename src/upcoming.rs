@@ -0,0 +1,146 @@
+//! Dates mentioned in mems: inline `@YYYY-MM-DD` markers and the `dates:`
+//! frontmatter list, for `mem upcoming`.
+
+use crate::dates::parse_date;
+use crate::mem::Mem;
+use crate::structure;
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::ops::Range;
+
+/// Frontmatter field listing dates, each `YYYY-MM-DD` optionally followed by
+/// a description (`2025-03-01 TLS cert expires`).
+pub const DATES_FIELD: &str = "dates";
+
+/// A date a mem refers to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Event {
+    pub date: NaiveDate,
+
+    /// What the date is about: the line it appears on, or the description in
+    /// the `dates:` entry
+    pub label: String,
+
+    /// 1-based content line of an inline date (`None` for frontmatter dates)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+/// `@YYYY-MM-DD` markers in a line, with their byte ranges.
+fn inline_dates(text: &str) -> Vec<(NaiveDate, Range<usize>)> {
+    text.match_indices('@')
+        .filter_map(|(at, _)| {
+            let end = at + 11;
+            let date = parse_date(text.get(at + 1..end)?)?;
+            let standalone = !text[..at]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric)
+                && !text[end..]
+                    .chars()
+                    .next()
+                    .is_some_and(char::is_alphanumeric);
+            standalone.then_some((date, at..end))
+        })
+        .collect()
+}
+
+/// Every date a mem refers to, frontmatter dates first, then inline dates in
+/// document order. Dates in code blocks are ignored.
+pub fn events(mem: &Mem) -> Vec<Event> {
+    let entries = match mem.extra.get(DATES_FIELD) {
+        Some(Value::Sequence(items)) => items.iter().filter_map(Value::as_str).collect(),
+        Some(Value::String(item)) => vec![item.as_str()],
+        _ => Vec::new(),
+    };
+    let mut events: Vec<Event> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let (date, label) = entry.trim().split_once(' ').unwrap_or((entry.trim(), ""));
+            Some(Event {
+                date: parse_date(date)?,
+                label: label.trim().to_string(),
+                line: None,
+            })
+        })
+        .collect();
+
+    for (line, text) in structure::prose_lines(&mem.content) {
+        let found = inline_dates(text);
+        if found.is_empty() {
+            continue;
+        }
+        // The line with the `@`s dropped, and without list or heading syntax
+        let mut label = text.to_string();
+        for (_, range) in found.iter().rev() {
+            label.remove(range.start);
+        }
+        let label = label
+            .trim()
+            .trim_start_matches(['#', '-', '*', '+', ' '])
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        events.extend(found.into_iter().map(|(date, _)| Event {
+            date,
+            label: label.clone(),
+            line: Some(line),
+        }));
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn date(text: &str) -> NaiveDate {
+        parse_date(text).unwrap()
+    }
+
+    #[test]
+    fn test_events() {
+        let mut mem = Mem::new(
+            PathBuf::from("certs"),
+            "Certs".to_string(),
+            "# Certs\n\n- API cert expires @2025-03-01 (renew early)\n\
+             Contact ops@2025-01-01.example or @2025-13-01.\n\
+             ```\n@2025-04-01\n```\nMigrate @2025-05-01, cut over @2025-05-15"
+                .to_string(),
+        );
+        mem.extra.insert(
+            DATES_FIELD.to_string(),
+            serde_yaml::from_str("[2025-02-01 Kickoff, 2025-06-30, not a date]").unwrap(),
+        );
+
+        let events = events(&mem);
+        let found: Vec<(NaiveDate, &str, Option<usize>)> = events
+            .iter()
+            .map(|e| (e.date, e.label.as_str(), e.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (date("2025-02-01"), "Kickoff", None),
+                (date("2025-06-30"), "", None),
+                (
+                    date("2025-03-01"),
+                    "API cert expires 2025-03-01 (renew early)",
+                    Some(3)
+                ),
+                (
+                    date("2025-05-01"),
+                    "Migrate 2025-05-01, cut over 2025-05-15",
+                    Some(8)
+                ),
+                (
+                    date("2025-05-15"),
+                    "Migrate 2025-05-01, cut over 2025-05-15",
+                    Some(8)
+                ),
+            ]
+        );
+    }
+}
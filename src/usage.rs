@@ -0,0 +1,201 @@
+//! Local access tracking in `.mems/.usage.json`: how often and when each mem
+//! was shown, for `ls --hot`, and which mems were shown together, for
+//! `related`.
+//!
+//! Tracking is opt-in, like the cache: nothing is recorded until
+//! `mem usage enable` creates the file, and `mem usage disable` deletes it
+//! with everything recorded.
+
+use crate::backend::{FsBackend, StorageBackend};
+use crate::mem::Mem;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Usage file inside .mems/.
+pub const USAGE_FILE: &str = ".usage.json";
+
+/// Mems shown within this many minutes of each other count as used together.
+const SESSION_MINUTES: i64 = 30;
+
+/// How often and when a mem was last shown.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub count: u64,
+    pub last_shown: DateTime<Utc>,
+}
+
+/// Access history of a store, keyed by mem id (or path for mems without one).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Usage {
+    pub mems: BTreeMap<String, Record>,
+
+    /// Times each pair of mems was shown together, recorded both ways
+    #[serde(default)]
+    pub together: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+fn key(mem: &Mem) -> String {
+    mem.id.clone().unwrap_or_else(|| mem.path_str())
+}
+
+impl Usage {
+    /// Load the usage of a store, or `None` if tracking is off.
+    pub fn open(root: &Path) -> Result<Option<Self>> {
+        let path = root.join(USAGE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path).context("failed to read usage")?;
+        serde_json::from_str(&text)
+            .map(Some)
+            .context("invalid usage file")
+    }
+
+    /// Turn tracking on, keeping anything already recorded. Returns whether
+    /// the usage file was created.
+    pub fn enable(root: &Path) -> Result<bool> {
+        if root.join(USAGE_FILE).exists() {
+            return Ok(false);
+        }
+        Self::default().save(root)?;
+        Ok(true)
+    }
+
+    /// Turn tracking off and delete what was recorded. Returns whether
+    /// tracking was on.
+    pub fn disable(root: &Path) -> Result<bool> {
+        let path = root.join(USAGE_FILE);
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(&path).context("failed to remove usage")?;
+        Ok(true)
+    }
+
+    /// Write the usage file through a temp file, so a crash or a concurrent
+    /// `mem` never leaves it half written.
+    pub fn save(&self, root: &Path) -> Result<()> {
+        FsBackend::new(root.to_path_buf())
+            .write(USAGE_FILE, serde_json::to_string_pretty(self)?.as_bytes())
+            .context("failed to write usage")
+    }
+
+    /// Record that `mems` were shown at `now`. Each counts as used together
+    /// with the others and with mems shown in the last half hour.
+    pub fn record(&mut self, mems: &[&Mem], now: DateTime<Utc>) {
+        let session = now - Duration::minutes(SESSION_MINUTES);
+        let shown: Vec<String> = mems.iter().map(|m| key(m)).collect();
+        let mut peers: Vec<String> = self
+            .mems
+            .iter()
+            .filter(|(k, r)| r.last_shown >= session && !shown.contains(k))
+            .map(|(k, _)| k.clone())
+            .collect();
+        for key in &shown {
+            for peer in &peers {
+                *self.together_entry(key, peer) += 1;
+                *self.together_entry(peer, key) += 1;
+            }
+            let record = self.mems.entry(key.clone()).or_insert(Record {
+                count: 0,
+                last_shown: now,
+            });
+            record.count += 1;
+            record.last_shown = now;
+            peers.push(key.clone());
+        }
+    }
+
+    fn together_entry(&mut self, a: &str, b: &str) -> &mut u64 {
+        self.together
+            .entry(a.to_string())
+            .or_default()
+            .entry(b.to_string())
+            .or_default()
+    }
+
+    /// How often and when `mem` was shown, if ever.
+    pub fn get(&self, mem: &Mem) -> Option<&Record> {
+        self.mems.get(&key(mem))
+    }
+
+    /// How strongly `other` is used together with `mem`, in [0, 1]: the
+    /// times they were shown together over the most `mem` was shown with
+    /// any one mem.
+    pub fn co_access(&self, mem: &Mem, other: &Mem) -> f32 {
+        let Some(peers) = self.together.get(&key(mem)) else {
+            return 0.0;
+        };
+        let most = peers.values().copied().max().unwrap_or(0);
+        if most == 0 {
+            return 0.0;
+        }
+        peers.get(&key(other)).copied().unwrap_or(0) as f32 / most as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn mem(path: &str) -> Mem {
+        let mut mem = Mem::new(PathBuf::from(path), path.to_string(), String::new());
+        mem.id = None;
+        mem
+    }
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        "2026-03-01T09:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn test_enable_and_disable() {
+        let temp = TempDir::new().unwrap();
+        assert!(Usage::open(temp.path()).unwrap().is_none());
+
+        assert!(Usage::enable(temp.path()).unwrap());
+        let mut usage = Usage::open(temp.path()).unwrap().unwrap();
+        usage.record(&[&mem("a")], at(0));
+        usage.save(temp.path()).unwrap();
+        assert!(!Usage::enable(temp.path()).unwrap());
+        let usage = Usage::open(temp.path()).unwrap().unwrap();
+        assert_eq!(usage.get(&mem("a")).unwrap().count, 1);
+
+        assert!(Usage::disable(temp.path()).unwrap());
+        assert!(!temp.path().join(USAGE_FILE).exists());
+        assert!(!Usage::disable(temp.path()).unwrap());
+    }
+
+    #[test]
+    fn test_record() {
+        let (a, b, c, d) = (mem("a"), mem("b"), mem("c"), mem("d"));
+        let mut usage = Usage::default();
+        usage.record(&[&a], at(0));
+        usage.record(&[&b], at(10));
+        usage.record(&[&a], at(20));
+        // Long after: a new session
+        usage.record(&[&c, &d], at(120));
+
+        assert_eq!(
+            usage.get(&a),
+            Some(&Record {
+                count: 2,
+                last_shown: at(20)
+            })
+        );
+        assert_eq!(usage.together["a"]["b"], 2);
+        assert_eq!(usage.together["b"]["a"], 2);
+        assert_eq!(usage.together["c"]["d"], 1);
+        assert!(!usage.together["c"].contains_key("a"));
+
+        assert_eq!(usage.co_access(&a, &b), 1.0);
+        assert_eq!(usage.co_access(&a, &c), 0.0);
+        assert_eq!(usage.co_access(&mem("never"), &a), 0.0);
+    }
+}
@@ -0,0 +1,299 @@
+use crate::html::escape;
+use crate::links;
+use std::path::Path;
+
+/// The browser UI served at `/` by `mem serve --web`.
+pub const INDEX_HTML: &str = include_str!("web/index.html");
+
+/// Render mem markdown as HTML for the browser UI.
+///
+/// Covers what mems commonly use: headings, paragraphs, nested lists, block
+/// quotes, fenced code, rules, and inline code, emphasis, links, and images.
+/// Links to other mems become `#/<path>` routes in the UI. Raw HTML is escaped.
+pub fn markdown_to_html(markdown: &str, mem_path: &Path) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut lists: Vec<&'static str> = Vec::new();
+    let mut fence: Option<String> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(code) = &mut fence {
+            if trimmed.starts_with("```") {
+                html.push_str(&format!("<pre><code>{}</code></pre>\n", escape(code)));
+                fence = None;
+            } else {
+                code.push_str(line);
+                code.push('\n');
+            }
+            continue;
+        }
+
+        let indent = line.len() - trimmed.len();
+        if let Some((tag, text)) = list_item(trimmed).filter(|_| !is_rule(trimmed)) {
+            flush(&mut html, &mut paragraph, &mut quote, mem_path);
+            let depth = indent / 2 + 1;
+            while lists.len() > depth {
+                close_list(&mut html, &mut lists);
+            }
+            if lists.len() == depth && lists.last() != Some(&tag) {
+                close_list(&mut html, &mut lists);
+            }
+            if lists.len() == depth {
+                html.push_str("</li>\n");
+            } else {
+                html.push_str(&format!("<{tag}>\n"));
+                lists.push(tag);
+            }
+            html.push_str(&format!("<li>{}", inline(text, mem_path)));
+            continue;
+        }
+        if !lists.is_empty() && indent > 0 && !trimmed.is_empty() {
+            // Continuation of a list item
+            html.push(' ');
+            html.push_str(&inline(trimmed, mem_path));
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush(&mut html, &mut paragraph, &mut quote, mem_path);
+            continue;
+        }
+        while !lists.is_empty() {
+            close_list(&mut html, &mut lists);
+        }
+
+        if trimmed.starts_with("```") {
+            flush(&mut html, &mut paragraph, &mut quote, mem_path);
+            fence = Some(String::new());
+        } else if let Some(text) = trimmed.strip_prefix('>') {
+            if !paragraph.is_empty() {
+                flush(&mut html, &mut paragraph, &mut quote, mem_path);
+            }
+            quote.push(text.trim_start());
+        } else if let Some((level, text)) = heading(trimmed) {
+            flush(&mut html, &mut paragraph, &mut quote, mem_path);
+            html.push_str(&format!(
+                "<h{level}>{}</h{level}>\n",
+                inline(text, mem_path)
+            ));
+        } else if is_rule(trimmed) {
+            flush(&mut html, &mut paragraph, &mut quote, mem_path);
+            html.push_str("<hr>\n");
+        } else {
+            if !quote.is_empty() {
+                flush(&mut html, &mut paragraph, &mut quote, mem_path);
+            }
+            paragraph.push(trimmed);
+        }
+    }
+
+    flush(&mut html, &mut paragraph, &mut quote, mem_path);
+    while !lists.is_empty() {
+        close_list(&mut html, &mut lists);
+    }
+    if let Some(code) = fence {
+        html.push_str(&format!("<pre><code>{}</code></pre>\n", escape(&code)));
+    }
+    html
+}
+
+fn flush(html: &mut String, paragraph: &mut Vec<&str>, quote: &mut Vec<&str>, mem_path: &Path) {
+    if !paragraph.is_empty() {
+        html.push_str(&format!(
+            "<p>{}</p>\n",
+            inline(&paragraph.join("\n"), mem_path)
+        ));
+        paragraph.clear();
+    }
+    if !quote.is_empty() {
+        html.push_str(&format!(
+            "<blockquote><p>{}</p></blockquote>\n",
+            inline(&quote.join("\n"), mem_path)
+        ));
+        quote.clear();
+    }
+}
+
+fn close_list(html: &mut String, lists: &mut Vec<&'static str>) {
+    if let Some(tag) = lists.pop() {
+        html.push_str(&format!("</li>\n</{tag}>\n"));
+    }
+}
+
+/// `- item`, `* item`, `+ item`, or `1. item`.
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(marker) {
+            return Some(("ul", text));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        if let Some(text) = line[digits..].strip_prefix(". ") {
+            return Some(("ol", text));
+        }
+    }
+    None
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    line[level..]
+        .strip_prefix(' ')
+        .map(|text| (level, text.trim()))
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&c| compact.chars().all(|x| x == c))
+}
+
+/// Render inline markup, escaping everything else.
+fn inline(text: &str, mem_path: &Path) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some((html, len)) = inline_span(rest, &out, mem_path) {
+            out.push_str(&html);
+            rest = &rest[len..];
+        } else {
+            out.push_str(&escape(&c.to_string()));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+/// HTML for a span starting at the beginning of `text`, and its length.
+fn inline_span(text: &str, before: &str, mem_path: &Path) -> Option<(String, usize)> {
+    if let Some(inner) = text.strip_prefix('`') {
+        let end = inner.find('`')?;
+        return Some((format!("<code>{}</code>", escape(&inner[..end])), end + 2));
+    }
+    if let Some(inner) = text.strip_prefix("**") {
+        let end = inner.find("**").filter(|&e| e > 0)?;
+        let html = format!("<strong>{}</strong>", inline(&inner[..end], mem_path));
+        return Some((html, end + 4));
+    }
+    if let Some(inner) = text.strip_prefix("[[") {
+        let end = inner.find("]]")?;
+        let (target, label) = inner[..end]
+            .split_once('|')
+            .unwrap_or((&inner[..end], &inner[..end]));
+        let html = format!(
+            "<a href=\"#/{}\">{}</a>",
+            escape(target.trim()),
+            escape(label.trim())
+        );
+        return Some((html, end + 4));
+    }
+    if let Some(inner) = text.strip_prefix("![") {
+        let (alt, target, len) = bracketed(inner)?;
+        let html = format!(
+            "<img alt=\"{}\" src=\"{}\">",
+            escape(alt),
+            escape(&safe_url(target))
+        );
+        return Some((html, len + 2));
+    }
+    if let Some(inner) = text.strip_prefix('[') {
+        let (label, target, len) = bracketed(inner)?;
+        let href = match links::resolve_internal(mem_path, target) {
+            Some(path) => format!("#/{path}"),
+            None => safe_url(target),
+        };
+        let html = format!(
+            "<a href=\"{}\">{}</a>",
+            escape(&href),
+            inline(label, mem_path)
+        );
+        return Some((html, len + 1));
+    }
+    for marker in ['*', '_'] {
+        if let Some(inner) = text.strip_prefix(marker) {
+            // Skip intraword underscores (snake_case)
+            let intraword = before
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric);
+            if marker == '_' && intraword {
+                return None;
+            }
+            let end = inner.find(marker).filter(|&e| e > 0)?;
+            let html = format!("<em>{}</em>", inline(&inner[..end], mem_path));
+            return Some((html, end + 2));
+        }
+    }
+    None
+}
+
+/// Parse `label](target)` after an opening bracket: label, target, and the
+/// length consumed.
+fn bracketed(text: &str) -> Option<(&str, &str, usize)> {
+    let close = text.find("](")?;
+    let end = text[close + 2..].find(')')?;
+    Some((
+        &text[..close],
+        &text[close + 2..close + 2 + end],
+        close + 3 + end,
+    ))
+}
+
+/// Keep relative and http(s)/mailto URLs; drop anything else (`javascript:`).
+fn safe_url(url: &str) -> String {
+    let scheme = url.split_once(':').map(|(s, _)| s.to_ascii_lowercase());
+    match scheme.as_deref() {
+        None | Some("http" | "https" | "mailto") => url.to_string(),
+        Some(s) if s.contains('/') || s.contains('#') => url.to_string(),
+        Some(_) => "#".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(markdown: &str) -> String {
+        markdown_to_html(markdown, Path::new("guides/setup"))
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            render("# Title\n\nSome *text*\nmore.\n\n---\n> quoted"),
+            "<h1>Title</h1>\n<p>Some <em>text</em>\nmore.</p>\n<hr>\n<blockquote><p>quoted</p></blockquote>\n"
+        );
+        assert_eq!(
+            render("```\n<b>\n```"),
+            "<pre><code>&lt;b&gt;\n</code></pre>\n"
+        );
+        assert_eq!(
+            render("- a\n  - b\n- c\n1. d"),
+            "<ul>\n<li>a<ul>\n<li>b</li>\n</ul>\n</li>\n<li>c</li>\n</ul>\n<ol>\n<li>d</li>\n</ol>\n"
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(
+            render("**bold** `x<y` snake_case_name"),
+            "<p><strong>bold</strong> <code>x&lt;y</code> snake_case_name</p>\n"
+        );
+        assert_eq!(
+            render("[db](../arch/db.md) [site](https://x.io) [bad](javascript:alert(1))"),
+            "<p><a href=\"#/arch/db\">db</a> <a href=\"https://x.io\">site</a> <a href=\"#\">bad</a>)</p>\n"
+        );
+        assert_eq!(
+            render("[[id:01ABC|Plan]] <script>"),
+            "<p><a href=\"#/id:01ABC\">Plan</a> &lt;script&gt;</p>\n"
+        );
+    }
+}
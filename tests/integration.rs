@@ -1,10 +1,26 @@
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Output};
 
 fn mem_cmd() -> Command {
     Command::new(env!("CARGO_BIN_EXE_mem"))
 }
 
+/// Run mem in `dir`, whether or not it succeeds.
+fn run(dir: &Path, args: &[&str]) -> Output {
+    mem_cmd()
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("failed to run")
+}
+
+/// Run mem in `dir`, check that it succeeded, and return its stdout.
+fn run_ok(dir: &Path, args: &[&str]) -> String {
+    let output = run(dir, args);
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
 fn setup_temp_dir() -> tempfile::TempDir {
     tempfile::TempDir::new().unwrap()
 }
@@ -397,7 +413,7 @@ fn test_archive() {
     assert!(!stdout.contains("to-archive"));
 
     // But file should exist in archive
-    assert!(temp.path().join(".mems/archive/to-archive.md").exists());
+    assert!(temp.path().join(".mems/archive/to-archive.md.zst").exists());
 }
 
 #[test]
@@ -610,3 +626,5137 @@ fn test_workflow_init_add_edit_archive() {
         .unwrap();
     assert!(!String::from_utf8_lossy(&output.stdout).contains("workflow"));
 }
+
+#[test]
+fn test_ls_long_shows_sizes() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "sized", "-c", "one two three four"])
+        .status()
+        .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["ls", "--long"])
+        .output()
+        .expect("failed to run");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("WORDS"));
+    let line = stdout.lines().find(|l| l.contains("sized")).unwrap();
+    let columns: Vec<&str> = line.split_whitespace().collect();
+    assert_eq!(columns[..3], ["18", "4", "5"]);
+
+    // JSON output carries the same fields
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["ls", "--json"])
+        .output()
+        .expect("failed to run");
+
+    let json: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    assert_eq!(json[0]["size"], 18);
+    assert_eq!(json[0]["words"], 4);
+    assert_eq!(json[0]["tokens"], 5);
+}
+
+#[test]
+fn test_stats_by_size() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "small", "-c", "tiny"])
+        .status()
+        .unwrap();
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "large", "-c", "a much longer piece of content"])
+        .status()
+        .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["stats", "--by-size"])
+        .output()
+        .expect("failed to run");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Mems:    2"));
+    assert!(stdout.contains("Words:   7"));
+    let large = stdout.find("large").unwrap();
+    let small = stdout.find("small").unwrap();
+    assert!(large < small, "largest mem should be listed first");
+}
+
+#[test]
+fn test_split() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "add",
+            "guides/setup",
+            "-c",
+            "Overview.\n\n# Install\nRun the installer.\n\n# Configure\nEdit the config, see [FAQ](../faq.md).",
+            "--tags",
+            "ops",
+        ])
+        .status()
+        .unwrap();
+    run_ok(temp.path(), &["add", "faq", "-c", "Answers."]);
+    assert!(run(temp.path(), &["lint"]).status.success());
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["split", "guides/setup"])
+        .output()
+        .expect("failed to run");
+
+    assert!(output.status.success());
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "guides/setup/configure", "--json"])
+        .output()
+        .expect("failed to run");
+
+    let json: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    assert_eq!(json["title"], "Configure");
+    // The child is a level deeper, so its relative link is too
+    assert_eq!(json["content"], "Edit the config, see [FAQ](../../faq.md).");
+    assert_eq!(json["tags"][0], "ops");
+
+    // The original becomes an index whose links pass lint
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "guides/setup"])
+        .output()
+        .expect("failed to run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Overview."));
+    assert!(stdout.contains("[Install](setup/install.md)"));
+    assert!(!stdout.contains("Run the installer."));
+
+    let output = run(temp.path(), &["lint"]);
+    assert!(output.status.success(), "{output:?}");
+
+    // The index has no headings left to split
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["split", "guides/setup"])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_find_semantic() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "add",
+            "ops/secrets",
+            "-c",
+            "Database credentials are rotated monthly through the vault.",
+            "-t",
+            "Secret rotation",
+        ])
+        .status()
+        .unwrap();
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "ui/colors", "-c", "Primary buttons are blue."])
+        .status()
+        .unwrap();
+
+    // Keyword search misses a paraphrased question entirely
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["find", "how do we cycle database credentials"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No matches"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "find",
+            "--semantic",
+            "how do we rotate database credentials",
+            "--json",
+        ])
+        .output()
+        .expect("failed to run");
+
+    assert!(output.status.success());
+    let json: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    assert_eq!(json[0]["path"], "ops/secrets");
+    assert!(json[0]["score"].as_f64().unwrap() > 0.0);
+    assert!(temp.path().join(".mems/.index/embeddings").exists());
+}
+
+#[test]
+fn test_related() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    for (path, content, tags) in [
+        (
+            "db/postgres",
+            "We chose postgres. See [pooling](pooling.md).",
+            "db",
+        ),
+        (
+            "db/pooling",
+            "Postgres connection pooling with pgbouncer.",
+            "db",
+        ),
+        ("ui/theme", "Dark mode colors for the dashboard.", "ui"),
+    ] {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", content, "--tags", tags])
+            .status()
+            .unwrap();
+    }
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["related", "db/postgres", "--json"])
+        .output()
+        .expect("failed to run");
+
+    assert!(output.status.success());
+    let json: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    assert_eq!(json[0]["path"], "db/pooling");
+    assert_eq!(json[0]["linked"], true);
+    assert_eq!(json[0]["tag_score"], 1.0);
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["related", "missing"])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+}
+
+/// Serve a single canned HTTP response on a local port, returning its base URL
+/// and a handle yielding the raw request that was received.
+fn serve_once(
+    content_type: &'static str,
+    body: &'static str,
+) -> (String, std::thread::JoinHandle<String>) {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let length = text
+                    .lines()
+                    .find_map(|l| {
+                        l.to_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|v| v.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                if request.len() >= header_end + 4 + length {
+                    break;
+                }
+            }
+            if n == 0 {
+                break;
+            }
+        }
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        String::from_utf8_lossy(&request).to_string()
+    });
+    (url, handle)
+}
+
+#[test]
+fn test_ask() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "add",
+            "db/choice",
+            "-c",
+            "We chose postgres because of JSONB support and team experience.",
+            "-t",
+            "Database choice",
+        ])
+        .status()
+        .unwrap();
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "ui/colors", "-c", "Primary buttons are blue."])
+        .status()
+        .unwrap();
+
+    // Retrieval alone needs no LLM
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["ask", "why did we choose postgres?", "--retrieve-only"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().next().unwrap().starts_with("db/choice"));
+
+    let (url, server) = serve_once(
+        "application/json",
+        r#"{"choices":[{"message":{"role":"assistant","content":"For JSONB support [db/choice]."}}]}"#,
+    );
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        format!("llm:\n  url: {url}/v1/chat/completions\n  model: test-model\n"),
+    )
+    .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["ask", "why did we choose postgres?"])
+        .output()
+        .expect("failed to run");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("For JSONB support [db/choice]."));
+    assert!(stdout.contains("Sources:\n  - db/choice"));
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /v1/chat/completions"));
+    assert!(request.contains("test-model"));
+    assert!(request.contains("team experience"));
+}
+
+#[test]
+fn test_clip() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    let (url, server) = serve_once(
+        "text/html",
+        "<html><head><title>Rotation Guide</title></head><body><h1>Rotation</h1>\
+         <p>Rotate keys <a href=\"/docs/keys\">monthly</a>.</p></body></html>",
+    );
+    let page_url = format!("{url}/guide");
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "clip",
+            &page_url,
+            "research/rotation",
+            "--note",
+            "Useful for our vault setup.",
+        ])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    server.join().unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "research/rotation", "--json"])
+        .output()
+        .expect("failed to run");
+
+    let json: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    assert_eq!(json["title"], "Rotation Guide");
+    assert_eq!(json["fields"]["source"], page_url.as_str());
+    assert!(json["fields"]["fetched-at"].is_string());
+    let content = json["content"].as_str().unwrap();
+    assert!(content.starts_with("Useful for our vault setup."));
+    assert!(content.contains(&format!("Rotate keys [monthly]({url}/docs/keys).")));
+}
+
+#[test]
+fn test_notify() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    std::fs::write(
+        temp.path().join(".mems/review.md"),
+        "---\ntitle: Cert renewal\ncreated-at: 2025-01-01T00:00:00Z\nupdated-at: 2025-01-01T00:00:00Z\ndue: 2025-02-01\n---\nRenew the TLS cert.",
+    )
+    .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["notify", "--format", "json"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    let json: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    assert_eq!(json["due"][0]["path"], "review");
+    assert_eq!(json["stale"][0]["path"], "review");
+
+    // Delivery goes to the webhook as a Slack-style text payload
+    let (url, server) = serve_once("text/plain", "ok");
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["notify", "--format", "webhook", "--webhook-url", &url])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    let request = server.join().unwrap();
+    assert!(request.contains("\"text\""));
+    assert!(request.contains("Cert renewal"));
+
+    // Only delivery failures are errors
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "notify",
+            "--format",
+            "webhook",
+            "--webhook-url",
+            "http://127.0.0.1:1/hook",
+        ])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_lint_formats_and_threshold() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    std::fs::write(
+        temp.path().join(".mems/empty.md"),
+        "---\ntitle: Empty\ncreated-at: 2025-01-01T00:00:00Z\nupdated-at: 2025-01-01T00:00:00Z\n---\n",
+    )
+    .unwrap();
+
+    // An empty mem is only a warning
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint", "--severity-threshold", "error"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("warning: empty content"));
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "linked", "-c", "Intro\n\nSee [gone](gone.md)"])
+        .status()
+        .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "lint",
+            "--format",
+            "github",
+            "--severity-threshold",
+            "error",
+        ])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout
+        .contains("::error file=.mems/linked.md,line=9,title=broken-link::broken link to gone.md"));
+    assert!(stdout.contains("::warning file=.mems/empty.md"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint", "--format", "sarif"])
+        .output()
+        .expect("failed to run");
+    let sarif: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    assert_eq!(sarif["version"], "2.1.0");
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    let broken = results
+        .iter()
+        .find(|r| r["ruleId"] == "broken-link")
+        .unwrap();
+    assert_eq!(
+        broken["locations"][0]["physicalLocation"]["region"]["startLine"],
+        9
+    );
+}
+
+#[test]
+fn test_lint_changed_since() {
+    let temp = setup_temp_dir();
+    let git = |args: &[&str]| {
+        assert!(Command::new("git")
+            .current_dir(temp.path())
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .success());
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.email", "t@example.com"]);
+    git(&["config", "user.name", "t"]);
+    init_mems(temp.path());
+
+    // A pre-existing broken link outside the change set is ignored
+    for (path, content) in [
+        ("legacy", "See [missing](missing.md)"),
+        ("target", "Target"),
+        ("linker", "See [target](target.md)"),
+    ] {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", content])
+            .status()
+            .unwrap();
+    }
+    git(&["add", "-A"]);
+    git(&["commit", "-q", "-m", "base"]);
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint", "--changed-since", "HEAD"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 mems checked"));
+
+    // Removing a mem breaks links into it from unchanged mems
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["rm", "target"])
+        .status()
+        .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint", "--changed-since", "HEAD"])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("linker"));
+    assert!(!stdout.contains("legacy"));
+}
+
+#[test]
+fn test_backup_create_restore_prune() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "notes", "-c", "Original"])
+        .status()
+        .unwrap();
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "old", "-c", "Old"])
+        .status()
+        .unwrap();
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["archive", "old"])
+        .status()
+        .unwrap();
+
+    let out = temp.path().join("snap.tar.zst");
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["backup", "create", "--out", out.to_str().unwrap()])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Backed up 3 files"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["backup", "verify", out.to_str().unwrap()])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["edit", "notes", "-c", "Changed"])
+        .status()
+        .unwrap();
+
+    // A non-empty store is only replaced with --force
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["backup", "restore", out.to_str().unwrap()])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["backup", "restore", out.to_str().unwrap(), "--force"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    let notes = std::fs::read_to_string(temp.path().join(".mems/notes.md")).unwrap();
+    assert!(notes.contains("Original"));
+    assert!(temp.path().join(".mems/archive/old.md.zst").exists());
+
+    for _ in 0..3 {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["backup", "create"])
+            .status()
+            .unwrap();
+    }
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["backup", "prune", "--keep", "1"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("2 backups removed"));
+    let remaining = std::fs::read_dir(temp.path().join(".mems/.backups"))
+        .unwrap()
+        .count();
+    assert_eq!(remaining, 1);
+}
+
+#[test]
+fn test_sync() {
+    let temp = setup_temp_dir();
+    let laptop = temp.path().join("laptop");
+    let nas = temp.path().join("nas");
+    std::fs::create_dir_all(&laptop).unwrap();
+    std::fs::create_dir_all(&nas).unwrap();
+    init_mems(&laptop);
+    init_mems(&nas);
+
+    mem_cmd()
+        .current_dir(&laptop)
+        .args(["add", "notes", "-c", "From laptop"])
+        .status()
+        .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(&laptop)
+        .args(["sync", nas.to_str().unwrap(), "--dry-run"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Would sync: 1 pushed"), "{stdout}");
+    assert!(!stdout.contains("store.yaml"), "{stdout}");
+    assert!(!nas.join(".mems/notes.md").exists());
+
+    let output = mem_cmd()
+        .current_dir(&laptop)
+        .args(["sync", nas.to_str().unwrap()])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(nas.join(".mems/notes.md").exists());
+
+    // Each store keeps its own identity
+    let store = std::fs::read_to_string(nas.join(".mems/store.yaml")).unwrap();
+    assert!(store.contains("name: nas"), "{store}");
+
+    // Concurrent edits produce a conflict copy instead of an overwrite
+    mem_cmd()
+        .current_dir(&laptop)
+        .args(["edit", "notes", "-c", "Laptop edit"])
+        .status()
+        .unwrap();
+    mem_cmd()
+        .current_dir(&nas)
+        .args(["edit", "notes", "-c", "NAS edit"])
+        .status()
+        .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(&laptop)
+        .args(["sync", nas.to_str().unwrap()])
+        .output()
+        .expect("failed to run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Conflict: notes.md (kept "));
+    assert!(stdout.contains("1 conflicts"));
+
+    let output = mem_cmd()
+        .current_dir(&laptop)
+        .args(["ls"])
+        .output()
+        .expect("failed to run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("notes.conflict-"));
+
+    // The copy has an id of its own
+    let output = run(&laptop, &["lint"]);
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_store_flag() {
+    let temp = setup_temp_dir();
+    let other = temp.path().join("other");
+    std::fs::create_dir_all(&other).unwrap();
+    init_mems(&other);
+    mem_cmd()
+        .current_dir(&other)
+        .args(["add", "remote-note", "-c", "Hello"])
+        .status()
+        .unwrap();
+
+    // No .mems/ in the working directory, but --store points at one
+    let store = other.join(".mems");
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["--store", store.to_str().unwrap(), "ls"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("remote-note"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .env("MEM_STORE", store.to_str().unwrap())
+        .args(["show", "remote-note"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+
+    if !cfg!(feature = "s3") {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(["--store", "s3://bucket/prefix", "ls"])
+            .output()
+            .expect("failed to run");
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--features s3"));
+    }
+}
+
+#[test]
+fn test_cache_tags_and_backlinks() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    for (path, content, tags) in [
+        ("db/postgres", "Chosen for JSONB", "db,decision"),
+        ("db/index", "See [postgres](postgres.md)", "db"),
+        ("overview", "Start at [db](db/postgres.md)", "doc"),
+    ] {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", content, "--tags", tags])
+            .status()
+            .unwrap();
+    }
+
+    // Same answers with and without the cache
+    for enable in [false, true] {
+        if enable {
+            let output = mem_cmd()
+                .current_dir(temp.path())
+                .args(["cache", "build"])
+                .output()
+                .expect("failed to run");
+            assert!(String::from_utf8_lossy(&output.stdout).contains("Cached 3 mems"));
+        }
+
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(["tags", "--json"])
+            .output()
+            .expect("failed to run");
+        let json: serde_json::Value =
+            serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+        assert_eq!(json[0]["tag"], "db");
+        assert_eq!(json[0]["count"], 2);
+
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(["backlinks", "db/postgres"])
+            .output()
+            .expect("failed to run");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(
+            stdout.lines().collect::<Vec<_>>(),
+            vec!["db/index", "overview"]
+        );
+
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(["find", "postgres", "--title-only"])
+            .output()
+            .expect("failed to run");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("db/postgres"));
+        assert!(!stdout.contains("db/index"));
+    }
+
+    // Writes and out-of-band edits are both reflected
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["rm", "overview"])
+        .status()
+        .unwrap();
+    std::fs::write(
+        temp.path().join(".mems/manual.md"),
+        "---\ntitle: Manual\ncreated-at: 2025-01-01T00:00:00Z\nupdated-at: 2025-01-01T00:00:00Z\n---\nSee [pg](db/postgres.md)",
+    )
+    .unwrap();
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["backlinks", "db/postgres"])
+        .output()
+        .expect("failed to run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["db/index", "manual"]
+    );
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["cache", "status"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("3 mems"));
+}
+
+#[test]
+fn test_ids_survive_mv() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "drafts/plan", "-c", "The plan"])
+        .status()
+        .unwrap();
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "drafts/plan", "--json"])
+        .output()
+        .expect("failed to run");
+    let json: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    let id = json["id"].as_str().unwrap().to_string();
+    assert_eq!(id.len(), 26);
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "index", "-c", &format!("See [[id:{id}|the plan]]")])
+        .status()
+        .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["mv", "drafts/plan", "plans/q3"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Moved: drafts/plan -> plans/q3"));
+
+    // The id still resolves after the move, and links by id still count
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", &format!("id:{id}")])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("The plan"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["backlinks", &format!("id:{id}")])
+        .output()
+        .expect("failed to run");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "index");
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+
+    // Overwriting a mem keeps its id
+    run_ok(
+        temp.path(),
+        &["add", "plans/q3", "-c", "The new plan", "--force"],
+    );
+    assert!(run_ok(temp.path(), &["show", &format!("id:{id}")]).contains("The new plan"));
+
+    // A copy with the same id makes the id ambiguous, and lint says so
+    let plans = temp.path().join(".mems/plans");
+    std::fs::copy(plans.join("q3.md"), plans.join("q3-copy.md")).unwrap();
+    let output = run(temp.path(), &["show", &format!("id:{id}")]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("matches several mems"));
+    let output = run(temp.path(), &["lint"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!(
+            "plans/q3:2: error: id {id} is also used by plans/q3-copy"
+        )),
+        "{stdout}"
+    );
+    std::fs::remove_file(plans.join("q3-copy.md")).unwrap();
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["rm", &format!("id:{id}")])
+        .status()
+        .unwrap();
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint"])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("broken link to id:{id}")));
+}
+
+#[test]
+fn test_check_refs_and_backrefs() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "arch/retries", "-c", "Retry three times"])
+        .status()
+        .unwrap();
+    std::fs::create_dir_all(temp.path().join("src")).unwrap();
+    std::fs::write(
+        temp.path().join("src/client.rs"),
+        "// Retry policy: see mem:arch/retries.\nfn call() {}\n",
+    )
+    .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["check-refs", "--src", "src"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("All 1 mem references resolve"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["backrefs", "arch/retries", "--src", "src"])
+        .output()
+        .expect("failed to run");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "src/client.rs:1"
+    );
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["archive", "arch/retries"])
+        .status()
+        .unwrap();
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["check-refs", "--src", "src"])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("src/client.rs:1: mem:arch/retries (archived)"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["check-refs", "--src", "src", "--allow-archived"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_conflicts_and_resolve() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    std::fs::write(
+        temp.path().join(".mems/plan.md"),
+        "---
+title: Plan
+created-at: 2025-01-01T00:00:00Z
+<<<<<<< HEAD
+updated-at: 2025-01-03T00:00:00Z
+tags:
+  - a
+=======
+updated-at: 2025-01-02T00:00:00Z
+tags:
+  - b
+>>>>>>> feature
+---
+Ship it
+",
+    )
+    .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["ls"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unresolved merge conflict in plan"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["conflicts"])
+        .output()
+        .expect("failed to run");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "plan");
+
+    // A side must be chosen
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["resolve", "plan"])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["resolve", "plan", "--union-tags"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "plan", "--json"])
+        .output()
+        .expect("failed to run");
+    let json: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    assert_eq!(json["tags"], serde_json::json!(["a", "b"]));
+    assert!(json["updated_at"]
+        .as_str()
+        .unwrap()
+        .starts_with("2025-01-03"));
+}
+
+#[test]
+fn test_stale_git_timestamps() {
+    let temp = setup_temp_dir();
+    let git = |args: &[&str]| {
+        assert!(Command::new("git")
+            .current_dir(temp.path())
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .success());
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.email", "t@example.com"]);
+    git(&["config", "user.name", "t"]);
+    init_mems(temp.path());
+
+    // Frontmatter says fresh, but the last commit is old
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "old-news", "-c", "Edited long ago"])
+        .status()
+        .unwrap();
+    git(&["add", "-A"]);
+    git(&[
+        "commit",
+        "-q",
+        "-m",
+        "old",
+        "--date",
+        "2020-01-01T00:00:00Z",
+    ]);
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["stale", "--days", "30"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No stale mems"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["stale", "--days", "30", "--timestamps", "git"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("old-news"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["notify", "--format", "json", "--timestamps", "git"])
+        .output()
+        .expect("failed to run");
+    let json: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    assert_eq!(json["stale"][0]["path"], "old-news");
+}
+
+#[test]
+fn test_path_normalization() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "arch\\decisions\\ADR-001/", "-c", "Use Postgres"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Created: arch/decisions/ADR-001"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "Arch/Decisions/adr-001"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Use Postgres"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "../escape", "-c", "nope"])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("must not contain '..'"));
+    assert!(!temp.path().join("escape.md").exists());
+}
+
+#[test]
+fn test_not_found_suggestions() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "guides/setup", "-c", "Install it"])
+        .status()
+        .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "guides/setpu"])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("mem not found: guides/setpu (did you mean guides/setup?)"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "guides/setpu", "--fuzzy"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Install it"));
+}
+
+#[test]
+fn test_exit_codes() {
+    let temp = setup_temp_dir();
+    let code = |args: &[&str]| {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(args)
+            .output()
+            .expect("failed to run")
+            .status
+            .code()
+    };
+
+    assert_eq!(code(&["ls"]), Some(6));
+    init_mems(temp.path());
+    assert_eq!(code(&["ls"]), Some(0));
+    assert_eq!(code(&["show", "missing"]), Some(3));
+    assert_eq!(code(&["add", "../escape", "-c", "x"]), Some(2));
+    assert_eq!(code(&["no-such-command"]), Some(2));
+
+    assert_eq!(code(&["add", "empty", "-c", " "]), Some(0));
+    assert_eq!(code(&["lint"]), Some(4));
+
+    std::fs::write(
+        temp.path().join(".mems/empty.md"),
+        "<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> other\n",
+    )
+    .unwrap();
+    assert_eq!(code(&["show", "empty"]), Some(5));
+
+    let output = mem_cmd().arg("--help").output().expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("4  check failed"));
+}
+
+#[test]
+fn test_quiet_and_verbose() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["-q", "add", "note", "-c", "Hello"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // Data is still printed
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["ls", "--quiet"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("note"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["-v", "edit", "note", "-c", "Bye"])
+        .output()
+        .expect("failed to run");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("wrote mem"), "{stderr}");
+    assert!(stderr.contains("command finished"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Updated: note"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .env("MEM_LOG", "mem=trace")
+        .args(["show", "note"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("reading mem"));
+}
+
+#[test]
+fn test_output_json_on_mutations() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let json = |args: &[&str]| -> serde_json::Value {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(args)
+            .output()
+            .expect("failed to run");
+        assert!(output.status.success());
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON")
+    };
+
+    let created = json(&["add", "notes/a", "-c", "Hello", "--output", "json"]);
+    assert_eq!(created["path"], "notes/a");
+    let id = created["id"].as_str().unwrap().to_string();
+    assert!(created["created_at"].is_string());
+
+    let edited = json(&["edit", "notes/a", "-t", "Renamed", "--output", "json"]);
+    assert_eq!(edited["title"], "Renamed");
+
+    let moved = json(&["mv", "notes/a", "notes/b", "--output", "json"]);
+    assert_eq!(moved["path"], "notes/b");
+    assert_eq!(moved["id"], id.as_str());
+
+    let archived = json(&["archive", "notes/b", "--output", "json"]);
+    assert_eq!(
+        archived,
+        serde_json::json!({"action": "archived", "path": "notes/b", "id": id})
+    );
+
+    json(&["add", "notes/c", "-c", "Bye", "--output", "json"]);
+    let deleted = json(&["rm", "notes/c", "--output", "json"]);
+    assert_eq!(deleted["action"], "deleted");
+}
+
+#[test]
+fn test_verify_checksums() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let mems = temp.path().join(".mems");
+    std::fs::write(mems.join("config.yaml"), "integrity:\n  checksums: true\n").unwrap();
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "note", "-c", "Original"])
+        .output()
+        .expect("failed to run");
+    let raw = std::fs::read_to_string(mems.join("note.md")).unwrap();
+    assert!(raw.contains("checksum: sha256:"));
+
+    let verify = |args: &[&str]| {
+        mem_cmd()
+            .current_dir(temp.path())
+            .arg("verify")
+            .args(args)
+            .output()
+            .expect("failed to run")
+    };
+    assert!(verify(&[]).status.success());
+
+    std::fs::write(mems.join("note.md"), raw.replace("Original", "Mangled")).unwrap();
+    let output = verify(&["--json"]);
+    assert_eq!(output.status.code(), Some(4));
+    let issues: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).expect("invalid JSON");
+    assert_eq!(issues[0]["path"], "note");
+    assert_eq!(issues[0]["problem"], "checksum-mismatch");
+
+    assert!(verify(&["--update"]).status.success());
+    assert!(verify(&[]).status.success());
+}
+
+#[test]
+fn test_import_dendron() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let vault = temp.path().join("vault");
+    std::fs::create_dir(&vault).unwrap();
+    std::fs::write(
+        vault.join("arch.decisions.adr-001.md"),
+        "---\nid: x1\ntitle: Database Choice\ncreated: 1690000000000\nupdated: 1700000000000\n---\nSee [[arch.overview]].\n",
+    )
+    .unwrap();
+    std::fs::write(vault.join("arch.overview.md"), "# Overview\nThe map.\n").unwrap();
+
+    let import = || {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["import", "--format", "dendron", "vault", "--into", "notes"])
+            .output()
+            .expect("failed to run")
+    };
+    let output = import();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Imported 2 mems"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "notes/arch/decisions/adr-001"])
+        .output()
+        .expect("failed to run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Database Choice"));
+    assert!(stdout.contains("[arch.overview](../overview.md)"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["backlinks", "notes/arch/overview"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("notes/arch/decisions/adr-001"));
+
+    // Re-importing refuses to overwrite
+    assert!(!import().status.success());
+}
+
+#[test]
+fn test_export_dir_plain() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "add",
+            "guides/setup",
+            "-t",
+            "Setup",
+            "-c",
+            "Run it.",
+            "--tags",
+            "ops",
+        ])
+        .output()
+        .expect("failed to run");
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["export", "dir", "out", "--plain"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(temp.path().join("out/guides/setup.md")).unwrap(),
+        "# Setup\n\nRun it.\n\n---\n\nTags: `ops`\n"
+    );
+
+    // A non-empty target needs --force
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["export", "dir", "out"])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_includes() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let add = |path: &str, content: &str| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", content])
+            .output()
+            .expect("failed to run");
+        assert!(output.status.success());
+    };
+    add("parts/intro", "Welcome.");
+    add("overview", "Start\n{{include: parts/intro}}\nEnd");
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "overview", "--render"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Start\nWelcome.\nEnd"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["dump"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Start\nWelcome.\nEnd"));
+
+    // Include-like text that isn't one doesn't stop the dump
+    add(
+        "notes/syntax",
+        "Write `{{include: path}}`; see ![[Some Note]]",
+    );
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["dump"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Write `{{include: path}}`; see ![[Some Note]]"));
+
+    add("loop/a", "![[loop/b]]");
+    add("loop/b", "![[loop/a]] and ![[loop/missing]]");
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint"])
+        .output()
+        .expect("failed to run");
+    assert_eq!(output.status.code(), Some(4));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("include cycle: loop/a -> loop/b -> loop/a"));
+    assert!(stdout.contains("broken include of loop/missing"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "loop/a", "--render"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("include cycle"));
+}
+
+#[test]
+fn test_serve_api() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "notes/a", "-t", "Alpha", "-c", "Hello"])
+        .output()
+        .expect("failed to run");
+
+    let mut server = mem_cmd()
+        .current_dir(temp.path())
+        .args(["serve", "--addr", "127.0.0.1:0"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to start server");
+    let mut banner = String::new();
+    BufReader::new(server.stdout.take().unwrap())
+        .read_line(&mut banner)
+        .unwrap();
+    let addr = banner
+        .split("http://")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .expect("no address in banner")
+        .to_string();
+
+    let get = |path: &str| {
+        let mut stream = std::net::TcpStream::connect(&addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    // A client that connects and sends nothing doesn't hold up the others
+    let _idle = std::net::TcpStream::connect(&addr).unwrap();
+    let list = get("/api/mems");
+    let show = get("/api/mems/notes/a");
+    let missing = get("/api/mems/nope");
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(list.starts_with("HTTP/1.1 200"), "{list}");
+    assert!(list.contains("\"title\": \"Alpha\""));
+    assert!(show.contains("\"content\": \"Hello\""));
+    assert!(missing.starts_with("HTTP/1.1 404"));
+}
+
+#[test]
+fn test_directory_metadata() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let adr_dir = temp.path().join(".mems/arch/decisions");
+    std::fs::create_dir_all(&adr_dir).unwrap();
+    std::fs::write(
+        adr_dir.join("_dir.yaml"),
+        "description: Architecture decision records\nowner: platform\ntags: [adr]\nrequired-fields: [status]\n",
+    )
+    .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "add",
+            "arch/decisions/adr-001",
+            "-c",
+            "Use Postgres",
+            "--tags",
+            "db",
+        ])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    let raw = std::fs::read_to_string(adr_dir.join("adr-001.md")).unwrap();
+    assert!(raw.contains("- db\n- adr\n"), "{raw}");
+    assert!(raw.contains("owner: platform"), "{raw}");
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["tree"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("decisions/ - Architecture decision records"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["ls"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("arch/decisions/: Architecture decision records\narch/decisions/adr-001:"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint"])
+        .output()
+        .expect("failed to run");
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("missing required field: status"));
+}
+
+#[test]
+fn test_schemas() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        "schemas:\n  - prefix: arch/decisions\n    required-fields: [status, deciders]\n    allowed-statuses: [proposed, accepted]\n    allowed-tags: [adr, db]\n",
+    )
+    .unwrap();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "add",
+            "arch/decisions/adr-001",
+            "-c",
+            "Use Postgres",
+            "--tags",
+            "db,misc",
+        ])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    let raw = std::fs::read_to_string(temp.path().join(".mems/arch/decisions/adr-001.md")).unwrap();
+    assert!(raw.contains("status: proposed"), "{raw}");
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint"])
+        .output()
+        .expect("failed to run");
+    assert_eq!(output.status.code(), Some(4));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("missing required field: deciders"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("tag \"misc\" is not allowed here"),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("status"), "{stdout}");
+}
+
+#[test]
+fn test_tag_vocabulary() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let add = |path: &str, tags: &str| {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", "x", "--tags", tags])
+            .output()
+            .expect("failed to run")
+    };
+
+    // Without a vocabulary, near misses of tags in use are flagged
+    add("a", "infra");
+    let output = add("b", "infar");
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Unknown tag infar (did you mean infra?)")
+    );
+
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        "tags:\n  allowed: [infra, ops]\n",
+    )
+    .unwrap();
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["tags", "--unknown"])
+        .output()
+        .expect("failed to run");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "b: infar (did you mean infra?)\n"
+    );
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint"])
+        .output()
+        .expect("failed to run");
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("tag \"infar\" is not allowed here (did you mean infra?)"));
+}
+
+#[test]
+fn test_blame() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "oncall",
+            "-c",
+            "Intro\n\n## Escalation\n\nPage the lead",
+        ],
+    );
+
+    // Outside git, history comes from backup snapshots
+    run_ok(temp.path(), &["backup", "create"]);
+    let stdout = run_ok(temp.path(), &["blame", "oncall"]);
+    assert!(stdout.contains("changes to oncall between backup snapshots"));
+    assert!(stdout.contains("added"));
+
+    let git = |args: &[&str]| {
+        assert!(Command::new("git")
+            .current_dir(temp.path())
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .success());
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.email", "ana@example.com"]);
+    git(&["config", "user.name", "Ana"]);
+    git(&["add", "-A"]);
+    git(&[
+        "commit",
+        "-q",
+        "-m",
+        "init",
+        "--date",
+        "2024-03-01T00:00:00Z",
+    ]);
+
+    let stdout = run_ok(temp.path(), &["blame", "oncall", "--sections"]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{stdout}");
+    assert!(lines[0].contains("2024-03-01  Ana") && lines[0].contains("(top) (lines"));
+    assert!(lines[1].contains("Escalation (lines"));
+
+    let stdout = run_ok(temp.path(), &["blame", "oncall"]);
+    assert!(
+        stdout.lines().next().unwrap().ends_with("Intro"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn test_ownership() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(
+        temp.path(),
+        &["add", "runbooks/db", "-c", "x", "--owner", "alice"],
+    );
+    run_ok(temp.path(), &["add", "runbooks/web", "-c", "x"]);
+    run_ok(temp.path(), &["add", "runbooks/dns", "-c", "x"]);
+    run_ok(temp.path(), &["add", "notes", "-c", "x"]);
+
+    let before = std::fs::read_to_string(temp.path().join(".mems/runbooks/web.md")).unwrap();
+    let stdout = run_ok(temp.path(), &["assign", "runbooks", "bob"]);
+    assert!(stdout.contains("Assigned: runbooks/web -> bob"));
+    let after = std::fs::read_to_string(temp.path().join(".mems/runbooks/web.md")).unwrap();
+    assert!(after.contains("owner: bob"));
+    let updated = |raw: &str| {
+        raw.lines()
+            .find(|l| l.starts_with("updated-at"))
+            .map(str::to_string)
+    };
+    assert_eq!(updated(&before), updated(&after));
+    run_ok(temp.path(), &["assign", "runbooks/db", "alice"]);
+
+    let stdout = run_ok(temp.path(), &["ls", "--owner", "bob"]);
+    assert!(stdout.contains("runbooks/dns") && stdout.contains("runbooks/web"));
+    assert!(!stdout.contains("runbooks/db") && !stdout.contains("notes"));
+
+    let stdout = run_ok(temp.path(), &["stale", "--days", "0", "--by-owner"]);
+    let alice = stdout.find("alice (1):").unwrap();
+    let bob = stdout.find("bob (2):").unwrap();
+    let unowned = stdout.find("(unowned) (1):").unwrap();
+    assert!(alice < bob && bob < unowned, "{stdout}");
+}
+
+#[test]
+fn test_digest() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(temp.path(), &["add", "fresh", "-c", "x"]);
+    run_ok(temp.path(), &["add", "retired", "-c", "x"]);
+    run_ok(temp.path(), &["archive", "retired"]);
+
+    let stdout = run_ok(temp.path(), &["digest", "--since", "1d"]);
+    assert!(
+        stdout.contains("## New (1)\n\n- fresh (`fresh`"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("## Archived (1)\n\n- retired (`retired`"),
+        "{stdout}"
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_str(&run_ok(temp.path(), &["digest", "--format", "json"])).unwrap();
+    assert_eq!(json["created"][0]["path"], "fresh");
+    assert_eq!(json["archived"][0]["path"], "retired");
+
+    assert!(run_ok(temp.path(), &["digest", "--format", "html"]).contains("<h2>New (1)</h2>"));
+}
+
+#[test]
+fn test_review() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(temp.path(), &["add", "a", "-c", "x"]);
+    run_ok(temp.path(), &["add", "b", "-c", "x"]);
+    run_ok(temp.path(), &["add", "c", "-c", "x"]);
+
+    let picks = |stdout: &str| -> Vec<String> {
+        let json: serde_json::Value = serde_json::from_str(stdout).unwrap();
+        json.as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["path"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let today = picks(&run_ok(temp.path(), &["review", "--count", "2", "--json"]));
+    assert_eq!(today.len(), 2);
+    assert_eq!(
+        picks(&run_ok(temp.path(), &["review", "--count", "2", "--json"])),
+        today
+    );
+
+    let stdout = run_ok(temp.path(), &["review", "--count", "2"]);
+    assert!(stdout.contains(&format!("  {}: {}", today[0], today[0])));
+
+    run_ok(temp.path(), &["review", "done", "a", "b", "c"]);
+    assert!(temp.path().join(".mems/.review.json").exists());
+    assert!(picks(&run_ok(temp.path(), &["review", "--json"])).is_empty());
+    assert!(run_ok(temp.path(), &["review"]).contains("Nothing to review today"));
+}
+
+#[test]
+fn test_pinned() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(temp.path(), &["add", "alpha", "-c", "x"]);
+    run_ok(
+        temp.path(),
+        &["add", "team/oncall", "-c", "x", "-t", "Oncall"],
+    );
+    run_ok(temp.path(), &["add", "team/zeta", "-c", "x"]);
+    run_ok(temp.path(), &["pin", "team/oncall"]);
+
+    let stdout = run_ok(temp.path(), &["ls"]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "team/oncall: Oncall (pinned)");
+    assert!(lines[1].starts_with("alpha:"), "{stdout}");
+
+    let stdout = run_ok(temp.path(), &["ls", "--pinned"]);
+    assert_eq!(stdout, "team/oncall: Oncall (pinned)\n");
+
+    let stdout = run_ok(temp.path(), &["tree"]);
+    let pinned = stdout.find("oncall - Oncall (pinned)").unwrap();
+    let zeta = stdout.find("zeta").unwrap();
+    assert!(pinned < zeta, "{stdout}");
+
+    run_ok(temp.path(), &["unpin", "team/oncall"]);
+    assert_eq!(run_ok(temp.path(), &["ls", "--pinned"]), "No mems found\n");
+}
+
+#[test]
+fn test_find_highlight_and_open() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let colored = |args: &[&str]| {
+        mem_cmd()
+            .current_dir(temp.path())
+            .env("CLICOLOR_FORCE", "1")
+            .args(args)
+            .output()
+            .expect("failed to run")
+    };
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "db/rotate",
+            "-t",
+            "Rotate Postgres",
+            "-c",
+            "# Steps\n\nRun the postgres rotation job.",
+        ],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "db/backup", "-c", "Postgres backups run nightly."],
+    );
+
+    let output = colored(&["find", "postgres"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("db/rotate: Rotate \x1b[1;33mPostgres\x1b[0m\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("    Run the \x1b[1;33mpostgres\x1b[0m rotation job.\n"));
+
+    // Without a terminal, the only match is shown
+    let output = colored(&["find", "rotation", "--open"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("# Rotate Postgres\n"), "{stdout}");
+
+    // Several matches need a terminal to pick from
+    let output = colored(&["find", "postgres", "--open"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 mems match"));
+}
+
+#[test]
+fn test_lint_structure() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        "schemas:\n  - prefix: adr\n    required-headings: [\"## Context\", \"## Decision\", \"## Consequences\"]\nlint:\n  rules:\n    skipped-heading-level: error\n",
+    )
+    .unwrap();
+    let lint = || {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["lint", "--severity-threshold", "error"])
+            .output()
+            .expect("failed to run")
+    };
+    mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "add",
+            "adr/001",
+            "-c",
+            "## Context\n\nSlow queries.\n\n#### Options\n\n## Decision\n\nAdd an index.",
+        ])
+        .output()
+        .expect("failed to run");
+
+    let output = lint();
+    assert_eq!(output.status.code(), Some(4));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("missing required heading: ## Consequences"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("heading level skips from h2 to h4: Options"));
+
+    mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "edit",
+            "adr/001",
+            "-c",
+            "## Context\n\nSlow queries.\n\n### Options\n\n## Decision\n\nAdd an index.\n\n## Consequences\n\nFaster reads.",
+        ])
+        .output()
+        .expect("failed to run");
+    assert!(lint().status.success());
+}
+
+#[test]
+fn test_lint_dictionary() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    std::fs::write(
+        temp.path().join(".mems/dictionary.txt"),
+        "# Renamed in 2024\nMegaStore -> Catalog\nteh -> the\nwhitelist\n",
+    )
+    .unwrap();
+    mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "add",
+            "notes",
+            "-c",
+            "Teh MegaStore API keeps a whitelist.\n\n`megastore` stays in code.",
+        ])
+        .output()
+        .expect("failed to run");
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint"])
+        .output()
+        .expect("failed to run");
+    assert_eq!(output.status.code(), Some(4));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("\"MegaStore\" should be \"Catalog\""),
+        "{stdout}"
+    );
+    assert!(stdout.contains("\"whitelist\" is a banned term"));
+    assert!(stdout.contains("Found 3 issues"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint", "--fix"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Fixed 2 terms in notes"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Found 1 issues"), "{stdout}");
+    let raw = std::fs::read_to_string(temp.path().join(".mems/notes.md")).unwrap();
+    assert!(raw.contains("The Catalog API keeps a whitelist.\n\n`megastore` stays in code."));
+}
+
+#[test]
+fn test_lint_check_external() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let (live, handle) = serve_once("text/html", "");
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let dead = format!("http://{}/gone", closed.local_addr().unwrap());
+    drop(closed);
+    mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "add",
+            "links",
+            "-c",
+            &format!("See [docs]({live}/docs).\n\nOld [page]({dead})."),
+        ])
+        .output()
+        .expect("failed to run");
+
+    let lint = || {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["lint", "--check-external"])
+            .output()
+            .expect("failed to run")
+    };
+    let output = lint();
+    assert!(handle.join().unwrap().starts_with("HEAD /docs"));
+    assert_eq!(output.status.code(), Some(4));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Found 1 issues"), "{stdout}");
+    assert!(stdout.contains(&format!(
+        "links:9: warning: dead link to {dead} (Connection Failed)"
+    )));
+
+    // Cached: the live server is gone, but the link is not requested again
+    let stdout = String::from_utf8_lossy(&lint().stdout).to_string();
+    assert!(stdout.contains("Found 1 issues"), "{stdout}");
+    assert!(temp.path().join(".mems/.linkcache").exists());
+
+    // Without the flag, no requests are made
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint"])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_meta() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(temp.path(), &["add", "svc/api", "-c", "x"]);
+    run_ok(temp.path(), &["add", "svc/db", "-c", "x"]);
+    run_ok(temp.path(), &["meta", "set", "svc/api", "priority", "high"]);
+    run_ok(temp.path(), &["meta", "set", "svc/api", "tier", "1"]);
+    run_ok(temp.path(), &["meta", "set", "svc/db", "priority", "low"]);
+
+    assert_eq!(
+        run_ok(temp.path(), &["meta", "get", "svc/api", "priority"]),
+        "high\n"
+    );
+    assert_eq!(
+        run_ok(temp.path(), &["meta", "get", "svc/api"]),
+        "priority: high\ntier: 1\n"
+    );
+    assert_eq!(
+        run_ok(temp.path(), &["meta", "get", "svc/api", "tier", "--json"]),
+        "1\n"
+    );
+    let raw = std::fs::read_to_string(temp.path().join(".mems/svc/api.md")).unwrap();
+    assert!(raw.contains("tier: 1\n"), "{raw}");
+
+    let stdout = run_ok(temp.path(), &["ls", "--where", "priority=high"]);
+    assert!(stdout.contains("svc/api") && !stdout.contains("svc/db"));
+    let stdout = run_ok(
+        temp.path(),
+        &["ls", "--where", "priority", "--where", "tier!=1"],
+    );
+    assert!(stdout.contains("svc/db") && !stdout.contains("svc/api"));
+
+    run_ok(temp.path(), &["meta", "unset", "svc/api", "priority"]);
+    assert_eq!(
+        run_ok(temp.path(), &["ls", "--where", "priority=high"]),
+        "No mems found\n"
+    );
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["meta", "set", "svc/api", "title", "x"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("title is a built-in field"));
+}
+
+#[test]
+fn test_tasks() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "ops/oncall",
+            "-c",
+            "Follow-ups:\n\n- [ ] Rotate keys\n- [x] Page secondary",
+        ],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "notes", "--content=- [ ] Write notes"],
+    );
+
+    let stdout = run_ok(temp.path(), &["tasks"]);
+    assert!(stdout.contains("notes:"), "{stdout}");
+    let line = stdout
+        .lines()
+        .find(|l| l.ends_with("[ ] Rotate keys"))
+        .and_then(|l| l.split(':').nth(1))
+        .expect("task listed")
+        .to_string();
+    assert!(!stdout.contains("Page secondary"));
+    let raw = std::fs::read_to_string(temp.path().join(".mems/ops/oncall.md")).unwrap();
+    let number: usize = line.parse().unwrap();
+    assert_eq!(raw.lines().nth(number - 1), Some("- [ ] Rotate keys"));
+
+    let stdout = run_ok(temp.path(), &["tasks", "ops", "--all", "--json"]);
+    let tasks: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(tasks.as_array().unwrap().len(), 2);
+    assert_eq!(tasks[1]["done"], true);
+
+    run_ok(temp.path(), &["tasks", "done", "ops/oncall", &line]);
+    assert_eq!(run_ok(temp.path(), &["tasks", "ops"]), "No open tasks\n");
+    let raw = std::fs::read_to_string(temp.path().join(".mems/ops/oncall.md")).unwrap();
+    assert!(raw.contains("- [x] Rotate keys"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["tasks", "done", "ops/oncall", "1"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no task on line 1"));
+}
+
+#[test]
+fn test_upcoming() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let today = chrono::Utc::now().date_naive();
+    let day = |n: i64| (today + chrono::Duration::days(n)).to_string();
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "certs/api",
+            "-c",
+            &format!(
+                "API cert expires @{}\n\nOld cert expired @{}",
+                day(10),
+                day(-3)
+            ),
+        ],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "plans/migration", "-c", "Move to the new cluster"],
+    );
+    run_ok(
+        temp.path(),
+        &[
+            "meta",
+            "set",
+            "plans/migration",
+            "dates",
+            &format!("[{} Cutover, {} Cleanup]", day(3), day(60)),
+        ],
+    );
+
+    let stdout = run_ok(temp.path(), &["upcoming"]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert!(lines[1].contains("(in 3 days)  plans/migration: Cutover"));
+    assert!(lines[2].contains(&format!("certs/api: API cert expires {}", day(10))));
+
+    let stdout = run_ok(temp.path(), &["upcoming", "--days", "90", "--json"]);
+    let events: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let labels: Vec<&str> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(labels.len(), 3);
+    assert_eq!(labels[2], "Cleanup");
+    let line = events[1]["line"].as_u64().unwrap() as usize;
+    let raw = std::fs::read_to_string(temp.path().join(".mems/certs/api.md")).unwrap();
+    assert!(raw
+        .lines()
+        .nth(line - 1)
+        .unwrap()
+        .starts_with("API cert expires"));
+
+    assert_eq!(
+        run_ok(temp.path(), &["upcoming", "--days", "1"]),
+        "Nothing coming up in the next 1 days\n"
+    );
+}
+
+#[test]
+fn test_diff() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "db/a",
+            "-c",
+            "Rotate the keys weekly.\nPage the DBA.\n",
+        ],
+    );
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "db/b",
+            "-c",
+            "Rotate all keys weekly.\nPage the DBA.\n",
+        ],
+    );
+
+    assert_eq!(
+        run_ok(temp.path(), &["diff", "db/a", "db/b"]),
+        "--- db/a\n+++ db/b\n@@ -1,2 +1,2 @@\n-Rotate the keys weekly.\n+Rotate all keys weekly.\n Page the DBA.\n"
+    );
+    assert_eq!(
+        run_ok(temp.path(), &["diff", "db/a", "db/b", "--words"]),
+        "Rotate [-the-]{+all+} keys weekly.\nPage the DBA.\n"
+    );
+
+    let hunks: serde_json::Value =
+        serde_json::from_str(&run_ok(temp.path(), &["diff", "db/a", "db/b", "--json"])).unwrap();
+    assert_eq!(hunks[0]["lines"][0]["op"], "delete");
+
+    std::fs::write(
+        temp.path().join("proposed.md"),
+        "Rotate the keys weekly.\nPage the DBA.\nUpdate the runbook.\n",
+    )
+    .unwrap();
+    let stdout = run_ok(temp.path(), &["diff", "db/a", "--against", "proposed.md"]);
+    assert!(stdout.ends_with("+Update the runbook.\n"), "{stdout}");
+
+    let mut child = mem_cmd()
+        .current_dir(temp.path())
+        .args(["diff", "db/a", "--against", "-"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to run");
+    use std::io::Write;
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"Rotate the keys weekly.\nPage the DBA.\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "No differences\n");
+}
+
+#[test]
+fn test_proposals() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let fails = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(args)
+            .output()
+            .expect("failed to run");
+        assert!(!output.status.success());
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+    run_ok(
+        temp.path(),
+        &["add", "policy", "-c", "Rotate keys yearly.\n"],
+    );
+    std::fs::write(temp.path().join("a.md"), "Rotate keys monthly.\n").unwrap();
+    std::fs::write(temp.path().join("b.md"), "Rotate keys weekly.\n").unwrap();
+
+    let stdout = run_ok(
+        temp.path(),
+        &[
+            "propose", "policy", "--file", "a.md", "-m", "Tighten", "--author", "bot",
+        ],
+    );
+    assert!(stdout.starts_with("Proposed "), "{stdout}");
+    let first = stdout.split_whitespace().nth(1).unwrap().to_string();
+    run_ok(temp.path(), &["propose", "policy", "--file", "b.md"]);
+
+    let proposals: serde_json::Value =
+        serde_json::from_str(&run_ok(temp.path(), &["proposals", "ls", "--json"])).unwrap();
+    assert_eq!(proposals.as_array().unwrap().len(), 2);
+    assert_eq!(proposals[0]["author"], "bot");
+    let second = proposals[1]["id"].as_str().unwrap().to_string();
+
+    let stdout = run_ok(temp.path(), &["proposals", "diff", &first]);
+    assert!(stdout.contains("-Rotate keys yearly.\n+Rotate keys monthly.\n"));
+    assert!(run_ok(temp.path(), &["proposals", "show", &first])
+        .ends_with("---\nRotate keys monthly.\n"));
+
+    // Accepting the first makes the second outdated
+    run_ok(temp.path(), &["accept", &first]);
+    assert!(run_ok(temp.path(), &["show", "policy"]).ends_with("Rotate keys monthly.\n\n"));
+    assert!(run_ok(temp.path(), &["proposals", "ls"]).contains("(outdated)"));
+    assert!(fails(&["accept", &second]).contains("changed since proposal"));
+    assert!(fails(&["accept", &first]).contains("no proposal"));
+
+    run_ok(temp.path(), &["reject", &second[..20]]);
+    assert_eq!(
+        run_ok(temp.path(), &["proposals", "ls"]),
+        "No pending proposals\n"
+    );
+    assert!(fails(&["propose", "policy", "--file", "a.md"]).contains("matches"));
+}
+
+#[test]
+fn test_lock() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let refused = |args: &[&str]| {
+        let output = run(temp.path(), args);
+        assert_eq!(output.status.code(), Some(7), "{args:?}: {output:?}");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("policy is locked"));
+    };
+    run_ok(temp.path(), &["add", "policy", "-c", "Never share keys."]);
+    run_ok(temp.path(), &["lock", "policy"]);
+    let raw = std::fs::read_to_string(temp.path().join(".mems/policy.md")).unwrap();
+    assert!(raw.contains("locked: true"));
+
+    refused(&["edit", "policy", "-c", "x"]);
+    refused(&["rm", "policy"]);
+    refused(&["mv", "policy", "old-policy"]);
+    refused(&["archive", "policy"]);
+    refused(&["add", "policy", "-c", "x", "--force"]);
+    refused(&["add", "policy", "-c", "x", "--upsert"]);
+    refused(&["meta", "set", "policy", "locked", "false"]);
+    refused(&["meta", "unset", "policy", "locked"]);
+    refused(&["meta", "set", "policy", "team", "infra"]);
+    refused(&["assign", "policy", "alice"]);
+    refused(&["pin", "policy"]);
+    let vault = temp.path().join("vault");
+    std::fs::create_dir(&vault).unwrap();
+    std::fs::write(vault.join("policy.md"), "# Policy\nShare freely.\n").unwrap();
+    refused(&["import", "--format", "dendron", "vault", "--force"]);
+    assert!(run_ok(temp.path(), &["show", "policy"]).contains("Never share keys."));
+    let raw = std::fs::read_to_string(temp.path().join(".mems/policy.md")).unwrap();
+    assert!(raw.contains("locked: true") && !raw.contains("owner:") && !raw.contains("pinned:"));
+
+    run_ok(
+        temp.path(),
+        &[
+            "edit",
+            "policy",
+            "-c",
+            "Never share keys or tokens.",
+            "--force-unlock",
+        ],
+    );
+    let raw = std::fs::read_to_string(temp.path().join(".mems/policy.md")).unwrap();
+    assert!(raw.contains("locked: true") && raw.contains("or tokens"));
+
+    run_ok(temp.path(), &["unlock", "policy"]);
+    // The locked field is for lock and unlock, not meta
+    let output = run(temp.path(), &["meta", "set", "policy", "locked", "true"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("mem lock"));
+    run_ok(temp.path(), &["rm", "policy"]);
+}
+
+#[test]
+fn test_generate_from_cargo() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    std::fs::write(
+        temp.path().join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.3.0\"\nedition = \"2021\"\n\
+         description = \"Demo service\"\n\n[features]\nfast = []\n",
+    )
+    .unwrap();
+    std::fs::create_dir(temp.path().join("src")).unwrap();
+    std::fs::write(temp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+
+    let stdout = run_ok(temp.path(), &["generate", "from-cargo"]);
+    assert!(
+        stdout.contains("Created: generated/crates/demo"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Created: generated/workspace"));
+    let stdout = run_ok(temp.path(), &["show", "generated/crates/demo"]);
+    assert!(stdout.contains("Demo service"));
+    assert!(stdout.contains("- `demo` (`src/main.rs`)"));
+    assert!(stdout.contains("- `fast`"));
+    let raw = std::fs::read_to_string(temp.path().join(".mems/generated/crates/demo.md")).unwrap();
+    assert!(raw.contains("generated: true"));
+
+    assert_eq!(
+        run_ok(temp.path(), &["generate", "from-cargo"]),
+        "2 mems unchanged\n"
+    );
+
+    // Generated mems never count as stale
+    run_ok(temp.path(), &["add", "notes", "-c", "x"]);
+    let stdout = run_ok(temp.path(), &["stale", "--days", "0"]);
+    assert!(
+        stdout.contains("notes") && !stdout.contains("generated/"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn test_capture() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let capture = |args: &[&str]| {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(args)
+            .output()
+            .expect("failed to run")
+    };
+
+    let output = capture(&[
+        "capture",
+        "sessions/disk",
+        "--tags",
+        "investigation",
+        "--",
+        "sh",
+        "-c",
+        "echo 'disk 91%'; echo warn >&2; exit 2",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "disk 91%\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("(exit code 2) in sessions/disk"),
+        "{stderr}"
+    );
+
+    let output = capture(&["capture", "sessions/disk", "--", "echo", "second"]);
+    assert!(output.status.success(), "{output:?}");
+
+    let raw = std::fs::read_to_string(temp.path().join(".mems/sessions/disk.md")).unwrap();
+    assert!(raw.contains("- investigation"));
+    assert!(raw.contains("## `sh -c 'echo '\\''disk 91%'\\''; echo warn >&2; exit 2'`"));
+    assert!(raw.contains(
+        "exit code 2.\n\nstdout:\n\n```text\ndisk 91%\n```\n\nstderr:\n\n```text\nwarn\n```\n"
+    ));
+    assert!(raw.ends_with("stdout:\n\n```text\nsecond\n```\n"));
+    assert_eq!(raw.matches("## `").count(), 2);
+
+    let output = capture(&["capture", "sessions/other"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_log_entries() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    assert_eq!(
+        run_ok(
+            temp.path(),
+            &["log-entry", "oncall/march", "Paged for disk usage"]
+        ),
+        "Logged to oncall/march\n"
+    );
+    run_ok(
+        temp.path(),
+        &[
+            "log-entry",
+            "oncall/march",
+            "Cleaned /var/log\nUsage at 40%",
+        ],
+    );
+    run_ok(temp.path(), &["log-entry", "oncall/march", "Handed off"]);
+
+    let raw = std::fs::read_to_string(temp.path().join(".mems/oncall/march.md")).unwrap();
+    assert!(raw.contains("kind: log"));
+    assert!(raw.contains("** Cleaned /var/log\n  Usage at 40%\n- **"));
+
+    let stdout = run_ok(temp.path(), &["show", "oncall/march", "--last", "2"]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert!(lines[0].ends_with("** Cleaned /var/log"));
+    assert!(lines[2].ends_with("** Handed off"));
+
+    let entries: serde_json::Value = serde_json::from_str(&run_ok(
+        temp.path(),
+        &["show", "oncall/march", "--last", "1", "--json"],
+    ))
+    .unwrap();
+    assert_eq!(entries[0]["message"], "Handed off");
+
+    run_ok(temp.path(), &["add", "notes", "-c", "x"]);
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["log-entry", "notes", "y"])
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("notes is not a log mem"));
+}
+
+#[test]
+fn test_kinds() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        "kinds:\n  glossary:\n    order: -1\n",
+    )
+    .unwrap();
+
+    // Without content, a new ADR starts from the template
+    run_ok(temp.path(), &["add", "decisions/adr-001", "--kind", "adr"]);
+    let raw = std::fs::read_to_string(temp.path().join(".mems/decisions/adr-001.md")).unwrap();
+    assert!(raw.contains("kind: adr"));
+    assert!(raw.contains("## Context\n\n## Decision\n\n## Consequences\n"));
+    run_ok(
+        temp.path(),
+        &["add", "decisions/terms", "--kind", "glossary", "-c", "x"],
+    );
+    run_ok(temp.path(), &["add", "decisions/notes", "-c", "x"]);
+
+    // Runbooks need a Steps section
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "runbooks/db",
+            "--kind",
+            "runbook",
+            "-c",
+            "Restart it.",
+        ],
+    );
+    let output = run(temp.path(), &["lint"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("missing required heading: Steps"),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("adr-001"), "{stdout}");
+
+    let stdout = run_ok(temp.path(), &["ls", "--kind", "adr"]);
+    assert!(stdout.contains("decisions/adr-001"));
+    assert!(!stdout.contains("decisions/notes") && !stdout.contains("runbooks/db"));
+    assert!(run_ok(temp.path(), &["ls", "--kind", "note"]).contains("decisions/notes"));
+    assert!(!run_ok(temp.path(), &["find", "x", "--kind", "glossary"]).contains("decisions/notes"));
+
+    // ADRs never go stale and runbooks have their own threshold; notes use --days
+    let stdout = run_ok(temp.path(), &["stale", "--days", "0"]);
+    assert!(stdout.contains("decisions/notes"), "{stdout}");
+    assert!(!stdout.contains("adr-001") && !stdout.contains("runbooks/db"));
+    assert!(
+        run_ok(temp.path(), &["stale", "--days", "0", "--kind", "adr"])
+            .starts_with("No stale mems")
+    );
+
+    // Configured order puts the glossary first within its directory
+    let stdout = run_ok(temp.path(), &["tree"]);
+    let terms = stdout.find("terms").unwrap();
+    assert!(terms < stdout.find("adr-001").unwrap(), "{stdout}");
+}
+
+#[test]
+fn test_glossary() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(temp.path(), &[
+        "add",
+        "ops/terms",
+        "--kind",
+        "glossary",
+        "-c",
+        "## RTO (Recovery Time Objective)\n\nHow long a restore may take.\n\n- **SLO**: Service level objective.",
+    ]);
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "glossary/mttr",
+            "-t",
+            "MTTR",
+            "-c",
+            "Mean time to recovery.",
+        ],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "runbooks/db", "-c", "Restore within the RTO."],
+    );
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "runbooks/web",
+            "-c",
+            "Restore within the [RTO](../ops/terms.md).",
+        ],
+    );
+
+    assert_eq!(
+        run_ok(temp.path(), &["def", "rto"]),
+        "ops/terms: RTO (Recovery Time Objective)\n  How long a restore may take.\n"
+    );
+    let output = run(temp.path(), &["def", "MTTR", "--json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["definition"], "Mean time to recovery.");
+
+    let output = run(temp.path(), &["def", "SLOs"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("did you mean SLO?"));
+
+    let stdout = String::from_utf8_lossy(&run(temp.path(), &["lint"]).stdout).to_string();
+    assert!(
+        stdout.contains("\"RTO\" is defined in ops/terms; link to it"),
+        "{stdout}"
+    );
+    assert_eq!(stdout.matches("link to it").count(), 1, "{stdout}");
+}
+
+#[test]
+fn test_run_runbook() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let run = |args: &[&str], input: &str| {
+        let mut child = mem_cmd()
+            .current_dir(temp.path())
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("failed to spawn");
+        use std::io::Write;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().expect("failed to wait")
+    };
+    let content = "1. Drain the node\n2. Restart:\n\n   ```sh\n   echo restarted\n   ```\n\n3. Check the dashboard\n";
+    run(
+        &["add", "runbooks/app", "--kind", "runbook", "-c", content],
+        "",
+    );
+    run(&["add", "notes", "-c", "1. Not a runbook"], "");
+
+    let output = run(&["run", "runbooks/app", "--exec"], "\n\ny\ns\n");
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("):\n    echo restarted\n"), "{stdout}");
+    assert!(stdout.contains("[q]uit: restarted\n"), "{stdout}");
+    assert!(stdout.contains("recorded the run in runs/runbooks/app"));
+
+    let raw = std::fs::read_to_string(temp.path().join(".mems/runs/runbooks/app.md")).unwrap();
+    assert!(raw.contains("kind: log"));
+    assert!(raw.contains("Ran runbooks/app: all 4 steps\n  - 1. Drain the node (done)\n"));
+    assert!(raw.contains(
+        "  - 3. `echo restarted` (ran, exit code 0)\n  - 4. Check the dashboard (skipped)\n"
+    ));
+
+    // Without --exec, shell blocks are checked off by hand; q stops the run
+    run(&["run", "runbooks/app"], "\n\nq\n");
+    let raw = std::fs::read_to_string(temp.path().join(".mems/runs/runbooks/app.md")).unwrap();
+    assert!(raw.contains("stopped after 2 of 4 steps"));
+
+    let output = run(&["run", "notes"], "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("notes is not a runbook"));
+}
+
+#[test]
+fn test_gc() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let mems = temp.path().join(".mems");
+    std::fs::write(
+        mems.join("config.yaml"),
+        "retention:\n  policies:\n    - prefix: scratch\n      auto-archive-after: 30d\n  purge-archived-after: 26w\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(mems.join("scratch")).unwrap();
+    std::fs::write(
+        mems.join("scratch/idea.md"),
+        "---\ntitle: Idea\ncreated-at: 2025-01-01T00:00:00Z\nupdated-at: 2025-01-01T00:00:00Z\n---\nMaybe.",
+    )
+    .unwrap();
+    std::fs::write(
+        mems.join("archive/retired.md"),
+        "---\ntitle: Retired\ncreated-at: 2023-01-01T00:00:00Z\nupdated-at: 2023-01-01T00:00:00Z\narchived-at: 2024-01-01T00:00:00Z\n---\nOld.",
+    )
+    .unwrap();
+    run_ok(temp.path(), &["add", "scratch/fresh", "-c", "New."]);
+
+    let stdout = run_ok(temp.path(), &["gc", "--dry-run"]);
+    assert!(stdout.contains("Would archive scratch/idea: not updated in"));
+    assert!(stdout.contains("policy scratch: 30d"));
+    assert!(stdout.contains("Would purge archive/retired: archived"));
+    assert!(!stdout.contains("fresh"));
+    assert!(mems.join("scratch/idea.md").exists());
+
+    let stdout = run_ok(temp.path(), &["gc"]);
+    assert!(stdout.contains("Archived scratch/idea"));
+    assert!(!mems.join("scratch/idea.md").exists());
+    assert!(mems.join("archive/scratch/idea.md.zst").exists());
+    assert!(!mems.join("archive/retired.md").exists());
+    assert_eq!(
+        run_ok(temp.path(), &["gc"]),
+        "Nothing to archive or purge\n"
+    );
+}
+
+#[test]
+fn test_compressed_archive() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let mems = temp.path().join(".mems");
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "plans/migration",
+            "-t",
+            "Migration",
+            "-c",
+            "Move to Postgres.",
+        ],
+    );
+    run_ok(temp.path(), &["archive", "plans/migration"]);
+    assert!(mems.join("archive/plans/migration.md.zst").exists());
+
+    // Archived before archives were compressed
+    std::fs::write(
+        mems.join("archive/legacy.md"),
+        "---\ntitle: Legacy\ncreated-at: 2023-01-01T00:00:00Z\nupdated-at: 2023-01-01T00:00:00Z\n---\nPostgres tuning notes.",
+    )
+    .unwrap();
+
+    let stdout = run_ok(temp.path(), &["show", "--archived", "plans/migration"]);
+    assert!(stdout.contains("# Migration"));
+    assert!(stdout.contains("Move to Postgres."));
+
+    assert_eq!(
+        run_ok(temp.path(), &["find", "postgres"]),
+        "No matches found for: postgres\n"
+    );
+    let stdout = run_ok(temp.path(), &["find", "postgres", "--include-archived"]);
+    assert!(stdout.contains("plans/migration (archived): Migration"));
+    assert!(stdout.contains("legacy (archived): Legacy"));
+
+    let stdout = run_ok(temp.path(), &["archive", "compact"]);
+    assert!(stdout.contains("Compressed archive/legacy:"));
+    assert!(!mems.join("archive/legacy.md").exists());
+    assert!(
+        run_ok(temp.path(), &["show", "--archived", "legacy"]).contains("Postgres tuning notes.")
+    );
+    assert!(run_ok(temp.path(), &["archive", "compact"]).contains("Nothing to compact"));
+}
+
+#[test]
+fn test_cwd_scope() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        "scopes:\n  src/payments: services/payments\n",
+    )
+    .unwrap();
+    let service = temp.path().join("src/payments/api");
+    std::fs::create_dir_all(&service).unwrap();
+    run_ok(temp.path(), &["add", "guides/setup", "-c", "Setup."]);
+    run_ok(
+        temp.path(),
+        &["add", "services/payments/retries", "-c", "Retries."],
+    );
+
+    let paths = |stdout: String| -> Vec<String> {
+        stdout
+            .lines()
+            .map(|l| l.split(':').next().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(
+        paths(run_ok(temp.path(), &["ls"])),
+        ["guides/setup", "services/payments/retries"]
+    );
+    assert_eq!(
+        paths(run_ok(&service, &["ls"])),
+        ["services/payments/retries", "guides/setup"]
+    );
+    assert_eq!(
+        paths(run_ok(&service, &["ls", "--no-scope"])),
+        ["guides/setup", "services/payments/retries"]
+    );
+    assert_eq!(run_ok(&service, &["scope"]), "services/payments\n");
+    assert_eq!(
+        run_ok(temp.path(), &["scope"]),
+        "No scope for this directory\n"
+    );
+}
+
+#[test]
+fn test_nested_store_discovery() {
+    let temp = setup_temp_dir();
+    let root = temp.path();
+    // The repository root is the top of the git work tree
+    assert!(Command::new("git")
+        .current_dir(root)
+        .args(["init", "-q"])
+        .status()
+        .unwrap()
+        .success());
+    let billing = root.join("packages/billing");
+    let search = root.join("packages/search");
+    for package in [&billing, &search] {
+        std::fs::create_dir_all(package).unwrap();
+        init_mems(package);
+    }
+    run_ok(
+        &billing,
+        &["add", "invoices/retries", "-c", "Retry nightly."],
+    );
+    run_ok(
+        &search,
+        &[
+            "add",
+            "ranking",
+            "-c",
+            "See [[billing:invoices/retries]] and [[billing:gone]].",
+        ],
+    );
+
+    // Only the nearest store without discovery
+    let listed = run_ok(&search, &["ls"]);
+    assert!(!listed.contains("invoices/retries"));
+
+    let listed = run_ok(root, &["ls", "--recursive-discover"]);
+    assert!(listed.contains("[billing] invoices/retries"));
+    assert!(listed.contains("[search] ranking"));
+
+    // Cross-store references
+    let shown = run_ok(&search, &["show", "billing:invoices/retries"]);
+    assert!(shown.contains("Retry nightly."));
+
+    // With discover-nested, every command sees all stores
+    std::fs::write(search.join(".mems/config.yaml"), "discover-nested: true\n").unwrap();
+    let found = run_ok(&search, &["find", "nightly"]);
+    assert!(found.contains("[billing] invoices/retries"));
+    let output = run(&search, &["lint"]);
+    let lint = String::from_utf8_lossy(&output.stdout);
+    assert!(lint.contains("broken link to billing:gone"));
+    assert!(!lint.contains("billing:invoices/retries"));
+}
+
+#[test]
+fn test_cross_store_links() {
+    let temp = setup_temp_dir();
+    let billing = temp.path().join("billing");
+    let search = temp.path().join("search");
+    for package in [&billing, &search] {
+        std::fs::create_dir_all(package).unwrap();
+        init_mems(package);
+    }
+    let dirs = ["--dir", "billing/.mems", "--dir", "search/.mems"];
+    let both = |args: &[&str]| run_ok(temp.path(), &[&dirs[..], args].concat());
+    let add = |dir: &Path, path: &str, content: &str| {
+        run_ok(dir, &["add", path, "-c", content]);
+    };
+    add(&billing, "ledger", "Double entry.");
+    add(
+        &search,
+        "ranking",
+        "See [the ledger](billing:ledger.md) and [[billing:refunds]].",
+    );
+
+    // The markdown cross-store link is not a broken internal link
+    let output = run(temp.path(), &[&dirs[..], &["lint"]].concat());
+    assert!(!output.status.success());
+    let lint = String::from_utf8_lossy(&output.stdout);
+    assert!(!lint.contains("billing:ledger"), "{lint}");
+    assert!(lint.contains("broken link to billing:refunds"));
+
+    assert_eq!(both(&["backlinks", "billing:ledger"]), "search:ranking\n");
+
+    let moved = both(&["mv", "billing:ledger", "finance/ledger"]);
+    assert!(moved.contains("Updated links in [search] ranking"));
+    assert!(billing.join(".mems/finance/ledger.md").exists());
+    let raw = std::fs::read_to_string(search.join(".mems/ranking.md")).unwrap();
+    assert!(raw.contains("(billing:finance/ledger.md)"));
+}
+
+#[test]
+fn test_edit_noop_keeps_timestamps() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let file = temp.path().join(".mems/doc.md");
+    std::fs::write(
+        &file,
+        "---\ntitle: Doc\ntags:\n- a\ncreated-at: 2025-01-01T00:00:00Z\nupdated-at: 2025-01-01T00:00:00Z\n---\nSame.",
+    )
+    .unwrap();
+    let edit = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .arg("edit")
+            .arg("doc")
+            .args(args)
+            .output()
+            .expect("failed to run");
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let before = std::fs::read_to_string(&file).unwrap();
+    assert_eq!(
+        edit(&["-c", "Same.", "-t", "Doc", "--tags", "a"]),
+        "Unchanged: doc\n"
+    );
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), before);
+
+    assert_eq!(edit(&["-c", "Same.", "--force-touch"]), "Updated: doc\n");
+    assert!(!std::fs::read_to_string(&file)
+        .unwrap()
+        .contains("updated-at: 2025-01-01"));
+}
+
+#[test]
+fn test_add_upsert() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let upsert = |content: &str, extra: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", "ci/report", "--upsert", "-c", content])
+            .args(extra)
+            .output()
+            .expect("failed to run");
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let file = temp.path().join(".mems/ci/report.md");
+
+    assert_eq!(upsert("Build green.", &[]), "Created: ci/report\n");
+    let created = std::fs::read_to_string(&file).unwrap();
+    assert_eq!(upsert("Build green.", &[]), "Unchanged: ci/report\n");
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), created);
+
+    assert_eq!(upsert("Build red.", &[]), "Updated: ci/report\n");
+    let json: serde_json::Value =
+        serde_json::from_str(&upsert("Build red.", &["--tags", "ci", "--output", "json"])).unwrap();
+    assert_eq!(json["action"], "updated");
+    assert_eq!(json["tags"][0], "ci");
+    assert_eq!(json["content"], "Build red.");
+}
+
+#[test]
+fn test_add_with_frontmatter() {
+    use std::io::Write;
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        "schemas:\n  - prefix: adr\n    required-fields: [status]\n    allowed-statuses: [proposed, accepted]\n",
+    )
+    .unwrap();
+    let add = |path: &str, document: &str| {
+        let mut child = mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "--with-frontmatter"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("failed to spawn");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(document.as_bytes())
+            .unwrap();
+        child.wait_with_output().expect("failed to wait")
+    };
+
+    let output = add(
+        "notes/imported",
+        "---\ntitle: Imported Note\ntags: [db]\nsource: wiki\n---\nBody text.\n",
+    );
+    assert!(output.status.success(), "{output:?}");
+    let raw = std::fs::read_to_string(temp.path().join(".mems/notes/imported.md")).unwrap();
+    assert!(raw.contains("title: Imported Note"));
+    assert!(raw.contains("- db"));
+    assert!(raw.contains("source: wiki"));
+    assert!(raw.contains("created-at:"));
+    assert!(raw.ends_with("Body text.\n"));
+    assert!(!raw.contains("---\ntitle: Imported Note\ntags: [db]"));
+
+    // The first allowed status fills a missing one; a bad one is refused
+    let output = add("adr/one", "---\ntitle: One\n---\nDecided.");
+    assert!(output.status.success(), "{output:?}");
+    let raw = std::fs::read_to_string(temp.path().join(".mems/adr/one.md")).unwrap();
+    assert!(raw.contains("status: proposed"));
+    let output = add("adr/two", "---\nstatus: maybe\n---\nUnsure.");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("invalid frontmatter for adr/two: status \"maybe\" is not one of"));
+
+    // An id another mem already has is replaced, with a warning
+    let raw = std::fs::read_to_string(temp.path().join(".mems/adr/one.md")).unwrap();
+    let id = raw
+        .lines()
+        .find_map(|l| l.strip_prefix("id: "))
+        .unwrap()
+        .to_string();
+    let output = add("notes/copy", &raw);
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains(&format!("warning: id {id} is used by another mem")));
+    let copy = std::fs::read_to_string(temp.path().join(".mems/notes/copy.md")).unwrap();
+    assert!(copy.contains("id: ") && !copy.contains(&id));
+    assert!(run(temp.path(), &["lint"]).status.success());
+}
+
+#[test]
+fn test_show_several() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    for (path, content) in [
+        ("runbooks/deploy", "Ship it."),
+        ("runbooks/restore", "Restore it."),
+        ("runbooks/db/failover", "Fail over."),
+        ("notes/a", "A note."),
+    ] {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", content])
+            .output()
+            .unwrap();
+    }
+    let show = |args: &[&str]| {
+        mem_cmd()
+            .current_dir(temp.path())
+            .arg("show")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = show(&["runbooks/*"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("<!-- runbooks/deploy -->"));
+    assert!(stdout.find("Ship it.").unwrap() < stdout.find("Restore it.").unwrap());
+    assert!(!stdout.contains("Fail over."));
+
+    let output = show(&["notes/a", "runbooks/**", "--json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let paths: Vec<&str> = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "notes/a",
+            "runbooks/db/failover",
+            "runbooks/deploy",
+            "runbooks/restore"
+        ]
+    );
+
+    // One plain path keeps the single-mem output
+    let stdout = String::from_utf8_lossy(&show(&["notes/a"]).stdout).to_string();
+    assert!(stdout.starts_with("# "));
+    let output = show(&["notes/a", "--json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["path"], "notes/a");
+
+    let output = show(&["missing/*"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no mems match missing/*"));
+}
+
+#[test]
+fn test_tree_depth_counts_json() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    for path in [
+        "arch/decisions/adr-001",
+        "arch/decisions/adr-002",
+        "arch/overview",
+        "top",
+    ] {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", "x"])
+            .output()
+            .unwrap();
+    }
+    let tree = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .arg("tree")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = tree(&["--depth", "1", "--counts"]);
+    assert!(stdout.starts_with(".mems/ (4 mems)\n"));
+    assert!(stdout.contains("arch/ (3 mems)"));
+    assert!(!stdout.contains("decisions/"));
+    assert!(stdout.contains("top - top"));
+
+    let stdout = tree(&["--updated"]);
+    assert!(stdout.contains("decisions/ (updated "));
+
+    let json: serde_json::Value = serde_json::from_str(&tree(&["--json", "--depth", "2"])).unwrap();
+    assert_eq!(json["count"], 4);
+    let arch = &json["dirs"][0];
+    assert_eq!(arch["path"], "arch");
+    assert_eq!(arch["mems"][0]["path"], "arch/overview");
+    assert_eq!(arch["dirs"][0]["count"], 2);
+    assert!(arch["dirs"][0]["mems"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_tree_archived() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    for path in ["arch/overview", "arch/old", "notes/a"] {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", "x"])
+            .output()
+            .unwrap();
+    }
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["archive", "arch/old"])
+        .output()
+        .unwrap();
+    let tree = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .arg("tree")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = tree(&["--archived"]);
+    assert!(stdout.starts_with(".mems/archive/\n"));
+    assert!(stdout.contains("old - old"));
+    assert!(!stdout.contains("overview"));
+
+    let stdout = tree(&["--all", "--counts"]);
+    assert!(stdout.contains("arch/ (2 mems)"));
+    assert!(stdout.contains("old - old (archived)"));
+    assert!(stdout.contains("overview - overview\n"));
+
+    let json: serde_json::Value = serde_json::from_str(&tree(&["--all", "--json"])).unwrap();
+    let mems = json["dirs"][0]["mems"].as_array().unwrap();
+    assert_eq!(mems[0]["path"], "arch/old");
+    assert_eq!(mems[0]["archived"], true);
+    assert_eq!(mems[1]["archived"], false);
+}
+
+#[test]
+fn test_find_under_path() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    for (path, content) in [
+        ("runbooks/tls", "Renew the TLS cert."),
+        ("runbooks-old/tls", "Old TLS steps."),
+        ("notes/tls", "TLS thoughts."),
+        ("runbooks/legacy", "Legacy TLS setup."),
+    ] {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", content])
+            .output()
+            .unwrap();
+    }
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["archive", "runbooks/legacy"])
+        .output()
+        .unwrap();
+    let find = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .arg("find")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = find(&["tls", "runbooks/"]);
+    assert!(stdout.contains("runbooks/tls"));
+    assert!(!stdout.contains("runbooks-old/tls"));
+    assert!(!stdout.contains("notes/tls"));
+
+    let stdout = find(&["tls", "--path", "notes", "--include-archived"]);
+    assert!(stdout.contains("notes/tls"));
+    assert!(!stdout.contains("runbooks"));
+
+    let stdout = find(&["tls", "runbooks", "--include-archived"]);
+    assert!(stdout.contains("runbooks/legacy (archived)"));
+}
+
+#[test]
+fn test_find_multi_term() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    for (path, content) in [
+        ("db/migrate", "How we run the Postgres migration."),
+        ("db/legacy", "Legacy postgres migration notes."),
+        ("db/backup", "Postgres backups."),
+        ("ops/dr", "The disaster recovery drill."),
+    ] {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", content])
+            .output()
+            .unwrap();
+    }
+    let find = |query: &str| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(["find", query])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = find("postgres migration -legacy");
+    assert!(stdout.contains("db/migrate"));
+    assert!(!stdout.contains("db/legacy"));
+    assert!(!stdout.contains("db/backup"));
+
+    let stdout = find(r#"how to do a "recovery drill" OR backups"#);
+    assert!(stdout.contains("ops/dr"));
+    assert!(stdout.contains("db/backup"));
+    assert!(!stdout.contains("db/migrate"));
+}
+
+#[test]
+fn test_find_in_archive() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    for (path, content) in [
+        ("notes/current", "Kafka topic layout."),
+        ("notes/old", "Kafka cluster sizing from last year."),
+    ] {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", content])
+            .output()
+            .unwrap();
+    }
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["archive", "notes/old"])
+        .output()
+        .unwrap();
+    let find = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(["find", "kafka"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = find(&["--in", "archive"]);
+    assert!(stdout.contains("notes/old (archived)"));
+    assert!(!stdout.contains("notes/current"));
+    assert!(stdout.contains("mem show --archived <path>"));
+
+    let stdout = find(&[]);
+    assert!(!stdout.contains("notes/old"));
+    assert!(!stdout.contains("--archived"));
+
+    let json: serde_json::Value = serde_json::from_str(&find(&["--in", "all", "--json"])).unwrap();
+    assert_eq!(json[0]["path"], "notes/current");
+    assert_eq!(json[0]["location"], "active");
+    assert_eq!(json[1]["location"], "archive");
+}
+
+#[test]
+fn test_find_dedupes_across_stores() {
+    let temp = setup_temp_dir();
+    for store in ["app", "vendor"] {
+        let root = temp.path().join(store);
+        std::fs::create_dir_all(&root).unwrap();
+        init_mems(&root);
+        mem_cmd()
+            .current_dir(&root)
+            .args(["add", "shared/style", "-c", "Use tabs in Makefiles."])
+            .output()
+            .unwrap();
+    }
+    mem_cmd()
+        .current_dir(temp.path().join("app"))
+        .args(["add", "notes/own", "-c", "Makefiles build the app."])
+        .output()
+        .unwrap();
+    let find = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args([
+                "find",
+                "makefiles",
+                "--dir",
+                "app/.mems",
+                "--dir",
+                "vendor/.mems",
+            ])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = find(&[]);
+    assert_eq!(stdout.matches("shared/style:").count(), 1, "{stdout}");
+    assert!(
+        stdout.contains("Also in: [vendor] shared/style"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("notes/own"));
+
+    let stdout = find(&["--no-dedupe"]);
+    assert_eq!(stdout.matches("shared/style:").count(), 2);
+    assert!(!stdout.contains("Also in"));
+
+    let json: serde_json::Value = serde_json::from_str(&find(&["--json"])).unwrap();
+    let results = json.as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    let shared = results
+        .iter()
+        .find(|r| r["path"] == "shared/style")
+        .unwrap();
+    assert_eq!(shared["duplicates"][0], "vendor:shared/style");
+    assert!(shared.get("location").is_none());
+}
+
+#[test]
+fn test_find_ranks_across_stores() {
+    let temp = setup_temp_dir();
+    for (store, path, content) in [
+        ("personal", "deploy", "How I deploy on Fridays."),
+        ("team", "notes/misc", "Mentions deploy once."),
+    ] {
+        let root = temp.path().join(store);
+        std::fs::create_dir_all(&root).unwrap();
+        init_mems(&root);
+        std::fs::write(root.join(".mems/store.yaml"), format!("name: {store}\n")).unwrap();
+        mem_cmd()
+            .current_dir(&root)
+            .args(["add", path, "-c", content])
+            .output()
+            .unwrap();
+    }
+    let find = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(["find", "deploy"])
+            .args(["--dir", "personal/.mems", "--dir", "team/.mems"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        stdout
+            .lines()
+            .filter(|l| l.starts_with('['))
+            .map(|l| l.split(':').next().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let personal_first = ["[personal] deploy", "[team] notes/misc"];
+    let team_first = ["[team] notes/misc", "[personal] deploy"];
+    assert_eq!(find(&[]), personal_first);
+    assert_eq!(find(&["--prefer", "team"]), team_first);
+    assert_eq!(find(&["--layout", "interleaved"]), personal_first);
+
+    // Weights come from the first store's config
+    std::fs::write(
+        temp.path().join("personal/.mems/config.yaml"),
+        "search:\n  store-weights: {team: 5}\n  layout: interleaved\n",
+    )
+    .unwrap();
+    assert_eq!(find(&[]), team_first);
+    assert_eq!(find(&["--prefer", "personal"]), personal_first);
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["find", "deploy", "--prefer", "vendor"])
+        .args(["--dir", "personal/.mems", "--dir", "team/.mems"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("no store labeled 'vendor' (stores: personal, team)"));
+}
+
+#[test]
+fn test_memsignore() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    for path in ["notes/plan", "scratch/idea", "team/scratch/wip", "gen/api"] {
+        mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", "Widget rollout notes."])
+            .output()
+            .unwrap();
+    }
+    std::fs::write(
+        temp.path().join(".mems/.memsignore"),
+        "# scratch areas\nscratch/\n/gen/**\n",
+    )
+    .unwrap();
+
+    for stdout in [
+        run_ok(temp.path(), &["ls"]),
+        run_ok(temp.path(), &["find", "widget"]),
+        run_ok(temp.path(), &["dump"]),
+    ] {
+        assert!(stdout.contains("notes/plan"), "{stdout}");
+        assert!(!stdout.contains("scratch"), "{stdout}");
+        assert!(!stdout.contains("gen/api"), "{stdout}");
+    }
+    // Ignored mems can still be read by path
+    assert!(run_ok(temp.path(), &["show", "scratch/idea"]).contains("Widget rollout"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinked_mems() {
+    use std::os::unix::fs::symlink;
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let mems = temp.path().join(".mems");
+    mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "shared/onboarding", "-c", "Read the handbook."])
+        .output()
+        .unwrap();
+    std::fs::create_dir_all(mems.join("team")).unwrap();
+    symlink("../shared/onboarding.md", mems.join("team/onboarding.md")).unwrap();
+    symlink("..", mems.join("team/up")).unwrap();
+
+    // The looping directory link is not walked by default
+    let stdout = run_ok(temp.path(), &["ls"]);
+    assert!(stdout.contains("team/onboarding"));
+    assert!(!stdout.contains("team/up"));
+
+    // Editing the link updates the shared file, and mv keeps the link
+    run_ok(
+        temp.path(),
+        &["edit", "team/onboarding", "-c", "Read the new handbook."],
+    );
+    assert!(run_ok(temp.path(), &["show", "shared/onboarding"]).contains("new handbook"));
+    run_ok(temp.path(), &["mv", "team/onboarding", "people/onboarding"]);
+    assert!(mems.join("people/onboarding.md").is_symlink());
+    assert!(run_ok(temp.path(), &["show", "people/onboarding"]).contains("new handbook"));
+
+    // rm removes the link, not the file it points to
+    run_ok(temp.path(), &["rm", "people/onboarding"]);
+    assert!(run_ok(temp.path(), &["show", "shared/onboarding"]).contains("new handbook"));
+}
+
+#[test]
+fn test_unicode_normalization() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    // Decomposed path, tag, and content, as some editors and macOS write them
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "places/cafe\u{301}",
+            "-c",
+            "The cafe\u{301} on the STRASSE",
+            "--tags",
+            "cafe\u{301}",
+        ],
+    );
+    assert!(temp.path().join(".mems/places/caf\u{e9}.md").exists());
+    assert!(run_ok(temp.path(), &["show", "places/caf\u{e9}"]).contains("Tags: caf\u{e9}"));
+
+    let stdout = run_ok(temp.path(), &["find", "CAF\u{c9} straße"]);
+    assert!(stdout.contains("places/caf\u{e9}"), "{stdout}");
+}
+
+#[test]
+fn test_large_mem_guardrails() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    std::fs::write(temp.path().join(".mems/config.yaml"), "max-mem-kb: 1\n").unwrap();
+    let large = "log line\n".repeat(200);
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "logs/paste", "-c", &large])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("over the 1 KB limit"), "{stderr}");
+    assert!(!temp.path().join(".mems/logs/paste.md").exists());
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "logs/paste", "-c", &large, "--allow-large"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    // Editing it still needs the flag, while listing works as usual
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["edit", "logs/paste", "-c", &format!("{large}more\n")])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["ls"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("logs/paste: paste"));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("mem is 2 KB (limit 1 KB)"), "{stdout}");
+}
+
+#[test]
+fn test_add_rejects_binary() {
+    use std::io::Write;
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    let mut child = mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "assets/logo"])
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("stdin is a binary file, not text"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("mem attach <mem> <file>"), "{stderr}");
+    assert!(!temp.path().join(".mems/assets/logo.md").exists());
+
+    // A mem file that isn't UTF-8 is reported as such, not as a parse error
+    std::fs::write(
+        temp.path().join(".mems/latin1.md"),
+        b"---\ntitle: caf\xe9\n---\n",
+    )
+    .unwrap();
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "latin1"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("latin1.md is not valid UTF-8 text"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_bench() {
+    let temp = setup_temp_dir();
+    let store = temp.path().join("synthetic");
+    let output = mem_cmd()
+        .args(["bench", "--mems", "30", "--depth", "1", "--size", "300"])
+        .args(["--runs", "1", "--path"])
+        .arg(&store)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["mems"], 30);
+    let operations: Vec<&str> = report["timings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["operation"].as_str().unwrap())
+        .collect();
+    assert_eq!(operations, ["list", "find", "lint", "dump"]);
+    assert!(store.join("d0-1/mem-000001.md").exists());
+
+    // A store that isn't empty is left alone
+    let output = mem_cmd()
+        .args(["bench", "--mems", "1", "--path"])
+        .arg(&store)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_dump_out_gzip_and_order() {
+    use std::io::Read;
+
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    for (path, day) in [("a", 3), ("b", 5), ("c", 1)] {
+        std::fs::write(
+            temp.path().join(format!(".mems/{path}.md")),
+            format!(
+                "---\ntitle: Mem {path}\ncreated-at: 2025-01-0{day}T00:00:00Z\n\
+                 updated-at: 2025-01-0{day}T00:00:00Z\n---\n\nBody of {path}.\n"
+            ),
+        )
+        .unwrap();
+    }
+    let titles = |dump: &str| -> Vec<String> {
+        dump.lines()
+            .filter_map(|l| l.strip_prefix("# Mem "))
+            .map(String::from)
+            .collect()
+    };
+
+    let stdout = mem_cmd()
+        .current_dir(temp.path())
+        .args(["dump"])
+        .output()
+        .unwrap()
+        .stdout;
+    let by_path = String::from_utf8(stdout).unwrap();
+    assert_eq!(titles(&by_path), ["a", "b", "c"]);
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["dump", "--order", "updated", "--out", "dump.md"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stdout.is_empty());
+    let by_updated = std::fs::read_to_string(temp.path().join("dump.md")).unwrap();
+    assert_eq!(titles(&by_updated), ["b", "a", "c"]);
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["dump", "--gzip", "--out", "dump.md.gz"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let file = std::fs::File::open(temp.path().join("dump.md.gz")).unwrap();
+    let mut unzipped = String::new();
+    flate2::read::GzDecoder::new(file)
+        .read_to_string(&mut unzipped)
+        .unwrap();
+    assert_eq!(unzipped, by_path);
+}
+
+#[test]
+fn test_dump_manifest_and_diff() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let manifest = |dump: &str| -> serde_json::Value {
+        let json = dump
+            .strip_prefix("<!-- mem-manifest\n")
+            .and_then(|rest| rest.split("\n-->\n").next())
+            .unwrap();
+        serde_json::from_str(json).unwrap()
+    };
+    for path in ["keep", "edit", "drop"] {
+        run_ok(
+            temp.path(),
+            &["add", path, "-t", path, "-c", &format!("About {path}.")],
+        );
+    }
+
+    // The manifest leads and the dump is reproducible
+    run_ok(temp.path(), &["dump", "--manifest", "--out", "base.md"]);
+    let base = std::fs::read_to_string(temp.path().join("base.md")).unwrap();
+    assert_eq!(run_ok(temp.path(), &["dump", "--manifest"]), base);
+    let listed = manifest(&base);
+    let paths: Vec<&str> = listed["mems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(paths, ["drop", "edit", "keep"]);
+    assert_eq!(listed["store_hash"].as_str().unwrap().len(), 64);
+    assert!(base.contains("# keep"));
+
+    // Only new and changed mems follow a diff's manifest
+    run_ok(temp.path(), &["edit", "edit", "-c", "Edited."]);
+    run_ok(temp.path(), &["rm", "drop"]);
+    run_ok(temp.path(), &["add", "new", "-t", "new", "-c", "Fresh."]);
+    let diff = run_ok(temp.path(), &["dump", "--diff", "base.md"]);
+    let changed = manifest(&diff);
+    assert_eq!(changed["removed"][0]["path"], "drop");
+    assert_ne!(changed["store_hash"], listed["store_hash"]);
+    assert!(diff.contains("# edit") && diff.contains("# new"));
+    assert!(!diff.contains("# keep"));
+
+    // Nothing changed: just the manifest
+    run_ok(temp.path(), &["dump", "--manifest", "--out", "now.json"]);
+    let unchanged = run_ok(temp.path(), &["dump", "--diff", "now.json"]);
+    assert!(unchanged.ends_with("-->\n\n"), "{unchanged}");
+}
+
+#[test]
+fn test_export_dir_incremental() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let export = ["export", "dir", "docs", "--plain", "--incremental"];
+    run_ok(
+        temp.path(),
+        &["add", "guide", "-t", "Guide", "-c", "Read me."],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "scratch/todo", "-t", "Todo", "-c", "Later."],
+    );
+    assert!(run_ok(temp.path(), &export).contains("(2 written, 0 unchanged, 0 removed)"));
+
+    // Ignored mems are dropped from the export; unchanged files stay put
+    let before = std::fs::metadata(temp.path().join("docs/guide.md"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    std::fs::write(temp.path().join(".mems/.memsignore"), "scratch/\n").unwrap();
+    run_ok(temp.path(), &["add", "new", "-t", "New", "-c", "Fresh."]);
+    assert!(run_ok(temp.path(), &export).contains("(1 written, 1 unchanged, 1 removed)"));
+    let after = std::fs::metadata(temp.path().join("docs/guide.md"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    assert_eq!(before, after);
+    assert!(temp.path().join("docs/new.md").exists());
+    assert!(!temp.path().join("docs/scratch").exists());
+
+    // Files the export didn't write are never deleted
+    std::fs::write(temp.path().join("docs/README.md"), "Hand-written.").unwrap();
+    std::fs::write(temp.path().join("docs/setup.md"), "Hand-written.").unwrap();
+    assert!(run_ok(temp.path(), &export).contains("(0 written, 2 unchanged, 0 removed)"));
+    assert!(temp.path().join("docs/README.md").exists());
+    assert!(temp.path().join("docs/setup.md").exists());
+
+    // A first export into a directory still needs it empty
+    std::fs::create_dir(temp.path().join("site")).unwrap();
+    std::fs::write(temp.path().join("site/index.md"), "Home.").unwrap();
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["export", "dir", "site", "--plain", "--incremental"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("directory not empty"));
+}
+
+#[test]
+fn test_lint_timestamps() {
+    let temp = setup_temp_dir();
+    let git = |args: &[&str]| {
+        assert!(Command::new("git")
+            .current_dir(temp.path())
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .success());
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.email", "t@example.com"]);
+    git(&["config", "user.name", "t"]);
+    init_mems(temp.path());
+    let write = |path: &str, created: &str, updated: &str| {
+        std::fs::write(
+            temp.path().join(format!(".mems/{path}.md")),
+            format!(
+                "---\ntitle: {path}\ncreated-at: {created}\nupdated-at: {updated}\n---\n\nBody.\n"
+            ),
+        )
+        .unwrap();
+    };
+    write("swapped", "2025-03-01T00:00:00Z", "2025-02-01T00:00:00Z");
+    write("ahead", "2025-01-01T00:00:00Z", "2999-01-01T00:00:00Z");
+    write(
+        "local",
+        "2025-01-01T09:00:00+09:00",
+        "2025-01-01T09:00:00+09:00",
+    );
+    // Committed in 2025, but says it was last updated years before
+    write("untouched", "2019-01-01T00:00:00Z", "2019-01-01T00:00:00Z");
+    git(&["add", "-A"]);
+    git(&[
+        "commit",
+        "-q",
+        "-m",
+        "mems",
+        "--date",
+        "2025-03-02T00:00:00Z",
+    ]);
+
+    let lint = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .arg("lint")
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let stdout = lint(&[]);
+    assert!(
+        stdout.contains("swapped:4: error: updated-at 2025-02-01 is before created-at 2025-03-01"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("ahead:4: warning: updated-at 2999-01-01 is in the future"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("local:3: warning: created-at 2025-01-01T09:00:00+09:00 is not in UTC; write it as 2025-01-01T00:00:00Z"), "{stdout}");
+    assert!(
+        stdout.contains("untouched:4: warning: updated-at 2019-01-01 is"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("older than the file's last commit"),
+        "{stdout}"
+    );
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint", "--fix"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Fixed 2 timestamps in local"), "{stderr}");
+    let local = std::fs::read_to_string(temp.path().join(".mems/local.md")).unwrap();
+    assert!(!local.contains("+09:00"), "{local}");
+    let swapped = std::fs::read_to_string(temp.path().join(".mems/swapped.md")).unwrap();
+    assert!(swapped.contains("updated-at: 2025-03-01"), "{swapped}");
+    assert!(lint(&[]).contains("No issues found"));
+}
+
+#[test]
+fn test_output_templates() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "ops/deploy",
+            "-t",
+            "Deploy",
+            "--tags",
+            "ops,ci",
+            "-c",
+            "Ship it.",
+        ],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "notes", "-t", "Notes", "-c", "Ship notes."],
+    );
+
+    let stdout = run_ok(
+        temp.path(),
+        &[
+            "ls",
+            "--template",
+            "{{ path }}|{{ title }}|{{ tags | join(',') }}",
+        ],
+    );
+    assert_eq!(stdout, "notes|Notes|\nops/deploy|Deploy|ops,ci\n");
+
+    let stdout = run_ok(
+        temp.path(),
+        &["find", "ship", "--template", "{{ path }} {{ words }}"],
+    );
+    assert_eq!(stdout, "notes 2\nops/deploy 2\n");
+
+    std::fs::write(
+        temp.path().join("show.j2"),
+        "# {{ title }}\n\n{{ content }}\n",
+    )
+    .unwrap();
+    let stdout = run_ok(
+        temp.path(),
+        &["show", "ops/deploy", "--template-file", "show.j2"],
+    );
+    assert_eq!(stdout, "# Deploy\n\nShip it.\n");
+
+    let stdout = run_ok(
+        temp.path(),
+        &["stale", "--days", "0", "--template", "{{ path }}"],
+    );
+    assert_eq!(stdout, "notes\nops/deploy\n");
+
+    // Syntax errors fail before anything is printed
+    let output = run(temp.path(), &["ls", "--template", "{{ path"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_schema() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+
+    let output = mem_cmd().arg("schema").output().unwrap();
+    assert!(output.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(schema["title"], "MemRecord");
+    assert_eq!(schema["properties"]["schema_version"]["const"], 1);
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["add", "notes/a", "-t", "A", "-c", "Body"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["show", "notes/a", "--json"])
+        .output()
+        .unwrap();
+    let shown: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(shown["schema_version"], 1);
+    let record: mem::json::MemRecord = serde_json::from_value(shown).unwrap();
+    assert_eq!(record.into_mem().unwrap().title, "A");
+}
+
+#[test]
+fn test_replace() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "services/billing",
+            "-t",
+            "billing-v1",
+            "-c",
+            "Call billing-v1.\nbilling-v1 retries twice.\n",
+        ],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "notes/billing", "-c", "See billing-v1."],
+    );
+    run_ok(temp.path(), &["add", "policy", "-c", "billing-v1 only."]);
+    run_ok(temp.path(), &["lock", "policy"]);
+    let raw =
+        |path: &str| std::fs::read_to_string(temp.path().join(format!(".mems/{path}.md"))).unwrap();
+    let before = raw("services/billing");
+
+    let preview = run_ok(
+        temp.path(),
+        &["replace", "billing-v1", "billing-v2", "--dry-run"],
+    );
+    assert!(preview.contains("--- notes/billing\n+++ notes/billing\n"));
+    assert!(preview.contains("\n-Call billing-v1.\n"));
+    assert!(preview.contains("\n+Call billing-v2.\n"));
+    assert!(preview.contains("Would replace 3 matches in 2 mems"));
+    assert!(!preview.contains("policy"));
+    assert_eq!(raw("services/billing"), before);
+
+    let out = run_ok(
+        temp.path(),
+        &["replace", "billing-v1", "billing-v2", "--path", "services"],
+    );
+    assert!(out.contains("Replaced 2 in services/billing"));
+    assert!(out.contains("Replaced 2 matches in 1 mem\n"));
+    let after = raw("services/billing");
+    assert!(
+        after.contains("title: billing-v1"),
+        "frontmatter is untouched"
+    );
+    assert!(after.contains("Call billing-v2.\nbilling-v2 retries twice."));
+    assert!(raw("notes/billing").contains("billing-v1"));
+
+    let out = run_ok(
+        temp.path(),
+        &["replace", "--regex", r"billing-v(\d)", "billing/v$1"],
+    );
+    assert!(out.contains("Replaced 3 matches in 2 mems"));
+    assert!(raw("notes/billing").contains("See billing/v1."));
+    assert!(raw("policy").contains("billing-v1 only."));
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["replace", "--regex", "(", "x"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid regex"));
+}
+
+#[test]
+fn test_add_template_variables() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        r###"kinds:
+  rfc:
+    template: "## Summary\n\nOwner: {{ owner }}\n"
+    fields:
+      owner: "{{ owner }}"
+      status: "{{status}}"
+      decide-by: "{{ due }}"
+    variables:
+      - name: owner
+        prompt: Decision owner
+      - name: status
+        choices: [draft, review]
+        default: draft
+      - name: due
+        type: date
+"###,
+    )
+    .unwrap();
+    let add = |args: &[&str]| {
+        mem_cmd()
+            .current_dir(temp.path())
+            .arg("add")
+            .args(args)
+            .output()
+            .expect("failed to run")
+    };
+
+    let output = add(&[
+        "rfcs/queues",
+        "--template",
+        "rfc",
+        "--var",
+        "owner=sam",
+        "--var",
+        "due=2026-12-01T09:00:00Z",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    let raw = std::fs::read_to_string(temp.path().join(".mems/rfcs/queues.md")).unwrap();
+    assert!(raw.contains("kind: rfc"));
+    assert!(raw.contains("owner: sam"));
+    assert!(raw.contains("status: draft"));
+    assert!(raw.contains("decide-by: 2026-12-01"));
+    assert!(raw.contains("## Summary\n\nOwner: sam\n"));
+
+    // Values are checked, and required ones must be given without a terminal
+    let invalid = [
+        (
+            vec![
+                "--var",
+                "owner=sam",
+                "--var",
+                "status=done",
+                "--var",
+                "due=2026-12-01",
+            ],
+            "status must be one of draft, review",
+        ),
+        (
+            vec!["--var", "owner=sam", "--var", "due=soon"],
+            "due must be a date",
+        ),
+        (
+            vec!["--var", "due=2026-12-01"],
+            "missing value for template variable owner",
+        ),
+        (
+            vec!["--var", "onwer=sam"],
+            "unknown template variable onwer",
+        ),
+    ];
+    for (vars, message) in invalid {
+        let mut args = vec!["rfcs/retries", "--template", "rfc"];
+        args.extend(vars);
+        let output = add(&args);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(message), "{stderr}");
+    }
+    assert!(!temp.path().join(".mems/rfcs/retries.md").exists());
+
+    // Given content needs no variables; fields get the values there are
+    let output = add(&["rfcs/notes", "--template", "rfc", "-c", "Notes."]);
+    assert!(output.status.success(), "{output:?}");
+    let raw = std::fs::read_to_string(temp.path().join(".mems/rfcs/notes.md")).unwrap();
+    assert!(raw.contains("status: draft"));
+    assert!(!raw.contains("owner") && !raw.contains("{{"), "{raw}");
+}
+
+#[test]
+fn test_archive_reason() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(
+        temp.path(),
+        &["add", "plans/v1", "-t", "Plan v1", "-c", "Old plan"],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "plans/v0", "-t", "Plan v0", "-c", "Older plan"],
+    );
+    run_ok(
+        temp.path(),
+        &["archive", "plans/v1", "--reason", "superseded by adr-017"],
+    );
+    run_ok(temp.path(), &["archive", "plans/v0"]);
+
+    let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    let listed = run_ok(temp.path(), &["ls", "--archived"]);
+    assert!(listed.contains(&format!(
+        "plans/v1: Plan v1 (archived {today}: superseded by adr-017)"
+    )));
+    assert!(listed.contains(&format!("plans/v0: Plan v0 (archived {today})\n")));
+    assert!(run_ok(temp.path(), &["ls"]).contains("No mems found"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&run_ok(temp.path(), &["ls", "--archived", "--json"])).unwrap();
+    let v1 = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["path"] == "plans/v1")
+        .unwrap();
+    assert_eq!(v1["fields"]["archive-reason"], "superseded by adr-017");
+
+    let digest = run_ok(temp.path(), &["digest", "--since", "1d"]);
+    assert!(digest.contains(&format!(
+        "- Plan v1 (`plans/v1`, {today}): superseded by adr-017\n"
+    )));
+}
+
+#[test]
+fn test_usage_tracking() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(
+        temp.path(),
+        &["add", "deploy", "-t", "Deploy", "-c", "Ship it"],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "oncall", "-t", "Oncall", "-c", "Pager rota"],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "rollback", "-t", "Rollback", "-c", "Undo it"],
+    );
+
+    // Off by default: nothing is recorded
+    run_ok(temp.path(), &["show", "deploy"]);
+    assert!(!temp.path().join(".mems/.usage.json").exists());
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["ls", "--hot"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("mem usage enable"));
+
+    // Enabling it in a repository keeps the file out of git
+    assert!(Command::new("git")
+        .current_dir(temp.path())
+        .args(["init", "-q"])
+        .status()
+        .unwrap()
+        .success());
+    let enabled = run_ok(temp.path(), &["usage", "enable"]);
+    assert!(enabled.contains("Added to .gitignore: .mems/.usage.json"));
+    let gitignore = std::fs::read_to_string(temp.path().join(".gitignore")).unwrap();
+    assert!(gitignore.contains(".mems/.usage.json\n"));
+    run_ok(temp.path(), &["show", "deploy"]);
+    run_ok(temp.path(), &["show", "oncall"]);
+    run_ok(temp.path(), &["show", "deploy", "--json"]);
+    let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    let hot = run_ok(temp.path(), &["ls", "--hot"]);
+    assert_eq!(
+        hot,
+        format!(
+            "deploy: Deploy (shown 2 times, last {today})\noncall: Oncall (shown 1 time, last {today})\n"
+        )
+    );
+    assert!(run_ok(temp.path(), &["usage", "status"]).contains("2 mems shown 3 times"));
+
+    let related: serde_json::Value =
+        serde_json::from_str(&run_ok(temp.path(), &["related", "deploy", "--json"])).unwrap();
+    let oncall = related
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["path"] == "oncall")
+        .unwrap();
+    assert_eq!(oncall["usage_score"], 1.0);
+
+    run_ok(temp.path(), &["usage", "disable"]);
+    assert!(!temp.path().join(".mems/.usage.json").exists());
+    assert!(run_ok(temp.path(), &["usage", "status"]).contains("disabled"));
+}
+
+#[test]
+fn test_init_with_structure_and_git() {
+    let temp = setup_temp_dir();
+
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "init",
+            "--dir",
+            "project",
+            "--with-structure",
+            "full",
+            "--git",
+        ])
+        .output()
+        .expect("failed to run");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let project = temp.path().join("project");
+    assert!(project
+        .join(".mems/decisions/adr-001-record-decisions.md")
+        .exists());
+    assert!(project.join(".mems/runbooks/_dir.yaml").exists());
+    assert!(project.join(".git").exists());
+    let gitignore = std::fs::read_to_string(project.join(".gitignore")).unwrap();
+    assert!(gitignore.contains(".mems/.cache.db\n"));
+    assert!(gitignore.contains(".mems/.index/\n"));
+
+    // The starter store lints clean and its templates are in use
+    let output = mem_cmd()
+        .current_dir(&project)
+        .arg("lint")
+        .output()
+        .expect("failed to run");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let output = mem_cmd()
+        .current_dir(&project)
+        .args([
+            "add",
+            "--kind",
+            "runbook",
+            "runbooks/deploy",
+            "--title",
+            "Deploy",
+        ])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    let content = std::fs::read_to_string(project.join(".mems/runbooks/deploy.md")).unwrap();
+    assert!(content.contains("## Steps"));
+
+    // --dir may also name the store itself
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["init", "--dir", "project/.mems"])
+        .output()
+        .expect("failed to run");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+}
+
+#[test]
+fn test_store_info() {
+    let temp = setup_temp_dir();
+    for package in ["billing", "search"] {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .args(["init", "--dir", package])
+            .output()
+            .expect("failed to run");
+        assert!(output.status.success());
+    }
+    std::fs::write(
+        temp.path().join("search/.mems/store.yaml"),
+        "name: search-docs\ndescription: How search works\nowners: [sam]\n\
+         schema-version: 1\npolicies:\n  review: quarterly\n",
+    )
+    .unwrap();
+    assert!(mem_cmd()
+        .current_dir(temp.path().join("billing"))
+        .args(["add", "ledger", "-c", "Double entry."])
+        .status()
+        .unwrap()
+        .success());
+
+    let output = mem_cmd()
+        .current_dir(temp.path().join("search"))
+        .arg("info")
+        .output()
+        .expect("failed to run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Name:        search-docs\n"), "{stdout}");
+    assert!(stdout.contains("Owners:      sam\n"));
+    assert!(stdout.contains("Format:      version 1\n"));
+    assert!(stdout.contains("  review: quarterly\n"));
+
+    // Stores are labeled by name, the directory by default
+    let dirs = ["--dir", "billing/.mems", "--dir", "search/.mems"];
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(dirs)
+        .args(["info", "--json"])
+        .output()
+        .expect("failed to run");
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["name"], "billing");
+    assert_eq!(json[0]["mems"], 1);
+    assert_eq!(json[1]["name"], "search-docs");
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(dirs)
+        .arg("ls")
+        .output()
+        .expect("failed to run");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[billing] ledger"));
+}
+
+#[test]
+fn test_dump_with_header() {
+    let temp = setup_temp_dir();
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args([
+            "init",
+            "--name",
+            "billing",
+            "--description",
+            "How payments work",
+        ])
+        .output()
+        .expect("failed to run");
+    assert!(output.status.success());
+    for (path, content) in [("ops/deploy", "Ship it."), ("overview", "Payments.")] {
+        assert!(mem_cmd()
+            .current_dir(temp.path())
+            .args(["add", path, "-c", content])
+            .status()
+            .unwrap()
+            .success());
+    }
+    let dump = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(temp.path())
+            .arg("dump")
+            .args(args)
+            .output()
+            .expect("failed to run");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = dump(&["--with-header"]);
+    assert!(
+        stdout.starts_with("# billing\n\nHow payments work\n\nGenerated "),
+        "{stdout}"
+    );
+    assert!(stdout.contains(" from 2 mems (~"));
+    assert!(stdout.contains(
+        "## Outline\n\n- ops/ (1 mem)\n  - deploy: deploy\n- overview: overview\n\n<!-- ═"
+    ));
+
+    // The manifest stays first, so the dump still works with --diff
+    let stdout = dump(&["--with-header", "--manifest"]);
+    assert!(stdout.starts_with("<!-- mem-manifest"));
+    assert!(stdout.contains("# billing\n"));
+
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        "dump:\n  header: \"Context for {{ name }}: {{ mems }} mems\"\n",
+    )
+    .unwrap();
+    assert!(dump(&["--with-header", "ops"]).starts_with("Context for billing: 1 mems\n\n<!--"));
+}
+
+#[test]
+fn test_index_status_verify_rebuild() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(temp.path(), &["add", "a", "-c", "alpha"]);
+    run_ok(temp.path(), &["add", "b", "-c", "beta"]);
+    assert!(run_ok(temp.path(), &["index", "status"]).starts_with("No index yet"));
+
+    let stdout = run_ok(temp.path(), &["index", "rebuild"]);
+    assert!(stdout.contains("Embedded 2 mems and removed 0 vectors"));
+
+    run_ok(temp.path(), &["add", "c", "-c", "gamma"]);
+    run_ok(temp.path(), &["rm", "b"]);
+    let output = run(temp.path(), &["index", "verify"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "missing: c\norphaned: b\n"
+    );
+
+    let status: serde_json::Value =
+        serde_json::from_str(&run_ok(temp.path(), &["index", "status", "--json"])).unwrap();
+    assert_eq!(status["indexed"], 2);
+    assert_eq!(status["fresh"], 1);
+
+    run_ok(temp.path(), &["index", "verify", "--repair"]);
+    let stdout = run_ok(temp.path(), &["index", "verify"]);
+    assert!(stdout.contains("Index matches all 2 mems"));
+}
+
+#[test]
+fn test_semantic_search_analyzers() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(
+        temp.path(),
+        &["add", "db", "-t", "数据库", "-c", "每月轮换数据库密码。"],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "ui", "-t", "界面", "-c", "按钮是蓝色的。"],
+    );
+    run_ok(
+        temp.path(),
+        &[
+            "add",
+            "ops",
+            "-t",
+            "Ops",
+            "-c",
+            "Deploys are rolled out weekly.",
+        ],
+    );
+
+    // CJK text is matched by character bigrams, not only whole runs
+    let stdout = run_ok(temp.path(), &["find", "--semantic", "数据库密码"]);
+    assert!(stdout.starts_with("db"), "{stdout}");
+
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        "analyzer:\n  stemming: english\n",
+    )
+    .unwrap();
+    let stdout = run_ok(
+        temp.path(),
+        &["find", "--semantic", "deploying rollouts", "--json"],
+    );
+    let results: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(results[0]["path"], "ops");
+    assert!(run_ok(temp.path(), &["index", "status"]).contains("Analyzer: stemming=english"));
+}
+
+#[test]
+fn test_config_aliases_and_defaults() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    run_ok(
+        temp.path(),
+        &["add", "ops/deploy", "-t", "Deploy", "-c", "Ship it."],
+    );
+    run_ok(
+        temp.path(),
+        &["add", "ops/rollback", "-t", "Rollback", "-c", "Undo it."],
+    );
+    std::fs::write(
+        temp.path().join(".mems/config.yaml"),
+        "aliases:\n  rb: ls --where 'title=Rollback'\n  again: rb\n  loop: loop\n\
+         defaults:\n  ls: --where title=None\n",
+    )
+    .unwrap();
+
+    // The default hides everything, unless the command line sets the flag
+    assert!(!run_ok(temp.path(), &["ls"]).contains("ops/"));
+
+    let stdout = run_ok(temp.path(), &["again", "ops"]);
+    assert!(stdout.contains("ops/rollback"), "{stdout}");
+    assert!(!stdout.contains("ops/deploy"), "{stdout}");
+
+    let output = run(temp.path(), &["loop"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("alias 'loop' expands to itself"));
+}
+
+#[test]
+fn test_export_import_mem_bundle() {
+    let source = setup_temp_dir();
+    init_mems(source.path());
+    let dest = setup_temp_dir();
+    init_mems(dest.path());
+    run_ok(
+        source.path(),
+        &[
+            "add",
+            "ops/deploy",
+            "-t",
+            "Deploy",
+            "--tags",
+            "ops",
+            "-c",
+            "![Flow](flow.png)",
+        ],
+    );
+    std::fs::write(source.path().join(".mems/ops/flow.png"), b"\x89PNG").unwrap();
+
+    let bundle = source.path().join("deploy.memx");
+    let stdout = run_ok(
+        source.path(),
+        &[
+            "export",
+            "mem",
+            "ops/deploy",
+            "--out",
+            bundle.to_str().unwrap(),
+        ],
+    );
+    assert!(stdout.contains("(1 attachment, 0 commits"), "{stdout}");
+
+    let stdout = run_ok(
+        dest.path(),
+        &["import", "memx", bundle.to_str().unwrap(), "shared/deploy"],
+    );
+    assert!(stdout.contains("(1 attachment, 0 commits"), "{stdout}");
+    let copy = std::fs::read_to_string(dest.path().join(".mems/shared/deploy.md")).unwrap();
+    assert!(copy.contains("title: Deploy"), "{copy}");
+    assert!(copy.contains("- ops"), "{copy}");
+    assert!(!dest.path().join(".mems/shared/flow.png").exists());
+    let blob = copy
+        .split("![Flow](../")
+        .nth(1)
+        .and_then(|rest| rest.split(')').next())
+        .unwrap();
+    assert!(blob.starts_with("attachments/"), "{copy}");
+    assert_eq!(
+        std::fs::read(dest.path().join(".mems").join(blob)).unwrap(),
+        b"\x89PNG"
+    );
+
+    // Nothing is overwritten without --force; the directory import still works
+    let output = mem_cmd()
+        .current_dir(dest.path())
+        .args(["import", "memx", bundle.to_str().unwrap(), "shared/deploy"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let output = mem_cmd()
+        .current_dir(dest.path())
+        .args(["import", "--format", "zk"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("<DIR>"));
+}
+
+#[test]
+fn test_capture_email() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let email = "From: Ana Lima <ana@example.com>\r\n\
+        Subject: Decision: freeze deploys\r\n\
+        Date: Tue, 3 Mar 2026 14:30:00 +0000\r\n\
+        MIME-Version: 1.0\r\n\
+        Content-Type: multipart/mixed; boundary=\"b1\"\r\n\
+        \r\n\
+        --b1\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        No deploys during the migration.\r\n\
+        --b1\r\n\
+        Content-Type: text/csv\r\n\
+        Content-Disposition: attachment; filename=\"dates.csv\"\r\n\
+        \r\n\
+        start,end\r\n\
+        --b1--\r\n";
+    let capture = |args: &[&str]| {
+        let mut child = mem_cmd()
+            .current_dir(temp.path())
+            .arg("capture-email")
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        use std::io::Write;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(email.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    };
+
+    let output = capture(&["--tags", "decision"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let path = ".mems/email/2026-03-03-decision-freeze-deploys";
+    let mem = std::fs::read_to_string(temp.path().join(format!("{path}.md"))).unwrap();
+    assert!(mem.contains("title: 'Decision: freeze deploys'"), "{mem}");
+    assert!(mem.contains("created-at: 2026-03-03T14:30:00Z"), "{mem}");
+    assert!(mem.contains("from: Ana Lima <ana@example.com>"), "{mem}");
+    assert!(mem.contains("No deploys during the migration."), "{mem}");
+    let blobs = || {
+        std::fs::read_dir(temp.path().join(".mems/attachments"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+    };
+    let blob = blobs().pop().unwrap();
+    assert!(blob.ends_with(".csv"), "{blob}");
+    assert!(
+        mem.contains(&format!("[dates.csv](../attachments/{blob})")),
+        "{mem}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(temp.path().join(format!(".mems/attachments/{blob}")))
+            .unwrap()
+            .trim(),
+        "start,end"
+    );
+
+    // The attachment is a file, not a mem, and the store stays clean
+    let output = mem_cmd()
+        .current_dir(temp.path())
+        .args(["lint"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let output = capture(&[]);
+    assert!(!output.status.success());
+
+    // The same attachment again is stored once
+    let output = capture(&["decisions/freeze"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(1 attachment, 1 already stored)"));
+    assert_eq!(blobs(), [blob]);
+}
+
+#[test]
+fn test_import_slack_and_discord() {
+    let temp = setup_temp_dir();
+    init_mems(temp.path());
+    let slack = temp.path().join("incidents.json");
+    std::fs::write(
+        &slack,
+        r#"{"messages": [
+          {"user": "U1", "user_profile": {"real_name": "Ana"}, "text": "DB failover at 3am", "ts": "1709476200.0", "thread_ts": "1709476200.0"},
+          {"user": "U2", "user_profile": {"real_name": "Bo"}, "text": "Root cause: disk", "ts": "1709476800.0", "thread_ts": "1709476200.0"}
+        ]}"#,
+    )
+    .unwrap();
+    let stdout = run_ok(
+        temp.path(),
+        &["import", "slack", slack.to_str().unwrap(), "--dry-run"],
+    );
+    assert_eq!(
+        stdout.trim(),
+        "slack/incidents/2024-03-03-db-failover-at-3am"
+    );
+    run_ok(temp.path(), &["import", "slack", slack.to_str().unwrap()]);
+    let mem = std::fs::read_to_string(
+        temp.path()
+            .join(".mems/slack/incidents/2024-03-03-db-failover-at-3am.md"),
+    )
+    .unwrap();
+    assert!(mem.contains("participants:\n- Ana\n- Bo"), "{mem}");
+    assert!(mem.contains("created-at: 2024-03-03T14:30:00Z"), "{mem}");
+
+    let discord = temp.path().join("discord.json");
+    std::fs::write(
+        &discord,
+        r#"{"channel": {"name": "release-42", "type": "GuildPublicThread"}, "messages": [
+          {"id": "1", "type": "Default", "timestamp": "2024-03-05T10:00:00+00:00", "content": "Ship it?", "author": {"name": "cy"}}
+        ]}"#,
+    )
+    .unwrap();
+    run_ok(
+        temp.path(),
+        &[
+            "import",
+            "discord",
+            discord.to_str().unwrap(),
+            "--into",
+            "chat",
+        ],
+    );
+    let stdout = run_ok(temp.path(), &["show", "chat/release-42/2024-03-05"]);
+    assert!(stdout.contains("Ship it?"), "{stdout}");
+}
+
+/// Serve canned JSON by request path (query included) for the rest of the
+/// test. Returns the base URL.
+fn serve_routes(routes: Vec<(&'static str, &'static str)>) -> String {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut header = String::from("-");
+            while !header.trim().is_empty() {
+                header.clear();
+                reader.read_line(&mut header).unwrap();
+            }
+            let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+            let (status, body) = match routes.iter().find(|(route, _)| *route == path) {
+                Some((_, body)) => ("200 OK", *body),
+                None => ("404 Not Found", "{}"),
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+    });
+    url
+}
+
+#[test]
+fn test_import_github_and_refresh() {
+    let temp = setup_temp_dir();
+    let path = temp.path();
+    init_mems(path);
+    let api = serve_routes(vec![
+        (
+            "/repos/acme/api/issues/12",
+            r#"{"number": 12, "title": "Retry policy", "state": "open",
+                "html_url": "https://github.com/acme/api/issues/12",
+                "user": {"login": "ana"}, "body": "Retry three times?",
+                "labels": [{"name": "decision"}],
+                "created_at": "2024-03-01T10:00:00Z", "updated_at": "2024-03-02T10:00:00Z"}"#,
+        ),
+        (
+            "/repos/acme/api/issues/12/comments?per_page=100&page=1",
+            r#"[{"user": {"login": "bo"}, "body": "Agreed, with backoff.",
+                 "created_at": "2024-03-02T10:00:00Z"}]"#,
+        ),
+    ]);
+
+    let output = mem_cmd()
+        .current_dir(path)
+        .env("GITHUB_API_URL", &api)
+        .args(["import", "github", "--repo", "acme/api", "--issue", "12"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Imported 1 mems from acme/api"));
+
+    let file = path.join(".mems/github/api/12-retry-policy.md");
+    let text = std::fs::read_to_string(&file).unwrap();
+    assert!(text.contains("title: Retry policy (#12)"));
+    assert!(text.contains("source: https://github.com/acme/api/issues/12"));
+    assert!(text.contains("synced-at:"));
+    assert!(text.contains("**@bo** · 2024-03-02 10:00\n\nAgreed, with backoff."));
+
+    // Importing again needs --force
+    let output = mem_cmd()
+        .current_dir(path)
+        .env("GITHUB_API_URL", &api)
+        .args(["import", "github", "--repo", "acme/api", "--issue", "12"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let output = mem_cmd()
+        .current_dir(path)
+        .args([
+            "edit",
+            "github/api/12-retry-policy",
+            "--content",
+            "Nope",
+            "--tags",
+            "retries",
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!mem_cmd()
+        .current_dir(path)
+        .args(["refresh"])
+        .output()
+        .unwrap()
+        .status
+        .success());
+
+    // Local edits are kept unless forced; a dry run shows what would change
+    let refresh = |args: &[&str]| {
+        let output = mem_cmd()
+            .current_dir(path)
+            .env("GITHUB_API_URL", &api)
+            .arg("refresh")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    };
+    let output = refresh(&["--all"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("was edited since it was last synced"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Refreshed 0 mems"));
+    let output = refresh(&["github/api", "--dry-run", "--force"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("-Nope"), "{stdout}");
+    assert!(stdout.contains("Would refresh 1 mem"));
+    assert!(std::fs::read_to_string(&file).unwrap().contains("Nope"));
+
+    let output = refresh(&["github/api/12-retry-policy", "--force"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Refreshed 1 mem"));
+    let text = std::fs::read_to_string(&file).unwrap();
+    assert!(text.contains("Agreed, with backoff."));
+    assert!(text.contains("retries"));
+    let output = refresh(&["--all"]);
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("Up to date: github/api/12-retry-policy")
+    );
+
+    // File sources are relative to the directory holding the store
+    std::fs::write(path.join("adr.md"), "Use Postgres.\n").unwrap();
+    std::fs::write(
+        path.join(".mems/adr.md"),
+        "---\ntitle: ADR\ncreated-at: 2024-01-01T00:00:00Z\nupdated-at: 2024-01-01T00:00:00Z\nsource: ./adr.md\n---\nUse Postgres.\n",
+    )
+    .unwrap();
+    refresh(&["adr"]);
+    std::fs::write(path.join("adr.md"), "Use Postgres 16.\n").unwrap();
+    refresh(&["adr"]);
+    assert!(std::fs::read_to_string(path.join(".mems/adr.md"))
+        .unwrap()
+        .contains("Use Postgres 16."));
+
+    // Options are validated before anything is fetched
+    let output = mem_cmd()
+        .current_dir(path)
+        .args(["import", "github", "--repo", "acme"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_activity() {
+    let temp = setup_temp_dir();
+    let path = temp.path();
+    init_mems(path);
+    let today = chrono::Utc::now().date_naive();
+    let write = |name: &str, created: chrono::NaiveDate, updated: chrono::NaiveDate| {
+        let file = path.join(format!(".mems/{name}.md"));
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(
+            file,
+            format!(
+                "---\ntitle: {name}\ncreated-at: {created}T10:00:00Z\nupdated-at: {updated}T10:00:00Z\n---\nBody\n"
+            ),
+        )
+        .unwrap();
+    };
+    write("docs/a", today - chrono::Days::new(40), today);
+    write(
+        "docs/b",
+        today - chrono::Days::new(40),
+        today - chrono::Days::new(40),
+    );
+    write(
+        "legacy/old",
+        today - chrono::Days::new(900),
+        today - chrono::Days::new(800),
+    );
+
+    let output = mem_cmd()
+        .current_dir(path)
+        .args(["activity", "--heatmap"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\nMon "), "{stdout}");
+    assert!(stdout.contains("3 changes in the past year: 2 created, 1 edited"));
+    assert!(stdout.contains("(0 days ago)"));
+
+    let output = mem_cmd()
+        .current_dir(path)
+        .args(["activity", "docs", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["created"], 2);
+    assert_eq!(json["days"][today.to_string()]["edited"], 1);
+
+    let output = mem_cmd()
+        .current_dir(path)
+        .args(["activity", "legacy"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("0 changes in the past year"));
+    assert!(stdout.contains("(800 days ago)"), "{stdout}");
+}
+
+#[test]
+fn test_decisions() {
+    let temp = setup_temp_dir();
+    let path = temp.path();
+    init_mems(path);
+    run_ok(path, &[
+        "add",
+        "--kind",
+        "adr",
+        "arch/adr-001",
+        "-t",
+        "Use Postgres",
+        "-c",
+        "Status: Accepted\nDate: 2023-02-01\n\n## Decision\n\nWe will use Postgres. It is boring.\n",
+    ]);
+    run_ok(
+        path,
+        &[
+            "add",
+            "notes/queues",
+            "-t",
+            "Queues",
+            "-c",
+            "## Context\n\nBacklogs.\n\n## Decision\n\nMove jobs to SQS.\n",
+        ],
+    );
+    run_ok(path, &["add", "notes/misc", "-c", "Nothing decided here."]);
+
+    let stdout = run_ok(path, &["decisions"]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4, "{stdout}");
+    assert_eq!(
+        lines[0],
+        "2023-02-01  Accepted  Use Postgres (arch/adr-001)"
+    );
+    assert_eq!(lines[1], "    We will use Postgres.");
+    assert!(lines[2].ends_with("Queues (notes/queues)"), "{stdout}");
+    assert_eq!(lines[3], "    Move jobs to SQS.");
+
+    let stdout = run_ok(path, &["decisions", "--since", "2024-01-01", "--markdown"]);
+    assert!(
+        stdout.contains("[Queues](notes/queues.md) — Move jobs to SQS."),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("Postgres"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&run_ok(path, &["decisions", "arch", "--json"])).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 1);
+    assert_eq!(json[0]["status"], "Accepted");
+    assert_eq!(json[0]["date"], "2023-02-01");
+}
+
+#[test]
+fn test_attach_and_gc_attachments() {
+    let temp = setup_temp_dir();
+    let path = temp.path();
+    init_mems(path);
+    std::fs::write(path.join("flow.png"), b"diagram").unwrap();
+    std::fs::write(path.join("copy.png"), b"diagram").unwrap();
+    std::fs::write(path.join("old.pdf"), b"spec").unwrap();
+    run_ok(path, &["add", "ops/deploy", "-c", "Steps."]);
+    run_ok(path, &["add", "intro", "-c", "Welcome."]);
+
+    let stdout = run_ok(path, &["attach", "ops/deploy", "flow.png", "old.pdf"]);
+    assert!(stdout.contains("Attached flow.png to ops/deploy (stored as attachments/"));
+    let stdout = run_ok(path, &["attach", "intro", "copy.png"]);
+    assert!(
+        stdout.contains("(already stored as attachments/"),
+        "{stdout}"
+    );
+    let blobs = || {
+        std::fs::read_dir(path.join(".mems/attachments"))
+            .unwrap()
+            .count()
+    };
+    assert_eq!(blobs(), 2);
+
+    let deploy = run_ok(path, &["show", "ops/deploy"]);
+    assert!(
+        deploy.contains("Steps.\n\n![flow.png](../attachments/"),
+        "{deploy}"
+    );
+    let intro = run_ok(path, &["show", "intro"]);
+    assert!(intro.contains("![copy.png](attachments/"), "{intro}");
+
+    // Dropping the link to the pdf leaves its blob unreferenced
+    let file = path.join(".mems/ops/deploy.md");
+    let source = std::fs::read_to_string(&file).unwrap();
+    std::fs::write(&file, source.replace(".pdf)", ".pdf.gone)")).unwrap();
+    let stdout = run_ok(path, &["gc", "--attachments", "--dry-run"]);
+    assert!(
+        stdout.contains("Would delete attachment attachments/"),
+        "{stdout}"
+    );
+    assert!(stdout.contains(".pdf: no mem links to it"), "{stdout}");
+    assert_eq!(blobs(), 2);
+    run_ok(path, &["gc", "--attachments"]);
+    assert_eq!(blobs(), 1);
+
+    // Moving a mem keeps its links working, and its blobs with them
+    run_ok(path, &["mv", "ops/deploy", "deploy"]);
+    let deploy = run_ok(path, &["show", "deploy"]);
+    assert!(deploy.contains("![flow.png](attachments/"), "{deploy}");
+    assert!(run_ok(path, &["gc", "--attachments", "--dry-run"]).contains("Nothing to"));
+
+    // The image stays while any mem links to it
+    run_ok(path, &["rm", "intro"]);
+    assert!(run_ok(path, &["gc", "--attachments"]).contains("Nothing to"));
+    assert_eq!(blobs(), 1);
+}
+
+#[test]
+fn test_mv_redirect() {
+    let temp = setup_temp_dir();
+    let path = temp.path();
+    init_mems(path);
+    run_ok(path, &["add", "setup", "-t", "Setup", "-c", "Run make."]);
+    run_ok(path, &["add", "index", "-c", "See [setup](setup.md)."]);
+
+    let stdout = run_ok(path, &["mv", "--redirect", "setup", "guides/setup"]);
+    assert!(stdout.contains("Left a redirect at setup"));
+    let stub = std::fs::read_to_string(path.join(".mems/setup.md")).unwrap();
+    assert!(stub.contains("redirect-to: guides/setup"));
+    assert!(stub.contains("Moved to [Setup](guides/setup.md)."));
+
+    // show follows the stub, and says so
+    let output = run(path, &["show", "setup"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Run make."));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Redirected from setup to guides/setup")
+    );
+
+    // A second move points the first stub straight at the new path
+    let stdout = run_ok(
+        path,
+        &["mv", "--redirect", "guides/setup", "guides/dev-setup"],
+    );
+    assert!(stdout.contains("Updated redirect at setup"));
+    let stub = std::fs::read_to_string(path.join(".mems/setup.md")).unwrap();
+    assert!(stub.contains("redirect-to: guides/dev-setup"));
+    assert!(run_ok(path, &["show", "setup"]).contains("Run make."));
+
+    // gc removes the stub nothing links to; index still links to setup.md
+    let stdout = run_ok(path, &["gc", "--redirects", "--dry-run"]);
+    assert!(
+        stdout.contains("Would delete redirect guides/setup"),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("redirect setup:"));
+    run_ok(path, &["gc", "--redirects"]);
+    assert!(!path.join(".mems/guides/setup.md").exists());
+    assert!(path.join(".mems/setup.md").exists());
+
+    // lint reports chains and redirects to nowhere
+    let stub = |to: &str| {
+        format!("---\ntitle: Old\ncreated-at: 2024-01-01T00:00:00Z\nupdated-at: 2024-01-01T00:00:00Z\nredirect-to: {to}\n---\nMoved.\n")
+    };
+    std::fs::write(path.join(".mems/old.md"), stub("setup")).unwrap();
+    std::fs::write(path.join(".mems/older.md"), stub("gone")).unwrap();
+    let output = run(path, &["lint"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("redirect to setup, which redirects to guides/dev-setup"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("redirect to gone, which does not exist"));
+
+    // Moving a mem back over its own stub replaces it
+    run_ok(path, &["mv", "guides/dev-setup", "setup"]);
+    let text = std::fs::read_to_string(path.join(".mems/setup.md")).unwrap();
+    assert!(text.contains("Run make.") && !text.contains("redirect-to"));
+}